@@ -270,9 +270,222 @@ impl From<Delay> for Duration {
     }
 }
 
+/// How output frames that span several source frames are computed when retiming an animation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FrameBlending {
+    /// Use the source frame that is shown in the middle of the output frame, dropping or
+    /// duplicating frames as necessary.
+    Nearest,
+    /// Average all source frames shown during the output frame, weighted by how long each of
+    /// them is visible. Frames of different size or position are not blended, the nearest
+    /// frame is used instead.
+    Average,
+}
+
+/// Resample an animation to a constant frame delay, such as `Delay::from_numer_denom_ms(1000,
+/// 30)` for 30 frames per second or `Delay::from_numer_denom_ms(20, 1)` for the 10 millisecond
+/// granularity of GIF.
+///
+/// The total duration is kept as closely as possible, rounded to a multiple of `frame_delay`
+/// and at least one frame. Source frames without any delay are each shown for one output
+/// frame. All returned frames have a delay of `frame_delay`.
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn main() -> image::ImageResult<()> {
+/// use std::fs::File;
+/// use image::{AnimationDecoder, Delay, FrameBlending};
+/// use image::codecs::gif::GifDecoder;
+///
+/// let decoder = GifDecoder::new(File::open("animation.gif")?)?;
+/// let frames = decoder.into_frames().collect_frames()?;
+/// let frame_delay = Delay::from_numer_denom_ms(1000, 25);
+/// let retimed = image::resample_frames(&frames, frame_delay, FrameBlending::Average);
+/// # Ok(())
+/// # }
+/// ```
+pub fn resample_frames(
+    frames: &[Frame],
+    frame_delay: Delay,
+    blending: FrameBlending,
+) -> Vec<Frame> {
+    let interval = delay_ms(frame_delay);
+    if frames.is_empty() || interval <= 0.0 {
+        return frames.to_vec();
+    }
+
+    let mut durations: Vec<f64> = frames.iter().map(|frame| delay_ms(frame.delay)).collect();
+    if durations.iter().all(|&d| d == 0.0) {
+        durations.iter_mut().for_each(|d| *d = interval);
+    }
+    let mut starts = Vec::with_capacity(frames.len());
+    let mut total = 0.0;
+    for &duration in &durations {
+        starts.push(total);
+        total += duration;
+    }
+
+    let count = ((total / interval).round() as usize).max(1);
+    (0..count)
+        .map(|k| {
+            let (begin, end) = (k as f64 * interval, (k + 1) as f64 * interval);
+            let middle = (begin + end) / 2.0;
+            let nearest = starts.iter().rposition(|&start| start <= middle).unwrap_or(0);
+
+            let mut frame = frames[nearest].clone();
+            frame.delay = frame_delay;
+            if blending == FrameBlending::Average {
+                let weights: Vec<(usize, f64)> = starts
+                    .iter()
+                    .zip(&durations)
+                    .enumerate()
+                    .map(|(i, (&start, &duration))| {
+                        (i, (end.min(start + duration) - begin.max(start)).max(0.0))
+                    })
+                    .filter(|&(_, weight)| weight > 0.0)
+                    .collect();
+                let compatible = weights.iter().all(|&(i, _)| {
+                    let other = &frames[i];
+                    other.left == frame.left
+                        && other.top == frame.top
+                        && other.buffer.dimensions() == frame.buffer.dimensions()
+                });
+                if weights.len() > 1 && compatible {
+                    frame.buffer = average_buffers(frames, &weights);
+                }
+            }
+            frame
+        })
+        .collect()
+}
+
+/// Scale the delays of all frames so that the animation plays for the given total duration.
+///
+/// If all frames lack a delay, the duration is distributed evenly among them.
+pub fn stretch_frames(frames: &[Frame], duration: Duration) -> Vec<Frame> {
+    let target = duration.as_secs() as f64 * 1000.0 + f64::from(duration.subsec_nanos()) / 1e6;
+    let total: f64 = frames.iter().map(|frame| delay_ms(frame.delay)).sum();
+
+    // Scale the end times instead of the individual delays, so that rounding does not accumulate.
+    let mut elapsed = 0.0;
+    let mut previous = Duration::from_millis(0);
+    frames
+        .iter()
+        .map(|frame| {
+            elapsed += if total > 0.0 {
+                delay_ms(frame.delay) / total
+            } else {
+                1.0 / frames.len() as f64
+            };
+            let end = Duration::from_nanos((elapsed * target * 1e6).round() as u64);
+            let delay = end.checked_sub(previous).unwrap_or_default();
+            let delay = Delay::from_saturating_duration(delay);
+            previous = end;
+
+            let mut frame = frame.clone();
+            frame.delay = delay;
+            frame
+        })
+        .collect()
+}
+
+fn delay_ms(delay: Delay) -> f64 {
+    let (numer, denom) = delay.numer_denom_ms();
+    if denom == 0 {
+        0.0
+    } else {
+        f64::from(numer) / f64::from(denom)
+    }
+}
+
+/// The weighted average of frame buffers of equal size, computed on premultiplied alpha.
+fn average_buffers(frames: &[Frame], weights: &[(usize, f64)]) -> RgbaImage {
+    let total: f64 = weights.iter().map(|&(_, weight)| weight).sum();
+    let (width, height) = frames[weights[0].0].buffer.dimensions();
+    let mut sums = vec![[0.0f64; 4]; width as usize * height as usize];
+    for &(i, weight) in weights {
+        for (sum, pixel) in sums.iter_mut().zip(frames[i].buffer.pixels()) {
+            let alpha = f64::from(pixel[3]) / 255.0;
+            for c in 0..3 {
+                sum[c] += weight * f64::from(pixel[c]) * alpha;
+            }
+            sum[3] += weight * alpha;
+        }
+    }
+
+    let mut out = RgbaImage::new(width, height);
+    for (pixel, sum) in out.pixels_mut().zip(&sums) {
+        let alpha = sum[3] / total;
+        for c in 0..3 {
+            let value = if sum[3] > 0.0 { sum[c] / sum[3] } else { 0.0 };
+            pixel[c] = value.round() as u8;
+        }
+        pixel[3] = (alpha * 255.0).round() as u8;
+    }
+    out
+}
+
+/// A frame of a single color, to build animated files in the tests of the decoders.
+#[cfg(test)]
+#[allow(dead_code)] // Unused if no decoder of animations is enabled.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct SolidFrame {
+    pub(crate) left: u32,
+    pub(crate) top: u32,
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    pub(crate) color: [u8; 4],
+    pub(crate) delay_ms: u32,
+    /// Whether the frame is alpha blended onto the canvas instead of replacing its area.
+    pub(crate) blend: bool,
+    pub(crate) dispose: SolidFrameDisposal,
+}
+
+/// What happens to the area of a `SolidFrame` before the next frame is drawn.
+#[cfg(test)]
+#[allow(dead_code)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum SolidFrameDisposal {
+    Keep,
+    Background,
+    Previous,
+}
+
+#[cfg(test)]
+#[allow(dead_code)]
+impl SolidFrame {
+    /// A frame that replaces its area, is kept afterwards and is shown for 100 ms.
+    pub(crate) fn new(left: u32, top: u32, width: u32, height: u32, color: [u8; 4]) -> Self {
+        SolidFrame {
+            left,
+            top,
+            width,
+            height,
+            color,
+            delay_ms: 100,
+            blend: false,
+            dispose: SolidFrameDisposal::Keep,
+        }
+    }
+
+    pub(crate) fn blend(self) -> Self {
+        SolidFrame { blend: true, ..self }
+    }
+
+    pub(crate) fn dispose(self, dispose: SolidFrameDisposal) -> Self {
+        SolidFrame { dispose, ..self }
+    }
+
+    pub(crate) fn delay_ms(self, delay_ms: u32) -> Self {
+        SolidFrame { delay_ms, ..self }
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{Delay, Duration, Ratio};
+    use super::{resample_frames, stretch_frames, Delay, Duration, Frame, FrameBlending, Ratio};
+    use crate::{Rgba, RgbaImage};
 
     #[test]
     fn simple() {
@@ -332,4 +545,55 @@ mod tests {
         let delay = Delay::from_saturating_duration(duration);
         assert_eq!(delay.into_ratio().to_integer(), 0);
     }
+
+    fn solid(value: u8, delay_ms: u32) -> Frame {
+        let buffer = RgbaImage::from_pixel(2, 2, Rgba([value, value, value, 255]));
+        Frame::from_parts(buffer, 0, 0, Delay::from_numer_denom_ms(delay_ms, 1))
+    }
+
+    #[test]
+    fn resample_nearest() {
+        let frames = vec![solid(0, 100), solid(100, 50), solid(200, 50)];
+        let delay = Delay::from_numer_denom_ms(50, 1);
+        let retimed = resample_frames(&frames, delay, FrameBlending::Nearest);
+        let values: Vec<u8> = retimed.iter().map(|f| f.buffer().get_pixel(0, 0)[0]).collect();
+        assert_eq!(values, vec![0, 0, 100, 200]);
+        assert!(retimed.iter().all(|f| f.delay() == delay));
+
+        // Dropping frames when the frame rate decreases.
+        let delay = Delay::from_numer_denom_ms(100, 1);
+        let retimed = resample_frames(&frames, delay, FrameBlending::Nearest);
+        let values: Vec<u8> = retimed.iter().map(|f| f.buffer().get_pixel(0, 0)[0]).collect();
+        assert_eq!(values, vec![0, 200]);
+    }
+
+    #[test]
+    fn resample_average() {
+        let frames = vec![solid(0, 100), solid(100, 50), solid(200, 50)];
+        let delay = Delay::from_numer_denom_ms(100, 1);
+        let retimed = resample_frames(&frames, delay, FrameBlending::Average);
+        let values: Vec<u8> = retimed.iter().map(|f| f.buffer().get_pixel(0, 0)[0]).collect();
+        assert_eq!(values, vec![0, 150]);
+        assert_eq!(retimed[1].buffer().get_pixel(1, 1)[3], 255);
+    }
+
+    #[test]
+    fn stretch() {
+        let frames = vec![solid(0, 10), solid(1, 30), solid(2, 60)];
+        let stretched = stretch_frames(&frames, Duration::from_millis(50));
+        let delays: Vec<Duration> = stretched.iter().map(|f| Duration::from(f.delay())).collect();
+        assert_eq!(delays, vec![
+            Duration::from_millis(5),
+            Duration::from_millis(15),
+            Duration::from_millis(30),
+        ]);
+
+        let frames = vec![solid(0, 0), solid(1, 0), solid(2, 0)];
+        let stretched = stretch_frames(&frames, Duration::from_millis(1000));
+        let total = stretched
+            .iter()
+            .map(|f| Duration::from(f.delay()))
+            .fold(Duration::from_millis(0), |a, b| a + b);
+        assert_eq!(total, Duration::from_millis(1000));
+    }
 }
@@ -6,14 +6,19 @@ use std::ops::{Deref, DerefMut, Index, IndexMut, Range};
 use std::path::Path;
 use std::slice::{ChunksExact, ChunksExactMut};
 
-use crate::{ImageOutputFormat, color::{FromColor, Luma, LumaA, Rgb, Rgba, Bgr, Bgra}};
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+use crate::{ImageOutputFormat, color::{ColorType, FromColor, Luma, LumaA, Rgb, Rgba, Bgr, Bgra, Cmyk}};
+use crate::color::{premultiply_channels, unpremultiply_channels};
 use crate::flat::{FlatSamples, SampleLayout};
 use crate::dynimage::{save_buffer, save_buffer_with_format, write_buffer_with_format};
-use crate::error::ImageResult;
-use crate::image::{GenericImage, GenericImageView, ImageFormat};
+use crate::error::{ImageError, ImageFormatHint, ImageResult, UnsupportedError, UnsupportedErrorKind};
+use crate::image::{GenericImage, GenericImageView, ImageEncoder, ImageFormat};
 use crate::math::Rect;
 use crate::traits::{EncodableLayout, Pixel};
 use crate::utils::expand_packed;
+use crate::view::ImageView;
 
 /// Iterate over pixel refs.
 pub struct Pixels<'a, P: Pixel + 'a>
@@ -917,6 +922,57 @@ where
     pub fn put_pixel(&mut self, x: u32, y: u32, pixel: P) {
         *self.get_pixel_mut(x, y) = pixel
     }
+
+    /// Multiplies the color channels of every pixel by its alpha.
+    ///
+    /// See [`Rgba::premultiply`]. Images without an alpha channel are left unchanged.
+    ///
+    /// [`Rgba::premultiply`]: struct.Rgba.html#method.premultiply
+    pub fn premultiply_alpha(&mut self) {
+        if P::COLOR_TYPE.has_alpha() {
+            for pixel in self.pixels_mut() {
+                premultiply_channels(pixel.channels_mut());
+            }
+        }
+    }
+
+    /// Divides the color channels of every pixel by its alpha, undoing [`premultiply_alpha`].
+    ///
+    /// [`premultiply_alpha`]: #method.premultiply_alpha
+    pub fn unpremultiply_alpha(&mut self) {
+        if P::COLOR_TYPE.has_alpha() {
+            for pixel in self.pixels_mut() {
+                unpremultiply_channels(pixel.channels_mut());
+            }
+        }
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl<P, Container> ImageBuffer<P, Container>
+where
+    P: Pixel + Send + 'static,
+    P::Subpixel: Send + 'static,
+    Container: Deref<Target = [P::Subpixel]> + DerefMut,
+{
+    /// Returns a parallel iterator over the mutable pixels of this image.
+    pub fn par_pixels_mut(&mut self) -> impl IndexedParallelIterator<Item = &mut P> {
+        self.inner_pixels_mut()
+            .par_chunks_exact_mut(<P as Pixel>::CHANNEL_COUNT as usize)
+            .map(|v| <P as Pixel>::from_slice_mut(v))
+    }
+
+    /// Enumerates over the pixels of the image in parallel.
+    /// The iterator yields the coordinates of each pixel
+    /// along with a mutable reference to them.
+    pub fn par_enumerate_pixels_mut(
+        &mut self,
+    ) -> impl IndexedParallelIterator<Item = (u32, u32, &mut P)> {
+        let width = self.width as usize;
+        self.par_pixels_mut()
+            .enumerate()
+            .map(move |(i, p)| ((i % width) as u32, (i / width) as u32, p))
+    }
 }
 
 impl<P, Container> ImageBuffer<P, Container>
@@ -928,9 +984,8 @@ where
     /// Saves the buffer to a file at the path specified.
     ///
     /// The image format is derived from the file extension.
-    /// Currently only jpeg, png, ico, pnm, bmp and
+    /// Currently only jpeg, png, ico, pnm, bmp, exr and
     /// tiff files are supported.
-    // TODO exr supported, but Rgba32F is not yet
     pub fn save<Q>(&self, path: Q) -> ImageResult<()>
     where
         Q: AsRef<Path>,
@@ -941,11 +996,56 @@ where
             self.as_bytes(),
             self.width(),
             self.height(),
-            <P as Pixel>::COLOR_TYPE,
+            exact_color_type::<P>()?,
         )
     }
 }
 
+impl<'a, P> ImageBuffer<P, &'a [P::Subpixel]>
+where
+    P: Pixel + 'static,
+    P::Subpixel: bytemuck::Pod,
+{
+    /// Reinterprets bytes as the samples of an image, without copying them, for example to
+    /// process memory mapped from a GPU or received from foreign code.
+    ///
+    /// Returns `None` if the bytes are not aligned for the subpixel type, are not a whole number
+    /// of subpixels, or are too few for the dimensions. The samples are in native byte order.
+    pub fn from_bytes(width: u32, height: u32, bytes: &'a [u8]) -> Option<Self> {
+        let samples = bytemuck::try_cast_slice(bytes).ok()?;
+        ImageBuffer::from_raw(width, height, samples)
+    }
+}
+
+impl<'a, P> ImageBuffer<P, &'a mut [P::Subpixel]>
+where
+    P: Pixel + 'static,
+    P::Subpixel: bytemuck::Pod,
+{
+    /// Reinterprets mutable bytes as the samples of an image, without copying them.
+    ///
+    /// Returns `None` under the same conditions as [`from_bytes`].
+    ///
+    /// [`from_bytes`]: #method.from_bytes
+    pub fn from_bytes_mut(width: u32, height: u32, bytes: &'a mut [u8]) -> Option<Self> {
+        let samples = bytemuck::try_cast_slice_mut(bytes).ok()?;
+        ImageBuffer::from_raw(width, height, samples)
+    }
+}
+
+impl<P, Container> ImageBuffer<P, Container>
+where
+    P: Pixel + bytemuck::Pod,
+    P::Subpixel: bytemuck::Pod,
+    Container: Deref<Target = [P::Subpixel]>,
+{
+    /// The pixels of the image as a slice, for example to upload them to a GPU as a texture.
+    pub fn as_pixels(&self) -> &[P] {
+        let len = Self::image_buffer_len(self.width, self.height).unwrap();
+        bytemuck::cast_slice(&self.data[..len])
+    }
+}
+
 impl<P, Container> ImageBuffer<P, Container>
 where
     P: Pixel + 'static,
@@ -967,7 +1067,7 @@ where
             self.as_bytes(),
             self.width(),
             self.height(),
-            <P as Pixel>::COLOR_TYPE,
+            exact_color_type::<P>()?,
             format,
         )
     }
@@ -1000,10 +1100,30 @@ where
             self.as_bytes(),
             self.width(),
             self.height(),
-            <P as Pixel>::COLOR_TYPE,
+            exact_color_type::<P>()?,
             format,
         )
     }
+
+    /// Encode the buffer with an encoder that has already been configured, for example
+    /// `JpegEncoder::new_with_quality(w, 85)`.
+    pub fn write_with_encoder<E: ImageEncoder>(&self, encoder: E) -> ImageResult<()> {
+        // This is valid as the subpixel is u8.
+        encoder.write_image(self.as_bytes(), self.width(), self.height(), exact_color_type::<P>()?)
+    }
+}
+
+/// The color type of the samples of `P`, for encoding them as they are.
+fn exact_color_type<P: Pixel>() -> ImageResult<ColorType> {
+    P::EXACT_COLOR_TYPE.ok_or_else(|| {
+        ImageError::Unsupported(UnsupportedError::from_format_and_kind(
+            ImageFormatHint::Unknown,
+            UnsupportedErrorKind::GenericFeature(format!(
+                "encoding {} pixels without a matching color type",
+                P::COLOR_MODEL
+            )),
+        ))
+    })
 }
 
 impl<P, Container> Default for ImageBuffer<P, Container>
@@ -1270,6 +1390,22 @@ where
         ImageBuffer::from_raw(width, height, buf)
     }
 
+    /// Creates an image buffer by copying samples whose rows start `stride` samples apart, as
+    /// in GPU readbacks and Windows bitmaps whose rows are padded to an alignment.
+    ///
+    /// Returns `None` if the stride is smaller than a row or the buffer is not big enough. Use an
+    /// [`ImageView`] instead to process such samples without copying them.
+    ///
+    /// [`ImageView`]: struct.ImageView.html
+    pub fn from_raw_with_stride(
+        width: u32,
+        height: u32,
+        stride: usize,
+        buf: &[P::Subpixel],
+    ) -> Option<ImageBuffer<P, Vec<P::Subpixel>>> {
+        ImageView::with_stride(buf, width, height, stride).map(|view| view.to_image())
+    }
+
     /// Consumes the image buffer and returns the underlying data
     /// as an owned buffer
     pub fn into_vec(self) -> Vec<P::Subpixel> {
@@ -1375,6 +1511,8 @@ pub(crate) type Rgba16Image = ImageBuffer<Rgba<u16>, Vec<u16>>;
 pub(crate) type Gray16Image = ImageBuffer<Luma<u16>, Vec<u16>>;
 /// Sendable 16-bit grayscale + alpha channel image buffer
 pub(crate) type GrayAlpha16Image = ImageBuffer<LumaA<u16>, Vec<u16>>;
+/// Sendable CMYK image buffer
+pub(crate) type CmykImage = ImageBuffer<Cmyk<u8>, Vec<u8>>;
 
 /// An image buffer for 32-bit float RGB pixels,
 /// where the backing container is a flattened vector of floats.
@@ -1474,11 +1612,95 @@ mod test {
         assert_eq!(image.rows_mut().count(), 1);
     }
 
+    #[test]
+    #[cfg(feature = "rayon")]
+    fn par_enumerate_pixels_mut() {
+        use rayon::prelude::*;
+
+        let mut a: RgbImage = ImageBuffer::new(7, 5);
+        a.par_enumerate_pixels_mut()
+            .for_each(|(x, y, p)| *p = Rgb([x as u8, y as u8, 0]));
+
+        let mut b: RgbImage = ImageBuffer::new(7, 5);
+        for (x, y, p) in b.enumerate_pixels_mut() {
+            *p = Rgb([x as u8, y as u8, 0]);
+        }
+        assert_eq!(a, b);
+
+        a.par_pixels_mut().for_each(|p| p[2] = 1);
+        assert!(a.pixels().all(|p| p[2] == 1));
+    }
+
     #[test]
     fn default() {
         let image = ImageBuffer::<Rgb<u8>, Vec<u8>>::default();
         assert_eq!(image.dimensions(), (0, 0));
     }
+
+    #[test]
+    fn premultiply_alpha() {
+        let mut image = crate::RgbaImage::from_fn(2, 1, |x, _| {
+            crate::Rgba([255, 128, 0, if x == 0 { 51 } else { 0 }])
+        });
+        image.premultiply_alpha();
+        assert_eq!(image.as_raw(), &[51, 26, 0, 51, 0, 0, 0, 0]);
+        image.unpremultiply_alpha();
+        assert_eq!(image.as_raw(), &[255, 130, 0, 51, 0, 0, 0, 0]);
+
+        // Without alpha nothing changes.
+        let mut image = RgbImage::from_pixel(2, 2, Rgb([10, 20, 30]));
+        image.premultiply_alpha();
+        assert!(image.pixels().all(|&p| p == Rgb([10, 20, 30])));
+    }
+
+    #[test]
+    fn from_raw_with_stride() {
+        // Rows of three pixels padded to four, as in a bitmap aligned to 32 bits.
+        let data = [1, 2, 3, 0, 4, 5, 6, 0, 7, 8, 9];
+        let image: crate::GrayImage = ImageBuffer::from_raw_with_stride(3, 3, 4, &data).unwrap();
+        assert_eq!(image.into_raw(), vec![1, 2, 3, 4, 5, 6, 7, 8, 9]);
+
+        assert!(crate::GrayImage::from_raw_with_stride(3, 3, 2, &data).is_none());
+        assert!(crate::GrayImage::from_raw_with_stride(3, 4, 4, &data).is_none());
+    }
+
+    #[test]
+    fn from_raw_with_stride_edge_cases() {
+        // The stride counts samples, not pixels.
+        let data = [1, 2, 3, 4, 5, 6, 0, 0, 7, 8, 9, 10, 11, 12];
+        let image = RgbImage::from_raw_with_stride(2, 2, 8, &data).unwrap();
+        assert_eq!(image.into_raw(), (1..=12).collect::<Vec<u8>>());
+        // Packed rows and strides that would skip part of a pixel.
+        let packed = RgbImage::from_raw_with_stride(2, 2, 6, &data[..12]).unwrap();
+        assert_eq!(packed.into_raw(), &data[..12]);
+        assert!(RgbImage::from_raw_with_stride(2, 2, 5, &data).is_none());
+
+        // Empty images need no samples.
+        let empty = RgbImage::from_raw_with_stride(0, 3, 0, &[]).unwrap();
+        assert_eq!(empty.dimensions(), (0, 3));
+        let empty = RgbImage::from_raw_with_stride(2, 0, 8, &[]).unwrap();
+        assert_eq!(empty.dimensions(), (2, 0));
+
+        // Sizes that overflow are rejected instead of wrapping around.
+        assert!(RgbImage::from_raw_with_stride(2, 3, usize::max_value(), &data).is_none());
+    }
+
+    #[test]
+    fn from_bytes() {
+        let mut samples = vec![0u16, 1, 2, 3, 4, 5];
+        let bytes: &mut [u8] = bytemuck::cast_slice_mut(&mut samples);
+        {
+            let mut image = ImageBuffer::<Rgb<u16>, _>::from_bytes_mut(2, 1, bytes).unwrap();
+            image.put_pixel(1, 0, Rgb([7, 8, 9]));
+        }
+        let image = ImageBuffer::<Rgb<u16>, _>::from_bytes(2, 1, bytes).unwrap();
+        assert_eq!(image.as_pixels(), &[Rgb([0, 1, 2]), Rgb([7, 8, 9])]);
+
+        // Misaligned and incomplete bytes are rejected.
+        assert!(ImageBuffer::<Rgb<u16>, _>::from_bytes(1, 1, &bytes[1..7]).is_none());
+        assert!(ImageBuffer::<Rgb<u16>, _>::from_bytes(1, 1, &bytes[..5]).is_none());
+        assert!(ImageBuffer::<Rgb<u16>, _>::from_bytes(3, 1, &bytes[..]).is_none());
+    }
 }
 
 #[cfg(test)]
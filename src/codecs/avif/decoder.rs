@@ -8,13 +8,17 @@ use std::error::Error;
 use std::io::{self, Cursor, Read};
 use std::marker::PhantomData;
 use std::mem;
+use std::time::Duration;
 
-use crate::error::DecodingError;
-use crate::{ColorType, ImageDecoder, ImageError, ImageFormat, ImageResult};
+use crate::animation::{Delay, Frame, Frames};
+use crate::error::{DecodingError, UnsupportedError, UnsupportedErrorKind};
+use crate::{
+    AnimationDecoder, ColorType, ImageBuffer, ImageDecoder, ImageError, ImageFormat, ImageResult,
+};
 
 use dav1d::{PixelLayout, PlanarImageComponent};
 use dcv_color_primitives as dcp;
-use mp4parse::read_avif;
+use mp4parse::{read_avif, read_mp4, Track, TrackType};
 
 fn error_map<E: Into<Box<dyn Error + Send + Sync>>>(err: E) -> ImageError {
     ImageError::Decoding(DecodingError::new(ImageFormat::Avif.into(), err))
@@ -22,9 +26,14 @@ fn error_map<E: Into<Box<dyn Error + Send + Sync>>>(err: E) -> ImageError {
 
 /// AVIF Decoder.
 ///
-/// Reads one image into the chosen input.
+/// Reads one image into the chosen input. Image sequences (AVIFS) can be decoded frame by frame
+/// through the [`AnimationDecoder`] implementation, as a still image they decode to their primary
+/// item or, if there is none, to their first frame.
+///
+/// [`AnimationDecoder`]: ../../trait.AnimationDecoder.html
 pub struct AvifDecoder<R> {
     inner: PhantomData<R>,
+    data: Vec<u8>,
     picture: dav1d::Picture,
     alpha_picture: Option<dav1d::Picture>,
 }
@@ -32,30 +41,246 @@ pub struct AvifDecoder<R> {
 impl<R: Read> AvifDecoder<R> {
     /// Create a new decoder that reads its input from `r`.
     pub fn new(mut r: R) -> ImageResult<Self> {
-        let ctx = read_avif(&mut r).map_err(error_map)?;
-        let mut primary_decoder = dav1d::Decoder::new();
-        primary_decoder
-            .send_data(ctx.primary_item(), None, None, None)
-            .map_err(error_map)?;
-        let picture = primary_decoder.get_picture().map_err(error_map)?;
-        let alpha_picture = if let Some(alpha_item) = ctx.alpha_item() {
-            let mut alpha_decoder = dav1d::Decoder::new();
-            alpha_decoder
-                .send_data(alpha_item, None, None, None)
-                .map_err(error_map)?;
-            Some(alpha_decoder.get_picture().map_err(error_map)?)
-        } else {
-            None
+        // The complete file is kept, the samples of an image sequence can be anywhere in it.
+        let mut data = Vec::new();
+        r.read_to_end(&mut data)?;
+
+        let (picture, alpha_picture) = match read_avif(&mut Cursor::new(&data)) {
+            Ok(ctx) => {
+                let mut primary_decoder = dav1d::Decoder::new();
+                primary_decoder
+                    .send_data(ctx.primary_item(), None, None, None)
+                    .map_err(error_map)?;
+                let picture = primary_decoder.get_picture().map_err(error_map)?;
+                let alpha_picture = if let Some(alpha_item) = ctx.alpha_item() {
+                    let mut alpha_decoder = dav1d::Decoder::new();
+                    alpha_decoder
+                        .send_data(alpha_item, None, None, None)
+                        .map_err(error_map)?;
+                    Some(alpha_decoder.get_picture().map_err(error_map)?)
+                } else {
+                    None
+                };
+                (picture, alpha_picture)
+            }
+            // Image sequences are not required to have a primary item.
+            Err(err) => match SequenceDecoder::new(&data) {
+                Ok(mut sequence) => match sequence.next_frame(&data)? {
+                    Some((picture, alpha_picture, _)) => (picture, alpha_picture),
+                    None => return Err(error_map("image sequence contains no frames")),
+                },
+                Err(_) => return Err(error_map(err)),
+            },
         };
-        assert_eq!(picture.bit_depth(), 8);
+        check_bit_depth(&picture)?;
         Ok(AvifDecoder {
             inner: PhantomData,
+            data,
             picture,
             alpha_picture,
         })
     }
 }
 
+/// A sample of the color track of an image sequence.
+struct Sample {
+    offset: usize,
+    size: usize,
+    delay: Delay,
+}
+
+/// Decodes the pictures of one track of an image sequence in presentation order.
+struct TrackDecoder {
+    samples: Vec<Sample>,
+    sent: usize,
+    received: usize,
+    pending: bool,
+    decoder: dav1d::Decoder,
+}
+
+impl TrackDecoder {
+    /// Read the sample table of `track` of the image sequence in `data`.
+    fn new(track: &Track, data: &[u8]) -> ImageResult<Self> {
+        let missing = || error_map("incomplete sample table in image sequence");
+        let offsets = track.stco.as_ref().ok_or_else(missing)?;
+        let chunks = track.stsc.as_ref().ok_or_else(missing)?;
+        let sizes = track.stsz.as_ref().ok_or_else(missing)?;
+        let times = track.stts.as_ref().ok_or_else(missing)?;
+        let timescale = track.timescale.as_ref().ok_or_else(missing)?.0;
+
+        // Samples of equal size have no size table, only the common size.
+        let sample_count = if sizes.sample_size == 0 {
+            sizes.sample_sizes.iter().count()
+        } else {
+            times
+                .samples
+                .iter()
+                .map(|entry| entry.sample_count as usize)
+                .sum()
+        };
+        let mut sample_sizes = sizes
+            .sample_sizes
+            .iter()
+            .cloned()
+            .chain(std::iter::repeat(sizes.sample_size));
+        let mut delays = times.samples.iter().flat_map(|entry| {
+            (0..entry.sample_count).map(move |_| sample_delay(entry.sample_delta, timescale))
+        });
+
+        // Every entry of the chunk table applies up to the first chunk of the next one.
+        let runs: Vec<_> = chunks.samples.iter().collect();
+        let mut samples = Vec::with_capacity(sample_count);
+        for (index, &chunk_offset) in offsets.offsets.iter().enumerate() {
+            let chunk = index as u32 + 1;
+            let per_chunk = runs
+                .iter()
+                .rev()
+                .find(|run| run.first_chunk <= chunk)
+                .map_or(0, |run| run.samples_per_chunk);
+
+            let mut offset = usize::try_from(chunk_offset).map_err(error_map)?;
+            for _ in 0..per_chunk {
+                if samples.len() == sample_count {
+                    break;
+                }
+                let size = sample_sizes.next().ok_or_else(missing)? as usize;
+                if offset
+                    .checked_add(size)
+                    .map_or(true, |end| end > data.len())
+                {
+                    return Err(error_map("sample of image sequence exceeds the file"));
+                }
+                samples.push(Sample {
+                    offset,
+                    size,
+                    delay: delays
+                        .next()
+                        .unwrap_or_else(|| Delay::from_numer_denom_ms(0, 1)),
+                });
+                offset += size;
+            }
+        }
+
+        Ok(TrackDecoder {
+            samples,
+            sent: 0,
+            received: 0,
+            pending: false,
+            decoder: dav1d::Decoder::new(),
+        })
+    }
+
+    /// The next picture and its display duration, or `None` after the last one.
+    fn next_picture(&mut self, data: &[u8]) -> ImageResult<Option<(dav1d::Picture, Delay)>> {
+        loop {
+            // The decoder refuses new data while it holds too many pictures, it keeps it
+            // until some of them have been taken.
+            if self.pending {
+                match self.decoder.send_pending_data() {
+                    Ok(()) => self.pending = false,
+                    Err(err) if err.is_again() => {}
+                    Err(err) => return Err(error_map(err)),
+                }
+            }
+
+            match self.decoder.get_picture() {
+                Ok(picture) => {
+                    // Every sample holds exactly one shown frame.
+                    let delay = self
+                        .samples
+                        .get(self.received)
+                        .map_or_else(|| Delay::from_numer_denom_ms(0, 1), |s| s.delay);
+                    self.received += 1;
+                    return Ok(Some((picture, delay)));
+                }
+                Err(err) if err.is_again() => {
+                    if self.pending {
+                        continue;
+                    }
+                    let sample = match self.samples.get(self.sent) {
+                        Some(sample) => sample,
+                        // Everything was sent and all pictures are drained.
+                        None => return Ok(None),
+                    };
+                    let bytes = data[sample.offset..sample.offset + sample.size].to_vec();
+                    match self.decoder.send_data(bytes, None, None, None) {
+                        Ok(()) => {}
+                        Err(err) if err.is_again() => self.pending = true,
+                        Err(err) => return Err(error_map(err)),
+                    }
+                    self.sent += 1;
+                }
+                Err(err) => return Err(error_map(err)),
+            }
+        }
+    }
+}
+
+/// Decodes the frames of an image sequence from its color track and its alpha track, if any.
+struct SequenceDecoder {
+    color: TrackDecoder,
+    alpha: Option<TrackDecoder>,
+}
+
+impl SequenceDecoder {
+    /// Find the tracks of the image sequence in `data` and read their sample tables.
+    fn new(data: &[u8]) -> ImageResult<Self> {
+        let context = read_mp4(&mut Cursor::new(data)).map_err(error_map)?;
+        // The handlers of image sequence tracks (`pict` for color, `auxv` for the alpha
+        // auxiliary track) are not video handlers, so any track with samples is a candidate. The
+        // alpha track follows the color track it belongs to.
+        let mut tracks = context
+            .tracks
+            .iter()
+            .filter(|track| match track.track_type {
+                TrackType::Audio | TrackType::Metadata => false,
+                _ => track.stco.is_some(),
+            });
+        let color = tracks
+            .next()
+            .ok_or_else(|| error_map("image sequence has no video track"))?;
+        Ok(SequenceDecoder {
+            color: TrackDecoder::new(color, data)?,
+            alpha: tracks
+                .next()
+                .map(|track| TrackDecoder::new(track, data))
+                .transpose()?,
+        })
+    }
+
+    /// The next picture, its alpha picture and its display duration, or `None` after the last
+    /// frame.
+    fn next_frame(
+        &mut self,
+        data: &[u8],
+    ) -> ImageResult<Option<(dav1d::Picture, Option<dav1d::Picture>, Delay)>> {
+        let (picture, delay) = match self.color.next_picture(data)? {
+            Some(frame) => frame,
+            None => return Ok(None),
+        };
+        let alpha_picture = match self.alpha.as_mut() {
+            Some(alpha) => alpha.next_picture(data)?.map(|(picture, _)| picture),
+            None => None,
+        };
+        Ok(Some((picture, alpha_picture, delay)))
+    }
+}
+
+/// Convert a sample duration in units of the track timescale into a frame delay.
+fn sample_delay(delta: u32, timescale: u64) -> Delay {
+    let numerator = u64::from(delta) * 1000;
+    match (u32::try_from(numerator), u32::try_from(timescale)) {
+        (Ok(numerator), Ok(denominator)) if denominator != 0 => {
+            Delay::from_numer_denom_ms(numerator, denominator)
+        }
+        _ => {
+            let nanos = u128::from(delta) * 1_000_000_000 / u128::from(timescale.max(1));
+            let nanos = u64::try_from(nanos).unwrap_or(std::u64::MAX);
+            Delay::from_saturating_duration(Duration::from_nanos(nanos))
+        }
+    }
+}
+
 /// Wrapper struct around a `Cursor<Vec<u8>>`
 pub struct AvifReader<R>(Cursor<Vec<u8>>, PhantomData<R>);
 impl<R> Read for AvifReader<R> {
@@ -93,71 +318,187 @@ impl<'a, R: 'a + Read> ImageDecoder<'a> for AvifDecoder<R> {
 
     fn read_image(self, buf: &mut [u8]) -> ImageResult<()> {
         assert_eq!(u64::try_from(buf.len()), Ok(self.total_bytes()));
+        picture_to_bgra(&self.picture, self.alpha_picture.as_ref(), buf)
+    }
+}
 
-        dcp::initialize();
-
-        if self.picture.pixel_layout() != PixelLayout::I400 {
-            let pixel_format = match self.picture.pixel_layout() {
-                PixelLayout::I400 => todo!(),
-                PixelLayout::I420 => dcp::PixelFormat::I420,
-                PixelLayout::I422 => dcp::PixelFormat::I422,
-                PixelLayout::I444 => dcp::PixelFormat::I444,
-                PixelLayout::Unknown => panic!("Unknown pixel layout"),
-            };
-            let src_format = dcp::ImageFormat {
-                pixel_format,
-                color_space: dcp::ColorSpace::Bt601,
-                num_planes: 3,
-            };
-            let dst_format = dcp::ImageFormat {
-                pixel_format: dcp::PixelFormat::Bgra,
-                color_space: dcp::ColorSpace::Lrgb,
-                num_planes: 1,
-            };
-            let (width, height) = self.dimensions();
-            let planes = &[
-                self.picture.plane(PlanarImageComponent::Y),
-                self.picture.plane(PlanarImageComponent::U),
-                self.picture.plane(PlanarImageComponent::V),
-            ];
-            let src_buffers = planes.iter().map(AsRef::as_ref).collect::<Vec<_>>();
-            let strides = &[
-                self.picture.stride(PlanarImageComponent::Y) as usize,
-                self.picture.stride(PlanarImageComponent::U) as usize,
-                self.picture.stride(PlanarImageComponent::V) as usize,
-            ];
-            let dst_buffers = &mut [&mut buf[..]];
-            dcp::convert_image(
-                width,
-                height,
-                &src_format,
-                Some(strides),
-                &src_buffers,
-                &dst_format,
-                None,
-                dst_buffers,
-            )
-            .map_err(error_map)?;
-        } else {
-            let plane = self.picture.plane(PlanarImageComponent::Y);
-            buf.copy_from_slice(plane.as_ref());
+impl<'a, R: 'a + Read> AnimationDecoder<'a> for AvifDecoder<R> {
+    fn into_frames(self) -> Frames<'a> {
+        struct FrameIterator {
+            data: Vec<u8>,
+            // The picture of a still image, which is returned as a single frame.
+            still: Option<(dav1d::Picture, Option<dav1d::Picture>)>,
+            sequence: Option<SequenceDecoder>,
         }
 
-        if let Some(picture) = self.alpha_picture {
-            assert_eq!(picture.pixel_layout(), PixelLayout::I400);
-            let stride = picture.stride(PlanarImageComponent::Y) as usize;
-            let plane = picture.plane(PlanarImageComponent::Y);
-            let width = picture.width();
-            for (buf, slice) in Iterator::zip(
-                buf.chunks_exact_mut(width as usize * 4),
-                plane.as_ref().chunks_exact(stride),
-            ) {
-                for i in 0..width as usize {
-                    buf[3 + i * 4] = slice[i];
+        impl Iterator for FrameIterator {
+            type Item = ImageResult<Frame>;
+
+            fn next(&mut self) -> Option<Self::Item> {
+                let (picture, alpha_picture, delay) = match self.still.take() {
+                    Some((picture, alpha_picture)) => {
+                        (picture, alpha_picture, Delay::from_numer_denom_ms(0, 1))
+                    }
+                    None => match self.sequence.as_mut()?.next_frame(&self.data) {
+                        Ok(Some(frame)) => frame,
+                        Ok(None) => return None,
+                        Err(err) => {
+                            self.sequence = None;
+                            return Some(Err(err));
+                        }
+                    },
+                };
+
+                let (width, height) = (picture.width(), picture.height());
+                let mut buf = vec![0; width as usize * height as usize * 4];
+                if let Err(err) = picture_to_bgra(&picture, alpha_picture.as_ref(), &mut buf) {
+                    self.sequence = None;
+                    return Some(Err(err));
+                }
+                for pixel in buf.chunks_exact_mut(4) {
+                    pixel.swap(0, 2);
                 }
+                let image = ImageBuffer::from_raw(width, height, buf).unwrap();
+                Some(Ok(Frame::from_parts(image, 0, 0, delay)))
             }
         }
 
+        let (still, sequence) = match SequenceDecoder::new(&self.data) {
+            Ok(sequence) => (None, Some(sequence)),
+            Err(_) => (Some((self.picture, self.alpha_picture)), None),
+        };
+        Frames::new(Box::new(FrameIterator {
+            data: self.data,
+            still,
+            sequence,
+        }))
+    }
+}
+
+/// Only 8-bit pictures can be converted to the output color type.
+fn check_bit_depth(picture: &dav1d::Picture) -> ImageResult<()> {
+    if picture.bit_depth() == 8 {
         Ok(())
+    } else {
+        Err(ImageError::Unsupported(
+            UnsupportedError::from_format_and_kind(
+                ImageFormat::Avif.into(),
+                UnsupportedErrorKind::GenericFeature(format!("{}-bit images", picture.bit_depth())),
+            ),
+        ))
+    }
+}
+
+/// Convert a decoded picture, and its alpha plane if any, to 8-bit BGRA.
+fn picture_to_bgra(
+    picture: &dav1d::Picture,
+    alpha_picture: Option<&dav1d::Picture>,
+    buf: &mut [u8],
+) -> ImageResult<()> {
+    check_bit_depth(picture)?;
+    let (width, height) = (picture.width(), picture.height());
+
+    let pixel_format = match picture.pixel_layout() {
+        PixelLayout::I400 => None,
+        PixelLayout::I420 => Some(dcp::PixelFormat::I420),
+        PixelLayout::I422 => Some(dcp::PixelFormat::I422),
+        PixelLayout::I444 => Some(dcp::PixelFormat::I444),
+        PixelLayout::Unknown => return Err(error_map("unknown pixel layout")),
+    };
+
+    if let Some(pixel_format) = pixel_format {
+        dcp::initialize();
+        let src_format = dcp::ImageFormat {
+            pixel_format,
+            color_space: dcp::ColorSpace::Bt601,
+            num_planes: 3,
+        };
+        let dst_format = dcp::ImageFormat {
+            pixel_format: dcp::PixelFormat::Bgra,
+            color_space: dcp::ColorSpace::Lrgb,
+            num_planes: 1,
+        };
+        let planes = &[
+            picture.plane(PlanarImageComponent::Y),
+            picture.plane(PlanarImageComponent::U),
+            picture.plane(PlanarImageComponent::V),
+        ];
+        let src_buffers = planes.iter().map(AsRef::as_ref).collect::<Vec<_>>();
+        let strides = &[
+            picture.stride(PlanarImageComponent::Y) as usize,
+            picture.stride(PlanarImageComponent::U) as usize,
+            picture.stride(PlanarImageComponent::V) as usize,
+        ];
+        let dst_buffers = &mut [&mut buf[..]];
+        dcp::convert_image(
+            width,
+            height,
+            &src_format,
+            Some(strides),
+            &src_buffers,
+            &dst_format,
+            None,
+            dst_buffers,
+        )
+        .map_err(error_map)?;
+    } else {
+        // Monochrome pictures only have a luma plane, which is the gray value of every channel.
+        let stride = picture.stride(PlanarImageComponent::Y) as usize;
+        let plane = picture.plane(PlanarImageComponent::Y);
+        for (buf, slice) in Iterator::zip(
+            buf.chunks_exact_mut(width as usize * 4),
+            plane.as_ref().chunks(stride),
+        ) {
+            for (pixel, &luma) in buf.chunks_exact_mut(4).zip(slice) {
+                pixel.copy_from_slice(&[luma, luma, luma, 0xff]);
+            }
+        }
+    }
+
+    if let Some(picture) = alpha_picture {
+        check_bit_depth(picture)?;
+        if (picture.width(), picture.height()) != (width, height) {
+            return Err(error_map("alpha plane does not match the image dimensions"));
+        }
+        // Only the luma plane carries alpha, whatever the layout of the auxiliary image.
+        let stride = picture.stride(PlanarImageComponent::Y) as usize;
+        let plane = picture.plane(PlanarImageComponent::Y);
+        for (buf, slice) in Iterator::zip(
+            buf.chunks_exact_mut(width as usize * 4),
+            plane.as_ref().chunks(stride),
+        ) {
+            for i in 0..width as usize {
+                buf[3 + i * 4] = slice[i];
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn animated_with_alpha() {
+        let data = std::fs::read("tests/images/avif/animated/alpha.avif").unwrap();
+        let frames = AvifDecoder::new(&data[..])
+            .unwrap()
+            .into_frames()
+            .collect_frames()
+            .unwrap();
+
+        // Solid frames, lossy encoded, each shown for one second.
+        let expected = [[255, 0, 0, 255], [0, 255, 0, 128], [0, 0, 255, 0]];
+        assert_eq!(frames.len(), expected.len());
+        for (frame, expected) in frames.iter().zip(&expected) {
+            assert_eq!(frame.delay().numer_denom_ms(), (1000, 1));
+            assert_eq!(frame.buffer().dimensions(), (8, 8));
+            for pixel in frame.buffer().pixels() {
+                for (&channel, &expected) in pixel.0.iter().zip(expected) {
+                    assert!((i32::from(channel) - expected).abs() <= 16, "{:?}", pixel);
+                }
+            }
+        }
     }
 }
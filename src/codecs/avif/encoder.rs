@@ -7,7 +7,7 @@ use std::borrow::Cow;
 use std::io::Write;
 use std::cmp::min;
 
-use crate::{ColorType, ImageBuffer, ImageFormat, Pixel};
+use crate::{ColorType, ImageBuffer, ImageEncoder, ImageFormat, Pixel};
 use crate::{ImageError, ImageResult};
 use crate::buffer::ConvertBuffer;
 use crate::color::{FromColor, Luma, LumaA, Bgr, Bgra, Rgb, Rgba};
@@ -89,6 +89,13 @@ impl<W: Write> AvifEncoder<W> {
         self
     }
 
+    /// Encode the alpha channel with the specified `alpha_quality`, independently of the color
+    /// channels. Accepts a value in the range 0-100 like the quality, which it defaults to.
+    pub fn with_alpha_quality(mut self, alpha_quality: u8) -> Self {
+        self.config.alpha_quality = min(alpha_quality, 100);
+        self
+    }
+
     /// Encode image data with the indicated color type.
     ///
     /// The encoder currently requires all data to be RGBA8, it will be converted internally if
@@ -269,3 +276,15 @@ impl<W: Write> AvifEncoder<W> {
         }
     }
 }
+
+impl<W: Write> ImageEncoder for AvifEncoder<W> {
+    fn write_image(
+        self,
+        buf: &[u8],
+        width: u32,
+        height: u32,
+        color_type: ColorType,
+    ) -> ImageResult<()> {
+        AvifEncoder::write_image(self, buf, width, height, color_type)
+    }
+}
@@ -1454,6 +1454,18 @@ impl<'a, R: 'a + Read + Seek> ImageDecoder<'a> for BmpDecoder<R> {
         Ok(BmpReader(Cursor::new(image::decoder_to_vec(self)?), PhantomData))
     }
 
+    fn read_rect(
+        &mut self,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+        buf: &mut [u8],
+    ) -> ImageResult<()> {
+        image::check_rect(self, x, y, width, height, buf)?;
+        self.read_rect_with_progress(x, y, width, height, buf, |_| {})
+    }
+
     fn read_image(mut self, buf: &mut [u8]) -> ImageResult<()> {
         assert_eq!(u64::try_from(buf.len()), Ok(self.total_bytes()));
         self.read_image_data(buf)
@@ -1503,7 +1515,12 @@ mod test {
         let mut decoder = super::BmpDecoder::new(f).unwrap();
 
         let mut buf: Vec<u8> = vec![0; 8 * 8 * 3];
-        decoder.read_rect(0, 0, 8, 8, &mut *buf).unwrap();
+        ImageDecoder::read_rect(&mut decoder, 0, 0, 8, 8, &mut buf).unwrap();
+
+        let mut deprecated: Vec<u8> = vec![0; 8 * 8 * 3];
+        #[allow(deprecated)]
+        ImageDecoderExt::read_rect(&mut decoder, 0, 0, 8, 8, &mut deprecated).unwrap();
+        assert_eq!(deprecated, buf);
     }
 
     #[test]
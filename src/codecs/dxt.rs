@@ -12,7 +12,7 @@ use std::io::{self, Read, Seek, SeekFrom, Write};
 
 use crate::color::ColorType;
 use crate::error::{ImageError, ImageResult, ParameterError, ParameterErrorKind};
-use crate::image::{self, ImageDecoder, ImageDecoderExt, ImageReadBuffer, Progress};
+use crate::image::{self, DecodedRows, ImageDecoder, ImageDecoderExt, ImageReadBuffer, Progress};
 
 /// What version of DXT compression are we using?
 /// Note that DXT2 and DXT4 are left away as they're
@@ -80,6 +80,7 @@ pub struct DxtDecoder<R: Read> {
     height_blocks: u32,
     variant: DXTVariant,
     row: u32,
+    block_rows: Option<DecodedRows>,
 }
 
 impl<R: Read> DxtDecoder<R> {
@@ -112,10 +113,11 @@ impl<R: Read> DxtDecoder<R> {
             height_blocks,
             variant,
             row: 0,
+            block_rows: None,
         })
     }
 
-    fn read_scanline(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+    fn read_block_row(&mut self, buf: &mut [u8]) -> io::Result<usize> {
         assert_eq!(u64::try_from(buf.len()), Ok(self.scanline_bytes()));
 
         let mut src =
@@ -148,6 +150,26 @@ impl<'a, R: 'a + Read> ImageDecoder<'a> for DxtDecoder<R> {
         self.variant.decoded_bytes_per_block() as u64 * u64::from(self.width_blocks)
     }
 
+    fn read_scanline(&mut self, buf: &mut [u8]) -> ImageResult<bool> {
+        assert_eq!(u64::try_from(buf.len()), Ok(self.row_bytes()));
+
+        // The four rows of a block row are decoded together.
+        if let Some(ref mut rows) = self.block_rows {
+            if rows.read_into(buf) {
+                return Ok(true);
+            }
+        }
+        if self.row == self.height_blocks {
+            return Ok(false);
+        }
+        let mut block_row = vec![0; self.scanline_bytes() as usize];
+        self.read_block_row(&mut block_row)?;
+        let mut rows = DecodedRows::new(block_row);
+        rows.read_into(buf);
+        self.block_rows = Some(rows);
+        Ok(true)
+    }
+
     fn into_reader(self) -> ImageResult<Self::Reader> {
         Ok(DxtReader {
             buffer: ImageReadBuffer::new(self.scanline_bytes(), self.total_bytes()),
@@ -159,7 +181,7 @@ impl<'a, R: 'a + Read> ImageDecoder<'a> for DxtDecoder<R> {
         assert_eq!(u64::try_from(buf.len()), Ok(self.total_bytes()));
 
         for chunk in buf.chunks_mut(self.scanline_bytes() as usize) {
-            self.read_scanline(chunk)?;
+            self.read_block_row(chunk)?;
         }
         Ok(())
     }
@@ -184,7 +206,7 @@ impl<'a, R: 'a + Read + Seek> ImageDecoderExt<'a> for DxtDecoder<R> {
                              s.inner.seek(SeekFrom::Start(start + scanline * encoded_scanline_bytes))?;
                              Ok(())
                          },
-                         |s, buf| s.read_scanline(buf).map(|_| ()))?;
+                         |s, buf| s.read_block_row(buf).map(|_| ()))?;
         self.inner.seek(SeekFrom::Start(start))?;
         Ok(())
     }
@@ -210,7 +232,7 @@ pub type DXTReader<R> = DxtReader<R>;
 impl<R: Read> Read for DxtReader<R> {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
         let decoder = &mut self.decoder;
-        self.buffer.read(buf, |buf| decoder.read_scanline(buf))
+        self.buffer.read(buf, |buf| decoder.read_block_row(buf))
     }
 }
 
@@ -205,6 +205,15 @@ impl<'a, R: 'a + Read> ImageDecoder<'a> for FarbfeldDecoder<R> {
         Ok(self.reader)
     }
 
+    fn read_scanline(&mut self, buf: &mut [u8]) -> ImageResult<bool> {
+        assert_eq!(u64::try_from(buf.len()), Ok(self.row_bytes()));
+        if buf.is_empty() || self.reader.current_offset >= self.total_bytes() {
+            return Ok(false);
+        }
+        self.reader.read_exact(buf)?;
+        Ok(true)
+    }
+
     fn scanline_bytes(&self) -> u64 {
         2
     }
@@ -290,7 +299,7 @@ impl<W: Write> ImageEncoder for FarbfeldEncoder<W> {
 #[cfg(test)]
 mod tests {
     use crate::farbfeld::FarbfeldDecoder;
-    use crate::ImageDecoderExt;
+    use crate::ImageDecoder;
     use std::io::{Cursor, Seek, SeekFrom};
     use byteorder::{ByteOrder, NativeEndian};
 
@@ -351,6 +360,18 @@ mod tests {
         assert_eq!(&out_buf[..exp.len()], &exp[..]);
     }
 
+    #[test]
+    fn read_scanlines() {
+        let mut decoder = FarbfeldDecoder::new(Cursor::new(RECTANGLE_IN)).unwrap();
+        let mut row = [0u8; 16];
+        for y in 0..3 {
+            assert!(decoder.read_scanline(&mut row).unwrap());
+            let exp_wide: Vec<u16> = (0..8).map(|i| 0xFF01 - 0xFF * (8 * y + i)).collect();
+            assert_eq!(&row[..], &degenerate_pixels(&exp_wide)[..]);
+        }
+        assert!(!decoder.read_scanline(&mut row).unwrap());
+    }
+
     #[test]
     fn dimension_overflow() {        
         let header = b"farbfeld\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF";
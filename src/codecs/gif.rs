@@ -26,6 +26,7 @@
 //! ```
 #![allow(clippy::while_let_loop)]
 
+use std::borrow::Cow;
 use std::convert::TryInto;
 use std::convert::TryFrom;
 use std::io::{self, Cursor, Read, Write};
@@ -37,8 +38,11 @@ use gif::{DisposalMethod, Frame};
 use num_rational::Ratio;
 
 use crate::animation;
-use crate::ImageBuffer;
-use crate::color::{ColorType, Rgba};
+use crate::buffer::ConvertBuffer;
+use crate::imageops::{dither, ColorMap};
+use crate::quant::{NeuQuant, Palette, Quantizer};
+use crate::{ImageBuffer, RgbaImage};
+use crate::color::{ColorType, Rgb, Rgba};
 use crate::error::{DecodingError, EncodingError, ImageError, ImageResult, ParameterError, ParameterErrorKind, UnsupportedError, UnsupportedErrorKind};
 use crate::image::{self, AnimationDecoder, ImageDecoder, ImageFormat};
 use crate::traits::Pixel;
@@ -58,6 +62,16 @@ impl<R: Read> GifDecoder<R> {
             reader: decoder.read_info(r).map_err(ImageError::from_decoding)?,
         })
     }
+
+    /// Returns the frames as they are stored in the file, without compositing them.
+    ///
+    /// Each frame only covers its own sub-rectangle of the canvas. Unlike the frames of
+    /// [`into_frames`](trait.AnimationDecoder.html#tymethod.into_frames), transparent pixels
+    /// are left transparent and the disposal method of each frame is reported instead of
+    /// applied, so users can do their own compositing.
+    pub fn into_raw_frames(self) -> RawFrames<R> {
+        RawFrames { reader: self.reader }
+    }
 }
 
 /// Wrapper struct around a `Cursor<Vec<u8>>`
@@ -173,42 +187,70 @@ impl<'a, R: 'a + Read> ImageDecoder<'a> for GifDecoder<R> {
     }
 }
 
-struct GifFrameIterator<R: Read> {
-    reader: gif::Decoder<R>,
-
-    width: u32,
-    height: u32,
-
-    non_disposed_frame: ImageBuffer<Rgba<u8>, Vec<u8>>,
+/// How the area of a frame is treated once it has been displayed, before the next frame is drawn
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FrameDisposal {
+    /// The frame is left in place and the next frame is drawn on top of it
+    Keep,
+    /// The area of the frame is cleared to transparent
+    Background,
+    /// The area of the frame is restored to what it was before the frame was drawn
+    Previous,
+
+    #[doc(hidden)]
+    __NonExhaustive(crate::utils::NonExhaustiveMarker),
 }
 
-impl<R: Read> GifFrameIterator<R> {
-    fn new(decoder: GifDecoder<R>) -> GifFrameIterator<R> {
-        let (width, height) = decoder.dimensions();
+impl FrameDisposal {
+    fn from_gif(dispose: DisposalMethod) -> FrameDisposal {
+        match dispose {
+            // note: the `Any` disposal method is underspecified in the GIF
+            // spec, but most viewers treat it identically to `Keep`
+            DisposalMethod::Any | DisposalMethod::Keep => FrameDisposal::Keep,
+            DisposalMethod::Background => FrameDisposal::Background,
+            DisposalMethod::Previous => FrameDisposal::Previous,
+        }
+    }
+}
 
-        // TODO: Avoid this cast
-        let (width, height) = (width as u32, height as u32);
+/// A frame as it is stored in the GIF, before it is composited onto the canvas
+#[derive(Clone)]
+pub struct RawFrame {
+    frame: animation::Frame,
+    disposal: FrameDisposal,
+}
 
-        // intentionally ignore the background color for web compatibility
+impl RawFrame {
+    /// The pixels of the frame, together with its offset on the canvas and its delay.
+    ///
+    /// The buffer only covers the sub-rectangle of the canvas that the frame updates.
+    /// Pixels of the transparent color index have an alpha of zero.
+    pub fn frame(&self) -> &animation::Frame {
+        &self.frame
+    }
 
-        // create the first non disposed frame
-        let non_disposed_frame = ImageBuffer::from_pixel(width, height, Rgba([0, 0, 0, 0]));
+    /// Returns the frame
+    pub fn into_frame(self) -> animation::Frame {
+        self.frame
+    }
 
-        GifFrameIterator {
-            reader: decoder.reader,
-            width,
-            height,
-            non_disposed_frame,
-        }
+    /// How the area of this frame is disposed of before the next frame is drawn
+    pub fn disposal(&self) -> FrameDisposal {
+        self.disposal
     }
 }
 
-impl<R: Read> Iterator for GifFrameIterator<R> {
-    type Item = ImageResult<animation::Frame>;
+/// An iterator over the frames of a GIF as they are stored in the file
+///
+/// Obtained from [`GifDecoder::into_raw_frames`](struct.GifDecoder.html#method.into_raw_frames).
+pub struct RawFrames<R: Read> {
+    reader: gif::Decoder<R>,
+}
 
-    fn next(&mut self) -> Option<ImageResult<animation::Frame>> {
-        // begin looping over each frame
+impl<R: Read> Iterator for RawFrames<R> {
+    type Item = ImageResult<RawFrame>;
 
+    fn next(&mut self) -> Option<ImageResult<RawFrame>> {
         let frame = match self.reader.next_frame_info() {
             Ok(frame_info) => {
                 if let Some(frame) = frame_info {
@@ -231,7 +273,7 @@ impl<R: Read> Iterator for GifFrameIterator<R> {
         // correct storage requirement if the result does not fit in `usize`.
         // on the other hand, `ImageBuffer::from_raw` detects overflow and
         // reports by returning `None`.
-        let mut frame_buffer = match ImageBuffer::from_raw(frame.width, frame.height, vec) {
+        let frame_buffer = match ImageBuffer::from_raw(frame.width, frame.height, vec) {
             Some(frame_buffer) => frame_buffer,
             None => {
                 return Some(Err(ImageError::Unsupported(
@@ -246,9 +288,57 @@ impl<R: Read> Iterator for GifFrameIterator<R> {
             }
         };
 
+        Some(Ok(RawFrame {
+            frame: animation::Frame::from_parts(frame_buffer, frame.left, frame.top, frame.delay),
+            disposal: FrameDisposal::from_gif(frame.disposal_method),
+        }))
+    }
+}
+
+struct GifFrameIterator<R: Read> {
+    frames: RawFrames<R>,
+
+    width: u32,
+    height: u32,
+
+    non_disposed_frame: ImageBuffer<Rgba<u8>, Vec<u8>>,
+}
+
+impl<R: Read> GifFrameIterator<R> {
+    fn new(decoder: GifDecoder<R>) -> GifFrameIterator<R> {
+        let (width, height) = decoder.dimensions();
+
+        // intentionally ignore the background color for web compatibility
+
+        // create the first non disposed frame
+        let non_disposed_frame = ImageBuffer::from_pixel(width, height, Rgba([0, 0, 0, 0]));
+
+        GifFrameIterator {
+            frames: decoder.into_raw_frames(),
+            width,
+            height,
+            non_disposed_frame,
+        }
+    }
+}
+
+impl<R: Read> Iterator for GifFrameIterator<R> {
+    type Item = ImageResult<animation::Frame>;
+
+    fn next(&mut self) -> Option<ImageResult<animation::Frame>> {
+        // begin looping over each frame
+        let raw_frame = match self.frames.next()? {
+            Ok(raw_frame) => raw_frame,
+            Err(err) => return Some(Err(err)),
+        };
+        let disposal = raw_frame.disposal();
+        let (left, top) = (raw_frame.frame.left(), raw_frame.frame.top());
+        let delay = raw_frame.frame.delay();
+        let mut frame_buffer = raw_frame.into_frame().into_buffer();
+
         // blend the current frame with the non-disposed frame, then update
         // the non-disposed frame according to the disposal method.
-        fn blend_and_dispose_pixel(dispose: DisposalMethod,
+        fn blend_and_dispose_pixel(dispose: FrameDisposal,
                 previous: &mut Rgba<u8>, current: &mut Rgba<u8>) {
             let pixel_alpha = current.channels()[3];
             if pixel_alpha == 0 {
@@ -256,44 +346,42 @@ impl<R: Read> Iterator for GifFrameIterator<R> {
             }
 
             match dispose {
-                DisposalMethod::Any | DisposalMethod::Keep => {
-                    // do not dispose
-                    // (keep pixels from this frame)
-                    // note: the `Any` disposal method is underspecified in the GIF
-                    // spec, but most viewers treat it identically to `Keep`
-                    *previous = *current;
-                }
-                DisposalMethod::Background => {
+                FrameDisposal::Background => {
                     // restore to background color
                     // (background shows through transparent pixels in the next frame)
                     *previous = Rgba([0, 0, 0, 0]);
                 }
-                DisposalMethod::Previous => {
+                FrameDisposal::Previous => {
                     // restore to previous
                     // (dispose frames leaving the last none disposal frame)
                 }
+                _ => {
+                    // do not dispose
+                    // (keep pixels from this frame)
+                    *previous = *current;
+                }
             }
         }
 
         // if `frame_buffer`'s frame exactly matches the entire image, then
         // use it directly, else create a new buffer to hold the composited
         // image.
-        let image_buffer = if (frame.left, frame.top) == (0, 0)
+        let image_buffer = if (left, top) == (0, 0)
                 && (self.width, self.height) == frame_buffer.dimensions() {
             for (x, y, pixel) in frame_buffer.enumerate_pixels_mut() {
                 let previous_pixel = self.non_disposed_frame.get_pixel_mut(x, y);
-                blend_and_dispose_pixel(frame.disposal_method, previous_pixel, pixel);
+                blend_and_dispose_pixel(disposal, previous_pixel, pixel);
             }
             frame_buffer
         } else {
             ImageBuffer::from_fn(self.width, self.height, |x, y| {
-                let frame_x = x.wrapping_sub(frame.left);
-                let frame_y = y.wrapping_sub(frame.top);
+                let frame_x = x.wrapping_sub(left);
+                let frame_y = y.wrapping_sub(top);
                 let previous_pixel = self.non_disposed_frame.get_pixel_mut(x, y);
 
                 if frame_x < frame_buffer.width() && frame_y < frame_buffer.height() {
                     let mut pixel = *frame_buffer.get_pixel(frame_x, frame_y);
-                    blend_and_dispose_pixel(frame.disposal_method, previous_pixel, &mut pixel);
+                    blend_and_dispose_pixel(disposal, previous_pixel, &mut pixel);
                     pixel
                 } else {
                     // out of bounds, return pixel from previous frame
@@ -303,7 +391,7 @@ impl<R: Read> Iterator for GifFrameIterator<R> {
         };
 
         Some(Ok(animation::Frame::from_parts(
-            image_buffer, 0,0, frame.delay,
+            image_buffer, 0,0, delay,
         )))
     }
 }
@@ -338,7 +426,7 @@ impl FrameInfo {
 }
 
 /// Number of repetitions for a GIF animation
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Repeat {
     /// Finite number of repetitions
     Finite(u16),
@@ -355,11 +443,37 @@ impl Repeat {
     }
 }
 
+/// The color tables written for the frames of an encoded GIF. The default setting is `PerFrame`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PaletteMode {
+    /// Every frame has its own local color table, computed from the pixels of that frame
+    PerFrame,
+    /// A single global color table, computed from the first frame, is used for all frames
+    ///
+    /// This saves space and avoids colors shifting between frames, but colors that only
+    /// appear in later frames are approximated by the closest color of the first one.
+    Global,
+
+    #[doc(hidden)]
+    __NonExhaustive(crate::utils::NonExhaustiveMarker),
+}
+
+impl Default for PaletteMode {
+    fn default() -> Self {
+        PaletteMode::PerFrame
+    }
+}
+
 /// GIF encoder.
 pub struct GifEncoder<W: Write> {
     w: Option<W>,
     gif_encoder: Option<gif::Encoder<W>>,
     speed: i32,
+    max_colors: usize,
+    dithering: bool,
+    palette_mode: PaletteMode,
+    quantizer: Option<Box<dyn Quantizer + Send + Sync>>,
+    global_palette: Option<Palette>,
     repeat: Option<Repeat>,
 }
 
@@ -383,17 +497,65 @@ impl<W: Write> GifEncoder<W> {
     /// Create a new GIF encoder, and has the speed parameter `speed`. See
     /// [`Frame::from_rgba_speed`](/gif/struct.Frame.html#method.from_rgb_speed)
     /// for more information.
+    ///
+    /// The speed is the sample factor of the NeuQuant color quantization, only every
+    /// `speed`-th pixel is considered when computing a palette.
     pub fn new_with_speed(w: W, speed: i32) -> GifEncoder<W> {
         assert!(speed >= 1 && speed <= 30, "speed needs to be in the range [1, 30]");
         GifEncoder {
             w: Some(w),
             gif_encoder: None,
             speed,
+            max_colors: 256,
+            dithering: false,
+            palette_mode: PaletteMode::default(),
+            quantizer: None,
+            global_palette: None,
             repeat: None,
         }
     }
 
-    /// Set the repeat behaviour of the encoded GIF
+    /// Set the maximum number of colors in each palette. The default is 256.
+    ///
+    /// Fewer colors make for smaller files at the cost of more banding.
+    pub fn with_max_colors(mut self, max_colors: usize) -> Self {
+        assert!(max_colors >= 2 && max_colors <= 256, "max_colors needs to be in the range [2, 256]");
+        self.max_colors = max_colors;
+        self
+    }
+
+    /// Set whether Floyd-Steinberg dithering is applied when reducing the colors of a frame.
+    /// The default is `false`.
+    ///
+    /// Dithering hides banding in gradients but is slower and compresses worse.
+    pub fn with_dithering(mut self, dithering: bool) -> Self {
+        self.dithering = dithering;
+        self
+    }
+
+    /// Set whether the frames share one global palette or each get their own.
+    pub fn with_palette_mode(mut self, palette_mode: PaletteMode) -> Self {
+        self.palette_mode = palette_mode;
+        self
+    }
+
+    /// Set the quantizer choosing the palettes of the frames.
+    ///
+    /// The default is [`NeuQuant`] with the speed of the encoder.
+    ///
+    /// [`NeuQuant`]: ../../quant/struct.NeuQuant.html
+    pub fn with_quantizer<Q>(mut self, quantizer: Q) -> Self
+    where
+        Q: Quantizer + Send + Sync + 'static,
+    {
+        self.quantizer = Some(Box::new(quantizer));
+        self
+    }
+
+    /// Set the repeat behaviour of the encoded GIF.
+    ///
+    /// This writes the NETSCAPE2.0 application extension, which is also how an infinitely
+    /// looping animation is signalled. Without it most viewers play the animation once.
     pub fn set_repeat(&mut self, repeat: Repeat) -> ImageResult<()> {
         if let Some(ref mut encoder) = self.gif_encoder {
             encoder.set_repeat(repeat.to_gif_enum()).map_err(ImageError::from_encoding)?;
@@ -410,17 +572,22 @@ impl<W: Write> GifEncoder<W> {
         height: u32,
         color: ColorType,
     ) -> ImageResult<()> {
-        let (width, height) = self.gif_dimensions(width, height)?;
-        match color {
-            ColorType::Rgb8 => self.encode_gif(Frame::from_rgb(width, height, data)),
-            ColorType::Rgba8 => {
-                self.encode_gif(Frame::from_rgba(width, height, &mut data.to_owned()))
+        let rgba: Option<RgbaImage> = match color {
+            ColorType::Rgb8 => {
+                ImageBuffer::<Rgb<u8>, _>::from_raw(width, height, data).map(|rgb| rgb.convert())
             },
-            _ => Err(ImageError::Unsupported(UnsupportedError::from_format_and_kind(
+            ColorType::Rgba8 => ImageBuffer::from_raw(width, height, data.to_owned()),
+            _ => return Err(ImageError::Unsupported(UnsupportedError::from_format_and_kind(
                 ImageFormat::Gif.into(),
                 UnsupportedErrorKind::Color(color.into())
             ))),
-        }
+        };
+        let rgba = rgba.ok_or_else(|| ImageError::Parameter(ParameterError::from_kind(
+            ParameterErrorKind::DimensionMismatch
+        )))?;
+        let (width, height) = self.gif_dimensions(width, height)?;
+        let frame = self.quantize(width, height, rgba);
+        self.encode_gif(frame)
     }
 
     /// Encode one frame of animation.
@@ -457,25 +624,79 @@ impl<W: Write> GifEncoder<W> {
     pub(crate) fn convert_frame(&mut self, img_frame: animation::Frame)
         -> ImageResult<Frame<'static>>
     {
-        // get the delay before converting img_frame
-        let frame_delay = img_frame.delay().into_ratio().to_integer();
+        // get the delay and offset before converting img_frame
+        let (delay_numer, delay_denom) = img_frame.delay().numer_denom_ms();
+        let (left, top) = self.gif_dimensions(img_frame.left(), img_frame.top())?;
         // convert img_frame into RgbaImage
-        let mut rbga_frame = img_frame.into_buffer();
+        let rbga_frame = img_frame.into_buffer();
         let (width, height) = self.gif_dimensions(
             rbga_frame.width(),
             rbga_frame.height())?;
 
         // Create the gif::Frame from the animation::Frame
-        let mut frame = Frame::from_rgba_speed(width, height, &mut *rbga_frame, self.speed);
+        let mut frame = self.quantize(width, height, rbga_frame);
+        frame.left = left;
+        frame.top = top;
+        // The delay is stored in units of 10ms, round to the closest one.
+        let delay = (u64::from(delay_numer) + 5 * u64::from(delay_denom))
+            / (10 * u64::from(delay_denom));
         // Saturate the conversion to u16::MAX instead of returning an error as that
         // would require a new special cased variant in ParameterErrorKind which most
         // likely couldn't be reused for other cases. This isn't a bad trade-off given
         // that the current algorithm is already lossy.
-        frame.delay = (frame_delay / 10).try_into().unwrap_or(std::u16::MAX);
+        frame.delay = delay.try_into().unwrap_or(std::u16::MAX);
 
         Ok(frame)
     }
 
+    /// Reduce the colors of `rgba` to a palette and create a frame of its indices.
+    ///
+    /// This mirrors `Frame::from_rgba_speed`, but honors the palette settings of the encoder.
+    fn quantize(&mut self, width: u16, height: u16, mut rgba: RgbaImage) -> Frame<'static> {
+        // gif only knows fully transparent or opaque pixels
+        let mut transparent = None;
+        for pixel in rgba.pixels_mut() {
+            if pixel[3] != 0 {
+                pixel[3] = 0xFF;
+            } else {
+                transparent = Some(*pixel);
+            }
+        }
+
+        let default_quantizer = NeuQuant::new(self.speed);
+        let quantizer: &dyn Quantizer = match self.quantizer {
+            Some(ref quantizer) => &**quantizer,
+            None => &default_quantizer,
+        };
+        let max_colors = self.max_colors;
+        let local_palette;
+        let palette = match self.palette_mode {
+            PaletteMode::Global => &*self.global_palette
+                .get_or_insert_with(|| quantizer.palette(&rgba, max_colors)),
+            _ => {
+                local_palette = quantizer.palette(&rgba, max_colors);
+                &local_palette
+            }
+        };
+
+        if self.dithering {
+            dither(&mut rgba, palette);
+        }
+
+        let buffer = palette.index_image(&rgba).into_raw();
+        Frame {
+            width,
+            height,
+            buffer: Cow::Owned(buffer),
+            palette: match self.palette_mode {
+                PaletteMode::Global => None,
+                _ => Some(color_table(palette)),
+            },
+            transparent: transparent.map(|pixel| palette.index_of(&pixel) as u8),
+            ..Frame::default()
+        }
+    }
+
     fn gif_dimensions(&self, width: u32, height: u32) -> ImageResult<(u16, u16)> {
         fn inner_dimensions(width: u32, height: u32) -> Option<(u16, u16)> {
             let width = u16::try_from(width).ok()?;
@@ -495,7 +716,12 @@ impl<W: Write> GifEncoder<W> {
             gif_encoder = encoder;
         } else {
             let writer = self.w.take().unwrap();
-            let mut encoder = gif::Encoder::new(writer, frame.width, frame.height, &[])
+            // The logical screen has to contain the first frame including its offset.
+            let width = frame.left.saturating_add(frame.width);
+            let height = frame.top.saturating_add(frame.height);
+            let global_palette = self.global_palette.as_ref()
+                .map_or_else(Vec::new, color_table);
+            let mut encoder = gif::Encoder::new(writer, width, height, &global_palette)
                 .map_err(ImageError::from_encoding)?;
             if let Some(ref repeat) = self.repeat {
                 encoder.set_repeat(repeat.to_gif_enum()).map_err(ImageError::from_encoding)?;
@@ -508,6 +734,11 @@ impl<W: Write> GifEncoder<W> {
     }
 }
 
+/// The colors of a palette as RGB triplets, the format of GIF color tables.
+fn color_table(palette: &Palette) -> Vec<u8> {
+    palette.colors().iter().flat_map(|color| color.0[..3].to_vec()).collect()
+}
+
 impl ImageError {
     fn from_decoding(err: gif::DecodingError) -> ImageError {
         use gif::DecodingError::*;
@@ -547,4 +778,204 @@ mod test {
 
         assert!(decoder.read_image(&mut buf).is_ok());
     }
+
+    #[test]
+    fn encode_frames_with_delays_and_repeat() {
+        use crate::animation::Delay;
+
+        let delays = [(100, 1), (1000, 30), (20, 1)];
+        let frames = delays.iter().enumerate().map(|(i, &(numer, denom))| {
+            let buffer = ImageBuffer::from_pixel(4, 4, Rgba([i as u8 * 50, 0, 0, 255]));
+            animation::Frame::from_parts(buffer, 0, 0, Delay::from_numer_denom_ms(numer, denom))
+        });
+
+        let mut data = Vec::new();
+        {
+            let mut encoder = GifEncoder::new(&mut data);
+            encoder.set_repeat(Repeat::Finite(3)).unwrap();
+            encoder.encode_frames(frames).unwrap();
+        }
+
+        // NETSCAPE2.0 block with a little endian loop count of 3
+        let netscape = b"NETSCAPE2.0\x03\x01\x03\x00";
+        assert!(data.windows(netscape.len()).any(|window| window == &netscape[..]));
+
+        let decoder = GifDecoder::new(Cursor::new(data)).unwrap();
+        let frames = decoder.into_frames().collect_frames().unwrap();
+        let delays: Vec<_> = frames.iter().map(|frame| frame.delay().numer_denom_ms()).collect();
+        assert_eq!(delays, [(100, 1), (30, 1), (20, 1)]);
+    }
+
+    #[test]
+    fn encode_with_global_palette_and_dithering() {
+        let gradient = |shift: u32| ImageBuffer::from_fn(32, 8, |x, y| {
+            Rgba([(x * 8) as u8, (y * 32) as u8, (x * 4 + shift) as u8, 255])
+        });
+        let frames = vec![
+            animation::Frame::new(gradient(0)),
+            animation::Frame::new(gradient(100)),
+        ];
+
+        let mut data = Vec::new();
+        {
+            let mut encoder = GifEncoder::new(&mut data)
+                .with_max_colors(16)
+                .with_dithering(true)
+                .with_palette_mode(PaletteMode::Global);
+            encoder.encode_frames(frames).unwrap();
+        }
+
+        // the logical screen descriptor announces a global color table
+        assert_eq!(data[10] & 0x80, 0x80);
+
+        let decoder = GifDecoder::new(Cursor::new(data)).unwrap();
+        let frames = decoder.into_frames().collect_frames().unwrap();
+        assert_eq!(frames.len(), 2);
+        let mut colors: Vec<_> = frames.iter()
+            .flat_map(|frame| frame.buffer().pixels().cloned())
+            .map(|pixel| pixel.0)
+            .collect();
+        colors.sort();
+        colors.dedup();
+        assert!(colors.len() <= 16, "{} colors in a 16 color palette", colors.len());
+    }
+
+    #[test]
+    fn encode_palette_edge_cases() {
+        let decode = |data: Vec<u8>| {
+            let decoder = GifDecoder::new(Cursor::new(data)).unwrap();
+            let mut buf = vec![0; decoder.total_bytes() as usize];
+            decoder.read_image(&mut buf).unwrap();
+            buf
+        };
+
+        // Dithering a single column and the smallest palette.
+        let column: Vec<u8> = (0..8u8).flat_map(|y| vec![y * 32, 255 - y * 32, 0, 255]).collect();
+        let mut data = Vec::new();
+        GifEncoder::new(&mut data)
+            .with_max_colors(2)
+            .with_dithering(true)
+            .encode(&column, 1, 8, ColorType::Rgba8)
+            .unwrap();
+        let mut colors: Vec<_> = decode(data).chunks(4).map(|pixel| pixel.to_vec()).collect();
+        colors.sort();
+        colors.dedup();
+        assert!(colors.len() <= 2, "{} colors in a 2 color palette", colors.len());
+
+        // Transparent pixels stay transparent.
+        let pixels = [255, 0, 0, 255, 0, 0, 0, 0, 0, 0, 255, 128];
+        let mut data = Vec::new();
+        GifEncoder::new(&mut data)
+            .with_palette_mode(PaletteMode::Global)
+            .encode(&pixels, 3, 1, ColorType::Rgba8)
+            .unwrap();
+        let alpha: Vec<u8> = decode(data).chunks(4).map(|pixel| pixel[3]).collect();
+        assert_eq!(alpha, [255, 0, 255]);
+    }
+
+    #[test]
+    fn encode_invalid_input() {
+        let mut data = Vec::new();
+        let mut encoder = GifEncoder::new(&mut data);
+        match encoder.encode(&[0; 4], 2, 2, ColorType::L8) {
+            Err(ImageError::Unsupported(_)) => {}
+            other => panic!("expected an unsupported error, got {:?}", other),
+        }
+        match encoder.encode(&[0; 11], 2, 2, ColorType::Rgb8) {
+            Err(ImageError::Parameter(_)) => {}
+            other => panic!("expected a parameter error, got {:?}", other),
+        }
+        assert!(encoder.encode(&[], 0x1_0000, 0, ColorType::Rgba8).is_err());
+    }
+
+    #[test]
+    #[should_panic]
+    fn encode_too_few_colors() {
+        let _ = GifEncoder::new(Vec::new()).with_max_colors(1);
+    }
+
+    #[test]
+    #[should_panic]
+    fn encode_too_many_colors() {
+        let _ = GifEncoder::new(Vec::new()).with_max_colors(257);
+    }
+
+    #[test]
+    fn encode_frame_with_offset() {
+        let buffer = ImageBuffer::from_pixel(2, 3, Rgba([255, 0, 0, 255]));
+        let frame = animation::Frame::from_parts(buffer, 1, 2, animation::Delay::from_numer_denom_ms(0, 1));
+
+        let mut data = Vec::new();
+        GifEncoder::new(&mut data).encode_frame(frame).unwrap();
+
+        let decoder = GifDecoder::new(Cursor::new(data)).unwrap();
+        assert_eq!(decoder.dimensions(), (3, 5));
+        let frames = decoder.into_frames().collect_frames().unwrap();
+        let image = frames[0].buffer();
+        assert_eq!(*image.get_pixel(0, 0), Rgba([0, 0, 0, 0]));
+        assert_eq!(*image.get_pixel(1, 2), Rgba([255, 0, 0, 255]));
+    }
+
+    fn gif_with_disposal(dispose: DisposalMethod) -> Vec<u8> {
+        // a red 2x1 frame followed by a green pixel on the right, the red pixel of
+        // which is transparent
+        let palette = vec![255, 0, 0, 0, 255, 0];
+        let first = Frame {
+            width: 2,
+            height: 1,
+            buffer: Cow::Owned(vec![0, 0]),
+            palette: Some(palette.clone()),
+            dispose,
+            ..Frame::default()
+        };
+        let second = Frame {
+            width: 2,
+            height: 1,
+            buffer: Cow::Owned(vec![0, 1]),
+            palette: Some(palette),
+            transparent: Some(0),
+            ..Frame::default()
+        };
+
+        let mut data = Vec::new();
+        {
+            let mut encoder = gif::Encoder::new(&mut data, 2, 1, &[]).unwrap();
+            encoder.write_frame(&first).unwrap();
+            encoder.write_frame(&second).unwrap();
+        }
+        data
+    }
+
+    #[test]
+    fn frame_disposal() {
+        let red = Rgba([255, 0, 0, 255]);
+        let green = Rgba([0, 255, 0, 255]);
+        let cases = [
+            (DisposalMethod::Keep, red),
+            (DisposalMethod::Background, Rgba([0, 0, 0, 0])),
+            (DisposalMethod::Previous, Rgba([0, 0, 0, 0])),
+        ];
+        for &(dispose, expected) in &cases {
+            let decoder = GifDecoder::new(Cursor::new(gif_with_disposal(dispose))).unwrap();
+            let frames = decoder.into_frames().collect_frames().unwrap();
+            let image = frames[1].buffer();
+            assert_eq!(*image.get_pixel(0, 0), expected, "{:?}", dispose);
+            assert_eq!(*image.get_pixel(1, 0), green, "{:?}", dispose);
+        }
+    }
+
+    #[test]
+    fn raw_frames() {
+        let data = gif_with_disposal(DisposalMethod::Background);
+        let decoder = GifDecoder::new(Cursor::new(data)).unwrap();
+        let frames = decoder.into_raw_frames().collect::<ImageResult<Vec<_>>>().unwrap();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].disposal(), FrameDisposal::Background);
+        assert_eq!(frames[1].disposal(), FrameDisposal::Keep);
+
+        let second = frames[1].frame();
+        assert_eq!((second.left(), second.top()), (0, 0));
+        assert_eq!(second.buffer().get_pixel(0, 0)[3], 0);
+        assert_eq!(*second.buffer().get_pixel(1, 0), Rgba([0, 255, 0, 255]));
+    }
 }
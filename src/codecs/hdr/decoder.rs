@@ -203,6 +203,19 @@ impl<'a, R: 'a + BufRead> ImageDecoder<'a> for HdrAdapter<R> {
         Ok(HdrReader(Cursor::new(image::decoder_to_vec(self)?), PhantomData))
     }
 
+    fn read_rect(
+        &mut self,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+        buf: &mut [u8],
+    ) -> ImageResult<()> {
+        image::check_rect(self, x, y, width, height, buf)?;
+        image::load_rect(x, y, width, height, buf, |_| {}, self, |_, _| unreachable!(),
+                         |s, buf| s.read_image_data(buf))
+    }
+
     fn read_image(mut self, buf: &mut [u8]) -> ImageResult<()> {
         self.read_image_data(buf)
     }
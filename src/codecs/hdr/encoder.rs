@@ -1,12 +1,18 @@
-use crate::color::Rgb;
-use crate::error::ImageResult;
+use crate::color::{ColorType, Rgb};
+use crate::error::{
+    EncodingError, ImageError, ImageFormatHint, ImageResult, UnsupportedError,
+    UnsupportedErrorKind,
+};
 use crate::hdr::{rgbe8, Rgbe8Pixel, SIGNATURE};
+use crate::image::{ImageEncoder, ImageFormat};
+use std::convert::TryInto;
 use std::io::{Result, Write};
 use std::cmp::Ordering;
 
 /// Radiance HDR encoder
 pub struct HdrEncoder<W: Write> {
     w: W,
+    rle: bool,
 }
 
 /// HDR Encoder
@@ -23,13 +29,23 @@ pub type HDREncoder<R> = HdrEncoder<R>;
 impl<W: Write> HdrEncoder<W> {
     /// Creates encoder
     pub fn new(w: W) -> HdrEncoder<W> {
-        HdrEncoder { w }
+        HdrEncoder { w, rle: true }
+    }
+
+    /// Set whether scanlines are run length encoded. The default is `true`.
+    ///
+    /// Scanlines narrower than 8 or wider than 32767 pixels are always written flat, the
+    /// format has no way to run length encode them.
+    pub fn with_rle(mut self, rle: bool) -> Self {
+        self.rle = rle;
+        self
     }
 
     /// Encodes the image ```data```
     /// that has dimensions ```width``` and ```height```
     pub fn encode(mut self, data: &[Rgb<f32>], width: usize, height: usize) -> ImageResult<()> {
         assert!(data.len() >= width * height);
+        let data = &data[..width * height];
         let w = &mut self.w;
         w.write_all(SIGNATURE)?;
         w.write_all(b"\n")?;
@@ -37,7 +53,7 @@ impl<W: Write> HdrEncoder<W> {
         w.write_all(b"FORMAT=32-bit_rle_rgbe\n\n")?;
         w.write_all(format!("-Y {} +X {}\n", height, width).as_bytes())?;
 
-        if width < 8 || width > 32_768 {
+        if !self.rle || width < 8 || width > 32_767 {
             for &pix in data {
                 write_rgbe8(w, to_rgbe8(pix))?;
             }
@@ -82,6 +98,51 @@ impl<W: Write> HdrEncoder<W> {
     }
 }
 
+impl<W: Write> ImageEncoder for HdrEncoder<W> {
+    /// Writes the complete image.
+    ///
+    /// Only `Rgb32F` is supported, the samples are assumed to be native endian.
+    fn write_image(
+        self,
+        buf: &[u8],
+        width: u32,
+        height: u32,
+        color_type: ColorType,
+    ) -> ImageResult<()> {
+        if color_type != ColorType::Rgb32F {
+            return Err(ImageError::Unsupported(UnsupportedError::from_format_and_kind(
+                ImageFormat::Hdr.into(),
+                UnsupportedErrorKind::Color(color_type.into()),
+            )));
+        }
+
+        let (width, height) = (width as usize, height as usize);
+        let expected_len = width
+            .checked_mul(height)
+            .and_then(|size| size.checked_mul(color_type.bytes_per_pixel() as usize));
+        if expected_len.map_or(true, |len| buf.len() < len) {
+            return Err(ImageError::Encoding(EncodingError::new(
+                ImageFormatHint::Exact(ImageFormat::Hdr),
+                "byte buffer not large enough for the specified dimensions and f32 pixels",
+            )));
+        }
+
+        // The buffer might not be aligned to f32, so read every sample individually
+        let pixels: Vec<Rgb<f32>> = buf
+            .chunks_exact(12)
+            .take(width * height)
+            .map(|pixel| {
+                let sample = |i: usize| {
+                    let bytes = pixel[i * 4..(i + 1) * 4].try_into().unwrap();
+                    f32::from_bits(u32::from_ne_bytes(bytes))
+                };
+                Rgb([sample(0), sample(1), sample(2)])
+            })
+            .collect();
+        self.encode(&pixels, width, height)
+    }
+}
+
 #[derive(Debug, PartialEq, Eq)]
 enum RunOrNot {
     Run(u8, usize),
@@ -440,3 +501,68 @@ fn noruncombine_test() {
     assert_eq!(rsi.next(), Some(Norun(256, 1)));
     assert_eq!(rsi.next(), None);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hdr::HdrDecoder;
+    use std::io::Cursor;
+
+    fn round_trip<F>(encode: F, width: usize, height: usize)
+    where
+        F: FnOnce(&mut Vec<u8>, &[Rgb<f32>], usize, usize),
+    {
+        let pixels: Vec<Rgb<f32>> = (0..width * height)
+            .map(|i| {
+                let v = (i / 3) as f32;
+                Rgb([v, v * 0.5, 1000.0 / (v + 1.0)])
+            })
+            .collect();
+        let mut data = Vec::new();
+        encode(&mut data, &pixels, width, height);
+
+        let decoder = HdrDecoder::new(Cursor::new(data)).unwrap();
+        let meta = decoder.metadata();
+        assert_eq!((meta.width, meta.height), (width as u32, height as u32));
+        let decoded = decoder.read_image_hdr().unwrap();
+        assert_eq!(decoded.len(), pixels.len());
+        for (&pix, &dec) in pixels.iter().zip(decoded.iter()) {
+            assert_eq!(to_rgbe8(pix), to_rgbe8(dec));
+        }
+    }
+
+    #[test]
+    fn round_trip_rle() {
+        for &(width, height) in &[(1, 1), (7, 3), (8, 2), (300, 5)] {
+            round_trip(|w, pixels, width, height| {
+                HdrEncoder::new(w).encode(pixels, width, height).unwrap()
+            }, width, height);
+        }
+    }
+
+    #[test]
+    fn round_trip_flat() {
+        round_trip(|w, pixels, width, height| {
+            HdrEncoder::new(w).with_rle(false).encode(pixels, width, height).unwrap()
+        }, 300, 5);
+    }
+
+    #[test]
+    fn write_image_rgb32f() {
+        round_trip(|w, pixels, width, height| {
+            let buf: Vec<f32> = pixels.iter().flat_map(|pix| pix.0.iter().cloned()).collect();
+            HdrEncoder::new(w)
+                .write_image(bytemuck::cast_slice(&buf), width as u32, height as u32, ColorType::Rgb32F)
+                .unwrap()
+        }, 13, 4);
+    }
+
+    #[test]
+    fn write_image_rejects_other_colors() {
+        let result = HdrEncoder::new(Vec::new()).write_image(&[0; 3], 1, 1, ColorType::Rgb8);
+        match result {
+            Err(ImageError::Unsupported(_)) => {}
+            other => panic!("expected an unsupported error, got {:?}", other),
+        }
+    }
+}
@@ -3,39 +3,76 @@ use std::io::{self, Cursor, Read};
 use std::marker::PhantomData;
 use std::mem;
 
-use crate::color::ColorType;
+use crate::color::{ColorType, ExtendedColorType};
 use crate::error::{
-    DecodingError, ImageError, ImageResult, UnsupportedError, UnsupportedErrorKind,
+    DecodingError, ImageError, ImageResult, ParameterError, ParameterErrorKind, UnsupportedError,
+    UnsupportedErrorKind,
 };
-use crate::image::{ImageDecoder, ImageFormat};
+use crate::image::{DecodedRows, ImageDecoder, ImageFormat};
+use crate::metadata::{self, Resolution};
 
 /// JPEG decoder
 pub struct JpegDecoder<R> {
-    decoder: jpeg::Decoder<R>,
+    decoder: jpeg::Decoder<io::Chain<Cursor<Vec<u8>>, R>>,
     metadata: jpeg::ImageInfo,
+    exif: Option<Vec<u8>>,
+    xmp: Option<Vec<u8>>,
+    resolution: Option<Resolution>,
+    cmyk_to_rgb: bool,
+    rows: Option<DecodedRows>,
+    #[cfg(feature = "jpeg_rayon")]
+    threads: Option<(usize, DecodeOnPool<R>)>,
 }
 
+/// Decode on a thread pool, only available for readers that can be sent to it.
+#[cfg(feature = "jpeg_rayon")]
+type DecodeOnPool<R> = fn(
+    &rayon::ThreadPool,
+    &mut jpeg::Decoder<io::Chain<Cursor<Vec<u8>>, R>>,
+) -> Result<Vec<u8>, jpeg::Error>;
+
+/// Images with fewer pixels are decoded on a single thread, where threads cost more than they save.
+#[cfg(feature = "jpeg_rayon")]
+const MIN_PARALLEL_PIXELS: u64 = 512 * 512;
+
 impl<R: Read> JpegDecoder<R> {
     /// Create a new decoder that decodes from the stream ```r```
-    pub fn new(r: R) -> ImageResult<JpegDecoder<R>> {
-        let mut decoder = jpeg::Decoder::new(r);
+    pub fn new(mut r: R) -> ImageResult<JpegDecoder<R>> {
+        // The decoder skips the metadata segments, so they are read ahead and passed on to it.
+        let header = read_metadata_segments(&mut r)?;
+        let exif = metadata::exif_from_jpeg(&header);
+        let xmp = metadata::xmp_from_jpeg(&header);
+        let resolution = metadata::resolution_from_jpeg(&header);
+        let mut decoder = jpeg::Decoder::new(Cursor::new(header).chain(r));
 
         decoder.read_info().map_err(ImageError::from_jpeg)?;
-        let mut metadata = decoder.info().ok_or_else(|| {
+        let metadata = decoder.info().ok_or_else(|| {
             ImageError::Decoding(DecodingError::from_format_hint(ImageFormat::Jpeg.into()))
         })?;
 
-        // We convert CMYK data to RGB before returning it to the user.
-        if metadata.pixel_format == jpeg::PixelFormat::CMYK32 {
-            metadata.pixel_format = jpeg::PixelFormat::RGB24;
-        }
-
         Ok(JpegDecoder {
             decoder,
             metadata,
+            exif,
+            xmp,
+            resolution,
+            cmyk_to_rgb: true,
+            rows: None,
+            #[cfg(feature = "jpeg_rayon")]
+            threads: None,
         })
     }
 
+    /// Configure whether CMYK images are converted to RGB.
+    ///
+    /// By default the decoder converts CMYK data to RGB before returning it. When the conversion
+    /// is disabled, CMYK images are returned as their original samples with a color type of
+    /// `ColorType::Cmyk8`, for example to apply a color profile to them, and decode into a
+    /// `DynamicImage::ImageCmyk8`. Images of other color types are not affected by this setting.
+    pub fn set_cmyk_to_rgb(&mut self, convert: bool) {
+        self.cmyk_to_rgb = convert;
+    }
+
     /// Configure the decoder to scale the image during decoding.
     ///
     /// This efficiently scales the image by the smallest supported
@@ -48,12 +85,21 @@ impl<R: Read> JpegDecoder<R> {
     /// resampling algorithm.
     ///
     /// The size of the image to be loaded, with the scale factor
-    /// applied, is returned.
+    /// applied, is returned. It is also reported by `dimensions`
+    /// from then on, all methods reading the image data return the
+    /// scaled image. The scale must be set before reading any data,
+    /// an error is returned once rows have been read.
     pub fn scale(
         &mut self,
         requested_width: u16,
         requested_height: u16
     ) -> ImageResult<(u16, u16)> {
+        if self.rows.is_some() {
+            return Err(ImageError::Parameter(ParameterError::from_kind(
+                ParameterErrorKind::Generic("the scale must be set before reading rows".to_owned()),
+            )));
+        }
+
         let result = self.decoder.scale(requested_width, requested_height)
             .map_err(ImageError::from_jpeg)?;
 
@@ -62,6 +108,93 @@ impl<R: Read> JpegDecoder<R> {
 
         Ok(result)
     }
+
+    fn decode_data(&mut self) -> ImageResult<Vec<u8>> {
+        let data = self.decode_on_threads()?;
+        Ok(match self.decoder.info().unwrap().pixel_format {
+            jpeg::PixelFormat::CMYK32 if self.cmyk_to_rgb => cmyk_to_rgb(&data),
+            _ => data,
+        })
+    }
+
+    #[cfg(feature = "jpeg_rayon")]
+    fn decode_on_threads(&mut self) -> ImageResult<Vec<u8>> {
+        let (threads, decode) = match self.threads {
+            Some(threads) => threads,
+            None => return self.decoder.decode().map_err(ImageError::from_jpeg),
+        };
+
+        let pixels = u64::from(self.metadata.width) * u64::from(self.metadata.height);
+        let threads = if pixels < MIN_PARALLEL_PIXELS { 1 } else { threads };
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .map_err(|err| ImageError::IoError(io::Error::new(io::ErrorKind::Other, err)))?;
+        decode(&pool, &mut self.decoder).map_err(ImageError::from_jpeg)
+    }
+
+    #[cfg(not(feature = "jpeg_rayon"))]
+    fn decode_on_threads(&mut self) -> ImageResult<Vec<u8>> {
+        self.decoder.decode().map_err(ImageError::from_jpeg)
+    }
+}
+
+#[cfg(feature = "jpeg_rayon")]
+impl<R: Read + Send> JpegDecoder<R> {
+    /// Decode the image with `threads` worker threads.
+    ///
+    /// The image is decoded on a thread pool of its own instead of the global `rayon` pool. A
+    /// value of zero lets `rayon` choose the number of threads. Images smaller than 512×512 pixels
+    /// are still decoded on a single thread.
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        fn decode_on_pool<R: Read + Send>(
+            pool: &rayon::ThreadPool,
+            decoder: &mut jpeg::Decoder<io::Chain<Cursor<Vec<u8>>, R>>,
+        ) -> Result<Vec<u8>, jpeg::Error> {
+            pool.install(|| decoder.decode())
+        }
+
+        self.threads = Some((threads, decode_on_pool::<R>));
+        self
+    }
+}
+
+/// Read the start of the file up to the first segment that cannot hold metadata.
+///
+/// Decoding errors are left to the decoder, reading stops at anything unexpected.
+fn read_metadata_segments<R: Read>(r: &mut R) -> io::Result<Vec<u8>> {
+    fn read_more<R: Read>(r: &mut R, header: &mut Vec<u8>, count: u64) -> io::Result<bool> {
+        Ok(r.by_ref().take(count).read_to_end(header)? as u64 == count)
+    }
+
+    let mut header = Vec::new();
+    if !read_more(r, &mut header, 2)? || header[..] != [0xFF, 0xD8] {
+        return Ok(header);
+    }
+    loop {
+        if !read_more(r, &mut header, 2)? || header[header.len() - 2] != 0xFF {
+            return Ok(header);
+        }
+        // Markers may be preceded by any number of fill bytes.
+        while header[header.len() - 1] == 0xFF {
+            if !read_more(r, &mut header, 1)? {
+                return Ok(header);
+            }
+        }
+        match header[header.len() - 1] {
+            // Application segments, comments and tables.
+            0xE0..=0xEF | 0xFE | 0xDB | 0xC4 | 0xDD => {}
+            _ => return Ok(header),
+        }
+
+        if !read_more(r, &mut header, 2)? {
+            return Ok(header);
+        }
+        let length = u16::from_be_bytes([header[header.len() - 2], header[header.len() - 1]]);
+        if !read_more(r, &mut header, u64::from(length.saturating_sub(2)))? {
+            return Ok(header);
+        }
+    }
 }
 
 /// Wrapper struct around a `Cursor<Vec<u8>>`
@@ -88,28 +221,59 @@ impl<'a, R: 'a + Read> ImageDecoder<'a> for JpegDecoder<R> {
     }
 
     fn color_type(&self) -> ColorType {
-        ColorType::from_jpeg(self.metadata.pixel_format)
+        match self.metadata.pixel_format {
+            // We convert CMYK data to RGB before returning it to the user, unless disabled.
+            jpeg::PixelFormat::CMYK32 if self.cmyk_to_rgb => ColorType::Rgb8,
+            pixel_format => ColorType::from_jpeg(pixel_format),
+        }
     }
 
-    fn into_reader(mut self) -> ImageResult<Self::Reader> {
-        let mut data = self.decoder.decode().map_err(ImageError::from_jpeg)?;
-        data = match self.decoder.info().unwrap().pixel_format {
-            jpeg::PixelFormat::CMYK32 => cmyk_to_rgb(&data),
-            _ => data,
-        };
+    fn original_color_type(&self) -> ExtendedColorType {
+        ColorType::from_jpeg(self.metadata.pixel_format).into()
+    }
+
+    fn exif_metadata(&mut self) -> Option<Vec<u8>> {
+        self.exif.clone()
+    }
+
+    fn xmp_metadata(&mut self) -> Option<Vec<u8>> {
+        self.xmp.clone()
+    }
+
+    fn resolution(&mut self) -> Option<Resolution> {
+        self.resolution
+    }
+
+    fn icc_profile(&mut self) -> Option<Vec<u8>> {
+        // The profile may be split over several APP2 segments, they are joined by the decoder.
+        self.decoder.icc_profile()
+    }
 
+    fn into_reader(mut self) -> ImageResult<Self::Reader> {
+        let data = self.decode_data()?;
         Ok(JpegReader(Cursor::new(data), PhantomData))
     }
 
+    /// Decode the next row of the image into `buf`.
+    ///
+    /// The underlying decoder only decodes complete images. The first call decodes the whole
+    /// image and keeps it in memory while its rows are read, so this needs as much memory as
+    /// `read_image`.
+    fn read_scanline(&mut self, buf: &mut [u8]) -> ImageResult<bool> {
+        assert_eq!(u64::try_from(buf.len()), Ok(self.row_bytes()));
+
+        // The decoder only returns complete images, so the rows are taken from a full decode.
+        if self.rows.is_none() {
+            let data = self.decode_data()?;
+            self.rows = Some(DecodedRows::new(data));
+        }
+        Ok(self.rows.as_mut().unwrap().read_into(buf))
+    }
+
     fn read_image(mut self, buf: &mut [u8]) -> ImageResult<()> {
         assert_eq!(u64::try_from(buf.len()), Ok(self.total_bytes()));
 
-        let mut data = self.decoder.decode().map_err(ImageError::from_jpeg)?;
-        data = match self.decoder.info().unwrap().pixel_format {
-            jpeg::PixelFormat::CMYK32 => cmyk_to_rgb(&data),
-            _ => data,
-        };
-
+        let data = self.decode_data()?;
         buf.copy_from_slice(&data);
         Ok(())
     }
@@ -119,8 +283,15 @@ fn cmyk_to_rgb(input: &[u8]) -> Vec<u8> {
     let count = input.len() / 4;
     let mut output = vec![0; 3 * count];
 
-    let in_pixels = input[..4 * count].chunks_exact(4);
-    let out_pixels = output[..3 * count].chunks_exact_mut(3);
+    let converted = super::simd::cmyk_to_rgb(&input[..4 * count], &mut output);
+    cmyk_to_rgb_scalar(&input[4 * converted..4 * count], &mut output[3 * converted..]);
+
+    output
+}
+
+fn cmyk_to_rgb_scalar(input: &[u8], output: &mut [u8]) {
+    let in_pixels = input.chunks_exact(4);
+    let out_pixels = output.chunks_exact_mut(3);
 
     for (pixel, outp) in in_pixels.zip(out_pixels) {
         let c = 255 - u16::from(pixel[0]);
@@ -136,8 +307,6 @@ fn cmyk_to_rgb(input: &[u8]) -> Vec<u8> {
         outp[1] = g as u8;
         outp[2] = b as u8;
     }
-
-    output
 }
 
 impl ColorType {
@@ -146,7 +315,7 @@ impl ColorType {
         match pixel_format {
             L8 => ColorType::L8,
             RGB24 => ColorType::Rgb8,
-            CMYK32 => panic!(),
+            CMYK32 => ColorType::Cmyk8,
         }
     }
 }
@@ -175,7 +344,7 @@ mod tests {
     #[cfg(feature = "benchmarks")]
     extern crate test;
 
-    use super::cmyk_to_rgb;
+    use super::{cmyk_to_rgb, cmyk_to_rgb_scalar, read_metadata_segments, JpegDecoder};
     #[cfg(feature = "benchmarks")]
     use test::Bencher;
 
@@ -206,6 +375,27 @@ mod tests {
         }
     }
 
+    #[test]
+    fn cmyk_to_rgb_matches_scalar() {
+        // Every combination of a channel and black, shifted through all channel positions and
+        // followed by a remainder that does not fill a whole vector.
+        let mut input = Vec::new();
+        for c in 0..=255u8 {
+            for k in 0..=255u8 {
+                input.extend_from_slice(&[c, k.wrapping_add(c), 255 - c, k]);
+            }
+        }
+        input.extend_from_slice(&[1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13]);
+
+        let mut expected = vec![0; input.len() / 4 * 3];
+        cmyk_to_rgb_scalar(&input, &mut expected);
+        assert_eq!(cmyk_to_rgb(&input), expected);
+        for offset in 1..8 {
+            let len = (input.len() - 4 * offset) / 4;
+            assert_eq!(cmyk_to_rgb(&input[4 * offset..]), &expected[3 * offset..][..3 * len]);
+        }
+    }
+
     fn single_pix_correct(cmyk_pix: [u8; 4], rgb_pix_true: [u8; 3]) {
         let rgb_pix = cmyk_to_rgb(&cmyk_pix);
         assert_eq!(rgb_pix_true[0], rgb_pix[0], "With CMYK {:?} expected {:?}, got {:?}", cmyk_pix, rgb_pix_true, rgb_pix);
@@ -222,6 +412,163 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_read_metadata_segments() {
+        let mut data = vec![0xFF, 0xD8, 0xFF, 0xE1, 0, 6, b'E', b'x', b'i', b'f'];
+        data.extend_from_slice(&[0xFF, 0xFF, 0xDB, 0, 3, 0]);
+        let header_len = data.len() + 2;
+        // The frame header ends the metadata.
+        data.extend_from_slice(&[0xFF, 0xC0, 0, 11, 8]);
+
+        let mut reader = &data[..];
+        let header = read_metadata_segments(&mut reader).unwrap();
+        assert_eq!(header, &data[..header_len]);
+        assert_eq!(reader, &data[header_len..]);
+
+        let truncated = &data[..8];
+        assert_eq!(read_metadata_segments(&mut &truncated[..]).unwrap(), truncated);
+    }
+
+    #[test]
+    fn test_scaled_decoding() {
+        use crate::image::ImageDecoder;
+
+        let file = std::fs::File::open("tests/images/jpg/progressive/test.jpg").unwrap();
+        let mut decoder = JpegDecoder::new(file).unwrap();
+        assert_eq!(decoder.dimensions(), (32, 23));
+
+        // The image is scaled by 1/8, rounding up.
+        assert_eq!(decoder.scale(4, 3).unwrap(), (4, 3));
+        assert_eq!(decoder.dimensions(), (4, 3));
+        let mut buf = vec![0; decoder.total_bytes() as usize];
+        decoder.read_image(&mut buf).unwrap();
+    }
+
+    #[test]
+    fn test_scale_bounds() {
+        use crate::image::ImageDecoder;
+
+        let open = || {
+            let file = std::fs::File::open("tests/images/jpg/progressive/test.jpg").unwrap();
+            JpegDecoder::new(file).unwrap()
+        };
+        // The smallest scale is used for an empty size, the image is never enlarged.
+        assert_eq!(open().scale(0, 0).unwrap(), (4, 3));
+        assert_eq!(open().scale(1000, 1000).unwrap(), (32, 23));
+        // A size that is reached in one axis is enough.
+        assert_eq!(open().scale(16, 1).unwrap(), (4, 3));
+        assert_eq!(open().scale(16, 12).unwrap(), (16, 12));
+
+        // Rows that have already been decoded can not be scaled.
+        let mut decoder = open();
+        let mut row = vec![0; decoder.row_bytes() as usize];
+        assert!(decoder.read_scanline(&mut row).unwrap());
+        assert!(decoder.scale(4, 3).is_err());
+        assert_eq!(decoder.dimensions(), (32, 23));
+        assert!(decoder.read_scanline(&mut row).unwrap());
+    }
+
+    #[test]
+    fn test_icc_profile() {
+        use crate::codecs::jpeg::JpegEncoder;
+        use crate::image::ImageDecoder;
+        use crate::ColorType;
+
+        let file = std::fs::File::open("tests/images/jpg/progressive/test.jpg").unwrap();
+        let mut decoder = JpegDecoder::new(file).unwrap();
+        let profile = decoder.icc_profile().expect("test.jpg embeds an ICC profile");
+        // The profile header starts with its own size and carries the `acsp` signature.
+        assert_eq!(profile.len(), 3144);
+        assert_eq!(&profile[..4], &(profile.len() as u32).to_be_bytes());
+        assert_eq!(&profile[36..40], b"acsp");
+
+        // Our encoder does not write a profile.
+        let mut data = Vec::new();
+        JpegEncoder::new(&mut data).encode(&[0; 8 * 8], 8, 8, ColorType::L8).unwrap();
+        let mut decoder = JpegDecoder::new(&data[..]).unwrap();
+        assert_eq!(decoder.icc_profile(), None);
+    }
+
+    #[test]
+    fn test_cmyk_without_conversion() {
+        use crate::color::{ColorType, ExtendedColorType};
+        use crate::image::ImageDecoder;
+
+        // Cyan and black in the top half, white and red in the bottom half.
+        let path = "tests/images/jpg/cmyk/quadrants.jpg";
+        let quadrants = [[255, 0, 0, 0], [0, 0, 0, 255], [0, 0, 0, 0], [0, 255, 255, 0]];
+
+        let mut decoder = JpegDecoder::new(std::fs::File::open(path).unwrap()).unwrap();
+        decoder.set_cmyk_to_rgb(false);
+        assert_eq!(decoder.color_type(), ColorType::Cmyk8);
+        assert_eq!(decoder.original_color_type(), ExtendedColorType::Cmyk8);
+        assert_eq!(decoder.total_bytes(), 16 * 8 * 4);
+        let mut buf = vec![0; decoder.total_bytes() as usize];
+        decoder.read_image(&mut buf).unwrap();
+        for (i, pixel) in buf.chunks(4).enumerate() {
+            let (x, y) = (i % 16, i / 16);
+            assert_eq!(pixel, quadrants[2 * (y / 4) + x / 8], "at ({}, {})", x, y);
+        }
+
+        // The conversion is on by default.
+        let decoder = JpegDecoder::new(std::fs::File::open(path).unwrap()).unwrap();
+        assert_eq!(decoder.color_type(), ColorType::Rgb8);
+        let mut rgb = vec![0; decoder.total_bytes() as usize];
+        decoder.read_image(&mut rgb).unwrap();
+        assert_eq!(rgb, cmyk_to_rgb(&buf));
+    }
+
+    #[test]
+    #[cfg(feature = "jpeg_rayon")]
+    fn test_decoding_with_threads() {
+        use crate::image::ImageDecoder;
+
+        let path = "tests/images/jpg/progressive/test.jpg";
+        let decode = |threads: Option<usize>| {
+            let mut decoder = JpegDecoder::new(std::fs::File::open(path).unwrap()).unwrap();
+            if let Some(threads) = threads {
+                decoder = decoder.with_threads(threads);
+            }
+            let mut buf = vec![0; decoder.total_bytes() as usize];
+            decoder.read_image(&mut buf).unwrap();
+            buf
+        };
+
+        let expected = decode(None);
+        assert_eq!(decode(Some(0)), expected);
+        assert_eq!(decode(Some(4)), expected);
+    }
+
+    #[test]
+    #[cfg(feature = "jpeg_rayon")]
+    fn test_decoding_large_image_on_pool() {
+        use crate::codecs::jpeg::JpegEncoder;
+        use crate::image::ImageDecoder;
+        use crate::ColorType;
+
+        // Large enough to be decoded on the pool instead of a single thread.
+        let (width, height) = (640, 512);
+        assert!(u64::from(width) * u64::from(height) >= super::MIN_PARALLEL_PIXELS);
+        let rgb: Vec<u8> = (0..width * height * 3).map(|i| ((i % 251) ^ (i / 1920)) as u8).collect();
+        let mut data = Vec::new();
+        JpegEncoder::new(&mut data).encode(&rgb, width, height, ColorType::Rgb8).unwrap();
+
+        let decode = |threads: Option<usize>| {
+            let mut decoder = JpegDecoder::new(&data[..]).unwrap();
+            if let Some(threads) = threads {
+                decoder = decoder.with_threads(threads);
+            }
+            let mut buf = vec![0; decoder.total_bytes() as usize];
+            decoder.read_image(&mut buf).unwrap();
+            buf
+        };
+
+        let expected = decode(None);
+        assert_eq!(expected.len(), rgb.len());
+        assert_eq!(decode(Some(1)), expected);
+        assert_eq!(decode(Some(3)), expected);
+    }
+
     #[cfg(feature = "benchmarks")]
     #[bench]
     fn bench_cmyk_to_rgb(b: &mut Bencher) {
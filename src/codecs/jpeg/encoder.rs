@@ -8,6 +8,7 @@ use num_iter::range_step;
 use crate::{Bgr, Bgra, ColorType, GenericImageView, ImageBuffer, Luma, LumaA, Pixel, Rgb, Rgba};
 use crate::error::{ImageError, ImageResult, ParameterError, ParameterErrorKind, UnsupportedError, UnsupportedErrorKind};
 use crate::image::{ImageEncoder, ImageFormat};
+use crate::metadata::{ImageMetadata, JPEG_XMP_SIGNATURE};
 use crate::utils::clamp;
 
 use super::entropy::build_huff_lut;
@@ -16,6 +17,8 @@ use super::transform;
 // Markers
 // Baseline DCT
 static SOF0: u8 = 0xC0;
+// Progressive DCT, Huffman coding
+static SOF2: u8 = 0xC2;
 // Huffman Tables
 static DHT: u8 = 0xC4;
 // Start of Image (standalone)
@@ -28,6 +31,11 @@ static SOS: u8 = 0xDA;
 static DQT: u8 = 0xDB;
 // Application segments start and end
 static APP0: u8 = 0xE0;
+static APP1: u8 = 0xE1;
+static APP2: u8 = 0xE2;
+
+// The largest payload of a segment, its length field counts itself.
+const MAX_SEGMENT_DATA: usize = 65533;
 
 // section K.1
 // table K.1
@@ -199,7 +207,11 @@ impl<'a, W: Write + 'a> BitWriter<'a, W> {
     }
 
     fn pad_byte(&mut self) -> io::Result<()> {
-        self.write_bits(0x7F, 7)
+        self.write_bits(0x7F, 7)?;
+        // Discard the padding that did not complete a byte, the next scan starts on a byte.
+        self.accumulator = 0;
+        self.nbits = 0;
+        Ok(())
     }
 
     fn huffman_encode(&mut self, val: u8, table: &[(u8, u16); 256]) -> io::Result<()> {
@@ -256,6 +268,126 @@ impl<'a, W: Write + 'a> BitWriter<'a, W> {
         Ok(dcval)
     }
 
+    /// Write the DC coefficient of a block in the first scan of a progressive image.
+    fn write_dc_first(
+        &mut self,
+        block: &[i32; 64],
+        prevdc: i32,
+        al: u8,
+        dctable: &[(u8, u16); 256],
+    ) -> io::Result<i32> {
+        // The point transform is an arithmetic shift for DC coefficients.
+        let dcval = block[0] >> al;
+        let (size, value) = encode_coefficient(dcval - prevdc);
+
+        self.huffman_encode(size, dctable)?;
+        self.write_bits(value, size)?;
+
+        Ok(dcval)
+    }
+
+    /// Write a band of AC coefficients of a block in the first scan of the band.
+    fn write_ac_first(
+        &mut self,
+        block: &[i32; 64],
+        start: u8,
+        end: u8,
+        al: u8,
+        actable: &[(u8, u16); 256],
+    ) -> io::Result<()> {
+        let mut zero_run = 0;
+
+        for &k in &UNZIGZAG[usize::from(start)..=usize::from(end)] {
+            // The point transform divides the magnitude, rounding towards zero.
+            let coefficient = block[k as usize];
+            let magnitude = coefficient.abs() >> al;
+            if magnitude == 0 {
+                zero_run += 1;
+                continue;
+            }
+
+            while zero_run > 15 {
+                self.huffman_encode(0xF0, actable)?;
+                zero_run -= 16;
+            }
+
+            let (size, value) = encode_coefficient(if coefficient < 0 {
+                -magnitude
+            } else {
+                magnitude
+            });
+            self.huffman_encode((zero_run << 4) | size, actable)?;
+            self.write_bits(value, size)?;
+
+            zero_run = 0;
+        }
+
+        if zero_run > 0 {
+            self.huffman_encode(0x00, actable)?;
+        }
+
+        Ok(())
+    }
+
+    /// Write the next bit of a band of AC coefficients of a block, see section G.1.2.3.
+    fn write_ac_refine(
+        &mut self,
+        block: &[i32; 64],
+        start: u8,
+        end: u8,
+        al: u8,
+        actable: &[(u8, u16); 256],
+    ) -> io::Result<()> {
+        let band = &UNZIGZAG[usize::from(start)..=usize::from(end)];
+        let magnitude = |k: u8| block[k as usize].abs() >> al;
+        // Zero runs can only be coded up to the last coefficient becoming nonzero in this scan,
+        // the rest of the block is part of the end of block.
+        let last_new = band.iter().rposition(|&k| magnitude(k) == 1);
+
+        let mut zero_run = 0;
+        // Correction bits of coefficients that were already nonzero, they are sent after the
+        // next symbol.
+        let mut corrections = Vec::with_capacity(band.len());
+
+        for (i, &k) in band.iter().enumerate() {
+            let value = magnitude(k);
+            if value == 0 {
+                zero_run += 1;
+                continue;
+            }
+
+            while zero_run > 15 && last_new.map_or(false, |last| i <= last) {
+                self.huffman_encode(0xF0, actable)?;
+                zero_run -= 16;
+                for bit in corrections.drain(..) {
+                    self.write_bits(bit, 1)?;
+                }
+            }
+
+            if value > 1 {
+                corrections.push((value & 1) as u16);
+                continue;
+            }
+
+            self.huffman_encode((zero_run << 4) | 1, actable)?;
+            self.write_bits(if block[k as usize] < 0 { 0 } else { 1 }, 1)?;
+            for bit in corrections.drain(..) {
+                self.write_bits(bit, 1)?;
+            }
+
+            zero_run = 0;
+        }
+
+        if zero_run > 0 || !corrections.is_empty() {
+            self.huffman_encode(0x00, actable)?;
+            for bit in corrections {
+                self.write_bits(bit, 1)?;
+            }
+        }
+
+        Ok(())
+    }
+
     fn write_marker(&mut self, marker: u8) -> io::Result<()> {
         self.w.write_all(&[0xFF, marker])
     }
@@ -320,6 +452,117 @@ impl Default for PixelDensity {
     }
 }
 
+/// The resolution at which the chroma components of a color image are stored.
+///
+/// The eye is less sensitive to detail in color than in brightness, so storing the chroma at a
+/// reduced resolution makes images considerably smaller at little visible cost. Sharp edges
+/// between saturated colors, such as colored text, become blurry however.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum Subsampling {
+    /// Chroma at full resolution.
+    S444,
+    /// Chroma at half the horizontal resolution.
+    S422,
+    /// Chroma at half the horizontal and half the vertical resolution.
+    S420,
+}
+
+impl Subsampling {
+    /// The horizontal and vertical sampling factors of the luma component.
+    fn luma_factors(self) -> (u8, u8) {
+        match self {
+            Subsampling::S444 => (1, 1),
+            Subsampling::S422 => (2, 1),
+            Subsampling::S420 => (2, 2),
+        }
+    }
+}
+
+/// A scan of a progressive JPEG image.
+///
+/// Progressive images are stored in several scans, which each contain a band of the coefficients
+/// of one or all components, possibly with reduced precision. Decoders can display the image
+/// after every scan, so a coarse version appears early while the image is loading. A scan script
+/// for the encoder is set with [`JpegEncoder::set_scan_script`].
+///
+/// [`JpegEncoder::set_scan_script`]: struct.JpegEncoder.html#method.set_scan_script
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct ProgressiveScan {
+    /// The component contained in the scan, where 0 is the luma and 1 and 2 are the chroma
+    /// components, or `None` for all components of the image. Scans of AC coefficients can only
+    /// contain a single component.
+    pub component: Option<u8>,
+    /// The index of the first coefficient of the band in zigzag order, 0 for the DC coefficient.
+    pub spectral_start: u8,
+    /// The index of the last coefficient of the band, up to 63. Scans of the DC coefficient
+    /// contain only that coefficient.
+    pub spectral_end: u8,
+    /// The point transform of the previous scan of the band, or 0 for the first scan of the band.
+    /// Otherwise it must be one more than `approximation_low`, the scan refines the band by a
+    /// single bit.
+    pub approximation_high: u8,
+    /// The point transform of the scan, the number of low bits of the coefficients not yet sent.
+    pub approximation_low: u8,
+}
+
+impl ProgressiveScan {
+    fn new(
+        component: Option<u8>,
+        spectral_start: u8,
+        spectral_end: u8,
+        approximation_high: u8,
+        approximation_low: u8,
+    ) -> Self {
+        ProgressiveScan {
+            component,
+            spectral_start,
+            spectral_end,
+            approximation_high,
+            approximation_low,
+        }
+    }
+
+    fn is_valid(&self, num_components: usize) -> bool {
+        let dc = self.spectral_start == 0;
+        self.component.map_or(true, |c| usize::from(c) < num_components)
+            && self.spectral_start <= self.spectral_end
+            && self.spectral_end <= 63
+            && dc == (self.spectral_end == 0)
+            && (dc || self.component.is_some() || num_components == 1)
+            && (self.approximation_high == 0
+                || self.approximation_high == self.approximation_low + 1)
+            && self.approximation_low <= 13
+    }
+}
+
+/// The scan script of libjpeg, which sends the low bits of the coefficients last.
+fn default_scan_script(num_components: usize) -> Vec<ProgressiveScan> {
+    let scan = ProgressiveScan::new;
+    if num_components == 1 {
+        vec![
+            scan(None, 0, 0, 0, 1),
+            scan(None, 1, 5, 0, 2),
+            scan(None, 6, 63, 0, 2),
+            scan(None, 1, 63, 2, 1),
+            scan(None, 0, 0, 1, 0),
+            scan(None, 1, 63, 1, 0),
+        ]
+    } else {
+        vec![
+            scan(None, 0, 0, 0, 1),
+            scan(Some(0), 1, 5, 0, 2),
+            scan(Some(2), 1, 63, 0, 1),
+            scan(Some(1), 1, 63, 0, 1),
+            scan(Some(0), 6, 63, 0, 2),
+            scan(Some(0), 1, 63, 2, 1),
+            scan(None, 0, 0, 1, 0),
+            scan(Some(2), 1, 63, 1, 0),
+            scan(Some(1), 1, 63, 1, 0),
+            scan(Some(0), 1, 63, 1, 0),
+        ]
+    }
+}
+
 /// The representation of a JPEG encoder
 pub struct JpegEncoder<'a, W: 'a> {
     writer: BitWriter<'a, W>,
@@ -333,6 +576,13 @@ pub struct JpegEncoder<'a, W: 'a> {
     chroma_actable: Box<[(u8, u16); 256]>,
 
     pixel_density: PixelDensity,
+
+    progressive: bool,
+    scan_script: Option<Vec<ProgressiveScan>>,
+
+    subsampling: Subsampling,
+
+    metadata: ImageMetadata,
 }
 
 /// JPEG Encoder
@@ -421,6 +671,13 @@ impl<'a, W: Write> JpegEncoder<'a, W> {
             chroma_actable: ca,
 
             pixel_density: PixelDensity::default(),
+
+            progressive: false,
+            scan_script: None,
+
+            subsampling: Subsampling::S444,
+
+            metadata: ImageMetadata::default(),
         }
     }
 
@@ -431,11 +688,47 @@ impl<'a, W: Write> JpegEncoder<'a, W> {
         self.pixel_density = pixel_density;
     }
 
+    /// Configure whether the image is encoded as a progressive JPEG.
+    ///
+    /// Progressive images are displayed at increasing quality while they are loading, which is
+    /// useful for images on the web, and are often slightly smaller. They use the scan script of
+    /// libjpeg unless one is set with `set_scan_script`. By default baseline images are written.
+    pub fn set_progressive(&mut self, progressive: bool) {
+        self.progressive = progressive;
+    }
+
+    /// Encode a progressive JPEG with the given scans.
+    ///
+    /// The scans are written in order, a valid script sends every coefficient of every component
+    /// down to the last bit. Encoding returns an error if a scan is invalid for the color type of
+    /// the image, for example if it refers to a chroma component of a grayscale image.
+    pub fn set_scan_script(&mut self, scans: &[ProgressiveScan]) {
+        self.progressive = true;
+        self.scan_script = Some(scans.to_vec());
+    }
+
+    /// Set the resolution at which the chroma of color images is stored.
+    ///
+    /// Subsampling the chroma makes images smaller, but blurs edges between colors. Grayscale
+    /// images are not affected. By default the chroma is stored at full resolution.
+    pub fn set_subsampling(&mut self, subsampling: Subsampling) {
+        self.subsampling = subsampling;
+    }
+
+    /// Embed metadata in the encoded image.
+    ///
+    /// The EXIF metadata and the XMP packet are written to APP1 segments, the ICC profile to APP2
+    /// segments. Encoding fails if the EXIF metadata or the XMP packet do not fit into a single
+    /// segment of 64 KiB.
+    pub fn set_metadata(&mut self, metadata: ImageMetadata) {
+        self.metadata = metadata;
+    }
+
     /// Encodes the image stored in the raw byte buffer ```image```
     /// that has dimensions ```width``` and ```height```
     /// and ```ColorType``` ```c```
     ///
-    /// The Image in encoded with subsampling ratio 4:2:2
+    /// The chroma is subsampled as set with `set_subsampling`.
     pub fn encode(
         &mut self,
         image: &[u8],
@@ -485,9 +778,10 @@ impl<'a, W: Write> JpegEncoder<'a, W> {
     /// same time such that it may be computed on the fly, which is why this method exists on this
     /// encoder but not on others. Instead the encoder will iterate over 8-by-8 blocks of pixels at
     /// a time, inspecting each pixel exactly once. You can rely on this behaviour when calling
-    /// this method.
+    /// this method. With chroma subsampling the blocks of pixels are 16 pixels wide, and for
+    /// 4:2:0 also 16 pixels high.
     ///
-    /// The Image in encoded with subsampling ratio 4:2:2
+    /// The chroma is subsampled as set with `set_subsampling`.
     pub fn encode_image<I: GenericImageView>(
         &mut self,
         image: &I,
@@ -495,6 +789,37 @@ impl<'a, W: Write> JpegEncoder<'a, W> {
         let n = I::Pixel::CHANNEL_COUNT;
         let num_components = if n == 1 || n == 2 { 1 } else { 3 };
 
+        // The sampling factors of a single component have no effect.
+        let (h, v) = if num_components == 1 {
+            (1, 1)
+        } else {
+            self.subsampling.luma_factors()
+        };
+        self.components[0].h = h;
+        self.components[0].v = v;
+
+        let scans = if self.progressive {
+            let scans = match self.scan_script {
+                Some(ref scans) => scans.clone(),
+                None => default_scan_script(num_components),
+            };
+            if let Some(scan) = scans.iter().find(|scan| !scan.is_valid(num_components)) {
+                return Err(ImageError::Parameter(ParameterError::from_kind(
+                    ParameterErrorKind::Generic(format!("invalid progressive JPEG scan {:?}", scan)),
+                )));
+            }
+            if scans.is_empty() {
+                return Err(ImageError::Parameter(ParameterError::from_kind(
+                    ParameterErrorKind::Generic("empty progressive JPEG scan script".to_owned()),
+                )));
+            }
+            Some(scans)
+        } else {
+            None
+        };
+
+        let metadata_segments = build_metadata_segments(&self.metadata)?;
+
         self.writer.write_marker(SOI)?;
 
         let mut buf = Vec::new();
@@ -502,6 +827,10 @@ impl<'a, W: Write> JpegEncoder<'a, W> {
         build_jfif_header(&mut buf, self.pixel_density);
         self.writer.write_segment(APP0, &buf)?;
 
+        for (marker, segment) in &metadata_segments {
+            self.writer.write_segment(*marker, segment)?;
+        }
+
         build_frame_header(
             &mut buf,
             8,
@@ -519,7 +848,7 @@ impl<'a, W: Write> JpegEncoder<'a, W> {
             })?,
             &self.components[..num_components],
         );
-        self.writer.write_segment(SOF0, &buf)?;
+        self.writer.write_segment(if scans.is_some() { SOF2 } else { SOF0 }, &buf)?;
 
         assert_eq!(self.tables.len(), 2);
         let numtables = if num_components == 1 { 1 } else { 2 };
@@ -567,6 +896,12 @@ impl<'a, W: Write> JpegEncoder<'a, W> {
             self.writer.write_segment(DHT, &buf)?;
         }
 
+        if let Some(scans) = scans {
+            self.encode_progressive(image, num_components, &scans)?;
+            self.writer.write_marker(EOI)?;
+            return Ok(());
+        }
+
         build_scan_header(&mut buf, &self.components[..num_components]);
         self.writer.write_segment(SOS, &buf)?;
 
@@ -582,6 +917,128 @@ impl<'a, W: Write> JpegEncoder<'a, W> {
         Ok(())
     }
 
+    fn encode_progressive<I: GenericImageView>(
+        &mut self,
+        image: &I,
+        num_components: usize,
+        scans: &[ProgressiveScan],
+    ) -> io::Result<()> {
+        let (h, v) = (u32::from(self.components[0].h), u32::from(self.components[0].v));
+        let mcus_x = (image.width() + 8 * h - 1) / (8 * h);
+        let mcus_y = (image.height() + 8 * v - 1) / (8 * v);
+        let sampling: Vec<(u32, u32)> = self.components[..num_components]
+            .iter()
+            .map(|c| (u32::from(c.h), u32::from(c.v)))
+            .collect();
+
+        // Every scan goes over the whole image, so all blocks are transformed first. The blocks
+        // of each component are stored row by row, padded to whole MCUs.
+        let mut blocks: Vec<Vec<[i32; 64]>> = sampling
+            .iter()
+            .map(|&(ch, cv)| vec![[0i32; 64]; (mcus_x * ch * mcus_y * cv) as usize])
+            .collect();
+        let mut yblocks = [[0u8; 64]; 4];
+        let mut cb_block = [0u8; 64];
+        let mut cr_block = [0u8; 64];
+
+        for my in 0..mcus_y {
+            for mx in 0..mcus_x {
+                if num_components == 1 {
+                    copy_blocks_gray(image, mx * 8, my * 8, &mut yblocks[0]);
+                } else {
+                    let (x, y) = (mx * 8 * h, my * 8 * v);
+                    copy_blocks_ycbcr(image, x, y, h, v, &mut yblocks, &mut cb_block, &mut cr_block);
+                    let index = (my * mcus_x + mx) as usize;
+                    transform::fdct(&cb_block, &mut blocks[1][index]);
+                    transform::fdct(&cr_block, &mut blocks[2][index]);
+                    quantize(&mut blocks[1][index], &self.tables[1]);
+                    quantize(&mut blocks[2][index], &self.tables[1]);
+                }
+                for by in 0..v {
+                    for bx in 0..h {
+                        let index = ((my * v + by) * mcus_x * h + mx * h + bx) as usize;
+                        transform::fdct(&yblocks[(by * h + bx) as usize], &mut blocks[0][index]);
+                        quantize(&mut blocks[0][index], &self.tables[0]);
+                    }
+                }
+            }
+        }
+
+        let mut buf = Vec::new();
+        for scan in scans {
+            let components: Vec<usize> = match scan.component {
+                Some(component) => vec![usize::from(component)],
+                None => (0..num_components).collect(),
+            };
+            let headers: Vec<Component> = components.iter().map(|&c| self.components[c]).collect();
+            build_progressive_scan_header(&mut buf, &headers, scan);
+            self.writer.write_segment(SOS, &buf)?;
+
+            let mut dc_preds = vec![0; components.len()];
+            if components.len() > 1 {
+                // Interleaved scans go over the MCUs, like baseline images.
+                for my in 0..mcus_y {
+                    for mx in 0..mcus_x {
+                        for (dcprev, &c) in dc_preds.iter_mut().zip(&components) {
+                            let (ch, cv) = sampling[c];
+                            for by in 0..cv {
+                                for bx in 0..ch {
+                                    let index = (my * cv + by) * mcus_x * ch + mx * ch + bx;
+                                    let block = &blocks[c][index as usize];
+                                    self.write_progressive_block(block, dcprev, c, scan)?;
+                                }
+                            }
+                        }
+                    }
+                }
+            } else {
+                // Scans of a single component only contain the blocks covering its samples.
+                let c = components[0];
+                let (ch, cv) = sampling[c];
+                let width = (image.width() * ch + h - 1) / h;
+                let height = (image.height() * cv + v - 1) / v;
+                for by in 0..(height + 7) / 8 {
+                    for bx in 0..(width + 7) / 8 {
+                        let block = &blocks[c][(by * mcus_x * ch + bx) as usize];
+                        self.write_progressive_block(block, &mut dc_preds[0], c, scan)?;
+                    }
+                }
+            }
+
+            self.writer.pad_byte()?;
+        }
+
+        Ok(())
+    }
+
+    /// Write the part of a block of component `c` that is contained in a progressive scan.
+    fn write_progressive_block(
+        &mut self,
+        block: &[i32; 64],
+        dcprev: &mut i32,
+        c: usize,
+        scan: &ProgressiveScan,
+    ) -> io::Result<()> {
+        let (dctable, actable) = if c == 0 {
+            (&*self.luma_dctable, &*self.luma_actable)
+        } else {
+            (&*self.chroma_dctable, &*self.chroma_actable)
+        };
+
+        let (ss, se) = (scan.spectral_start, scan.spectral_end);
+        let (ah, al) = (scan.approximation_high, scan.approximation_low);
+        if ss == 0 && ah == 0 {
+            *dcprev = self.writer.write_dc_first(block, *dcprev, al, dctable)?;
+        } else if ss == 0 {
+            self.writer.write_bits(((block[0] >> al) & 1) as u16, 1)?;
+        } else if ah == 0 {
+            self.writer.write_ac_first(block, ss, se, al, actable)?;
+        } else {
+            self.writer.write_ac_refine(block, ss, se, al, actable)?;
+        }
+        Ok(())
+    }
+
     fn encode_gray<I: GenericImageView>(
         &mut self,
         image: &I,
@@ -598,10 +1055,7 @@ impl<'a, W: Write> JpegEncoder<'a, W> {
                 // Coeffs are scaled by 8
                 transform::fdct(&yblock, &mut dct_yblock);
 
-                // Quantization
-                for (i, dct) in dct_yblock.iter_mut().enumerate() {
-                    *dct = ((*dct / 8) as f32 / f32::from(self.tables[0][i])).round() as i32;
-                }
+                quantize(&mut dct_yblock, &self.tables[0]);
 
                 let la = &*self.luma_actable;
                 let ld = &*self.luma_dctable;
@@ -625,46 +1079,46 @@ impl<'a, W: Write> JpegEncoder<'a, W> {
         let mut dct_cb_block = [0i32; 64];
         let mut dct_cr_block = [0i32; 64];
 
-        let mut yblock = [0u8; 64];
+        let mut yblocks = [[0u8; 64]; 4];
         let mut cb_block = [0u8; 64];
         let mut cr_block = [0u8; 64];
 
-        for y in range_step(0, image.height(), 8) {
-            for x in range_step(0, image.width(), 8) {
+        // The luma blocks of a MCU are followed by one block of each chroma component.
+        let (h, v) = (u32::from(self.components[0].h), u32::from(self.components[0].v));
+
+        for y in range_step(0, image.height(), 8 * v) {
+            for x in range_step(0, image.width(), 8 * h) {
                 // RGB -> YCbCr
                 copy_blocks_ycbcr(
                     image,
                     x,
                     y,
-                    &mut yblock,
+                    h,
+                    v,
+                    &mut yblocks,
                     &mut cb_block,
                     &mut cr_block,
                 );
 
-                // Level shift and fdct
-                // Coeffs are scaled by 8
-                transform::fdct(&yblock, &mut dct_yblock);
-                transform::fdct(&cb_block, &mut dct_cb_block);
-                transform::fdct(&cr_block, &mut dct_cr_block);
-
-                // Quantization
-                for i in 0usize..64 {
-                    dct_yblock[i] =
-                        ((dct_yblock[i] / 8) as f32 / f32::from(self.tables[0][i])).round() as i32;
-                    dct_cb_block[i] = ((dct_cb_block[i] / 8) as f32
-                        / f32::from(self.tables[1][i]))
-                        .round() as i32;
-                    dct_cr_block[i] = ((dct_cr_block[i] / 8) as f32
-                        / f32::from(self.tables[1][i]))
-                        .round() as i32;
-                }
-
                 let la = &*self.luma_actable;
                 let ld = &*self.luma_dctable;
                 let cd = &*self.chroma_dctable;
                 let ca = &*self.chroma_actable;
 
-                y_dcprev = self.writer.write_block(&dct_yblock, y_dcprev, ld, la)?;
+                for yblock in &yblocks[..(h * v) as usize] {
+                    // Level shift and fdct
+                    // Coeffs are scaled by 8
+                    transform::fdct(yblock, &mut dct_yblock);
+                    quantize(&mut dct_yblock, &self.tables[0]);
+                    y_dcprev = self.writer.write_block(&dct_yblock, y_dcprev, ld, la)?;
+                }
+
+                transform::fdct(&cb_block, &mut dct_cb_block);
+                transform::fdct(&cr_block, &mut dct_cr_block);
+
+                quantize(&mut dct_cb_block, &self.tables[1]);
+                quantize(&mut dct_cr_block, &self.tables[1]);
+
                 cb_dcprev = self.writer.write_block(&dct_cb_block, cb_dcprev, cd, ca)?;
                 cr_dcprev = self.writer.write_block(&dct_cr_block, cr_dcprev, cd, ca)?;
             }
@@ -700,6 +1154,49 @@ fn build_jfif_header(m: &mut Vec<u8>, density: PixelDensity) {
     m.extend_from_slice(&[0, 0]);
 }
 
+/// Build the application segments storing `metadata`, as pairs of marker and payload.
+fn build_metadata_segments(metadata: &ImageMetadata) -> ImageResult<Vec<(u8, Vec<u8>)>> {
+    const ICC_SIGNATURE: &[u8] = b"ICC_PROFILE\0";
+    let too_large = |what: &str| {
+        ImageError::Parameter(ParameterError::from_kind(ParameterErrorKind::Generic(format!(
+            "the {} does not fit into a JPEG segment",
+            what
+        ))))
+    };
+
+    let mut segments = Vec::new();
+    if let Some(exif) = &metadata.exif {
+        if exif.len() + 6 > MAX_SEGMENT_DATA {
+            return Err(too_large("EXIF metadata"));
+        }
+        let mut segment = b"Exif\0\0".to_vec();
+        segment.extend_from_slice(exif);
+        segments.push((APP1, segment));
+    }
+    if let Some(xmp) = &metadata.xmp {
+        if xmp.len() + JPEG_XMP_SIGNATURE.len() > MAX_SEGMENT_DATA {
+            return Err(too_large("XMP packet"));
+        }
+        let mut segment = JPEG_XMP_SIGNATURE.to_vec();
+        segment.extend_from_slice(xmp);
+        segments.push((APP1, segment));
+    }
+    if let Some(profile) = &metadata.icc_profile {
+        // The profile is split over numbered segments, each preceded by its number and the count.
+        let chunks: Vec<&[u8]> = profile.chunks(MAX_SEGMENT_DATA - ICC_SIGNATURE.len() - 2).collect();
+        if chunks.len() > 255 {
+            return Err(too_large("ICC profile"));
+        }
+        for (index, chunk) in chunks.iter().enumerate() {
+            let mut segment = ICC_SIGNATURE.to_vec();
+            segment.extend_from_slice(&[index as u8 + 1, chunks.len() as u8]);
+            segment.extend_from_slice(chunk);
+            segments.push((APP2, segment));
+        }
+    }
+    Ok(segments)
+}
+
 fn build_frame_header(
     m: &mut Vec<u8>,
     precision: u8,
@@ -734,6 +1231,30 @@ fn build_scan_header(m: &mut Vec<u8>, components: &[Component]) {
     m.extend_from_slice(&[0, 63, 0]);
 }
 
+fn build_progressive_scan_header(m: &mut Vec<u8>, components: &[Component], scan: &ProgressiveScan) {
+    m.clear();
+
+    m.push(components.len() as u8);
+
+    for &comp in components.iter() {
+        // Only the table used by the scan is named, as done by libjpeg.
+        let tables = if scan.spectral_start != 0 {
+            comp.ac_table
+        } else if scan.approximation_high == 0 {
+            comp.dc_table << 4
+        } else {
+            0
+        };
+        m.extend_from_slice(&[comp.id, tables]);
+    }
+
+    m.extend_from_slice(&[
+        scan.spectral_start,
+        scan.spectral_end,
+        (scan.approximation_high << 4) | scan.approximation_low,
+    ]);
+}
+
 fn build_huffman_segment(
     m: &mut Vec<u8>,
     class: u8,
@@ -771,6 +1292,13 @@ fn build_quantization_segment(m: &mut Vec<u8>, precision: u8, identifier: u8, qt
     }
 }
 
+/// Quantize the coefficients of a block, which are scaled by 8 by the fdct.
+fn quantize(coefficients: &mut [i32; 64], table: &[u8; 64]) {
+    for (dct, &q) in coefficients.iter_mut().zip(table.iter()) {
+        *dct = ((*dct / 8) as f32 / f32::from(q)).round() as i32;
+    }
+}
+
 fn encode_coefficient(coefficient: i32) -> (u8, u16) {
     let mut magnitude = coefficient.abs() as u16;
     let mut num_bits = 0u8;
@@ -823,24 +1351,36 @@ fn pixel_at_or_near<I: GenericImageView>(source: &I, x: u32, y: u32) -> I::Pixel
     }
 }
 
+/// Convert a MCU of `h` by `v` luma blocks, the chroma is averaged over `h` by `v` pixels.
 fn copy_blocks_ycbcr<I: GenericImageView>(
     source: &I,
     x0: u32,
     y0: u32,
-    yb: &mut [u8; 64],
+    h: u32,
+    v: u32,
+    yb: &mut [[u8; 64]; 4],
     cbb: &mut [u8; 64],
     crb: &mut [u8; 64],
 ) {
-    for y in 0..8 {
-        for x in 0..8 {
+    let mut cb_sums = [0u32; 64];
+    let mut cr_sums = [0u32; 64];
+
+    for y in 0..8 * v {
+        for x in 0..8 * h {
             let pixel = pixel_at_or_near(source, x + x0, y + y0);
             let (yc, cb, cr) = rgb_to_ycbcr(pixel);
 
-            yb[(y * 8 + x) as usize] = yc;
-            cbb[(y * 8 + x) as usize] = cb;
-            crb[(y * 8 + x) as usize] = cr;
+            yb[(y / 8 * h + x / 8) as usize][(y % 8 * 8 + x % 8) as usize] = yc;
+            cb_sums[(y / v * 8 + x / h) as usize] += u32::from(cb);
+            cr_sums[(y / v * 8 + x / h) as usize] += u32::from(cr);
         }
     }
+
+    let count = h * v;
+    for i in 0..64 {
+        cbb[i] = ((cb_sums[i] + count / 2) / count) as u8;
+        crb[i] = ((cr_sums[i] + count / 2) / count) as u8;
+    }
 }
 
 fn copy_blocks_gray<I: GenericImageView>(
@@ -868,7 +1408,7 @@ mod tests {
     #[cfg(feature = "benchmarks")]
     use test::{Bencher};
 
-    use crate::{Bgra, ImageBuffer, ImageEncoder, ImageError};
+    use crate::{Bgra, ImageBuffer, ImageEncoder, ImageError, Luma, Rgb};
     use crate::color::ColorType;
     use crate::error::ParameterErrorKind::DimensionMismatch;
     use crate::image::ImageDecoder;
@@ -884,8 +1424,10 @@ mod tests {
         JpegEncoder,
         LUMADESTINATION,
         PixelDensity,
+        ProgressiveScan,
         STD_LUMA_DC_CODE_LENGTHS,
         STD_LUMA_DC_VALUES,
+        Subsampling,
     };
     use super::super::JpegDecoder;
 
@@ -899,6 +1441,35 @@ mod tests {
         decoded
     }
 
+    #[test]
+    fn metadata_roundtrip() {
+        use crate::metadata::ImageMetadata;
+
+        let metadata = ImageMetadata {
+            exif: Some(b"II*\0\x08\0\0\0\0\0\0\0\0\0".to_vec()),
+            xmp: Some(b"<x:xmpmeta xmlns:x='adobe:ns:meta/'/>".to_vec()),
+            icc_profile: Some((0..70_000).map(|i| (i % 251) as u8).collect()),
+        };
+        let mut encoded = Vec::new();
+        let mut encoder = JpegEncoder::new(&mut encoded);
+        encoder.set_metadata(metadata.clone());
+        encoder.encode(&[255, 0, 0], 1, 1, ColorType::Rgb8).unwrap();
+
+        let mut decoder = JpegDecoder::new(Cursor::new(&encoded)).unwrap();
+        assert_eq!(decoder.metadata(), metadata);
+    }
+
+    #[test]
+    fn metadata_too_large() {
+        use crate::metadata::ImageMetadata;
+
+        let mut encoded = Vec::new();
+        let mut encoder = JpegEncoder::new(&mut encoded);
+        encoder.set_metadata(ImageMetadata { exif: Some(vec![0; 70_000]), ..Default::default() });
+        assert!(encoder.encode(&[0], 1, 1, ColorType::L8).is_err());
+        assert!(encoded.is_empty());
+    }
+
     #[test]
     fn roundtrip_sanity_check() {
         // create a 1x1 8-bit image buffer containing a single red pixel
@@ -952,6 +1523,132 @@ mod tests {
         }
     }
 
+    #[test]
+    fn progressive_roundtrip() {
+        let img = ImageBuffer::from_fn(37, 21, |x, y| {
+            Rgb([(x * 7 + y * 3) as u8, (x * y % 256) as u8, if (x / 5 + y / 4) % 2 == 0 { 250 } else { 10 }])
+        });
+        let encode = |progressive: bool, scans: Option<&[ProgressiveScan]>| {
+            let mut encoded = Vec::new();
+            let mut encoder = JpegEncoder::new_with_quality(&mut encoded, 90);
+            encoder.set_progressive(progressive);
+            if let Some(scans) = scans {
+                encoder.set_scan_script(scans);
+            }
+            encoder.encode_image(&img).unwrap();
+            encoded
+        };
+
+        // All coefficients are sent in full, so the image decodes exactly like the baseline one.
+        let baseline = decode(&encode(false, None));
+        assert_eq!(decode(&encode(true, None)), baseline);
+
+        let scan = |component, spectral_start, spectral_end, approximation_high, approximation_low| {
+            ProgressiveScan { component, spectral_start, spectral_end, approximation_high, approximation_low }
+        };
+        let spectral_selection = [
+            scan(None, 0, 0, 0, 0),
+            scan(Some(0), 1, 63, 0, 0),
+            scan(Some(1), 1, 9, 0, 0),
+            scan(Some(1), 10, 63, 0, 0),
+            scan(Some(2), 1, 63, 0, 0),
+        ];
+        assert_eq!(decode(&encode(true, Some(&spectral_selection))), baseline);
+    }
+
+    #[test]
+    fn subsampling_roundtrip() {
+        let img = ImageBuffer::from_fn(37, 21, |x, y| {
+            Rgb([(x * 7 + y * 3) as u8, (x * y % 256) as u8, if (x / 5 + y / 4) % 2 == 0 { 250 } else { 10 }])
+        });
+        let encode = |subsampling: Subsampling, progressive: bool| {
+            let mut encoded = Vec::new();
+            let mut encoder = JpegEncoder::new_with_quality(&mut encoded, 90);
+            encoder.set_subsampling(subsampling);
+            encoder.set_progressive(progressive);
+            encoder.encode_image(&img).unwrap();
+            encoded
+        };
+
+        let full = encode(Subsampling::S444, false);
+        for &subsampling in &[Subsampling::S422, Subsampling::S420] {
+            let baseline = encode(subsampling, false);
+            assert!(baseline.len() < full.len(), "{:?}", subsampling);
+            assert_eq!(decode(&baseline).len(), decode(&full).len());
+            // Progressive scans of single components skip the blocks that only pad the MCUs.
+            assert_eq!(decode(&encode(subsampling, true)), decode(&baseline));
+        }
+
+        // A uniform color is not changed by subsampling.
+        let img = ImageBuffer::from_pixel(19, 13, Rgb([200u8, 40, 90]));
+        let mut encoded = Vec::new();
+        let mut encoder = JpegEncoder::new_with_quality(&mut encoded, 100);
+        encoder.set_subsampling(Subsampling::S420);
+        encoder.encode_image(&img).unwrap();
+        for (&decoded, &original) in decode(&encoded).iter().zip(img.as_raw()) {
+            assert!((i32::from(decoded) - i32::from(original)).abs() <= 2);
+        }
+    }
+
+    #[test]
+    fn subsampling_edge_cases() {
+        fn encode<I: crate::GenericImageView>(img: &I, subsampling: Subsampling) -> Vec<u8>
+        where
+            I::Pixel: 'static,
+        {
+            let mut encoded = Vec::new();
+            let mut encoder = JpegEncoder::new_with_quality(&mut encoded, 90);
+            encoder.set_subsampling(subsampling);
+            encoder.encode_image(img).unwrap();
+            encoded
+        }
+
+        // The sampling factors of the luma component in the frame header.
+        fn luma_sampling(encoded: &[u8]) -> u8 {
+            let frame = encoded.windows(2).position(|marker| marker == [0xFF, 0xC0]).unwrap();
+            encoded[frame + 11]
+        }
+
+        // Images smaller than a single MCU.
+        for &(width, height) in &[(1, 1), (17, 1), (1, 9)] {
+            let img = ImageBuffer::from_pixel(width, height, Rgb([10u8, 200, 30]));
+            for &(subsampling, sampling) in
+                &[(Subsampling::S444, 0x11), (Subsampling::S422, 0x21), (Subsampling::S420, 0x22)]
+            {
+                let encoded = encode(&img, subsampling);
+                assert_eq!(luma_sampling(&encoded), sampling);
+                let decoder = JpegDecoder::new(Cursor::new(&encoded)).unwrap();
+                assert_eq!(decoder.dimensions(), (width, height));
+                assert_eq!(decode(&encoded).len(), 3 * (width * height) as usize);
+            }
+        }
+
+        // Grayscale images have no chroma to subsample.
+        let img = ImageBuffer::from_fn(13, 7, |x, y| Luma([(x * 19 + y * 7) as u8]));
+        let full = encode(&img, Subsampling::S444);
+        assert_eq!(luma_sampling(&full), 0x11);
+        assert_eq!(encode(&img, Subsampling::S420), full);
+    }
+
+    #[test]
+    fn progressive_invalid_scans() {
+        let img: ImageBuffer<Luma<u8>, _> = ImageBuffer::new(8, 8);
+        let invalid = [
+            // A chroma component of a grayscale image.
+            ProgressiveScan { component: Some(1), spectral_start: 0, spectral_end: 0, approximation_high: 0, approximation_low: 0 },
+            // DC and AC coefficients in one scan.
+            ProgressiveScan { component: Some(0), spectral_start: 0, spectral_end: 5, approximation_high: 0, approximation_low: 0 },
+            // Refining by more than one bit.
+            ProgressiveScan { component: None, spectral_start: 1, spectral_end: 63, approximation_high: 3, approximation_low: 1 },
+        ];
+        for scan in &invalid {
+            let mut encoded = Vec::new();
+            let mut encoder = JpegEncoder::new(&mut encoded);
+            encoder.set_scan_script(&[*scan]);
+            assert!(encoder.encode_image(&img).is_err(), "{:?}", scan);
+        }
+    }
+
     #[test]
     fn jfif_header_density_check() {
         let mut buffer = Vec::new();
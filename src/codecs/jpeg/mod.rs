@@ -1,7 +1,7 @@
 //! Decoding and Encoding of JPEG Images
 //!
 //! JPEG (Joint Photographic Experts Group) is an image format that supports lossy compression.
-//! This module implements the Baseline JPEG standard.
+//! This module implements the Baseline and Progressive JPEG standard.
 //!
 //! # Related Links
 //! * <http://www.w3.org/Graphics/JPEG/itu-t81.pdf> - The JPEG specification
@@ -9,9 +9,10 @@
 
 pub use self::decoder::JpegDecoder;
 #[allow(deprecated)] // TODO: when `JPEGEncoder` is removed, remove this tag
-pub use self::encoder::{JpegEncoder, PixelDensity, PixelDensityUnit, JPEGEncoder};
+pub use self::encoder::{JpegEncoder, PixelDensity, PixelDensityUnit, ProgressiveScan, Subsampling, JPEGEncoder};
 
 mod decoder;
 mod encoder;
 mod entropy;
+mod simd;
 mod transform;
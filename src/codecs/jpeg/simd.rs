@@ -0,0 +1,155 @@
+//! Vectorized color conversions for the JPEG decoder.
+//!
+//! Every function converts a prefix of its input and returns the number of pixels it handled.
+//! The caller converts the remaining pixels with the scalar implementation, whose results the
+//! vectorized paths reproduce exactly.
+//!
+//! On x86 and x86_64 the widest instruction set available is selected at runtime. Other targets
+//! currently convert everything with the scalar loop, NEON intrinsics are not available on the
+//! minimum supported compiler.
+
+/// Convert inverted CMYK to RGB, returning the number of converted pixels.
+#[allow(unused_variables)] // Unused on targets without a vectorized path.
+pub(super) fn cmyk_to_rgb(input: &[u8], output: &mut [u8]) -> usize {
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    {
+        if is_x86_feature_detected!("avx2") {
+            // Safety: the required target feature was detected above.
+            return unsafe { x86::cmyk_to_rgb_avx2(input, output) };
+        }
+        if is_x86_feature_detected!("sse2") {
+            // Safety: the required target feature was detected above.
+            return unsafe { x86::cmyk_to_rgb_sse2(input, output) };
+        }
+    }
+
+    0
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+mod x86 {
+    #[cfg(target_arch = "x86")]
+    use std::arch::x86::*;
+    #[cfg(target_arch = "x86_64")]
+    use std::arch::x86_64::*;
+
+    /// Copy the first three channels of each four channel pixel.
+    fn store_rgb(rgbx: &[u8], output: &mut [u8]) {
+        for (pixel, outp) in rgbx.chunks_exact(4).zip(output.chunks_exact_mut(3)) {
+            outp.copy_from_slice(&pixel[..3]);
+        }
+    }
+
+    /// Multiply each 16-bit channel by the last channel of its pixel and divide by 255.
+    ///
+    /// The division rounds down like the scalar one: for all products `p` of two bytes,
+    /// `p / 255 == ((p + 1) * 257) >> 16`.
+    #[inline]
+    #[target_feature(enable = "sse2")]
+    unsafe fn scale_by_black_sse2(cmyk: __m128i) -> __m128i {
+        let black = _mm_shufflehi_epi16(_mm_shufflelo_epi16(cmyk, 0xFF), 0xFF);
+        let product = _mm_mullo_epi16(cmyk, black);
+        _mm_mulhi_epu16(_mm_add_epi16(product, _mm_set1_epi16(1)), _mm_set1_epi16(257))
+    }
+
+    /// Convert four pixels at a time with SSE2.
+    #[target_feature(enable = "sse2")]
+    pub(super) unsafe fn cmyk_to_rgb_sse2(input: &[u8], output: &mut [u8]) -> usize {
+        let pixels = (input.len() / 4).min(output.len() / 3) / 4 * 4;
+        let zero = _mm_setzero_si128();
+        let max = _mm_set1_epi16(255);
+        let mut rgbx = [0u8; 16];
+
+        for i in (0..pixels).step_by(4) {
+            let cmyk = _mm_loadu_si128(input.as_ptr().add(4 * i) as *const __m128i);
+            let low = scale_by_black_sse2(_mm_sub_epi16(max, _mm_unpacklo_epi8(cmyk, zero)));
+            let high = scale_by_black_sse2(_mm_sub_epi16(max, _mm_unpackhi_epi8(cmyk, zero)));
+            _mm_storeu_si128(rgbx.as_mut_ptr() as *mut __m128i, _mm_packus_epi16(low, high));
+            store_rgb(&rgbx, &mut output[3 * i..3 * (i + 4)]);
+        }
+
+        pixels
+    }
+
+    /// See `scale_by_black_sse2`, the shuffles operate on each 128-bit lane separately.
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    unsafe fn scale_by_black_avx2(cmyk: __m256i) -> __m256i {
+        let black = _mm256_shufflehi_epi16(_mm256_shufflelo_epi16(cmyk, 0xFF), 0xFF);
+        let product = _mm256_mullo_epi16(cmyk, black);
+        _mm256_mulhi_epu16(
+            _mm256_add_epi16(product, _mm256_set1_epi16(1)),
+            _mm256_set1_epi16(257),
+        )
+    }
+
+    /// Convert eight pixels at a time with AVX2.
+    ///
+    /// Unpacking and packing both work per 128-bit lane, so pixels keep their order.
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn cmyk_to_rgb_avx2(input: &[u8], output: &mut [u8]) -> usize {
+        let pixels = (input.len() / 4).min(output.len() / 3) / 8 * 8;
+        let zero = _mm256_setzero_si256();
+        let max = _mm256_set1_epi16(255);
+        let mut rgbx = [0u8; 32];
+
+        for i in (0..pixels).step_by(8) {
+            let cmyk = _mm256_loadu_si256(input.as_ptr().add(4 * i) as *const __m256i);
+            let low = scale_by_black_avx2(_mm256_sub_epi16(max, _mm256_unpacklo_epi8(cmyk, zero)));
+            let high = scale_by_black_avx2(_mm256_sub_epi16(max, _mm256_unpackhi_epi8(cmyk, zero)));
+            _mm256_storeu_si256(rgbx.as_mut_ptr() as *mut __m256i, _mm256_packus_epi16(low, high));
+            store_rgb(&rgbx, &mut output[3 * i..3 * (i + 8)]);
+        }
+
+        pixels
+    }
+}
+
+#[cfg(all(test, any(target_arch = "x86", target_arch = "x86_64")))]
+mod tests {
+    use super::x86;
+
+    type Conversion = unsafe fn(&[u8], &mut [u8]) -> usize;
+
+    /// Check each instruction set that is available, not only the widest one.
+    fn conversions() -> Vec<(&'static str, Conversion)> {
+        let mut conversions: Vec<(_, Conversion)> = Vec::new();
+        if is_x86_feature_detected!("sse2") {
+            conversions.push(("sse2", x86::cmyk_to_rgb_sse2));
+        }
+        if is_x86_feature_detected!("avx2") {
+            conversions.push(("avx2", x86::cmyk_to_rgb_avx2));
+        }
+        conversions
+    }
+
+    #[test]
+    fn cmyk_to_rgb_prefixes() {
+        let input: Vec<u8> = (0..4 * 19 + 3).map(|i| (i * 37 % 256) as u8).collect();
+        let expected: Vec<u8> = input
+            .chunks_exact(4)
+            .flat_map(|cmyk| {
+                let k = 255 - u16::from(cmyk[3]);
+                let channel = move |c: u8| ((255 - u16::from(c)) * k / 255) as u8;
+                vec![channel(cmyk[0]), channel(cmyk[1]), channel(cmyk[2])]
+            })
+            .collect();
+
+        for (name, convert) in conversions() {
+            // Inputs shorter than a vector, including empty ones and partial pixels.
+            for len in 0..input.len() {
+                let mut output = vec![0; expected.len()];
+                let pixels = unsafe { convert(&input[..len], &mut output) };
+                assert!(pixels <= len / 4, "{}: {} pixels of {} bytes", name, pixels, len);
+                assert_eq!(&output[..3 * pixels], &expected[..3 * pixels], "{}", name);
+                assert!(output[3 * pixels..].iter().all(|&b| b == 0), "{}", name);
+            }
+
+            // The output limits the conversion as well.
+            let mut output = vec![0; 3 * 9 + 2];
+            let pixels = unsafe { convert(&input, &mut output) };
+            assert!(pixels <= 9, "{}: {} pixels", name, pixels);
+            assert_eq!(&output[..3 * pixels], &expected[..3 * pixels], "{}", name);
+        }
+    }
+}
@@ -0,0 +1,144 @@
+//! Decoding of JPEG XL images
+//!
+//! JPEG XL supports lossy and lossless compression of images with high bit depths and alpha.
+//! The codestream is decoded by the `jxl-oxide` crate, both bare codestreams and codestreams in
+//! the ISOBMFF based container are accepted.
+//!
+//! Only grayscale and RGB images, with or without alpha, are supported. Images with more than
+//! 8 bits per sample decode to 16-bit samples. Of animated images only the first frame is decoded.
+//!
+//! # Related Links
+//! * <https://jpeg.org/jpegxl/> - The JPEG XL overview.
+//!
+use std::convert::TryFrom;
+use std::error::Error;
+use std::io::{Cursor, Read};
+use std::marker::PhantomData;
+
+use crate::error::{DecodingError, UnsupportedError, UnsupportedErrorKind};
+use crate::utils::clamp;
+use crate::{ColorType, ImageDecoder, ImageError, ImageFormat, ImageResult};
+
+use jxl_oxide::{JxlImage, PixelFormat};
+
+fn error_map<E: Into<Box<dyn Error + Send + Sync>>>(err: E) -> ImageError {
+    ImageError::Decoding(DecodingError::new(ImageFormat::JpegXl.into(), err))
+}
+
+/// JPEG XL decoder.
+///
+/// Decodes the first frame of an image on creation.
+pub struct JxlDecoder<R> {
+    inner: PhantomData<R>,
+    dimensions: (u32, u32),
+    color_type: ColorType,
+    /// The samples in the layout of the color type.
+    data: Vec<u8>,
+}
+
+impl<R: Read> JxlDecoder<R> {
+    /// Create a new decoder that reads its input from `r`.
+    pub fn new(r: R) -> ImageResult<Self> {
+        let image = JxlImage::builder().read(r).map_err(error_map)?;
+
+        let high_bit_depth = image.image_header().metadata.bit_depth.bits_per_sample() > 8;
+        let color_type = match (image.pixel_format(), high_bit_depth) {
+            (PixelFormat::Gray, false) => ColorType::L8,
+            (PixelFormat::Gray, true) => ColorType::L16,
+            (PixelFormat::Graya, false) => ColorType::La8,
+            (PixelFormat::Graya, true) => ColorType::La16,
+            (PixelFormat::Rgb, false) => ColorType::Rgb8,
+            (PixelFormat::Rgb, true) => ColorType::Rgb16,
+            (PixelFormat::Rgba, false) => ColorType::Rgba8,
+            (PixelFormat::Rgba, true) => ColorType::Rgba16,
+            (format, _) => {
+                return Err(ImageError::Unsupported(UnsupportedError::from_format_and_kind(
+                    ImageFormat::JpegXl.into(),
+                    UnsupportedErrorKind::GenericFeature(format!("{:?} pixels", format)),
+                )))
+            }
+        };
+
+        let render = image.render_frame(0).map_err(error_map)?;
+        // The color channels are followed by the alpha channel and any other extra channels.
+        let framebuffer = render.image_all_channels();
+        let stride = framebuffer.channels();
+        let channels = usize::from(color_type.channel_count());
+        if stride < channels {
+            return Err(error_map("rendered image is missing channels"));
+        }
+
+        let width = u32::try_from(framebuffer.width()).map_err(error_map)?;
+        let height = u32::try_from(framebuffer.height()).map_err(error_map)?;
+        let mut data = Vec::with_capacity(
+            framebuffer.width() * framebuffer.height() * usize::from(color_type.bytes_per_pixel()),
+        );
+        for pixel in framebuffer.buf().chunks(stride) {
+            for &sample in &pixel[..channels] {
+                // Samples are nominally in the range 0.0 to 1.0.
+                let sample = clamp(sample, 0.0, 1.0);
+                if high_bit_depth {
+                    let sample = (sample * f32::from(u16::max_value())).round() as u16;
+                    data.extend_from_slice(&sample.to_ne_bytes());
+                } else {
+                    data.push((sample * f32::from(u8::max_value())).round() as u8);
+                }
+            }
+        }
+
+        Ok(JxlDecoder {
+            inner: PhantomData,
+            dimensions: (width, height),
+            color_type,
+            data,
+        })
+    }
+}
+
+impl<'a, R: 'a + Read> ImageDecoder<'a> for JxlDecoder<R> {
+    type Reader = Cursor<Vec<u8>>;
+
+    fn dimensions(&self) -> (u32, u32) {
+        self.dimensions
+    }
+
+    fn color_type(&self) -> ColorType {
+        self.color_type
+    }
+
+    fn into_reader(self) -> ImageResult<Self::Reader> {
+        Ok(Cursor::new(self.data))
+    }
+
+    fn read_image(self, buf: &mut [u8]) -> ImageResult<()> {
+        assert_eq!(u64::try_from(buf.len()), Ok(self.total_bytes()));
+        buf.copy_from_slice(&self.data);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::JxlDecoder;
+    use crate::{ImageError, ImageFormat};
+
+    #[test]
+    fn guess_format() {
+        assert_eq!(crate::guess_format(&[0xff, 0x0a, 0x00]).unwrap(), ImageFormat::JpegXl);
+        let container = b"\0\0\0\x0cJXL \r\n\x87\n\0\0\0\x14ftypjxl ";
+        assert_eq!(crate::guess_format(container).unwrap(), ImageFormat::JpegXl);
+    }
+
+    #[test]
+    fn invalid_input() {
+        for data in &[&b""[..], &[0xff, 0x0a][..], b"not a jxl image"] {
+            match JxlDecoder::new(*data) {
+                Err(ImageError::Decoding(err)) => {
+                    assert_eq!(err.format_hint(), ImageFormat::JpegXl.into())
+                }
+                Err(err) => panic!("unexpected error: {}", err),
+                Ok(_) => panic!("decoded {:?}", data),
+            }
+        }
+    }
+}
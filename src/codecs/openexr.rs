@@ -217,13 +217,14 @@ impl<'a, R: 'a + BufRead + Seek> ImageDecoder<'a> for OpenExrDecoder<R> {
 /// Write a raw byte buffer of pixels,
 /// returning an Error if the buffer is not aligned to `f32`
 /// or if it has an invalid length.
+/// The samples are stored as 16-bit half floats if `half_float` is set.
 ///
 /// Assumes the writer is buffered. In most cases,
 /// you should wrap your writer in a `BufWriter` for best performance.
 // private. access via `OpenExrEncoder`
 fn write_buffer(
     mut buffered_write: impl Write/* + Seek*/, unaligned_bytes: &[u8],
-    width: u32, height: u32, color_type: ColorType
+    width: u32, height: u32, color_type: ColorType, half_float: bool
 ) -> ImageResult<()>
 {
     let width = width as usize;
@@ -254,10 +255,45 @@ fn write_buffer(
     // bytes might be unaligned so we cannot cast the whole thing, instead lookup each f32 individually
     let lookup_f32 = move |f32_index: usize| {
         let f32_bytes_slice = &unaligned_bytes[f32_index * 4 .. (f32_index + 1) * 4];
-        f32::from_ne_bytes(f32_bytes_slice.try_into().expect("indexing error"))
+        f32::from_bits(u32::from_ne_bytes(f32_bytes_slice.try_into().expect("indexing error")))
     };
 
     match color_type {
+        ColorType::Rgb32F if half_float => {
+            Image
+                ::from_channels(
+                    (width, height),
+                    SpecificChannels::rgb(|pixel: Vec2<usize>| {
+                        let pixel_index = 3 * pixel.flat_index_for_size(Vec2(width, height));
+                        (
+                            f16::from_f32(lookup_f32(pixel_index)),
+                            f16::from_f32(lookup_f32(pixel_index+1)),
+                            f16::from_f32(lookup_f32(pixel_index+2))
+                        )
+                    })
+                )
+                .write()
+                .to_buffered(&mut seekable_write).map_err(to_image_err)?;
+        }
+
+        ColorType::Rgba32F if half_float => {
+            Image
+                ::from_channels(
+                    (width, height),
+                    SpecificChannels::rgba(|pixel: Vec2<usize>| {
+                        let pixel_index = 4 * pixel.flat_index_for_size(Vec2(width, height));
+                        (
+                            f16::from_f32(lookup_f32(pixel_index)),
+                            f16::from_f32(lookup_f32(pixel_index+1)),
+                            f16::from_f32(lookup_f32(pixel_index+2)),
+                            f16::from_f32(lookup_f32(pixel_index+3))
+                        )
+                    })
+                )
+                .write()
+                .to_buffered(&mut seekable_write).map_err(to_image_err)?;
+        }
+
         ColorType::Rgb32F => {
             exr::prelude::Image // TODO compression method zip??
                 ::from_channels(
@@ -304,13 +340,23 @@ fn write_buffer(
 // TODO is this struct and trait actually used anywhere?
 /// A thin wrapper that implements `ImageEncoder` for OpenEXR images. Will behave like `image::codecs::openexr::write_buffer`.
 #[derive(Debug)]
-pub struct OpenExrEncoder<W> (W);
+pub struct OpenExrEncoder<W> {
+    write: W,
+    half_float: bool,
+}
 
 impl<W> OpenExrEncoder<W> {
 
     /// Create an `ImageEncoder`. Does not write anything yet. Writing later will behave like `image::codecs::openexr::write_buffer`.
     // use constructor, not public field, for future backwards-compatibility
-    pub fn new(write: W) -> Self { Self(write) }
+    pub fn new(write: W) -> Self { Self { write, half_float: false } }
+
+    /// Store the channels as 16-bit half floats instead of 32-bit floats.
+    /// This halves the size of the pixel data, values are rounded to the nearest half float.
+    pub fn with_half_float(mut self, half_float: bool) -> Self {
+        self.half_float = half_float;
+        self
+    }
 }
 
 impl<W> ImageEncoder for OpenExrEncoder<W> where W: Write /*+ Seek*/ {
@@ -323,7 +369,7 @@ impl<W> ImageEncoder for OpenExrEncoder<W> where W: Write /*+ Seek*/ {
     /// Assumes the writer is buffered. In most cases,
     /// you should wrap your writer in a `BufWriter` for best performance.
     fn write_image(self, buf: &[u8], width: u32, height: u32, color_type: ColorType) -> ImageResult<()> {
-        write_buffer(self.0, buf, width, height, color_type)
+        write_buffer(self.write, buf, width, height, color_type, self.half_float)
     }
 }
 
@@ -353,7 +399,7 @@ mod test {
             write,
             bytemuck::cast_slice(image.as_raw().as_slice()),
             image.width(), image.height(),
-            ColorType::Rgb32F
+            ColorType::Rgb32F, false
         )
     }
 
@@ -365,7 +411,7 @@ mod test {
             write,
             bytemuck::cast_slice(image.as_raw().as_slice()),
             image.width(), image.height(),
-            ColorType::Rgba32F
+            ColorType::Rgba32F, false
         )
     }
 
@@ -464,6 +510,44 @@ mod test {
         debug_assert_eq!(generated_image, decoded_image);
     }
 
+    #[test]
+    fn roundtrip_half_float() {
+        // all of these values can be represented exactly as half floats
+        let mut next_random = vec![ 1.0, 0.0, -1.0, -3.125, 27.0, 0.5, 31.0 ].into_iter().cycle();
+        let mut next_random = move || next_random.next().unwrap();
+
+        let generated_image: Rgba32FImage = ImageBuffer::from_fn(9, 31, |_x, _y|{
+            Rgba([next_random(), next_random(), next_random(), next_random()])
+        });
+
+        let mut half_bytes = vec![];
+        OpenExrEncoder::new(Cursor::new(&mut half_bytes)).with_half_float(true)
+            .write_image(
+                bytemuck::cast_slice(generated_image.as_raw().as_slice()),
+                generated_image.width(), generated_image.height(),
+                ColorType::Rgba32F
+            ).unwrap();
+
+        let mut full_bytes = vec![];
+        write_rgba_image(Cursor::new(&mut full_bytes), &generated_image).unwrap();
+        assert!(half_bytes.len() < full_bytes.len());
+
+        let decoded_image = read_as_rgba_image(Cursor::new(half_bytes)).unwrap();
+        assert_eq!(generated_image, decoded_image);
+
+        let imprecise: Rgb32FImage = ImageBuffer::from_pixel(3, 2, Rgb([0.1, 1000.3, -2.7]));
+        let mut bytes = vec![];
+        OpenExrEncoder::new(Cursor::new(&mut bytes)).with_half_float(true)
+            .write_image(bytemuck::cast_slice(imprecise.as_raw().as_slice()), 3, 2, ColorType::Rgb32F)
+            .unwrap();
+        let decoded_image = read_as_rgb_image(Cursor::new(bytes)).unwrap();
+        for (expected, found) in imprecise.pixels().zip(decoded_image.pixels()) {
+            for (expected, found) in expected.0.iter().zip(found.0.iter()) {
+                assert!((expected - found).abs() <= expected.abs() / 1000.0, "expected {}, found {}", expected, found);
+            }
+        }
+    }
+
     #[test]
     fn compare_rgba_rgb() {
         let exr_path = BASE_PATH.iter().collect::<PathBuf>()
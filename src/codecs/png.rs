@@ -7,26 +7,31 @@
 //!
 
 use std::convert::TryFrom;
-use std::io::{self, Read, Write};
+use std::io::{self, Cursor, Read, Write};
+use std::ops::Deref;
 
 use num_rational::Ratio;
 use png::{BlendOp, DisposeOp};
 
-use crate::{DynamicImage, GenericImage, ImageBuffer, Luma, LumaA, RgbaImage, Rgb, Rgba};
+use crate::{DynamicImage, GenericImage, GenericImageView, GrayImage, ImageBuffer, Luma, LumaA, RgbaImage, Rgb, Rgba};
 use crate::animation::{Delay, Frame, Frames};
 use crate::color::{Blend, ColorType, ExtendedColorType};
 use crate::error::{
-    DecodingError, ImageError, ImageResult, LimitError, LimitErrorKind, ParameterError, ParameterErrorKind, UnsupportedError, UnsupportedErrorKind
+    DecodingError, EncodingError, ImageError, ImageResult, LimitError, LimitErrorKind, ParameterError, ParameterErrorKind, UnsupportedError, UnsupportedErrorKind
 };
-use crate::image::{AnimationDecoder, ImageDecoder, ImageEncoder, ImageFormat};
+use crate::image::{AnimationDecoder, DecodedRows, ImageDecoder, ImageEncoder, ImageFormat};
+use crate::metadata::{ImageMetadata, Resolution, ResolutionUnit};
 
 /// Png Reader
 ///
 /// This reader will try to read the png one row at a time,
 /// however for interlaced png files this is not possible and
 /// these are therefore read at once.
+///
+/// Like `read_image`, samples of 16-bit images are returned in native endianness.
 pub struct PngReader<R: Read> {
-    reader: png::Reader<R>,
+    reader: png::Reader<ChunkReader<R>>,
+    color_type: ColorType,
     buffer: Vec<u8>,
     index: usize,
 }
@@ -43,7 +48,7 @@ pub struct PngReader<R: Read> {
 pub type PNGReader<R> = PngReader<R>;
 
 impl<R: Read> PngReader<R> {
-    fn new(mut reader: png::Reader<R>) -> ImageResult<PngReader<R>> {
+    fn new(mut reader: png::Reader<ChunkReader<R>>, color_type: ColorType) -> ImageResult<PngReader<R>> {
         let len = reader.output_buffer_size();
         // Since interlaced images do not come in
         // scanline order it is almost impossible to
@@ -53,6 +58,7 @@ impl<R: Read> PngReader<R> {
         let buffer = if reader.info().interlaced {
             let mut buffer = vec![0; len];
             reader.next_frame(&mut buffer).map_err(ImageError::from_png)?;
+            to_native_endian(color_type, &mut buffer);
             buffer
         } else {
             Vec::new()
@@ -60,6 +66,7 @@ impl<R: Read> PngReader<R> {
 
         Ok(PngReader {
             reader,
+            color_type,
             buffer,
             index: 0,
         })
@@ -77,11 +84,13 @@ impl<R: Read> Read for PngReader<R> {
         while self.index >= self.buffer.len() {
             match self.reader.next_row()? {
                 Some(row) => {
-                    // Faster to copy directly to external buffer
-                    let readed  = buf.write(row).unwrap();
+                    let mut row = row.to_owned();
+                    to_native_endian(self.color_type, &mut row);
+                    let readed  = buf.write(&row).unwrap();
                     bytes += readed;
 
-                    self.buffer = (&row[readed..]).to_owned();
+                    row.drain(..readed);
+                    self.buffer = row;
                     self.index = 0;
                 }
                 None => return Ok(bytes)
@@ -103,7 +112,9 @@ impl<R: Read> Read for PngReader<R> {
         self.index = 0;
 
         while let Some(row) = self.reader.next_row()? {
+            let start = buf.len();
             buf.extend_from_slice(row);
+            to_native_endian(self.color_type, &mut buf[start..]);
             bytes += row.len();
         }
 
@@ -111,19 +122,38 @@ impl<R: Read> Read for PngReader<R> {
     }
 }
 
+/// The stream passed to the png decoder: the chunks in front of the image data which have already
+/// been read to collect the metadata, followed by the rest of the stream.
+type ChunkReader<R> = io::Chain<Cursor<Vec<u8>>, R>;
+
 /// PNG decoder
 pub struct PngDecoder<R: Read> {
     color_type: ColorType,
-    reader: png::Reader<R>,
+    reader: png::Reader<ChunkReader<R>>,
+    interlaced_rows: Option<DecodedRows>,
+    metadata: Metadata,
 }
 
+/// The metadata found in the chunks in front of the image data
+#[derive(Default)]
+struct Metadata {
+    text_chunks: Vec<TextChunk>,
+    icc_profile: Option<Vec<u8>>,
+    exif: Option<Vec<u8>>,
+    resolution: Option<Resolution>,
+}
+
+/// The keyword of the `iTXt` chunk holding the XMP packet
+const XMP_KEYWORD: &str = "XML:com.adobe.xmp";
+
 impl<R: Read> PngDecoder<R> {
     /// Creates a new decoder that decodes from the stream ```r```
-    pub fn new(r: R) -> ImageResult<PngDecoder<R>> {
+    pub fn new(mut r: R) -> ImageResult<PngDecoder<R>> {
         let limits = png::Limits {
             bytes: usize::max_value(),
         };
-        let mut decoder = png::Decoder::new_with_limits(r, limits);
+        let (header, metadata) = read_metadata_chunks(&mut r)?;
+        let mut decoder = png::Decoder::new_with_limits(Cursor::new(header).chain(r), limits);
         // By default the PNG decoder will scale 16 bpc to 8 bpc, so custom
         // transformations must be set. EXPAND preserves the default behavior
         // expanding bpc < 8 to 8 bpc.
@@ -171,7 +201,15 @@ impl<R: Read> PngDecoder<R> {
                 return Err(unsupported_color(ExtendedColorType::Unknown(bits as u8))),
         };
 
-        Ok(PngDecoder { color_type, reader })
+        Ok(PngDecoder { color_type, reader, interlaced_rows: None, metadata })
+    }
+
+    /// The textual metadata of the image, from its `tEXt`, `zTXt` and `iTXt` chunks.
+    ///
+    /// Only the chunks in front of the image data are returned, which is where encoders
+    /// customarily write them. Chunks that can not be decoded are skipped.
+    pub fn text_chunks(&self) -> &[TextChunk] {
+        &self.metadata.text_chunks
     }
 
     /// Turn this into an iterator over the animation frames.
@@ -199,6 +237,180 @@ impl<R: Read> PngDecoder<R> {
     }
 }
 
+/// Read the chunks in front of the image data, collecting the text chunks and the ICC profile.
+///
+/// Returns all bytes that were read, so they can be passed on to the png decoder. Anything
+/// malformed ends the search, the png decoder will report it.
+fn read_metadata_chunks<R: Read>(r: &mut R) -> io::Result<(Vec<u8>, Metadata)> {
+    const SIGNATURE: [u8; 8] = [137, 80, 78, 71, 13, 10, 26, 10];
+
+    let mut header = Vec::new();
+    let mut metadata = Metadata::default();
+    r.by_ref().take(8).read_to_end(&mut header)?;
+    if header[..] != SIGNATURE[..] {
+        return Ok((header, metadata));
+    }
+
+    loop {
+        let start = header.len();
+        if r.by_ref().take(8).read_to_end(&mut header)? < 8 {
+            break;
+        }
+        let length = u32::from_be_bytes([header[start], header[start + 1], header[start + 2], header[start + 3]]);
+        let kind = [header[start + 4], header[start + 5], header[start + 6], header[start + 7]];
+        if &kind == b"IDAT" {
+            break;
+        }
+
+        // chunk data followed by the crc, which the png decoder checks
+        let data_start = header.len();
+        let expected = u64::from(length) + 4;
+        if r.by_ref().take(expected).read_to_end(&mut header)? as u64 != expected {
+            break;
+        }
+        let data = &header[data_start..header.len() - 4];
+        if &kind == b"iCCP" {
+            metadata.icc_profile = decode_icc_profile(data);
+        } else if &kind == b"eXIf" {
+            metadata.exif = Some(data.to_vec());
+        } else if &kind == b"pHYs" {
+            metadata.resolution = decode_resolution(data);
+        } else if let Some(chunk) = TextChunk::decode(&kind, data) {
+            metadata.text_chunks.push(chunk);
+        }
+    }
+
+    Ok((header, metadata))
+}
+
+/// Decode the pixels per unit of a `pHYs` chunk.
+fn decode_resolution(data: &[u8]) -> Option<Resolution> {
+    if data.len() != 9 {
+        return None;
+    }
+    let unit = match data[8] {
+        0 => ResolutionUnit::AspectRatio,
+        1 => ResolutionUnit::Meters,
+        _ => return None,
+    };
+    Some(Resolution {
+        density: (
+            u32::from_be_bytes([data[0], data[1], data[2], data[3]]),
+            u32::from_be_bytes([data[4], data[5], data[6], data[7]]),
+        ),
+        unit,
+    })
+}
+
+/// Encode the data of a `pHYs` chunk, which stores pixels per meter.
+fn encode_resolution(resolution: Resolution) -> Vec<u8> {
+    let per_meter = |density: u32| -> u32 {
+        let factor = match resolution.unit {
+            ResolutionUnit::AspectRatio | ResolutionUnit::Meters => return density,
+            ResolutionUnit::Inches => 1.0 / 0.0254,
+            ResolutionUnit::Centimeters => 100.0,
+        };
+        (f64::from(density) * factor).round().min(f64::from(u32::max_value())) as u32
+    };
+    let mut data = Vec::with_capacity(9);
+    data.extend_from_slice(&per_meter(resolution.density.0).to_be_bytes());
+    data.extend_from_slice(&per_meter(resolution.density.1).to_be_bytes());
+    data.push(if resolution.unit == ResolutionUnit::AspectRatio { 0 } else { 1 });
+    data
+}
+
+/// Decode the profile of an `iCCP` chunk, which follows the name of the profile.
+fn decode_icc_profile(data: &[u8]) -> Option<Vec<u8>> {
+    let name_end = data.iter().position(|&b| b == 0)?;
+    match data[name_end + 1..].split_first()? {
+        // compression method 0, zlib
+        (0, compressed) => miniz_oxide::inflate::decompress_to_vec_zlib(compressed).ok(),
+        _ => None,
+    }
+}
+
+/// A decoder of paletted PNG images that keeps the palette indices instead of expanding them to
+/// RGB(A) colors, which takes a third or a quarter of the memory.
+///
+/// Images of any other color type are rejected with an unsupported color error, use
+/// [`PngDecoder`](struct.PngDecoder.html) for those.
+pub struct IndexedPngDecoder<R: Read> {
+    reader: png::Reader<R>,
+    bits: u8,
+    palette: Vec<Rgba<u8>>,
+}
+
+impl<R: Read> IndexedPngDecoder<R> {
+    /// Creates a new decoder that decodes from the stream ```r```
+    pub fn new(r: R) -> ImageResult<IndexedPngDecoder<R>> {
+        let limits = png::Limits {
+            bytes: usize::max_value(),
+        };
+        let mut decoder = png::Decoder::new_with_limits(r, limits);
+        decoder.set_transformations(png::Transformations::IDENTITY);
+        let (_, mut reader) = decoder.read_info().map_err(ImageError::from_png)?;
+
+        let bits = match reader.output_color_type() {
+            (png::ColorType::Indexed, bits) => bits as u8,
+            (png::ColorType::Grayscale, _) => return Err(unsupported_color(ExtendedColorType::L8)),
+            (png::ColorType::GrayscaleAlpha, _) => return Err(unsupported_color(ExtendedColorType::La8)),
+            (png::ColorType::RGB, _) => return Err(unsupported_color(ExtendedColorType::Rgb8)),
+            (png::ColorType::RGBA, _) => return Err(unsupported_color(ExtendedColorType::Rgba8)),
+        };
+
+        let info = reader.info();
+        let plte = match info.palette {
+            Some(ref plte) => plte,
+            None => return Err(ImageError::Decoding(DecodingError::new(
+                ImageFormat::Png.into(),
+                "paletted image without a PLTE chunk",
+            ))),
+        };
+        let trns = info.trns.as_ref().map_or(&[][..], |trns| &trns[..]);
+        let palette = plte.chunks_exact(3)
+            .enumerate()
+            .map(|(i, rgb)| Rgba([rgb[0], rgb[1], rgb[2], trns.get(i).cloned().unwrap_or(0xFF)]))
+            .collect();
+
+        Ok(IndexedPngDecoder { reader, bits, palette })
+    }
+
+    /// Returns the width and height of the image
+    pub fn dimensions(&self) -> (u32, u32) {
+        self.reader.info().size()
+    }
+
+    /// The colors of the palette, with the alpha values of the `tRNS` chunk if there is one
+    pub fn palette(&self) -> &[Rgba<u8>] {
+        &self.palette
+    }
+
+    /// Decode the palette index of every pixel.
+    ///
+    /// Note that indices are not checked against the length of the palette.
+    pub fn read_indices(mut self) -> ImageResult<GrayImage> {
+        let (width, height) = self.dimensions();
+        let mut packed = vec![0; self.reader.output_buffer_size()];
+        self.reader.next_frame(&mut packed).map_err(ImageError::from_png)?;
+
+        let line_size = self.reader.output_line_size(width);
+        let bits = usize::from(self.bits);
+        let mask = ((1u16 << bits) - 1) as u8;
+        let mut indices = Vec::with_capacity(width as usize * height as usize);
+        for row in packed.chunks(line_size.max(1)).take(height as usize) {
+            for x in 0..width as usize {
+                let bit = x * bits;
+                let shift = 8 - bits - bit % 8;
+                indices.push((row[bit / 8] >> shift) & mask);
+            }
+        }
+
+        ImageBuffer::from_raw(width, height, indices).ok_or_else(|| ImageError::Limits(
+            LimitError::from_kind(LimitErrorKind::InsufficientMemory)
+        ))
+    }
+}
+
 fn unsupported_color(ect: ExtendedColorType) -> ImageError {
     ImageError::Unsupported(UnsupportedError::from_format_and_kind(
         ImageFormat::Png.into(),
@@ -217,28 +429,55 @@ impl<'a, R: 'a + Read> ImageDecoder<'a> for PngDecoder<R> {
         self.color_type
     }
 
+    fn icc_profile(&mut self) -> Option<Vec<u8>> {
+        self.metadata.icc_profile.clone()
+    }
+
+    fn exif_metadata(&mut self) -> Option<Vec<u8>> {
+        self.metadata.exif.clone()
+    }
+
+    fn resolution(&mut self) -> Option<Resolution> {
+        self.metadata.resolution
+    }
+
+    fn xmp_metadata(&mut self) -> Option<Vec<u8>> {
+        self.metadata.text_chunks.iter()
+            .find(|chunk| chunk.keyword == XMP_KEYWORD)
+            .map(|chunk| chunk.text.clone().into_bytes())
+    }
+
     fn into_reader(self) -> ImageResult<Self::Reader> {
-        PngReader::new(self.reader)
+        PngReader::new(self.reader, self.color_type)
     }
 
-    fn read_image(mut self, buf: &mut [u8]) -> ImageResult<()> {
-        use byteorder::{BigEndian, ByteOrder, NativeEndian};
+    fn read_scanline(&mut self, buf: &mut [u8]) -> ImageResult<bool> {
+        assert_eq!(u64::try_from(buf.len()), Ok(self.row_bytes()));
+        if self.reader.info().interlaced {
+            // The passes of interlaced images can only be combined into rows once all of them
+            // have been decoded.
+            if self.interlaced_rows.is_none() {
+                let mut data = vec![0; self.reader.output_buffer_size()];
+                self.reader.next_frame(&mut data).map_err(ImageError::from_png)?;
+                self.interlaced_rows = Some(DecodedRows::new(data));
+            }
+            if !self.interlaced_rows.as_mut().unwrap().read_into(buf) {
+                return Ok(false);
+            }
+        } else {
+            match self.reader.next_row().map_err(ImageError::from_png)? {
+                Some(row) => buf.copy_from_slice(row),
+                None => return Ok(false),
+            }
+        }
+        to_native_endian(self.color_type, buf);
+        Ok(true)
+    }
 
+    fn read_image(mut self, buf: &mut [u8]) -> ImageResult<()> {
         assert_eq!(u64::try_from(buf.len()), Ok(self.total_bytes()));
         self.reader.next_frame(buf).map_err(ImageError::from_png)?;
-        // PNG images are big endian. For 16 bit per channel and larger types,
-        // the buffer may need to be reordered to native endianness per the
-        // contract of `read_image`.
-        // TODO: assumes equal channel bit depth.
-        let bpc = self.color_type().bytes_per_pixel() / self.color_type().channel_count();
-        match bpc {
-            1 => (),  // No reodering necessary for u8
-            2 => buf.chunks_mut(2).for_each(|c| {
-                let v = BigEndian::read_u16(c);
-                NativeEndian::write_u16(c, v)
-            }),
-            _ => unreachable!(),
-        }
+        to_native_endian(self.color_type, buf);
         Ok(())
     }
 
@@ -248,6 +487,23 @@ impl<'a, R: 'a + Read> ImageDecoder<'a> for PngDecoder<R> {
     }
 }
 
+/// PNG images are big endian. For 16 bit per channel and larger types, the buffer may need to be
+/// reordered to native endianness per the contract of `read_image`.
+fn to_native_endian(color_type: ColorType, buf: &mut [u8]) {
+    use byteorder::{BigEndian, ByteOrder, NativeEndian};
+
+    // TODO: assumes equal channel bit depth.
+    let bpc = color_type.bytes_per_pixel() / color_type.channel_count();
+    match bpc {
+        1 => (),  // No reodering necessary for u8
+        2 => buf.chunks_mut(2).for_each(|c| {
+            let v = BigEndian::read_u16(c);
+            NativeEndian::write_u16(c, v)
+        }),
+        _ => unreachable!(),
+    }
+}
+
 /// An [`AnimationDecoder`] adapter of [`PngDecoder`].
 ///
 /// See [`PngDecoder::apng`] for more information.
@@ -259,10 +515,12 @@ pub struct ApngDecoder<R: Read> {
     inner: PngDecoder<R>,
     /// The current output buffer.
     current: RgbaImage,
-    /// The previous output buffer, used for dispose op previous.
+    /// The output buffer before the current frame, used for dispose op previous.
     previous: RgbaImage,
     /// The dispose op of the current frame.
     dispose: DisposeOp,
+    /// The region of the current frame, which is disposed before the next one is drawn.
+    dispose_region: Option<(u32, u32, u32, u32)>,
     /// The number of image still expected to be able to load.
     remaining: u32,
     /// The next (first) image is the thumbnail.
@@ -287,6 +545,7 @@ impl<R: Read> ApngDecoder<R> {
             current: RgbaImage::new(width, height),
             previous: RgbaImage::new(width, height),
             dispose: DisposeOp::Background,
+            dispose_region: None,
             remaining,
             has_thumbnail,
         }
@@ -315,17 +574,22 @@ impl<R: Read> ApngDecoder<R> {
 
         self.animatable_color_type()?;
 
-        // Dispose of the previous frame.
-        match self.dispose {
-            DisposeOp::None => {
-                self.previous.clone_from(&self.current);
-            }
-            DisposeOp::Background => {
-                self.previous.clone_from(&self.current);
-                self.current.pixels_mut().for_each(|pixel| *pixel = Rgba([0, 0, 0, 0]));
-            }
-            DisposeOp::Previous => {
-                self.current.clone_from(&self.previous);
+        // Dispose of the region of the previous frame.
+        if let Some((px, py, width, height)) = self.dispose_region {
+            match self.dispose {
+                DisposeOp::None => {}
+                DisposeOp::Background => {
+                    for y in py..py + height {
+                        for x in px..px + width {
+                            self.current.put_pixel(x, y, Rgba([0, 0, 0, 0]));
+                        }
+                    }
+                }
+                DisposeOp::Previous => {
+                    let previous = self.previous.view(px, py, width, height);
+                    self.current.copy_from(&previous, px, py)
+                        .expect("Invalid png image not detected in png");
+                }
             }
         }
 
@@ -343,6 +607,7 @@ impl<R: Read> ApngDecoder<R> {
                 px = 0;
                 py = 0;
                 blend = BlendOp::Source;
+                self.dispose = DisposeOp::None;
             }
             Some(fc) => {
                 width = fc.width;
@@ -350,10 +615,21 @@ impl<R: Read> ApngDecoder<R> {
                 px = fc.x_offset;
                 py = fc.y_offset;
                 blend = fc.blend_op;
-                self.dispose = fc.dispose_op;
+                self.dispose = match (fc.dispose_op, self.dispose_region) {
+                    // There is nothing to revert to before the first frame, it is cleared.
+                    (DisposeOp::Previous, None) => DisposeOp::Background,
+                    (dispose, _) => dispose,
+                };
             }
         };
 
+        // Only the part covered by this frame needs to be restored afterwards.
+        if self.dispose == DisposeOp::Previous {
+            let region = self.current.view(px, py, width, height).to_image();
+            self.previous.copy_from(&region, px, py)
+                .expect("Invalid png image not detected in png");
+        }
+
         // Turn the data into an rgba image proper.
         let source = match self.inner.color_type {
             ColorType::L8 => {
@@ -391,6 +667,8 @@ impl<R: Read> ApngDecoder<R> {
             }
         }
 
+        self.dispose_region = Some((px, py, width, height));
+
         // Ok, we can proceed with actually remaining images.
         self.remaining = remaining;
         // Return composited output buffer.
@@ -446,6 +724,10 @@ pub struct PngEncoder<W: Write> {
     w: W,
     compression: CompressionType,
     filter: FilterType,
+    text_chunks: Vec<TextChunk>,
+    icc_profile: Option<Vec<u8>>,
+    exif: Option<Vec<u8>>,
+    resolution: Option<Resolution>,
 }
 
 /// PNG Encoder
@@ -479,8 +761,8 @@ pub enum CompressionType {
 
 /// Filter algorithms used to process image data to improve compression.
 ///
-/// The default filter is `Sub` though this default may change in the future, most notable if an
-/// adaptive encoding option is implemented.
+/// The default filter is `Sub` though this default may change in the future, most notably to
+/// `Adaptive`.
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum FilterType {
     /// No processing done, best used for low bit depth greyscale or data with a
@@ -494,6 +776,9 @@ pub enum FilterType {
     Avg,
     /// Algorithm that takes into account the left, upper left, and above pixels
     Paeth,
+    /// Chooses the filter for each scanline separately, by the heuristic recommended in the PNG
+    /// specification. Usually produces the smallest images, but is slower to encode.
+    Adaptive,
 
     #[doc(hidden)]
     __NonExhaustive(crate::utils::NonExhaustiveMarker),
@@ -506,6 +791,10 @@ impl<W: Write> PngEncoder<W> {
             w,
             compression: CompressionType::Fast,
             filter: FilterType::Sub,
+            text_chunks: Vec::new(),
+            icc_profile: None,
+            exif: None,
+            resolution: None,
         }
     }
 
@@ -528,10 +817,65 @@ impl<W: Write> PngEncoder<W> {
             w,
             compression,
             filter,
+            text_chunks: Vec::new(),
+            icc_profile: None,
+            exif: None,
+            resolution: None,
+        }
+    }
+
+    /// Add a text chunk, such as the author or a comment, to the encoded image.
+    ///
+    /// The chunks are written in front of the image data, in the order they were added.
+    pub fn with_text_chunk(mut self, chunk: TextChunk) -> Self {
+        self.text_chunks.push(chunk);
+        self
+    }
+
+    /// Embed an ICC color profile, given as the raw bytes of the ICC data, in an `iCCP` chunk.
+    ///
+    /// Decoders that support color management use it to interpret the colors of the image,
+    /// instead of assuming sRGB.
+    pub fn with_icc_profile(mut self, profile: Vec<u8>) -> Self {
+        self.icc_profile = Some(profile);
+        self
+    }
+
+    /// Store the physical resolution of the image in a `pHYs` chunk.
+    ///
+    /// PNG stores the density in pixels per meter, other units are converted and rounded.
+    pub fn with_resolution(mut self, resolution: Resolution) -> Self {
+        self.resolution = Some(resolution);
+        self
+    }
+
+    /// Embed metadata, such as the metadata of a decoded image, in the encoded image.
+    ///
+    /// The EXIF metadata is written to an `eXIf` chunk and the XMP packet to an `iTXt` chunk. An
+    /// ICC profile replaces one set with `with_icc_profile`.
+    pub fn with_metadata(mut self, metadata: ImageMetadata) -> Self {
+        if let Some(xmp) = metadata.xmp {
+            self.text_chunks.push(TextChunk {
+                keyword: XMP_KEYWORD.to_owned(),
+                text: String::from_utf8_lossy(&xmp).into_owned(),
+                encoding: TextEncoding::Utf8 {
+                    compressed: false,
+                    language_tag: String::new(),
+                    translated_keyword: String::new(),
+                },
+            });
+        }
+        if metadata.icc_profile.is_some() {
+            self.icc_profile = metadata.icc_profile;
         }
+        self.exif = metadata.exif;
+        self
     }
 
     /// Encodes the image `data` that has dimensions `width` and `height` and `ColorType` `c`.
+    ///
+    /// 16-bit samples are expected in big endian order, as they are stored in the file. Use
+    /// `write_image` to encode native endian samples.
     pub fn encode(self, data: &[u8], width: u32, height: u32, color: ColorType) -> ImageResult<()> {
         let (ct, bits) = match color {
             ColorType::L8 => (png::ColorType::Grayscale, png::BitDepth::Eight),
@@ -547,6 +891,46 @@ impl<W: Write> PngEncoder<W> {
                 UnsupportedErrorKind::Color(color.into()),
             ))),
         };
+        let bpp = color.bytes_per_pixel() as usize;
+        self.write_png(data, width, height, ct, bits, bpp, &[])
+    }
+
+    /// Encodes the paletted image `indices` as a PNG with a `PLTE` chunk, without expanding it
+    /// to RGB(A).
+    ///
+    /// Each pixel of `indices` is an index into `palette`, which must have between 1 and 256
+    /// entries. A `tRNS` chunk is added if any of the colors is not fully opaque.
+    pub fn encode_indexed<C>(self, indices: &ImageBuffer<Luma<u8>, C>, palette: &[Rgba<u8>]) -> ImageResult<()>
+    where
+        C: Deref<Target = [u8]>,
+    {
+        fn error(message: &str) -> ImageError {
+            ImageError::Encoding(EncodingError::new(ImageFormat::Png.into(), message))
+        }
+
+        if palette.is_empty() || palette.len() > 256 {
+            return Err(error("a palette must have between 1 and 256 entries"));
+        }
+        let (width, height) = indices.dimensions();
+        let data = &indices.as_raw()[..width as usize * height as usize];
+        if data.iter().any(|&index| usize::from(index) >= palette.len()) {
+            return Err(error("palette index out of range"));
+        }
+        self.write_png(data, width, height, png::ColorType::Indexed, png::BitDepth::Eight, 1, palette)
+    }
+
+    /// Write the image, with a palette unless `palette` is empty.
+    #[allow(clippy::too_many_arguments)]
+    fn write_png(
+        self,
+        data: &[u8],
+        width: u32,
+        height: u32,
+        ct: png::ColorType,
+        bits: png::BitDepth,
+        bpp: usize,
+        palette: &[Rgba<u8>],
+    ) -> ImageResult<()> {
         let comp = match self.compression {
             CompressionType::Default => png::Compression::Default,
             CompressionType::Fast => png::Compression::Fast,
@@ -561,19 +945,124 @@ impl<W: Write> PngEncoder<W> {
             FilterType::Up => png::FilterType::Up,
             FilterType::Avg => png::FilterType::Avg,
             FilterType::Paeth => png::FilterType::Paeth,
+            // Not used, the rows are filtered before they are passed to the png crate.
+            FilterType::Adaptive => png::FilterType::NoFilter,
             FilterType::__NonExhaustive(marker) => match marker._private {},
         };
 
+        let text_chunks = self.text_chunks.iter()
+            .map(TextChunk::encode)
+            .collect::<ImageResult<Vec<_>>>()?;
+
         let mut encoder = png::Encoder::new(self.w, width, height);
         encoder.set_color(ct);
         encoder.set_depth(bits);
         encoder.set_compression(comp);
         encoder.set_filter(filt);
+        if !palette.is_empty() {
+            encoder.set_palette(palette.iter().flat_map(|color| color.0[..3].to_vec()).collect());
+            // trailing opaque entries may be left out
+            let alphas = palette.iter().rposition(|color| color[3] != 0xFF).map_or(0, |last| last + 1);
+            if alphas > 0 {
+                encoder.set_trns(palette[..alphas].iter().map(|color| color[3]).collect());
+            }
+        }
         let mut writer = encoder.write_header().map_err(|e| ImageError::IoError(e.into()))?;
-        writer.write_image_data(data).map_err(|e| ImageError::IoError(e.into()))
+        if let Some(profile) = &self.icc_profile {
+            // profile name, compression method 0 and the zlib compressed profile
+            let mut iccp = b"ICC profile\0\0".to_vec();
+            iccp.extend_from_slice(&miniz_oxide::deflate::compress_to_vec_zlib(profile, 6));
+            writer.write_chunk(*b"iCCP", &iccp).map_err(|e| ImageError::IoError(e.into()))?;
+        }
+        if let Some(exif) = &self.exif {
+            writer.write_chunk(*b"eXIf", exif).map_err(|e| ImageError::IoError(e.into()))?;
+        }
+        if let Some(resolution) = self.resolution {
+            let phys = encode_resolution(resolution);
+            writer.write_chunk(*b"pHYs", &phys).map_err(|e| ImageError::IoError(e.into()))?;
+        }
+        for (kind, chunk) in text_chunks {
+            writer.write_chunk(kind, &chunk).map_err(|e| ImageError::IoError(e.into()))?;
+        }
+
+        if self.filter != FilterType::Adaptive {
+            return writer.write_image_data(data).map_err(|e| ImageError::IoError(e.into()));
+        }
+
+        // The png crate only applies a single filter to all rows, so filter and compress the
+        // image data here and write it as a single IDAT chunk.
+        let row_bytes = (width as usize).checked_mul(bpp);
+        if row_bytes.and_then(|row| row.checked_mul(height as usize)) != Some(data.len()) {
+            return Err(ImageError::Parameter(ParameterError::from_kind(
+                ParameterErrorKind::DimensionMismatch,
+            )));
+        }
+        let filtered = adaptive_filter(data, row_bytes.unwrap(), bpp);
+        let level = match self.compression {
+            CompressionType::Default => 6,
+            CompressionType::Best => 9,
+            // miniz has no separate Huffman-only or RLE mode at this level of its interface.
+            _ => 1,
+        };
+        let compressed = miniz_oxide::deflate::compress_to_vec_zlib(&filtered, level);
+        writer.write_chunk(*b"IDAT", &compressed).map_err(|e| ImageError::IoError(e.into()))
     }
 }
 
+/// Filter each row of `data` with the filter that minimizes the sum of the absolute values of the
+/// filtered bytes, taken as signed. Returns the rows, each prefixed with its filter type.
+fn adaptive_filter(data: &[u8], row_bytes: usize, bpp: usize) -> Vec<u8> {
+    fn paeth(a: u8, b: u8, c: u8) -> u8 {
+        let (ia, ib, ic) = (i16::from(a), i16::from(b), i16::from(c));
+        let p = ia + ib - ic;
+        let (pa, pb, pc) = ((p - ia).abs(), (p - ib).abs(), (p - ic).abs());
+        if pa <= pb && pa <= pc {
+            a
+        } else if pb <= pc {
+            b
+        } else {
+            c
+        }
+    }
+
+    let mut out = Vec::with_capacity(data.len() + data.len() / row_bytes.max(1));
+    let mut candidate = vec![0; row_bytes];
+    let mut best = vec![0; row_bytes];
+    let zeros = vec![0; row_bytes];
+    let mut previous: &[u8] = &zeros;
+
+    for row in data.chunks(row_bytes.max(1)) {
+        let mut best_filter = 0;
+        let mut best_sum = u64::max_value();
+        for filter in 0..5u8 {
+            for i in 0..row.len() {
+                let a = if i >= bpp { row[i - bpp] } else { 0 };
+                let b = previous[i];
+                let c = if i >= bpp { previous[i - bpp] } else { 0 };
+                let predicted = match filter {
+                    0 => 0,
+                    1 => a,
+                    2 => b,
+                    3 => ((u16::from(a) + u16::from(b)) / 2) as u8,
+                    _ => paeth(a, b, c),
+                };
+                candidate[i] = row[i].wrapping_sub(predicted);
+            }
+            let sum: u64 = candidate.iter().map(|&v| u64::from((v as i8).wrapping_abs() as u8)).sum();
+            if sum < best_sum {
+                best_sum = sum;
+                best_filter = filter;
+                std::mem::swap(&mut best, &mut candidate);
+            }
+        }
+        out.push(best_filter);
+        out.extend_from_slice(&best);
+        previous = row;
+    }
+
+    out
+}
+
 impl<W: Write> ImageEncoder for PngEncoder<W> {
     fn write_image(
         self,
@@ -601,9 +1090,353 @@ impl<W: Write> ImageEncoder for PngEncoder<W> {
                     .for_each(|(b, r)| BigEndian::write_u16(r, NativeEndian::read_u16(b)));
                 self.encode(&reordered, width, height, color_type)
             },
-            _ => unreachable!(),
+            // Not supported by PNG, `encode` reports the error.
+            _ => self.encode(buf, width, height, color_type),
+        }
+    }
+}
+
+/// A textual chunk of a PNG image
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TextChunk {
+    /// The keyword describing the text, such as `Title`, `Author`, `Software` or `Comment`
+    ///
+    /// Keywords have to be 1 to 79 Latin-1 characters long.
+    pub keyword: String,
+    /// The text itself
+    pub text: String,
+    /// How the chunk is stored
+    pub encoding: TextEncoding,
+}
+
+/// The storage of a text chunk
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TextEncoding {
+    /// Latin-1 text, stored in a `tEXt` chunk
+    Latin1,
+    /// zlib compressed Latin-1 text, stored in a `zTXt` chunk
+    CompressedLatin1,
+    /// UTF-8 text, stored in an `iTXt` chunk
+    Utf8 {
+        /// Whether the text is zlib compressed
+        compressed: bool,
+        /// The language of the text, such as `en-us`, or empty if unknown
+        language_tag: String,
+        /// The keyword translated to the language of the text
+        translated_keyword: String,
+    },
+
+    #[doc(hidden)]
+    __NonExhaustive(crate::utils::NonExhaustiveMarker),
+}
+
+impl TextChunk {
+    /// Create an uncompressed text chunk.
+    ///
+    /// The text is stored as Latin-1 if possible and as UTF-8 otherwise.
+    pub fn new(keyword: impl Into<String>, text: impl Into<String>) -> TextChunk {
+        let text = text.into();
+        let encoding = if text.chars().all(|c| u32::from(c) <= 0xFF) {
+            TextEncoding::Latin1
+        } else {
+            TextEncoding::Utf8 {
+                compressed: false,
+                language_tag: String::new(),
+                translated_keyword: String::new(),
+            }
+        };
+        TextChunk { keyword: keyword.into(), text, encoding }
+    }
+
+    /// Decode the data of a chunk of type `kind`, or `None` if it is no valid text chunk.
+    fn decode(kind: &[u8; 4], data: &[u8]) -> Option<TextChunk> {
+        fn latin1(bytes: &[u8]) -> String {
+            bytes.iter().map(|&b| char::from(b)).collect()
+        }
+        fn inflate(bytes: &[u8]) -> Option<Vec<u8>> {
+            miniz_oxide::inflate::decompress_to_vec_zlib(bytes).ok()
+        }
+        // split off a null terminated field
+        fn field(data: &[u8]) -> Option<(&[u8], &[u8])> {
+            let end = data.iter().position(|&b| b == 0)?;
+            Some((&data[..end], &data[end + 1..]))
+        }
+
+        let (keyword, rest) = field(data)?;
+        let keyword = latin1(keyword);
+        match kind {
+            b"tEXt" => Some(TextChunk {
+                keyword,
+                text: latin1(rest),
+                encoding: TextEncoding::Latin1,
+            }),
+            b"zTXt" => match rest.split_first()? {
+                (0, compressed) => Some(TextChunk {
+                    keyword,
+                    text: latin1(&inflate(compressed)?),
+                    encoding: TextEncoding::CompressedLatin1,
+                }),
+                _ => None,
+            },
+            b"iTXt" => {
+                let (&compressed, rest) = rest.split_first()?;
+                let (&method, rest) = rest.split_first()?;
+                let (language_tag, rest) = field(rest)?;
+                let (translated_keyword, text) = field(rest)?;
+                let text = match (compressed, method) {
+                    (0, _) => text.to_vec(),
+                    (1, 0) => inflate(text)?,
+                    _ => return None,
+                };
+                Some(TextChunk {
+                    keyword,
+                    text: String::from_utf8(text).ok()?,
+                    encoding: TextEncoding::Utf8 {
+                        compressed: compressed == 1,
+                        language_tag: String::from_utf8(language_tag.to_vec()).ok()?,
+                        translated_keyword: String::from_utf8(translated_keyword.to_vec()).ok()?,
+                    },
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// Encode the chunk into its type and data.
+    fn encode(&self) -> ImageResult<([u8; 4], Vec<u8>)> {
+        fn error(message: &str) -> ImageError {
+            ImageError::Encoding(EncodingError::new(ImageFormat::Png.into(), message))
+        }
+        fn latin1(text: &str) -> Option<Vec<u8>> {
+            text.chars().map(|c| u8::try_from(u32::from(c)).ok()).collect()
+        }
+        fn deflate(bytes: &[u8]) -> Vec<u8> {
+            miniz_oxide::deflate::compress_to_vec_zlib(bytes, 6)
+        }
+
+        let mut data = match latin1(&self.keyword) {
+            Some(ref keyword) if !keyword.is_empty() && keyword.len() < 80 && !keyword.contains(&0) => {
+                keyword.clone()
+            }
+            _ => return Err(error("text chunk keywords must be 1 to 79 Latin-1 characters")),
+        };
+        data.push(0);
+
+        let kind = match &self.encoding {
+            TextEncoding::Latin1 | TextEncoding::CompressedLatin1 => {
+                let text = latin1(&self.text)
+                    .ok_or_else(|| error("text of a Latin-1 text chunk is not Latin-1"))?;
+                if self.encoding == TextEncoding::Latin1 {
+                    data.extend_from_slice(&text);
+                    *b"tEXt"
+                } else {
+                    // compression method 0, zlib
+                    data.push(0);
+                    data.extend_from_slice(&deflate(&text));
+                    *b"zTXt"
+                }
+            }
+            TextEncoding::Utf8 { compressed, language_tag, translated_keyword } => {
+                if language_tag.contains('\0') || translated_keyword.contains('\0') {
+                    return Err(error("language tag and translated keyword must not contain null characters"));
+                }
+                data.push(*compressed as u8);
+                data.push(0);
+                data.extend_from_slice(language_tag.as_bytes());
+                data.push(0);
+                data.extend_from_slice(translated_keyword.as_bytes());
+                data.push(0);
+                if *compressed {
+                    data.extend_from_slice(&deflate(self.text.as_bytes()));
+                } else {
+                    data.extend_from_slice(self.text.as_bytes());
+                }
+                *b"iTXt"
+            }
+            TextEncoding::__NonExhaustive(marker) => match marker._private {},
+        };
+        Ok((kind, data))
+    }
+}
+
+/// An encoder of animated PNG images.
+///
+/// The frames are stored with 8-bit RGBA colors. The whole animation has to be known before
+/// anything can be written, so the frames are passed all at once to `encode_frames`.
+pub struct ApngEncoder<W: Write> {
+    w: W,
+    compression: CompressionType,
+    filter: FilterType,
+    loop_count: u32,
+    default_image: Option<RgbaImage>,
+}
+
+impl<W: Write> ApngEncoder<W> {
+    /// Create a new encoder that writes its output to `w`.
+    pub fn new(w: W) -> ApngEncoder<W> {
+        ApngEncoder::new_with_quality(w, CompressionType::default(), FilterType::default())
+    }
+
+    /// Create a new encoder that writes its output to `w` with `CompressionType` `compression` and
+    /// `FilterType` `filter`, see `PngEncoder::new_with_quality`.
+    pub fn new_with_quality(w: W, compression: CompressionType, filter: FilterType) -> ApngEncoder<W> {
+        ApngEncoder {
+            w,
+            compression,
+            filter,
+            loop_count: 0,
+            default_image: None,
+        }
+    }
+
+    /// Set how often the animation is played, 0 means that it loops forever. This is the default.
+    pub fn set_loop_count(&mut self, count: u32) {
+        self.loop_count = count;
+    }
+
+    /// Set an image that is shown instead of the animation by decoders that do not support
+    /// animated PNG images.
+    ///
+    /// The image determines the size of the canvas and is not part of the animation. Without a
+    /// default image the first frame is used, it has to cover the whole canvas.
+    pub fn set_default_image(&mut self, image: RgbaImage) {
+        self.default_image = Some(image);
+    }
+
+    /// Encode the frames of an animation.
+    ///
+    /// Each frame is placed on the canvas at its offset and replaces what was displayed before.
+    /// An error is returned if there are no frames or a frame extends past the canvas.
+    pub fn encode_frames<F>(self, frames: F) -> ImageResult<()>
+    where
+        F: IntoIterator<Item = Frame>,
+    {
+        self.try_encode_frames(frames.into_iter().map(Ok))
+    }
+
+    /// Try to encode a collection of `ImageResult<Frame>` objects, for example an `animation::Frames`.
+    /// Whenever an `Err` item is encountered, that value is returned and nothing is written.
+    pub fn try_encode_frames<F>(mut self, frames: F) -> ImageResult<()>
+    where
+        F: IntoIterator<Item = ImageResult<Frame>>,
+    {
+        let frames = frames.into_iter().collect::<ImageResult<Vec<_>>>()?;
+        let first = match frames.first() {
+            Some(first) => first,
+            None => return Err(ImageError::Parameter(ParameterError::from_kind(
+                ParameterErrorKind::Generic("an animated PNG needs at least one frame".to_owned()),
+            ))),
+        };
+
+        let (width, height) = match self.default_image {
+            Some(ref image) => image.dimensions(),
+            None if first.left() == 0 && first.top() == 0 => first.buffer().dimensions(),
+            None => return Err(ImageError::Parameter(ParameterError::from_kind(
+                ParameterErrorKind::DimensionMismatch,
+            ))),
+        };
+        let fits = |frame: &Frame| {
+            u64::from(frame.left()) + u64::from(frame.buffer().width()) <= u64::from(width)
+                && u64::from(frame.top()) + u64::from(frame.buffer().height()) <= u64::from(height)
+        };
+        if !frames.iter().all(fits) {
+            return Err(ImageError::Parameter(ParameterError::from_kind(
+                ParameterErrorKind::DimensionMismatch,
+            )));
+        }
+
+        self.w.write_all(b"\x89PNG\r\n\x1a\n")?;
+        let mut header = Vec::with_capacity(13);
+        header.extend_from_slice(&width.to_be_bytes());
+        header.extend_from_slice(&height.to_be_bytes());
+        // 8-bit RGBA, no interlacing.
+        header.extend_from_slice(&[8, 6, 0, 0, 0]);
+        write_chunk(&mut self.w, b"IHDR", &header)?;
+
+        let mut control = (frames.len() as u32).to_be_bytes().to_vec();
+        control.extend_from_slice(&self.loop_count.to_be_bytes());
+        write_chunk(&mut self.w, b"acTL", &control)?;
+
+        if let Some(ref image) = self.default_image {
+            let data = self.compress(image)?;
+            write_chunk(&mut self.w, b"IDAT", &data)?;
+        }
+
+        let mut sequence = 0u32;
+        for (index, frame) in frames.iter().enumerate() {
+            let (delay_num, delay_den) = apng_delay(frame.delay());
+            let mut control = sequence.to_be_bytes().to_vec();
+            sequence += 1;
+            control.extend_from_slice(&frame.buffer().width().to_be_bytes());
+            control.extend_from_slice(&frame.buffer().height().to_be_bytes());
+            control.extend_from_slice(&frame.left().to_be_bytes());
+            control.extend_from_slice(&frame.top().to_be_bytes());
+            control.extend_from_slice(&delay_num.to_be_bytes());
+            control.extend_from_slice(&delay_den.to_be_bytes());
+            // The frames are complete images, so they replace the canvas without disposal: dispose
+            // op none and blend op source.
+            control.extend_from_slice(&[0, 0]);
+            write_chunk(&mut self.w, b"fcTL", &control)?;
+
+            let data = self.compress(frame.buffer())?;
+            if index == 0 && self.default_image.is_none() {
+                write_chunk(&mut self.w, b"IDAT", &data)?;
+            } else {
+                let mut frame_data = sequence.to_be_bytes().to_vec();
+                sequence += 1;
+                frame_data.extend_from_slice(&data);
+                write_chunk(&mut self.w, b"fdAT", &frame_data)?;
+            }
+        }
+
+        write_chunk(&mut self.w, b"IEND", &[])?;
+        Ok(())
+    }
+
+    /// The filtered and compressed image data of an image, as stored in the IDAT chunks.
+    fn compress(&self, image: &RgbaImage) -> ImageResult<Vec<u8>> {
+        let mut encoded = Vec::new();
+        PngEncoder::new_with_quality(&mut encoded, self.compression, self.filter)
+            .encode(image.as_raw(), image.width(), image.height(), ColorType::Rgba8)?;
+
+        let mut data = Vec::new();
+        let mut chunks = &encoded[8..];
+        while chunks.len() >= 12 {
+            let length = u32::from_be_bytes([chunks[0], chunks[1], chunks[2], chunks[3]]) as usize;
+            if &chunks[4..8] == b"IDAT" {
+                data.extend_from_slice(&chunks[8..8 + length]);
+            }
+            chunks = &chunks[12 + length..];
         }
+        Ok(data)
+    }
+}
+
+/// Convert a delay to the fraction of seconds stored in a frame control chunk.
+fn apng_delay(delay: Delay) -> (u16, u16) {
+    let (numer, denom) = delay.numer_denom_ms();
+    let seconds = Ratio::new(u64::from(numer), u64::from(denom) * 1000);
+    if let (Ok(num), Ok(den)) = (u16::try_from(*seconds.numer()), u16::try_from(*seconds.denom())) {
+        return (num, den);
     }
+
+    // Round to milliseconds, or to seconds for delays that are too long.
+    let ms = (u64::from(numer) + u64::from(denom) / 2) / u64::from(denom);
+    match u16::try_from(ms) {
+        Ok(ms) => (ms, 1000),
+        Err(_) => (u16::try_from((ms + 500) / 1000).unwrap_or(std::u16::MAX), 1),
+    }
+}
+
+fn write_chunk<W: Write>(w: &mut W, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    let mut crc = crc32fast::Hasher::new();
+    crc.update(kind);
+    crc.update(data);
+
+    w.write_all(&(data.len() as u32).to_be_bytes())?;
+    w.write_all(kind)?;
+    w.write_all(data)?;
+    w.write_all(&crc.finalize().to_be_bytes())
 }
 
 impl ImageError {
@@ -648,10 +1481,296 @@ impl Default for FilterType {
 
 #[cfg(test)]
 mod tests {
+    use crate::animation::{SolidFrame, SolidFrameDisposal};
     use crate::image::ImageDecoder;
     use std::io::Read;
     use super::*;
 
+    #[test]
+    fn reader_16bpc_native_endian() {
+        let samples: Vec<u16> = (0..4 * 3 * 3).map(|i| i * 0x0102).collect();
+        let mut data = Vec::new();
+        PngEncoder::new(&mut data)
+            .write_image(bytemuck::cast_slice(&samples), 4, 3, ColorType::Rgb16)
+            .unwrap();
+
+        let decoder = PngDecoder::new(&data[..]).unwrap();
+        let mut reader = decoder.into_reader().unwrap();
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).unwrap();
+        assert_eq!(bytes, bytemuck::cast_slice::<u16, u8>(&samples));
+
+        // reading in small pieces splits the rows
+        let decoder = PngDecoder::new(&data[..]).unwrap();
+        let mut reader = decoder.into_reader().unwrap();
+        let mut bytes = vec![0; samples.len() * 2];
+        for chunk in bytes.chunks_mut(5) {
+            reader.read_exact(chunk).unwrap();
+        }
+        assert_eq!(bytes, bytemuck::cast_slice::<u16, u8>(&samples));
+    }
+
+    #[test]
+    fn read_scanlines() {
+        let paths = ["tests/images/png/interlaced/basi2c08.png", "tests/images/png/16bpc/basn6a16.png"];
+        for path in &paths {
+            let open = || PngDecoder::new(std::fs::File::open(path).unwrap()).unwrap();
+            let mut expected = vec![0; open().total_bytes() as usize];
+            open().read_image(&mut expected).unwrap();
+
+            let mut decoder = open();
+            let mut row = vec![0; decoder.row_bytes() as usize];
+            let mut rows = Vec::new();
+            while decoder.read_scanline(&mut row).unwrap() {
+                rows.extend_from_slice(&row);
+            }
+            assert_eq!(rows, expected, "{}", path);
+            assert!(!decoder.read_scanline(&mut row).unwrap());
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn read_scanline_wrong_length() {
+        let file = std::fs::File::open("tests/images/png/interlaced/basi2c08.png").unwrap();
+        let mut decoder = PngDecoder::new(file).unwrap();
+        let mut row = vec![0; decoder.row_bytes() as usize + 1];
+        let _ = decoder.read_scanline(&mut row);
+    }
+
+    #[test]
+    fn text_chunks_round_trip() {
+        let chunks = [
+            TextChunk::new("Software", "image"),
+            TextChunk {
+                keyword: "Comment".into(),
+                text: "compressed ".repeat(20),
+                encoding: TextEncoding::CompressedLatin1,
+            },
+            TextChunk::new("Author", "Zoë Ýlfa 佐藤"),
+            TextChunk {
+                keyword: "Title".into(),
+                text: "Überschrift".into(),
+                encoding: TextEncoding::Utf8 {
+                    compressed: true,
+                    language_tag: "de".into(),
+                    translated_keyword: "Titel".into(),
+                },
+            },
+        ];
+        assert_eq!(chunks[0].encoding, TextEncoding::Latin1);
+        match chunks[2].encoding {
+            TextEncoding::Utf8 { compressed: false, .. } => {}
+            ref other => panic!("expected uncompressed UTF-8, got {:?}", other),
+        }
+
+        let mut data = Vec::new();
+        let mut encoder = PngEncoder::new(&mut data);
+        for chunk in chunks.iter().cloned() {
+            encoder = encoder.with_text_chunk(chunk);
+        }
+        encoder.write_image(&[1, 2, 3, 4], 2, 2, ColorType::L8).unwrap();
+
+        let decoder = PngDecoder::new(&data[..]).unwrap();
+        assert_eq!(decoder.text_chunks(), &chunks[..]);
+        let mut image = [0; 4];
+        decoder.read_image(&mut image).unwrap();
+        assert_eq!(image, [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn text_chunk_invalid_keyword() {
+        for keyword in &["", "Schlüssel\u{0}", "佐藤"] {
+            let result = PngEncoder::new(Vec::new())
+                .with_text_chunk(TextChunk::new(*keyword, "text"))
+                .write_image(&[0], 1, 1, ColorType::L8);
+            match result {
+                Err(ImageError::Encoding(_)) => {}
+                other => panic!("expected an encoding error for {:?}, got {:?}", keyword, other),
+            }
+        }
+    }
+
+    #[test]
+    fn adaptive_filter_round_trip() {
+        let rgb: Vec<u8> = (0..17 * 9 * 3).map(|i| ((i * i) % 251) as u8).collect();
+        let wide: Vec<u16> = (0..5 * 4 * 4).map(|i| (i * 1031) as u16).collect();
+        let images = [
+            (&rgb[..], 17, 9, ColorType::Rgb8),
+            (bytemuck::cast_slice(&wide), 5, 4, ColorType::Rgba16),
+        ];
+        for &(data, width, height, color) in &images {
+            for &compression in &[CompressionType::Fast, CompressionType::Default, CompressionType::Best] {
+                let mut encoded = Vec::new();
+                PngEncoder::new_with_quality(&mut encoded, compression, FilterType::Adaptive)
+                    .write_image(data, width, height, color)
+                    .unwrap();
+
+                let decoder = PngDecoder::new(&encoded[..]).unwrap();
+                assert_eq!(decoder.color_type(), color);
+                let mut decoded = vec![0; decoder.total_bytes() as usize];
+                decoder.read_image(&mut decoded).unwrap();
+                assert_eq!(decoded, data);
+            }
+        }
+    }
+
+    #[test]
+    fn adaptive_filter_choice() {
+        // a constant row is best predicted from its left neighbour, a repeated one from above
+        let data = [7, 7, 7, 7, 7, 7, 7, 7];
+        assert_eq!(adaptive_filter(&data, 4, 1), [1, 7, 0, 0, 0, 2, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn indexed_round_trip() {
+        let palette = [
+            Rgba([255, 0, 0, 255]),
+            Rgba([0, 255, 0, 128]),
+            Rgba([0, 0, 255, 255]),
+        ];
+        let indices = GrayImage::from_fn(7, 3, |x, y| Luma([((x + y) % 3) as u8]));
+
+        let mut data = Vec::new();
+        PngEncoder::new(&mut data).encode_indexed(&indices, &palette).unwrap();
+
+        let decoder = IndexedPngDecoder::new(&data[..]).unwrap();
+        assert_eq!(decoder.dimensions(), (7, 3));
+        assert_eq!(decoder.palette(), &palette[..]);
+        assert_eq!(decoder.read_indices().unwrap(), indices);
+
+        // the regular decoder expands the palette
+        let decoder = PngDecoder::new(&data[..]).unwrap();
+        assert_eq!(decoder.color_type(), ColorType::Rgba8);
+        let mut rgba = vec![0; decoder.total_bytes() as usize];
+        decoder.read_image(&mut rgba).unwrap();
+        assert_eq!(&rgba[4..8], &[0, 255, 0, 128]);
+    }
+
+    #[test]
+    fn indexed_low_bit_depth() {
+        let mut data = Vec::new();
+        {
+            let mut encoder = png::Encoder::new(&mut data, 10, 2);
+            encoder.set_color(png::ColorType::Indexed);
+            encoder.set_depth(png::BitDepth::One);
+            encoder.set_palette(vec![0, 0, 0, 255, 255, 255]);
+            let mut writer = encoder.write_header().unwrap();
+            writer.write_image_data(&[0b1010_1010, 0b1000_0000, 0b0101_0101, 0b0100_0000]).unwrap();
+        }
+
+        let decoder = IndexedPngDecoder::new(&data[..]).unwrap();
+        assert_eq!(decoder.palette(), &[Rgba([0, 0, 0, 255]), Rgba([255, 255, 255, 255])]);
+        let indices = decoder.read_indices().unwrap();
+        let expected = GrayImage::from_fn(10, 2, |x, y| Luma([((x + y + 1) % 2) as u8]));
+        assert_eq!(indices, expected);
+    }
+
+    #[test]
+    fn indexed_rejects_invalid_input() {
+        let indices = GrayImage::from_pixel(2, 2, Luma([3]));
+        let palette = [Rgba([0, 0, 0, 255]); 3];
+        assert!(PngEncoder::new(Vec::new()).encode_indexed(&indices, &palette).is_err());
+        assert!(PngEncoder::new(Vec::new()).encode_indexed(&indices, &[]).is_err());
+
+        let mut data = Vec::new();
+        PngEncoder::new(&mut data).encode(&[0; 4], 2, 2, ColorType::L8).unwrap();
+        assert!(IndexedPngDecoder::new(&data[..]).is_err());
+    }
+
+    #[test]
+    fn icc_profile_round_trip() {
+        let profile: Vec<u8> = (0..600).map(|i| (i % 7) as u8).collect();
+        let mut data = Vec::new();
+        PngEncoder::new(&mut data)
+            .with_icc_profile(profile.clone())
+            .write_image(&[10, 20, 30], 1, 1, ColorType::Rgb8)
+            .unwrap();
+
+        let mut decoder = PngDecoder::new(&data[..]).unwrap();
+        assert_eq!(decoder.icc_profile(), Some(profile));
+        let mut image = [0; 3];
+        decoder.read_image(&mut image).unwrap();
+        assert_eq!(image, [10, 20, 30]);
+
+        let mut data = Vec::new();
+        PngEncoder::new(&mut data).write_image(&[0], 1, 1, ColorType::L8).unwrap();
+        assert_eq!(PngDecoder::new(&data[..]).unwrap().icc_profile(), None);
+    }
+
+    #[test]
+    fn icc_profile_invalid_chunks() {
+        let compressed = miniz_oxide::deflate::compress_to_vec_zlib(b"profile", 6);
+        let chunk = |name: &[u8], method: u8, data: &[u8]| {
+            let mut chunk = name.to_vec();
+            chunk.push(method);
+            chunk.extend_from_slice(data);
+            chunk
+        };
+        let valid = chunk(b"name\0", 0, &compressed);
+        assert_eq!(decode_icc_profile(&valid), Some(b"profile".to_vec()));
+        // The name is not terminated.
+        assert_eq!(decode_icc_profile(b"name"), None);
+        // The compression method is missing or unknown.
+        assert_eq!(decode_icc_profile(b"name\0"), None);
+        assert_eq!(decode_icc_profile(&chunk(b"name\0", 1, &compressed)), None);
+        // The profile is not a zlib stream.
+        assert_eq!(decode_icc_profile(&chunk(b"name\0", 0, b"profile")), None);
+
+        // An empty profile is still a profile.
+        let mut data = Vec::new();
+        PngEncoder::new(&mut data)
+            .with_icc_profile(Vec::new())
+            .write_image(&[0], 1, 1, ColorType::L8)
+            .unwrap();
+        assert_eq!(PngDecoder::new(&data[..]).unwrap().icc_profile(), Some(Vec::new()));
+    }
+
+    #[test]
+    fn resolution_round_trip() {
+        let mut data = Vec::new();
+        PngEncoder::new(&mut data)
+            .with_resolution(Resolution::dpi(300))
+            .write_image(&[0], 1, 1, ColorType::L8)
+            .unwrap();
+        let mut decoder = PngDecoder::new(&data[..]).unwrap();
+        assert_eq!(
+            decoder.resolution(),
+            Some(Resolution { density: (11811, 11811), unit: ResolutionUnit::Meters })
+        );
+
+        let mut data = Vec::new();
+        PngEncoder::new(&mut data).write_image(&[0], 1, 1, ColorType::L8).unwrap();
+        assert_eq!(PngDecoder::new(&data[..]).unwrap().resolution(), None);
+    }
+
+    #[test]
+    fn metadata_round_trip() {
+        let metadata = ImageMetadata {
+            exif: Some(b"MM\0*\0\0\0\x08\0\0\0\0\0\0".to_vec()),
+            xmp: Some("<x:xmpmeta xmlns:x='adobe:ns:meta/'>\u{e9}</x:xmpmeta>".as_bytes().to_vec()),
+            icc_profile: Some(vec![1, 2, 3, 4]),
+        };
+        let mut data = Vec::new();
+        PngEncoder::new(&mut data)
+            .with_metadata(metadata.clone())
+            .write_image(&[7], 1, 1, ColorType::L8)
+            .unwrap();
+
+        let mut decoder = PngDecoder::new(&data[..]).unwrap();
+        assert_eq!(decoder.metadata(), metadata);
+        assert_eq!(decoder.text_chunks()[0].keyword, "XML:com.adobe.xmp");
+    }
+
+    #[test]
+    fn float_is_unsupported() {
+        let result = PngEncoder::new(Vec::new()).write_image(&[0; 12], 1, 1, ColorType::Rgb32F);
+        match result {
+            Err(ImageError::Unsupported(_)) => {}
+            other => panic!("expected an unsupported error, got {:?}", other),
+        }
+    }
+
     #[test]
     fn ensure_no_decoder_off_by_one() {
         let dec = PngDecoder::new(std::fs::File::open("tests/images/png/bugfixes/debug_triangle_corners_widescreen.png").unwrap())
@@ -675,6 +1794,196 @@ mod tests {
         assert_eq![6_000_000, correct_bytes.len()];
     }
 
+    /// Build an RGBA animation from frames of a single color.
+    fn apng(width: u32, height: u32, frames: &[SolidFrame]) -> Vec<u8> {
+        fn chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+            write_chunk(out, kind, data).unwrap();
+        }
+
+        // A zlib stream with a single uncompressed block.
+        fn zlib(data: &[u8]) -> Vec<u8> {
+            let len = data.len() as u16;
+            let mut out = vec![0x78, 0x01, 0x01];
+            out.extend_from_slice(&len.to_le_bytes());
+            out.extend_from_slice(&(!len).to_le_bytes());
+            out.extend_from_slice(data);
+            let (mut a, mut b) = (1u32, 0u32);
+            for &byte in data {
+                a = (a + u32::from(byte)) % 65521;
+                b = (b + a) % 65521;
+            }
+            out.extend_from_slice(&((b << 16) | a).to_be_bytes());
+            out
+        }
+
+        let mut out = b"\x89PNG\r\n\x1a\n".to_vec();
+        let mut ihdr = Vec::new();
+        ihdr.extend_from_slice(&width.to_be_bytes());
+        ihdr.extend_from_slice(&height.to_be_bytes());
+        ihdr.extend_from_slice(&[8, 6, 0, 0, 0]);
+        chunk(&mut out, b"IHDR", &ihdr);
+
+        let mut actl = (frames.len() as u32).to_be_bytes().to_vec();
+        actl.extend_from_slice(&0u32.to_be_bytes());
+        chunk(&mut out, b"acTL", &actl);
+
+        let mut sequence = 0u32;
+        for (i, frame) in frames.iter().enumerate() {
+            let mut fctl = sequence.to_be_bytes().to_vec();
+            sequence += 1;
+            for value in &[frame.width, frame.height, frame.left, frame.top] {
+                fctl.extend_from_slice(&value.to_be_bytes());
+            }
+            // The delay in milliseconds.
+            fctl.extend_from_slice(&(frame.delay_ms as u16).to_be_bytes());
+            fctl.extend_from_slice(&1000u16.to_be_bytes());
+            fctl.push(match frame.dispose {
+                SolidFrameDisposal::Keep => 0,
+                SolidFrameDisposal::Background => 1,
+                SolidFrameDisposal::Previous => 2,
+            });
+            fctl.push(frame.blend as u8);
+            chunk(&mut out, b"fcTL", &fctl);
+
+            // Each row starts with the filter type 0.
+            let mut row = vec![0];
+            for _ in 0..frame.width {
+                row.extend_from_slice(&frame.color);
+            }
+            let raw = row.repeat(frame.height as usize);
+            if i == 0 {
+                chunk(&mut out, b"IDAT", &zlib(&raw));
+            } else {
+                let mut fdat = sequence.to_be_bytes().to_vec();
+                sequence += 1;
+                fdat.extend_from_slice(&zlib(&raw));
+                chunk(&mut out, b"fdAT", &fdat);
+            }
+        }
+
+        chunk(&mut out, b"IEND", &[]);
+        out
+    }
+
+    #[test]
+    fn apng_dispose_and_blend() {
+        let red = [255, 0, 0, 255];
+        let green = [0, 255, 0, 255];
+        let blue = [0, 0, 255, 255];
+        let clear = [0, 0, 0, 0];
+        let data = apng(3, 2, &[
+            SolidFrame::new(0, 0, 3, 2, red),
+            SolidFrame::new(2, 0, 1, 2, green).dispose(SolidFrameDisposal::Background),
+            SolidFrame::new(0, 0, 1, 1, blue).dispose(SolidFrameDisposal::Previous).blend(),
+            SolidFrame::new(1, 1, 1, 1, clear).blend(),
+        ]);
+
+        let decoder = PngDecoder::new(&data[..]).unwrap();
+        assert!(decoder.is_apng());
+        let frames = decoder.apng().into_frames().collect_frames().unwrap();
+        let pixels: Vec<Vec<[u8; 4]>> = frames
+            .iter()
+            .map(|frame| frame.buffer().pixels().map(|p| p.0).collect())
+            .collect();
+        assert_eq!(pixels, vec![
+            vec![red, red, red, red, red, red],
+            vec![red, red, green, red, red, green],
+            // Only the region of the second frame is cleared.
+            vec![blue, red, clear, red, red, clear],
+            // The first pixel is restored and the transparent pixel leaves the canvas unchanged.
+            vec![red, red, clear, red, red, clear],
+        ]);
+        for frame in &frames {
+            assert_eq!(frame.delay().numer_denom_ms(), (100, 1));
+        }
+    }
+
+    #[test]
+    fn apng_edge_cases() {
+        // Images without an animation have no frames.
+        let file = std::fs::File::open("tests/images/png/interlaced/basi2c08.png").unwrap();
+        let decoder = PngDecoder::new(file).unwrap();
+        assert!(!decoder.is_apng());
+        assert_eq!(decoder.apng().into_frames().count(), 0);
+
+        // A frame outside of the canvas is an error.
+        let data = apng(3, 2, &[
+            SolidFrame::new(0, 0, 3, 2, [0; 4]),
+            SolidFrame::new(2, 1, 2, 2, [0; 4]),
+        ]);
+        let mut frames = PngDecoder::new(&data[..]).unwrap().apng().into_frames();
+        assert!(frames.next().unwrap().is_ok());
+        assert!(frames.next().unwrap().is_err());
+        assert!(frames.next().is_none());
+
+        // 16-bit frames can not be composited.
+        let mut data = apng(1, 1, &[SolidFrame::new(0, 0, 1, 1, [0; 4])]);
+        // Patch the bit depth and the checksum of the IHDR chunk.
+        data[24] = 16;
+        let crc = crc32fast::hash(&data[12..29]);
+        data[29..33].copy_from_slice(&crc.to_be_bytes());
+        let decoder = PngDecoder::new(&data[..]).unwrap();
+        assert_eq!(decoder.color_type(), ColorType::Rgba16);
+        let mut frames = decoder.apng().into_frames();
+        match frames.next() {
+            Some(Err(ImageError::Unsupported(_))) => {}
+            other => panic!("expected an unsupported error, got {:?}", other.map(|r| r.is_ok())),
+        }
+        assert!(frames.next().is_none());
+    }
+
+    #[test]
+    fn apng_encode_roundtrip() {
+        let frame = |color: [u8; 4], left, top, width, height, ms| {
+            let buffer = RgbaImage::from_pixel(width, height, Rgba(color));
+            Frame::from_parts(buffer, left, top, Delay::from_numer_denom_ms(ms, 1))
+        };
+        let red = [255, 0, 0, 255];
+        let blue = [0, 0, 255, 128];
+        let frames = vec![frame(red, 0, 0, 4, 3, 100), frame(blue, 1, 1, 2, 2, 40)];
+
+        let mut encoded = Vec::new();
+        let mut encoder = ApngEncoder::new(&mut encoded);
+        encoder.set_loop_count(3);
+        encoder.encode_frames(frames.clone()).unwrap();
+
+        let decoder = PngDecoder::new(&encoded[..]).unwrap();
+        assert_eq!(decoder.reader.info().animation_control().unwrap().num_plays, 3);
+        let decoded = decoder.apng().into_frames().collect_frames().unwrap();
+        assert_eq!(decoded.len(), 2);
+        assert_eq!(decoded[0].buffer(), frames[0].buffer());
+        assert_eq!(decoded[1].buffer().get_pixel(0, 0).0, red);
+        assert_eq!(decoded[1].buffer().get_pixel(2, 2).0, blue);
+        assert_eq!(decoded[0].delay().numer_denom_ms(), (100, 1));
+        assert_eq!(decoded[1].delay().numer_denom_ms(), (40, 1));
+
+        // The default image is not part of the animation.
+        let mut encoded = Vec::new();
+        let mut encoder = ApngEncoder::new(&mut encoded);
+        encoder.set_default_image(RgbaImage::from_pixel(4, 3, Rgba(blue)));
+        encoder.encode_frames(frames[1..].to_vec()).unwrap();
+        let image = crate::load_from_memory_with_format(&encoded, ImageFormat::Png).unwrap();
+        assert_eq!(image.to_rgba8().get_pixel(3, 2).0, blue);
+        let decoded = PngDecoder::new(&encoded[..]).unwrap().apng().into_frames().collect_frames().unwrap();
+        assert_eq!(decoded.len(), 1);
+        assert_eq!(decoded[0].buffer().get_pixel(0, 0).0, [0, 0, 0, 0]);
+        assert_eq!(decoded[0].buffer().get_pixel(1, 1).0, blue);
+
+        // Without a default image the first frame has to cover the canvas.
+        assert!(ApngEncoder::new(Vec::new()).encode_frames(frames[1..].to_vec()).is_err());
+        assert!(ApngEncoder::new(Vec::new()).encode_frames(vec![]).is_err());
+        let outside = frame(red, 3, 0, 2, 1, 10);
+        assert!(ApngEncoder::new(Vec::new()).encode_frames(vec![frames[0].clone(), outside]).is_err());
+    }
+
+    #[test]
+    fn apng_delays() {
+        assert_eq!(apng_delay(Delay::from_numer_denom_ms(100, 1)), (1, 10));
+        assert_eq!(apng_delay(Delay::from_numer_denom_ms(1, 3)), (1, 3000));
+        assert_eq!(apng_delay(Delay::from_numer_denom_ms(70_001, 3)), (23_334, 1000));
+        assert_eq!(apng_delay(Delay::from_numer_denom_ms(100_000_001, 1)), (std::u16::MAX, 1));
+    }
+
     #[test]
     fn underlying_error() {
         use std::error::Error;
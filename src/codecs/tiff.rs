@@ -8,80 +8,253 @@
 
 extern crate tiff;
 
+use std::collections::HashSet;
 use std::convert::TryFrom;
-use std::io::{self, Cursor, Read, Write, Seek};
+use std::io::{self, Cursor, Read, Write, Seek, SeekFrom};
 use std::marker::PhantomData;
 use std::mem;
 
+use num_traits::WrappingSub;
+use tiff::encoder::colortype;
+use tiff::encoder::compression::{Compression, Deflate, Lzw, Uncompressed};
+use tiff::encoder::TiffValue;
+
 use crate::color::{ColorType, ExtendedColorType};
 use crate::error::{
     DecodingError, EncodingError, ImageError, ImageResult, LimitError, LimitErrorKind,
     ParameterError, ParameterErrorKind, UnsupportedError, UnsupportedErrorKind,
 };
-use crate::image::{ImageDecoder, ImageEncoder, ImageFormat};
+use crate::image::{self, DecodedRows, ImageDecoder, ImageEncoder, ImageFormat};
+use crate::metadata::{Orientation, Resolution, ResolutionUnit};
 use crate::utils;
 
 /// Decoder for TIFF images.
+///
+/// Files with multiple pages decode to their first page, further pages can be chosen with
+/// [`select_page`](#method.select_page).
 pub struct TiffDecoder<R>
     where R: Read + Seek
 {
     dimensions: (u32, u32),
     color_type: ColorType,
     inner: tiff::decoder::Decoder<R>,
+    rows: Option<DecodedRows>,
+    /// The first row that `read_scanline` has not decoded yet.
+    next_row: u32,
+    num_pages: usize,
+    page: usize,
 }
 
 impl<R> TiffDecoder<R>
     where R: Read + Seek
 {
     /// Create a new TiffDecoder.
-    pub fn new(r: R) -> Result<TiffDecoder<R>, ImageError> {
-        let mut inner = tiff::decoder::Decoder::new(r).map_err(ImageError::from_tiff_decode)?;
-
-        let dimensions = inner.dimensions()
-            .map_err(ImageError::from_tiff_decode)?;
-        let color_type = inner.colortype()
-            .map_err(ImageError::from_tiff_decode)?;
-        match inner.find_tag_unsigned_vec::<u16>(tiff::tags::Tag::SampleFormat) {
-            Ok(Some(sample_formats)) => {
-                for format in sample_formats {
-                    check_sample_format(format)?;
-                }
-            }
-            Ok(None) => { /* assume UInt format */ },
-            Err(other) => return Err(ImageError::from_tiff_decode(other)),
-        };
+    pub fn new(mut r: R) -> Result<TiffDecoder<R>, ImageError> {
+        let start = r.seek(SeekFrom::Current(0))?;
+        let num_pages = count_ifds(&mut r)?;
+        r.seek(SeekFrom::Start(start))?;
 
-        let color_type = match color_type {
-            tiff::ColorType::Gray(8) => ColorType::L8,
-            tiff::ColorType::Gray(16) => ColorType::L16,
-            tiff::ColorType::GrayA(8) => ColorType::La8,
-            tiff::ColorType::GrayA(16) => ColorType::La16,
-            tiff::ColorType::RGB(8) => ColorType::Rgb8,
-            tiff::ColorType::RGB(16) => ColorType::Rgb16,
-            tiff::ColorType::RGBA(8) => ColorType::Rgba8,
-            tiff::ColorType::RGBA(16) => ColorType::Rgba16,
-
-            tiff::ColorType::Palette(n) | tiff::ColorType::Gray(n) => {
-                return Err(err_unknown_color_type(n))
-            }
-            tiff::ColorType::GrayA(n) => return Err(err_unknown_color_type(n * 2)),
-            tiff::ColorType::RGB(n) => return Err(err_unknown_color_type(n * 3)),
-            tiff::ColorType::RGBA(n) | tiff::ColorType::CMYK(n) => {
-                return Err(err_unknown_color_type(n * 4))
-            }
-        };
+        // Large scans are common, the size of the image is checked against the limits of the
+        // caller instead.
+        let mut inner = tiff::decoder::Decoder::new(r)
+            .map_err(ImageError::from_tiff_decode)?
+            .with_limits(tiff::decoder::Limits::unlimited());
+        let (dimensions, color_type) = page_info(&mut inner)?;
 
         Ok(TiffDecoder {
             dimensions,
             color_type,
             inner,
+            rows: None,
+            next_row: 0,
+            num_pages,
+            page: 0,
         })
     }
+
+    /// The number of pages in the file.
+    pub fn num_pages(&self) -> usize {
+        self.num_pages
+    }
+
+    /// The index of the page that is decoded, starting at 0.
+    pub fn page(&self) -> usize {
+        self.page
+    }
+
+    /// Choose the page to decode, the dimensions and color type change to the ones of that page.
+    ///
+    /// Pages are stored as a linked list, so they can only be selected in increasing order.
+    /// Returns an error if the page does not exist or precedes the current page.
+    pub fn select_page(&mut self, page: usize) -> ImageResult<()> {
+        if page >= self.num_pages || page < self.page {
+            return Err(ImageError::Parameter(ParameterError::from_kind(
+                ParameterErrorKind::Generic(format!(
+                    "cannot select page {} of {} after page {}",
+                    page, self.num_pages, self.page,
+                )),
+            )));
+        }
+
+        while self.page < page {
+            self.inner.next_image().map_err(ImageError::from_tiff_decode)?;
+            self.page += 1;
+        }
+        let (dimensions, color_type) = page_info(&mut self.inner)?;
+        self.dimensions = dimensions;
+        self.color_type = color_type;
+        self.rows = None;
+        self.next_row = 0;
+        Ok(())
+    }
+
+    /// Decode the image into bytes in native endianness.
+    fn decode_bytes(&mut self) -> ImageResult<Vec<u8>> {
+        let result = self
+            .inner
+            .read_image()
+            .map_err(ImageError::from_tiff_decode)?;
+        result_to_bytes(result)
+    }
+}
+
+/// Count the image file directories, one per page, by following their chain from the header.
+fn count_ifds<R: Read + Seek>(r: &mut R) -> ImageResult<usize> {
+    fn format_error() -> ImageError {
+        ImageError::Decoding(DecodingError::new(
+            ImageFormat::Tiff.into(),
+            "invalid image file directory chain",
+        ))
+    }
+
+    let start = r.seek(SeekFrom::Current(0))?;
+    let mut header = [0; 16];
+    r.read_exact(&mut header[..8])?;
+    let big_endian = match &header[..2] {
+        b"II" => false,
+        b"MM" => true,
+        _ => return Err(format_error()),
+    };
+    let read_u16 = |bytes: &[u8]| {
+        let bytes = [bytes[0], bytes[1]];
+        if big_endian { u16::from_be_bytes(bytes) } else { u16::from_le_bytes(bytes) }
+    };
+    let read_u64 = |bytes: &[u8], len: usize| {
+        let mut value = 0u64;
+        for i in 0..len {
+            let byte = u64::from(bytes[if big_endian { i } else { len - 1 - i }]);
+            value = value << 8 | byte;
+        }
+        value
+    };
+
+    // Classic files use 32-bit offsets and 12-byte entries, BigTIFF files 64-bit ones and 20-byte
+    // entries.
+    let (offset_len, count_len, entry_len) = match read_u16(&header[2..]) {
+        42 => (4, 2, 12),
+        43 => {
+            r.read_exact(&mut header[8..])?;
+            (8, 8, 20)
+        }
+        _ => return Err(format_error()),
+    };
+    let mut offset = read_u64(&header[offset_len..], offset_len);
+
+    let mut seen = HashSet::new();
+    let mut buf = [0; 8];
+    while offset != 0 {
+        if !seen.insert(offset) {
+            return Err(format_error());
+        }
+        r.seek(SeekFrom::Start(start.checked_add(offset).ok_or_else(format_error)?))?;
+        r.read_exact(&mut buf[..count_len])?;
+        let entries = read_u64(&buf, count_len);
+        let skip = entries.checked_mul(entry_len).ok_or_else(format_error)?;
+        r.seek(SeekFrom::Current(i64::try_from(skip).map_err(|_| format_error())?))?;
+        r.read_exact(&mut buf[..offset_len])?;
+        offset = read_u64(&buf, offset_len);
+    }
+    Ok(seen.len())
+}
+
+/// The dimensions and color type of the current page.
+fn page_info<R: Read + Seek>(
+    inner: &mut tiff::decoder::Decoder<R>,
+) -> ImageResult<((u32, u32), ColorType)> {
+    let dimensions = inner.dimensions()
+        .map_err(ImageError::from_tiff_decode)?;
+    let color_type = inner.colortype()
+        .map_err(ImageError::from_tiff_decode)?;
+    let mut float = false;
+    match inner.find_tag_unsigned_vec::<u16>(tiff::tags::Tag::SampleFormat) {
+        Ok(Some(sample_formats)) => {
+            for format in sample_formats {
+                float |= check_sample_format(format)? == tiff::tags::SampleFormat::IEEEFP;
+            }
+        }
+        Ok(None) => { /* assume UInt format */ },
+        Err(other) => return Err(ImageError::from_tiff_decode(other)),
+    };
+
+    if float {
+        let color_type = match color_type {
+            tiff::ColorType::RGB(32) => ColorType::Rgb32F,
+            tiff::ColorType::RGBA(32) => ColorType::Rgba32F,
+            other => {
+                return Err(ImageError::Unsupported(UnsupportedError::from_format_and_kind(
+                    ImageFormat::Tiff.into(),
+                    UnsupportedErrorKind::GenericFeature(
+                        format!("Floating point samples of {:?}", other)
+                    ),
+                )))
+            }
+        };
+        return Ok((dimensions, color_type));
+    }
+
+    let color_type = match color_type {
+        tiff::ColorType::Gray(8) => ColorType::L8,
+        tiff::ColorType::Gray(16) => ColorType::L16,
+        tiff::ColorType::GrayA(8) => ColorType::La8,
+        tiff::ColorType::GrayA(16) => ColorType::La16,
+        tiff::ColorType::RGB(8) => ColorType::Rgb8,
+        tiff::ColorType::RGB(16) => ColorType::Rgb16,
+        tiff::ColorType::RGBA(8) => ColorType::Rgba8,
+        tiff::ColorType::RGBA(16) => ColorType::Rgba16,
+        tiff::ColorType::CMYK(8) => ColorType::Cmyk8,
+
+        tiff::ColorType::Palette(n) | tiff::ColorType::Gray(n) => {
+            return Err(err_unknown_color_type(n))
+        }
+        tiff::ColorType::GrayA(n) => return Err(err_unknown_color_type(n * 2)),
+        tiff::ColorType::RGB(n) => return Err(err_unknown_color_type(n * 3)),
+        tiff::ColorType::RGBA(n) | tiff::ColorType::CMYK(n) => {
+            return Err(err_unknown_color_type(n * 4))
+        }
+    };
+
+    Ok((dimensions, color_type))
+}
+
+fn result_to_bytes(result: tiff::decoder::DecodingResult) -> ImageResult<Vec<u8>> {
+    Ok(match result {
+        tiff::decoder::DecodingResult::U8(v) => v,
+        tiff::decoder::DecodingResult::U16(v) => utils::vec_u16_into_u8(v),
+        tiff::decoder::DecodingResult::U32(v) => utils::vec_u32_into_u8(v),
+        tiff::decoder::DecodingResult::U64(v) => utils::vec_u64_into_u8(v),
+        tiff::decoder::DecodingResult::F32(v) => bytemuck::cast_slice::<f32, u8>(v.as_slice()).to_owned(),
+        tiff::decoder::DecodingResult::F64(v) => bytemuck::cast_slice::<f64, u8>(v.as_slice()).to_owned(),
+        tiff::decoder::DecodingResult::I8(_)
+        | tiff::decoder::DecodingResult::I16(_)
+        | tiff::decoder::DecodingResult::I32(_)
+        | tiff::decoder::DecodingResult::I64(_) => return Err(err_signed_samples()),
+    })
 }
 
-fn check_sample_format(sample_format: u16) -> Result<(), ImageError> {
+fn check_sample_format(sample_format: u16) -> Result<tiff::tags::SampleFormat, ImageError> {
     match tiff::tags::SampleFormat::from_u16(sample_format) {
-        Some(tiff::tags::SampleFormat::Uint) => Ok(()),
+        Some(format @ tiff::tags::SampleFormat::Uint)
+        | Some(format @ tiff::tags::SampleFormat::IEEEFP) => Ok(format),
         Some(other) => {
             Err(ImageError::Unsupported(UnsupportedError::from_format_and_kind(
                 ImageFormat::Tiff.into(),
@@ -98,6 +271,13 @@ fn check_sample_format(sample_format: u16) -> Result<(), ImageError> {
     }
 }
 
+fn err_signed_samples() -> ImageError {
+    ImageError::Unsupported(UnsupportedError::from_format_and_kind(
+        ImageFormat::Tiff.into(),
+        UnsupportedErrorKind::GenericFeature("signed integer TIFF samples".to_owned()),
+    ))
+}
+
 fn err_unknown_color_type(value: u8) -> ImageError {
     ImageError::Unsupported(UnsupportedError::from_format_and_kind(
         ImageFormat::Tiff.into(),
@@ -121,6 +301,9 @@ impl ImageError {
             tiff::TiffError::LimitsExceeded => {
                 ImageError::Limits(LimitError::from_kind(LimitErrorKind::InsufficientMemory))
             }
+            tiff::TiffError::UsageError(err) => ImageError::Parameter(ParameterError::from_kind(
+                ParameterErrorKind::Generic(err.to_string()),
+            )),
         }
     }
 
@@ -139,6 +322,9 @@ impl ImageError {
             tiff::TiffError::LimitsExceeded => {
                 ImageError::Limits(LimitError::from_kind(LimitErrorKind::InsufficientMemory))
             }
+            tiff::TiffError::UsageError(err) => ImageError::Parameter(ParameterError::from_kind(
+                ParameterErrorKind::Generic(err.to_string()),
+            )),
         }
     }
 }
@@ -170,23 +356,113 @@ impl<'a, R: 'a + Read + Seek> ImageDecoder<'a> for TiffDecoder<R> {
         self.color_type
     }
 
-    fn into_reader(mut self) -> ImageResult<Self::Reader> {
-        let buf = match self
-            .inner
-            .read_image()
-            .map_err(ImageError::from_tiff_decode)?
-        {
-            tiff::decoder::DecodingResult::U8(v) => v,
-            tiff::decoder::DecodingResult::U16(v) => utils::vec_u16_into_u8(v),
-            tiff::decoder::DecodingResult::U32(v) => utils::vec_u32_into_u8(v),
-            tiff::decoder::DecodingResult::U64(v) => utils::vec_u64_into_u8(v),
-            tiff::decoder::DecodingResult::F32(v) => bytemuck::cast_slice::<f32, u8>(v.as_slice()).to_owned(),
-            tiff::decoder::DecodingResult::F64(v) => bytemuck::cast_slice::<f64, u8>(v.as_slice()).to_owned(),
+    fn orientation(&mut self) -> Option<Orientation> {
+        // TIFF files store the orientation as a tag of the image itself.
+        match self.inner.find_tag_unsigned::<u16>(tiff::tags::Tag::Orientation) {
+            Ok(Some(value)) => u8::try_from(value).ok().and_then(Orientation::from_exif),
+            _ => None,
+        }
+    }
+
+    fn resolution(&mut self) -> Option<Resolution> {
+        use tiff::decoder::ifd::Value;
+        use tiff::tags::Tag;
+
+        let mut density = |tag| match self.inner.find_tag(tag) {
+            Ok(Some(Value::Rational(n, d))) if d != 0 => {
+                Some((f64::from(n) / f64::from(d)).round() as u32)
+            }
+            _ => None,
+        };
+        let density = (density(Tag::XResolution)?, density(Tag::YResolution)?);
+        // The unit defaults to inches.
+        let unit = match self.inner.find_tag_unsigned::<u16>(Tag::ResolutionUnit) {
+            Ok(Some(1)) => ResolutionUnit::AspectRatio,
+            Ok(Some(3)) => ResolutionUnit::Centimeters,
+            _ => ResolutionUnit::Inches,
         };
+        Some(Resolution { density, unit })
+    }
 
+    fn xmp_metadata(&mut self) -> Option<Vec<u8>> {
+        // The XMP packet is stored in the XMLPacket tag.
+        self.inner.get_tag_u8_vec(tiff::tags::Tag::Unknown(700)).ok()
+    }
+
+    fn into_reader(mut self) -> ImageResult<Self::Reader> {
+        let buf = self.decode_bytes()?;
         Ok(TiffReader(Cursor::new(buf), PhantomData))
     }
 
+    fn read_scanline(&mut self, buf: &mut [u8]) -> ImageResult<bool> {
+        assert_eq!(u64::try_from(buf.len()), Ok(self.row_bytes()));
+
+        // Strips and tiles span several rows, only the strip or the row of tiles holding the
+        // current row is kept.
+        loop {
+            if let Some(rows) = self.rows.as_mut() {
+                if rows.read_into(buf) {
+                    return Ok(true);
+                }
+            }
+
+            let (width, height) = self.dimensions;
+            if self.next_row >= height {
+                return Ok(false);
+            }
+            let chunk_height = self.inner.chunk_dimensions().1.max(1);
+            let rows = chunk_height.min(height - self.next_row);
+            let mut data = vec![0; rows as usize * buf.len()];
+            self.read_rect(0, self.next_row, width, rows, &mut data)?;
+            self.next_row += rows;
+            self.rows = Some(DecodedRows::new(data));
+        }
+    }
+
+    fn read_rect(
+        &mut self,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+        buf: &mut [u8],
+    ) -> ImageResult<()> {
+        image::check_rect(self, x, y, width, height, buf)?;
+        if width == 0 || height == 0 {
+            return Ok(());
+        }
+
+        // Only the strips or tiles overlapping the section are decoded. They are numbered
+        // row-major, strips being tiles of the full image width.
+        let bytes_per_pixel = usize::from(self.color_type.bytes_per_pixel());
+        let (chunk_width, chunk_height) = self.inner.chunk_dimensions();
+        let chunks_across = (self.dimensions.0 + chunk_width - 1) / chunk_width;
+        let (right, bottom) = (x + width, y + height);
+        for chunk_y in y / chunk_height..(bottom - 1) / chunk_height + 1 {
+            for chunk_x in x / chunk_width..(right - 1) / chunk_width + 1 {
+                let index = chunk_y * chunks_across + chunk_x;
+                let (data_width, data_height) = self.inner.chunk_data_dimensions(index);
+                let data = self
+                    .inner
+                    .read_chunk(index)
+                    .map_err(ImageError::from_tiff_decode)?;
+                let data = result_to_bytes(data)?;
+
+                let (left, top) = (chunk_x * chunk_width, chunk_y * chunk_height);
+                let start = x.max(left);
+                let len = (right.min(left + data_width) - start) as usize * bytes_per_pixel;
+                for row in y.max(top)..bottom.min(top + data_height) {
+                    let from = ((row - top) as usize * data_width as usize + (start - left) as usize)
+                        * bytes_per_pixel;
+                    let to = ((row - y) as usize * width as usize + (start - x) as usize)
+                        * bytes_per_pixel;
+                    buf[to..][..len].copy_from_slice(&data[from..][..len]);
+                }
+            }
+        }
+        Ok(())
+    }
+
     fn read_image(mut self, buf: &mut [u8]) -> ImageResult<()> {
         assert_eq!(u64::try_from(buf.len()), Ok(self.total_bytes()));
         match self
@@ -212,14 +488,62 @@ impl<'a, R: 'a + Read + Seek> ImageDecoder<'a> for TiffDecoder<R> {
             tiff::decoder::DecodingResult::F64(v) => {
                 buf.copy_from_slice(bytemuck::cast_slice(&v));
             }
+            tiff::decoder::DecodingResult::I8(_)
+            | tiff::decoder::DecodingResult::I16(_)
+            | tiff::decoder::DecodingResult::I32(_)
+            | tiff::decoder::DecodingResult::I64(_) => return Err(err_signed_samples()),
         }
         Ok(())
     }
 }
 
+/// Compression of the strips of an encoded TIFF image. The default setting is `Uncompressed`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CompressionType {
+    /// No compression
+    Uncompressed,
+    /// Lempel-Ziv-Welch compression
+    Lzw,
+    /// Deflate compression, as used by zlib
+    Deflate,
+
+    #[doc(hidden)]
+    __NonExhaustive(utils::NonExhaustiveMarker),
+}
+
+impl Default for CompressionType {
+    fn default() -> Self {
+        CompressionType::Uncompressed
+    }
+}
+
+/// Prediction applied to the samples before compression. The default setting is `None`.
+///
+/// Prediction is intended for `Lzw` and `Deflate` compression, for smooth images such as
+/// photographs or scientific data it can reduce the size of the file considerably.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Predictor {
+    /// The samples are stored unchanged
+    None,
+    /// Each sample is stored as the difference to the same sample of the pixel to its left
+    Horizontal,
+
+    #[doc(hidden)]
+    __NonExhaustive(utils::NonExhaustiveMarker),
+}
+
+impl Default for Predictor {
+    fn default() -> Self {
+        Predictor::None
+    }
+}
+
 /// Encoder for tiff images
 pub struct TiffEncoder<W> {
     w: W,
+    compression: CompressionType,
+    predictor: Predictor,
+    resolution: Option<Resolution>,
 }
 
 // Utility to simplify and deduplicate error handling during 16-bit encoding.
@@ -239,22 +563,66 @@ fn u8_slice_as_u16(buf: &[u8]) -> ImageResult<&[u16]> {
 impl<W: Write + Seek> TiffEncoder<W> {
     /// Create a new encoder that writes its output to `w`
     pub fn new(w: W) -> TiffEncoder<W> {
-        TiffEncoder { w }
+        TiffEncoder {
+            w,
+            compression: CompressionType::default(),
+            predictor: Predictor::default(),
+            resolution: None,
+        }
+    }
+
+    /// Set the compression of the image data.
+    pub fn with_compression(mut self, compression: CompressionType) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Set the prediction applied to the samples before compression.
+    pub fn with_predictor(mut self, predictor: Predictor) -> Self {
+        self.predictor = predictor;
+        self
+    }
+
+    /// Store the physical resolution of the image. Densities per meter are stored per centimeter.
+    pub fn with_resolution(mut self, resolution: Resolution) -> Self {
+        self.resolution = Some(resolution);
+        self
     }
 
     /// Encodes the image `image` that has dimensions `width` and `height` and `ColorType` `c`.
     ///
     /// 16-bit types assume the buffer is native endian.
     pub fn encode(self, data: &[u8], width: u32, height: u32, color: ColorType) -> ImageResult<()> {
+        match self.compression {
+            CompressionType::Lzw => self.encode_with(Lzw, data, width, height, color),
+            CompressionType::Deflate => {
+                self.encode_with(Deflate::default(), data, width, height, color)
+            }
+            _ => self.encode_with(Uncompressed, data, width, height, color),
+        }
+    }
+
+    fn encode_with<D: Compression>(
+        self,
+        compression: D,
+        data: &[u8],
+        width: u32,
+        height: u32,
+        color: ColorType,
+    ) -> ImageResult<()> {
+        let predictor = self.predictor;
+        let resolution = self.resolution;
         let mut encoder =
             tiff::encoder::TiffEncoder::new(self.w).map_err(ImageError::from_tiff_encode)?;
+        let encoder = &mut encoder;
         match color {
-            ColorType::L8 => encoder.write_image::<tiff::encoder::colortype::Gray8>(width, height, data),
-            ColorType::Rgb8 => encoder.write_image::<tiff::encoder::colortype::RGB8>(width, height, data),
-            ColorType::Rgba8 => encoder.write_image::<tiff::encoder::colortype::RGBA8>(width, height, data),
-            ColorType::L16 => encoder.write_image::<tiff::encoder::colortype::Gray16>(width, height, &u8_slice_as_u16(data)?),
-            ColorType::Rgb16 => encoder.write_image::<tiff::encoder::colortype::RGB16>(width, height, &u8_slice_as_u16(data)?),
-            ColorType::Rgba16 => encoder.write_image::<tiff::encoder::colortype::RGBA16>(width, height, &u8_slice_as_u16(data)?),
+            ColorType::L8 => write_image::<_, colortype::Gray8, _>(encoder, width, height, compression, predictor, resolution, data),
+            ColorType::Rgb8 => write_image::<_, colortype::RGB8, _>(encoder, width, height, compression, predictor, resolution, data),
+            ColorType::Rgba8 => write_image::<_, colortype::RGBA8, _>(encoder, width, height, compression, predictor, resolution, data),
+            ColorType::L16 => write_image::<_, colortype::Gray16, _>(encoder, width, height, compression, predictor, resolution, u8_slice_as_u16(data)?),
+            ColorType::Rgb16 => write_image::<_, colortype::RGB16, _>(encoder, width, height, compression, predictor, resolution, u8_slice_as_u16(data)?),
+            ColorType::Rgba16 => write_image::<_, colortype::RGBA16, _>(encoder, width, height, compression, predictor, resolution, u8_slice_as_u16(data)?),
+            ColorType::Cmyk8 => write_image::<_, colortype::CMYK8, _>(encoder, width, height, compression, predictor, resolution, data),
             _ => {
                 return Err(ImageError::Unsupported(
                     UnsupportedError::from_format_and_kind(
@@ -270,6 +638,55 @@ impl<W: Write + Seek> TiffEncoder<W> {
     }
 }
 
+/// Write a single image, applying the predictor to a copy of the samples.
+fn write_image<W, C, D>(
+    encoder: &mut tiff::encoder::TiffEncoder<W>,
+    width: u32,
+    height: u32,
+    compression: D,
+    predictor: Predictor,
+    resolution: Option<Resolution>,
+    data: &[C::Inner],
+) -> tiff::TiffResult<()>
+where
+    W: Write + Seek,
+    C: colortype::ColorType,
+    C::Inner: Copy + WrappingSub,
+    [C::Inner]: TiffValue,
+    D: Compression,
+{
+    let mut image = encoder.new_image_with_compression::<C, D>(width, height, compression)?;
+    if let Some(resolution) = resolution {
+        use tiff::encoder::Rational;
+        use tiff::tags::ResolutionUnit as Unit;
+
+        let (unit, d) = match resolution.unit {
+            ResolutionUnit::AspectRatio => (Unit::None, 1),
+            ResolutionUnit::Inches => (Unit::Inch, 1),
+            ResolutionUnit::Centimeters => (Unit::Centimeter, 1),
+            ResolutionUnit::Meters => (Unit::Centimeter, 100),
+        };
+        image.resolution_unit(unit);
+        image.x_resolution(Rational { n: resolution.density.0, d });
+        image.y_resolution(Rational { n: resolution.density.1, d });
+    }
+    match predictor {
+        Predictor::Horizontal => {
+            image.encoder().write_tag(tiff::tags::Tag::Predictor, 2u16)?;
+            let samples = C::BITS_PER_SAMPLE.len();
+            let mut data = data.to_vec();
+            for row in data.chunks_mut(width as usize * samples) {
+                // Backwards, so that every difference is taken to an original sample.
+                for i in (samples..row.len()).rev() {
+                    row[i] = row[i].wrapping_sub(&row[i - samples]);
+                }
+            }
+            image.write_data(&data)
+        }
+        _ => image.write_data(data),
+    }
+}
+
 impl<W: Write + Seek> ImageEncoder for TiffEncoder<W> {
     fn write_image(
         self,
@@ -281,3 +698,247 @@ impl<W: Write + Seek> ImageEncoder for TiffEncoder<W> {
         self.encode(buf, width, height, color_type)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn select_pages() {
+        let mut data = Cursor::new(Vec::new());
+        {
+            let mut encoder = tiff::encoder::TiffEncoder::new(&mut data).unwrap();
+            encoder.write_image::<colortype::Gray8>(2, 2, &[1, 2, 3, 4]).unwrap();
+            encoder.write_image::<colortype::RGB8>(1, 1, &[5, 6, 7]).unwrap();
+        }
+        data.set_position(0);
+
+        let mut decoder = TiffDecoder::new(data).unwrap();
+        assert_eq!(decoder.num_pages(), 2);
+        assert_eq!(decoder.dimensions(), (2, 2));
+        assert_eq!(decoder.color_type(), ColorType::L8);
+
+        decoder.select_page(1).unwrap();
+        assert_eq!(decoder.page(), 1);
+        assert_eq!(decoder.dimensions(), (1, 1));
+        assert_eq!(decoder.color_type(), ColorType::Rgb8);
+        assert!(decoder.select_page(0).is_err());
+        assert!(decoder.select_page(2).is_err());
+
+        let mut buf = [0; 3];
+        decoder.read_image(&mut buf).unwrap();
+        assert_eq!(buf, [5, 6, 7]);
+    }
+
+    #[test]
+    fn read_scanlines_by_strip() {
+        let pixels: Vec<u8> = (0..21).collect();
+        let mut data = Cursor::new(Vec::new());
+        {
+            let mut encoder = tiff::encoder::TiffEncoder::new(&mut data).unwrap();
+            let mut image = encoder.new_image::<colortype::Gray8>(3, 7).unwrap();
+            image.rows_per_strip(2).unwrap();
+            image.write_data(&pixels).unwrap();
+        }
+        data.set_position(0);
+
+        let mut decoder = TiffDecoder::new(data).unwrap();
+        let mut row = [0; 3];
+        for (y, expected) in pixels.chunks(3).enumerate() {
+            assert!(decoder.read_scanline(&mut row).unwrap());
+            assert_eq!(row, expected);
+            // Only the strip of the current row has been decoded.
+            assert_eq!(decoder.next_row, ((y as u32 / 2 + 1) * 2).min(7));
+        }
+        assert!(!decoder.read_scanline(&mut row).unwrap());
+    }
+
+    #[test]
+    fn select_pages_edge_cases() {
+        let mut data = Cursor::new(Vec::new());
+        {
+            let mut encoder = tiff::encoder::TiffEncoder::new(&mut data).unwrap();
+            encoder.write_image::<colortype::Gray8>(2, 1, &[1, 2]).unwrap();
+            encoder.write_image::<colortype::Gray64>(1, 1, &[3]).unwrap();
+            encoder.write_image::<colortype::RGB8>(1, 1, &[4, 5, 6]).unwrap();
+        }
+        data.set_position(0);
+
+        let mut decoder = TiffDecoder::new(data).unwrap();
+        assert_eq!(decoder.num_pages(), 3);
+
+        // Selecting the current page starts reading its rows again.
+        let mut row = [0; 2];
+        assert!(decoder.read_scanline(&mut row).unwrap());
+        assert!(!decoder.read_scanline(&mut row).unwrap());
+        decoder.select_page(0).unwrap();
+        assert!(decoder.read_scanline(&mut row).unwrap());
+        assert_eq!(row, [1, 2]);
+
+        // Pages with unsupported samples can be skipped.
+        match decoder.select_page(1) {
+            Err(ImageError::Unsupported(_)) => {}
+            other => panic!("expected an unsupported error, got {:?}", other),
+        }
+        decoder.select_page(2).unwrap();
+        assert_eq!(decoder.color_type(), ColorType::Rgb8);
+        let mut buf = [0; 3];
+        decoder.read_image(&mut buf).unwrap();
+        assert_eq!(buf, [4, 5, 6]);
+
+        // A file with a single page.
+        let mut data = Cursor::new(Vec::new());
+        tiff::encoder::TiffEncoder::new(&mut data)
+            .unwrap()
+            .write_image::<colortype::Gray8>(1, 1, &[7])
+            .unwrap();
+        data.set_position(0);
+        let mut decoder = TiffDecoder::new(data).unwrap();
+        assert_eq!(decoder.num_pages(), 1);
+        assert!(decoder.select_page(1).is_err());
+        assert_eq!(decoder.page(), 0);
+    }
+
+    /// A 2x2 grayscale BigTIFF image, with 64-bit offsets and the pixels `[1, 2, 3, 4]`.
+    fn big_tiff_data() -> Vec<u8> {
+        let mut data = b"II\x2b\x00\x08\x00\x00\x00".to_vec();
+        data.extend_from_slice(&16u64.to_le_bytes());
+        let entries: [(u16, u16, u64); 9] = [
+            (256, 3, 2),    // ImageWidth
+            (257, 3, 2),    // ImageLength
+            (258, 3, 8),    // BitsPerSample
+            (259, 3, 1),    // Compression
+            (262, 3, 1),    // PhotometricInterpretation
+            (273, 16, 212), // StripOffsets
+            (277, 3, 1),    // SamplesPerPixel
+            (278, 3, 2),    // RowsPerStrip
+            (279, 16, 4),   // StripByteCounts
+        ];
+        data.extend_from_slice(&(entries.len() as u64).to_le_bytes());
+        for &(tag, kind, value) in &entries {
+            data.extend_from_slice(&tag.to_le_bytes());
+            data.extend_from_slice(&kind.to_le_bytes());
+            data.extend_from_slice(&1u64.to_le_bytes());
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+        data.extend_from_slice(&0u64.to_le_bytes());
+        assert_eq!(data.len(), 212);
+        data.extend_from_slice(&[1, 2, 3, 4]);
+        data
+    }
+
+    #[test]
+    fn big_tiff() {
+        let data = big_tiff_data();
+        assert_eq!(crate::guess_format(&data).unwrap(), ImageFormat::Tiff);
+        let decoder = TiffDecoder::new(Cursor::new(data)).unwrap();
+        assert_eq!(decoder.num_pages(), 1);
+        assert_eq!(decoder.dimensions(), (2, 2));
+        assert_eq!(decoder.color_type(), ColorType::L8);
+        let mut buf = [0; 4];
+        decoder.read_image(&mut buf).unwrap();
+        assert_eq!(buf, [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn big_tiff_errors() {
+        let data = big_tiff_data();
+
+        // Truncated files are an error, either when reading the header or the pixels.
+        for len in 0..data.len() {
+            let mut buf = [0; 4];
+            let result = TiffDecoder::new(Cursor::new(&data[..len]))
+                .and_then(|decoder| decoder.read_image(&mut buf));
+            assert!(result.is_err(), "truncated to {} bytes", len);
+        }
+
+        // The next image file directory is the same one.
+        let mut looped = data.clone();
+        looped[204..212].copy_from_slice(&16u64.to_le_bytes());
+        assert!(TiffDecoder::new(Cursor::new(looped)).is_err());
+
+        // The first image file directory is past the end of the file.
+        let mut past_end = data;
+        past_end[8..16].copy_from_slice(&(1u64 << 40).to_le_bytes());
+        assert!(TiffDecoder::new(Cursor::new(past_end)).is_err());
+    }
+
+    #[test]
+    fn compressed_round_trip() {
+        let (width, height) = (13, 7);
+        let rgb: Vec<u8> = (0..width * height * 3).map(|i| (i * 5 % 256) as u8).collect();
+        let compressions = [CompressionType::Uncompressed, CompressionType::Lzw, CompressionType::Deflate];
+        for &compression in &compressions {
+            for &predictor in &[Predictor::None, Predictor::Horizontal] {
+                let mut data = Cursor::new(Vec::new());
+                TiffEncoder::new(&mut data)
+                    .with_compression(compression)
+                    .with_predictor(predictor)
+                    .encode(&rgb, width, height, ColorType::Rgb8)
+                    .unwrap();
+                data.set_position(0);
+
+                let decoder = TiffDecoder::new(data).unwrap();
+                assert_eq!(decoder.color_type(), ColorType::Rgb8);
+                let mut buf = vec![0; rgb.len()];
+                decoder.read_image(&mut buf).unwrap();
+                assert_eq!(buf, rgb, "{:?} {:?}", compression, predictor);
+            }
+        }
+    }
+
+    #[test]
+    fn cmyk_round_trip() {
+        let cmyk = [0, 51, 255, 102, 255, 0, 0, 0];
+        let mut data = Cursor::new(Vec::new());
+        TiffEncoder::new(&mut data).encode(&cmyk, 2, 1, ColorType::Cmyk8).unwrap();
+        data.set_position(0);
+
+        let decoder = TiffDecoder::new(data).unwrap();
+        assert_eq!(decoder.color_type(), ColorType::Cmyk8);
+        let mut buf = [0; 8];
+        decoder.read_image(&mut buf).unwrap();
+        assert_eq!(buf, cmyk);
+    }
+
+    #[test]
+    fn resolution_round_trip() {
+        let cases = [
+            (Resolution::dpi(300), Resolution::dpi(300)),
+            (
+                Resolution { density: (11811, 5906), unit: ResolutionUnit::Meters },
+                Resolution { density: (118, 59), unit: ResolutionUnit::Centimeters },
+            ),
+        ];
+        for &(resolution, expected) in &cases {
+            let mut data = Cursor::new(Vec::new());
+            TiffEncoder::new(&mut data)
+                .with_resolution(resolution)
+                .encode(&[0], 1, 1, ColorType::L8)
+                .unwrap();
+            data.set_position(0);
+            let mut decoder = TiffDecoder::new(data).unwrap();
+            assert_eq!(decoder.resolution(), Some(expected));
+        }
+    }
+
+    #[test]
+    fn signed_samples() {
+        let mut data = Cursor::new(Vec::new());
+        {
+            let mut encoder = tiff::encoder::TiffEncoder::new(&mut data).unwrap();
+            encoder.write_image::<colortype::GrayI16>(2, 1, &[-1, 1]).unwrap();
+        }
+        data.set_position(0);
+        match TiffDecoder::new(data) {
+            Err(ImageError::Unsupported(_)) => {}
+            other => panic!("{:?}", other.map(|decoder| decoder.color_type())),
+        }
+
+        let usage = tiff::TiffError::UsageError(tiff::UsageError::InvalidChunkIndex(3));
+        match ImageError::from_tiff_decode(usage) {
+            ImageError::Parameter(_) => {}
+            other => panic!("{:?}", other),
+        }
+    }
+}
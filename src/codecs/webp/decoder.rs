@@ -5,14 +5,21 @@ use std::{error, fmt, mem};
 use std::io::{self, Cursor, Read};
 use std::marker::PhantomData;
 
+use crate::animation::{Delay, Frame, Frames};
 use crate::error::{DecodingError, ImageError, ImageResult, UnsupportedError, UnsupportedErrorKind};
-use crate::image::{ImageDecoder, ImageFormat};
+use crate::image::{AnimationDecoder, ImageDecoder, ImageFormat};
+use crate::metadata::ImageMetadata;
+use crate::{Pixel, Rgba, RgbaImage};
 
 use crate::color;
 
-use super::vp8::Frame;
+use super::lossless;
+use super::vp8::Frame as Vp8Frame;
 use super::vp8::Vp8Decoder;
 
+/// The flag of the VP8X chunk marking animated images.
+const ANIMATION_FLAG: u8 = 0x02;
+
 /// All errors that can occur when attempting to parse a WEBP container
 #[derive(Debug, Clone, Copy)]
 enum DecoderError {
@@ -20,6 +27,16 @@ enum DecoderError {
     RiffSignatureInvalid([u8; 4]),
     /// WebP's "WEBP" signature not found or invalid
     WebpSignatureInvalid([u8; 4]),
+    /// A chunk extends past the end of its parent
+    ChunkTruncated([u8; 4]),
+    /// A required chunk was not found
+    ChunkMissing([u8; 4]),
+    /// The compression method of an alpha chunk is unknown
+    AlphaCompressionInvalid(u8),
+    /// The image data of a frame does not match the size in its header
+    FrameSizeMismatch,
+    /// A frame extends past the edge of the canvas
+    FrameOutsideImage,
 }
 
 impl fmt::Display for DecoderError {
@@ -36,6 +53,16 @@ impl fmt::Display for DecoderError {
                 f.write_fmt(format_args!("Invalid RIFF signature: {}", SignatureWriter(*riff))),
             DecoderError::WebpSignatureInvalid(webp) =>
                 f.write_fmt(format_args!("Invalid WebP signature: {}", SignatureWriter(*webp))),
+            DecoderError::ChunkTruncated(chunk) =>
+                f.write_fmt(format_args!("Truncated chunk: {}", SignatureWriter(*chunk))),
+            DecoderError::ChunkMissing(chunk) =>
+                f.write_fmt(format_args!("Missing chunk: {}", SignatureWriter(*chunk))),
+            DecoderError::AlphaCompressionInvalid(method) =>
+                f.write_fmt(format_args!("Invalid alpha compression method {}", method)),
+            DecoderError::FrameSizeMismatch =>
+                f.write_str("Frame image data does not match the frame size"),
+            DecoderError::FrameOutsideImage =>
+                f.write_str("Frame is outside of the canvas"),
         }
     }
 }
@@ -48,24 +75,35 @@ impl From<DecoderError> for ImageError {
 
 impl error::Error for DecoderError {}
 
-/// WebP Image format decoder. Currently only supportes the luma channel (meaning that decoded
-/// images will be grayscale).
+/// WebP Image format decoder. Currently only supportes the luma channel of lossy images (meaning
+/// that decoded lossy images will be grayscale, with an alpha channel if the image has one).
+/// Lossless images decode to RGB or RGBA.
+///
+/// Animated images decode to RGBA. Their frames can be read through the [`AnimationDecoder`]
+/// implementation, as a still image they decode to their first frame.
+///
+/// [`AnimationDecoder`]: ../../trait.AnimationDecoder.html
 pub struct WebPDecoder<R> {
     r: R,
-    frame: Frame,
+    image: StillImage,
     have_frame: bool,
+    animation: Option<Animation>,
+    metadata: ImageMetadata,
+    /// The bytes of the RIFF payload following the image data.
+    remaining: u32,
 }
 
 impl<R: Read> WebPDecoder<R> {
     /// Create a new WebPDecoder from the Reader ```r```.
     /// This function takes ownership of the Reader.
     pub fn new(r: R) -> ImageResult<WebPDecoder<R>> {
-        let f: Frame = Default::default();
-
         let mut decoder = WebPDecoder {
             r,
             have_frame: false,
-            frame: f,
+            image: StillImage::Lossy(Default::default(), None),
+            animation: None,
+            metadata: ImageMetadata::default(),
+            remaining: 0,
         };
         decoder.read_metadata()?;
         Ok(decoder)
@@ -89,7 +127,12 @@ impl<R: Read> WebPDecoder<R> {
         Ok(size)
     }
 
-    fn read_vp8_header(&mut self) -> ImageResult<u32> {
+    /// Reads chunks up to the image data. Returns the length of the VP8 chunk and the payload of
+    /// its ALPH chunk, or `None` if the image is animated or lossless.
+    fn read_vp8_header(&mut self, riff_size: u32) -> ImageResult<Option<(u32, Option<Vec<u8>>)>> {
+        // The size of the RIFF payload includes the "WEBP" signature.
+        let mut remaining = riff_size.saturating_sub(4);
+        let mut alpha = None;
         loop {
             let mut chunk = [0; 4];
             self.r.read_exact(&mut chunk)?;
@@ -97,15 +140,56 @@ impl<R: Read> WebPDecoder<R> {
             match &chunk {
                 b"VP8 " => {
                     let len = self.r.read_u32::<LittleEndian>()?;
-                    return Ok(len);
+                    self.remaining = remaining.saturating_sub(8).saturating_sub(len + len % 2);
+                    return Ok(Some((len, alpha)));
+                }
+                b"VP8L" => {
+                    let data = self.read_chunk_payload(chunk)?;
+                    let len = data.len() as u32;
+                    self.remaining = remaining.saturating_sub(8).saturating_sub(len + len % 2);
+                    let (width, height, pixels) = lossless::decode(&data)?;
+                    // The alpha hint of the header is cleared if all pixels are opaque.
+                    let has_alpha = data.len() > 4 && data[4] & 0x10 != 0;
+                    self.image = StillImage::Lossless(argb_to_rgba(width, height, &pixels), has_alpha);
+                    return Ok(None);
+                }
+                b"ALPH" => {
+                    let data = self.read_chunk_payload(chunk)?;
+                    remaining = remaining.saturating_sub(8).saturating_sub(data.len() as u32);
+                    alpha = Some(data);
+                }
+                b"VP8X" => {
+                    let len = self.r.read_u32::<LittleEndian>()?;
+                    let mut data = Vec::new();
+                    self.r.by_ref().take(u64::from(len) + u64::from(len % 2)).read_to_end(&mut data)?;
+                    if data.len() < 10 {
+                        return Err(DecoderError::ChunkTruncated(chunk).into());
+                    }
+                    remaining = remaining.saturating_sub(8).saturating_sub(data.len() as u32);
+
+                    if data[0] & ANIMATION_FLAG != 0 {
+                        let width = read_u24(&data[4..]) + 1;
+                        let height = read_u24(&data[7..]) + 1;
+                        let mut chunks = Vec::new();
+                        self.r.by_ref().take(u64::from(remaining)).read_to_end(&mut chunks)?;
+                        self.read_metadata_chunks(&chunks);
+                        self.animation = Some(Animation::new(width, height, chunks));
+                        return Ok(None);
+                    }
                 }
-                b"ALPH" | b"VP8L" | b"ANIM" | b"ANMF" => {
-                    // Alpha, Lossless and Animation isn't supported
+                b"ANIM" | b"ANMF" => {
+                    // Animation outside of an animated image isn't supported
                     return Err(ImageError::Unsupported(UnsupportedError::from_format_and_kind(
                         ImageFormat::WebP.into(),
                         UnsupportedErrorKind::GenericFeature(chunk.iter().map(|&b| b as char).collect()),
                     )));
                 }
+                b"EXIF" | b"XMP " | b"ICCP" => {
+                    let data = self.read_chunk_payload(chunk)?;
+                    let len = data.len() as u32;
+                    remaining = remaining.saturating_sub(8).saturating_sub(len + len % 2);
+                    self.store_metadata(chunk, &data);
+                }
                 _ => {
                     let mut len = self.r.read_u32::<LittleEndian>()?;
                     if len % 2 != 0 {
@@ -114,12 +198,48 @@ impl<R: Read> WebPDecoder<R> {
                         len += 1;
                     }
                     io::copy(&mut self.r.by_ref().take(len as u64), &mut io::sink())?;
+                    remaining = remaining.saturating_sub(8).saturating_sub(len);
                 }
             }
         }
     }
 
-    fn read_frame(&mut self, len: u32) -> ImageResult<()> {
+    /// Reads the payload of a chunk whose name has already been read, including its padding.
+    fn read_chunk_payload(&mut self, chunk: [u8; 4]) -> ImageResult<Vec<u8>> {
+        let len = self.r.read_u32::<LittleEndian>()?;
+        let mut data = Vec::new();
+        self.r.by_ref().take(u64::from(len) + u64::from(len % 2)).read_to_end(&mut data)?;
+        if data.len() < len as usize {
+            return Err(DecoderError::ChunkTruncated(chunk).into());
+        }
+        data.truncate(len as usize);
+        Ok(data)
+    }
+
+    /// Keep the payload of a metadata chunk.
+    fn store_metadata(&mut self, chunk: [u8; 4], data: &[u8]) {
+        match &chunk {
+            b"EXIF" => self.metadata.exif = Some(data.to_vec()),
+            b"XMP " => self.metadata.xmp = Some(data.to_vec()),
+            b"ICCP" => self.metadata.icc_profile = Some(data.to_vec()),
+            _ => {}
+        }
+    }
+
+    /// Collect the metadata chunks of `data`, ignoring anything malformed.
+    fn read_metadata_chunks(&mut self, mut data: &[u8]) {
+        while data.len() >= 8 {
+            match split_chunk(data) {
+                Ok((chunk, payload, next)) => {
+                    self.store_metadata(chunk, payload);
+                    data = next;
+                }
+                Err(_) => break,
+            }
+        }
+    }
+
+    fn read_frame(&mut self, len: u32, alpha: Option<Vec<u8>>) -> ImageResult<()> {
         let mut framedata = Vec::new();
         self.r.by_ref().take(len as u64).read_to_end(&mut framedata)?;
         let m = io::Cursor::new(framedata);
@@ -127,22 +247,315 @@ impl<R: Read> WebPDecoder<R> {
         let mut v = Vp8Decoder::new(m);
         let frame = v.decode_frame()?;
 
-        self.frame = frame.clone();
+        let alpha = match alpha {
+            Some(data) => Some(decode_alpha(&data, u32::from(frame.width), u32::from(frame.height))?),
+            None => None,
+        };
+        self.image = StillImage::Lossy(frame.clone(), alpha);
 
         Ok(())
     }
 
     fn read_metadata(&mut self) -> ImageResult<()> {
         if !self.have_frame {
-            self.read_riff_header()?;
-            let len = self.read_vp8_header()?;
-            self.read_frame(len)?;
+            let size = self.read_riff_header()?;
+            if let Some((len, alpha)) = self.read_vp8_header(size)? {
+                self.read_frame(len, alpha)?;
+                // skip the padding of the VP8 chunk
+                io::copy(&mut self.r.by_ref().take(u64::from(len % 2)), &mut io::sink())?;
+            }
+            if self.animation.is_none() {
+                // The metadata of the extended format follows the image data.
+                let mut trailing = Vec::new();
+                self.r.by_ref().take(u64::from(self.remaining)).read_to_end(&mut trailing)?;
+                self.read_metadata_chunks(&trailing);
+            }
 
             self.have_frame = true;
         }
 
         Ok(())
     }
+
+    /// Returns true if the image is animated.
+    pub fn has_animation(&self) -> bool {
+        self.animation.is_some()
+    }
+
+    /// The pixels of the still image, or of the first frame of an animation.
+    fn into_image_data(self) -> ImageResult<Vec<u8>> {
+        match self.animation {
+            Some(mut animation) => match animation.next_frame() {
+                Some(frame) => Ok(frame?.into_buffer().into_raw()),
+                None => Err(DecoderError::ChunkMissing(*b"ANMF").into()),
+            },
+            None => Ok(self.image.into_data()),
+        }
+    }
+}
+
+/// The image of a file that is not animated.
+enum StillImage {
+    /// The luma channel of a lossy image, with the alpha plane if it has one.
+    Lossy(Vp8Frame, Option<Vec<u8>>),
+    /// A lossless image, and whether it uses the alpha channel.
+    Lossless(RgbaImage, bool),
+}
+
+impl StillImage {
+    fn dimensions(&self) -> (u32, u32) {
+        match self {
+            StillImage::Lossy(frame, _) => (u32::from(frame.width), u32::from(frame.height)),
+            StillImage::Lossless(image, _) => image.dimensions(),
+        }
+    }
+
+    fn color_type(&self) -> color::ColorType {
+        match self {
+            StillImage::Lossy(_, None) => color::ColorType::L8,
+            StillImage::Lossy(_, Some(_)) => color::ColorType::La8,
+            StillImage::Lossless(_, false) => color::ColorType::Rgb8,
+            StillImage::Lossless(_, true) => color::ColorType::Rgba8,
+        }
+    }
+
+    /// The pixels in the layout of its color type.
+    fn into_data(self) -> Vec<u8> {
+        match self {
+            StillImage::Lossy(frame, None) => frame.ybuf,
+            StillImage::Lossy(frame, Some(alpha)) => frame
+                .ybuf
+                .iter()
+                .zip(&alpha)
+                .flat_map(|(&luma, &a)| vec![luma, a])
+                .collect(),
+            StillImage::Lossless(image, true) => image.into_raw(),
+            StillImage::Lossless(image, false) => image
+                .pixels()
+                .flat_map(|pixel| pixel.0[..3].to_vec())
+                .collect(),
+        }
+    }
+
+    fn into_rgba(self) -> RgbaImage {
+        match self {
+            StillImage::Lossy(frame, alpha) => {
+                let (width, height) = (u32::from(frame.width), u32::from(frame.height));
+                RgbaImage::from_fn(width, height, |x, y| {
+                    let i = (y * width + x) as usize;
+                    let luma = frame.ybuf[i];
+                    let a = alpha.as_ref().map_or(0xFF, |alpha| alpha[i]);
+                    Rgba([luma, luma, luma, a])
+                })
+            }
+            StillImage::Lossless(image, _) => image,
+        }
+    }
+}
+
+/// The state of an animation while its frames are decoded.
+struct Animation {
+    /// The chunks following the VP8X chunk.
+    chunks: Vec<u8>,
+    position: usize,
+    canvas: RgbaImage,
+    /// The area of the previous frame, if it is to be cleared before the next frame.
+    dispose: Option<(u32, u32, u32, u32)>,
+}
+
+impl Animation {
+    fn new(width: u32, height: u32, chunks: Vec<u8>) -> Self {
+        Animation {
+            chunks,
+            position: 0,
+            // The background color of the ANIM chunk is only a hint, frames are drawn onto a
+            // transparent canvas like libwebp does.
+            canvas: RgbaImage::new(width, height),
+            dispose: None,
+        }
+    }
+
+    /// Decode the next ANMF chunk and draw it onto the canvas.
+    fn next_frame(&mut self) -> Option<ImageResult<Frame>> {
+        loop {
+            if self.chunks.len() - self.position < 8 {
+                return None;
+            }
+            let (chunk, _, next) = match split_chunk(&self.chunks[self.position..]) {
+                Ok(split) => split,
+                Err(err) => {
+                    self.position = self.chunks.len();
+                    return Some(Err(err));
+                }
+            };
+            let start = self.position;
+            self.position = self.chunks.len() - next.len();
+            if &chunk == b"ANMF" {
+                let end = self.position;
+                let result = self.draw_frame(start, end);
+                if result.is_err() {
+                    self.position = self.chunks.len();
+                }
+                return Some(result);
+            }
+        }
+    }
+
+    /// Draw the ANMF chunk at `start..end` of the chunks.
+    fn draw_frame(&mut self, start: usize, end: usize) -> ImageResult<Frame> {
+        let (chunk, payload, _) = split_chunk(&self.chunks[start..end])?;
+        if payload.len() < 16 {
+            return Err(DecoderError::ChunkTruncated(chunk).into());
+        }
+        let x = read_u24(&payload[0..]) * 2;
+        let y = read_u24(&payload[3..]) * 2;
+        let width = read_u24(&payload[6..]) + 1;
+        let height = read_u24(&payload[9..]) + 1;
+        let duration = read_u24(&payload[12..]);
+        let use_blending = payload[15] & 0x02 == 0;
+        let dispose = payload[15] & 0x01 != 0;
+
+        if x + width > self.canvas.width() || y + height > self.canvas.height() {
+            return Err(DecoderError::FrameOutsideImage.into());
+        }
+        let image = decode_frame_image(&payload[16..])?;
+        if image.dimensions() != (width, height) {
+            return Err(DecoderError::FrameSizeMismatch.into());
+        }
+
+        if let Some((dx, dy, dw, dh)) = self.dispose.take() {
+            for py in dy..dy + dh {
+                for px in dx..dx + dw {
+                    self.canvas.put_pixel(px, py, Rgba([0; 4]));
+                }
+            }
+        }
+        for (px, py, &pixel) in image.enumerate_pixels() {
+            if use_blending {
+                self.canvas.get_pixel_mut(x + px, y + py).blend(&pixel);
+            } else {
+                self.canvas.put_pixel(x + px, y + py, pixel);
+            }
+        }
+        if dispose {
+            self.dispose = Some((x, y, width, height));
+        }
+
+        let delay = Delay::from_numer_denom_ms(duration, 1);
+        Ok(Frame::from_parts(self.canvas.clone(), 0, 0, delay))
+    }
+}
+
+/// Split the chunk at the start of `data` off, returns its name, payload and the following data.
+fn split_chunk(data: &[u8]) -> ImageResult<([u8; 4], &[u8], &[u8])> {
+    let mut chunk = [0; 4];
+    chunk.copy_from_slice(&data[..4]);
+    let len = u32::from_le_bytes([data[4], data[5], data[6], data[7]]) as usize;
+    let data = &data[8..];
+    if len > data.len() {
+        return Err(DecoderError::ChunkTruncated(chunk).into());
+    }
+    // Chunks are padded to an even size, the padding may be missing at the end of the file.
+    let next = (len + len % 2).min(data.len());
+    Ok((chunk, &data[..len], &data[next..]))
+}
+
+/// Decode the image of an animation frame, from the chunks in the payload of its ANMF chunk.
+fn decode_frame_image(mut data: &[u8]) -> ImageResult<RgbaImage> {
+    let mut alpha = None;
+    while data.len() >= 8 {
+        let (chunk, payload, next) = split_chunk(data)?;
+        match &chunk {
+            b"ALPH" => alpha = Some(payload),
+            b"VP8L" => {
+                let (width, height, pixels) = lossless::decode(payload)?;
+                return Ok(argb_to_rgba(width, height, &pixels));
+            }
+            b"VP8 " => {
+                let mut vp8 = Vp8Decoder::new(Cursor::new(payload));
+                let frame = vp8.decode_frame()?;
+                let (width, height) = (u32::from(frame.width), u32::from(frame.height));
+                let alpha = match alpha {
+                    Some(alpha) => decode_alpha(alpha, width, height)?,
+                    None => vec![0xFF; frame.ybuf.len()],
+                };
+                let mut image = RgbaImage::new(width, height);
+                for ((pixel, &luma), &a) in image.pixels_mut().zip(&frame.ybuf).zip(&alpha) {
+                    *pixel = Rgba([luma, luma, luma, a]);
+                }
+                return Ok(image);
+            }
+            _ => {}
+        }
+        data = next;
+    }
+    Err(DecoderError::ChunkMissing(*b"VP8 ").into())
+}
+
+fn argb_to_rgba(width: u32, height: u32, pixels: &[u32]) -> RgbaImage {
+    let mut image = RgbaImage::new(width, height);
+    for (pixel, &argb) in image.pixels_mut().zip(pixels) {
+        let [a, r, g, b] = argb.to_be_bytes();
+        *pixel = Rgba([r, g, b, a]);
+    }
+    image
+}
+
+/// Decode the payload of an ALPH chunk into one alpha value per pixel.
+fn decode_alpha(data: &[u8], width: u32, height: u32) -> ImageResult<Vec<u8>> {
+    let header = match data.first() {
+        Some(&header) => header,
+        None => return Err(DecoderError::ChunkTruncated(*b"ALPH").into()),
+    };
+    let size = width as usize * height as usize;
+    let mut alpha = match header & 0x03 {
+        0 => {
+            if data.len() - 1 < size {
+                return Err(DecoderError::ChunkTruncated(*b"ALPH").into());
+            }
+            data[1..=size].to_vec()
+        }
+        1 => lossless::decode_headerless(&data[1..], width, height)?
+            .iter()
+            .map(|&argb| (argb >> 8) as u8)
+            .collect(),
+        method => return Err(DecoderError::AlphaCompressionInvalid(method).into()),
+    };
+    unfilter_alpha(&mut alpha, width as usize, (header >> 2) & 0x03);
+    Ok(alpha)
+}
+
+/// Reverse the prediction filter of an alpha plane.
+fn unfilter_alpha(alpha: &mut [u8], width: usize, filter: u8) {
+    if filter == 0 || width == 0 {
+        return;
+    }
+    for i in 1..alpha.len() {
+        let (x, y) = (i % width, i / width);
+        let predictor = if y == 0 {
+            alpha[i - 1]
+        } else if x == 0 {
+            alpha[i - width]
+        } else {
+            match filter {
+                // Horizontal
+                1 => alpha[i - 1],
+                // Vertical
+                2 => alpha[i - width],
+                // Gradient
+                _ => {
+                    let gradient = i16::from(alpha[i - 1]) + i16::from(alpha[i - width])
+                        - i16::from(alpha[i - width - 1]);
+                    gradient.max(0).min(255) as u8
+                }
+            }
+        };
+        alpha[i] = alpha[i].wrapping_add(predictor);
+    }
+}
+
+fn read_u24(data: &[u8]) -> u32 {
+    u32::from(data[0]) | u32::from(data[1]) << 8 | u32::from(data[2]) << 16
 }
 
 /// Wrapper struct around a `Cursor<Vec<u8>>`
@@ -165,20 +578,235 @@ impl<'a, R: 'a + Read> ImageDecoder<'a> for WebPDecoder<R> {
     type Reader = WebpReader<R>;
 
     fn dimensions(&self) -> (u32, u32) {
-        (u32::from(self.frame.width), u32::from(self.frame.height))
+        match self.animation {
+            Some(ref animation) => animation.canvas.dimensions(),
+            None => self.image.dimensions(),
+        }
     }
 
     fn color_type(&self) -> color::ColorType {
-        color::ColorType::L8
+        if self.has_animation() {
+            color::ColorType::Rgba8
+        } else {
+            self.image.color_type()
+        }
+    }
+
+    fn icc_profile(&mut self) -> Option<Vec<u8>> {
+        self.metadata.icc_profile.clone()
+    }
+
+    fn exif_metadata(&mut self) -> Option<Vec<u8>> {
+        self.metadata.exif.clone()
+    }
+
+    fn xmp_metadata(&mut self) -> Option<Vec<u8>> {
+        self.metadata.xmp.clone()
     }
 
     fn into_reader(self) -> ImageResult<Self::Reader> {
-        Ok(WebpReader(Cursor::new(self.frame.ybuf), PhantomData))
+        Ok(WebpReader(Cursor::new(self.into_image_data()?), PhantomData))
     }
 
     fn read_image(self, buf: &mut [u8]) -> ImageResult<()> {
         assert_eq!(u64::try_from(buf.len()), Ok(self.total_bytes()));
-        buf.copy_from_slice(&self.frame.ybuf);
+        buf.copy_from_slice(&self.into_image_data()?);
         Ok(())
     }
 }
+
+impl<'a, R: 'a + Read> AnimationDecoder<'a> for WebPDecoder<R> {
+    fn into_frames(self) -> Frames<'a> {
+        struct FrameIterator {
+            // The image of a still file, which is returned as a single frame.
+            still: Option<RgbaImage>,
+            animation: Option<Animation>,
+        }
+
+        impl Iterator for FrameIterator {
+            type Item = ImageResult<Frame>;
+
+            fn next(&mut self) -> Option<Self::Item> {
+                if let Some(image) = self.still.take() {
+                    return Some(Ok(Frame::new(image)));
+                }
+                self.animation.as_mut()?.next_frame()
+            }
+        }
+
+        let iterator = match self.animation {
+            Some(animation) => FrameIterator {
+                still: None,
+                animation: Some(animation),
+            },
+            None => FrameIterator {
+                still: Some(self.image.into_rgba()),
+                animation: None,
+            },
+        };
+        Frames::new(Box::new(iterator))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::encoder::write_chunk;
+    use super::{unfilter_alpha, WebPDecoder};
+    use crate::animation::{SolidFrame, SolidFrameDisposal};
+    use crate::image::{AnimationDecoder, ImageDecoder};
+    use crate::{ColorType, Rgba};
+
+    /// A lossless image of a single color, using prefix codes with one symbol each.
+    fn solid_vp8l(width: u32, height: u32, color: [u8; 4]) -> Vec<u8> {
+        let mut bits: Vec<(u32, u32)> = vec![(0x2f, 8), (width - 1, 14), (height - 1, 14)];
+        // Alpha hint, version, no transform, no color cache, no meta prefix codes.
+        bits.extend_from_slice(&[(1, 1), (0, 3), (0, 1), (0, 1), (0, 1)]);
+        // Simple codes with a single 8-bit symbol for green, red, blue and alpha.
+        for &value in &[color[1], color[0], color[2], color[3]] {
+            bits.extend_from_slice(&[(1, 1), (0, 1), (1, 1), (u32::from(value), 8)]);
+        }
+        // The distance code with a single 1-bit symbol.
+        bits.extend_from_slice(&[(1, 1), (0, 1), (0, 1), (0, 1)]);
+
+        let mut data = Vec::new();
+        let (mut acc, mut count) = (0u64, 0);
+        for (value, n) in bits {
+            acc |= u64::from(value) << count;
+            count += n;
+            while count >= 8 {
+                data.push(acc as u8);
+                acc >>= 8;
+                count -= 8;
+            }
+        }
+        data.push(acc as u8);
+        data
+    }
+
+    fn chunk(name: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+        let mut data = Vec::new();
+        write_chunk(&mut data, name, payload);
+        data
+    }
+
+    fn u24(value: u32) -> [u8; 3] {
+        let bytes = value.to_le_bytes();
+        [bytes[0], bytes[1], bytes[2]]
+    }
+
+    /// An animated image of the given frames.
+    fn animation(width: u32, height: u32, frames: &[SolidFrame]) -> Vec<u8> {
+        let mut vp8x = vec![0x12, 0, 0, 0];
+        vp8x.extend_from_slice(&u24(width - 1));
+        vp8x.extend_from_slice(&u24(height - 1));
+        let mut body = b"WEBP".to_vec();
+        body.extend(chunk(b"VP8X", &vp8x));
+        body.extend(chunk(b"ANIM", &[0xFF, 0xFF, 0xFF, 0xFF, 0, 0]));
+        for frame in frames {
+            let mut anmf = Vec::new();
+            let (width, height) = (frame.width, frame.height);
+            for &value in &[frame.left / 2, frame.top / 2, width - 1, height - 1, frame.delay_ms] {
+                anmf.extend_from_slice(&u24(value));
+            }
+            let dispose = match frame.dispose {
+                SolidFrameDisposal::Keep => 0,
+                SolidFrameDisposal::Background => 1,
+                SolidFrameDisposal::Previous => panic!("WebP can not restore the previous canvas"),
+            };
+            anmf.push(dispose | if frame.blend { 0 } else { 2 });
+            anmf.extend(chunk(b"VP8L", &solid_vp8l(width, height, frame.color)));
+            body.extend(chunk(b"ANMF", &anmf));
+        }
+        chunk(b"RIFF", &body)
+    }
+
+    #[test]
+    fn animation_blend_and_dispose() {
+        let data = animation(
+            4,
+            4,
+            &[
+                SolidFrame::new(0, 0, 4, 4, [255, 0, 0, 255]).blend(),
+                // Blended, then disposed to the background.
+                SolidFrame::new(2, 2, 2, 2, [0, 0, 255, 128])
+                    .delay_ms(50)
+                    .blend()
+                    .dispose(SolidFrameDisposal::Background),
+                // Not blended.
+                SolidFrame::new(0, 0, 2, 2, [0, 255, 0, 0]).delay_ms(20),
+            ],
+        );
+
+        let decoder = WebPDecoder::new(&data[..]).unwrap();
+        assert!(decoder.has_animation());
+        assert_eq!(decoder.dimensions(), (4, 4));
+        assert_eq!(decoder.color_type(), ColorType::Rgba8);
+        let mut buf = vec![0; 4 * 4 * 4];
+        decoder.read_image(&mut buf).unwrap();
+        assert!(buf.chunks(4).all(|p| p == [255, 0, 0, 255]));
+
+        let frames = WebPDecoder::new(&data[..])
+            .unwrap()
+            .into_frames()
+            .collect_frames()
+            .unwrap();
+        assert_eq!(frames.len(), 3);
+        let delays: Vec<_> = frames.iter().map(|f| f.delay().numer_denom_ms()).collect();
+        assert_eq!(delays, [(100, 1), (50, 1), (20, 1)]);
+
+        let second = frames[1].buffer();
+        assert_eq!(*second.get_pixel(0, 0), Rgba([255, 0, 0, 255]));
+        let blended = second.get_pixel(3, 3);
+        assert!(blended[3] >= 254);
+        assert!(blended[0] > 100 && blended[0] < 155 && blended[2] > 100 && blended[2] < 155);
+
+        let third = frames[2].buffer();
+        assert_eq!(*third.get_pixel(1, 1), Rgba([0, 255, 0, 0]));
+        assert_eq!(*third.get_pixel(3, 0), Rgba([255, 0, 0, 255]));
+        assert_eq!(*third.get_pixel(2, 2), Rgba([0, 0, 0, 0]));
+    }
+
+    #[test]
+    fn extended_metadata() {
+        let mut vp8x = vec![0x2C, 0, 0, 0];
+        vp8x.extend_from_slice(&u24(1));
+        vp8x.extend_from_slice(&u24(0));
+        let mut body = b"WEBP".to_vec();
+        body.extend(chunk(b"VP8X", &vp8x));
+        body.extend(chunk(b"ICCP", &[1, 2, 3]));
+        body.extend(chunk(b"VP8L", &solid_vp8l(2, 1, [9, 8, 7, 255])));
+        body.extend(chunk(b"EXIF", b"II*\0\x08\0\0\0"));
+        body.extend(chunk(b"XMP ", b"<x:xmpmeta/>"));
+        let data = chunk(b"RIFF", &body);
+
+        let mut decoder = WebPDecoder::new(&data[..]).unwrap();
+        assert_eq!(decoder.icc_profile(), Some(vec![1, 2, 3]));
+        assert_eq!(decoder.exif_metadata(), Some(b"II*\0\x08\0\0\0".to_vec()));
+        assert_eq!(decoder.xmp_metadata(), Some(b"<x:xmpmeta/>".to_vec()));
+        let mut buf = vec![0; 2 * 4];
+        decoder.read_image(&mut buf).unwrap();
+        assert_eq!(buf, [9, 8, 7, 255, 9, 8, 7, 255]);
+    }
+
+    #[test]
+    fn animation_frame_outside_canvas() {
+        let data = animation(4, 4, &[SolidFrame::new(2, 0, 4, 4, [0; 4])]);
+        let mut frames = WebPDecoder::new(&data[..]).unwrap().into_frames();
+        assert!(frames.next().unwrap().is_err());
+        assert!(frames.next().is_none());
+    }
+
+    #[test]
+    fn alpha_filters() {
+        let deltas = [10, 1, 2, 3, 4, 5];
+        let mut horizontal = deltas;
+        unfilter_alpha(&mut horizontal, 3, 1);
+        assert_eq!(horizontal, [10, 11, 13, 13, 17, 22]);
+        let mut vertical = deltas;
+        unfilter_alpha(&mut vertical, 3, 2);
+        assert_eq!(vertical, [10, 11, 13, 13, 15, 18]);
+        let mut gradient = deltas;
+        unfilter_alpha(&mut gradient, 3, 3);
+        assert_eq!(gradient, [10, 11, 13, 13, 18, 25]);
+    }
+}
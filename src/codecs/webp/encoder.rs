@@ -0,0 +1,293 @@
+use std::io::Write;
+use std::{error, fmt};
+
+use crate::error::{
+    EncodingError, ImageError, ImageResult, UnsupportedError, UnsupportedErrorKind,
+};
+use crate::{ColorType, ImageEncoder, ImageFormat};
+
+use super::{lossless_encoder, vp8_encoder};
+
+/// The flag of the VP8X chunk marking images with an alpha channel.
+const ALPHA_FLAG: u8 = 0x10;
+
+/// The largest width or height of a WebP image.
+const MAX_DIMENSION: u32 = 16383;
+
+/// Errors that can occur during encoding and saving of a WebP image.
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+enum EncoderError {
+    /// Invalid WebP width.
+    WidthInvalid(u32),
+
+    /// Invalid WebP height.
+    HeightInvalid(u32),
+
+    /// The image is too large for lossy encoding.
+    ImageTooLarge,
+}
+
+impl fmt::Display for EncoderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EncoderError::WidthInvalid(s) => f.write_fmt(format_args!("Invalid WebP width: {}", s)),
+            EncoderError::HeightInvalid(s) => {
+                f.write_fmt(format_args!("Invalid WebP height: {}", s))
+            }
+            EncoderError::ImageTooLarge => f.write_str("Image too large for lossy WebP encoding"),
+        }
+    }
+}
+
+impl From<EncoderError> for ImageError {
+    fn from(e: EncoderError) -> ImageError {
+        ImageError::Encoding(EncodingError::new(ImageFormat::WebP.into(), e))
+    }
+}
+
+impl error::Error for EncoderError {}
+
+/// The compression of an encoded WebP image.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WebPQuality {
+    /// Lossless compression, the image is stored exactly.
+    Lossless,
+
+    /// Lossy compression with a quality between 1 and 100, higher values keep more detail at the
+    /// cost of larger files. An alpha channel is still stored losslessly.
+    Lossy(u8),
+}
+
+impl Default for WebPQuality {
+    fn default() -> Self {
+        WebPQuality::Lossless
+    }
+}
+
+/// WebP encoder.
+pub struct WebPEncoder<W: Write> {
+    writer: W,
+    quality: WebPQuality,
+}
+
+impl<W: Write> WebPEncoder<W> {
+    /// Create a new encoder that writes its output to ```w```, with lossless compression.
+    pub fn new(w: W) -> WebPEncoder<W> {
+        WebPEncoder::new_with_quality(w, WebPQuality::default())
+    }
+
+    /// Create a new encoder that writes its output to ```w```, with the given compression.
+    pub fn new_with_quality(w: W, quality: WebPQuality) -> WebPEncoder<W> {
+        WebPEncoder { writer: w, quality }
+    }
+
+    /// Encodes the image ```buf``` that has dimensions ```width```
+    /// and ```height``` and ```ColorType``` ```color_type```.
+    ///
+    /// The dimensions of the image must be between 1 and 16383 (inclusive) and the color type
+    /// one of 8-bit luma, RGB or BGR with or without alpha, or an error will be returned.
+    pub fn encode(
+        mut self,
+        buf: &[u8],
+        width: u32,
+        height: u32,
+        color_type: ColorType,
+    ) -> ImageResult<()> {
+        if width == 0 || width > MAX_DIMENSION {
+            return Err(EncoderError::WidthInvalid(width).into());
+        }
+        if height == 0 || height > MAX_DIMENSION {
+            return Err(EncoderError::HeightInvalid(height).into());
+        }
+
+        let pixels = to_rgba(buf, color_type)?;
+        let has_alpha = color_type.has_alpha() && pixels.iter().any(|pixel| pixel[3] != 0xFF);
+
+        let mut chunks = Vec::new();
+        match self.quality {
+            WebPQuality::Lossless => {
+                let argb: Vec<u32> = pixels
+                    .iter()
+                    .map(|&[r, g, b, a]| u32::from_be_bytes([a, r, g, b]))
+                    .collect();
+                let data = lossless_encoder::encode(&argb, width, height, has_alpha);
+                write_chunk(&mut chunks, b"VP8L", &data);
+            }
+            WebPQuality::Lossy(quality) => {
+                let rgb: Vec<u8> = pixels
+                    .iter()
+                    .flat_map(|pixel| pixel[..3].to_vec())
+                    .collect();
+                let data = vp8_encoder::encode(&rgb, width, height, quality)
+                    .ok_or(EncoderError::ImageTooLarge)?;
+
+                if has_alpha {
+                    let mut vp8x = [0; 10];
+                    vp8x[0] = ALPHA_FLAG;
+                    vp8x[4..7].copy_from_slice(&(width - 1).to_le_bytes()[..3]);
+                    vp8x[7..10].copy_from_slice(&(height - 1).to_le_bytes()[..3]);
+                    write_chunk(&mut chunks, b"VP8X", &vp8x);
+
+                    // The alpha plane is stored in the green channel of a lossless image,
+                    // without filtering.
+                    let alpha: Vec<u32> = pixels
+                        .iter()
+                        .map(|pixel| u32::from(pixel[3]) << 8)
+                        .collect();
+                    let mut alph = vec![0x01];
+                    alph.extend(lossless_encoder::encode_headerless(&alpha, width, height));
+                    write_chunk(&mut chunks, b"ALPH", &alph);
+                }
+                write_chunk(&mut chunks, b"VP8 ", &data);
+            }
+        }
+
+        self.writer.write_all(b"RIFF")?;
+        self.writer
+            .write_all(&(chunks.len() as u32 + 4).to_le_bytes())?;
+        self.writer.write_all(b"WEBP")?;
+        self.writer.write_all(&chunks)?;
+        Ok(())
+    }
+}
+
+impl<W: Write> ImageEncoder for WebPEncoder<W> {
+    fn write_image(
+        self,
+        buf: &[u8],
+        width: u32,
+        height: u32,
+        color_type: ColorType,
+    ) -> ImageResult<()> {
+        self.encode(buf, width, height, color_type)
+    }
+}
+
+/// Convert the supported color types to RGBA pixels.
+fn to_rgba(buf: &[u8], color_type: ColorType) -> ImageResult<Vec<[u8; 4]>> {
+    let pixels = match color_type {
+        ColorType::L8 => buf.iter().map(|&l| [l, l, l, 0xFF]).collect(),
+        ColorType::La8 => buf.chunks(2).map(|p| [p[0], p[0], p[0], p[1]]).collect(),
+        ColorType::Rgb8 => buf.chunks(3).map(|p| [p[0], p[1], p[2], 0xFF]).collect(),
+        ColorType::Rgba8 => buf.chunks(4).map(|p| [p[0], p[1], p[2], p[3]]).collect(),
+        ColorType::Bgr8 => buf.chunks(3).map(|p| [p[2], p[1], p[0], 0xFF]).collect(),
+        ColorType::Bgra8 => buf.chunks(4).map(|p| [p[2], p[1], p[0], p[3]]).collect(),
+        _ => {
+            return Err(ImageError::Unsupported(
+                UnsupportedError::from_format_and_kind(
+                    ImageFormat::WebP.into(),
+                    UnsupportedErrorKind::Color(color_type.into()),
+                ),
+            ))
+        }
+    };
+    Ok(pixels)
+}
+
+/// Append a RIFF chunk, padded to an even length.
+pub(super) fn write_chunk(out: &mut Vec<u8>, name: &[u8; 4], payload: &[u8]) {
+    out.extend_from_slice(name);
+    out.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    out.extend_from_slice(payload);
+    if payload.len() % 2 != 0 {
+        out.push(0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{WebPEncoder, WebPQuality};
+    use crate::codecs::webp::WebPDecoder;
+    use crate::image::ImageDecoder;
+    use crate::{ColorType, ImageError};
+    use std::io::Cursor;
+
+    fn round_trip(
+        buf: &[u8],
+        width: u32,
+        height: u32,
+        color_type: ColorType,
+        quality: WebPQuality,
+    ) -> (ColorType, Vec<u8>) {
+        let mut encoded = Vec::new();
+        WebPEncoder::new_with_quality(&mut encoded, quality)
+            .encode(buf, width, height, color_type)
+            .unwrap();
+        let decoder = WebPDecoder::new(Cursor::new(encoded)).unwrap();
+        assert_eq!(decoder.dimensions(), (width, height));
+        let color_type = decoder.color_type();
+        let mut decoded = vec![0; decoder.total_bytes() as usize];
+        decoder.read_image(&mut decoded).unwrap();
+        (color_type, decoded)
+    }
+
+    #[test]
+    fn lossless_round_trip() {
+        let (width, height) = (23, 17);
+        let rgba: Vec<u8> = (0..width * height * 4)
+            .map(|i| (i * 7 % 251) as u8)
+            .collect();
+        let (color_type, decoded) = round_trip(
+            &rgba,
+            width,
+            height,
+            ColorType::Rgba8,
+            WebPQuality::Lossless,
+        );
+        assert_eq!(color_type, ColorType::Rgba8);
+        assert_eq!(decoded, rgba);
+
+        let rgb: Vec<u8> = (0..width * height * 3)
+            .map(|i| (i * 13 % 256) as u8)
+            .collect();
+        let (color_type, decoded) =
+            round_trip(&rgb, width, height, ColorType::Rgb8, WebPQuality::Lossless);
+        assert_eq!(color_type, ColorType::Rgb8);
+        assert_eq!(decoded, rgb);
+
+        let luma: Vec<u8> = (0..width * height).map(|i| (i % 200) as u8).collect();
+        let (color_type, decoded) =
+            round_trip(&luma, width, height, ColorType::L8, WebPQuality::Lossless);
+        assert_eq!(color_type, ColorType::Rgb8);
+        let expected: Vec<u8> = luma.iter().flat_map(|&l| vec![l, l, l]).collect();
+        assert_eq!(decoded, expected);
+    }
+
+    #[test]
+    fn lossy_round_trip() {
+        let (width, height) = (30, 20);
+        let luma: Vec<u8> = (0..width * height).map(|i| (i % width * 8) as u8).collect();
+        let (color_type, decoded) =
+            round_trip(&luma, width, height, ColorType::L8, WebPQuality::Lossy(90));
+        assert_eq!(color_type, ColorType::L8);
+        for (&l, &d) in luma.iter().zip(&decoded) {
+            // The luma channel is stored in limited range.
+            let expected = 16 + i32::from(l) * 219 / 255;
+            assert!((expected - i32::from(d)).abs() <= 6, "{} {}", expected, d);
+        }
+
+        let la: Vec<u8> = luma.iter().flat_map(|&l| vec![l, 255 - l]).collect();
+        let (color_type, decoded) =
+            round_trip(&la, width, height, ColorType::La8, WebPQuality::Lossy(90));
+        assert_eq!(color_type, ColorType::La8);
+        for (source, decoded) in la.chunks(2).zip(decoded.chunks(2)) {
+            assert_eq!(source[1], decoded[1]);
+        }
+    }
+
+    #[test]
+    fn invalid_dimensions() {
+        let result = WebPEncoder::new(Vec::new()).encode(&[], 0, 1, ColorType::Rgb8);
+        assert!(matches_encoding(result));
+        let result =
+            WebPEncoder::new(Vec::new()).encode(&[0; 3 * 16384], 16384, 1, ColorType::Rgb8);
+        assert!(matches_encoding(result));
+    }
+
+    fn matches_encoding(result: Result<(), ImageError>) -> bool {
+        match result {
+            Err(ImageError::Encoding(_)) => true,
+            _ => false,
+        }
+    }
+}
@@ -0,0 +1,687 @@
+//! Decoding of lossless WebP images
+//!
+//! Lossless images store ARGB pixels, which are compressed with a set of reversible transforms,
+//! backward references and prefix codes. The same format without the header is used for the
+//! compressed alpha channel of lossy images.
+//!
+//! # Related Links
+//! * [WebP Lossless Bitstream](https://developers.google.com/speed/webp/docs/webp_lossless_bitstream_specification)
+//!
+
+use std::{error, fmt};
+
+use crate::error::{DecodingError, ImageError, ImageResult};
+use crate::image::ImageFormat;
+
+pub(crate) const SIGNATURE: u8 = 0x2f;
+
+pub(crate) const GREEN: usize = 0;
+pub(crate) const RED: usize = 1;
+pub(crate) const BLUE: usize = 2;
+pub(crate) const ALPHA: usize = 3;
+pub(crate) const DISTANCE: usize = 4;
+
+pub(crate) const NUM_LITERAL_CODES: u16 = 256;
+pub(crate) const NUM_LENGTH_CODES: u16 = 24;
+pub(crate) const NUM_DISTANCE_CODES: u16 = 40;
+pub(crate) const MAX_CODE_LENGTH: usize = 15;
+
+pub(crate) static CODE_LENGTH_CODE_ORDER: [usize; 19] = [
+    17, 18, 0, 1, 2, 3, 4, 5, 16, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15,
+];
+
+/// The offsets `(x, y)` of the first 120 distance codes.
+#[rustfmt::skip]
+pub(crate) static DISTANCE_MAP: [(i8, i8); 120] = [
+    (0, 1),  (1, 0),  (1, 1),  (-1, 1), (0, 2),  (2, 0),  (1, 2),  (-1, 2),
+    (2, 1),  (-2, 1), (2, 2),  (-2, 2), (0, 3),  (3, 0),  (1, 3),  (-1, 3),
+    (3, 1),  (-3, 1), (2, 3),  (-2, 3), (3, 2),  (-3, 2), (0, 4),  (4, 0),
+    (1, 4),  (-1, 4), (4, 1),  (-4, 1), (3, 3),  (-3, 3), (2, 4),  (-2, 4),
+    (4, 2),  (-4, 2), (0, 5),  (3, 4),  (-3, 4), (4, 3),  (-4, 3), (5, 0),
+    (1, 5),  (-1, 5), (5, 1),  (-5, 1), (2, 5),  (-2, 5), (5, 2),  (-5, 2),
+    (4, 4),  (-4, 4), (3, 5),  (-3, 5), (5, 3),  (-5, 3), (0, 6),  (6, 0),
+    (1, 6),  (-1, 6), (6, 1),  (-6, 1), (2, 6),  (-2, 6), (6, 2),  (-6, 2),
+    (4, 5),  (-4, 5), (5, 4),  (-5, 4), (3, 6),  (-3, 6), (6, 3),  (-6, 3),
+    (0, 7),  (7, 0),  (1, 7),  (-1, 7), (5, 5),  (-5, 5), (7, 1),  (-7, 1),
+    (4, 6),  (-4, 6), (6, 4),  (-6, 4), (2, 7),  (-2, 7), (7, 2),  (-7, 2),
+    (3, 7),  (-3, 7), (7, 3),  (-7, 3), (5, 6),  (-5, 6), (6, 5),  (-6, 5),
+    (8, 0),  (4, 7),  (-4, 7), (7, 4),  (-7, 4), (8, 1),  (8, 2),  (6, 6),
+    (-6, 6), (8, 3),  (5, 7),  (-5, 7), (7, 5),  (-7, 5), (8, 4),  (6, 7),
+    (-6, 7), (7, 6),  (-7, 6), (8, 5),  (7, 7),  (-7, 7), (8, 6),  (8, 7),
+];
+
+/// All errors that can occur when decoding a lossless WebP image
+#[derive(Debug, Clone, Copy)]
+enum DecoderError {
+    /// The lossless signature `0x2f` was not found
+    LosslessSignatureInvalid(u8),
+    /// Only version 0 of the format is specified
+    VersionNumberInvalid(u8),
+    /// The data ended before the image was complete
+    BitStreamEnded,
+    /// The code lengths of a prefix code do not describe a valid code
+    PrefixCodeInvalid,
+    /// A transform appears more than once
+    TransformRepeated(u8),
+    /// The number of bits of the color cache is out of range
+    ColorCacheBitsInvalid(u8),
+    /// A symbol refers to a color cache entry that does not exist
+    ColorCacheIndexInvalid,
+    /// A backward reference reaches before the start or past the end of the image
+    BackwardReferenceInvalid,
+}
+
+impl fmt::Display for DecoderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecoderError::LosslessSignatureInvalid(sig) => {
+                f.write_fmt(format_args!("Invalid lossless signature: {:#04X?}", sig))
+            }
+            DecoderError::VersionNumberInvalid(version) => {
+                f.write_fmt(format_args!("Invalid lossless version number {}", version))
+            }
+            DecoderError::BitStreamEnded => f.write_str("Unexpected end of lossless image data"),
+            DecoderError::PrefixCodeInvalid => f.write_str("Invalid prefix code"),
+            DecoderError::TransformRepeated(kind) => {
+                f.write_fmt(format_args!("Transform {} is used more than once", kind))
+            }
+            DecoderError::ColorCacheBitsInvalid(bits) => {
+                f.write_fmt(format_args!("Invalid color cache bits {}", bits))
+            }
+            DecoderError::ColorCacheIndexInvalid => f.write_str("Invalid color cache index"),
+            DecoderError::BackwardReferenceInvalid => f.write_str("Invalid backward reference"),
+        }
+    }
+}
+
+impl From<DecoderError> for ImageError {
+    fn from(e: DecoderError) -> ImageError {
+        ImageError::Decoding(DecodingError::new(ImageFormat::WebP.into(), e))
+    }
+}
+
+impl error::Error for DecoderError {}
+
+/// Decode a lossless image, returns its width, height and ARGB pixels.
+pub(crate) fn decode(data: &[u8]) -> ImageResult<(u32, u32, Vec<u32>)> {
+    let mut reader = BitReader::new(data);
+    let signature = reader.read_bits(8)? as u8;
+    if signature != SIGNATURE {
+        return Err(DecoderError::LosslessSignatureInvalid(signature).into());
+    }
+
+    let width = reader.read_bits(14)? + 1;
+    let height = reader.read_bits(14)? + 1;
+    // Whether the alpha channel is used is only a hint.
+    let _alpha = reader.read_bits(1)?;
+    let version = reader.read_bits(3)? as u8;
+    if version != 0 {
+        return Err(DecoderError::VersionNumberInvalid(version).into());
+    }
+
+    let pixels = decode_image_data(&mut reader, width, height)?;
+    Ok((width, height, pixels))
+}
+
+/// Decode image data without a header, as stored in alpha chunks. The alpha values are the
+/// green channel of the pixels.
+pub(crate) fn decode_headerless(data: &[u8], width: u32, height: u32) -> ImageResult<Vec<u32>> {
+    decode_image_data(&mut BitReader::new(data), width, height)
+}
+
+enum Transform {
+    Predictor { bits: u8, width: u32, data: Vec<u32> },
+    Color { bits: u8, width: u32, data: Vec<u32> },
+    SubtractGreen,
+    ColorIndexing { bits: u8, width: u32, table: Vec<u32> },
+}
+
+impl Transform {
+    fn kind(&self) -> u8 {
+        match self {
+            Transform::Predictor { .. } => 0,
+            Transform::Color { .. } => 1,
+            Transform::SubtractGreen => 2,
+            Transform::ColorIndexing { .. } => 3,
+        }
+    }
+}
+
+fn decode_image_data(reader: &mut BitReader<'_>, width: u32, height: u32) -> ImageResult<Vec<u32>> {
+    let mut transforms: Vec<Transform> = Vec::new();
+    // The color indexing transform packs several pixels into one.
+    let mut xsize = width;
+
+    while reader.read_bits(1)? == 1 {
+        let kind = reader.read_bits(2)? as u8;
+        if transforms.iter().any(|t| t.kind() == kind) {
+            return Err(DecoderError::TransformRepeated(kind).into());
+        }
+
+        let transform = match kind {
+            0 | 1 => {
+                let bits = reader.read_bits(3)? as u8 + 2;
+                let data = decode_image_stream(
+                    reader,
+                    subsample_size(xsize, bits),
+                    subsample_size(height, bits),
+                    false,
+                )?;
+                if kind == 0 {
+                    Transform::Predictor { bits, width: xsize, data }
+                } else {
+                    Transform::Color { bits, width: xsize, data }
+                }
+            }
+            2 => Transform::SubtractGreen,
+            _ => {
+                let size = reader.read_bits(8)? + 1;
+                let mut table = decode_image_stream(reader, size, 1, false)?;
+                // The entries are stored as differences to the previous one.
+                for i in 1..table.len() {
+                    table[i] = add_pixels(table[i], table[i - 1]);
+                }
+                let bits = match size {
+                    0..=2 => 3,
+                    3..=4 => 2,
+                    5..=16 => 1,
+                    _ => 0,
+                };
+                let transform = Transform::ColorIndexing { bits, width: xsize, table };
+                xsize = subsample_size(xsize, bits);
+                transform
+            }
+        };
+        transforms.push(transform);
+    }
+
+    let mut pixels = decode_image_stream(reader, xsize, height, true)?;
+    for transform in transforms.iter().rev() {
+        match transform {
+            Transform::Predictor { bits, width, data } => {
+                apply_predictor_transform(&mut pixels, *width, *bits, data)
+            }
+            Transform::Color { bits, width, data } => {
+                apply_color_transform(&mut pixels, *width, *bits, data)
+            }
+            Transform::SubtractGreen => apply_subtract_green_transform(&mut pixels),
+            Transform::ColorIndexing { bits, width, table } => {
+                pixels = apply_color_indexing_transform(&pixels, *width, height, *bits, table)
+            }
+        }
+    }
+
+    Ok(pixels)
+}
+
+/// Decode the entropy coded pixels of an image. Only the main image may use a color cache with
+/// several groups of prefix codes.
+fn decode_image_stream(
+    reader: &mut BitReader<'_>,
+    xsize: u32,
+    ysize: u32,
+    is_argb_image: bool,
+) -> ImageResult<Vec<u32>> {
+    let cache_bits = if reader.read_bits(1)? == 1 {
+        let bits = reader.read_bits(4)? as u8;
+        if bits < 1 || bits > 11 {
+            return Err(DecoderError::ColorCacheBitsInvalid(bits).into());
+        }
+        Some(bits)
+    } else {
+        None
+    };
+    let cache_size = cache_bits.map_or(0, |bits| 1u16 << bits);
+
+    let mut entropy_image = None;
+    if is_argb_image && reader.read_bits(1)? == 1 {
+        let bits = reader.read_bits(3)? as u8 + 2;
+        let width = subsample_size(xsize, bits);
+        let data = decode_image_stream(reader, width, subsample_size(ysize, bits), false)?;
+        entropy_image = Some((bits, width, data));
+    }
+
+    let num_groups = match entropy_image {
+        Some((_, _, ref data)) => data.iter().map(|p| (p >> 8) & 0xffff).max().unwrap_or(0) + 1,
+        None => 1,
+    };
+    let mut groups = Vec::with_capacity(num_groups as usize);
+    for _ in 0..num_groups {
+        groups.push([
+            read_prefix_code(reader, NUM_LITERAL_CODES + NUM_LENGTH_CODES + cache_size)?,
+            read_prefix_code(reader, NUM_LITERAL_CODES)?,
+            read_prefix_code(reader, NUM_LITERAL_CODES)?,
+            read_prefix_code(reader, NUM_LITERAL_CODES)?,
+            read_prefix_code(reader, NUM_DISTANCE_CODES)?,
+        ]);
+    }
+
+    let total = xsize as usize * ysize as usize;
+    let mut pixels = Vec::with_capacity(total);
+    let mut cache = vec![0u32; usize::from(cache_size)];
+    // The number of pixels already added to the color cache.
+    let mut cached = 0;
+
+    while pixels.len() < total {
+        let group = match entropy_image {
+            Some((bits, width, ref data)) => {
+                let x = (pixels.len() % xsize as usize) >> bits;
+                let y = (pixels.len() / xsize as usize) >> bits;
+                let index = (data[y * width as usize + x] >> 8) & 0xffff;
+                &groups[index as usize]
+            }
+            None => &groups[0],
+        };
+
+        let code = group[GREEN].read_symbol(reader)?;
+        if code < NUM_LITERAL_CODES {
+            let red = group[RED].read_symbol(reader)?;
+            let blue = group[BLUE].read_symbol(reader)?;
+            let alpha = group[ALPHA].read_symbol(reader)?;
+            pixels.push(
+                (u32::from(alpha) << 24)
+                    | (u32::from(red) << 16)
+                    | (u32::from(code) << 8)
+                    | u32::from(blue),
+            );
+        } else if code < NUM_LITERAL_CODES + NUM_LENGTH_CODES {
+            let length = read_lz77_value(reader, code - NUM_LITERAL_CODES)? as usize;
+            let distance_symbol = group[DISTANCE].read_symbol(reader)?;
+            let distance_code = read_lz77_value(reader, distance_symbol)?;
+            let distance = plane_code_to_distance(xsize, distance_code);
+
+            if distance > pixels.len() || pixels.len() + length > total {
+                return Err(DecoderError::BackwardReferenceInvalid.into());
+            }
+            for _ in 0..length {
+                let pixel = pixels[pixels.len() - distance];
+                pixels.push(pixel);
+            }
+        } else {
+            let index = usize::from(code - NUM_LITERAL_CODES - NUM_LENGTH_CODES);
+            // The cache holds the most recent color of each hash, it is only updated when it is
+            // needed.
+            for &pixel in &pixels[cached..] {
+                cache[color_cache_index(pixel, cache_bits.unwrap())] = pixel;
+            }
+            cached = pixels.len();
+            let pixel = *cache.get(index).ok_or(DecoderError::ColorCacheIndexInvalid)?;
+            pixels.push(pixel);
+        }
+    }
+
+    Ok(pixels)
+}
+
+fn color_cache_index(pixel: u32, bits: u8) -> usize {
+    (0x1e35_a7bd_u32.wrapping_mul(pixel) >> (32 - bits)) as usize
+}
+
+/// Read the length or distance code of a backward reference from its prefix symbol.
+fn read_lz77_value(reader: &mut BitReader<'_>, prefix: u16) -> ImageResult<u32> {
+    if prefix < 4 {
+        return Ok(u32::from(prefix) + 1);
+    }
+    let extra_bits = (prefix - 2) >> 1;
+    let offset = (2 + u32::from(prefix & 1)) << extra_bits;
+    Ok(offset + reader.read_bits(extra_bits as u8)? + 1)
+}
+
+fn plane_code_to_distance(xsize: u32, code: u32) -> usize {
+    if code > 120 {
+        (code - 120) as usize
+    } else {
+        let (x, y) = DISTANCE_MAP[code as usize - 1];
+        let distance = i64::from(x) + i64::from(y) * i64::from(xsize);
+        if distance < 1 {
+            1
+        } else {
+            distance as usize
+        }
+    }
+}
+
+fn read_prefix_code(reader: &mut BitReader<'_>, alphabet_size: u16) -> ImageResult<PrefixCode> {
+    let alphabet_size = usize::from(alphabet_size);
+    let mut lengths = vec![0u8; alphabet_size];
+
+    if reader.read_bits(1)? == 1 {
+        // A simple code of one or two symbols.
+        let num_symbols = reader.read_bits(1)? + 1;
+        let first_bits = if reader.read_bits(1)? == 1 { 8 } else { 1 };
+        let mut symbols = vec![reader.read_bits(first_bits)? as usize];
+        if num_symbols == 2 {
+            symbols.push(reader.read_bits(8)? as usize);
+        }
+        for symbol in symbols {
+            *lengths.get_mut(symbol).ok_or(DecoderError::PrefixCodeInvalid)? = 1;
+        }
+        return PrefixCode::from_lengths(&lengths);
+    }
+
+    // The code lengths are themselves prefix coded.
+    let mut code_length_lengths = [0u8; 19];
+    let num_code_lengths = reader.read_bits(4)? as usize + 4;
+    for &index in &CODE_LENGTH_CODE_ORDER[..num_code_lengths] {
+        code_length_lengths[index] = reader.read_bits(3)? as u8;
+    }
+    let code_length_code = PrefixCode::from_lengths(&code_length_lengths)?;
+
+    let mut max_symbol = if reader.read_bits(1)? == 1 {
+        let length_bits = 2 + 2 * reader.read_bits(3)? as u8;
+        let max_symbol = 2 + reader.read_bits(length_bits)? as usize;
+        if max_symbol > alphabet_size {
+            return Err(DecoderError::PrefixCodeInvalid.into());
+        }
+        max_symbol
+    } else {
+        alphabet_size
+    };
+
+    let mut previous_length = 8;
+    let mut symbol = 0;
+    while symbol < alphabet_size && max_symbol > 0 {
+        max_symbol -= 1;
+        let code_length = code_length_code.read_symbol(reader)? as u8;
+        if code_length < 16 {
+            lengths[symbol] = code_length;
+            symbol += 1;
+            if code_length != 0 {
+                previous_length = code_length;
+            }
+            continue;
+        }
+
+        let (extra_bits, offset, length) = match code_length {
+            16 => (2, 3, previous_length),
+            17 => (3, 3, 0),
+            _ => (7, 11, 0),
+        };
+        let repeat = offset + reader.read_bits(extra_bits)? as usize;
+        if symbol + repeat > alphabet_size {
+            return Err(DecoderError::PrefixCodeInvalid.into());
+        }
+        for length_slot in &mut lengths[symbol..symbol + repeat] {
+            *length_slot = length;
+        }
+        symbol += repeat;
+    }
+
+    PrefixCode::from_lengths(&lengths)
+}
+
+/// A canonical prefix code, decoded one bit at a time.
+struct PrefixCode {
+    /// The number of codes of each length.
+    counts: [u16; MAX_CODE_LENGTH + 1],
+    /// The symbols in the order of their codes.
+    symbols: Vec<u16>,
+}
+
+impl PrefixCode {
+    fn from_lengths(lengths: &[u8]) -> ImageResult<PrefixCode> {
+        let mut counts = [0u16; MAX_CODE_LENGTH + 1];
+        for &length in lengths {
+            if usize::from(length) > MAX_CODE_LENGTH {
+                return Err(DecoderError::PrefixCodeInvalid.into());
+            }
+            counts[usize::from(length)] += 1;
+        }
+        counts[0] = 0;
+
+        let mut symbols = Vec::new();
+        for length in 1..=MAX_CODE_LENGTH {
+            for (symbol, &l) in lengths.iter().enumerate() {
+                if usize::from(l) == length {
+                    symbols.push(symbol as u16);
+                }
+            }
+        }
+
+        // A single symbol is coded with zero bits.
+        if symbols.len() == 1 {
+            return Ok(PrefixCode { counts: [0; MAX_CODE_LENGTH + 1], symbols });
+        }
+
+        // Otherwise the code has to be complete.
+        let mut left = 1i32;
+        for &count in &counts[1..] {
+            left = (left << 1) - i32::from(count);
+            if left < 0 {
+                return Err(DecoderError::PrefixCodeInvalid.into());
+            }
+        }
+        if left != 0 {
+            return Err(DecoderError::PrefixCodeInvalid.into());
+        }
+
+        Ok(PrefixCode { counts, symbols })
+    }
+
+    fn read_symbol(&self, reader: &mut BitReader<'_>) -> ImageResult<u16> {
+        if self.symbols.len() == 1 {
+            return Ok(self.symbols[0]);
+        }
+
+        let mut code = 0i32;
+        let mut first = 0i32;
+        let mut index = 0i32;
+        for &count in &self.counts[1..] {
+            code |= reader.read_bits(1)? as i32;
+            let count = i32::from(count);
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+
+        Err(DecoderError::PrefixCodeInvalid.into())
+    }
+}
+
+/// Reads bits starting with the least significant bit of each byte.
+struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+    buffer: u64,
+    nbits: u8,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        BitReader { data, position: 0, buffer: 0, nbits: 0 }
+    }
+
+    fn read_bits(&mut self, n: u8) -> ImageResult<u32> {
+        debug_assert!(n <= 32);
+        while self.nbits < n {
+            let byte = *self
+                .data
+                .get(self.position)
+                .ok_or(DecoderError::BitStreamEnded)?;
+            self.buffer |= u64::from(byte) << self.nbits;
+            self.position += 1;
+            self.nbits += 8;
+        }
+
+        let value = (self.buffer & ((1u64 << n) - 1)) as u32;
+        self.buffer >>= n;
+        self.nbits -= n;
+        Ok(value)
+    }
+}
+
+pub(crate) fn subsample_size(size: u32, bits: u8) -> u32 {
+    (size + (1 << bits) - 1) >> bits
+}
+
+/// Add each channel of two pixels, modulo 256.
+pub(crate) fn add_pixels(a: u32, b: u32) -> u32 {
+    let alpha_green = (a & 0xff00_ff00).wrapping_add(b & 0xff00_ff00);
+    let red_blue = (a & 0x00ff_00ff).wrapping_add(b & 0x00ff_00ff);
+    (alpha_green & 0xff00_ff00) | (red_blue & 0x00ff_00ff)
+}
+
+fn channels(pixel: u32) -> [i32; 4] {
+    [
+        (pixel >> 24) as i32,
+        ((pixel >> 16) & 0xff) as i32,
+        ((pixel >> 8) & 0xff) as i32,
+        (pixel & 0xff) as i32,
+    ]
+}
+
+fn from_channels(channels: [i32; 4]) -> u32 {
+    channels
+        .iter()
+        .fold(0, |pixel, &c| (pixel << 8) | (c as u32 & 0xff))
+}
+
+fn average2(a: u32, b: u32) -> u32 {
+    (((a ^ b) & 0xfefe_fefe) >> 1) + (a & b)
+}
+
+fn select(left: u32, top: u32, top_left: u32) -> u32 {
+    let (l, t, tl) = (channels(left), channels(top), channels(top_left));
+    let mut distance_left = 0;
+    let mut distance_top = 0;
+    for i in 0..4 {
+        let estimate = l[i] + t[i] - tl[i];
+        distance_left += (estimate - l[i]).abs();
+        distance_top += (estimate - t[i]).abs();
+    }
+    if distance_left < distance_top {
+        left
+    } else {
+        top
+    }
+}
+
+fn clamp_add_subtract_full(a: u32, b: u32, c: u32) -> u32 {
+    let (a, b, c) = (channels(a), channels(b), channels(c));
+    let mut result = [0; 4];
+    for i in 0..4 {
+        result[i] = clamp_channel(a[i] + b[i] - c[i]);
+    }
+    from_channels(result)
+}
+
+fn clamp_add_subtract_half(a: u32, b: u32) -> u32 {
+    let (a, b) = (channels(a), channels(b));
+    let mut result = [0; 4];
+    for i in 0..4 {
+        result[i] = clamp_channel(a[i] + (a[i] - b[i]) / 2);
+    }
+    from_channels(result)
+}
+
+fn clamp_channel(value: i32) -> i32 {
+    if value < 0 {
+        0
+    } else if value > 255 {
+        255
+    } else {
+        value
+    }
+}
+
+pub(crate) fn predict(mode: u32, left: u32, top: u32, top_left: u32, top_right: u32) -> u32 {
+    match mode {
+        1 => left,
+        2 => top,
+        3 => top_right,
+        4 => top_left,
+        5 => average2(average2(left, top_right), top),
+        6 => average2(left, top_left),
+        7 => average2(left, top),
+        8 => average2(top_left, top),
+        9 => average2(top, top_right),
+        10 => average2(average2(left, top_left), average2(top, top_right)),
+        11 => select(left, top, top_left),
+        12 => clamp_add_subtract_full(left, top, top_left),
+        13 => clamp_add_subtract_half(average2(left, top), top_left),
+        // Mode 0, the unspecified modes 14 and 15 behave the same.
+        _ => 0xff00_0000,
+    }
+}
+
+fn apply_predictor_transform(pixels: &mut [u32], width: u32, bits: u8, modes: &[u32]) {
+    let width = width as usize;
+    let blocks_per_row = subsample_size(width as u32, bits) as usize;
+
+    for i in 0..pixels.len() {
+        let (x, y) = (i % width, i / width);
+        let prediction = if y == 0 {
+            if x == 0 {
+                0xff00_0000
+            } else {
+                pixels[i - 1]
+            }
+        } else if x == 0 {
+            pixels[i - width]
+        } else {
+            let mode = (modes[(y >> bits) * blocks_per_row + (x >> bits)] >> 8) & 0xf;
+            // For the last column this is the first pixel of the current row.
+            let top_right = pixels[i - width + 1];
+            predict(mode, pixels[i - 1], pixels[i - width], pixels[i - width - 1], top_right)
+        };
+        pixels[i] = add_pixels(pixels[i], prediction);
+    }
+}
+
+fn color_transform_delta(transform: u8, color: u8) -> u8 {
+    ((i32::from(transform as i8) * i32::from(color as i8)) >> 5) as u8
+}
+
+fn apply_color_transform(pixels: &mut [u32], width: u32, bits: u8, elements: &[u32]) {
+    let width = width as usize;
+    let blocks_per_row = subsample_size(width as u32, bits) as usize;
+
+    for (i, pixel) in pixels.iter_mut().enumerate() {
+        let (x, y) = (i % width, i / width);
+        let element = elements[(y >> bits) * blocks_per_row + (x >> bits)];
+        let green_to_red = element as u8;
+        let green_to_blue = (element >> 8) as u8;
+        let red_to_blue = (element >> 16) as u8;
+
+        let green = (*pixel >> 8) as u8;
+        let red = ((*pixel >> 16) as u8).wrapping_add(color_transform_delta(green_to_red, green));
+        let blue = (*pixel as u8)
+            .wrapping_add(color_transform_delta(green_to_blue, green))
+            .wrapping_add(color_transform_delta(red_to_blue, red));
+        *pixel = (*pixel & 0xff00_ff00) | (u32::from(red) << 16) | u32::from(blue);
+    }
+}
+
+fn apply_subtract_green_transform(pixels: &mut [u32]) {
+    for pixel in pixels {
+        let green = (*pixel >> 8) & 0xff;
+        *pixel = add_pixels(*pixel, (green << 16) | green);
+    }
+}
+
+fn apply_color_indexing_transform(
+    packed: &[u32],
+    width: u32,
+    height: u32,
+    bits: u8,
+    table: &[u32],
+) -> Vec<u32> {
+    let packed_width = subsample_size(width, bits) as usize;
+    let bits_per_index = 8 >> bits;
+    let index_mask = (1 << bits_per_index) - 1;
+    let x_mask = (1 << bits) - 1;
+
+    let mut pixels = Vec::with_capacity(width as usize * height as usize);
+    for y in 0..height as usize {
+        for x in 0..width as usize {
+            let indices = (packed[y * packed_width + (x >> bits)] >> 8) & 0xff;
+            let index = (indices >> ((x & x_mask) * bits_per_index)) & index_mask;
+            // Indices past the end of the table are transparent black.
+            pixels.push(table.get(index as usize).cloned().unwrap_or(0));
+        }
+    }
+    pixels
+}
@@ -0,0 +1,678 @@
+//! Encoding of lossless WebP images
+//!
+//! Images with at most 256 colors are stored with a color table, other images with the subtract
+//! green and predictor transforms. The pixels are then compressed with backward references and
+//! one group of prefix codes.
+//!
+//! # Related Links
+//! * [WebP Lossless Bitstream](https://developers.google.com/speed/webp/docs/webp_lossless_bitstream_specification)
+//!
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use super::lossless::{
+    predict, subsample_size, ALPHA, BLUE, CODE_LENGTH_CODE_ORDER, DISTANCE, DISTANCE_MAP, GREEN,
+    MAX_CODE_LENGTH, NUM_DISTANCE_CODES, NUM_LENGTH_CODES, NUM_LITERAL_CODES, RED, SIGNATURE,
+};
+
+/// The size of the tiles sharing one predictor, as a power of two.
+const PREDICTOR_BITS: u8 = 4;
+
+const HASH_BITS: u32 = 16;
+const MIN_MATCH_LENGTH: usize = 3;
+const MAX_MATCH_LENGTH: usize = 4096;
+const MAX_CHAIN_LENGTH: usize = 32;
+/// The largest distance that can be coded, leaving room for the 120 plane codes.
+const MAX_DISTANCE: usize = (1 << 20) - 120;
+
+const MAX_CODE_LENGTH_CODE_LENGTH: u8 = 7;
+
+/// Encode a lossless image with its header, from ARGB pixels. The dimensions have to be between
+/// 1 and 16384.
+pub(crate) fn encode(pixels: &[u32], width: u32, height: u32, alpha: bool) -> Vec<u8> {
+    let mut writer = BitWriter::new();
+    writer.write(u32::from(SIGNATURE), 8);
+    writer.write(width - 1, 14);
+    writer.write(height - 1, 14);
+    writer.write(alpha as u32, 1);
+    // Version
+    writer.write(0, 3);
+    encode_image_data(&mut writer, pixels, width, height);
+    writer.finish()
+}
+
+/// Encode image data without a header, as stored in alpha chunks.
+pub(crate) fn encode_headerless(pixels: &[u32], width: u32, height: u32) -> Vec<u8> {
+    let mut writer = BitWriter::new();
+    encode_image_data(&mut writer, pixels, width, height);
+    writer.finish()
+}
+
+fn encode_image_data(writer: &mut BitWriter, pixels: &[u32], width: u32, height: u32) {
+    let mut palette = pixels.to_vec();
+    palette.sort_unstable();
+    palette.dedup();
+
+    let (xsize, pixels) = if palette.len() <= 256 {
+        // Color indexing transform
+        writer.write(1, 1);
+        writer.write(3, 2);
+        writer.write(palette.len() as u32 - 1, 8);
+        let mut deltas = palette.clone();
+        for i in (1..deltas.len()).rev() {
+            deltas[i] = sub_pixels(deltas[i], deltas[i - 1]);
+        }
+        write_image_stream(writer, &deltas, palette.len() as u32, false);
+
+        let bits = match palette.len() {
+            0..=2 => 3,
+            3..=4 => 2,
+            5..=16 => 1,
+            _ => 0,
+        };
+        (
+            subsample_size(width, bits),
+            pack_indices(pixels, width, &palette, bits),
+        )
+    } else {
+        // Subtract green transform
+        writer.write(1, 1);
+        writer.write(2, 2);
+        let pixels: Vec<u32> = pixels.iter().map(|&p| subtract_green(p)).collect();
+
+        // Predictor transform
+        writer.write(1, 1);
+        writer.write(0, 2);
+        writer.write(u32::from(PREDICTOR_BITS - 2), 3);
+        let modes = choose_predictors(&pixels, width, height);
+        write_image_stream(writer, &modes, subsample_size(width, PREDICTOR_BITS), false);
+        (width, predict_residuals(&pixels, width, &modes))
+    };
+
+    // No more transforms.
+    writer.write(0, 1);
+    write_image_stream(writer, &pixels, xsize, true);
+}
+
+/// Subtract each channel of two pixels, modulo 256.
+fn sub_pixels(a: u32, b: u32) -> u32 {
+    let alpha_green = (a | 0x00ff_00ff).wrapping_sub(b & 0xff00_ff00);
+    let red_blue = (a | 0xff00_ff00).wrapping_sub(b & 0x00ff_00ff);
+    (alpha_green & 0xff00_ff00) | (red_blue & 0x00ff_00ff)
+}
+
+fn subtract_green(pixel: u32) -> u32 {
+    let green = (pixel >> 8) & 0xff;
+    sub_pixels(pixel, (green << 16) | green)
+}
+
+/// Replace the pixels by their indices into the palette, packing several into one pixel if the
+/// palette is small.
+fn pack_indices(pixels: &[u32], width: u32, palette: &[u32], bits: u8) -> Vec<u32> {
+    let width = width as usize;
+    let packed_width = subsample_size(width as u32, bits) as usize;
+    let bits_per_index = 8 >> bits;
+    let x_mask = (1 << bits) - 1;
+
+    let mut packed = vec![0xff00_0000; packed_width * (pixels.len() / width)];
+    for (i, pixel) in pixels.iter().enumerate() {
+        let (x, y) = (i % width, i / width);
+        let index = palette.binary_search(pixel).unwrap() as u32;
+        packed[y * packed_width + (x >> bits)] |= index << (8 + (x & x_mask) * bits_per_index);
+    }
+    packed
+}
+
+/// The prediction of a pixel for the inverse predictor transform, given the mode of its tile.
+fn prediction(pixels: &[u32], width: usize, i: usize, mode: u32) -> u32 {
+    let (x, y) = (i % width, i / width);
+    if y == 0 {
+        if x == 0 {
+            0xff00_0000
+        } else {
+            pixels[i - 1]
+        }
+    } else if x == 0 {
+        pixels[i - width]
+    } else {
+        let top_right = pixels[i - width + 1];
+        predict(
+            mode,
+            pixels[i - 1],
+            pixels[i - width],
+            pixels[i - width - 1],
+            top_right,
+        )
+    }
+}
+
+/// Choose the predictor of each tile with the smallest residuals. The modes are stored in the
+/// green channel.
+fn choose_predictors(pixels: &[u32], width: u32, height: u32) -> Vec<u32> {
+    let tiles_x = subsample_size(width, PREDICTOR_BITS) as usize;
+    let tiles_y = subsample_size(height, PREDICTOR_BITS) as usize;
+    let (width, height) = (width as usize, height as usize);
+    let tile = 1 << PREDICTOR_BITS;
+
+    let mut modes = Vec::with_capacity(tiles_x * tiles_y);
+    for ty in 0..tiles_y {
+        for tx in 0..tiles_x {
+            let best = (0..14)
+                .min_by_key(|&mode| {
+                    let mut cost = 0u32;
+                    for y in ty * tile..height.min((ty + 1) * tile) {
+                        for x in tx * tile..width.min((tx + 1) * tile) {
+                            let i = y * width + x;
+                            let residual =
+                                sub_pixels(pixels[i], prediction(pixels, width, i, mode));
+                            cost += residual
+                                .to_le_bytes()
+                                .iter()
+                                .map(|&c| u32::from((c as i8).wrapping_abs() as u8))
+                                .sum::<u32>();
+                        }
+                    }
+                    cost
+                })
+                .unwrap();
+            modes.push(0xff00_0000 | (best << 8));
+        }
+    }
+    modes
+}
+
+fn predict_residuals(pixels: &[u32], width: u32, modes: &[u32]) -> Vec<u32> {
+    let tiles_x = subsample_size(width, PREDICTOR_BITS) as usize;
+    let width = width as usize;
+    (0..pixels.len())
+        .map(|i| {
+            let (x, y) = (i % width, i / width);
+            let mode = (modes[(y >> PREDICTOR_BITS) * tiles_x + (x >> PREDICTOR_BITS)] >> 8) & 0xf;
+            sub_pixels(pixels[i], prediction(pixels, width, i, mode))
+        })
+        .collect()
+}
+
+#[derive(Clone, Copy)]
+enum Symbol {
+    Literal(u32),
+    BackwardReference { length: usize, distance_code: u32 },
+}
+
+/// Write the entropy coded pixels of an image, without a color cache and with a single group of
+/// prefix codes.
+fn write_image_stream(writer: &mut BitWriter, pixels: &[u32], xsize: u32, is_argb_image: bool) {
+    // No color cache
+    writer.write(0, 1);
+    if is_argb_image {
+        // No meta prefix codes
+        writer.write(0, 1);
+    }
+
+    let symbols = backward_references(pixels, xsize);
+
+    let mut histograms = [
+        vec![0u32; usize::from(NUM_LITERAL_CODES + NUM_LENGTH_CODES)],
+        vec![0u32; usize::from(NUM_LITERAL_CODES)],
+        vec![0u32; usize::from(NUM_LITERAL_CODES)],
+        vec![0u32; usize::from(NUM_LITERAL_CODES)],
+        vec![0u32; usize::from(NUM_DISTANCE_CODES)],
+    ];
+    for symbol in &symbols {
+        match *symbol {
+            Symbol::Literal(pixel) => {
+                let [alpha, red, green, blue] = pixel.to_be_bytes();
+                histograms[GREEN][usize::from(green)] += 1;
+                histograms[RED][usize::from(red)] += 1;
+                histograms[BLUE][usize::from(blue)] += 1;
+                histograms[ALPHA][usize::from(alpha)] += 1;
+            }
+            Symbol::BackwardReference {
+                length,
+                distance_code,
+            } => {
+                let (length_prefix, _, _) = prefix_encode(length as u32);
+                let (distance_prefix, _, _) = prefix_encode(distance_code);
+                histograms[GREEN][usize::from(NUM_LITERAL_CODES) + length_prefix] += 1;
+                histograms[DISTANCE][distance_prefix] += 1;
+            }
+        }
+    }
+
+    let codes: Vec<PrefixCode> = histograms
+        .iter()
+        .map(|histogram| PrefixCode::new(histogram, MAX_CODE_LENGTH as u8))
+        .collect();
+    for code in &codes {
+        code.write_header(writer);
+    }
+
+    for symbol in symbols {
+        match symbol {
+            Symbol::Literal(pixel) => {
+                let [alpha, red, green, blue] = pixel.to_be_bytes();
+                codes[GREEN].write_symbol(writer, usize::from(green));
+                codes[RED].write_symbol(writer, usize::from(red));
+                codes[BLUE].write_symbol(writer, usize::from(blue));
+                codes[ALPHA].write_symbol(writer, usize::from(alpha));
+            }
+            Symbol::BackwardReference {
+                length,
+                distance_code,
+            } => {
+                let (prefix, extra_bits, extra) = prefix_encode(length as u32);
+                codes[GREEN].write_symbol(writer, usize::from(NUM_LITERAL_CODES) + prefix);
+                writer.write(extra, extra_bits);
+                let (prefix, extra_bits, extra) = prefix_encode(distance_code);
+                codes[DISTANCE].write_symbol(writer, prefix);
+                writer.write(extra, extra_bits);
+            }
+        }
+    }
+}
+
+/// Split a length or distance code into its prefix symbol and extra bits.
+fn prefix_encode(value: u32) -> (usize, u8, u32) {
+    let value = value - 1;
+    if value < 4 {
+        return (value as usize, 0, 0);
+    }
+    let highest_bit = 31 - value.leading_zeros();
+    let second_bit = (value >> (highest_bit - 1)) & 1;
+    let extra_bits = highest_bit - 1;
+    let prefix = 2 * highest_bit + second_bit;
+    (
+        prefix as usize,
+        extra_bits as u8,
+        value & ((1 << extra_bits) - 1),
+    )
+}
+
+/// The code of a backward reference distance, preferring the short codes of nearby pixels.
+fn distance_code(xsize: u32, distance: usize) -> u32 {
+    DISTANCE_MAP
+        .iter()
+        .position(|&(x, y)| i64::from(x) + i64::from(y) * i64::from(xsize) == distance as i64)
+        .map_or(distance as u32 + 120, |index| index as u32 + 1)
+}
+
+/// Find backward references with hash chains over pairs of pixels.
+fn backward_references(pixels: &[u32], xsize: u32) -> Vec<Symbol> {
+    fn hash(pixels: &[u32], i: usize) -> usize {
+        let value = pixels[i].wrapping_mul(0x1e35_a7bd) ^ pixels[i + 1].wrapping_mul(0x9e37_79b1);
+        (value >> (32 - HASH_BITS)) as usize
+    }
+    let none = usize::max_value();
+    // The most recent position of each hash, and for each position the previous one.
+    let mut head = vec![none; 1 << HASH_BITS];
+    let mut chain = vec![none; pixels.len()];
+    let insert = |i: usize, head: &mut [usize], chain: &mut [usize]| {
+        if i + 1 < pixels.len() {
+            let h = hash(pixels, i);
+            chain[i] = head[h];
+            head[h] = i;
+        }
+    };
+
+    let mut symbols = Vec::with_capacity(pixels.len());
+    let mut i = 0;
+    while i < pixels.len() {
+        let max_length = MAX_MATCH_LENGTH.min(pixels.len() - i);
+        let (mut best_length, mut best_distance) = (0, 0);
+        if max_length >= MIN_MATCH_LENGTH {
+            let mut candidate = head[hash(pixels, i)];
+            let mut steps = 0;
+            while candidate != none && i - candidate <= MAX_DISTANCE && steps < MAX_CHAIN_LENGTH {
+                let length = pixels[candidate..]
+                    .iter()
+                    .zip(&pixels[i..i + max_length])
+                    .take_while(|(a, b)| a == b)
+                    .count();
+                if length > best_length {
+                    best_length = length;
+                    best_distance = i - candidate;
+                    if length == max_length {
+                        break;
+                    }
+                }
+                candidate = chain[candidate];
+                steps += 1;
+            }
+        }
+
+        if best_length >= MIN_MATCH_LENGTH {
+            symbols.push(Symbol::BackwardReference {
+                length: best_length,
+                distance_code: distance_code(xsize, best_distance),
+            });
+            for j in i..i + best_length {
+                insert(j, &mut head, &mut chain);
+            }
+            i += best_length;
+        } else {
+            symbols.push(Symbol::Literal(pixels[i]));
+            insert(i, &mut head, &mut chain);
+            i += 1;
+        }
+    }
+    symbols
+}
+
+/// A canonical prefix code for the symbols of a histogram.
+struct PrefixCode {
+    lengths: Vec<u8>,
+    codes: Vec<u16>,
+}
+
+impl PrefixCode {
+    fn new(histogram: &[u32], max_length: u8) -> PrefixCode {
+        let lengths = code_lengths(histogram, max_length);
+        let mut codes = vec![0u16; lengths.len()];
+        // A single symbol is coded with zero bits.
+        if lengths.iter().filter(|&&l| l > 0).count() > 1 {
+            let mut code = 0u16;
+            for length in 1..=max_length {
+                for (symbol, _) in lengths.iter().enumerate().filter(|&(_, &l)| l == length) {
+                    codes[symbol] = code;
+                    code += 1;
+                }
+                code <<= 1;
+            }
+        }
+        PrefixCode { lengths, codes }
+    }
+
+    fn write_symbol(&self, writer: &mut BitWriter, symbol: usize) {
+        let length = if self.codes.iter().all(|&c| c == 0) {
+            0
+        } else {
+            self.lengths[symbol]
+        };
+        writer.write_code(self.codes[symbol], length);
+    }
+
+    fn write_header(&self, writer: &mut BitWriter) {
+        let symbols: Vec<usize> = (0..self.lengths.len())
+            .filter(|&s| self.lengths[s] > 0)
+            .collect();
+
+        if symbols.len() <= 2 && symbols.iter().all(|&s| s < 256) {
+            // A simple code of one or two symbols.
+            writer.write(1, 1);
+            let first = symbols.first().cloned().unwrap_or(0);
+            writer.write(symbols.len().max(1) as u32 - 1, 1);
+            if first < 2 {
+                writer.write(0, 1);
+                writer.write(first as u32, 1);
+            } else {
+                writer.write(1, 1);
+                writer.write(first as u32, 8);
+            }
+            if let Some(&second) = symbols.get(1) {
+                writer.write(second as u32, 8);
+            }
+            return;
+        }
+
+        writer.write(0, 1);
+        let tokens = code_length_tokens(&self.lengths);
+        let mut histogram = [0u32; 19];
+        for &(token, _) in &tokens {
+            histogram[usize::from(token)] += 1;
+        }
+        let code_length_code = PrefixCode::new(&histogram, MAX_CODE_LENGTH_CODE_LENGTH);
+
+        let num_code_lengths = CODE_LENGTH_CODE_ORDER
+            .iter()
+            .rposition(|&i| code_length_code.lengths[i] > 0)
+            .map_or(0, |i| i + 1)
+            .max(4);
+        writer.write(num_code_lengths as u32 - 4, 4);
+        for &i in &CODE_LENGTH_CODE_ORDER[..num_code_lengths] {
+            writer.write(u32::from(code_length_code.lengths[i]), 3);
+        }
+        // The lengths of all symbols are written.
+        writer.write(0, 1);
+
+        for (token, extra) in tokens {
+            code_length_code.write_symbol(writer, usize::from(token));
+            match token {
+                16 => writer.write(extra, 2),
+                17 => writer.write(extra, 3),
+                18 => writer.write(extra, 7),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Run length encode code lengths, returns the code length symbols and their extra bits.
+fn code_length_tokens(lengths: &[u8]) -> Vec<(u8, u32)> {
+    let mut tokens = Vec::new();
+    // The length repeated by symbol 16, before any non-zero length was written.
+    let mut previous = 8;
+    let mut i = 0;
+    while i < lengths.len() {
+        let length = lengths[i];
+        let mut run = lengths[i..].iter().take_while(|&&l| l == length).count();
+        i += run;
+
+        if length == 0 {
+            while run >= 11 {
+                let repeat = run.min(138);
+                tokens.push((18, repeat as u32 - 11));
+                run -= repeat;
+            }
+            if run >= 3 {
+                tokens.push((17, run as u32 - 3));
+                run = 0;
+            }
+        } else {
+            if length != previous {
+                tokens.push((length, 0));
+                previous = length;
+                run -= 1;
+            }
+            while run >= 3 {
+                let repeat = run.min(6);
+                tokens.push((16, repeat as u32 - 3));
+                run -= repeat;
+            }
+        }
+        for _ in 0..run {
+            tokens.push((length, 0));
+        }
+    }
+    tokens
+}
+
+/// Compute Huffman code lengths limited to `max_length` bits. The counts of rare symbols are
+/// raised until the limit is met.
+fn code_lengths(histogram: &[u32], max_length: u8) -> Vec<u8> {
+    let mut lengths = vec![0u8; histogram.len()];
+    let used: Vec<usize> = (0..histogram.len()).filter(|&s| histogram[s] > 0).collect();
+    if used.len() == 1 {
+        lengths[used[0]] = 1;
+    }
+    if used.len() <= 1 {
+        return lengths;
+    }
+
+    let mut minimum = 1;
+    loop {
+        // Nodes are leaves for the used symbols followed by the inner nodes, each with the index
+        // of its parent.
+        let mut parents = vec![0usize; 2 * used.len() - 1];
+        let mut heap: BinaryHeap<Reverse<(u64, usize)>> = used
+            .iter()
+            .enumerate()
+            .map(|(node, &s)| Reverse((u64::from(histogram[s].max(minimum)), node)))
+            .collect();
+        let mut next = used.len();
+        while heap.len() > 1 {
+            let Reverse((a, first)) = heap.pop().unwrap();
+            let Reverse((b, second)) = heap.pop().unwrap();
+            parents[first] = next;
+            parents[second] = next;
+            heap.push(Reverse((a + b, next)));
+            next += 1;
+        }
+
+        // Inner nodes come after their children, so depths can be computed from the root down.
+        let root = next - 1;
+        let mut depths = vec![0u8; next];
+        for node in (0..root).rev() {
+            depths[node] = depths[parents[node]] + 1;
+        }
+        if depths[..used.len()].iter().all(|&d| d <= max_length) {
+            for (node, &s) in used.iter().enumerate() {
+                lengths[s] = depths[node];
+            }
+            return lengths;
+        }
+        minimum *= 2;
+    }
+}
+
+/// Writes bits starting with the least significant bit of each byte.
+struct BitWriter {
+    data: Vec<u8>,
+    buffer: u64,
+    nbits: u8,
+}
+
+impl BitWriter {
+    fn new() -> Self {
+        BitWriter {
+            data: Vec::new(),
+            buffer: 0,
+            nbits: 0,
+        }
+    }
+
+    fn write(&mut self, value: u32, n: u8) {
+        debug_assert!(n <= 32);
+        self.buffer |= u64::from(value) << self.nbits;
+        self.nbits += n;
+        while self.nbits >= 8 {
+            self.data.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.nbits -= 8;
+        }
+    }
+
+    /// Write a prefix code, which is read starting with its most significant bit.
+    fn write_code(&mut self, code: u16, length: u8) {
+        let mut reversed = 0;
+        for bit in 0..length {
+            reversed |= u32::from((code >> (length - 1 - bit)) & 1) << bit;
+        }
+        self.write(reversed, length);
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.nbits > 0 {
+            self.data.push(self.buffer as u8);
+        }
+        self.data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{code_lengths, encode, encode_headerless, prefix_encode};
+    use crate::codecs::webp::lossless::{decode, decode_headerless};
+
+    #[test]
+    fn prefix_encode_roundtrip() {
+        for value in 1..5000 {
+            let (prefix, extra_bits, extra) = prefix_encode(value);
+            assert!(prefix < 40);
+            // The decoding of the lossless format.
+            let decoded = if prefix < 4 {
+                prefix as u32 + 1
+            } else {
+                let bits = (prefix as u32 - 2) >> 1;
+                assert_eq!(u32::from(extra_bits), bits);
+                ((2 + (prefix as u32 & 1)) << bits) + extra + 1
+            };
+            assert_eq!(decoded, value);
+        }
+    }
+
+    #[test]
+    fn code_lengths_are_limited() {
+        // Fibonacci counts lead to a maximally deep tree.
+        let mut histogram = vec![1u32, 1];
+        while histogram.len() < 30 {
+            let n = histogram.len();
+            histogram.push(histogram[n - 1] + histogram[n - 2]);
+        }
+        let lengths = code_lengths(&histogram, 15);
+        assert!(lengths.iter().all(|&l| l >= 1 && l <= 15));
+        let kraft: f64 = lengths.iter().map(|&l| 0.5f64.powi(i32::from(l))).sum();
+        assert!((kraft - 1.0).abs() < 1e-9);
+    }
+
+    fn roundtrip(pixels: &[u32], width: u32, height: u32) {
+        let data = encode(pixels, width, height, true);
+        let (w, h, decoded) = decode(&data).unwrap();
+        assert_eq!((w, h), (width, height));
+        assert_eq!(decoded, pixels);
+    }
+
+    #[test]
+    fn lossless_roundtrip() {
+        // Few colors use the color indexing transform.
+        for &colors in &[1, 2, 3, 7, 200] {
+            let pixels: Vec<u32> = (0..37 * 11u32)
+                .map(|i| 0x8000_0000 | (((i * 7919) % colors) * 0x0001_0305))
+                .collect();
+            roundtrip(&pixels, 37, 11);
+        }
+
+        // A gradient with noise uses the predictors.
+        let pixels: Vec<u32> = (0..64 * 40u32)
+            .map(|i| {
+                let (x, y) = (i % 64, i / 64);
+                let noise = i.wrapping_mul(2_654_435_761) >> 29;
+                u32::from_be_bytes([
+                    (255 - y) as u8,
+                    (x * 4) as u8,
+                    (y * 6 + noise) as u8,
+                    (x + y) as u8,
+                ])
+            })
+            .collect();
+        roundtrip(&pixels, 64, 40);
+
+        // Random pixels without any structure.
+        let mut state = 1u32;
+        let pixels: Vec<u32> = (0..23 * 17)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state
+            })
+            .collect();
+        roundtrip(&pixels, 23, 17);
+        roundtrip(&pixels[..1], 1, 1);
+    }
+
+    #[test]
+    fn lossless_compresses_repetition() {
+        let pixels: Vec<u32> = (0..256 * 256u32).map(|i| (i % 256) << 8).collect();
+        let data = encode(&pixels, 256, 256, false);
+        assert!(data.len() < 1000);
+        assert_eq!(decode(&data).unwrap().2, pixels);
+    }
+
+    #[test]
+    fn headerless_roundtrip() {
+        let pixels: Vec<u32> = (0..30 * 3u32).map(|i| (i * i) << 8).collect();
+        let data = encode_headerless(&pixels, 30, 3);
+        assert_eq!(decode_headerless(&data, 30, 3).unwrap(), pixels);
+    }
+}
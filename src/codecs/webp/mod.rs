@@ -1,8 +1,13 @@
-//! Decoding of WebP Images
+//! Decoding and Encoding of WebP Images
 
 pub use self::decoder::WebPDecoder;
+pub use self::encoder::{WebPEncoder, WebPQuality};
 
 mod decoder;
+mod encoder;
+mod lossless;
+mod lossless_encoder;
 mod transform;
+mod vp8_encoder;
 
 pub mod vp8;
@@ -26,13 +26,13 @@ use crate::image::ImageFormat;
 use crate::utils::clamp;
 
 const MAX_SEGMENTS: usize = 4;
-const NUM_DCT_TOKENS: usize = 12;
+pub(crate) const NUM_DCT_TOKENS: usize = 12;
 
 // Prediction modes
-const DC_PRED: i8 = 0;
-const V_PRED: i8 = 1;
-const H_PRED: i8 = 2;
-const TM_PRED: i8 = 3;
+pub(crate) const DC_PRED: i8 = 0;
+pub(crate) const V_PRED: i8 = 1;
+pub(crate) const H_PRED: i8 = 2;
+pub(crate) const TM_PRED: i8 = 3;
 const B_PRED: i8 = 4;
 
 const B_DC_PRED: i8 = 0;
@@ -97,16 +97,16 @@ enum IntraMode {
     HU = B_HU_PRED,
 }
 
-type Prob = u8;
+pub(crate) type Prob = u8;
 
 static SEGMENT_ID_TREE: [i8; 6] = [2, 4, -0, -1, -2, -3];
 
 // Section 11.2
 // Tree for determining the keyframe luma intra prediction modes:
-static KEYFRAME_YMODE_TREE: [i8; 8] = [-B_PRED, 2, 4, 6, -DC_PRED, -V_PRED, -H_PRED, -TM_PRED];
+pub(crate) static KEYFRAME_YMODE_TREE: [i8; 8] = [-B_PRED, 2, 4, 6, -DC_PRED, -V_PRED, -H_PRED, -TM_PRED];
 
 // Default probabilities for decoding the keyframe luma modes
-static KEYFRAME_YMODE_PROBS: [Prob; 4] = [145, 156, 163, 128];
+pub(crate) static KEYFRAME_YMODE_PROBS: [Prob; 4] = [145, 156, 163, 128];
 
 // Tree for determining the keyframe B_PRED mode:
 static KEYFRAME_BPRED_MODE_TREE: [i8; 18] = [
@@ -239,16 +239,16 @@ static KEYFRAME_BPRED_MODE_PROBS: [[[u8; 9]; 10]; 10] = [
 ];
 
 // Section 11.4 Tree for determining macroblock the chroma mode
-static KEYFRAME_UV_MODE_TREE: [i8; 6] = [-DC_PRED, 2, -V_PRED, 4, -H_PRED, -TM_PRED];
+pub(crate) static KEYFRAME_UV_MODE_TREE: [i8; 6] = [-DC_PRED, 2, -V_PRED, 4, -H_PRED, -TM_PRED];
 
 // Probabilities for determining macroblock mode
-static KEYFRAME_UV_MODE_PROBS: [Prob; 3] = [142, 114, 183];
+pub(crate) static KEYFRAME_UV_MODE_PROBS: [Prob; 3] = [142, 114, 183];
 
 // Section 13.4
-type TokenProbTables = [[[[Prob; NUM_DCT_TOKENS - 1]; 3]; 8]; 4];
+pub(crate) type TokenProbTables = [[[[Prob; NUM_DCT_TOKENS - 1]; 3]; 8]; 4];
 
 // Probabilities that a token's probability will be updated
-static COEFF_UPDATE_PROBS: TokenProbTables = [
+pub(crate) static COEFF_UPDATE_PROBS: TokenProbTables = [
     [
         [
             [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
@@ -421,7 +421,7 @@ static COEFF_UPDATE_PROBS: TokenProbTables = [
 
 // Section 13.5
 // Default Probabilities for tokens
-static COEFF_PROBS: TokenProbTables = [
+pub(crate) static COEFF_PROBS: TokenProbTables = [
     [
         [
             [128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128],
@@ -593,25 +593,25 @@ static COEFF_PROBS: TokenProbTables = [
 ];
 
 // DCT Tokens
-const DCT_0: i8 = 0;
-const DCT_1: i8 = 1;
-const DCT_2: i8 = 2;
-const DCT_3: i8 = 3;
-const DCT_4: i8 = 4;
-const DCT_CAT1: i8 = 5;
-const DCT_CAT2: i8 = 6;
-const DCT_CAT3: i8 = 7;
-const DCT_CAT4: i8 = 8;
-const DCT_CAT5: i8 = 9;
-const DCT_CAT6: i8 = 10;
-const DCT_EOB: i8 = 11;
-
-static DCT_TOKEN_TREE: [i8; 22] = [
+pub(crate) const DCT_0: i8 = 0;
+pub(crate) const DCT_1: i8 = 1;
+pub(crate) const DCT_2: i8 = 2;
+pub(crate) const DCT_3: i8 = 3;
+pub(crate) const DCT_4: i8 = 4;
+pub(crate) const DCT_CAT1: i8 = 5;
+pub(crate) const DCT_CAT2: i8 = 6;
+pub(crate) const DCT_CAT3: i8 = 7;
+pub(crate) const DCT_CAT4: i8 = 8;
+pub(crate) const DCT_CAT5: i8 = 9;
+pub(crate) const DCT_CAT6: i8 = 10;
+pub(crate) const DCT_EOB: i8 = 11;
+
+pub(crate) static DCT_TOKEN_TREE: [i8; 22] = [
     -DCT_EOB, 2, -DCT_0, 4, -DCT_1, 6, 8, 12, -DCT_2, 10, -DCT_3, -DCT_4, 14, 16, -DCT_CAT1,
     -DCT_CAT2, 18, 20, -DCT_CAT3, -DCT_CAT4, -DCT_CAT5, -DCT_CAT6,
 ];
 
-static PROB_DCT_CAT: [[Prob; 12]; 6] = [
+pub(crate) static PROB_DCT_CAT: [[Prob; 12]; 6] = [
     [159, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
     [165, 145, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
     [173, 148, 140, 0, 0, 0, 0, 0, 0, 0, 0, 0],
@@ -620,11 +620,11 @@ static PROB_DCT_CAT: [[Prob; 12]; 6] = [
     [254, 254, 243, 230, 196, 177, 153, 140, 133, 130, 129, 0],
 ];
 
-static DCT_CAT_BASE: [u8; 6] = [5, 7, 11, 19, 35, 67];
-static COEFF_BANDS: [u8; 16] = [0, 1, 2, 3, 6, 4, 5, 6, 6, 6, 6, 6, 6, 6, 6, 7];
+pub(crate) static DCT_CAT_BASE: [u8; 6] = [5, 7, 11, 19, 35, 67];
+pub(crate) static COEFF_BANDS: [u8; 16] = [0, 1, 2, 3, 6, 4, 5, 6, 6, 6, 6, 6, 6, 6, 6, 7];
 
 #[rustfmt::skip]
-static DC_QUANT: [i16; 128] = [
+pub(crate) static DC_QUANT: [i16; 128] = [
       4,   5,   6,   7,   8,   9,  10,  10,
      11,  12,  13,  14,  15,  16,  17,  17,
      18,  19,  20,  20,  21,  21,  22,  22,
@@ -644,7 +644,7 @@ static DC_QUANT: [i16; 128] = [
 ];
 
 #[rustfmt::skip]
-static AC_QUANT: [i16; 128] = [
+pub(crate) static AC_QUANT: [i16; 128] = [
       4,   5,   6,   7,   8,    9,  10,  11,
       12,  13,  14,  15,  16,  17,  18,  19,
       20,  21,  22,  23,  24,  25,  26,  27,
@@ -663,7 +663,7 @@ static AC_QUANT: [i16; 128] = [
      249, 254, 259, 264, 269, 274, 279, 284,
 ];
 
-static ZIGZAG: [u8; 16] = [0, 1, 4, 8, 5, 2, 3, 6, 9, 12, 13, 10, 7, 11, 14, 15];
+pub(crate) static ZIGZAG: [u8; 16] = [0, 1, 4, 8, 5, 2, 3, 6, 9, 12, 13, 10, 7, 11, 14, 15];
 
 /// All errors that can occur when attempting to parse a VP8 codec inside WebP
 #[derive(Debug, Clone, Copy)]
@@ -1051,7 +1051,10 @@ impl<R: Read> Vp8Decoder<R> {
             1
         };
         for i in 0usize..n {
-            let base = i32::from(if !self.segment[i].delta_values {
+            // Without segmentation the base index applies to the whole frame.
+            let base = i32::from(if !self.segments_enabled {
+                i16::from(yac_abs)
+            } else if !self.segment[i].delta_values {
                 i16::from(self.segment[i].quantizer_level)
             } else {
                 i16::from(self.segment[i].quantizer_level) + i16::from(yac_abs)
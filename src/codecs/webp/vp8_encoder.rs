@@ -0,0 +1,864 @@
+//! Encoding of lossy WebP images
+//!
+//! Writes VP8 keyframes with one partition of coefficients and without loop filtering. Each
+//! macroblock is predicted with the best of the 16x16 luma and 8x8 chroma modes, the residuals
+//! are coded with the default token probabilities.
+//!
+//! # Related Links
+//! * [rfc-6386](http://tools.ietf.org/html/rfc6386) - The VP8 Data Format and Decoding Guide
+//!
+
+use super::transform;
+use super::vp8::{
+    Prob, AC_QUANT, COEFF_BANDS, COEFF_PROBS, COEFF_UPDATE_PROBS, DCT_0, DCT_1, DCT_4, DCT_CAT1,
+    DCT_CAT_BASE, DCT_EOB, DCT_TOKEN_TREE, DC_PRED, DC_QUANT, H_PRED, KEYFRAME_UV_MODE_PROBS,
+    KEYFRAME_UV_MODE_TREE, KEYFRAME_YMODE_PROBS, KEYFRAME_YMODE_TREE, NUM_DCT_TOKENS, PROB_DCT_CAT,
+    TM_PRED, V_PRED, ZIGZAG,
+};
+
+use crate::utils::clamp;
+
+/// The largest magnitude of a quantized coefficient.
+const MAX_LEVEL: i32 = 2048;
+
+/// The token probability planes.
+const PLANE_Y_NO_DC: usize = 0;
+const PLANE_Y2: usize = 1;
+const PLANE_CHROMA: usize = 2;
+
+/// Encode an image from RGB pixels, with a quality between 1 and 100. The dimensions have to be
+/// between 1 and 16383.
+///
+/// Returns `None` if the image is too large for the size field of the first partition.
+pub(crate) fn encode(rgb: &[u8], width: u32, height: u32, quality: u8) -> Option<Vec<u8>> {
+    let mut encoder = Encoder::new(rgb, width, height, quality);
+    for mby in 0..encoder.mbheight {
+        for context in &mut encoder.left {
+            *context = 0;
+        }
+        for mbx in 0..encoder.mbwidth {
+            encoder.encode_macroblock(mbx, mby);
+        }
+    }
+
+    let first_partition = encoder.write_header();
+    if first_partition.len() >= 1 << 19 {
+        return None;
+    }
+    let tokens = encoder.tokens.finish();
+
+    let mut data = Vec::with_capacity(10 + first_partition.len() + tokens.len());
+    // A keyframe of version 0, shown, followed by the size of the first partition.
+    let tag = (first_partition.len() as u32) << 5 | 1 << 4;
+    data.extend_from_slice(&tag.to_le_bytes()[..3]);
+    data.extend_from_slice(&[0x9d, 0x01, 0x2a]);
+    data.extend_from_slice(&(width as u16).to_le_bytes());
+    data.extend_from_slice(&(height as u16).to_le_bytes());
+    data.extend_from_slice(&first_partition);
+    data.extend_from_slice(&tokens);
+    Some(data)
+}
+
+/// Map a quality between 1 and 100 to a quantizer index between 127 and 0.
+fn quantizer_index(quality: u8) -> usize {
+    let quality = usize::from(clamp(quality, 1, 100));
+    (100 - quality) * 127 / 100
+}
+
+/// The modes and skip flag of an encoded macroblock.
+#[derive(Clone, Copy)]
+struct MacroblockInfo {
+    luma_mode: i8,
+    chroma_mode: i8,
+    skip: bool,
+}
+
+struct Encoder {
+    width: u32,
+    height: u32,
+    mbwidth: usize,
+    mbheight: usize,
+
+    /// The source planes, padded to whole macroblocks.
+    y: Vec<u8>,
+    u: Vec<u8>,
+    v: Vec<u8>,
+    /// The planes as reconstructed by a decoder, used for prediction.
+    recon_y: Vec<u8>,
+    recon_u: Vec<u8>,
+    recon_v: Vec<u8>,
+
+    quantizer: usize,
+    yac: i32,
+    y2dc: i32,
+    y2ac: i32,
+    uvdc: i32,
+    uvac: i32,
+
+    /// Whether the neighbouring blocks have coefficients, the Y2 block, four luma blocks and two
+    /// blocks of each chroma plane.
+    top: Vec<[u8; 9]>,
+    left: [u8; 9],
+
+    macroblocks: Vec<MacroblockInfo>,
+    tokens: BoolWriter,
+}
+
+impl Encoder {
+    fn new(rgb: &[u8], width: u32, height: u32, quality: u8) -> Encoder {
+        let mbwidth = (width as usize + 15) / 16;
+        let mbheight = (height as usize + 15) / 16;
+        let (y, u, v) = rgb_to_yuv(rgb, width as usize, height as usize, mbwidth, mbheight);
+
+        let quantizer = quantizer_index(quality);
+        let dc = i32::from(DC_QUANT[quantizer]);
+        let ac = i32::from(AC_QUANT[quantizer]);
+
+        Encoder {
+            width,
+            height,
+            mbwidth,
+            mbheight,
+            recon_y: vec![0; y.len()],
+            recon_u: vec![0; u.len()],
+            recon_v: vec![0; v.len()],
+            y,
+            u,
+            v,
+            quantizer,
+            // Derived like the decoder does, without deltas to the base index.
+            yac: ac,
+            y2dc: dc * 2,
+            y2ac: (ac * 155 / 100).max(8),
+            uvdc: dc.min(132),
+            uvac: ac,
+            top: vec![[0; 9]; mbwidth],
+            left: [0; 9],
+            macroblocks: Vec::with_capacity(mbwidth * mbheight),
+            tokens: BoolWriter::new(),
+        }
+    }
+
+    fn encode_macroblock(&mut self, mbx: usize, mby: usize) {
+        // Luma
+        let stride = self.mbwidth * 16;
+        let source = block_of(&self.y, stride, mbx * 16, mby * 16, 16);
+        let border = Border::of(&self.recon_y, stride, mbx, mby, 16);
+        let luma_mode = best_mode(&[(&source, &border)], 16);
+        let prediction = border.predict(luma_mode, 16);
+
+        let mut coefficients = [[0i32; 16]; 16];
+        let mut dcs = [0i32; 16];
+        for (b, block) in coefficients.iter_mut().enumerate() {
+            let (x0, y0) = ((b % 4) * 4, (b / 4) * 4);
+            let mut residual = [0i32; 16];
+            for (i, r) in residual.iter_mut().enumerate() {
+                let index = (y0 + i / 4) * 16 + x0 + i % 4;
+                *r = i32::from(source[index]) - i32::from(prediction[index]);
+            }
+            *block = forward_dct(&residual);
+            dcs[b] = block[0];
+            block[0] = 0;
+            for coefficient in block[1..].iter_mut() {
+                *coefficient = quantize(*coefficient, self.yac);
+            }
+        }
+        let mut y2 = forward_wht(&dcs);
+        y2[0] = quantize(y2[0], self.y2dc);
+        for coefficient in y2[1..].iter_mut() {
+            *coefficient = quantize(*coefficient, self.y2ac);
+        }
+
+        // Reconstruct the luma block like the decoder.
+        let mut dc = [0i32; 16];
+        dc[0] = y2[0] * self.y2dc;
+        for i in 1..16 {
+            dc[i] = y2[i] * self.y2ac;
+        }
+        transform::iwht4x4(&mut dc);
+        let mut reconstructed = prediction;
+        for (b, block) in coefficients.iter().enumerate() {
+            let mut dequantized = [0i32; 16];
+            dequantized[0] = dc[b];
+            for i in 1..16 {
+                dequantized[i] = block[i] * self.yac;
+            }
+            add_residual(
+                &mut reconstructed,
+                16,
+                (b % 4) * 4,
+                (b / 4) * 4,
+                &mut dequantized,
+            );
+        }
+        store_block(
+            &mut self.recon_y,
+            stride,
+            mbx * 16,
+            mby * 16,
+            16,
+            &reconstructed,
+        );
+
+        // Chroma, both planes share one prediction mode.
+        let stride = self.mbwidth * 8;
+        let source_u = block_of(&self.u, stride, mbx * 8, mby * 8, 8);
+        let source_v = block_of(&self.v, stride, mbx * 8, mby * 8, 8);
+        let border_u = Border::of(&self.recon_u, stride, mbx, mby, 8);
+        let border_v = Border::of(&self.recon_v, stride, mbx, mby, 8);
+        let chroma_mode = best_mode(&[(&source_u, &border_u), (&source_v, &border_v)], 8);
+
+        let mut chroma = [[[0i32; 16]; 4]; 2];
+        for (plane, (source, border)) in [(&source_u, &border_u), (&source_v, &border_v)]
+            .iter()
+            .enumerate()
+        {
+            let prediction = border.predict(chroma_mode, 8);
+            let mut reconstructed = prediction;
+            for (b, block) in chroma[plane].iter_mut().enumerate() {
+                let (x0, y0) = ((b % 2) * 4, (b / 2) * 4);
+                let mut residual = [0i32; 16];
+                for (i, r) in residual.iter_mut().enumerate() {
+                    let index = (y0 + i / 4) * 8 + x0 + i % 4;
+                    *r = i32::from(source[index]) - i32::from(prediction[index]);
+                }
+                *block = forward_dct(&residual);
+                block[0] = quantize(block[0], self.uvdc);
+                for coefficient in block[1..].iter_mut() {
+                    *coefficient = quantize(*coefficient, self.uvac);
+                }
+
+                let mut dequantized = [0i32; 16];
+                dequantized[0] = block[0] * self.uvdc;
+                for i in 1..16 {
+                    dequantized[i] = block[i] * self.uvac;
+                }
+                add_residual(&mut reconstructed, 8, x0, y0, &mut dequantized);
+            }
+            let recon = if plane == 0 {
+                &mut self.recon_u
+            } else {
+                &mut self.recon_v
+            };
+            store_block(recon, stride, mbx * 8, mby * 8, 8, &reconstructed);
+        }
+
+        let skip = y2.iter().all(|&c| c == 0)
+            && coefficients
+                .iter()
+                .all(|block| block.iter().all(|&c| c == 0))
+            && chroma
+                .iter()
+                .flatten()
+                .all(|block| block.iter().all(|&c| c == 0));
+        if skip {
+            self.top[mbx] = [0; 9];
+            self.left = [0; 9];
+        } else {
+            self.write_residuals(mbx, &y2, &coefficients, &chroma);
+        }
+
+        self.macroblocks.push(MacroblockInfo {
+            luma_mode,
+            chroma_mode,
+            skip,
+        });
+    }
+
+    /// Write the coefficients in the order the decoder reads them, tracking whether each block
+    /// has coefficients for the contexts of its neighbours.
+    fn write_residuals(
+        &mut self,
+        mbx: usize,
+        y2: &[i32; 16],
+        luma: &[[i32; 16]; 16],
+        chroma: &[[[i32; 16]; 4]; 2],
+    ) {
+        let context = self.top[mbx][0] + self.left[0];
+        let nonzero = self.tokens.write_coefficients(y2, PLANE_Y2, context) as u8;
+        self.top[mbx][0] = nonzero;
+        self.left[0] = nonzero;
+
+        for y in 0..4 {
+            for x in 0..4 {
+                let context = self.top[mbx][x + 1] + self.left[y + 1];
+                let block = &luma[y * 4 + x];
+                let nonzero = self
+                    .tokens
+                    .write_coefficients(block, PLANE_Y_NO_DC, context)
+                    as u8;
+                self.top[mbx][x + 1] = nonzero;
+                self.left[y + 1] = nonzero;
+            }
+        }
+
+        for (plane, &j) in [5usize, 7].iter().enumerate() {
+            for y in 0..2 {
+                for x in 0..2 {
+                    let context = self.top[mbx][x + j] + self.left[y + j];
+                    let block = &chroma[plane][y * 2 + x];
+                    let nonzero =
+                        self.tokens.write_coefficients(block, PLANE_CHROMA, context) as u8;
+                    self.top[mbx][x + j] = nonzero;
+                    self.left[y + j] = nonzero;
+                }
+            }
+        }
+    }
+
+    /// Write the frame header and the modes of the macroblocks into the first partition.
+    fn write_header(&self) -> Vec<u8> {
+        let mut header = BoolWriter::new();
+        // Color space and clamping type
+        header.write_literal(0, 1);
+        header.write_literal(0, 1);
+        // No segmentation
+        header.write_flag(false);
+        // Filter type, loop filter level and sharpness, without adjustments
+        header.write_literal(0, 1);
+        header.write_literal(0, 6);
+        header.write_literal(0, 3);
+        header.write_flag(false);
+        // A single partition of coefficients
+        header.write_literal(0, 2);
+        // The quantizer index without deltas
+        header.write_literal(self.quantizer as u8, 7);
+        for _ in 0..5 {
+            header.write_flag(false);
+        }
+        // Refresh entropy probabilities
+        header.write_flag(false);
+        // The default token probabilities are kept.
+        for planes in COEFF_UPDATE_PROBS.iter() {
+            for bands in planes.iter() {
+                for contexts in bands.iter() {
+                    for &prob in contexts.iter().take(NUM_DCT_TOKENS - 1) {
+                        header.write_bool(false, prob);
+                    }
+                }
+            }
+        }
+
+        let skipped = self.macroblocks.iter().filter(|mb| mb.skip).count();
+        let prob_skip_false = clamp(
+            (self.macroblocks.len() - skipped) * 256 / self.macroblocks.len(),
+            1,
+            255,
+        ) as Prob;
+        header.write_flag(true);
+        header.write_literal(prob_skip_false, 8);
+
+        for mb in &self.macroblocks {
+            header.write_bool(mb.skip, prob_skip_false);
+            header.write_with_tree(&KEYFRAME_YMODE_TREE, &KEYFRAME_YMODE_PROBS, 0, mb.luma_mode);
+            header.write_with_tree(
+                &KEYFRAME_UV_MODE_TREE,
+                &KEYFRAME_UV_MODE_PROBS,
+                0,
+                mb.chroma_mode,
+            );
+        }
+
+        debug_assert!(self.width <= 0x3fff && self.height <= 0x3fff);
+        header.finish()
+    }
+}
+
+/// Convert RGB pixels to YUV 4:2:0, padding the planes to whole macroblocks by repeating the
+/// last row and column.
+fn rgb_to_yuv(
+    rgb: &[u8],
+    width: usize,
+    height: usize,
+    mbwidth: usize,
+    mbheight: usize,
+) -> (Vec<u8>, Vec<u8>, Vec<u8>) {
+    let pixel = |x: usize, y: usize| -> [i32; 3] {
+        let i = (y.min(height - 1) * width + x.min(width - 1)) * 3;
+        [
+            i32::from(rgb[i]),
+            i32::from(rgb[i + 1]),
+            i32::from(rgb[i + 2]),
+        ]
+    };
+
+    let (luma_width, luma_height) = (mbwidth * 16, mbheight * 16);
+    let mut y_plane = Vec::with_capacity(luma_width * luma_height);
+    for y in 0..luma_height {
+        for x in 0..luma_width {
+            let [r, g, b] = pixel(x, y);
+            y_plane.push(((16839 * r + 33059 * g + 6420 * b + (16 << 16) + (1 << 15)) >> 16) as u8);
+        }
+    }
+
+    let (chroma_width, chroma_height) = (mbwidth * 8, mbheight * 8);
+    let mut u_plane = Vec::with_capacity(chroma_width * chroma_height);
+    let mut v_plane = Vec::with_capacity(chroma_width * chroma_height);
+    for y in 0..chroma_height {
+        for x in 0..chroma_width {
+            let mut sum = [0; 3];
+            for &(dx, dy) in &[(0, 0), (1, 0), (0, 1), (1, 1)] {
+                let p = pixel(2 * x + dx, 2 * y + dy);
+                for c in 0..3 {
+                    sum[c] += p[c];
+                }
+            }
+            let [r, g, b] = [(sum[0] + 2) >> 2, (sum[1] + 2) >> 2, (sum[2] + 2) >> 2];
+            let u = (-9719 * r - 19081 * g + 28800 * b + (128 << 16) + (1 << 15)) >> 16;
+            let v = (28800 * r - 24116 * g - 4684 * b + (128 << 16) + (1 << 15)) >> 16;
+            u_plane.push(clamp(u, 0, 255) as u8);
+            v_plane.push(clamp(v, 0, 255) as u8);
+        }
+    }
+
+    (y_plane, u_plane, v_plane)
+}
+
+fn block_of(plane: &[u8], stride: usize, x0: usize, y0: usize, size: usize) -> [u8; 256] {
+    let mut block = [0; 256];
+    for y in 0..size {
+        let row = (y0 + y) * stride + x0;
+        block[y * size..(y + 1) * size].copy_from_slice(&plane[row..row + size]);
+    }
+    block
+}
+
+fn store_block(plane: &mut [u8], stride: usize, x0: usize, y0: usize, size: usize, block: &[u8]) {
+    for y in 0..size {
+        let row = (y0 + y) * stride + x0;
+        plane[row..row + size].copy_from_slice(&block[y * size..(y + 1) * size]);
+    }
+}
+
+/// The reconstructed pixels around a macroblock, with the values the decoder uses outside of the
+/// frame.
+struct Border {
+    above: [u8; 16],
+    left: [u8; 16],
+    top_left: u8,
+    have_above: bool,
+    have_left: bool,
+}
+
+impl Border {
+    fn of(recon: &[u8], stride: usize, mbx: usize, mby: usize, size: usize) -> Border {
+        let (x0, y0) = (mbx * size, mby * size);
+        let mut above = [127; 16];
+        let mut left = [129; 16];
+        if mby > 0 {
+            above[..size].copy_from_slice(&recon[(y0 - 1) * stride + x0..][..size]);
+        }
+        if mbx > 0 {
+            for (y, l) in left[..size].iter_mut().enumerate() {
+                *l = recon[(y0 + y) * stride + x0 - 1];
+            }
+        }
+        let top_left = if mby == 0 {
+            127
+        } else if mbx == 0 {
+            129
+        } else {
+            recon[(y0 - 1) * stride + x0 - 1]
+        };
+        Border {
+            above,
+            left,
+            top_left,
+            have_above: mby > 0,
+            have_left: mbx > 0,
+        }
+    }
+
+    fn predict(&self, mode: i8, size: usize) -> [u8; 256] {
+        let mut block = [0; 256];
+        for y in 0..size {
+            for x in 0..size {
+                block[y * size + x] = match mode {
+                    V_PRED => self.above[x],
+                    H_PRED => self.left[y],
+                    TM_PRED => clamp(
+                        i32::from(self.left[y]) + i32::from(self.above[x])
+                            - i32::from(self.top_left),
+                        0,
+                        255,
+                    ) as u8,
+                    _ => 0,
+                };
+            }
+        }
+
+        if mode == DC_PRED {
+            let mut sum = 0;
+            let mut shift = if size == 8 { 2 } else { 3 };
+            if self.have_above {
+                sum += self.above[..size]
+                    .iter()
+                    .map(|&p| u32::from(p))
+                    .sum::<u32>();
+                shift += 1;
+            }
+            if self.have_left {
+                sum += self.left[..size].iter().map(|&p| u32::from(p)).sum::<u32>();
+                shift += 1;
+            }
+            let dc = if self.have_above || self.have_left {
+                (sum + (1 << (shift - 1))) >> shift
+            } else {
+                128
+            };
+            for p in block[..size * size].iter_mut() {
+                *p = dc as u8;
+            }
+        }
+        block
+    }
+}
+
+/// Choose the prediction mode with the smallest absolute differences over all given planes.
+fn best_mode(planes: &[(&[u8; 256], &Border)], size: usize) -> i8 {
+    *[DC_PRED, V_PRED, H_PRED, TM_PRED]
+        .iter()
+        .min_by_key(|&&mode| {
+            planes
+                .iter()
+                .map(|(source, border)| {
+                    let prediction = border.predict(mode, size);
+                    source[..size * size]
+                        .iter()
+                        .zip(&prediction[..size * size])
+                        .map(|(&s, &p)| (i32::from(s) - i32::from(p)).abs())
+                        .sum::<i32>()
+                })
+                .sum::<i32>()
+        })
+        .unwrap()
+}
+
+/// Add the inverse transform of dequantized coefficients to a 4x4 block of a prediction.
+fn add_residual(
+    block: &mut [u8],
+    stride: usize,
+    x0: usize,
+    y0: usize,
+    coefficients: &mut [i32; 16],
+) {
+    if coefficients.iter().all(|&c| c == 0) {
+        return;
+    }
+    transform::idct4x4(coefficients);
+    for (i, &residual) in coefficients.iter().enumerate() {
+        let p = &mut block[(y0 + i / 4) * stride + x0 + i % 4];
+        *p = clamp(i32::from(*p) + residual, 0, 255) as u8;
+    }
+}
+
+fn quantize(coefficient: i32, step: i32) -> i32 {
+    let level = ((coefficient.abs() + step / 2) / step).min(MAX_LEVEL);
+    if coefficient < 0 {
+        -level
+    } else {
+        level
+    }
+}
+
+/// The forward DCT of a 4x4 block of residuals, as in the reference encoder.
+fn forward_dct(input: &[i32; 16]) -> [i32; 16] {
+    let mut temp = [0i32; 16];
+    for i in 0..4 {
+        let row = &input[i * 4..i * 4 + 4];
+        let a1 = (row[0] + row[3]) * 8;
+        let b1 = (row[1] + row[2]) * 8;
+        let c1 = (row[1] - row[2]) * 8;
+        let d1 = (row[0] - row[3]) * 8;
+        temp[i * 4] = a1 + b1;
+        temp[i * 4 + 2] = a1 - b1;
+        temp[i * 4 + 1] = (c1 * 2217 + d1 * 5352 + 14500) >> 12;
+        temp[i * 4 + 3] = (d1 * 2217 - c1 * 5352 + 7500) >> 12;
+    }
+
+    let mut output = [0i32; 16];
+    for i in 0..4 {
+        let a1 = temp[i] + temp[12 + i];
+        let b1 = temp[4 + i] + temp[8 + i];
+        let c1 = temp[4 + i] - temp[8 + i];
+        let d1 = temp[i] - temp[12 + i];
+        output[i] = (a1 + b1 + 7) >> 4;
+        output[8 + i] = (a1 - b1 + 7) >> 4;
+        output[4 + i] = ((c1 * 2217 + d1 * 5352 + 12000) >> 16) + (d1 != 0) as i32;
+        output[12 + i] = (d1 * 2217 - c1 * 5352 + 51000) >> 16;
+    }
+    output
+}
+
+/// The forward Walsh-Hadamard transform of the DC coefficients of the luma blocks.
+fn forward_wht(input: &[i32; 16]) -> [i32; 16] {
+    let mut temp = [0i32; 16];
+    for i in 0..4 {
+        let row = &input[i * 4..i * 4 + 4];
+        let a1 = (row[0] + row[2]) * 4;
+        let d1 = (row[1] + row[3]) * 4;
+        let c1 = (row[1] - row[3]) * 4;
+        let b1 = (row[0] - row[2]) * 4;
+        temp[i * 4] = a1 + d1 + (a1 != 0) as i32;
+        temp[i * 4 + 1] = b1 + c1;
+        temp[i * 4 + 2] = b1 - c1;
+        temp[i * 4 + 3] = a1 - d1;
+    }
+
+    let mut output = [0i32; 16];
+    for i in 0..4 {
+        let a1 = temp[i] + temp[8 + i];
+        let d1 = temp[4 + i] + temp[12 + i];
+        let c1 = temp[4 + i] - temp[12 + i];
+        let b1 = temp[i] - temp[8 + i];
+        let values = [a1 + d1, b1 + c1, b1 - c1, a1 - d1];
+        for (j, &value) in values.iter().enumerate() {
+            let value = value + (value < 0) as i32;
+            output[4 * j + i] = (value + 3) >> 3;
+        }
+    }
+    output
+}
+
+/// The boolean entropy encoder of section 7 of the specification.
+struct BoolWriter {
+    data: Vec<u8>,
+    range: u32,
+    bottom: u32,
+    bit_count: i32,
+}
+
+impl BoolWriter {
+    fn new() -> BoolWriter {
+        BoolWriter {
+            data: Vec::new(),
+            range: 255,
+            bottom: 0,
+            bit_count: 24,
+        }
+    }
+
+    /// Propagate a carry into the bytes already written.
+    fn add_one_to_output(&mut self) {
+        for byte in self.data.iter_mut().rev() {
+            if *byte == 255 {
+                *byte = 0;
+            } else {
+                *byte += 1;
+                break;
+            }
+        }
+    }
+
+    fn write_bool(&mut self, value: bool, probability: Prob) {
+        let split = 1 + (((self.range - 1) * u32::from(probability)) >> 8);
+        if value {
+            self.bottom = self.bottom.wrapping_add(split);
+            self.range -= split;
+        } else {
+            self.range = split;
+        }
+
+        while self.range < 128 {
+            self.range <<= 1;
+            if self.bottom & (1 << 31) != 0 {
+                self.add_one_to_output();
+            }
+            self.bottom <<= 1;
+            self.bit_count -= 1;
+            if self.bit_count == 0 {
+                self.data.push((self.bottom >> 24) as u8);
+                self.bottom &= (1 << 24) - 1;
+                self.bit_count = 8;
+            }
+        }
+    }
+
+    fn write_flag(&mut self, value: bool) {
+        self.write_bool(value, 128);
+    }
+
+    fn write_literal(&mut self, value: u8, n: u8) {
+        for bit in (0..n).rev() {
+            self.write_flag((value >> bit) & 1 != 0);
+        }
+    }
+
+    /// Write the path to a leaf of a tree, starting at the node `start`.
+    fn write_with_tree(&mut self, tree: &[i8], probs: &[Prob], start: usize, value: i8) {
+        fn find_path(tree: &[i8], index: usize, value: i8, path: &mut Vec<(usize, bool)>) -> bool {
+            for branch in 0..2 {
+                path.push((index, branch == 1));
+                let next = tree[index + branch];
+                let found = if next <= 0 {
+                    -next == value
+                } else {
+                    find_path(tree, next as usize, value, path)
+                };
+                if found {
+                    return true;
+                }
+                path.pop();
+            }
+            false
+        }
+
+        let mut path = Vec::new();
+        let found = find_path(tree, start, value, &mut path);
+        debug_assert!(found);
+        for (index, bit) in path {
+            self.write_bool(bit, probs[index >> 1]);
+        }
+    }
+
+    /// Write the tokens of a block of quantized coefficients. Returns whether the block has
+    /// coefficients.
+    fn write_coefficients(&mut self, block: &[i32; 16], plane: usize, context: u8) -> bool {
+        let first = if plane == PLANE_Y_NO_DC { 1 } else { 0 };
+        let probs = &COEFF_PROBS[plane];
+        let last = (first..16)
+            .rev()
+            .find(|&i| block[usize::from(ZIGZAG[i])] != 0);
+
+        let mut context = usize::from(context);
+        let last = match last {
+            Some(last) => last,
+            None => {
+                let table = &probs[usize::from(COEFF_BANDS[first])][context];
+                self.write_with_tree(&DCT_TOKEN_TREE, table, 0, DCT_EOB);
+                return false;
+            }
+        };
+
+        // After a zero the end of block can not follow, so the tree is entered past its node.
+        let mut after_zero = false;
+        for i in first..=last {
+            let table = &probs[usize::from(COEFF_BANDS[i])][context];
+            let start = if after_zero { 2 } else { 0 };
+            let value = block[usize::from(ZIGZAG[i])];
+            let magnitude = value.abs();
+
+            if magnitude == 0 {
+                self.write_with_tree(&DCT_TOKEN_TREE, table, start, DCT_0);
+                after_zero = true;
+                context = 0;
+                continue;
+            }
+
+            if magnitude <= i32::from(DCT_4) {
+                self.write_with_tree(&DCT_TOKEN_TREE, table, start, DCT_1 + magnitude as i8 - 1);
+            } else {
+                let category = DCT_CAT_BASE
+                    .iter()
+                    .rposition(|&base| magnitude >= i32::from(base))
+                    .unwrap();
+                self.write_with_tree(&DCT_TOKEN_TREE, table, start, DCT_CAT1 + category as i8);
+                let extra = magnitude - i32::from(DCT_CAT_BASE[category]);
+                let extra_probs = &PROB_DCT_CAT[category];
+                let bits = extra_probs.iter().take_while(|&&p| p > 0).count();
+                for (j, &prob) in extra_probs[..bits].iter().enumerate() {
+                    self.write_bool((extra >> (bits - 1 - j)) & 1 != 0, prob);
+                }
+            }
+            self.write_flag(value < 0);
+
+            after_zero = false;
+            context = if magnitude == 1 { 1 } else { 2 };
+        }
+
+        if last < 15 {
+            let table = &probs[usize::from(COEFF_BANDS[last + 1])][context];
+            self.write_with_tree(&DCT_TOKEN_TREE, table, 0, DCT_EOB);
+        }
+        true
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        let mut c = self.bit_count;
+        let mut v = self.bottom;
+        if c < 32 && v & (1 << (32 - c)) != 0 {
+            self.add_one_to_output();
+        }
+        v <<= c & 7;
+        c >>= 3;
+        while c > 0 {
+            v <<= 8;
+            c -= 1;
+        }
+        for _ in 0..4 {
+            self.data.push((v >> 24) as u8);
+            v <<= 8;
+        }
+        self.data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{encode, forward_dct, forward_wht};
+    use crate::codecs::webp::transform;
+    use crate::codecs::webp::vp8::Vp8Decoder;
+    use std::io::Cursor;
+
+    #[test]
+    fn transforms_invert() {
+        let residual: Vec<i32> = (0..16).map(|i| (i * 37 % 23) - 11).collect();
+        let mut input = [0i32; 16];
+        input.copy_from_slice(&residual);
+
+        let mut coefficients = forward_dct(&input);
+        transform::idct4x4(&mut coefficients);
+        for (a, b) in coefficients.iter().zip(&input) {
+            assert!((a - b).abs() <= 1, "{:?} {:?}", coefficients, input);
+        }
+
+        let dcs: Vec<i32> = (0..16).map(|i| i * 50 - 400).collect();
+        input.copy_from_slice(&dcs);
+        let mut coefficients = forward_wht(&input);
+        transform::iwht4x4(&mut coefficients);
+        assert_eq!(coefficients, input);
+    }
+
+    /// The luma of the decoded frame, compared to the limited range luma of the source.
+    fn luma_error(rgb: &[u8], width: u32, height: u32, quality: u8) -> f64 {
+        let data = encode(rgb, width, height, quality).unwrap();
+        let mut decoder = Vp8Decoder::new(Cursor::new(data));
+        let frame = decoder.decode_frame().unwrap();
+        assert_eq!(
+            (u32::from(frame.width), u32::from(frame.height)),
+            (width, height)
+        );
+
+        let mut error = 0.0;
+        for (pixel, &luma) in rgb.chunks(3).zip(&frame.ybuf) {
+            let (r, g, b) = (
+                f64::from(pixel[0]),
+                f64::from(pixel[1]),
+                f64::from(pixel[2]),
+            );
+            let expected = 16.0 + 0.257 * r + 0.504 * g + 0.098 * b;
+            error += (expected - f64::from(luma)).abs();
+        }
+        error / f64::from(width * height)
+    }
+
+    #[test]
+    fn lossy_roundtrip() {
+        let (width, height) = (45u32, 37u32);
+        let rgb: Vec<u8> = (0..width * height)
+            .flat_map(|i| {
+                let (x, y) = (i % width, i / width);
+                let noise = (i.wrapping_mul(2_654_435_761) >> 28) as u8;
+                vec![(x * 5) as u8, (y * 6) as u8 ^ noise, ((x + y) * 3) as u8]
+            })
+            .collect();
+
+        let fine = luma_error(&rgb, width, height, 95);
+        let coarse = luma_error(&rgb, width, height, 10);
+        assert!(fine < 2.0, "{}", fine);
+        assert!(coarse < 12.0 && coarse > fine, "{} {}", coarse, fine);
+
+        // A flat image skips all macroblocks after the first.
+        assert!(luma_error(&[200; 3 * 40 * 20], 40, 20, 50) < 2.0);
+    }
+}
@@ -1,8 +1,10 @@
+use std::mem::size_of;
 use std::ops::{Index, IndexMut};
 
-use num_traits::{NumCast, ToPrimitive, Zero};
+use num_traits::{NumCast, Zero};
 
-use crate::traits::{Pixel, Primitive};
+use crate::traits::{BandArray, Pixel, Primitive};
+use crate::utils::clamp;
 
 /// An enumeration over supported color types and bit depths
 #[derive(Copy, PartialEq, Eq, Debug, Clone, Hash)]
@@ -35,6 +37,9 @@ pub enum ColorType {
     /// Pixel is 32-bit float RGBA
     Rgba32F,
 
+    /// Pixel contains 8-bit C, M, Y and K channels
+    Cmyk8,
+
     #[doc(hidden)]
     __NonExhaustive(crate::utils::NonExhaustiveMarker),
 }
@@ -46,7 +51,7 @@ impl ColorType {
             ColorType::L8 => 1,
             ColorType::L16 | ColorType::La8 => 2,
             ColorType::Rgb8 | ColorType::Bgr8 => 3,
-            ColorType::Rgba8 | ColorType::Bgra8 | ColorType::La16 => 4,
+            ColorType::Rgba8 | ColorType::Bgra8 | ColorType::La16 | ColorType::Cmyk8 => 4,
             ColorType::Rgb16 => 6,
             ColorType::Rgba16 => 8,
             ColorType::Rgb32F => 3 * 4,
@@ -59,7 +64,7 @@ impl ColorType {
     pub fn has_alpha(self) -> bool {
         use ColorType::*;
         match self {
-            L8 | L16 | Rgb8 | Bgr8 | Rgb16 | Rgb32F => false,
+            L8 | L16 | Rgb8 | Bgr8 | Rgb16 | Rgb32F | Cmyk8 => false,
             La8 | Rgba8 | Bgra8 | La16 | Rgba16 | Rgba32F => true,
             __NonExhaustive(marker) => match marker._private {},
         }
@@ -70,7 +75,7 @@ impl ColorType {
         use ColorType::*;
         match self {
             L8 | L16 | La8 | La16 => false,
-            Rgb8 | Bgr8 | Rgb16 | Rgba8 | Bgra8 | Rgba16 | Rgb32F | Rgba32F => true,
+            Rgb8 | Bgr8 | Rgb16 | Rgba8 | Bgra8 | Rgba16 | Rgb32F | Rgba32F | Cmyk8 => true,
             __NonExhaustive(marker) => match marker._private {},
         }
     }
@@ -151,6 +156,9 @@ pub enum ExtendedColorType {
     /// Pixel is 32-bit float RGBA
     Rgba32F,
 
+    /// Pixel contains 8-bit C, M, Y and K channels
+    Cmyk8,
+
     /// Pixel is of unknown color type with the specified bits per pixel. This can apply to pixels
     /// which are associated with an external palette. In that case, the pixel value is an index
     /// into the palette.
@@ -192,7 +200,8 @@ impl ExtendedColorType {
             ExtendedColorType::Rgba8 |
             ExtendedColorType::Rgba16 |
             ExtendedColorType::Rgba32F |
-            ExtendedColorType::Bgra8 => 4,
+            ExtendedColorType::Bgra8 |
+            ExtendedColorType::Cmyk8 => 4,
             ExtendedColorType::__NonExhaustive(marker) => match marker._private {},
         }
     }
@@ -212,6 +221,7 @@ impl From<ColorType> for ExtendedColorType {
             ColorType::Bgra8 => ExtendedColorType::Bgra8,
             ColorType::Rgb32F => ExtendedColorType::Rgb32F,
             ColorType::Rgba32F => ExtendedColorType::Rgba32F,
+            ColorType::Cmyk8 => ExtendedColorType::Cmyk8,
             ColorType::__NonExhaustive(marker) => match marker._private {},
         }
     }
@@ -225,6 +235,8 @@ macro_rules! define_colors {
         $interpretation: expr,
         $color_type_u8: expr,
         $color_type_u16: expr,
+        $color_type_f32: expr,
+        $exact_color_types: expr,
         #[$doc:meta];
     )*} => {
 
@@ -243,8 +255,14 @@ impl<T: Primitive + 'static> Pixel for $ident<T> {
 
     const COLOR_MODEL: &'static str = $interpretation;
 
-    const COLOR_TYPE: ColorType =
-        [$color_type_u8, $color_type_u16][(std::mem::size_of::<T>() > 1) as usize];
+    const COLOR_TYPE: ColorType = [
+        $color_type_u8,
+        $color_type_u16,
+        $color_type_f32,
+        [$color_type_u8, $color_type_u16][(std::mem::size_of::<T>() > 1) as usize],
+    ][T::COLOR_SAMPLE_INDEX];
+
+    const EXACT_COLOR_TYPE: Option<ColorType> = $exact_color_types[T::COLOR_SAMPLE_INDEX];
 
     #[inline(always)]
     fn channels(&self) -> &[T] {
@@ -257,7 +275,7 @@ impl<T: Primitive + 'static> Pixel for $ident<T> {
 
     fn channels4(&self) -> (T, T, T, T) {
         const CHANNELS: usize = $channels;
-        let mut channels = [T::max_value(); 4];
+        let mut channels = [T::default_max_value(); 4];
         channels[0..CHANNELS].copy_from_slice(&self.0);
         (channels[0], channels[1], channels[2], channels[3])
     }
@@ -384,18 +402,38 @@ impl<T: Primitive + 'static> From<[T; $channels]> for $ident<T> {
     }
 }
 
+// The pixel is `repr(C)` around an array of samples, so it has no padding and is valid for any
+// bits that are valid samples.
+unsafe impl<T: Primitive + bytemuck::Zeroable> bytemuck::Zeroable for $ident<T> {}
+unsafe impl<T: Primitive + bytemuck::Pod> bytemuck::Pod for $ident<T> {}
+
 )* // END Structure definitions
 
     }
 }
 
 define_colors! {
-    Rgb, 3, 0, "RGB", ColorType::Rgb8, ColorType::Rgb16, #[doc = "RGB colors"];
-    Bgr, 3, 0, "BGR", ColorType::Bgr8, ColorType::Bgr8, #[doc = "BGR colors"];
-    Luma, 1, 0, "Y", ColorType::L8, ColorType::L16, #[doc = "Grayscale colors"];
-    Rgba, 4, 1, "RGBA", ColorType::Rgba8, ColorType::Rgba16, #[doc = "RGB colors + alpha channel"];
-    Bgra, 4, 1, "BGRA", ColorType::Bgra8, ColorType::Bgra8, #[doc = "BGR colors + alpha channel"];
-    LumaA, 2, 1, "YA", ColorType::La8, ColorType::La16, #[doc = "Grayscale colors + alpha channel"];
+    Rgb, 3, 0, "RGB", ColorType::Rgb8, ColorType::Rgb16, ColorType::Rgb32F,
+        [Some(ColorType::Rgb8), Some(ColorType::Rgb16), Some(ColorType::Rgb32F), None],
+        #[doc = "RGB colors"];
+    Bgr, 3, 0, "BGR", ColorType::Bgr8, ColorType::Bgr8, ColorType::Bgr8,
+        [Some(ColorType::Bgr8), None, None, None],
+        #[doc = "BGR colors"];
+    Luma, 1, 0, "Y", ColorType::L8, ColorType::L16, ColorType::L16,
+        [Some(ColorType::L8), Some(ColorType::L16), None, None],
+        #[doc = "Grayscale colors"];
+    Rgba, 4, 1, "RGBA", ColorType::Rgba8, ColorType::Rgba16, ColorType::Rgba32F,
+        [Some(ColorType::Rgba8), Some(ColorType::Rgba16), Some(ColorType::Rgba32F), None],
+        #[doc = "RGB colors + alpha channel"];
+    Bgra, 4, 1, "BGRA", ColorType::Bgra8, ColorType::Bgra8, ColorType::Bgra8,
+        [Some(ColorType::Bgra8), None, None, None],
+        #[doc = "BGR colors + alpha channel"];
+    LumaA, 2, 1, "YA", ColorType::La8, ColorType::La16, ColorType::La16,
+        [Some(ColorType::La8), Some(ColorType::La16), None, None],
+        #[doc = "Grayscale colors + alpha channel"];
+    Cmyk, 4, 0, "CMYK", ColorType::Cmyk8, ColorType::Cmyk8, ColorType::Cmyk8,
+        [Some(ColorType::Cmyk8), None, None, None],
+        #[doc = "CMYK colors, the coverage of cyan, magenta, yellow and black ink"];
 }
 
 /// Provides color conversions for the different pixel types.
@@ -404,13 +442,6 @@ pub trait FromColor<Other> {
     fn from_color(&mut self, _: &Other);
 }
 
-// Self->Self: just copy
-impl<A: Copy> FromColor<A> for A {
-    fn from_color(&mut self, other: &A) {
-        *self = *other;
-    }
-}
-
 /// Copy-based conversions to target pixel types using `FromColor`.
 // FIXME: this trait should be removed and replaced with real color space models
 // rather than assuming sRGB.
@@ -450,657 +481,850 @@ fn bgr_to_luma<T: Primitive>(bgr: &[T]) -> T {
     NumCast::from(l).unwrap()
 }
 
+/// Converts CMYK to RGB without a color profile, as `(1 - C) * (1 - K)` for red and likewise
+/// for green and blue.
 #[inline]
-fn downcast_channel(c16: u16) -> u8 {
-    NumCast::from(c16.to_u64().unwrap() >> 8).unwrap()
+fn cmyk_to_rgb<T: Primitive>(cmyk: &[T]) -> [T; 3] {
+    let max = T::default_max_value().to_f32().unwrap();
+    let white = max - cmyk[3].to_f32().unwrap();
+    let channel = |ink: T| NumCast::from((max - ink.to_f32().unwrap()) * white / max).unwrap();
+    [channel(cmyk[0]), channel(cmyk[1]), channel(cmyk[2])]
 }
 
+/// Converts RGB to CMYK without a color profile, using as much black ink as possible.
 #[inline]
-fn upcast_channel(c8: u8) -> u16 {
-    let x = c8.to_u64().unwrap();
-    NumCast::from((x << 8) | x).unwrap()
-}
-
-
-// `FromColor` for Luma
-
-impl<T: Primitive + 'static> FromColor<Rgba<T>> for Luma<T> {
-    fn from_color(&mut self, other: &Rgba<T>) {
-        let gray = self.channels_mut();
-        let rgba = other.channels();
-        gray[0] = rgb_to_luma(rgba);
+fn rgb_to_cmyk<T: Primitive>(rgb: &[T]) -> [T; 4] {
+    let max = T::default_max_value().to_f32().unwrap();
+    let (r, g, b) = (rgb[0].to_f32().unwrap(), rgb[1].to_f32().unwrap(), rgb[2].to_f32().unwrap());
+    let white = r.max(g).max(b);
+    if white <= 0.0 {
+        return [T::zero(), T::zero(), T::zero(), T::default_max_value()];
     }
+    let ink = |channel: f32| round_sample((white - channel) * max / white, max);
+    [ink(r), ink(g), ink(b), round_sample(max - white, max)]
 }
 
-impl<T: Primitive + 'static> FromColor<Bgra<T>> for Luma<T> {
-    fn from_color(&mut self, other: &Bgra<T>) {
-        let gray = self.channels_mut();
-        let bgra = other.channels();
-        gray[0] = bgr_to_luma(bgra);
-    }
+/// Converts a single sample to another primitive type, scaling it to the value range of the
+/// target type.
+pub trait FromPrimitive<Component> {
+    /// Returns the sample in the value range of `Self`.
+    fn from_primitive(component: Component) -> Self;
 }
 
-impl<T: Primitive + 'static> FromColor<Rgb<T>> for Luma<T> {
-    fn from_color(&mut self, other: &Rgb<T>) {
-        let gray = self.channels_mut();
-        let rgb = other.channels();
-        gray[0] = rgb_to_luma(rgb);
+impl<T: Primitive> FromPrimitive<T> for T {
+    fn from_primitive(sample: T) -> Self {
+        sample
     }
 }
 
-impl<T: Primitive + 'static> FromColor<Bgr<T>> for Luma<T> {
-    fn from_color(&mut self, other: &Bgr<T>) {
-        let gray = self.channels_mut();
-        let bgr = other.channels();
-        gray[0] = bgr_to_luma(bgr);
+impl FromPrimitive<u16> for u8 {
+    fn from_primitive(c16: u16) -> Self {
+        (c16 >> 8) as u8
     }
 }
 
-impl<T: Primitive + 'static> FromColor<LumaA<T>> for Luma<T> {
-    fn from_color(&mut self, other: &LumaA<T>) {
-        self.channels_mut()[0] = other.channels()[0]
+impl FromPrimitive<u8> for u16 {
+    fn from_primitive(c8: u8) -> Self {
+        let x = c8 as u16;
+        (x << 8) | x
     }
 }
 
-
-impl FromColor<Rgba<u16>> for Luma<u8> {
-    fn from_color(&mut self, other: &Rgba<u16>) {
-        let gray = self.channels_mut();
-        let rgb = other.channels();
-        let l = rgb_to_luma(rgb);
-        gray[0] = downcast_channel(l);
+impl FromPrimitive<f32> for u8 {
+    fn from_primitive(float: f32) -> Self {
+        // NaN is mapped to 0 by the cast.
+        (clamp(float, 0.0, 1.0) * 255.0).round() as u8
     }
 }
 
-impl FromColor<Rgb<u16>> for Luma<u8> {
-    fn from_color(&mut self, other: &Rgb<u16>) {
-        let gray = self.channels_mut();
-        let rgb = other.channels();
-        let l = rgb_to_luma(rgb);
-        gray[0] = downcast_channel(l);
+impl FromPrimitive<f32> for u16 {
+    fn from_primitive(float: f32) -> Self {
+        (clamp(float, 0.0, 1.0) * 65535.0).round() as u16
     }
 }
 
-impl FromColor<Luma<u16>> for Luma<u8> {
-    fn from_color(&mut self, other: &Luma<u16>) {
-        let l = other.channels()[0];
-        self.channels_mut()[0] = downcast_channel(l);
+impl FromPrimitive<u8> for f32 {
+    fn from_primitive(c8: u8) -> Self {
+        c8 as f32 / 255.0
     }
 }
 
-impl FromColor<Luma<u8>> for Luma<u16> {
-    fn from_color(&mut self, other: &Luma<u8>) {
-        let l = other.channels()[0];
-        self.channels_mut()[0] = upcast_channel(l);
+impl FromPrimitive<u16> for f32 {
+    fn from_primitive(c16: u16) -> Self {
+        c16 as f32 / 65535.0
     }
 }
 
-impl FromColor<LumaA<u16>> for Luma<u8> {
-    fn from_color(&mut self, other: &LumaA<u16>) {
-        let l = other.channels()[0];
-        self.channels_mut()[0] = downcast_channel(l);
+// `FromColor` for Luma
+
+impl<S: Primitive + 'static, T: Primitive + 'static> FromColor<Rgba<S>> for Luma<T>
+where
+    T: FromPrimitive<S>,
+{
+    fn from_color(&mut self, other: &Rgba<S>) {
+        let rgba = other.channels();
+        let gray = self.channels_mut();
+        gray[0] = T::from_primitive(rgb_to_luma(rgba));
     }
 }
 
-impl FromColor<LumaA<u8>> for Luma<u16> {
-    fn from_color(&mut self, other: &LumaA<u8>) {
-        let la8 = other.channels();
+impl<S: Primitive + 'static, T: Primitive + 'static> FromColor<Bgra<S>> for Luma<T>
+where
+    T: FromPrimitive<S>,
+{
+    fn from_color(&mut self, other: &Bgra<S>) {
+        let bgra = other.channels();
         let gray = self.channels_mut();
-        gray[0] = upcast_channel(la8[0]);
+        gray[0] = T::from_primitive(bgr_to_luma(bgra));
     }
 }
 
-impl FromColor<Rgb<u8>> for Luma<u16> {
-    fn from_color(&mut self, other: &Rgb<u8>) {
+impl<S: Primitive + 'static, T: Primitive + 'static> FromColor<Rgb<S>> for Luma<T>
+where
+    T: FromPrimitive<S>,
+{
+    fn from_color(&mut self, other: &Rgb<S>) {
         let rgb = other.channels();
         let gray = self.channels_mut();
-        gray[0] = upcast_channel(rgb_to_luma(rgb));
+        gray[0] = T::from_primitive(rgb_to_luma(rgb));
     }
 }
 
-impl FromColor<Rgba<u8>> for Luma<u16> {
-    fn from_color(&mut self, other: &Rgba<u8>) {
-        let rgba = other.channels();
+impl<S: Primitive + 'static, T: Primitive + 'static> FromColor<Bgr<S>> for Luma<T>
+where
+    T: FromPrimitive<S>,
+{
+    fn from_color(&mut self, other: &Bgr<S>) {
+        let bgr = other.channels();
         let gray = self.channels_mut();
-        gray[0] = upcast_channel(rgb_to_luma(rgba));
+        gray[0] = T::from_primitive(bgr_to_luma(bgr));
     }
 }
 
-impl FromColor<Bgr<u8>> for Luma<u16> {
-    fn from_color(&mut self, other: &Bgr<u8>) {
-        let bgr = other.channels();
+impl<S: Primitive + 'static, T: Primitive + 'static> FromColor<LumaA<S>> for Luma<T>
+where
+    T: FromPrimitive<S>,
+{
+    fn from_color(&mut self, other: &LumaA<S>) {
+        let gray_a = other.channels();
         let gray = self.channels_mut();
-        gray[0] = upcast_channel(bgr_to_luma(bgr));
+        gray[0] = T::from_primitive(gray_a[0]);
     }
 }
 
-impl FromColor<Bgra<u8>> for Luma<u16> {
-    fn from_color(&mut self, other: &Bgra<u8>) {
-        let bgra = other.channels();
+impl<S: Primitive + 'static, T: Primitive + 'static> FromColor<Luma<S>> for Luma<T>
+where
+    T: FromPrimitive<S>,
+{
+    fn from_color(&mut self, other: &Luma<S>) {
+        let other = other.channels();
         let gray = self.channels_mut();
-        gray[0] = upcast_channel(bgr_to_luma(bgra));
+        gray[0] = T::from_primitive(other[0]);
     }
 }
 
-
 // `FromColor` for LumaA
 
-impl<T: Primitive + 'static> FromColor<Rgba<T>> for LumaA<T> {
-    fn from_color(&mut self, other: &Rgba<T>) {
-        let gray_a = self.channels_mut();
+impl<S: Primitive + 'static, T: Primitive + 'static> FromColor<Rgba<S>> for LumaA<T>
+where
+    T: FromPrimitive<S>,
+{
+    fn from_color(&mut self, other: &Rgba<S>) {
         let rgba = other.channels();
-        gray_a[0] = rgb_to_luma(rgba);
-        gray_a[1] = rgba[3];
+        let gray_a = self.channels_mut();
+        gray_a[0] = T::from_primitive(rgb_to_luma(rgba));
+        gray_a[1] = T::from_primitive(rgba[3]);
     }
 }
 
-impl<T: Primitive + 'static> FromColor<Bgra<T>> for LumaA<T> {
-    fn from_color(&mut self, other: &Bgra<T>) {
-        let gray_a = self.channels_mut();
+impl<S: Primitive + 'static, T: Primitive + 'static> FromColor<Bgra<S>> for LumaA<T>
+where
+    T: FromPrimitive<S>,
+{
+    fn from_color(&mut self, other: &Bgra<S>) {
         let bgra = other.channels();
-        gray_a[0] = bgr_to_luma(bgra);
-        gray_a[1] = bgra[3];
+        let gray_a = self.channels_mut();
+        gray_a[0] = T::from_primitive(bgr_to_luma(bgra));
+        gray_a[1] = T::from_primitive(bgra[3]);
     }
 }
 
-impl<T: Primitive + 'static> FromColor<Rgb<T>> for LumaA<T> {
-    fn from_color(&mut self, other: &Rgb<T>) {
-        let gray_a = self.channels_mut();
+impl<S: Primitive + 'static, T: Primitive + 'static> FromColor<Rgb<S>> for LumaA<T>
+where
+    T: FromPrimitive<S>,
+{
+    fn from_color(&mut self, other: &Rgb<S>) {
         let rgb = other.channels();
-        gray_a[0] = rgb_to_luma(rgb);
-        gray_a[1] = T::max_value();
+        let gray_a = self.channels_mut();
+        gray_a[0] = T::from_primitive(rgb_to_luma(rgb));
+        gray_a[1] = T::default_max_value();
     }
 }
 
-impl<T: Primitive + 'static> FromColor<Bgr<T>> for LumaA<T> {
-    fn from_color(&mut self, other: &Bgr<T>) {
-        let gray_a = self.channels_mut();
+impl<S: Primitive + 'static, T: Primitive + 'static> FromColor<Bgr<S>> for LumaA<T>
+where
+    T: FromPrimitive<S>,
+{
+    fn from_color(&mut self, other: &Bgr<S>) {
         let bgr = other.channels();
-        gray_a[0] = bgr_to_luma(bgr);
-        gray_a[1] = T::max_value();
+        let gray_a = self.channels_mut();
+        gray_a[0] = T::from_primitive(bgr_to_luma(bgr));
+        gray_a[1] = T::default_max_value();
     }
 }
 
-impl<T: Primitive + 'static> FromColor<Luma<T>> for LumaA<T> {
-    fn from_color(&mut self, other: &Luma<T>) {
+impl<S: Primitive + 'static, T: Primitive + 'static> FromColor<LumaA<S>> for LumaA<T>
+where
+    T: FromPrimitive<S>,
+{
+    fn from_color(&mut self, other: &LumaA<S>) {
+        let other = other.channels();
         let gray_a = self.channels_mut();
-        gray_a[0] = other.channels()[0];
-        gray_a[1] = T::max_value();
+        gray_a[0] = T::from_primitive(other[0]);
+        gray_a[1] = T::from_primitive(other[1]);
     }
 }
 
-impl FromColor<LumaA<u16>> for LumaA<u8> {
-    fn from_color(&mut self, other: &LumaA<u16>) {
-        let la8 = self.channels_mut();
-        let gray = other.channels()[0];
-        let alpha = other.channels()[1];
-        la8[0] = downcast_channel(gray);
-        la8[1] = downcast_channel(alpha);
+impl<S: Primitive + 'static, T: Primitive + 'static> FromColor<Luma<S>> for LumaA<T>
+where
+    T: FromPrimitive<S>,
+{
+    fn from_color(&mut self, other: &Luma<S>) {
+        let gray = other.channels();
+        let gray_a = self.channels_mut();
+        gray_a[0] = T::from_primitive(gray[0]);
+        gray_a[1] = T::default_max_value();
     }
 }
 
-impl FromColor<LumaA<u8>> for LumaA<u16> {
-    fn from_color(&mut self, other: &LumaA<u8>) {
-        let la8 = self.channels_mut();
-        let gray = other.channels()[0];
-        let alpha = other.channels()[1];
-        la8[0] = upcast_channel(gray);
-        la8[1] = upcast_channel(alpha);
+// `FromColor` for RGBA
+
+impl<S: Primitive + 'static, T: Primitive + 'static> FromColor<Rgba<S>> for Rgba<T>
+where
+    T: FromPrimitive<S>,
+{
+    fn from_color(&mut self, other: &Rgba<S>) {
+        let other = other.channels();
+        let rgba = self.channels_mut();
+        rgba[0] = T::from_primitive(other[0]);
+        rgba[1] = T::from_primitive(other[1]);
+        rgba[2] = T::from_primitive(other[2]);
+        rgba[3] = T::from_primitive(other[3]);
     }
 }
 
-impl FromColor<Luma<u8>> for LumaA<u16> {
-    fn from_color(&mut self, other: &Luma<u8>) {
-        let l8 = other.channels()[0];
-        let gray_a = self.channels_mut();
-        gray_a[0] = upcast_channel(l8);
-        gray_a[1] = u16::max_value();
+impl<S: Primitive + 'static, T: Primitive + 'static> FromColor<Bgra<S>> for Rgba<T>
+where
+    T: FromPrimitive<S>,
+{
+    fn from_color(&mut self, other: &Bgra<S>) {
+        let bgra = other.channels();
+        let rgba = self.channels_mut();
+        rgba[0] = T::from_primitive(bgra[2]);
+        rgba[1] = T::from_primitive(bgra[1]);
+        rgba[2] = T::from_primitive(bgra[0]);
+        rgba[3] = T::from_primitive(bgra[3]);
     }
 }
 
-impl FromColor<Rgb<u8>> for LumaA<u16> {
-    fn from_color(&mut self, other: &Rgb<u8>) {
+impl<S: Primitive + 'static, T: Primitive + 'static> FromColor<Rgb<S>> for Rgba<T>
+where
+    T: FromPrimitive<S>,
+{
+    fn from_color(&mut self, other: &Rgb<S>) {
         let rgb = other.channels();
-        let gray_a = self.channels_mut();
-        gray_a[0] = upcast_channel(rgb_to_luma(rgb));
-        gray_a[1] = u16::max_value();
+        let rgba = self.channels_mut();
+        rgba[0] = T::from_primitive(rgb[0]);
+        rgba[1] = T::from_primitive(rgb[1]);
+        rgba[2] = T::from_primitive(rgb[2]);
+        rgba[3] = T::default_max_value();
     }
 }
 
-impl FromColor<Rgba<u8>> for LumaA<u16> {
-    fn from_color(&mut self, other: &Rgba<u8>) {
-        let rgba = other.channels();
-        let gray_a = self.channels_mut();
-        gray_a[0] = upcast_channel(rgb_to_luma(rgba));
-        gray_a[1] = upcast_channel(rgba[3]);
+impl<S: Primitive + 'static, T: Primitive + 'static> FromColor<Bgr<S>> for Rgba<T>
+where
+    T: FromPrimitive<S>,
+{
+    fn from_color(&mut self, other: &Bgr<S>) {
+        let bgr = other.channels();
+        let rgba = self.channels_mut();
+        rgba[0] = T::from_primitive(bgr[2]);
+        rgba[1] = T::from_primitive(bgr[1]);
+        rgba[2] = T::from_primitive(bgr[0]);
+        rgba[3] = T::default_max_value();
     }
 }
 
-impl FromColor<Bgr<u8>> for LumaA<u16> {
-    fn from_color(&mut self, other: &Bgr<u8>) {
-        let bgr = other.channels();
-        let gray_a = self.channels_mut();
-        gray_a[0] = upcast_channel(bgr_to_luma(bgr));
-        gray_a[1] = u16::max_value();
+impl<S: Primitive + 'static, T: Primitive + 'static> FromColor<LumaA<S>> for Rgba<T>
+where
+    T: FromPrimitive<S>,
+{
+    fn from_color(&mut self, other: &LumaA<S>) {
+        let gray_a = other.channels();
+        let rgba = self.channels_mut();
+        rgba[0] = T::from_primitive(gray_a[0]);
+        rgba[1] = T::from_primitive(gray_a[0]);
+        rgba[2] = T::from_primitive(gray_a[0]);
+        rgba[3] = T::from_primitive(gray_a[1]);
     }
 }
 
-impl FromColor<Bgra<u8>> for LumaA<u16> {
-    fn from_color(&mut self, other: &Bgra<u8>) {
-        let bgra = other.channels();
-        let gray_a = self.channels_mut();
-        gray_a[0] = upcast_channel(bgr_to_luma(bgra));
-        gray_a[1] = upcast_channel(bgra[3]);
+impl<S: Primitive + 'static, T: Primitive + 'static> FromColor<Luma<S>> for Rgba<T>
+where
+    T: FromPrimitive<S>,
+{
+    fn from_color(&mut self, other: &Luma<S>) {
+        let gray = other.channels();
+        let rgba = self.channels_mut();
+        rgba[0] = T::from_primitive(gray[0]);
+        rgba[1] = T::from_primitive(gray[0]);
+        rgba[2] = T::from_primitive(gray[0]);
+        rgba[3] = T::default_max_value();
     }
 }
 
+// `FromColor` for BGRA
 
-// `FromColor` for RGBA
+impl<S: Primitive + 'static, T: Primitive + 'static> FromColor<Rgba<S>> for Bgra<T>
+where
+    T: FromPrimitive<S>,
+{
+    fn from_color(&mut self, other: &Rgba<S>) {
+        let rgba = other.channels();
+        let bgra = self.channels_mut();
+        bgra[0] = T::from_primitive(rgba[2]);
+        bgra[1] = T::from_primitive(rgba[1]);
+        bgra[2] = T::from_primitive(rgba[0]);
+        bgra[3] = T::from_primitive(rgba[3]);
+    }
+}
 
-impl<T: Primitive + 'static> FromColor<Rgb<T>> for Rgba<T> {
-    fn from_color(&mut self, other: &Rgb<T>) {
-        let rgba = self.channels_mut();
+impl<S: Primitive + 'static, T: Primitive + 'static> FromColor<Bgra<S>> for Bgra<T>
+where
+    T: FromPrimitive<S>,
+{
+    fn from_color(&mut self, other: &Bgra<S>) {
+        let other = other.channels();
+        let bgra = self.channels_mut();
+        bgra[0] = T::from_primitive(other[0]);
+        bgra[1] = T::from_primitive(other[1]);
+        bgra[2] = T::from_primitive(other[2]);
+        bgra[3] = T::from_primitive(other[3]);
+    }
+}
+
+impl<S: Primitive + 'static, T: Primitive + 'static> FromColor<Rgb<S>> for Bgra<T>
+where
+    T: FromPrimitive<S>,
+{
+    fn from_color(&mut self, other: &Rgb<S>) {
         let rgb = other.channels();
-        rgba[0] = rgb[0];
-        rgba[1] = rgb[1];
-        rgba[2] = rgb[2];
-        rgba[3] = T::max_value();
+        let bgra = self.channels_mut();
+        bgra[0] = T::from_primitive(rgb[2]);
+        bgra[1] = T::from_primitive(rgb[1]);
+        bgra[2] = T::from_primitive(rgb[0]);
+        bgra[3] = T::default_max_value();
     }
 }
 
-impl<T: Primitive + 'static> FromColor<Bgr<T>> for Rgba<T> {
-    fn from_color(&mut self, other: &Bgr<T>) {
-        let rgba = self.channels_mut();
+impl<S: Primitive + 'static, T: Primitive + 'static> FromColor<Bgr<S>> for Bgra<T>
+where
+    T: FromPrimitive<S>,
+{
+    fn from_color(&mut self, other: &Bgr<S>) {
         let bgr = other.channels();
-        rgba[0] = bgr[2];
-        rgba[1] = bgr[1];
-        rgba[2] = bgr[0];
-        rgba[3] = T::max_value();
+        let bgra = self.channels_mut();
+        bgra[0] = T::from_primitive(bgr[0]);
+        bgra[1] = T::from_primitive(bgr[1]);
+        bgra[2] = T::from_primitive(bgr[2]);
+        bgra[3] = T::default_max_value();
     }
 }
 
-impl<T: Primitive + 'static> FromColor<Bgra<T>> for Rgba<T> {
-    fn from_color(&mut self, other: &Bgra<T>) {
-        let rgba = self.channels_mut();
-        let bgra = other.channels();
-        rgba[0] = bgra[2];
-        rgba[1] = bgra[1];
-        rgba[2] = bgra[0];
-        rgba[3] = bgra[3];
+impl<S: Primitive + 'static, T: Primitive + 'static> FromColor<LumaA<S>> for Bgra<T>
+where
+    T: FromPrimitive<S>,
+{
+    fn from_color(&mut self, other: &LumaA<S>) {
+        let gray_a = other.channels();
+        let bgra = self.channels_mut();
+        bgra[0] = T::from_primitive(gray_a[0]);
+        bgra[1] = T::from_primitive(gray_a[0]);
+        bgra[2] = T::from_primitive(gray_a[0]);
+        bgra[3] = T::from_primitive(gray_a[1]);
     }
 }
 
-impl<T: Primitive + 'static> FromColor<LumaA<T>> for Rgba<T> {
-    fn from_color(&mut self, other: &LumaA<T>) {
-        let rgba = self.channels_mut();
+impl<S: Primitive + 'static, T: Primitive + 'static> FromColor<Luma<S>> for Bgra<T>
+where
+    T: FromPrimitive<S>,
+{
+    fn from_color(&mut self, other: &Luma<S>) {
         let gray = other.channels();
-        rgba[0] = gray[0];
-        rgba[1] = gray[0];
-        rgba[2] = gray[0];
-        rgba[3] = gray[1];
+        let bgra = self.channels_mut();
+        bgra[0] = T::from_primitive(gray[0]);
+        bgra[1] = T::from_primitive(gray[0]);
+        bgra[2] = T::from_primitive(gray[0]);
+        bgra[3] = T::default_max_value();
     }
 }
 
-impl<T: Primitive + 'static> FromColor<Luma<T>> for Rgba<T> {
-    fn from_color(&mut self, gray: &Luma<T>) {
-        let rgba = self.channels_mut();
-        let gray = gray.channels()[0];
-        rgba[0] = gray;
-        rgba[1] = gray;
-        rgba[2] = gray;
-        rgba[3] = T::max_value();
+// `FromColor` for RGB
+
+impl<S: Primitive + 'static, T: Primitive + 'static> FromColor<Rgba<S>> for Rgb<T>
+where
+    T: FromPrimitive<S>,
+{
+    fn from_color(&mut self, other: &Rgba<S>) {
+        let rgba = other.channels();
+        let rgb = self.channels_mut();
+        rgb[0] = T::from_primitive(rgba[0]);
+        rgb[1] = T::from_primitive(rgba[1]);
+        rgb[2] = T::from_primitive(rgba[2]);
     }
 }
 
-impl FromColor<Rgba<u16>> for Rgba<u8> {
-    fn from_color(&mut self, other: &Rgba<u16>) {
-        let rgba = self.channels_mut();
-        let rgba16 = other.channels();
-        rgba[0] = downcast_channel(rgba16[0]);
-        rgba[1] = downcast_channel(rgba16[1]);
-        rgba[2] = downcast_channel(rgba16[2]);
-        rgba[3] = downcast_channel(rgba16[3]);
+impl<S: Primitive + 'static, T: Primitive + 'static> FromColor<Bgra<S>> for Rgb<T>
+where
+    T: FromPrimitive<S>,
+{
+    fn from_color(&mut self, other: &Bgra<S>) {
+        let bgra = other.channels();
+        let rgb = self.channels_mut();
+        rgb[0] = T::from_primitive(bgra[2]);
+        rgb[1] = T::from_primitive(bgra[1]);
+        rgb[2] = T::from_primitive(bgra[0]);
     }
 }
 
-impl FromColor<Rgba<u8>> for Rgba<u16> {
-    fn from_color(&mut self, other: &Rgba<u8>) {
-        let rgba = self.channels_mut();
-        let rgba8 = other.channels();
-        rgba[0] = upcast_channel(rgba8[0]);
-        rgba[1] = upcast_channel(rgba8[1]);
-        rgba[2] = upcast_channel(rgba8[2]);
-        rgba[3] = upcast_channel(rgba8[3]);
+impl<S: Primitive + 'static, T: Primitive + 'static> FromColor<Rgb<S>> for Rgb<T>
+where
+    T: FromPrimitive<S>,
+{
+    fn from_color(&mut self, other: &Rgb<S>) {
+        let other = other.channels();
+        let rgb = self.channels_mut();
+        rgb[0] = T::from_primitive(other[0]);
+        rgb[1] = T::from_primitive(other[1]);
+        rgb[2] = T::from_primitive(other[2]);
     }
 }
 
-impl FromColor<LumaA<u8>> for Rgba<u16> {
-    fn from_color(&mut self, other: &LumaA<u8>) {
-        let la8 = other.channels();
-        let gray = upcast_channel(la8[0]);
-        let alpha = upcast_channel(la8[1]);
-        let rgba = self.channels_mut();
-        rgba[0] = gray;
-        rgba[1] = gray;
-        rgba[2] = gray;
-        rgba[3] = alpha;
+impl<S: Primitive + 'static, T: Primitive + 'static> FromColor<Bgr<S>> for Rgb<T>
+where
+    T: FromPrimitive<S>,
+{
+    fn from_color(&mut self, other: &Bgr<S>) {
+        let bgr = other.channels();
+        let rgb = self.channels_mut();
+        rgb[0] = T::from_primitive(bgr[2]);
+        rgb[1] = T::from_primitive(bgr[1]);
+        rgb[2] = T::from_primitive(bgr[0]);
     }
 }
 
-impl FromColor<Rgb<u8>> for Rgba<u16> {
-    fn from_color(&mut self, other: &Rgb<u8>) {
-        let rgb = other.channels();
-        let rgba = self.channels_mut();
-        rgba[0] = upcast_channel(rgb[0]);
-        rgba[1] = upcast_channel(rgb[1]);
-        rgba[2] = upcast_channel(rgb[2]);
-        rgba[3] = u16::max_value();
+impl<S: Primitive + 'static, T: Primitive + 'static> FromColor<LumaA<S>> for Rgb<T>
+where
+    T: FromPrimitive<S>,
+{
+    fn from_color(&mut self, other: &LumaA<S>) {
+        let gray_a = other.channels();
+        let rgb = self.channels_mut();
+        rgb[0] = T::from_primitive(gray_a[0]);
+        rgb[1] = T::from_primitive(gray_a[0]);
+        rgb[2] = T::from_primitive(gray_a[0]);
     }
 }
 
-impl FromColor<Luma<u8>> for Rgba<u16> {
-    fn from_color(&mut self, other: &Luma<u8>) {
-        let l8 = other.channels();
-        let rgba = self.channels_mut();
-        let gray = upcast_channel(l8[0]);
-        rgba[0] = gray;
-        rgba[1] = gray;
-        rgba[2] = gray;
-        rgba[3] = u16::max_value();
+impl<S: Primitive + 'static, T: Primitive + 'static> FromColor<Luma<S>> for Rgb<T>
+where
+    T: FromPrimitive<S>,
+{
+    fn from_color(&mut self, other: &Luma<S>) {
+        let gray = other.channels();
+        let rgb = self.channels_mut();
+        rgb[0] = T::from_primitive(gray[0]);
+        rgb[1] = T::from_primitive(gray[0]);
+        rgb[2] = T::from_primitive(gray[0]);
     }
 }
 
-impl FromColor<Bgr<u8>> for Rgba<u16> {
-    fn from_color(&mut self, other: &Bgr<u8>) {
-        let bgr = other.channels();
-        let rgba = self.channels_mut();
-        rgba[0] = upcast_channel(bgr[2]);
-        rgba[1] = upcast_channel(bgr[1]);
-        rgba[2] = upcast_channel(bgr[0]);
-        rgba[3] = u16::max_value();
+// `FromColor` for BGR
+
+impl<S: Primitive + 'static, T: Primitive + 'static> FromColor<Rgba<S>> for Bgr<T>
+where
+    T: FromPrimitive<S>,
+{
+    fn from_color(&mut self, other: &Rgba<S>) {
+        let rgba = other.channels();
+        let bgr = self.channels_mut();
+        bgr[0] = T::from_primitive(rgba[2]);
+        bgr[1] = T::from_primitive(rgba[1]);
+        bgr[2] = T::from_primitive(rgba[0]);
     }
 }
 
-impl FromColor<Bgra<u8>> for Rgba<u16> {
-    fn from_color(&mut self, other: &Bgra<u8>) {
+impl<S: Primitive + 'static, T: Primitive + 'static> FromColor<Bgra<S>> for Bgr<T>
+where
+    T: FromPrimitive<S>,
+{
+    fn from_color(&mut self, other: &Bgra<S>) {
         let bgra = other.channels();
-        let rgba = self.channels_mut();
-        rgba[0] = upcast_channel(bgra[2]);
-        rgba[1] = upcast_channel(bgra[1]);
-        rgba[2] = upcast_channel(bgra[0]);
-        rgba[3] = upcast_channel(bgra[3]);
+        let bgr = self.channels_mut();
+        bgr[0] = T::from_primitive(bgra[0]);
+        bgr[1] = T::from_primitive(bgra[1]);
+        bgr[2] = T::from_primitive(bgra[2]);
     }
 }
 
-// `FromColor` for BGRA
-
-impl<T: Primitive + 'static> FromColor<Rgb<T>> for Bgra<T> {
-    fn from_color(&mut self, other: &Rgb<T>) {
-        let bgra = self.channels_mut();
+impl<S: Primitive + 'static, T: Primitive + 'static> FromColor<Rgb<S>> for Bgr<T>
+where
+    T: FromPrimitive<S>,
+{
+    fn from_color(&mut self, other: &Rgb<S>) {
         let rgb = other.channels();
-        bgra[0] = rgb[2];
-        bgra[1] = rgb[1];
-        bgra[2] = rgb[0];
-        bgra[3] = T::max_value();
+        let bgr = self.channels_mut();
+        bgr[0] = T::from_primitive(rgb[2]);
+        bgr[1] = T::from_primitive(rgb[1]);
+        bgr[2] = T::from_primitive(rgb[0]);
     }
 }
 
-impl<T: Primitive + 'static> FromColor<Bgr<T>> for Bgra<T> {
-    fn from_color(&mut self, other: &Bgr<T>) {
-        let bgra = self.channels_mut();
-        let bgr = other.channels();
-        bgra[0] = bgr[0];
-        bgra[1] = bgr[1];
-        bgra[2] = bgr[2];
-        bgra[3] = T::max_value();
+impl<S: Primitive + 'static, T: Primitive + 'static> FromColor<Bgr<S>> for Bgr<T>
+where
+    T: FromPrimitive<S>,
+{
+    fn from_color(&mut self, other: &Bgr<S>) {
+        let other = other.channels();
+        let bgr = self.channels_mut();
+        bgr[0] = T::from_primitive(other[0]);
+        bgr[1] = T::from_primitive(other[1]);
+        bgr[2] = T::from_primitive(other[2]);
     }
 }
 
-impl<T: Primitive + 'static> FromColor<Rgba<T>> for Bgra<T> {
-    fn from_color(&mut self, other: &Rgba<T>) {
-        let bgra = self.channels_mut();
-        let rgba = other.channels();
-        bgra[2] = rgba[0];
-        bgra[1] = rgba[1];
-        bgra[0] = rgba[2];
-        bgra[3] = rgba[3];
+impl<S: Primitive + 'static, T: Primitive + 'static> FromColor<LumaA<S>> for Bgr<T>
+where
+    T: FromPrimitive<S>,
+{
+    fn from_color(&mut self, other: &LumaA<S>) {
+        let gray_a = other.channels();
+        let bgr = self.channels_mut();
+        bgr[0] = T::from_primitive(gray_a[0]);
+        bgr[1] = T::from_primitive(gray_a[0]);
+        bgr[2] = T::from_primitive(gray_a[0]);
     }
 }
 
-impl<T: Primitive + 'static> FromColor<LumaA<T>> for Bgra<T> {
-    fn from_color(&mut self, other: &LumaA<T>) {
-        let bgra = self.channels_mut();
+impl<S: Primitive + 'static, T: Primitive + 'static> FromColor<Luma<S>> for Bgr<T>
+where
+    T: FromPrimitive<S>,
+{
+    fn from_color(&mut self, other: &Luma<S>) {
         let gray = other.channels();
-        bgra[0] = gray[0];
-        bgra[1] = gray[0];
-        bgra[2] = gray[0];
-        bgra[3] = gray[1];
+        let bgr = self.channels_mut();
+        bgr[0] = T::from_primitive(gray[0]);
+        bgr[1] = T::from_primitive(gray[0]);
+        bgr[2] = T::from_primitive(gray[0]);
     }
 }
 
-impl<T: Primitive + 'static> FromColor<Luma<T>> for Bgra<T> {
-    fn from_color(&mut self, gray: &Luma<T>) {
-        let bgra = self.channels_mut();
-        let gray = gray.channels()[0];
-        bgra[0] = gray;
-        bgra[1] = gray;
-        bgra[2] = gray;
-        bgra[3] = T::max_value();
+// `FromColor` from and to CMYK
+
+impl<S: Primitive + 'static, T: Primitive + 'static> FromColor<Cmyk<S>> for Rgb<T>
+where
+    T: FromPrimitive<S>,
+{
+    fn from_color(&mut self, other: &Cmyk<S>) {
+        let [r, g, b] = cmyk_to_rgb(other.channels());
+        self.0 = [T::from_primitive(r), T::from_primitive(g), T::from_primitive(b)];
     }
 }
 
-
-// `FromColor` for RGB
-
-impl<T: Primitive + 'static> FromColor<Rgba<T>> for Rgb<T> {
-    fn from_color(&mut self, other: &Rgba<T>) {
-        let rgb = self.channels_mut();
-        let rgba = other.channels();
-        rgb[0] = rgba[0];
-        rgb[1] = rgba[1];
-        rgb[2] = rgba[2];
+impl<S: Primitive + 'static, T: Primitive + 'static> FromColor<Cmyk<S>> for Bgr<T>
+where
+    T: FromPrimitive<S>,
+{
+    fn from_color(&mut self, other: &Cmyk<S>) {
+        let [r, g, b] = cmyk_to_rgb(other.channels());
+        self.0 = [T::from_primitive(b), T::from_primitive(g), T::from_primitive(r)];
     }
 }
 
-impl<T: Primitive + 'static> FromColor<Bgra<T>> for Rgb<T> {
-    fn from_color(&mut self, other: &Bgra<T>) {
-        let rgb = self.channels_mut();
-        let bgra = other.channels();
-        rgb[0] = bgra[2];
-        rgb[1] = bgra[1];
-        rgb[2] = bgra[0];
+impl<S: Primitive + 'static, T: Primitive + 'static> FromColor<Cmyk<S>> for Rgba<T>
+where
+    T: FromPrimitive<S>,
+{
+    fn from_color(&mut self, other: &Cmyk<S>) {
+        let [r, g, b] = cmyk_to_rgb(other.channels());
+        let (r, g, b) = (T::from_primitive(r), T::from_primitive(g), T::from_primitive(b));
+        self.0 = [r, g, b, T::default_max_value()];
     }
 }
 
-impl<T: Primitive + 'static> FromColor<Bgr<T>> for Rgb<T> {
-    fn from_color(&mut self, other: &Bgr<T>) {
-        let rgb = self.channels_mut();
-        let bgr = other.channels();
-        rgb[0] = bgr[2];
-        rgb[1] = bgr[1];
-        rgb[2] = bgr[0];
+impl<S: Primitive + 'static, T: Primitive + 'static> FromColor<Cmyk<S>> for Bgra<T>
+where
+    T: FromPrimitive<S>,
+{
+    fn from_color(&mut self, other: &Cmyk<S>) {
+        let [r, g, b] = cmyk_to_rgb(other.channels());
+        let (r, g, b) = (T::from_primitive(r), T::from_primitive(g), T::from_primitive(b));
+        self.0 = [b, g, r, T::default_max_value()];
     }
 }
 
-impl<T: Primitive + 'static> FromColor<LumaA<T>> for Rgb<T> {
-    fn from_color(&mut self, other: &LumaA<T>) {
-        let rgb = self.channels_mut();
-        let gray = other.channels()[0];
-        rgb[0] = gray;
-        rgb[1] = gray;
-        rgb[2] = gray;
+impl<S: Primitive + 'static, T: Primitive + 'static> FromColor<Cmyk<S>> for Luma<T>
+where
+    T: FromPrimitive<S>,
+{
+    fn from_color(&mut self, other: &Cmyk<S>) {
+        let rgb = cmyk_to_rgb(other.channels());
+        self.0 = [T::from_primitive(rgb_to_luma(&rgb))];
     }
 }
 
-impl<T: Primitive + 'static> FromColor<Luma<T>> for Rgb<T> {
-    fn from_color(&mut self, gray: &Luma<T>) {
-        let rgb = self.channels_mut();
-        let gray = gray.channels()[0];
-        rgb[0] = gray;
-        rgb[1] = gray;
-        rgb[2] = gray;
+impl<S: Primitive + 'static, T: Primitive + 'static> FromColor<Cmyk<S>> for LumaA<T>
+where
+    T: FromPrimitive<S>,
+{
+    fn from_color(&mut self, other: &Cmyk<S>) {
+        let rgb = cmyk_to_rgb(other.channels());
+        self.0 = [T::from_primitive(rgb_to_luma(&rgb)), T::default_max_value()];
     }
 }
 
-impl FromColor<Rgb<u16>> for Rgb<u8> {
-    fn from_color(&mut self, other: &Rgb<u16>) {
-        for (c8, &c16) in self.channels_mut().iter_mut().zip(other.channels()) {
-            *c8 = downcast_channel(c16);
+impl<S: Primitive + 'static, T: Primitive + 'static> FromColor<Cmyk<S>> for Cmyk<T>
+where
+    T: FromPrimitive<S>,
+{
+    fn from_color(&mut self, other: &Cmyk<S>) {
+        for (to, &from) in self.0.iter_mut().zip(other.0.iter()) {
+            *to = T::from_primitive(from);
         }
     }
 }
 
-impl FromColor<Rgb<u8>> for Rgb<u16> {
-    fn from_color(&mut self, other: &Rgb<u8>) {
-        for (c8, &c16) in self.channels_mut().iter_mut().zip(other.channels()) {
-            *c8 = upcast_channel(c16);
+// Other colors are converted to RGB first, alpha is dropped.
+macro_rules! impl_cmyk_from_color {
+    ($($ident:ident),*) => {$(
+        impl<S: Primitive + 'static, T: Primitive + 'static> FromColor<$ident<S>> for Cmyk<T>
+        where
+            T: FromPrimitive<S>,
+        {
+            fn from_color(&mut self, other: &$ident<S>) {
+                let cmyk = rgb_to_cmyk(&other.to_rgb().0);
+                for (to, &from) in self.0.iter_mut().zip(cmyk.iter()) {
+                    *to = T::from_primitive(from);
+                }
+            }
         }
-    }
+    )*}
 }
 
-impl FromColor<LumaA<u8>> for Rgb<u16> {
-    fn from_color(&mut self, other: &LumaA<u8>) {
-        let la8 = other.channels();
-        let gray = upcast_channel(la8[0]);
-        let rgb = self.channels_mut();
-        rgb[0] = gray;
-        rgb[1] = gray;
-        rgb[2] = gray;
+impl_cmyk_from_color!(Rgb, Bgr, Rgba, Bgra, Luma, LumaA);
+
+/// A pixel of any number of bands, such as the channels of multispectral images, colors with
+/// an additional depth channel or two-dimensional vector fields.
+///
+/// The bands are given by an array of up to 32 samples, so `Bands<[f32; 5]>` holds five float
+/// samples. No band is treated as alpha. When the pixel is converted to a color, a single or
+/// two bands are taken as gray and otherwise the first three bands as red, green and blue.
+/// No `ColorType` describes the bands, its `EXACT_COLOR_TYPE` is `None` and its `COLOR_TYPE`
+/// is that of the gray or RGB colors.
+///
+/// # Examples
+///
+/// ```
+/// use image::{Bands, ImageBuffer, Pixel};
+///
+/// // Optical flow with a horizontal and a vertical component.
+/// let mut flow = ImageBuffer::from_pixel(4, 3, Bands([0.5f32, -0.25]));
+/// for pixel in flow.pixels_mut() {
+///     pixel.apply(|component| component * 2.0);
+/// }
+/// assert_eq!(flow.get_pixel(3, 2), &Bands([1.0, -0.5]));
+/// ```
+#[derive(PartialEq, Eq, Clone, Debug, Copy, Hash)]
+#[repr(transparent)]
+pub struct Bands<A: BandArray>(pub A);
+
+impl<A: BandArray> Bands<A> {
+    /// The samples that are converted to red, green and blue.
+    fn rgb(&self) -> [A::Sample; 3] {
+        let bands = self.0.as_slice();
+        if bands.len() >= 3 {
+            [bands[0], bands[1], bands[2]]
+        } else {
+            [bands[0]; 3]
+        }
     }
-}
 
-impl FromColor<Rgba<u8>> for Rgb<u16> {
-    fn from_color(&mut self, other: &Rgba<u8>) {
-        let rgba = other.channels();
-        let rgb = self.channels_mut();
-        rgb[0] = upcast_channel(rgba[0]);
-        rgb[1] = upcast_channel(rgba[1]);
-        rgb[2] = upcast_channel(rgba[2]);
+    fn luma(&self) -> A::Sample {
+        let bands = self.0.as_slice();
+        if bands.len() >= 3 {
+            rgb_to_luma(bands)
+        } else {
+            bands[0]
+        }
     }
 }
 
-impl FromColor<Luma<u8>> for Rgb<u16> {
-    fn from_color(&mut self, other: &Luma<u8>) {
-        let l8 = other.channels();
-        let rgb = self.channels_mut();
-        let gray = upcast_channel(l8[0]);
-        rgb[0] = gray;
-        rgb[1] = gray;
-        rgb[2] = gray;
+impl<A: BandArray + 'static> Pixel for Bands<A> {
+    type Subpixel = A::Sample;
+
+    const CHANNEL_COUNT: u8 = A::LEN;
+
+    const COLOR_MODEL: &'static str = "BANDS";
+
+    const COLOR_TYPE: ColorType = [
+        [ColorType::L8, ColorType::Rgb8],
+        [ColorType::L16, ColorType::Rgb16],
+        [ColorType::L16, ColorType::Rgb32F],
+        [
+            [ColorType::L8, ColorType::Rgb8],
+            [ColorType::L16, ColorType::Rgb16],
+        ][(size_of::<A::Sample>() > 1) as usize],
+    ][<A::Sample as Primitive>::COLOR_SAMPLE_INDEX][(A::LEN >= 3) as usize];
+
+    const EXACT_COLOR_TYPE: Option<ColorType> = None;
+
+    fn channels(&self) -> &[A::Sample] {
+        self.0.as_slice()
     }
-}
 
-impl FromColor<Bgr<u8>> for Rgb<u16> {
-    fn from_color(&mut self, other: &Bgr<u8>) {
-        let bgr = other.channels();
-        let rgb = self.channels_mut();
-        rgb[0] = upcast_channel(bgr[2]);
-        rgb[1] = upcast_channel(bgr[1]);
-        rgb[2] = upcast_channel(bgr[0]);
+    fn channels_mut(&mut self) -> &mut [A::Sample] {
+        self.0.as_mut_slice()
     }
-}
 
-impl FromColor<Bgra<u8>> for Rgb<u16> {
-    fn from_color(&mut self, other: &Bgra<u8>) {
-        let bgra = other.channels();
-        let rgb = self.channels_mut();
-        rgb[0] = upcast_channel(bgra[2]);
-        rgb[1] = upcast_channel(bgra[1]);
-        rgb[2] = upcast_channel(bgra[0]);
+    fn channels4(&self) -> (A::Sample, A::Sample, A::Sample, A::Sample) {
+        let mut channels = [A::Sample::default_max_value(); 4];
+        for (channel, &band) in channels.iter_mut().zip(self.0.as_slice()) {
+            *channel = band;
+        }
+        (channels[0], channels[1], channels[2], channels[3])
     }
-}
 
+    fn from_channels(a: A::Sample, b: A::Sample, c: A::Sample, d: A::Sample) -> Self {
+        let mut bands = A::filled(A::Sample::zero());
+        for (band, &channel) in bands.as_mut_slice().iter_mut().zip(&[a, b, c, d]) {
+            *band = channel;
+        }
+        Bands(bands)
+    }
 
-/// `FromColor` for BGR
+    fn from_slice(slice: &[A::Sample]) -> &Self {
+        assert_eq!(slice.len(), A::LEN as usize);
+        unsafe { &*(slice.as_ptr() as *const Self) }
+    }
 
-impl<T: Primitive + 'static> FromColor<Rgba<T>> for Bgr<T> {
-    fn from_color(&mut self, other: &Rgba<T>) {
-        let bgr = self.channels_mut();
-        let rgba = other.channels();
-        bgr[0] = rgba[2];
-        bgr[1] = rgba[1];
-        bgr[2] = rgba[0];
+    fn from_slice_mut(slice: &mut [A::Sample]) -> &mut Self {
+        assert_eq!(slice.len(), A::LEN as usize);
+        unsafe { &mut *(slice.as_mut_ptr() as *mut Self) }
     }
-}
 
-impl<T: Primitive + 'static> FromColor<Rgb<T>> for Bgr<T> {
-    fn from_color(&mut self, other: &Rgb<T>) {
-        let bgr = self.channels_mut();
-        let rgb = other.channels();
-        bgr[0] = rgb[2];
-        bgr[1] = rgb[1];
-        bgr[2] = rgb[0];
+    fn to_rgb(&self) -> Rgb<A::Sample> {
+        Rgb(self.rgb())
     }
-}
 
+    fn to_rgba(&self) -> Rgba<A::Sample> {
+        let [r, g, b] = self.rgb();
+        Rgba([r, g, b, A::Sample::default_max_value()])
+    }
 
-impl<T: Primitive + 'static> FromColor<Bgra<T>> for Bgr<T> {
-    fn from_color(&mut self, other: &Bgra<T>) {
-        let bgr = self.channels_mut();
-        let bgra = other.channels();
-        bgr[0] = bgra[0];
-        bgr[1] = bgra[1];
-        bgr[2] = bgra[2];
+    fn to_luma(&self) -> Luma<A::Sample> {
+        Luma([self.luma()])
+    }
+
+    fn to_luma_alpha(&self) -> LumaA<A::Sample> {
+        LumaA([self.luma(), A::Sample::default_max_value()])
+    }
+
+    fn to_bgr(&self) -> Bgr<A::Sample> {
+        let [r, g, b] = self.rgb();
+        Bgr([b, g, r])
+    }
+
+    fn to_bgra(&self) -> Bgra<A::Sample> {
+        let [r, g, b] = self.rgb();
+        Bgra([b, g, r, A::Sample::default_max_value()])
+    }
+
+    fn map<F>(&self, f: F) -> Self where F: FnMut(A::Sample) -> A::Sample {
+        let mut this = *self;
+        this.apply(f);
+        this
+    }
+
+    fn apply<F>(&mut self, mut f: F) where F: FnMut(A::Sample) -> A::Sample {
+        for v in self.0.as_mut_slice() {
+            *v = f(*v)
+        }
+    }
+
+    fn map_with_alpha<F, G>(&self, f: F, g: G) -> Self
+    where
+        F: FnMut(A::Sample) -> A::Sample,
+        G: FnMut(A::Sample) -> A::Sample,
+    {
+        let mut this = *self;
+        this.apply_with_alpha(f, g);
+        this
+    }
+
+    fn apply_with_alpha<F, G>(&mut self, f: F, _: G)
+    where
+        F: FnMut(A::Sample) -> A::Sample,
+        G: FnMut(A::Sample) -> A::Sample,
+    {
+        self.apply(f)
+    }
+
+    fn map2<F>(&self, other: &Self, f: F) -> Self where F: FnMut(A::Sample, A::Sample) -> A::Sample {
+        let mut this = *self;
+        this.apply2(other, f);
+        this
+    }
+
+    fn apply2<F>(&mut self, other: &Self, mut f: F) where F: FnMut(A::Sample, A::Sample) -> A::Sample {
+        for (a, &b) in self.0.as_mut_slice().iter_mut().zip(other.0.as_slice()) {
+            *a = f(*a, b)
+        }
+    }
+
+    fn invert(&mut self) {
+        let max = A::Sample::default_max_value();
+        self.apply(|v| max - v)
+    }
+
+    fn blend(&mut self, other: &Self) {
+        *self = *other
     }
 }
 
-impl<T: Primitive + 'static> FromColor<LumaA<T>> for Bgr<T> {
-    fn from_color(&mut self, other: &LumaA<T>) {
-        let bgr = self.channels_mut();
-        let gray = other.channels()[0];
-        bgr[0] = gray;
-        bgr[1] = gray;
-        bgr[2] = gray;
+impl<A: BandArray> Index<usize> for Bands<A> {
+    type Output = A::Sample;
+    #[inline(always)]
+    fn index(&self, index: usize) -> &A::Sample {
+        &self.0.as_slice()[index]
     }
 }
 
-impl<T: Primitive + 'static> FromColor<Luma<T>> for Bgr<T> {
-    fn from_color(&mut self, gray: &Luma<T>) {
-        let bgr = self.channels_mut();
-        let gray = gray.channels()[0];
-        bgr[0] = gray;
-        bgr[1] = gray;
-        bgr[2] = gray;
+impl<A: BandArray> IndexMut<usize> for Bands<A> {
+    #[inline(always)]
+    fn index_mut(&mut self, index: usize) -> &mut A::Sample {
+        &mut self.0.as_mut_slice()[index]
     }
 }
 
-macro_rules! downcast_bit_depth_early {
-    ($src:ident, $intermediate:ident, $dst:ident) => {
-        impl FromColor<$src<u16>> for $dst<u8> {
-            fn from_color(&mut self, other: &$src<u16>) {
-                let mut intermediate: $intermediate<u8> = $intermediate([Zero::zero(); <$intermediate<u8> as Pixel>::CHANNEL_COUNT as usize]);
-                intermediate.from_color(other);
-                self.from_color(&intermediate);
-            }
-        }
-    };
-}
-
-
-// Downcasts
-// LumaA
-downcast_bit_depth_early!(Luma, Luma, LumaA);
-downcast_bit_depth_early!(Rgb, Rgb, LumaA);
-downcast_bit_depth_early!(Rgba, Rgba, LumaA);
-// Rgb
-downcast_bit_depth_early!(Luma, Luma, Rgb);
-downcast_bit_depth_early!(LumaA, LumaA, Rgb);
-downcast_bit_depth_early!(Rgba, Rgba, Rgb);
-// Rgba
-downcast_bit_depth_early!(Luma, Luma, Rgba);
-downcast_bit_depth_early!(LumaA, LumaA, Rgba);
-downcast_bit_depth_early!(Rgb, Rgb, Rgba);
-// Bgr
-downcast_bit_depth_early!(Luma, Luma, Bgr);
-downcast_bit_depth_early!(LumaA, LumaA, Bgr);
-downcast_bit_depth_early!(Rgb, Rgb, Bgr);
-downcast_bit_depth_early!(Rgba, Rgba, Bgr);
-// Bgra
-downcast_bit_depth_early!(Luma, Luma, Bgra);
-downcast_bit_depth_early!(LumaA, LumaA, Bgra);
-downcast_bit_depth_early!(Rgb, Rgb, Bgra);
-downcast_bit_depth_early!(Rgba, Rgba, Bgra);
+impl<A: BandArray + 'static> FromColor<Bands<A>> for Bands<A> {
+    fn from_color(&mut self, other: &Bands<A>) {
+        *self = *other
+    }
+}
 
+// The pixel is transparent around an array of samples, like the color types.
+unsafe impl<A: BandArray + bytemuck::Zeroable> bytemuck::Zeroable for Bands<A> {}
+unsafe impl<A: BandArray + bytemuck::Pod> bytemuck::Pod for Bands<A> {}
 
 /// Blends a color inter another one
 pub(crate) trait Blend {
@@ -1110,7 +1334,7 @@ pub(crate) trait Blend {
 
 impl<T: Primitive> Blend for LumaA<T> {
     fn blend(&mut self, other: &LumaA<T>) {
-        let max_t = T::max_value();
+        let max_t = T::default_max_value();
         let max_t = max_t.to_f32().unwrap();
         let (bg_luma, bg_a) = (self.0[0], self.0[1]);
         let (fg_luma, fg_a) = (other.0[0], other.0[1]);
@@ -1152,7 +1376,7 @@ impl<T: Primitive> Blend for Rgba<T> {
         // http://stackoverflow.com/questions/7438263/alpha-compositing-algorithm-blend-modes#answer-11163848
 
         // First, as we don't know what type our pixel is, we have to convert to floats between 0.0 and 1.0
-        let max_t = T::max_value();
+        let max_t = T::default_max_value();
         let max_t = max_t.to_f32().unwrap();
         let (bg_r, bg_g, bg_b, bg_a) = (self.0[0], self.0[1], self.0[2], self.0[3]);
         let (fg_r, fg_g, fg_b, fg_a) = (other.0[0], other.0[1], other.0[2], other.0[3]);
@@ -1210,7 +1434,7 @@ impl<T: Primitive> Blend for Bgra<T> {
         // http://stackoverflow.com/questions/7438263/alpha-compositing-algorithm-blend-modes#answer-11163848
 
         // First, as we don't know what type our pixel is, we have to convert to floats between 0.0 and 1.0
-        let max_t = T::max_value();
+        let max_t = T::default_max_value();
         let max_t = max_t.to_f32().unwrap();
         let (bg_r, bg_g, bg_b, bg_a) = (self.0[2], self.0[1], self.0[0], self.0[3]);
         let (fg_r, fg_g, fg_b, fg_a) = (other.0[2], other.0[1], other.0[0], other.0[3]);
@@ -1273,6 +1497,11 @@ impl<T: Primitive> Blend for Bgr<T> {
     }
 }
 
+impl<T: Primitive> Blend for Cmyk<T> {
+    fn blend(&mut self, other: &Cmyk<T>) {
+        *self = *other
+    }
+}
 
 /// Invert a color
 pub(crate) trait Invert {
@@ -1283,7 +1512,7 @@ pub(crate) trait Invert {
 impl<T: Primitive> Invert for LumaA<T> {
     fn invert(&mut self) {
         let l = self.0;
-        let max = T::max_value();
+        let max = T::default_max_value();
 
         *self = LumaA([max - l[0], l[1]])
     }
@@ -1293,7 +1522,7 @@ impl<T: Primitive> Invert for Luma<T> {
     fn invert(&mut self) {
         let l = self.0;
 
-        let max = T::max_value();
+        let max = T::default_max_value();
         let l1 = max - l[0];
 
         *self = Luma([l1])
@@ -1304,7 +1533,7 @@ impl<T: Primitive> Invert for Rgba<T> {
     fn invert(&mut self) {
         let rgba = self.0;
 
-        let max = T::max_value();
+        let max = T::default_max_value();
 
         *self = Rgba([max - rgba[0], max - rgba[1], max - rgba[2], rgba[3]])
     }
@@ -1315,7 +1544,7 @@ impl<T: Primitive> Invert for Bgra<T> {
     fn invert(&mut self) {
         let bgra = self.0;
 
-        let max = T::max_value();
+        let max = T::default_max_value();
 
         *self = Bgra([max - bgra[2], max - bgra[1], max - bgra[0], bgra[3]])
     }
@@ -1326,7 +1555,7 @@ impl<T: Primitive> Invert for Rgb<T> {
     fn invert(&mut self) {
         let rgb = self.0;
 
-        let max = T::max_value();
+        let max = T::default_max_value();
 
         let r1 = max - rgb[0];
         let g1 = max - rgb[1];
@@ -1340,7 +1569,7 @@ impl<T: Primitive> Invert for Bgr<T> {
     fn invert(&mut self) {
         let bgr = self.0;
 
-        let max = T::max_value();
+        let max = T::default_max_value();
 
         let r1 = max - bgr[2];
         let g1 = max - bgr[1];
@@ -1350,6 +1579,74 @@ impl<T: Primitive> Invert for Bgr<T> {
     }
 }
 
+impl<T: Primitive> Invert for Cmyk<T> {
+    fn invert(&mut self) {
+        // Inverting the inks would turn black into black, so invert the color it represents.
+        let mut rgb = Rgb(cmyk_to_rgb(&self.0));
+        Invert::invert(&mut rgb);
+        self.0 = rgb_to_cmyk(&rgb.0);
+    }
+}
+
+/// Multiply the color channels by the alpha channel, which is the last one.
+pub(crate) fn premultiply_channels<T: Primitive>(channels: &mut [T]) {
+    let (alpha, colors) = channels.split_last_mut().unwrap();
+    let max = T::default_max_value().to_f32().unwrap();
+    let alpha = alpha.to_f32().unwrap() / max;
+    for color in colors {
+        *color = round_sample(color.to_f32().unwrap() * alpha, max);
+    }
+}
+
+/// Divide the color channels by the alpha channel, which is the last one.
+///
+/// Colors of fully transparent pixels are set to zero.
+pub(crate) fn unpremultiply_channels<T: Primitive>(channels: &mut [T]) {
+    let (alpha, colors) = channels.split_last_mut().unwrap();
+    let max = T::default_max_value().to_f32().unwrap();
+    let alpha = alpha.to_f32().unwrap() / max;
+    for color in colors {
+        *color = if alpha > 0.0 {
+            round_sample(color.to_f32().unwrap() / alpha, max)
+        } else {
+            T::zero()
+        };
+    }
+}
+
+/// Clamp a sample to the range of its type, rounding it if the type is an integer.
+fn round_sample<T: Primitive>(value: f32, max: f32) -> T {
+    let value = clamp(value, 0.0, max);
+    // Only float samples have a maximum of one.
+    let value = if max > 1.0 { value.round() } else { value };
+    NumCast::from(value).unwrap()
+}
+
+macro_rules! impl_premultiply {
+    ($($ident:ident),*) => {$(
+        impl<T: Primitive> $ident<T> {
+            /// Multiply the color channels by alpha, as used by compositing and filtering that
+            /// treat transparent pixels correctly.
+            ///
+            /// This is lossy for integer samples, the more the lower alpha is.
+            pub fn premultiply(&mut self) {
+                premultiply_channels(&mut self.0)
+            }
+
+            /// Divide the color channels by alpha, undoing [`premultiply`].
+            ///
+            /// The colors of fully transparent pixels are lost and set to zero.
+            ///
+            /// [`premultiply`]: #method.premultiply
+            pub fn unpremultiply(&mut self) {
+                unpremultiply_channels(&mut self.0)
+            }
+        }
+    )*}
+}
+
+impl_premultiply!(Rgba, Bgra, LumaA);
+
 #[cfg(test)]
 mod tests {
     use super::{Bgr, Bgra, Luma, LumaA, Pixel, Rgb, Rgba};
@@ -1529,5 +1826,128 @@ mod tests {
         test_lossless_conversion!(LumaA<u8>, LumaA<u16>, LumaA<u8>);
         test_lossless_conversion!(Rgb<u8>, Rgb<u16>, Rgb<u8>);
         test_lossless_conversion!(Rgba<u8>, Rgba<u16>, Rgba<u8>);
+        test_lossless_conversion!(Rgb<u8>, Rgb<f32>, Rgb<u8>);
+        test_lossless_conversion!(Rgba<u16>, Rgba<f32>, Rgba<u16>);
+    }
+
+    #[test]
+    fn test_float_conversions() {
+        use super::IntoColor;
+
+        let rgb: Rgb<f32> = Rgb([0u8, 51, 255]).into_color();
+        assert_eq!(rgb, Rgb([0.0, 0.2, 1.0]));
+
+        // Samples outside of the nominal range are clamped.
+        let rgba: Rgba<u16> = Rgba([0.5f32, -1.0, 2.0, 1.0]).into_color();
+        assert_eq!(rgba, Rgba([32768, 0, 65535, 65535]));
+
+        let luma_a: LumaA<f32> = Rgb([1.0f32, 1.0, 1.0]).into_color();
+        assert_eq!(luma_a.0[1], 1.0);
+    }
+
+    #[test]
+    fn test_color_types() {
+        use super::ColorType;
+
+        assert_eq!(Rgb::<f32>::COLOR_TYPE, ColorType::Rgb32F);
+        assert_eq!(Rgba::<f32>::EXACT_COLOR_TYPE, Some(ColorType::Rgba32F));
+        assert_eq!(LumaA::<u16>::EXACT_COLOR_TYPE, Some(ColorType::La16));
+
+        // No color type has these samples.
+        assert_eq!(Luma::<f32>::EXACT_COLOR_TYPE, None);
+        assert_eq!(Bgr::<u16>::EXACT_COLOR_TYPE, None);
+        assert_eq!(Rgb::<u32>::EXACT_COLOR_TYPE, None);
+        assert_eq!(Rgb::<u32>::COLOR_TYPE, ColorType::Rgb16);
+        assert_eq!(Rgba::<f64>::EXACT_COLOR_TYPE, None);
+    }
+
+    #[test]
+    fn test_premultiply() {
+        let mut rgba = Rgba([200u8, 100, 50, 128]);
+        rgba.premultiply();
+        assert_eq!(rgba, Rgba([100, 50, 25, 128]));
+        rgba.unpremultiply();
+        assert_eq!(rgba, Rgba([199, 100, 50, 128]));
+
+        let mut bgra = Bgra([0.5f32, 1.0, 0.25, 0.5]);
+        bgra.premultiply();
+        assert_eq!(bgra, Bgra([0.25, 0.5, 0.125, 0.5]));
+        bgra.unpremultiply();
+        assert_eq!(bgra, Bgra([0.5, 1.0, 0.25, 0.5]));
+
+        let mut luma_a = LumaA([40000u16, 0]);
+        luma_a.premultiply();
+        assert_eq!(luma_a, LumaA([0, 0]));
+        let mut luma_a = LumaA([40000u16, 0]);
+        luma_a.unpremultiply();
+        assert_eq!(luma_a, LumaA([0, 0]));
+    }
+
+    #[test]
+    fn test_pod() {
+        let pixels = [Rgba([1u8, 2, 3, 4]), Rgba([5, 6, 7, 8])];
+        assert_eq!(bytemuck::cast_slice::<_, u8>(&pixels), &[1, 2, 3, 4, 5, 6, 7, 8]);
+        let luma: &[Luma<u16>] = bytemuck::cast_slice(&[1u16, 2, 3][..]);
+        assert_eq!(luma[2], Luma([3]));
+        assert_eq!(<Bgr<f32> as bytemuck::Zeroable>::zeroed(), Bgr([0.0; 3]));
+    }
+
+    #[test]
+    fn test_cmyk_conversions() {
+        use super::{Cmyk, IntoColor};
+
+        let rgb: Rgb<u8> = Cmyk([0u8, 0, 0, 0]).into_color();
+        assert_eq!(rgb, Rgb([255, 255, 255]));
+        let rgba: Rgba<u8> = Cmyk([255u8, 0, 0, 0]).into_color();
+        assert_eq!(rgba, Rgba([0, 255, 255, 255]));
+        let bgr: Bgr<u16> = Cmyk([0u8, 51, 255, 102]).into_color();
+        assert_eq!(bgr, Bgr([0, 31354, 39321]));
+        let luma: Luma<u8> = Cmyk([0u8, 0, 0, 255]).into_color();
+        assert_eq!(luma, Luma([0]));
+
+        let cmyk: Cmyk<u8> = Rgb([153u8, 122, 0]).into_color();
+        assert_eq!(cmyk, Cmyk([0, 52, 255, 102]));
+        let cmyk: Cmyk<u8> = LumaA([0u8, 0]).into_color();
+        assert_eq!(cmyk, Cmyk([0, 0, 0, 255]));
+        let cmyk: Cmyk<f32> = Cmyk([255u8, 0, 51, 0]).into_color();
+        assert_eq!(cmyk, Cmyk([1.0, 0.0, 0.2, 0.0]));
+
+        let mut black = Cmyk([0u8, 0, 0, 255]);
+        black.invert();
+        assert_eq!(black, Cmyk([0, 0, 0, 0]));
+    }
+
+    #[test]
+    fn test_bands() {
+        use super::Bands;
+        use crate::{imageops, ColorType, ImageBuffer};
+
+        // Color with depth.
+        let pixel = Bands([10u16, 20, 30, 40, 65535]);
+        assert_eq!(<Bands<[u16; 5]> as Pixel>::CHANNEL_COUNT, 5);
+        assert_eq!(<Bands<[u16; 5]> as Pixel>::EXACT_COLOR_TYPE, None);
+        assert_eq!(<Bands<[f32; 2]> as Pixel>::EXACT_COLOR_TYPE, None);
+        assert_eq!(<Bands<[f32; 2]> as Pixel>::COLOR_TYPE, ColorType::L16);
+        assert_eq!(<Bands<[u32; 3]> as Pixel>::COLOR_TYPE, ColorType::Rgb16);
+        assert_eq!(pixel.to_rgba(), Rgba([10, 20, 30, 65535]));
+        assert_eq!(pixel.map_without_alpha(|v| v / 10), Bands([1, 2, 3, 4, 6553]));
+        assert_eq!(Bands([7u8, 9]).to_bgr(), Bgr([7, 7, 7]));
+        assert_eq!(Bands::<[u8; 6]>::from_channels(1, 2, 3, 4), Bands([1, 2, 3, 4, 0, 0]));
+        assert_eq!(Bands([1u8, 2, 3, 4, 5, 6]).channels4(), (1, 2, 3, 4));
+
+        let image = ImageBuffer::from_fn(4, 4, |x, y| Bands([x as u8, y as u8, 0, 0, 0, 0, 0, 7]));
+        assert_eq!(image.as_raw().len(), 4 * 4 * 8);
+        let cropped = imageops::crop_imm(&image, 1, 2, 2, 2).to_image();
+        assert_eq!(cropped.get_pixel(1, 0), &Bands([2, 2, 0, 0, 0, 0, 0, 7]));
+        let mut inverted = cropped.clone();
+        imageops::invert(&mut inverted);
+        assert_eq!(inverted.get_pixel(0, 1)[7], 248);
+
+        // The bands can't be encoded as any color type.
+        let mut bytes = Vec::new();
+        match image.write_to(&mut bytes, crate::ImageFormat::Png) {
+            Err(crate::ImageError::Unsupported(_)) => {}
+            other => panic!("{:?}", other),
+        }
     }
 }
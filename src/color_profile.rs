@@ -0,0 +1,623 @@
+//! Color management with ICC profiles.
+//!
+//! An [`IccProfile`] describes how the samples of an image relate to colors that can be seen,
+//! and is usually embedded in the file, from where decoders return it with
+//! `ImageDecoder::icc_profile`. Images that are not in sRGB, for example photos in Display P3 or
+//! Adobe RGB, look washed out or oversaturated unless they are converted, which a [`Transform`]
+//! between two profiles does and `DynamicImage::convert_to_srgb` does for the common case.
+//!
+//! Profiles are supported in the matrix and tone curve form used by nearly all RGB profiles of
+//! cameras, displays and working spaces, and gray profiles with a single tone curve. Profiles
+//! that describe colors through lookup tables, such as those of printers, are rejected as
+//! unsupported. Colors are converted with the relative colorimetric intent, and colors that the
+//! target cannot show are clipped.
+//!
+//! # Examples
+//!
+//! ```
+//! use image::color_profile::{IccProfile, Transform};
+//! use image::{Rgb, RgbImage};
+//!
+//! // The most saturated red of Display P3 is outside of sRGB and clipped.
+//! let mut image = RgbImage::from_pixel(1, 1, Rgb([255, 0, 0]));
+//! Transform::new(&IccProfile::display_p3(), &IccProfile::srgb()).apply(&mut image);
+//! assert_eq!(image.get_pixel(0, 0), &Rgb([255, 0, 0]));
+//!
+//! // Middle gray keeps its lightness, since both spaces have the same tone curve.
+//! let mut image = RgbImage::from_pixel(1, 1, Rgb([128, 128, 128]));
+//! Transform::new(&IccProfile::display_p3(), &IccProfile::srgb()).apply(&mut image);
+//! assert_eq!(image.get_pixel(0, 0), &Rgb([128, 128, 128]));
+//! ```
+//!
+//! [`IccProfile`]: struct.IccProfile.html
+//! [`Transform`]: struct.Transform.html
+
+use std::convert::TryInto;
+use std::ops::DerefMut;
+
+use num_traits::NumCast;
+
+use crate::buffer_::ImageBuffer;
+use crate::error::{
+    DecodingError, ImageError, ImageFormatHint, ImageResult, UnsupportedError,
+    UnsupportedErrorKind,
+};
+use crate::imageops::srgb::rgb_channels;
+use crate::imageops::plane::nominal_max;
+use crate::imageops::FloatNearest;
+use crate::traits::Pixel;
+use crate::utils::clamp;
+
+/// The CIE XYZ colors of the sRGB primaries, adapted to the D50 white of profiles.
+const SRGB_COLORANTS: [[f32; 3]; 3] = [
+    [0.436_074_7, 0.222_504_5, 0.013_932_2],
+    [0.385_064_9, 0.716_878_6, 0.097_104_5],
+    [0.143_080_4, 0.060_616_9, 0.714_173_3],
+];
+
+/// The sRGB transfer function as the parameters of an ICC parametric curve.
+const SRGB_CURVE: ToneCurve =
+    ToneCurve::Parametric([2.4, 1.0 / 1.055, 0.055 / 1.055, 1.0 / 12.92, 0.040_45, 0.0, 0.0]);
+
+/// A color profile in the matrix and tone curve form.
+///
+/// The profile holds the colors of the red, green and blue primaries and a tone curve for
+/// every channel, which maps samples to linear light. Gray profiles have a single tone curve.
+#[derive(Clone, Debug, PartialEq)]
+pub struct IccProfile {
+    gray: bool,
+    /// The XYZ color of every primary, relative to the D50 white of the profile connection
+    /// space.
+    colorants: [[f32; 3]; 3],
+    curves: [ToneCurve; 3],
+}
+
+/// A curve that maps samples in `[0, 1]` to linear light.
+#[derive(Clone, Debug, PartialEq)]
+enum ToneCurve {
+    /// The parameters `[g, a, b, c, d, e, f]` of `(a * x + b)^g + e` for `x >= d` and
+    /// `c * x + f` below, the most general parametric curve of ICC profiles, which all others
+    /// are expressed with.
+    Parametric([f32; 7]),
+    /// Samples of the curve at evenly spaced inputs.
+    Table(Vec<f32>),
+}
+
+impl IccProfile {
+    /// The sRGB profile, the color space assumed for images without a profile.
+    pub fn srgb() -> IccProfile {
+        IccProfile { gray: false, colorants: SRGB_COLORANTS, curves: rgb_curves(SRGB_CURVE) }
+    }
+
+    /// The Display P3 profile, with the primaries of DCI-P3 and the white and tone curve of
+    /// sRGB, used by the cameras and displays of many phones and computers.
+    pub fn display_p3() -> IccProfile {
+        let colorants = [
+            [0.515_102, 0.241_182, -0.001_050],
+            [0.291_965, 0.692_236, 0.041_882],
+            [0.157_153, 0.066_582, 0.784_378],
+        ];
+        IccProfile { gray: false, colorants, curves: rgb_curves(SRGB_CURVE) }
+    }
+
+    /// The Adobe RGB (1998) profile, a wide gamut space used in photography and print.
+    pub fn adobe_rgb() -> IccProfile {
+        let colorants = [
+            [0.609_755_9, 0.311_124_2, 0.019_481_1],
+            [0.205_240_1, 0.625_656, 0.060_890_2],
+            [0.149_224, 0.063_219_7, 0.744_838_7],
+        ];
+        IccProfile { gray: false, colorants, curves: rgb_curves(gamma(563.0 / 256.0)) }
+    }
+
+    /// Parse a profile from the bytes of the ICC data.
+    ///
+    /// RGB profiles need the colorant and tone curve tags, gray profiles the gray tone curve.
+    /// Other profiles result in an unsupported error, malformed data in a decoding error.
+    pub fn parse(data: &[u8]) -> ImageResult<IccProfile> {
+        if data.len() < 132 || &data[36..40] != b"acsp" {
+            return Err(icc_error("not an ICC profile"));
+        }
+        let size = read_u32(data, 0)? as usize;
+        if size < 132 || size > data.len() {
+            return Err(icc_error("invalid profile size"));
+        }
+        let data = &data[..size];
+
+        if &data[20..24] != b"XYZ " {
+            return Err(icc_unsupported("profiles with a Lab connection space".to_owned()));
+        }
+        let gray = match &data[16..20] {
+            b"RGB " => false,
+            b"GRAY" => true,
+            other => {
+                let space = String::from_utf8_lossy(other).trim().to_owned();
+                return Err(icc_unsupported(format!("profiles of {} colors", space)));
+            }
+        };
+
+        let tags = Tags::new(data)?;
+        if gray {
+            let curve = match tags.get(b"kTRC")? {
+                Some(tag) => parse_curve(tag)?,
+                None => {
+                    let kind = "gray profiles without a tone curve".to_owned();
+                    return Err(icc_unsupported(kind));
+                }
+            };
+            let curves = [curve.clone(), curve.clone(), curve];
+            return Ok(IccProfile { gray, colorants: SRGB_COLORANTS, curves });
+        }
+
+        let names = [(b"rXYZ", b"rTRC"), (b"gXYZ", b"gTRC"), (b"bXYZ", b"bTRC")];
+        let mut colorants = [[0.0; 3]; 3];
+        let mut curves = rgb_curves(gamma(1.0));
+        for (c, &(colorant, curve)) in names.iter().enumerate() {
+            match (tags.get(colorant)?, tags.get(curve)?) {
+                (Some(colorant), Some(curve)) => {
+                    colorants[c] = parse_xyz(colorant)?;
+                    curves[c] = parse_curve(curve)?;
+                }
+                _ => {
+                    let kind = "profiles without colorants and tone curves".to_owned();
+                    return Err(icc_unsupported(kind));
+                }
+            }
+        }
+        if invert(matrix(&colorants)).is_none() {
+            return Err(icc_error("the colorants are linearly dependent"));
+        }
+        Ok(IccProfile { gray, colorants, curves })
+    }
+
+    /// Whether this is a gray profile, which has no colorants of its own.
+    pub fn is_gray(&self) -> bool {
+        self.gray
+    }
+}
+
+/// A conversion of colors from one profile to another.
+///
+/// RGB images are converted as a whole, gray images are converted by their luminance. Alpha
+/// is not changed, and pixels of other color models, such as CMYK, are left as they are.
+#[derive(Clone, Debug)]
+pub struct Transform {
+    from: [ToneCurve; 3],
+    to: [ToneCurve; 3],
+    /// Maps linear light of the source to linear light of the target.
+    matrix: [[f32; 3]; 3],
+    /// The luminance of the primaries of the source.
+    luminance: [f32; 3],
+}
+
+impl Transform {
+    /// Create the conversion from colors in the profile `from` to colors in the profile `to`.
+    pub fn new(from: &IccProfile, to: &IccProfile) -> Transform {
+        let source = matrix(&from.colorants);
+        // Checked when the profile was created.
+        let target = invert(matrix(&to.colorants)).unwrap();
+        Transform {
+            from: from.curves.clone(),
+            to: to.curves.clone(),
+            matrix: multiply(&target, &source),
+            luminance: source[1],
+        }
+    }
+
+    /// Convert an RGB color with channels in `[0, 1]`. The result is clipped to `[0, 1]`.
+    pub fn map_color(&self, color: [f32; 3]) -> [f32; 3] {
+        let mut linear = [0.0; 3];
+        for c in 0..3 {
+            linear[c] = self.from[c].eval(clamp(color[c], 0.0, 1.0));
+        }
+        let mut out = [0.0; 3];
+        for (c, value) in out.iter_mut().enumerate() {
+            let row = self.matrix[c];
+            let linear = row[0] * linear[0] + row[1] * linear[1] + row[2] * linear[2];
+            *value = self.to[c].invert(clamp(linear, 0.0, 1.0));
+        }
+        out
+    }
+
+    /// Convert a gray sample in `[0, 1]`, keeping its luminance.
+    pub fn map_gray(&self, gray: f32) -> f32 {
+        let gray = clamp(gray, 0.0, 1.0);
+        let luminance: f32 = (0..3).map(|c| self.luminance[c] * self.from[c].eval(gray)).sum();
+        self.to[1].invert(clamp(luminance, 0.0, 1.0))
+    }
+
+    /// Convert the colors of every pixel of an image.
+    ///
+    /// Samples are scaled to `[0, 1]` by the maximum of their type, or taken as they are for
+    /// floating point images, and the results are rounded for integer types.
+    pub fn apply<P, Container>(&self, image: &mut ImageBuffer<P, Container>)
+    where
+        P: Pixel + 'static,
+        Container: DerefMut<Target = [P::Subpixel]>,
+    {
+        let max = nominal_max::<P::Subpixel>();
+        let read =
+            |sample: P::Subpixel| -> f32 { NumCast::from(sample).map_or(0.0, |s: f32| s / max) };
+        let write = |value: f32| NumCast::from(FloatNearest(value * max)).unwrap();
+
+        if let Some(rgb) = rgb_channels::<P>() {
+            for pixel in image.pixels_mut() {
+                let channels = pixel.channels_mut();
+                let color = self.map_color([
+                    read(channels[rgb[0]]),
+                    read(channels[rgb[1]]),
+                    read(channels[rgb[2]]),
+                ]);
+                for c in 0..3 {
+                    channels[rgb[c]] = write(color[c]);
+                }
+            }
+        } else if P::COLOR_MODEL.starts_with('Y') {
+            for pixel in image.pixels_mut() {
+                let channels = pixel.channels_mut();
+                channels[0] = write(self.map_gray(read(channels[0])));
+            }
+        }
+    }
+}
+
+impl ToneCurve {
+    /// Map a sample in `[0, 1]` to linear light.
+    fn eval(&self, x: f32) -> f32 {
+        match *self {
+            ToneCurve::Parametric([g, a, b, c, d, e, f]) => {
+                if x >= d {
+                    (a * x + b).max(0.0).powf(g) + e
+                } else {
+                    c * x + f
+                }
+            }
+            ToneCurve::Table(ref table) => {
+                let last = (table.len() - 1) as f32;
+                let position = x * last;
+                let i = (position.floor() as usize).min(table.len() - 2);
+                let fraction = position - i as f32;
+                table[i] + (table[i + 1] - table[i]) * fraction
+            }
+        }
+    }
+
+    /// Map linear light in `[0, 1]` back to a sample in `[0, 1]`.
+    fn invert(&self, y: f32) -> f32 {
+        let x = match *self {
+            ToneCurve::Parametric([g, a, b, c, d, e, f]) => {
+                let threshold = (a * d + b).max(0.0).powf(g) + e;
+                if y >= threshold && a != 0.0 {
+                    ((y - e).max(0.0).powf(1.0 / g) - b) / a
+                } else if c != 0.0 {
+                    (y - f) / c
+                } else {
+                    d
+                }
+            }
+            ToneCurve::Table(ref table) => {
+                // The first entry that is not below `y`, tables are increasing.
+                let (mut low, mut high) = (0, table.len() - 1);
+                while low < high {
+                    let middle = (low + high) / 2;
+                    if table[middle] < y {
+                        low = middle + 1;
+                    } else {
+                        high = middle;
+                    }
+                }
+                if low == 0 {
+                    0.0
+                } else {
+                    let (below, above) = (table[low - 1], table[low]);
+                    let fraction = if above > below { (y - below) / (above - below) } else { 0.0 };
+                    (low as f32 - 1.0 + fraction) / (table.len() - 1) as f32
+                }
+            }
+        };
+        clamp(x, 0.0, 1.0)
+    }
+}
+
+fn gamma(g: f32) -> ToneCurve {
+    ToneCurve::Parametric([g, 1.0, 0.0, 0.0, 0.0, 0.0, 0.0])
+}
+
+fn rgb_curves(curve: ToneCurve) -> [ToneCurve; 3] {
+    [curve.clone(), curve.clone(), curve]
+}
+
+/// The matrix that maps linear RGB to XYZ, whose columns are the colorants.
+fn matrix(colorants: &[[f32; 3]; 3]) -> [[f32; 3]; 3] {
+    let mut m = [[0.0; 3]; 3];
+    for (row, m_row) in m.iter_mut().enumerate() {
+        for (column, value) in m_row.iter_mut().enumerate() {
+            *value = colorants[column][row];
+        }
+    }
+    m
+}
+
+fn multiply(a: &[[f32; 3]; 3], b: &[[f32; 3]; 3]) -> [[f32; 3]; 3] {
+    let mut m = [[0.0; 3]; 3];
+    for row in 0..3 {
+        for column in 0..3 {
+            m[row][column] = (0..3).map(|k| a[row][k] * b[k][column]).sum();
+        }
+    }
+    m
+}
+
+fn invert(m: [[f32; 3]; 3]) -> Option<[[f32; 3]; 3]> {
+    let cofactor = |r0: usize, r1: usize, c0: usize, c1: usize| {
+        m[r0][c0] * m[r1][c1] - m[r0][c1] * m[r1][c0]
+    };
+    let adjugate = [
+        [cofactor(1, 2, 1, 2), -cofactor(0, 2, 1, 2), cofactor(0, 1, 1, 2)],
+        [-cofactor(1, 2, 0, 2), cofactor(0, 2, 0, 2), -cofactor(0, 1, 0, 2)],
+        [cofactor(1, 2, 0, 1), -cofactor(0, 2, 0, 1), cofactor(0, 1, 0, 1)],
+    ];
+    let determinant: f32 = (0..3).map(|k| m[0][k] * adjugate[k][0]).sum();
+    if determinant.abs() < 1e-6 {
+        return None;
+    }
+    let mut inverse = adjugate;
+    for row in inverse.iter_mut() {
+        for value in row.iter_mut() {
+            *value /= determinant;
+        }
+    }
+    Some(inverse)
+}
+
+/// The tag table of a profile.
+struct Tags<'a> {
+    data: &'a [u8],
+    count: usize,
+}
+
+impl<'a> Tags<'a> {
+    fn new(data: &'a [u8]) -> ImageResult<Tags<'a>> {
+        let count = read_u32(data, 128)? as usize;
+        if count > (data.len() - 132) / 12 {
+            return Err(icc_error("tag table out of bounds"));
+        }
+        Ok(Tags { data, count })
+    }
+
+    /// The data of the tag with the signature `name`.
+    fn get(&self, name: &[u8; 4]) -> ImageResult<Option<&'a [u8]>> {
+        for i in 0..self.count {
+            let entry = 132 + 12 * i;
+            if &self.data[entry..entry + 4] != name {
+                continue;
+            }
+            let offset = read_u32(self.data, entry + 4)? as usize;
+            let size = read_u32(self.data, entry + 8)? as usize;
+            return match offset.checked_add(size) {
+                Some(end) if end <= self.data.len() => Ok(Some(&self.data[offset..end])),
+                _ => Err(icc_error("tag out of bounds")),
+            };
+        }
+        Ok(None)
+    }
+}
+
+fn parse_xyz(tag: &[u8]) -> ImageResult<[f32; 3]> {
+    if tag.len() < 20 || &tag[..4] != b"XYZ " {
+        return Err(icc_error("invalid XYZ tag"));
+    }
+    Ok([read_s15_fixed16(tag, 8)?, read_s15_fixed16(tag, 12)?, read_s15_fixed16(tag, 16)?])
+}
+
+fn parse_curve(tag: &[u8]) -> ImageResult<ToneCurve> {
+    if tag.len() < 12 {
+        return Err(icc_error("invalid tone curve"));
+    }
+    match &tag[..4] {
+        b"curv" => {
+            let count = read_u32(tag, 8)? as usize;
+            match count {
+                0 => Ok(gamma(1.0)),
+                1 => Ok(gamma(read_u16(tag, 12)? as f32 / 256.0)),
+                _ => {
+                    let mut table = Vec::with_capacity(count.min(tag.len() / 2));
+                    for i in 0..count {
+                        table.push(read_u16(tag, 12 + 2 * i)? as f32 / 65535.0);
+                    }
+                    Ok(ToneCurve::Table(table))
+                }
+            }
+        }
+        b"para" => {
+            let kind = read_u16(tag, 8)?;
+            let count = match kind {
+                0 => 1,
+                1 => 3,
+                2 => 4,
+                3 => 5,
+                4 => 7,
+                _ => return Err(icc_error("unknown parametric curve")),
+            };
+            let mut p = [0.0; 7];
+            for (i, value) in p.iter_mut().take(count).enumerate() {
+                *value = read_s15_fixed16(tag, 12 + 4 * i)?;
+            }
+            let [g, a, b, c, d, e, f] = p;
+            if kind != 0 && a == 0.0 {
+                return Err(icc_error("invalid parametric curve"));
+            }
+            Ok(ToneCurve::Parametric(match kind {
+                0 => [g, 1.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+                1 => [g, a, b, 0.0, -b / a, 0.0, 0.0],
+                2 => [g, a, b, 0.0, -b / a, c, c],
+                3 => [g, a, b, c, d, 0.0, 0.0],
+                _ => [g, a, b, c, d, e, f],
+            }))
+        }
+        _ => Err(icc_error("invalid tone curve")),
+    }
+}
+
+fn read_bytes(data: &[u8], offset: usize, len: usize) -> ImageResult<&[u8]> {
+    data.get(offset..offset + len).ok_or_else(|| icc_error("unexpected end of data"))
+}
+
+fn read_u16(data: &[u8], offset: usize) -> ImageResult<u16> {
+    Ok(u16::from_be_bytes(read_bytes(data, offset, 2)?.try_into().unwrap()))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> ImageResult<u32> {
+    Ok(u32::from_be_bytes(read_bytes(data, offset, 4)?.try_into().unwrap()))
+}
+
+fn read_s15_fixed16(data: &[u8], offset: usize) -> ImageResult<f32> {
+    Ok(read_u32(data, offset)? as i32 as f32 / 65536.0)
+}
+
+fn icc_error<E: Into<Box<dyn std::error::Error + Send + Sync>>>(err: E) -> ImageError {
+    ImageError::Decoding(DecodingError::new(ImageFormatHint::Name("ICC".to_owned()), err))
+}
+
+fn icc_unsupported(kind: String) -> ImageError {
+    ImageError::Unsupported(UnsupportedError::from_format_and_kind(
+        ImageFormatHint::Name("ICC".to_owned()),
+        UnsupportedErrorKind::GenericFeature(kind),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{IccProfile, Transform};
+    use crate::error::ImageError;
+    use crate::{GrayImage, ImageBuffer, Luma, Rgb, RgbImage, Rgba};
+
+    /// Write a profile with the given color space and tags, each a signature and its data.
+    fn profile(space: &[u8; 4], tags: &[(&[u8; 4], Vec<u8>)]) -> Vec<u8> {
+        let mut data = vec![0; 128];
+        data[16..20].copy_from_slice(space);
+        data[20..24].copy_from_slice(b"XYZ ");
+        data[36..40].copy_from_slice(b"acsp");
+        data.extend_from_slice(&(tags.len() as u32).to_be_bytes());
+        let mut offset = 132 + 12 * tags.len();
+        for (name, tag) in tags {
+            data.extend_from_slice(*name);
+            data.extend_from_slice(&(offset as u32).to_be_bytes());
+            data.extend_from_slice(&(tag.len() as u32).to_be_bytes());
+            offset += tag.len();
+        }
+        for (_, tag) in tags {
+            data.extend_from_slice(tag);
+        }
+        let size = data.len() as u32;
+        data[..4].copy_from_slice(&size.to_be_bytes());
+        data
+    }
+
+    fn xyz(color: [f32; 3]) -> Vec<u8> {
+        let mut tag = b"XYZ \0\0\0\0".to_vec();
+        for &value in &color {
+            tag.extend_from_slice(&((value * 65536.0).round() as i32).to_be_bytes());
+        }
+        tag
+    }
+
+    fn gamma_curve(gamma: f32) -> Vec<u8> {
+        let mut tag = b"curv\0\0\0\0\0\0\0\x01".to_vec();
+        tag.extend_from_slice(&((gamma * 256.0).round() as u16).to_be_bytes());
+        tag
+    }
+
+    #[test]
+    fn test_parse_matrix_profile() {
+        let adobe = IccProfile::adobe_rgb();
+        let data = profile(
+            b"RGB ",
+            &[
+                (b"rXYZ", xyz(adobe.colorants[0])),
+                (b"gXYZ", xyz(adobe.colorants[1])),
+                (b"bXYZ", xyz(adobe.colorants[2])),
+                (b"rTRC", gamma_curve(2.2)),
+                (b"gTRC", gamma_curve(2.2)),
+                (b"bTRC", gamma_curve(2.2)),
+            ],
+        );
+        let parsed = IccProfile::parse(&data).unwrap();
+        assert!(!parsed.is_gray());
+
+        let builtin = Transform::new(&adobe, &IccProfile::srgb());
+        let parsed = Transform::new(&parsed, &IccProfile::srgb());
+        for &color in &[[0.0, 0.0, 0.0], [0.5, 0.5, 0.5], [0.2, 0.6, 0.3], [1.0, 1.0, 1.0]] {
+            let (a, b) = (builtin.map_color(color), parsed.map_color(color));
+            for c in 0..3 {
+                assert!((a[c] - b[c]).abs() < 1e-3, "{:?} {:?}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_gray_profile() {
+        // A linear gray profile, whose middle gray is lighter in sRGB.
+        let data = profile(b"GRAY", &[(b"kTRC", b"curv\0\0\0\0\0\0\0\0".to_vec())]);
+        let linear = IccProfile::parse(&data).unwrap();
+        assert!(linear.is_gray());
+
+        let mut image = GrayImage::from_fn(3, 1, |x, _| Luma([[0, 128, 255][x as usize]]));
+        Transform::new(&linear, &IccProfile::srgb()).apply(&mut image);
+        assert_eq!(image.into_raw(), [0, 188, 255]);
+
+        // A table with a gamma of two.
+        let mut table = b"curv\0\0\0\0\0\0\x01\x01".to_vec();
+        for i in 0..257u32 {
+            table.extend_from_slice(&((i * i * 65535 / (256 * 256)) as u16).to_be_bytes());
+        }
+        let data = profile(b"GRAY", &[(b"kTRC", table)]);
+        let transform = Transform::new(&IccProfile::parse(&data).unwrap(), &linear);
+        assert!((transform.map_gray(0.5) - 0.25).abs() < 1e-3);
+        let inverse = Transform::new(&linear, &IccProfile::parse(&data).unwrap());
+        assert!((inverse.map_gray(0.25) - 0.5).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_invalid_profiles() {
+        assert!(IccProfile::parse(&[0; 200]).is_err());
+
+        let truncated = profile(b"GRAY", &[(b"kTRC", b"curv\0\0\0\0\0\0\0\x05\0\0".to_vec())]);
+        match IccProfile::parse(&truncated) {
+            Err(ImageError::Decoding(_)) => {}
+            other => panic!("{:?}", other),
+        }
+
+        let lut_based = profile(b"RGB ", &[(b"A2B0", vec![0; 32])]);
+        match IccProfile::parse(&lut_based) {
+            Err(ImageError::Unsupported(_)) => {}
+            other => panic!("{:?}", other),
+        }
+
+        let cmyk = profile(b"CMYK", &[]);
+        match IccProfile::parse(&cmyk) {
+            Err(ImageError::Unsupported(_)) => {}
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_transform_images() {
+        let identity = Transform::new(&IccProfile::srgb(), &IccProfile::srgb());
+        let mut image = RgbImage::from_fn(16, 16, |x, y| Rgb([x as u8 * 16, y as u8 * 16, 77]));
+        let original = image.clone();
+        identity.apply(&mut image);
+        assert_eq!(image, original);
+
+        // The green of sRGB is less saturated in the wider Adobe RGB, and alpha is kept.
+        let mut image = ImageBuffer::from_pixel(1, 1, Rgba([0u16, 65535, 0, 1000]));
+        Transform::new(&IccProfile::srgb(), &IccProfile::adobe_rgb()).apply(&mut image);
+        let pixel = image.get_pixel(0, 0);
+        assert_eq!((pixel[0] / 1000, pixel[1], pixel[2] / 1000), (37, 65535, 15));
+        assert_eq!(pixel[3], 1000);
+
+        let mut image = ImageBuffer::from_pixel(1, 1, Rgb([0.5f32, 0.5, 0.5]));
+        Transform::new(&IccProfile::adobe_rgb(), &IccProfile::srgb()).apply(&mut image);
+        assert!((image.get_pixel(0, 0)[1] - 0.5).abs() < 0.01, "{:?}", image.get_pixel(0, 0));
+    }
+}
@@ -0,0 +1,314 @@
+//! Conversions between sRGB and the cylindrical and perceptual color spaces.
+//!
+//! The functions on colors take and return three floats, with sRGB components in `[0, 1]`:
+//!
+//! * HSV and HSL have the hue in degrees in `[0, 360)` and saturation and value or lightness in
+//!   `[0, 1]`. They are simple to compute and suited to picking and adjusting colors.
+//! * CIE L\*a\*b\* (for the D65 white point) has the lightness L\* in `[0, 100]` and the
+//!   opponent axes a\* and b\* roughly in `[-128, 128]`. Equal distances are about equally
+//!   visible, which makes it the space for comparing colors.
+//! * CIE LCh is L\*a\*b\* in polar coordinates, with the chroma C\* and the hue in degrees.
+//!
+//! Whole images are converted with [`to_color_space`] and back with [`from_color_space`], as
+//! `Rgb32FImage`s whose three channels hold the components of the color space in the order of
+//! its name. `DynamicImage` has shortcuts such as `to_lab32f`.
+//!
+//! # Examples
+//!
+//! ```
+//! use image::color_space::{rgb_to_hsv, to_color_space, ColorSpace};
+//! use image::{Rgb, RgbImage};
+//!
+//! assert_eq!(rgb_to_hsv([1.0, 0.5, 0.0]), [30.0, 1.0, 1.0]);
+//!
+//! let image = RgbImage::from_pixel(2, 2, Rgb([255, 255, 255]));
+//! let lab = to_color_space(&image, ColorSpace::Lab);
+//! assert!((lab.get_pixel(0, 0)[0] - 100.0).abs() < 0.01);
+//! ```
+//!
+//! [`to_color_space`]: fn.to_color_space.html
+//! [`from_color_space`]: fn.from_color_space.html
+
+use num_traits::NumCast;
+
+use crate::buffer_::Rgb32FImage;
+use crate::color::Rgb;
+use crate::image::GenericImageView;
+use crate::imageops::plane;
+use crate::imageops::srgb::{from_linear, to_linear};
+use crate::traits::{Pixel, Primitive};
+
+/// A color space that whole images can be converted to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ColorSpace {
+    /// Hue, saturation and value, see [`rgb_to_hsv`](fn.rgb_to_hsv.html).
+    Hsv,
+    /// Hue, saturation and lightness, see [`rgb_to_hsl`](fn.rgb_to_hsl.html).
+    Hsl,
+    /// CIE L\*a\*b\*, see [`rgb_to_lab`](fn.rgb_to_lab.html).
+    Lab,
+    /// CIE LCh, see [`rgb_to_lch`](fn.rgb_to_lch.html).
+    Lch,
+}
+
+impl ColorSpace {
+    fn from_rgb(self, rgb: [f32; 3]) -> [f32; 3] {
+        match self {
+            ColorSpace::Hsv => rgb_to_hsv(rgb),
+            ColorSpace::Hsl => rgb_to_hsl(rgb),
+            ColorSpace::Lab => rgb_to_lab(rgb),
+            ColorSpace::Lch => rgb_to_lch(rgb),
+        }
+    }
+
+    fn to_rgb(self, color: [f32; 3]) -> [f32; 3] {
+        match self {
+            ColorSpace::Hsv => hsv_to_rgb(color),
+            ColorSpace::Hsl => hsl_to_rgb(color),
+            ColorSpace::Lab => lab_to_rgb(color),
+            ColorSpace::Lch => lch_to_rgb(color),
+        }
+    }
+}
+
+/// Convert an image to a color space.
+///
+/// Pixels are converted to RGB first, and alpha is dropped. Integer samples are scaled to
+/// `[0, 1]`.
+pub fn to_color_space<I, P, S>(image: &I, space: ColorSpace) -> Rgb32FImage
+where
+    I: GenericImageView<Pixel = P>,
+    P: Pixel<Subpixel = S> + 'static,
+    S: Primitive + 'static,
+{
+    let (width, height) = image.dimensions();
+    let max = plane::nominal_max::<S>();
+    Rgb32FImage::from_fn(width, height, |x, y| {
+        let rgb = image.get_pixel(x, y).to_rgb();
+        let mut normalized = [0.0; 3];
+        for (n, &sample) in normalized.iter_mut().zip(rgb.channels()) {
+            let sample: f32 = NumCast::from(sample).unwrap();
+            *n = sample / max;
+        }
+        Rgb(space.from_rgb(normalized))
+    })
+}
+
+/// Convert an image from a color space back to sRGB, the inverse of [`to_color_space`].
+///
+/// Colors outside of the sRGB gamut, which Lab and LCh can express, are not clamped.
+///
+/// [`to_color_space`]: fn.to_color_space.html
+pub fn from_color_space(image: &Rgb32FImage, space: ColorSpace) -> Rgb32FImage {
+    let mut out = image.clone();
+    for pixel in out.pixels_mut() {
+        pixel.0 = space.to_rgb(pixel.0);
+    }
+    out
+}
+
+/// Convert sRGB to HSV.
+pub fn rgb_to_hsv([r, g, b]: [f32; 3]) -> [f32; 3] {
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let chroma = max - min;
+    let saturation = if max > 0.0 { chroma / max } else { 0.0 };
+    [hue(r, g, b, max, chroma), saturation, max]
+}
+
+/// Convert HSV to sRGB, the inverse of [`rgb_to_hsv`]. Hues outside of `[0, 360)` wrap around.
+///
+/// [`rgb_to_hsv`]: fn.rgb_to_hsv.html
+pub fn hsv_to_rgb([h, s, v]: [f32; 3]) -> [f32; 3] {
+    let chroma = v * s;
+    from_hue_chroma(h, chroma, v - chroma)
+}
+
+/// Convert sRGB to HSL.
+pub fn rgb_to_hsl([r, g, b]: [f32; 3]) -> [f32; 3] {
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let chroma = max - min;
+    let lightness = (max + min) / 2.0;
+    let denominator = 1.0 - (2.0 * lightness - 1.0).abs();
+    let saturation = if denominator > 0.0 { chroma / denominator } else { 0.0 };
+    [hue(r, g, b, max, chroma), saturation, lightness]
+}
+
+/// Convert HSL to sRGB, the inverse of [`rgb_to_hsl`]. Hues outside of `[0, 360)` wrap around.
+///
+/// [`rgb_to_hsl`]: fn.rgb_to_hsl.html
+pub fn hsl_to_rgb([h, s, l]: [f32; 3]) -> [f32; 3] {
+    let chroma = (1.0 - (2.0 * l - 1.0).abs()) * s;
+    from_hue_chroma(h, chroma, l - chroma / 2.0)
+}
+
+/// Convert sRGB to CIE L\*a\*b\*.
+pub fn rgb_to_lab([r, g, b]: [f32; 3]) -> [f32; 3] {
+    let (r, g, b) = (to_linear(r), to_linear(g), to_linear(b));
+    let x = 0.412_456_4 * r + 0.357_576_1 * g + 0.180_437_5 * b;
+    let y = 0.212_672_9 * r + 0.715_152_2 * g + 0.072_175 * b;
+    let z = 0.019_333_9 * r + 0.119_192 * g + 0.950_304_1 * b;
+
+    let (fx, fy, fz) = (
+        lab_f(x / WHITE[0]),
+        lab_f(y / WHITE[1]),
+        lab_f(z / WHITE[2]),
+    );
+    [116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz)]
+}
+
+/// Convert CIE L\*a\*b\* to sRGB, the inverse of [`rgb_to_lab`]. The result is not clamped.
+///
+/// [`rgb_to_lab`]: fn.rgb_to_lab.html
+pub fn lab_to_rgb([l, a, b]: [f32; 3]) -> [f32; 3] {
+    let fy = (l + 16.0) / 116.0;
+    let x = WHITE[0] * lab_f_inverse(fy + a / 500.0);
+    let y = WHITE[1] * lab_f_inverse(fy);
+    let z = WHITE[2] * lab_f_inverse(fy - b / 200.0);
+
+    let r = 3.240_454_2 * x - 1.537_138_5 * y - 0.498_531_4 * z;
+    let g = -0.969_266 * x + 1.876_010_8 * y + 0.041_556 * z;
+    let b = 0.055_643_4 * x - 0.204_025_9 * y + 1.057_225_2 * z;
+    [from_linear(r), from_linear(g), from_linear(b)]
+}
+
+/// Convert sRGB to CIE LCh.
+pub fn rgb_to_lch(rgb: [f32; 3]) -> [f32; 3] {
+    let [l, a, b] = rgb_to_lab(rgb);
+    let hue = b.atan2(a).to_degrees();
+    [l, a.hypot(b), if hue < 0.0 { hue + 360.0 } else { hue }]
+}
+
+/// Convert CIE LCh to sRGB, the inverse of [`rgb_to_lch`]. The result is not clamped.
+///
+/// [`rgb_to_lch`]: fn.rgb_to_lch.html
+pub fn lch_to_rgb([l, c, h]: [f32; 3]) -> [f32; 3] {
+    let h = h.to_radians();
+    lab_to_rgb([l, c * h.cos(), c * h.sin()])
+}
+
+/// The D65 reference white in CIE XYZ.
+const WHITE: [f32; 3] = [0.950_47, 1.0, 1.088_83];
+
+fn lab_f(t: f32) -> f32 {
+    const DELTA: f32 = 6.0 / 29.0;
+    if t > DELTA * DELTA * DELTA {
+        t.cbrt()
+    } else {
+        t / (3.0 * DELTA * DELTA) + 4.0 / 29.0
+    }
+}
+
+fn lab_f_inverse(t: f32) -> f32 {
+    const DELTA: f32 = 6.0 / 29.0;
+    if t > DELTA {
+        t * t * t
+    } else {
+        3.0 * DELTA * DELTA * (t - 4.0 / 29.0)
+    }
+}
+
+/// The hue in degrees of an RGB color with the given maximum component and chroma.
+fn hue(r: f32, g: f32, b: f32, max: f32, chroma: f32) -> f32 {
+    if chroma <= 0.0 {
+        return 0.0;
+    }
+    let sector = if max == r {
+        (g - b) / chroma
+    } else if max == g {
+        (b - r) / chroma + 2.0
+    } else {
+        (r - g) / chroma + 4.0
+    };
+    let hue = 60.0 * sector;
+    if hue < 0.0 {
+        hue + 360.0
+    } else {
+        hue
+    }
+}
+
+/// The RGB color of a hue and chroma, with `offset` added to all components.
+fn from_hue_chroma(h: f32, chroma: f32, offset: f32) -> [f32; 3] {
+    let sector = (h / 60.0) % 6.0;
+    let sector = if sector < 0.0 { sector + 6.0 } else { sector };
+    let x = chroma * (1.0 - (sector % 2.0 - 1.0).abs());
+    let (r, g, b) = match sector as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    [r + offset, g + offset, b + offset]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DynamicImage, GrayImage, Luma, RgbImage};
+
+    fn assert_close(a: [f32; 3], b: [f32; 3], tolerance: f32) {
+        for c in 0..3 {
+            assert!((a[c] - b[c]).abs() <= tolerance, "{:?} != {:?}", a, b);
+        }
+    }
+
+    #[test]
+    fn test_known_values() {
+        assert_eq!(rgb_to_hsv([1.0, 0.0, 0.0]), [0.0, 1.0, 1.0]);
+        assert_eq!(rgb_to_hsv([0.0, 0.5, 0.5]), [180.0, 1.0, 0.5]);
+        assert_eq!(rgb_to_hsv([0.5, 0.5, 0.5]), [0.0, 0.0, 0.5]);
+        assert_eq!(rgb_to_hsv([1.0, 0.0, 0.5]), [330.0, 1.0, 1.0]);
+        assert_eq!(rgb_to_hsl([1.0, 0.0, 0.0]), [0.0, 1.0, 0.5]);
+        assert_eq!(rgb_to_hsl([1.0, 1.0, 1.0]), [0.0, 0.0, 1.0]);
+        assert_eq!(rgb_to_hsl([0.25, 0.25, 0.75]), [240.0, 0.5, 0.5]);
+
+        // Reference values for white and pure sRGB red and blue.
+        assert_close(rgb_to_lab([1.0, 1.0, 1.0]), [100.0, 0.0, 0.0], 0.01);
+        assert_close(rgb_to_lab([1.0, 0.0, 0.0]), [53.24, 80.09, 67.20], 0.05);
+        assert_close(rgb_to_lab([0.0, 0.0, 1.0]), [32.30, 79.19, -107.86], 0.05);
+        assert_close(rgb_to_lch([0.0, 0.0, 1.0]), [32.30, 133.81, 306.29], 0.05);
+    }
+
+    #[test]
+    fn test_roundtrips() {
+        let colors = [
+            [0.0, 0.0, 0.0],
+            [1.0, 1.0, 1.0],
+            [0.2, 0.5, 0.9],
+            [0.9, 0.1, 0.3],
+            [0.5, 0.8, 0.1],
+            [0.3, 0.3, 0.31],
+        ];
+        for &rgb in &colors {
+            assert_close(hsv_to_rgb(rgb_to_hsv(rgb)), rgb, 1e-5);
+            assert_close(hsl_to_rgb(rgb_to_hsl(rgb)), rgb, 1e-5);
+            assert_close(lab_to_rgb(rgb_to_lab(rgb)), rgb, 1e-4);
+            assert_close(lch_to_rgb(rgb_to_lch(rgb)), rgb, 1e-4);
+        }
+
+        // Hues wrap around.
+        assert_close(hsv_to_rgb([360.0 + 120.0, 1.0, 1.0]), [0.0, 1.0, 0.0], 1e-5);
+        assert_close(hsl_to_rgb([-120.0, 1.0, 0.5]), [0.0, 0.0, 1.0], 1e-5);
+    }
+
+    #[test]
+    fn test_images() {
+        let image = RgbImage::from_fn(4, 3, |x, y| Rgb([(x * 60) as u8, (y * 100) as u8, 200]));
+        for &space in &[ColorSpace::Hsv, ColorSpace::Hsl, ColorSpace::Lab, ColorSpace::Lch] {
+            let converted = to_color_space(&image, space);
+            let back = from_color_space(&converted, space);
+            let original = DynamicImage::ImageRgb8(image.clone()).to_rgb32f();
+            for (a, b) in back.pixels().zip(original.pixels()) {
+                assert_close(a.0, b.0, 1e-4);
+            }
+        }
+
+        let gray = DynamicImage::ImageLuma8(GrayImage::from_pixel(1, 1, Luma([255])));
+        assert_close(gray.to_lab32f().get_pixel(0, 0).0, [100.0, 0.0, 0.0], 0.01);
+        assert_close(gray.to_hsv32f().get_pixel(0, 0).0, [0.0, 0.0, 1.0], 0.0);
+    }
+}
@@ -0,0 +1,398 @@
+//! Drawing of lines and simple shapes onto images.
+//!
+//! The functions draw onto any [`GenericImage`] with a color of its own pixel type, and clip
+//! shapes at the border of the image, so coordinates may lie outside of it. Points are given in
+//! pixels, with the center of the pixel `(x, y)` at `(x as f32, y as f32)`.
+//!
+//! Pixels covered by a shape are replaced by the color. With anti-aliasing, pixels that are only
+//! partly covered are instead mixed with the color by the fraction of their coverage, which
+//! smooths the jagged edges of lines and curves.
+//!
+//! # Examples
+//!
+//! ```
+//! use image::draw::{draw_filled_rect, draw_line};
+//! use image::math::Rect;
+//! use image::{Rgb, RgbImage};
+//!
+//! let mut image = RgbImage::new(16, 16);
+//! draw_filled_rect(&mut image, Rect { x: 2, y: 2, width: 4, height: 4 }, Rgb([255, 0, 0]));
+//! draw_line(&mut image, (0.0, 15.0), (15.0, 0.0), Rgb([255, 255, 255]), true);
+//! assert_eq!(image.get_pixel(3, 3), &Rgb([255, 0, 0]));
+//! assert_eq!(image.get_pixel(8, 7), &Rgb([255, 255, 255]));
+//! ```
+//!
+//! [`GenericImage`]: ../trait.GenericImage.html
+
+use num_traits::NumCast;
+
+use crate::image::GenericImage;
+use crate::imageops::FloatNearest;
+use crate::math::Rect;
+use crate::traits::Pixel;
+use crate::utils::clamp;
+
+/// Draw a line of one pixel width from `start` to `end`.
+///
+/// Without anti-aliasing, the pixel closest to the line is set in every column, or in every row
+/// for lines that are steeper than 45 degrees. With anti-aliasing, the color is split between
+/// the two closest pixels, as in the algorithm of Xiaolin Wu.
+pub fn draw_line<I>(image: &mut I, start: (f32, f32), end: (f32, f32), color: I::Pixel, antialiased: bool)
+where
+    I: GenericImage,
+{
+    let (width, height) = image.dimensions();
+    // Step along the axis on which the line is longer, so that no pixels are skipped.
+    let steep = (end.1 - start.1).abs() > (end.0 - start.0).abs();
+    let (start, end, major_len) = if steep {
+        ((start.1, start.0), (end.1, end.0), height)
+    } else {
+        (start, end, width)
+    };
+    let (start, end) = if start.0 > end.0 { (end, start) } else { (start, end) };
+
+    let dx = end.0 - start.0;
+    let gradient = if dx == 0.0 { 0.0 } else { (end.1 - start.1) / dx };
+    let first = clamp(start.0.round(), 0.0, major_len as f32) as i64;
+    let last = clamp(end.0.round(), -1.0, major_len as f32 - 1.0) as i64;
+
+    for major in first..=last {
+        let minor = start.1 + gradient * (major as f32 - start.0);
+        let mut plot_at = |minor: f32, coverage: f32| {
+            let minor = clamp(minor, -1.0, width.max(height) as f32) as i64;
+            if steep {
+                plot(image, minor, major, &color, coverage);
+            } else {
+                plot(image, major, minor, &color, coverage);
+            }
+        };
+
+        if antialiased {
+            let below = minor.floor();
+            let fraction = minor - below;
+            plot_at(below, 1.0 - fraction);
+            plot_at(below + 1.0, fraction);
+        } else {
+            plot_at(minor.round(), 1.0);
+        }
+    }
+}
+
+/// Draw the outline of a rectangle, one pixel wide, on the pixels at its edges.
+pub fn draw_rect<I>(image: &mut I, rect: Rect, color: I::Pixel)
+where
+    I: GenericImage,
+{
+    if rect.width == 0 || rect.height == 0 {
+        return;
+    }
+
+    let left = rect.x as i64;
+    let top = rect.y as i64;
+    let right = left + rect.width as i64 - 1;
+    let bottom = top + rect.height as i64 - 1;
+    let (width, height) = image.dimensions();
+
+    for x in left..=right.min(width as i64) {
+        plot(image, x, top, &color, 1.0);
+        plot(image, x, bottom, &color, 1.0);
+    }
+    for y in top..=bottom.min(height as i64) {
+        plot(image, left, y, &color, 1.0);
+        plot(image, right, y, &color, 1.0);
+    }
+}
+
+/// Fill a rectangle with a color.
+pub fn draw_filled_rect<I>(image: &mut I, rect: Rect, color: I::Pixel)
+where
+    I: GenericImage,
+{
+    let (width, height) = image.dimensions();
+    let right = rect.x.saturating_add(rect.width).min(width);
+    let bottom = rect.y.saturating_add(rect.height).min(height);
+
+    for y in rect.y..bottom {
+        for x in rect.x..right {
+            image.put_pixel(x, y, color);
+        }
+    }
+}
+
+/// Draw the outline of a circle, one pixel wide.
+///
+/// Without anti-aliasing, the center and radius are rounded to whole pixels and the circle is
+/// drawn with the midpoint algorithm. With anti-aliasing, every pixel is covered by how close it
+/// is to the circle, so the center and radius may lie between pixels.
+pub fn draw_circle<I>(image: &mut I, center: (f32, f32), radius: f32, color: I::Pixel, antialiased: bool)
+where
+    I: GenericImage,
+{
+    let (width, height) = image.dimensions();
+    if width == 0 || height == 0 || radius.is_nan() || radius < 0.0 {
+        return;
+    }
+
+    if !antialiased {
+        let limit = width.max(height) as f32;
+        let cx = clamp(center.0.round(), -limit - radius, 2.0 * limit + radius) as i64;
+        let cy = clamp(center.1.round(), -limit - radius, 2.0 * limit + radius) as i64;
+        let radius = radius.round().min(2.0 * limit) as i64;
+
+        let (mut x, mut y) = (radius, 0);
+        let mut error = 1 - radius;
+        while x >= y {
+            for &(dx, dy) in &[(x, y), (y, x), (-y, x), (-x, y), (-x, -y), (-y, -x), (y, -x), (x, -y)] {
+                plot(image, cx + dx, cy + dy, &color, 1.0);
+            }
+            y += 1;
+            if error < 0 {
+                error += 2 * y + 1;
+            } else {
+                x -= 1;
+                error += 2 * (y - x) + 1;
+            }
+        }
+        return;
+    }
+
+    let left = clamp((center.0 - radius - 1.0).floor(), 0.0, width as f32) as u32;
+    let right = clamp((center.0 + radius + 1.0).ceil(), 0.0, width as f32 - 1.0) as u32;
+    let top = clamp((center.1 - radius - 1.0).floor(), 0.0, height as f32) as u32;
+    let bottom = clamp((center.1 + radius + 1.0).ceil(), 0.0, height as f32 - 1.0) as u32;
+
+    for y in top..=bottom {
+        for x in left..=right {
+            let dx = x as f32 - center.0;
+            let dy = y as f32 - center.1;
+            let distance = (dx * dx + dy * dy).sqrt();
+            plot(image, x as i64, y as i64, &color, 1.0 - (distance - radius).abs());
+        }
+    }
+}
+
+/// Fill a polygon with a color.
+///
+/// The polygon is closed by an edge from the last point back to the first. Where edges cross,
+/// areas are filled by the even-odd rule. Without anti-aliasing, a pixel is filled if its center
+/// is inside the polygon. With anti-aliasing, the coverage of every pixel is estimated on four
+/// scanlines through it.
+pub fn draw_polygon<I>(image: &mut I, points: &[(f32, f32)], color: I::Pixel, antialiased: bool)
+where
+    I: GenericImage,
+{
+    if points.len() < 3 {
+        return;
+    }
+
+    let (width, height) = image.dimensions();
+    let min_y = points.iter().map(|p| p.1).fold(std::f32::INFINITY, f32::min);
+    let max_y = points.iter().map(|p| p.1).fold(std::f32::NEG_INFINITY, f32::max);
+    let top = clamp((min_y - 0.5).floor(), 0.0, height as f32) as u32;
+    let bottom = clamp((max_y + 0.5).ceil() + 1.0, 0.0, height as f32) as u32;
+
+    let scanlines: &[f32] = if antialiased { &[-0.375, -0.125, 0.125, 0.375] } else { &[0.0] };
+    let weight = 1.0 / scanlines.len() as f32;
+    let mut coverage = vec![0.0f32; width as usize];
+    let mut crossings = Vec::new();
+
+    for y in top..bottom {
+        for c in coverage.iter_mut() {
+            *c = 0.0;
+        }
+
+        for &offset in scanlines {
+            let scan_y = y as f32 + offset;
+            crossings.clear();
+            for (i, &(x0, y0)) in points.iter().enumerate() {
+                let (x1, y1) = points[(i + 1) % points.len()];
+                if (y0 <= scan_y) != (y1 <= scan_y) {
+                    crossings.push(x0 + (scan_y - y0) * (x1 - x0) / (y1 - y0));
+                }
+            }
+            crossings.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+
+            for span in crossings.chunks(2) {
+                if span.len() < 2 {
+                    break;
+                }
+                let (start, end) = (span[0], span[1]);
+                if antialiased {
+                    // Add the overlap of the span with every pixel it touches.
+                    let first = clamp((start + 0.5).floor(), 0.0, width as f32) as u32;
+                    let last = clamp((end + 0.5).floor(), -1.0, width as f32 - 1.0);
+                    for x in first..(last + 1.0) as u32 {
+                        let overlap = end.min(x as f32 + 0.5) - start.max(x as f32 - 0.5);
+                        coverage[x as usize] += overlap.max(0.0) * weight;
+                    }
+                } else {
+                    // Fill the pixels with their center in the span.
+                    let first = clamp(start.ceil(), 0.0, width as f32) as u32;
+                    let last = clamp(end.ceil(), 0.0, width as f32) as u32;
+                    for x in first..last {
+                        coverage[x as usize] = 1.0;
+                    }
+                }
+            }
+        }
+
+        for (x, &c) in coverage.iter().enumerate() {
+            plot(image, x as i64, y as i64, &color, c);
+        }
+    }
+}
+
+/// Mix `color` into the pixel at `(x, y)` by the fraction `coverage`, if it is inside the image.
+fn plot<I: GenericImage>(image: &mut I, x: i64, y: i64, color: &I::Pixel, coverage: f32) {
+    let (width, height) = image.dimensions();
+    let outside = x < 0 || y < 0 || x >= width as i64 || y >= height as i64;
+    if outside || coverage.is_nan() || coverage <= 0.0 {
+        return;
+    }
+    let (x, y) = (x as u32, y as u32);
+
+    if coverage >= 1.0 {
+        image.put_pixel(x, y, *color);
+    } else {
+        let mixed = image.get_pixel(x, y).map2(color, |a, b| {
+            let a: f32 = NumCast::from(a).unwrap();
+            let b: f32 = NumCast::from(b).unwrap();
+            NumCast::from(FloatNearest(a + (b - a) * coverage)).unwrap()
+        });
+        image.put_pixel(x, y, mixed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{draw_circle, draw_filled_rect, draw_line, draw_polygon, draw_rect};
+    use crate::math::Rect;
+    use crate::{GrayImage, Luma, Rgba, RgbaImage};
+
+    /// The coordinates of all pixels that are not black.
+    fn lit(image: &GrayImage) -> Vec<(u32, u32)> {
+        image.enumerate_pixels().filter(|p| p.2[0] > 0).map(|p| (p.0, p.1)).collect()
+    }
+
+    /// The sum of all samples, in units of full pixels.
+    fn area(image: &GrayImage) -> f32 {
+        image.pixels().map(|p| f32::from(p[0])).sum::<f32>() / 255.0
+    }
+
+    #[test]
+    fn test_line() {
+        let mut image = GrayImage::new(5, 5);
+        draw_line(&mut image, (0.0, 0.0), (4.0, 2.0), Luma([255]), false);
+        assert_eq!(lit(&image), vec![(0, 0), (1, 1), (2, 1), (3, 2), (4, 2)]);
+
+        // Steep lines step along rows, and direction does not matter.
+        let mut image = GrayImage::new(5, 5);
+        draw_line(&mut image, (1.0, 4.0), (1.0, 0.0), Luma([255]), false);
+        assert_eq!(lit(&image), vec![(1, 0), (1, 1), (1, 2), (1, 3), (1, 4)]);
+
+        // A single point.
+        let mut image = GrayImage::new(3, 3);
+        draw_line(&mut image, (1.0, 1.0), (1.0, 1.0), Luma([255]), false);
+        assert_eq!(lit(&image), vec![(1, 1)]);
+    }
+
+    #[test]
+    fn test_antialiased_line() {
+        // A line between two rows covers both by half.
+        let mut image = GrayImage::new(4, 4);
+        draw_line(&mut image, (0.0, 1.5), (3.0, 1.5), Luma([200]), true);
+        for x in 0..4 {
+            assert_eq!(image.get_pixel(x, 1), &Luma([100]));
+            assert_eq!(image.get_pixel(x, 2), &Luma([100]));
+        }
+        assert!((area(&image) - 4.0 * 200.0 / 255.0).abs() < 0.05);
+
+        // On whole pixels, it is the same as without anti-aliasing.
+        let mut aliased = GrayImage::new(4, 4);
+        let mut smooth = GrayImage::new(4, 4);
+        draw_line(&mut aliased, (0.0, 3.0), (3.0, 0.0), Luma([255]), false);
+        draw_line(&mut smooth, (0.0, 3.0), (3.0, 0.0), Luma([255]), true);
+        assert_eq!(aliased, smooth);
+    }
+
+    #[test]
+    fn test_rects() {
+        let mut image = GrayImage::new(5, 5);
+        draw_rect(&mut image, Rect { x: 1, y: 1, width: 3, height: 3 }, Luma([255]));
+        assert_eq!(lit(&image).len(), 8);
+        assert_eq!(image.get_pixel(2, 2), &Luma([0]));
+
+        let mut image = GrayImage::new(5, 5);
+        draw_filled_rect(&mut image, Rect { x: 1, y: 2, width: 2, height: 2 }, Luma([255]));
+        assert_eq!(lit(&image), vec![(1, 2), (2, 2), (1, 3), (2, 3)]);
+
+        // Clipped at the border.
+        let mut image = GrayImage::new(5, 5);
+        draw_rect(&mut image, Rect { x: 3, y: 3, width: 10, height: 10 }, Luma([255]));
+        assert_eq!(lit(&image), vec![(3, 3), (4, 3), (3, 4)]);
+        draw_filled_rect(&mut image, Rect { x: 4, y: 4, width: 10, height: 10 }, Luma([255]));
+        assert_eq!(lit(&image), vec![(3, 3), (4, 3), (3, 4), (4, 4)]);
+    }
+
+    #[test]
+    fn test_circle() {
+        let mut image = GrayImage::new(7, 7);
+        draw_circle(&mut image, (3.0, 3.0), 2.0, Luma([255]), false);
+        let pixels = lit(&image);
+        assert_eq!(pixels.len(), 12);
+        for &(x, y) in &pixels {
+            let d = ((x as f32 - 3.0).powi(2) + (y as f32 - 3.0).powi(2)).sqrt();
+            assert!((d - 2.0).abs() < 0.5, "{:?}", (x, y));
+        }
+
+        // The anti-aliased outline has about the length of the circle.
+        let mut image = GrayImage::new(40, 40);
+        draw_circle(&mut image, (20.0, 20.0), 10.0, Luma([255]), true);
+        let length = 2.0 * std::f32::consts::PI * 10.0;
+        assert!((area(&image) - length).abs() < 0.05 * length, "{}", area(&image));
+        assert_eq!(image.get_pixel(30, 20), &Luma([255]));
+        assert_eq!(image.get_pixel(20, 20), &Luma([0]));
+
+        // Circles around the image, or outside of it, are clipped.
+        let mut image = GrayImage::new(4, 4);
+        draw_circle(&mut image, (2.0, 2.0), 100.0, Luma([255]), false);
+        draw_circle(&mut image, (-50.0, 1e9), 3.0, Luma([255]), true);
+        assert!(lit(&image).is_empty());
+    }
+
+    #[test]
+    fn test_polygon() {
+        let square = [(0.5, 0.5), (3.5, 0.5), (3.5, 3.5), (0.5, 3.5)];
+        let mut image = GrayImage::new(5, 5);
+        draw_polygon(&mut image, &square, Luma([255]), false);
+        let expected: Vec<_> = (1..4).flat_map(|y| (1..4).map(move |x| (x, y))).collect();
+        assert_eq!(lit(&image), expected);
+
+        // On pixel edges, anti-aliasing changes nothing.
+        let mut smooth = GrayImage::new(5, 5);
+        draw_polygon(&mut smooth, &square, Luma([255]), true);
+        assert_eq!(smooth, image);
+
+        // A triangle covering half of a 8x8 square.
+        let triangle = [(-0.5, -0.5), (7.5, -0.5), (-0.5, 7.5)];
+        let mut image = GrayImage::new(8, 8);
+        draw_polygon(&mut image, &triangle, Luma([255]), true);
+        assert!((area(&image) - 32.0).abs() < 0.1, "{}", area(&image));
+        assert_eq!(image.get_pixel(0, 0), &Luma([255]));
+        assert_eq!(image.get_pixel(7, 7), &Luma([0]));
+        assert_eq!(image.get_pixel(3, 3), &Luma([255]));
+        assert_eq!(image.get_pixel(3, 4), &Luma([128]));
+
+        // Too few points draw nothing.
+        let mut image = GrayImage::new(3, 3);
+        draw_polygon(&mut image, &[(0.0, 0.0), (2.0, 2.0)], Luma([255]), true);
+        assert!(lit(&image).is_empty());
+    }
+
+    #[test]
+    fn test_mixes_alpha() {
+        let mut image = RgbaImage::from_pixel(1, 2, Rgba([0, 0, 255, 0]));
+        draw_line(&mut image, (0.0, 0.5), (0.0, 0.5), Rgba([255, 0, 0, 255]), true);
+        assert_eq!(image.get_pixel(0, 0), &Rgba([128, 0, 128, 128]));
+    }
+}
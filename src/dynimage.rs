@@ -3,30 +3,33 @@ use std::io::Write;
 use std::path::Path;
 use std::u32;
 
-#[cfg(feature = "gif")]
-use crate::codecs::gif;
-#[cfg(feature = "png")]
-use crate::codecs::png;
 #[cfg(feature = "pnm")]
 use crate::codecs::pnm;
 
 use crate::buffer_::{
     BgrImage, BgraImage, ConvertBuffer, GrayAlphaImage, GrayAlpha16Image,
-    GrayImage, Gray16Image, ImageBuffer, RgbImage, Rgb16Image, RgbaImage,
-    Rgba16Image,
+    GrayImage, Gray16Image, ImageBuffer, RgbImage, Rgb16Image, Rgb32FImage, RgbaImage,
+    Rgba16Image, Rgba32FImage, CmykImage,
 };
 use crate::color::{self, IntoColor};
+use crate::color_profile;
+use crate::color_space::{self, ColorSpace};
 use crate::error::{ImageError, ImageFormatHint, ImageResult, ParameterError, ParameterErrorKind, UnsupportedError, UnsupportedErrorKind};
 use crate::flat::FlatSamples;
 use crate::image;
-use crate::image::{GenericImage, GenericImageView, ImageDecoder, ImageFormat, ImageOutputFormat};
+use crate::image::{EncoderOptions, GenericImage, GenericImageView, ImageDecoder, ImageEncoder, ImageFormat, ImageOutputFormat};
 use crate::io::free_functions;
 use crate::imageops;
 use crate::math::resize_dimensions;
+use crate::metadata::Orientation;
 use crate::traits::Pixel;
 
 /// A Dynamic Image
-#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+///
+/// Since the addition of the 32-bit float variants, whose samples have no total equality, this
+/// type implements neither `Eq` nor `Hash`.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum DynamicImage {
     /// Each pixel in this image is 8-bit Luma
     ImageLuma8(GrayImage),
@@ -57,6 +60,15 @@ pub enum DynamicImage {
 
     /// Each pixel in this image is 16-bit Rgb with alpha
     ImageRgba16(Rgba16Image),
+
+    /// Each pixel in this image is 32-bit float Rgb
+    ImageRgb32F(Rgb32FImage),
+
+    /// Each pixel in this image is 32-bit float Rgb with alpha
+    ImageRgba32F(Rgba32FImage),
+
+    /// Each pixel in this image is 8-bit Cmyk
+    ImageCmyk8(CmykImage),
 }
 
 macro_rules! dynamic_map(
@@ -72,6 +84,9 @@ macro_rules! dynamic_map(
                         DynamicImage::ImageLumaA16(ref $image) => DynamicImage::ImageLumaA16($action),
                         DynamicImage::ImageRgb16(ref $image) => DynamicImage::ImageRgb16($action),
                         DynamicImage::ImageRgba16(ref $image) => DynamicImage::ImageRgba16($action),
+                        DynamicImage::ImageRgb32F(ref $image) => DynamicImage::ImageRgb32F($action),
+                        DynamicImage::ImageRgba32F(ref $image) => DynamicImage::ImageRgba32F($action),
+                        DynamicImage::ImageCmyk8(ref $image) => DynamicImage::ImageCmyk8($action),
                 }
         );
 
@@ -87,6 +102,9 @@ macro_rules! dynamic_map(
                         DynamicImage::ImageLumaA16(ref mut $image) => DynamicImage::ImageLumaA16($action),
                         DynamicImage::ImageRgb16(ref mut $image) => DynamicImage::ImageRgb16($action),
                         DynamicImage::ImageRgba16(ref mut $image) => DynamicImage::ImageRgba16($action),
+                        DynamicImage::ImageRgb32F(ref mut $image) => DynamicImage::ImageRgb32F($action),
+                        DynamicImage::ImageRgba32F(ref mut $image) => DynamicImage::ImageRgba32F($action),
+                        DynamicImage::ImageCmyk8(ref mut $image) => DynamicImage::ImageCmyk8($action),
                 }
         );
 
@@ -102,6 +120,9 @@ macro_rules! dynamic_map(
                         DynamicImage::ImageLumaA16(ref $image) => $action,
                         DynamicImage::ImageRgb16(ref $image) => $action,
                         DynamicImage::ImageRgba16(ref $image) => $action,
+                        DynamicImage::ImageRgb32F(ref $image) => $action,
+                        DynamicImage::ImageRgba32F(ref $image) => $action,
+                        DynamicImage::ImageCmyk8(ref $image) => $action,
                 }
         );
 
@@ -117,6 +138,9 @@ macro_rules! dynamic_map(
                         DynamicImage::ImageLumaA16(ref mut $image) => $action,
                         DynamicImage::ImageRgb16(ref mut $image) => $action,
                         DynamicImage::ImageRgba16(ref mut $image) => $action,
+                        DynamicImage::ImageRgb32F(ref mut $image) => $action,
+                        DynamicImage::ImageRgba32F(ref mut $image) => $action,
+                        DynamicImage::ImageCmyk8(ref mut $image) => $action,
                 }
         );
 );
@@ -174,6 +198,21 @@ impl DynamicImage {
         DynamicImage::ImageRgba16(ImageBuffer::new(w, h))
     }
 
+    /// Creates a dynamic image backed by a buffer of RGB pixels with 32-bit float samples.
+    pub fn new_rgb32f(w: u32, h: u32) -> DynamicImage {
+        DynamicImage::ImageRgb32F(ImageBuffer::new(w, h))
+    }
+
+    /// Creates a dynamic image backed by a buffer of RGBA pixels with 32-bit float samples.
+    pub fn new_rgba32f(w: u32, h: u32) -> DynamicImage {
+        DynamicImage::ImageRgba32F(ImageBuffer::new(w, h))
+    }
+
+    /// Creates a dynamic image backed by a buffer of CMYK pixels.
+    pub fn new_cmyk8(w: u32, h: u32) -> DynamicImage {
+        DynamicImage::ImageCmyk8(ImageBuffer::new(w, h))
+    }
+
     /// Decodes an encoded image into a dynamic image.
     pub fn from_decoder<'a>(decoder: impl ImageDecoder<'a>)
         -> ImageResult<Self>
@@ -203,6 +242,45 @@ impl DynamicImage {
         })
     }
 
+    /// Returns a copy of this image as an RGB image with 32-bit float samples.
+    ///
+    /// Integer samples are scaled to the range from 0.0 to 1.0.
+    pub fn to_rgb32f(&self) -> Rgb32FImage {
+        dynamic_map!(*self, ref p -> {
+            p.convert()
+        })
+    }
+
+    /// Returns a copy of this image converted to a color space, with the three components of the
+    /// color space in the channels of an RGB image. See the [`color_space`] module.
+    ///
+    /// [`color_space`]: color_space/index.html
+    pub fn to_color_space(&self, space: ColorSpace) -> Rgb32FImage {
+        dynamic_map!(*self, ref p -> {
+            color_space::to_color_space(p, space)
+        })
+    }
+
+    /// Returns a copy of this image in HSV, with the hue in degrees.
+    pub fn to_hsv32f(&self) -> Rgb32FImage {
+        self.to_color_space(ColorSpace::Hsv)
+    }
+
+    /// Returns a copy of this image in HSL, with the hue in degrees.
+    pub fn to_hsl32f(&self) -> Rgb32FImage {
+        self.to_color_space(ColorSpace::Hsl)
+    }
+
+    /// Returns a copy of this image in CIE L\*a\*b\*.
+    pub fn to_lab32f(&self) -> Rgb32FImage {
+        self.to_color_space(ColorSpace::Lab)
+    }
+
+    /// Returns a copy of this image in CIE LCh, with the hue in degrees.
+    pub fn to_lch32f(&self) -> Rgb32FImage {
+        self.to_color_space(ColorSpace::Lch)
+    }
+
     /// Returns a copy of this image as an RGBA image.
     #[deprecated = "replaced by `to_rgba8`"]
     pub fn to_rgba(&self) -> RgbaImage {
@@ -225,6 +303,15 @@ impl DynamicImage {
         })
     }
 
+    /// Returns a copy of this image as an RGBA image with 32-bit float samples.
+    ///
+    /// Integer samples are scaled to the range from 0.0 to 1.0.
+    pub fn to_rgba32f(&self) -> Rgba32FImage {
+        dynamic_map!(*self, ref p -> {
+            p.convert()
+        })
+    }
+
     /// Returns a copy of this image as an BGR image.
     #[deprecated = "replaced by `to_bgr8`"]
     pub fn to_bgr(&self) -> BgrImage {
@@ -299,6 +386,16 @@ impl DynamicImage {
         })
     }
 
+    /// Returns a copy of this image as a CMYK image.
+    ///
+    /// Colors are converted without a color profile, with as much black ink as possible, and
+    /// alpha is dropped.
+    pub fn to_cmyk8(&self) -> CmykImage {
+        dynamic_map!(*self, ref p -> {
+            p.convert()
+        })
+    }
+
     /// Consume the image and returns a RGB image.
     ///
     /// If the image was already the correct format, it is returned as is.
@@ -333,6 +430,17 @@ impl DynamicImage {
         }
     }
 
+    /// Consume the image and returns a RGB image with 32-bit float samples.
+    ///
+    /// If the image was already the correct format, it is returned as is.
+    /// Otherwise, a copy is created.
+    pub fn into_rgb32f(self) -> Rgb32FImage {
+        match self {
+            DynamicImage::ImageRgb32F(x) => x,
+            x => x.to_rgb32f(),
+        }
+    }
+
     /// Consume the image and returns a RGBA image.
     ///
     /// If the image was already the correct format, it is returned as is.
@@ -367,6 +475,17 @@ impl DynamicImage {
         }
     }
 
+    /// Consume the image and returns a RGBA image with 32-bit float samples.
+    ///
+    /// If the image was already the correct format, it is returned as is.
+    /// Otherwise, a copy is created.
+    pub fn into_rgba32f(self) -> Rgba32FImage {
+        match self {
+            DynamicImage::ImageRgba32F(x) => x,
+            x => x.to_rgba32f(),
+        }
+    }
+
     /// Consume the image and returns a BGR image.
     ///
     /// If the image was already the correct format, it is returned as is.
@@ -485,6 +604,90 @@ impl DynamicImage {
         }
     }
 
+    /// Consume the image and returns a CMYK image.
+    ///
+    /// If the image was already the correct format, it is returned as is.
+    /// Otherwise, a copy is created.
+    pub fn into_cmyk8(self) -> CmykImage {
+        match self {
+            DynamicImage::ImageCmyk8(x) => x,
+            x => x.to_cmyk8(),
+        }
+    }
+
+    /// Returns a copy of this image with the given color type, scaling the samples between bit
+    /// depths.
+    ///
+    /// When samples lose precision, from 16-bit or float to 8-bit or from float to 16-bit, the
+    /// color channels are dithered with `dither` if one is given, which hides the banding of
+    /// smooth gradients. Otherwise they are rounded to the nearest level, unlike `to_rgb8` and
+    /// its siblings which truncate 16-bit samples.
+    ///
+    /// Returns an error for color types that a `DynamicImage` cannot hold.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use image::imageops::DitherMethod;
+    /// use image::{ColorType, DynamicImage, ImageBuffer, Luma};
+    ///
+    /// let gradient = ImageBuffer::from_fn(1024, 1, |x, _| Luma([x as u16 * 16]));
+    /// let image = DynamicImage::ImageLuma16(gradient);
+    /// let reduced = image.to_color_type(ColorType::L8, Some(DitherMethod::FloydSteinberg)).unwrap();
+    /// assert_eq!(reduced.color(), ColorType::L8);
+    /// ```
+    pub fn to_color_type(
+        &self,
+        color: color::ColorType,
+        dither: Option<imageops::DitherMethod>,
+    ) -> ImageResult<DynamicImage> {
+        use crate::color::{Bgr, Bgra, Cmyk, ColorType, Luma, LumaA, Rgb, Rgba};
+
+        let sample_bytes = |color: ColorType| color.bytes_per_pixel() / color.channel_count();
+        if sample_bytes(color) >= sample_bytes(self.color()) {
+            return Ok(match color {
+                ColorType::L8 => DynamicImage::ImageLuma8(self.to_luma8()),
+                ColorType::La8 => DynamicImage::ImageLumaA8(self.to_luma_alpha8()),
+                ColorType::Rgb8 => DynamicImage::ImageRgb8(self.to_rgb8()),
+                ColorType::Rgba8 => DynamicImage::ImageRgba8(self.to_rgba8()),
+                ColorType::Bgr8 => DynamicImage::ImageBgr8(self.to_bgr8()),
+                ColorType::Bgra8 => DynamicImage::ImageBgra8(self.to_bgra8()),
+                ColorType::L16 => DynamicImage::ImageLuma16(self.to_luma16()),
+                ColorType::La16 => DynamicImage::ImageLumaA16(self.to_luma_alpha16()),
+                ColorType::Rgb16 => DynamicImage::ImageRgb16(self.to_rgb16()),
+                ColorType::Rgba16 => DynamicImage::ImageRgba16(self.to_rgba16()),
+                ColorType::Rgb32F => DynamicImage::ImageRgb32F(self.to_rgb32f()),
+                ColorType::Rgba32F => DynamicImage::ImageRgba32F(self.to_rgba32f()),
+                ColorType::Cmyk8 => DynamicImage::ImageCmyk8(self.to_cmyk8()),
+                _ => return Err(unsupported_color(color)),
+            });
+        }
+
+        // Converts to float samples with the channels of the target, which are then reduced.
+        macro_rules! reduced {
+            ($pixel:ident) => {{
+                let image: ImageBuffer<$pixel<f32>, Vec<f32>> =
+                    dynamic_map!(*self, ref p -> p.convert());
+                imageops::reduce_depth(&image, dither)
+            }};
+        }
+
+        Ok(match color {
+            ColorType::L8 => DynamicImage::ImageLuma8(reduced!(Luma)),
+            ColorType::La8 => DynamicImage::ImageLumaA8(reduced!(LumaA)),
+            ColorType::Rgb8 => DynamicImage::ImageRgb8(reduced!(Rgb)),
+            ColorType::Rgba8 => DynamicImage::ImageRgba8(reduced!(Rgba)),
+            ColorType::Bgr8 => DynamicImage::ImageBgr8(reduced!(Bgr)),
+            ColorType::Bgra8 => DynamicImage::ImageBgra8(reduced!(Bgra)),
+            ColorType::L16 => DynamicImage::ImageLuma16(reduced!(Luma)),
+            ColorType::La16 => DynamicImage::ImageLumaA16(reduced!(LumaA)),
+            ColorType::Rgb16 => DynamicImage::ImageRgb16(reduced!(Rgb)),
+            ColorType::Rgba16 => DynamicImage::ImageRgba16(reduced!(Rgba)),
+            ColorType::Cmyk8 => DynamicImage::ImageCmyk8(reduced!(Cmyk)),
+            _ => return Err(unsupported_color(color)),
+        })
+    }
+
     /// Return a cut-out of this image delimited by the bounding rectangle.
     ///
     /// Note: this method does *not* modify the object,
@@ -658,6 +861,54 @@ impl DynamicImage {
         }
     }
 
+    /// Return a reference to an 32bit float RGB image
+    pub fn as_rgb32f(&self) -> Option<&Rgb32FImage> {
+        match *self {
+            DynamicImage::ImageRgb32F(ref p) => Some(p),
+            _ => None,
+        }
+    }
+
+    /// Return a mutable reference to an 32bit float RGB image
+    pub fn as_mut_rgb32f(&mut self) -> Option<&mut Rgb32FImage> {
+        match *self {
+            DynamicImage::ImageRgb32F(ref mut p) => Some(p),
+            _ => None,
+        }
+    }
+
+    /// Return a reference to an 32bit float RGBA image
+    pub fn as_rgba32f(&self) -> Option<&Rgba32FImage> {
+        match *self {
+            DynamicImage::ImageRgba32F(ref p) => Some(p),
+            _ => None,
+        }
+    }
+
+    /// Return a mutable reference to an 32bit float RGBA image
+    pub fn as_mut_rgba32f(&mut self) -> Option<&mut Rgba32FImage> {
+        match *self {
+            DynamicImage::ImageRgba32F(ref mut p) => Some(p),
+            _ => None,
+        }
+    }
+
+    /// Return a reference to an 8bit CMYK image
+    pub fn as_cmyk8(&self) -> Option<&CmykImage> {
+        match *self {
+            DynamicImage::ImageCmyk8(ref p) => Some(p),
+            _ => None,
+        }
+    }
+
+    /// Return a mutable reference to an 8bit CMYK image
+    pub fn as_mut_cmyk8(&mut self) -> Option<&mut CmykImage> {
+        match *self {
+            DynamicImage::ImageCmyk8(ref mut p) => Some(p),
+            _ => None,
+        }
+    }
+
     /// Return a view on the raw sample buffer for 8 bit per channel images.
     pub fn as_flat_samples_u8(&self) -> Option<FlatSamples<&[u8]>> {
         match *self {
@@ -667,6 +918,7 @@ impl DynamicImage {
             DynamicImage::ImageRgba8(ref p) => Some(p.as_flat_samples()),
             DynamicImage::ImageBgr8(ref p) => Some(p.as_flat_samples()),
             DynamicImage::ImageBgra8(ref p) => Some(p.as_flat_samples()),
+            DynamicImage::ImageCmyk8(ref p) => Some(p.as_flat_samples()),
             _ => None,
         }
     }
@@ -682,6 +934,15 @@ impl DynamicImage {
         }
     }
 
+    /// Return a view on the raw sample buffer for 32 bit float per channel images.
+    pub fn as_flat_samples_f32(&self) -> Option<FlatSamples<&[f32]>> {
+        match *self {
+            DynamicImage::ImageRgb32F(ref p) => Some(p.as_flat_samples()),
+            DynamicImage::ImageRgba32F(ref p) => Some(p.as_flat_samples()),
+            _ => None,
+        }
+    }
+
     /// Return this image's pixels as a native endian byte slice.
     pub fn as_bytes(&self) -> &[u8] {
         image_as_bytes(self)
@@ -712,6 +973,9 @@ impl DynamicImage {
             DynamicImage::ImageLumaA16(_) => color::ColorType::La16,
             DynamicImage::ImageRgb16(_) => color::ColorType::Rgb16,
             DynamicImage::ImageRgba16(_) => color::ColorType::Rgba16,
+            DynamicImage::ImageRgb32F(_) => color::ColorType::Rgb32F,
+            DynamicImage::ImageRgba32F(_) => color::ColorType::Rgba32F,
+            DynamicImage::ImageCmyk8(_) => color::ColorType::Cmyk8,
         }
     }
 
@@ -742,7 +1006,60 @@ impl DynamicImage {
             DynamicImage::ImageLumaA16(ref p) => DynamicImage::ImageLuma16(imageops::grayscale(p)),
             DynamicImage::ImageRgb16(ref p) => DynamicImage::ImageLuma16(imageops::grayscale(p)),
             DynamicImage::ImageRgba16(ref p) => DynamicImage::ImageLuma16(imageops::grayscale(p)),
+            DynamicImage::ImageRgb32F(ref p) => DynamicImage::ImageLuma16(imageops::grayscale(p).convert()),
+            DynamicImage::ImageRgba32F(ref p) => DynamicImage::ImageLuma16(imageops::grayscale(p).convert()),
+            DynamicImage::ImageCmyk8(ref p) => DynamicImage::ImageLuma8(imageops::grayscale(p)),
+        }
+    }
+
+    /// Return a grayscale version of this image, weighting the color channels as defined by
+    /// `standard` and optionally in linear light.
+    /// See [`imageops::grayscale_with`](imageops/fn.grayscale_with.html) for details.
+    ///
+    /// The color type of the result is the same as that of [`grayscale`](#method.grayscale).
+    pub fn to_luma_with(&self, standard: imageops::LumaStandard, linear: bool) -> DynamicImage {
+        macro_rules! luma {
+            ($p:expr) => {
+                imageops::grayscale_with($p, standard, linear)
+            };
         }
+        match *self {
+            DynamicImage::ImageLuma8(ref p) => DynamicImage::ImageLuma8(p.clone()),
+            DynamicImage::ImageLumaA8(ref p) => DynamicImage::ImageLuma8(luma!(p)),
+            DynamicImage::ImageRgb8(ref p) => DynamicImage::ImageLuma8(luma!(p)),
+            DynamicImage::ImageRgba8(ref p) => DynamicImage::ImageLuma8(luma!(p)),
+            DynamicImage::ImageBgr8(ref p) => DynamicImage::ImageLuma8(luma!(p)),
+            DynamicImage::ImageBgra8(ref p) => DynamicImage::ImageLuma8(luma!(p)),
+            DynamicImage::ImageLuma16(ref p) => DynamicImage::ImageLuma16(p.clone()),
+            DynamicImage::ImageLumaA16(ref p) => DynamicImage::ImageLuma16(luma!(p)),
+            DynamicImage::ImageRgb16(ref p) => DynamicImage::ImageLuma16(luma!(p)),
+            DynamicImage::ImageRgba16(ref p) => DynamicImage::ImageLuma16(luma!(p)),
+            DynamicImage::ImageRgb32F(ref p) => DynamicImage::ImageLuma16(luma!(p).convert()),
+            DynamicImage::ImageRgba32F(ref p) => DynamicImage::ImageLuma16(luma!(p).convert()),
+            DynamicImage::ImageCmyk8(ref p) => DynamicImage::ImageLuma8(luma!(p)),
+        }
+    }
+
+    /// Convert the colors of this image from the color profile it was created in to sRGB.
+    ///
+    /// The profile is usually the one embedded in the file the image was decoded from. Gray
+    /// images keep their luminance, and CMYK images are returned unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use image::color_profile::IccProfile;
+    /// use image::{DynamicImage, Rgb, RgbImage};
+    ///
+    /// let image = DynamicImage::ImageRgb8(RgbImage::from_pixel(1, 1, Rgb([0, 255, 0])));
+    /// let srgb = image.convert_to_srgb(&IccProfile::display_p3());
+    /// assert_eq!(srgb.as_rgb8().unwrap().get_pixel(0, 0), &Rgb([0, 255, 0]));
+    /// ```
+    pub fn convert_to_srgb(&self, profile: &color_profile::IccProfile) -> DynamicImage {
+        let transform = color_profile::Transform::new(profile, &color_profile::IccProfile::srgb());
+        let mut image = self.clone();
+        dynamic_map!(image, ref mut p -> transform.apply(p));
+        image
     }
 
     /// Invert the colors of this image.
@@ -803,26 +1120,72 @@ impl DynamicImage {
     /// The image is scaled to the maximum possible size that fits
     /// within the larger (relative to aspect ratio) of the bounds
     /// specified by ```nwidth``` and ```nheight```, then cropped to
-    /// fit within the other bound.
+    /// fit within the other bound. ```gravity``` chooses which part
+    /// of the scaled image is kept.
     pub fn resize_to_fill(
         &self,
         nwidth: u32,
         nheight: u32,
         filter: imageops::FilterType,
+        gravity: imageops::Gravity,
     ) -> DynamicImage {
         let (width2, height2) =
             resize_dimensions(self.width(), self.height(), nwidth, nheight, true);
 
         let mut intermediate = self.resize_exact(width2, height2, filter);
-        let (iwidth, iheight) = intermediate.dimensions();
-        let ratio = u64::from(iwidth) * u64::from(nheight);
-        let nratio = u64::from(nwidth) * u64::from(iheight);
+        let (x, y) = gravity.offset(intermediate.dimensions(), (nwidth, nheight));
+        intermediate.crop(x, y, nwidth, nheight)
+    }
 
-        if nratio > ratio {
-            intermediate.crop(0, (iheight - nheight) / 2, nwidth, nheight)
-        } else {
-            intermediate.crop((iwidth - nwidth) / 2, 0, nwidth, nheight)
-        }
+    /// Resize this image using the specified filter algorithm and
+    /// center it on a canvas of exactly ```nwidth``` by ```nheight```
+    /// pixels filled with ```background```.
+    /// Returns a new image. The image's aspect ratio is preserved.
+    /// The image is scaled as by [`resize`](#method.resize), and the
+    /// remaining space on one axis is filled with the background.
+    /// Transparent parts of the image are blended onto the background.
+    pub fn resize_to_fit(
+        &self,
+        nwidth: u32,
+        nheight: u32,
+        filter: imageops::FilterType,
+        background: color::Rgba<u8>,
+    ) -> DynamicImage {
+        let resized = self.resize(nwidth, nheight, filter);
+        let (x, y) = imageops::Gravity::Center.offset((nwidth, nheight), resized.dimensions());
+
+        dynamic_map!(resized, ref p => {
+            let mut canvas = ImageBuffer::from_pixel(nwidth, nheight, background.into_color());
+            imageops::overlay(&mut canvas, p, x, y);
+            canvas
+        })
+    }
+
+    /// Place this image on a canvas of exactly ```nwidth``` by
+    /// ```nheight``` pixels filled with ```background```, without
+    /// scaling it. ```gravity``` chooses where on the canvas the image
+    /// is placed, and on axes where the image is larger than the canvas
+    /// which part of it is kept. Returns a new image.
+    ///
+    /// The pixels of the image are copied as they are, so transparent
+    /// parts stay transparent.
+    pub fn extend_to(
+        &self,
+        nwidth: u32,
+        nheight: u32,
+        background: color::Rgba<u8>,
+        gravity: imageops::Gravity,
+    ) -> DynamicImage {
+        let dimensions = self.dimensions();
+        let (place_x, place_y) = gravity.offset((nwidth, nheight), dimensions);
+        let (crop_x, crop_y) = gravity.offset(dimensions, (nwidth, nheight));
+        let x = i64::from(crop_x) - i64::from(place_x);
+        let y = i64::from(crop_y) - i64::from(place_y);
+
+        dynamic_map!(*self, ref p => {
+            let pad = imageops::PadMode::Constant(background.into_color());
+            imageops::crop_padded(p, x, y, nwidth, nheight, pad)
+        })
     }
 
     /// Performs a Gaussian blur on this image.
@@ -896,6 +1259,25 @@ impl DynamicImage {
         dynamic_map!(*self, ref p => imageops::rotate270(p))
     }
 
+    /// Rotate and flip this image so that it is displayed upright, given the orientation in
+    /// which it was stored.
+    ///
+    /// The orientation of photos is commonly read from their EXIF metadata with
+    /// `ImageDecoder::orientation`.
+    pub fn apply_orientation(&mut self, orientation: Orientation) {
+        let oriented = match orientation {
+            Orientation::NoTransforms => return,
+            Orientation::Rotate90 => self.rotate90(),
+            Orientation::Rotate180 => self.rotate180(),
+            Orientation::Rotate270 => self.rotate270(),
+            Orientation::FlipHorizontal => self.fliph(),
+            Orientation::FlipVertical => self.flipv(),
+            Orientation::Rotate90FlipH => self.rotate90().fliph(),
+            Orientation::Rotate270FlipH => self.rotate270().fliph(),
+        };
+        *self = oriented;
+    }
+
     /// Encode this image and write it to ```w```.
     ///
     /// Assumes the writer is buffered. In most cases,
@@ -908,9 +1290,25 @@ impl DynamicImage {
         w: &mut W,
         format: F,
     ) -> ImageResult<()> {
-        #[allow(unused_variables)]
+        self.write_with_options(w, format, EncoderOptions::default())
+    }
+
+    /// Encode this image and write it to ```w```, with the format independent settings of
+    /// `options` such as the quality or compression level.
+    ///
+    /// Settings that do not apply to `format` are ignored. A quality given in `options` takes
+    /// precedence over the one of `ImageOutputFormat::Jpeg` or `ImageOutputFormat::WebP`.
+    ///
+    /// Assumes the writer is buffered. In most cases,
+    /// you should wrap your writer in a `BufWriter` for best performance.
+    pub fn write_with_options<W: Write, F: Into<ImageOutputFormat>>(
+        &self,
+        w: &mut W,
+        format: F,
+        options: EncoderOptions,
+    ) -> ImageResult<()> {
         // When no features are supported
-        let w = w;
+        let _ = &w;
         #[allow(unused_variables,unused_mut)]
         let mut bytes = self.as_bytes();
         #[allow(unused_variables)]
@@ -923,23 +1321,7 @@ impl DynamicImage {
         match format {
             #[cfg(feature = "png")]
             image::ImageOutputFormat::Png => {
-                let p = png::PngEncoder::new(w);
-                let converted;
-                match *self {
-                    DynamicImage::ImageBgra8(_) => {
-                        converted = self.to_rgba().into_raw();
-                        bytes = &converted;
-                        color = color::ColorType::Rgba8;
-                    }
-                    DynamicImage::ImageBgr8(_) => {
-                        converted = self.to_rgb().into_raw();
-                        bytes = &converted;
-                        color = color::ColorType::Rgb8;
-                    }
-                    _ => {}
-                }
-                p.encode(&bytes, width, height, color)?;
-                Ok(())
+                self.write_with_encoder(free_functions::png_encoder(w, options))
             }
 
             #[cfg(feature = "pnm")]
@@ -965,15 +1347,35 @@ impl DynamicImage {
 
             #[cfg(feature = "gif")]
             image::ImageOutputFormat::Gif => {
-                let mut g = gif::GifEncoder::new(w);
+                let mut g = free_functions::gif_encoder(w, options);
                 g.encode_frame(crate::animation::Frame::new(self.to_rgba()))?;
                 Ok(())
             }
 
-            format => write_buffer_with_format(w, bytes, width, height, color, format)
+            format => free_functions::write_buffer_impl(w, bytes, width, height, color, format, options)
         }
     }
 
+    /// Encode this image with an encoder that has already been configured, for example
+    /// `JpegEncoder::new_with_quality(w, 85)`.
+    ///
+    /// BGR and BGRA images are converted to RGB and RGBA first, as no encoder accepts them.
+    pub fn write_with_encoder<E: ImageEncoder>(&self, encoder: E) -> ImageResult<()> {
+        let converted;
+        let (bytes, color) = match *self {
+            DynamicImage::ImageBgra8(_) => {
+                converted = self.to_rgba8().into_raw();
+                (&converted[..], color::ColorType::Rgba8)
+            }
+            DynamicImage::ImageBgr8(_) => {
+                converted = self.to_rgb8().into_raw();
+                (&converted[..], color::ColorType::Rgb8)
+            }
+            _ => (self.as_bytes(), self.color()),
+        };
+        encoder.write_image(bytes, self.width(), self.height(), color)
+    }
+
     /// Saves the buffer to a file at the path specified.
     ///
     /// The image format is derived from the file extension.
@@ -1061,6 +1463,24 @@ impl From<Rgba16Image> for DynamicImage {
     }
 }
 
+impl From<Rgb32FImage> for DynamicImage {
+    fn from(image: Rgb32FImage) -> Self {
+        DynamicImage::ImageRgb32F(image)
+    }
+}
+
+impl From<Rgba32FImage> for DynamicImage {
+    fn from(image: Rgba32FImage) -> Self {
+        DynamicImage::ImageRgba32F(image)
+    }
+}
+
+impl From<CmykImage> for DynamicImage {
+    fn from(image: CmykImage) -> Self {
+        DynamicImage::ImageCmyk8(image)
+    }
+}
+
 #[allow(deprecated)]
 impl GenericImageView for DynamicImage {
     type Pixel = color::Rgba<u8>;
@@ -1099,6 +1519,9 @@ impl GenericImage for DynamicImage {
             DynamicImage::ImageLumaA16(ref mut p) => p.put_pixel(x, y, pixel.to_luma_alpha().into_color()),
             DynamicImage::ImageRgb16(ref mut p) => p.put_pixel(x, y, pixel.to_rgb().into_color()),
             DynamicImage::ImageRgba16(ref mut p) => p.put_pixel(x, y, pixel.into_color()),
+            DynamicImage::ImageRgb32F(ref mut p) => p.put_pixel(x, y, pixel.to_rgb().into_color()),
+            DynamicImage::ImageRgba32F(ref mut p) => p.put_pixel(x, y, pixel.into_color()),
+            DynamicImage::ImageCmyk8(ref mut p) => p.put_pixel(x, y, pixel.into_color()),
         }
     }
     /// DEPRECATED: Use iterator `pixels_mut` to blend the pixels directly.
@@ -1114,6 +1537,9 @@ impl GenericImage for DynamicImage {
             DynamicImage::ImageLumaA16(ref mut p) => p.blend_pixel(x, y, pixel.to_luma_alpha().into_color()),
             DynamicImage::ImageRgb16(ref mut p) => p.blend_pixel(x, y, pixel.to_rgb().into_color()),
             DynamicImage::ImageRgba16(ref mut p) => p.blend_pixel(x, y, pixel.into_color()),
+            DynamicImage::ImageRgb32F(ref mut p) => p.blend_pixel(x, y, pixel.to_rgb().into_color()),
+            DynamicImage::ImageRgba32F(ref mut p) => p.blend_pixel(x, y, pixel.into_color()),
+            DynamicImage::ImageCmyk8(ref mut p) => p.blend_pixel(x, y, pixel.into_color()),
         }
     }
 
@@ -1127,6 +1553,14 @@ impl GenericImage for DynamicImage {
     }
 }
 
+/// The error for color types that a dynamic image cannot hold.
+fn unsupported_color(color_type: color::ColorType) -> ImageError {
+    ImageError::Unsupported(UnsupportedError::from_format_and_kind(
+        ImageFormatHint::Unknown,
+        UnsupportedErrorKind::Color(color_type.into()),
+    ))
+}
+
 /// Decodes an image and stores it into a dynamic image
 fn decoder_to_image<'a, I: ImageDecoder<'a>>(decoder: I) -> ImageResult<DynamicImage> {
     let (w, h) = decoder.dimensions();
@@ -1181,10 +1615,23 @@ fn decoder_to_image<'a, I: ImageDecoder<'a>>(decoder: I) -> ImageResult<DynamicI
             let buf = image::decoder_to_vec(decoder)?;
             ImageBuffer::from_raw(w, h, buf).map(DynamicImage::ImageLumaA16)
         }
-        _ => return Err(ImageError::Unsupported(UnsupportedError::from_format_and_kind(
-            ImageFormatHint::Unknown,
-            UnsupportedErrorKind::Color(color_type.into()),
-        ))),
+
+        color::ColorType::Rgb32F => {
+            let buf = image::decoder_to_vec(decoder)?;
+            ImageBuffer::from_raw(w, h, buf).map(DynamicImage::ImageRgb32F)
+        }
+
+        color::ColorType::Rgba32F => {
+            let buf = image::decoder_to_vec(decoder)?;
+            ImageBuffer::from_raw(w, h, buf).map(DynamicImage::ImageRgba32F)
+        }
+
+        color::ColorType::Cmyk8 => {
+            let buf = image::decoder_to_vec(decoder)?;
+            ImageBuffer::from_raw(w, h, buf).map(DynamicImage::ImageCmyk8)
+        }
+
+        _ => return Err(unsupported_color(color_type)),
     };
     match image {
         Some(image) => Ok(image),
@@ -1209,6 +1656,9 @@ fn image_to_bytes(image: &DynamicImage) -> Vec<u8> {
         DynamicImage::ImageLumaA16(ref a) => a.as_bytes().to_vec(),
         DynamicImage::ImageRgb16(ref a) => a.as_bytes().to_vec(),
         DynamicImage::ImageRgba16(ref a) => a.as_bytes().to_vec(),
+        DynamicImage::ImageRgb32F(ref a) => a.as_bytes().to_vec(),
+        DynamicImage::ImageRgba32F(ref a) => a.as_bytes().to_vec(),
+        DynamicImage::ImageCmyk8(ref a) => a.as_raw().clone(),
     }
 }
 
@@ -1224,22 +1674,28 @@ fn image_into_bytes(image: DynamicImage) -> Vec<u8> {
         DynamicImage::ImageLumaA16(_) => image.to_bytes(),
         DynamicImage::ImageRgb16(_) => image.to_bytes(),
         DynamicImage::ImageRgba16(_) => image.to_bytes(),
+        DynamicImage::ImageRgb32F(_) => image.to_bytes(),
+        DynamicImage::ImageRgba32F(_) => image.to_bytes(),
+        DynamicImage::ImageCmyk8(a) => a.into_raw(),
     }
 }
 
 fn image_as_bytes(image: &DynamicImage) -> &[u8] {
     use bytemuck::cast_slice;
     match image {
-        DynamicImage::ImageLuma8(a) => &*a,
-        DynamicImage::ImageLumaA8(a) => &*a,
-        DynamicImage::ImageRgb8(a) => &*a,
-        DynamicImage::ImageRgba8(a) => &*a,
-        DynamicImage::ImageBgr8(a) => &*a,
-        DynamicImage::ImageBgra8(a) => &*a,
-        DynamicImage::ImageLuma16(a) => cast_slice(&*a),
-        DynamicImage::ImageLumaA16(a) => cast_slice(&*a),
-        DynamicImage::ImageRgb16(a) => cast_slice(&*a),
-        DynamicImage::ImageRgba16(a) => cast_slice(&*a),
+        DynamicImage::ImageLuma8(a) => a.as_raw(),
+        DynamicImage::ImageLumaA8(a) => a.as_raw(),
+        DynamicImage::ImageRgb8(a) => a.as_raw(),
+        DynamicImage::ImageRgba8(a) => a.as_raw(),
+        DynamicImage::ImageBgr8(a) => a.as_raw(),
+        DynamicImage::ImageBgra8(a) => a.as_raw(),
+        DynamicImage::ImageLuma16(a) => cast_slice(a.as_raw()),
+        DynamicImage::ImageLumaA16(a) => cast_slice(a.as_raw()),
+        DynamicImage::ImageRgb16(a) => cast_slice(a.as_raw()),
+        DynamicImage::ImageRgba16(a) => cast_slice(a.as_raw()),
+        DynamicImage::ImageRgb32F(a) => cast_slice(a.as_raw()),
+        DynamicImage::ImageRgba32F(a) => cast_slice(a.as_raw()),
+        DynamicImage::ImageCmyk8(a) => a.as_raw(),
     }
 }
 
@@ -1345,7 +1801,8 @@ where
     F: Into<ImageOutputFormat>,
 {
     // thin wrapper function to strip generics
-    free_functions::write_buffer_impl(writer, buf, width, height, color, format.into())
+    free_functions::write_buffer_impl(
+        writer, buf, width, height, color, format.into(), EncoderOptions::default())
 }
 
 /// Create a new image from a byte slice
@@ -1412,4 +1869,327 @@ mod test {
         let image = super::open(im_path).unwrap();
         assert_eq!(image.color(), super::color::ColorType::Rgba16);
     }
+
+    #[cfg(feature = "png")]
+    #[test]
+    fn png_16bpc_round_trip() {
+        use crate::{ImageBuffer, ImageOutputFormat, Luma, Rgb, Rgba};
+
+        let sample = |x: u32, y: u32, c: u32| (x * 0x1234 + y * 0x0101 + c * 0x4000) as u16;
+        let images = vec![
+            super::DynamicImage::ImageLuma16(ImageBuffer::from_fn(5, 3, |x, y| {
+                Luma([sample(x, y, 0)])
+            })),
+            super::DynamicImage::ImageRgb16(ImageBuffer::from_fn(5, 3, |x, y| {
+                Rgb([sample(x, y, 0), sample(x, y, 1), sample(x, y, 2)])
+            })),
+            super::DynamicImage::ImageRgba16(ImageBuffer::from_fn(5, 3, |x, y| {
+                Rgba([sample(x, y, 0), sample(x, y, 1), sample(x, y, 2), sample(x, y, 3)])
+            })),
+        ];
+
+        for image in images {
+            let mut encoded = Vec::new();
+            image.write_to(&mut encoded, ImageOutputFormat::Png).unwrap();
+            let decoded = super::load_from_memory(&encoded).unwrap();
+            assert_eq!(decoded.color(), image.color());
+            assert_eq!(decoded.as_bytes(), image.as_bytes());
+        }
+    }
+
+    #[cfg(feature = "jpeg")]
+    #[test]
+    fn write_with_options_quality() {
+        use crate::{EncoderOptions, GenericImageView, ImageOutputFormat, Rgb, RgbImage};
+
+        let image = super::DynamicImage::ImageRgb8(RgbImage::from_fn(32, 32, |x, y| {
+            Rgb([(x * 8) as u8, (y * 8) as u8, ((x ^ y) * 8) as u8])
+        }));
+        let encode = |options| {
+            let mut encoded = Vec::new();
+            image.write_with_options(&mut encoded, ImageOutputFormat::Jpeg(100), options).unwrap();
+            encoded
+        };
+
+        let default = encode(EncoderOptions::new());
+        let low = encode(EncoderOptions::new().with_quality(10));
+        assert!(low.len() < default.len());
+        assert_eq!(super::load_from_memory(&low).unwrap().dimensions(), (32, 32));
+    }
+
+    #[cfg(feature = "png")]
+    #[test]
+    fn write_with_encoder_converts_bgr() {
+        use crate::codecs::png::PngEncoder;
+        use crate::{Bgr, ImageBuffer};
+
+        let image = super::DynamicImage::ImageBgr8(ImageBuffer::from_pixel(2, 2, Bgr([1, 2, 3])));
+        let mut encoded = Vec::new();
+        image.write_with_encoder(PngEncoder::new(&mut encoded)).unwrap();
+        let decoded = super::load_from_memory(&encoded).unwrap();
+        assert_eq!(decoded.color(), super::color::ColorType::Rgb8);
+        assert_eq!(&decoded.as_bytes()[..3], &[3, 2, 1]);
+    }
+
+    #[cfg(feature = "png")]
+    #[test]
+    fn write_with_encoder_errors() {
+        use crate::codecs::png::PngEncoder;
+        use crate::{ImageBuffer, ImageError, Luma, Rgb32FImage, RgbImage};
+
+        // Float samples are not supported by the encoder.
+        let image = super::DynamicImage::ImageRgb32F(Rgb32FImage::new(2, 2));
+        match image.write_with_encoder(PngEncoder::new(Vec::new())) {
+            Err(ImageError::Unsupported(_)) => {}
+            other => panic!("expected an unsupported error, got {:?}", other),
+        }
+
+        // Buffers without an exact color type are never passed to the encoder.
+        let buffer: ImageBuffer<Luma<f32>, _> = ImageBuffer::new(2, 2);
+        match buffer.write_with_encoder(PngEncoder::new(Vec::new())) {
+            Err(ImageError::Unsupported(_)) => {}
+            other => panic!("expected an unsupported error, got {:?}", other),
+        }
+
+        // PNG images can not be empty.
+        let image = super::DynamicImage::ImageRgb8(RgbImage::new(0, 0));
+        assert!(image.write_with_encoder(PngEncoder::new(Vec::new())).is_err());
+    }
+
+    #[cfg(feature = "png")]
+    #[test]
+    fn write_with_options_compression() {
+        use crate::{CompressionLevel, EncoderOptions, GrayImage, ImageOutputFormat, Luma};
+
+        let image = super::DynamicImage::ImageLuma8(GrayImage::from_fn(40, 40, |x, y| {
+            Luma([(x * y % 256) as u8])
+        }));
+        for &level in &[CompressionLevel::Fast, CompressionLevel::Default, CompressionLevel::Best] {
+            let mut encoded = Vec::new();
+            let options = EncoderOptions::new().with_compression(level);
+            image.write_with_options(&mut encoded, ImageOutputFormat::Png, options).unwrap();
+            let decoded = super::load_from_memory(&encoded).unwrap();
+            assert_eq!(decoded.as_bytes(), image.as_bytes());
+        }
+    }
+
+    #[test]
+    fn test_float_image() {
+        use crate::{ColorType, GenericImageView, Rgb, RgbImage, Rgba};
+
+        let image = super::DynamicImage::ImageRgb8(RgbImage::from_pixel(2, 2, Rgb([0, 51, 255])));
+        let float = image.to_rgba32f();
+        assert_eq!(float.get_pixel(0, 0), &Rgba([0.0, 0.2, 1.0, 1.0]));
+
+        let image = super::DynamicImage::from(float);
+        assert_eq!(image.color(), ColorType::Rgba32F);
+        assert_eq!(image.as_bytes().len(), 2 * 2 * 4 * 4);
+        assert_eq!(image.get_pixel(1, 1), Rgba([0, 51, 255, 255]));
+        assert_eq!(image.to_rgb8().get_pixel(1, 1), &Rgb([0, 51, 255]));
+    }
+
+    #[test]
+    fn test_apply_orientation() {
+        use crate::metadata::Orientation;
+        use crate::{GenericImageView, GrayImage, Luma};
+
+        // A 3x2 image with distinct pixels, and where they end up for display.
+        let stored = GrayImage::from_fn(3, 2, |x, y| Luma([(10 * y + x) as u8]));
+        let cases = [
+            (Orientation::NoTransforms, (3, 2), [0, 1, 2, 10, 11, 12]),
+            (Orientation::FlipHorizontal, (3, 2), [2, 1, 0, 12, 11, 10]),
+            (Orientation::Rotate180, (3, 2), [12, 11, 10, 2, 1, 0]),
+            (Orientation::FlipVertical, (3, 2), [10, 11, 12, 0, 1, 2]),
+            (Orientation::Rotate90FlipH, (2, 3), [0, 10, 1, 11, 2, 12]),
+            (Orientation::Rotate90, (2, 3), [10, 0, 11, 1, 12, 2]),
+            (Orientation::Rotate270FlipH, (2, 3), [12, 2, 11, 1, 10, 0]),
+            (Orientation::Rotate270, (2, 3), [2, 12, 1, 11, 0, 10]),
+        ];
+        for &(orientation, dimensions, expected) in &cases {
+            let mut image = super::DynamicImage::ImageLuma8(stored.clone());
+            image.apply_orientation(orientation);
+            assert_eq!(image.dimensions(), dimensions, "{:?}", orientation);
+            assert_eq!(image.as_bytes(), &expected[..], "{:?}", orientation);
+        }
+    }
+
+    #[test]
+    fn test_resize_to_fill() {
+        use crate::imageops::{FilterType, Gravity};
+        use crate::{GenericImageView, GrayImage, Luma};
+
+        // Columns of 0, 100 and 200 scaled by two and cropped to a square.
+        let image = GrayImage::from_fn(3, 1, |x, _| Luma([(100 * x) as u8]));
+        let image = super::DynamicImage::ImageLuma8(image);
+        let cases = [
+            (Gravity::West, [0, 0]),
+            (Gravity::Center, [100, 100]),
+            (Gravity::SouthEast, [200, 200]),
+        ];
+        for &(gravity, expected) in &cases {
+            let filled = image.resize_to_fill(2, 2, FilterType::Nearest, gravity);
+            assert_eq!(filled.dimensions(), (2, 2));
+            assert_eq!(&filled.as_bytes()[..2], &expected[..], "{:?}", gravity);
+        }
+    }
+
+    #[test]
+    fn test_resize_to_fit() {
+        use crate::imageops::FilterType;
+        use crate::{GenericImageView, Rgb, RgbImage, Rgba};
+
+        // Scaled to 6x3 and centered, leaving one row above and two below.
+        let image = super::DynamicImage::ImageRgb8(RgbImage::from_pixel(4, 2, Rgb([255, 0, 0])));
+        let fitted = image.resize_to_fit(6, 6, FilterType::Nearest, Rgba([0, 0, 255, 255]));
+        assert_eq!(fitted.color(), crate::ColorType::Rgb8);
+        assert_eq!(fitted.dimensions(), (6, 6));
+        for (_, y, pixel) in fitted.pixels() {
+            let expected = if y >= 1 && y < 4 { [255, 0, 0, 255] } else { [0, 0, 255, 255] };
+            assert_eq!(pixel, Rgba(expected), "row {}", y);
+        }
+    }
+
+    #[test]
+    fn test_extend_to() {
+        use crate::imageops::Gravity;
+        use crate::{GenericImageView, GrayImage, Luma, Rgba};
+
+        let image = super::DynamicImage::ImageLuma8(GrayImage::from_fn(3, 2, |x, y| {
+            Luma([(10 * y + x + 1) as u8])
+        }));
+
+        let extended = image.extend_to(5, 4, Rgba([0, 0, 0, 255]), Gravity::Center);
+        assert_eq!(extended.dimensions(), (5, 4));
+        let raw = extended.to_luma8().into_raw();
+        assert_eq!(raw, [0, 0, 0, 0, 0, 0, 1, 2, 3, 0, 0, 11, 12, 13, 0, 0, 0, 0, 0, 0]);
+
+        // Narrower than the image: cut on the left, padded at the top.
+        let extended = image.extend_to(2, 3, Rgba([255, 255, 255, 255]), Gravity::SouthEast);
+        assert_eq!(extended.to_luma8().into_raw(), [255, 255, 2, 3, 12, 13]);
+    }
+
+    #[test]
+    fn test_extend_to_edge_cases() {
+        use super::DynamicImage;
+        use crate::imageops::Gravity;
+        use crate::{ColorType, GenericImageView, ImageBuffer, Rgb, Rgba, RgbaImage};
+
+        // Empty canvases and empty images.
+        let image = DynamicImage::ImageRgba8(RgbaImage::from_pixel(2, 2, Rgba([1, 2, 3, 4])));
+        let empty = image.extend_to(0, 0, Rgba([9, 9, 9, 9]), Gravity::Center);
+        assert_eq!(empty.dimensions(), (0, 0));
+        let background = DynamicImage::ImageRgba8(RgbaImage::new(0, 0))
+            .extend_to(2, 1, Rgba([9, 8, 7, 6]), Gravity::NorthWest);
+        assert_eq!(background.to_rgba8().into_raw(), [9, 8, 7, 6, 9, 8, 7, 6]);
+
+        // The color type is kept, the background is converted to it.
+        let deep = DynamicImage::ImageRgb16(ImageBuffer::from_pixel(1, 1, Rgb([1000u16, 0, 0])));
+        let extended = deep.extend_to(2, 1, Rgba([255, 0, 0, 255]), Gravity::West);
+        assert_eq!(extended.color(), ColorType::Rgb16);
+        assert_eq!(extended.as_rgb16().unwrap().as_raw(), &[1000, 0, 0, 65535, 0, 0]);
+
+        let float = DynamicImage::ImageRgba32F(ImageBuffer::new(1, 1));
+        let extended = float.extend_to(1, 2, Rgba([0, 255, 0, 0]), Gravity::North);
+        assert_eq!(extended.color(), ColorType::Rgba32F);
+        let raw = extended.as_rgba32f().unwrap().as_raw();
+        assert_eq!(raw, &[0.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_to_color_type() {
+        use super::DynamicImage;
+        use crate::imageops::DitherMethod;
+        use crate::{ColorType, ImageBuffer, Luma, Rgb};
+
+        let image = DynamicImage::ImageRgb16(ImageBuffer::from_fn(64, 4, |x, _| {
+            let v = x as u16 * 32;
+            Rgb([v, v, 65535])
+        }));
+
+        // Without dithering, samples are rounded to the nearest level.
+        let rounded = image.to_color_type(ColorType::Rgb8, None).unwrap().into_rgb8();
+        assert_eq!(rounded.get_pixel(4, 0), &Rgb([0, 0, 255]));
+        assert_eq!(rounded.get_pixel(5, 0), &Rgb([1, 1, 255]));
+
+        // Dithering keeps the mean of every column.
+        let dithered = image.to_color_type(ColorType::Rgb8, Some(DitherMethod::FloydSteinberg));
+        let dithered = dithered.unwrap().into_rgb8();
+        let mean = |x: u32| (0..4).map(|y| dithered.get_pixel(x, y)[0] as f32).sum::<f32>() / 4.0;
+        assert!((mean(10) - 320.0 / 257.0).abs() < 0.5, "{}", mean(10));
+        assert!(dithered.pixels().all(|p| p[2] == 255));
+
+        // Conversions that do not lose precision ignore the dithering.
+        let wider = DynamicImage::ImageLuma8(ImageBuffer::from_pixel(2, 2, Luma([3])));
+        let wider = wider.to_color_type(ColorType::La16, Some(DitherMethod::Atkinson)).unwrap();
+        assert_eq!(wider.as_luma_alpha16().unwrap().get_pixel(1, 1).0, [771, 65535]);
+
+        let cmyk = image.to_color_type(ColorType::Cmyk8, None).unwrap();
+        assert_eq!(cmyk.color(), ColorType::Cmyk8);
+    }
+
+    #[test]
+    fn test_cmyk8() {
+        use super::DynamicImage;
+        use crate::color::{Cmyk, ColorType, Luma, Rgb, Rgba};
+        use crate::{GenericImage, GenericImageView, ImageBuffer};
+
+        let cmyk = ImageBuffer::from_pixel(2, 1, Cmyk([0, 51, 255, 102]));
+        let mut image = DynamicImage::ImageCmyk8(cmyk);
+        assert_eq!(image.color(), ColorType::Cmyk8);
+        assert_eq!(image.as_bytes(), &[0, 51, 255, 102, 0, 51, 255, 102]);
+        assert_eq!(image.get_pixel(0, 0), Rgba([153, 122, 0, 255]));
+        assert_eq!(image.to_rgb8().get_pixel(1, 0), &Rgb([153, 122, 0]));
+        assert_eq!(image.grayscale().as_luma8().unwrap().get_pixel(0, 0), &Luma([119]));
+
+        image.put_pixel(1, 0, Rgba([255, 255, 255, 255]));
+        assert_eq!(image.as_cmyk8().unwrap().get_pixel(1, 0), &Cmyk([0, 0, 0, 0]));
+
+        let rgb = DynamicImage::ImageRgb16(ImageBuffer::from_pixel(1, 1, Rgb([0, 65535, 65535])));
+        let cmyk = rgb.to_color_type(ColorType::Cmyk8, None).unwrap();
+        assert_eq!(cmyk.into_cmyk8().get_pixel(0, 0), &Cmyk([255, 0, 0, 0]));
+    }
+
+    #[test]
+    #[cfg(feature = "png")]
+    fn test_luma_alpha16_png() {
+        use super::DynamicImage;
+        use crate::{ImageBuffer, ImageFormat, LumaA};
+
+        let image = DynamicImage::ImageLumaA16(ImageBuffer::from_fn(3, 2, |x, y| {
+            LumaA([x as u16 * 20000, y as u16 * 65535])
+        }));
+        let mut encoded = Vec::new();
+        image.write_to(&mut encoded, ImageFormat::Png).unwrap();
+        assert_eq!(super::load_from_memory(&encoded).unwrap(), image);
+    }
+
+    #[test]
+    fn test_to_luma_with() {
+        use super::DynamicImage;
+        use crate::imageops::LumaStandard;
+        use crate::{ImageBuffer, Rgb};
+
+        let image = DynamicImage::ImageRgb32F(ImageBuffer::from_pixel(1, 1, Rgb([0.0, 1.0, 0.0])));
+        let luma = image.to_luma_with(LumaStandard::Rec709, false);
+        assert_eq!(luma.as_luma16().unwrap().get_pixel(0, 0).0, [46871]);
+    }
+
+    #[test]
+    fn test_convert_to_srgb() {
+        use super::DynamicImage;
+        use crate::color_profile::IccProfile;
+        use crate::{ImageBuffer, Rgba};
+
+        let pixel = Rgba([0, 0, 65535, 7]);
+        let image = DynamicImage::ImageRgba16(ImageBuffer::from_pixel(1, 1, pixel));
+        let srgb = image.convert_to_srgb(&IccProfile::srgb());
+        assert_eq!(srgb, image);
+
+        let srgb = image.convert_to_srgb(&IccProfile::adobe_rgb());
+        let pixel = srgb.as_rgba16().unwrap().get_pixel(0, 0);
+        assert_eq!((pixel[2], pixel[3]), (65535, 7));
+
+        let cmyk = DynamicImage::new_cmyk8(2, 2);
+        assert_eq!(cmyk.convert_to_srgb(&IccProfile::adobe_rgb()), cmyk);
+    }
 }
@@ -0,0 +1,201 @@
+//! Perceptual hashes, compact fingerprints of what an image looks like.
+//!
+//! Unlike a cryptographic hash, a perceptual hash changes only a little when the image is
+//! resized, recompressed or slightly edited, so near duplicates can be found by comparing the
+//! hashes with [`ImageHash::distance`]. As a rule of thumb, images with a distance of up to 10
+//! out of 64 bits are likely to be the same picture.
+//!
+//! Three hashes are available, all of the luminance of the image:
+//!
+//! * [`average_hash`] compares an 8x8 thumbnail to its mean. Fast but easily fooled.
+//! * [`difference_hash`] compares horizontal neighbours of a 9x8 thumbnail. Similarly fast and
+//!   more robust.
+//! * [`perceptual_hash`] compares the lowest frequencies of the discrete cosine transform of a
+//!   32x32 thumbnail. Slowest, and the most robust against changes of brightness and contrast.
+//!
+//! # Examples
+//!
+//! ```
+//! use image::hash::difference_hash;
+//! use image::imageops::{resize, FilterType};
+//! use image::{GrayImage, Luma};
+//!
+//! let image = GrayImage::from_fn(64, 48, |x, y| Luma([((x * y) % 256) as u8]));
+//! let smaller = resize(&image, 32, 24, FilterType::Triangle);
+//! assert!(difference_hash(&image).distance(difference_hash(&smaller)) <= 10);
+//! ```
+//!
+//! [`ImageHash::distance`]: struct.ImageHash.html#method.distance
+//! [`average_hash`]: fn.average_hash.html
+//! [`difference_hash`]: fn.difference_hash.html
+//! [`perceptual_hash`]: fn.perceptual_hash.html
+
+use std::f64::consts::PI;
+use std::fmt;
+
+use crate::image::GenericImageView;
+use crate::imageops::plane::{self, Plane};
+use crate::imageops::{resize, FilterType};
+use crate::traits::{Pixel, Primitive};
+
+/// A 64 bit perceptual hash.
+///
+/// The bits are ordered by the thumbnail they were computed from, row by row, with the first
+/// pixel in the most significant bit. Formatting with `{:x}` prints the usual 16 hex digits.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ImageHash(pub u64);
+
+impl ImageHash {
+    /// The number of bits in which two hashes differ, from 0 for equal hashes to 64.
+    pub fn distance(self, other: ImageHash) -> u32 {
+        (self.0 ^ other.0).count_ones()
+    }
+}
+
+impl fmt::LowerHex for ImageHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+/// Hash an image by comparing every pixel of an 8x8 thumbnail to their mean.
+pub fn average_hash<I, P, S>(image: &I) -> ImageHash
+where
+    I: GenericImageView<Pixel = P>,
+    P: Pixel<Subpixel = S> + 'static,
+    S: Primitive + 'static,
+{
+    let thumbnail = luma_thumbnail(image, 8, 8);
+    let mean = thumbnail.as_raw().iter().sum::<f32>() / 64.0;
+    from_bits(thumbnail.as_raw().iter().map(|&v| v > mean))
+}
+
+/// Hash an image by comparing every pixel of a 9x8 thumbnail to its right neighbour.
+///
+/// A bit is set where the luminance increases to the right.
+pub fn difference_hash<I, P, S>(image: &I) -> ImageHash
+where
+    I: GenericImageView<Pixel = P>,
+    P: Pixel<Subpixel = S> + 'static,
+    S: Primitive + 'static,
+{
+    let thumbnail = luma_thumbnail(image, 9, 8);
+    let rows = thumbnail.as_raw().chunks(9);
+    from_bits(rows.flat_map(|row| row.windows(2).map(|pair| pair[1] > pair[0])))
+}
+
+/// Hash an image by comparing the 8x8 lowest frequencies of the discrete cosine transform of a
+/// 32x32 thumbnail to their median.
+pub fn perceptual_hash<I, P, S>(image: &I) -> ImageHash
+where
+    I: GenericImageView<Pixel = P>,
+    P: Pixel<Subpixel = S> + 'static,
+    S: Primitive + 'static,
+{
+    const SIZE: usize = 32;
+    let thumbnail = luma_thumbnail(image, SIZE as u32, SIZE as u32);
+    let samples = thumbnail.as_raw();
+
+    // cosines[u][x] is the basis function of frequency u at sample x.
+    let cosines: Vec<Vec<f64>> = (0..8)
+        .map(|u| {
+            (0..SIZE)
+                .map(|x| ((2 * x + 1) as f64 * u as f64 * PI / (2 * SIZE) as f64).cos())
+                .collect()
+        })
+        .collect();
+
+    // Transform the rows, then the columns of the result, keeping only the low frequencies.
+    let mut rows = vec![[0.0f64; 8]; SIZE];
+    for (y, row) in rows.iter_mut().enumerate() {
+        for (u, coefficient) in row.iter_mut().enumerate() {
+            *coefficient = (0..SIZE)
+                .map(|x| f64::from(samples[y * SIZE + x]) * cosines[u][x])
+                .sum();
+        }
+    }
+    let mut coefficients = Vec::with_capacity(64);
+    for column_cosines in &cosines {
+        for u in 0..8 {
+            let column = rows.iter().zip(column_cosines).map(|(row, &c)| row[u] * c);
+            coefficients.push(column.sum::<f64>());
+        }
+    }
+
+    let mut sorted = coefficients.clone();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    let median = (sorted[31] + sorted[32]) / 2.0;
+    from_bits(coefficients.iter().map(|&c| c > median))
+}
+
+/// The luminance of an image, resized to the given dimensions.
+fn luma_thumbnail<I, P, S>(image: &I, width: u32, height: u32) -> Plane
+where
+    I: GenericImageView<Pixel = P>,
+    P: Pixel<Subpixel = S> + 'static,
+    S: Primitive + 'static,
+{
+    let luma = plane::luma(&plane::split(image));
+    resize(&luma, width, height, FilterType::Triangle)
+}
+
+fn from_bits<B: Iterator<Item = bool>>(bits: B) -> ImageHash {
+    ImageHash(bits.fold(0, |hash, bit| hash << 1 | u64::from(bit)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{average_hash, difference_hash, perceptual_hash, ImageHash};
+    use crate::imageops::{resize, FilterType};
+    use crate::{GrayImage, Luma, Pixel, Rgb, RgbImage};
+
+    fn hashes(image: &RgbImage) -> [ImageHash; 3] {
+        [average_hash(image), difference_hash(image), perceptual_hash(image)]
+    }
+
+    fn picture() -> RgbImage {
+        RgbImage::from_fn(120, 90, |x, y| {
+            let (fx, fy) = (x as f32 / 120.0, y as f32 / 90.0);
+            let v = |f: f32| (127.0 + 127.0 * f.sin()) as u8;
+            Rgb([v(fx * 7.0 + fy * 3.0), v(fx * 2.0 - fy * 9.0), v(fx * fy * 20.0)])
+        })
+    }
+
+    #[test]
+    fn test_distance() {
+        assert_eq!(ImageHash(0).distance(ImageHash(0)), 0);
+        assert_eq!(ImageHash(0b1011).distance(ImageHash(0b0110)), 3);
+        assert_eq!(ImageHash(0).distance(ImageHash(!0)), 64);
+        assert_eq!(format!("{:x}", ImageHash(0xab)), "00000000000000ab");
+    }
+
+    #[test]
+    fn test_difference_hash_of_gradient() {
+        let image = GrayImage::from_fn(90, 80, |x, _| Luma([x as u8]));
+        assert_eq!(difference_hash(&image), ImageHash(!0));
+        let image = GrayImage::from_fn(90, 80, |x, _| Luma([255 - x as u8]));
+        assert_eq!(difference_hash(&image), ImageHash(0));
+    }
+
+    #[test]
+    fn test_similar_images() {
+        let image = picture();
+        let original = hashes(&image);
+
+        let small = resize(&image, 60, 45, FilterType::CatmullRom);
+        let brighter = RgbImage::from_fn(120, 90, |x, y| {
+            image.get_pixel(x, y).map(|s| s.saturating_add(20))
+        });
+        for other in &[small, brighter] {
+            for (a, b) in original.iter().zip(hashes(other).iter()) {
+                assert!(a.distance(*b) <= 8, "{:x} {:x}", a, b);
+            }
+        }
+
+        // A different picture is far away.
+        let flipped = crate::imageops::flip_vertical(&image);
+        for (a, b) in original.iter().zip(hashes(&flipped).iter()) {
+            assert!(a.distance(*b) >= 16, "{:x} {:x}", a, b);
+        }
+    }
+}
@@ -9,14 +9,18 @@ use std::usize;
 
 use crate::ImageBuffer;
 use crate::color::{ColorType, ExtendedColorType};
-use crate::error::{ImageError, ImageFormatHint, ImageResult, LimitError, LimitErrorKind, ParameterError, ParameterErrorKind};
+use crate::error::{ImageError, ImageFormatHint, ImageResult, LimitError, LimitErrorKind, ParameterError, ParameterErrorKind, UnsupportedError, UnsupportedErrorKind};
+use crate::io::Limits;
 use crate::math::Rect;
+use crate::metadata::{ImageMetadata, Orientation, Resolution};
 use crate::traits::Pixel;
 
 use crate::animation::Frames;
 
 #[cfg(feature = "pnm")]
 use crate::pnm::PNMSubtype;
+#[cfg(feature = "webp")]
+use crate::codecs::webp::WebPQuality;
 
 /// An enumeration of supported image formats.
 /// Not all formats support both encoding and decoding.
@@ -64,6 +68,9 @@ pub enum ImageFormat {
     /// An Image in AVIF format.
     Avif,
 
+    /// An Image in JPEG XL format.
+    JpegXl,
+
     #[doc(hidden)]
     __NonExhaustive(crate::utils::NonExhaustiveMarker),
 }
@@ -100,6 +107,7 @@ impl ImageFormat {
                 "exr" => ImageFormat::OpenExr,
                 "pbm" | "pam" | "ppm" | "pgm" => ImageFormat::Pnm,
                 "ff" | "farbfeld" => ImageFormat::Farbfeld,
+                "jxl" => ImageFormat::JpegXl,
                 _ => return None,
             })
         }
@@ -107,6 +115,45 @@ impl ImageFormat {
         inner(ext.as_ref())
     }
 
+    /// Return the image format specified by a MIME type, such as the `Content-Type` of an HTTP
+    /// request. Parameters following the type are ignored.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use image::ImageFormat;
+    ///
+    /// let format = ImageFormat::from_mime_type("image/webp");
+    /// assert_eq!(format, Some(ImageFormat::WebP));
+    /// ```
+    pub fn from_mime_type<M>(mime_type: M) -> Option<Self> where M: AsRef<str> {
+        let mime_type = mime_type.as_ref();
+        let essence = mime_type.split(';').next().unwrap_or(mime_type);
+
+        Some(match essence.trim().to_ascii_lowercase().as_str() {
+            "image/avif" => ImageFormat::Avif,
+            "image/jpeg" | "image/jpg" | "image/pjpeg" => ImageFormat::Jpeg,
+            "image/png" | "image/apng" => ImageFormat::Png,
+            "image/gif" => ImageFormat::Gif,
+            "image/webp" => ImageFormat::WebP,
+            "image/tiff" | "image/tiff-fx" => ImageFormat::Tiff,
+            "image/x-targa" | "image/x-tga" => ImageFormat::Tga,
+            "image/vnd-ms.dds" | "image/vnd.ms-dds" => ImageFormat::Dds,
+            "image/bmp" | "image/x-bmp" | "image/x-ms-bmp" => ImageFormat::Bmp,
+            "image/x-icon" | "image/vnd.microsoft.icon" => ImageFormat::Ico,
+            "image/vnd.radiance" => ImageFormat::Hdr,
+            "image/x-exr" => ImageFormat::OpenExr,
+            "image/x-portable-bitmap"
+            | "image/x-portable-graymap"
+            | "image/x-portable-pixmap"
+            | "image/x-portable-anymap"
+            | "image/x-portable-arbitrarymap" => ImageFormat::Pnm,
+            "image/farbfeld" | "image/x-farbfeld" => ImageFormat::Farbfeld,
+            "image/jxl" => ImageFormat::JpegXl,
+            _ => return None,
+        })
+    }
+
     /// Return the image format specified by the path's file extension.
     ///
     /// # Example
@@ -157,6 +204,7 @@ impl ImageFormat {
             ImageFormat::Pnm => true,
             ImageFormat::Farbfeld => true,
             ImageFormat::Avif => true,
+            ImageFormat::JpegXl => true,
             ImageFormat::__NonExhaustive(marker) => match marker._private {},
         }
     }
@@ -176,10 +224,11 @@ impl ImageFormat {
             ImageFormat::Pnm => true,
             ImageFormat::Farbfeld => true,
             ImageFormat::Avif => true,
-            ImageFormat::WebP => false,
-            ImageFormat::Hdr => false,
+            ImageFormat::WebP => true,
+            ImageFormat::Hdr => true,
             ImageFormat::OpenExr => true,
             ImageFormat::Dds => false,
+            ImageFormat::JpegXl => false,
             ImageFormat::__NonExhaustive(marker) => match marker._private {},
         }
     }
@@ -210,6 +259,7 @@ impl ImageFormat {
             ImageFormat::Farbfeld => &["ff"],
             // According to: https://aomediacodec.github.io/av1-avif/#mime-registration
             ImageFormat::Avif => &["avif"],
+            ImageFormat::JpegXl => &["jxl"],
             ImageFormat::__NonExhaustive(marker) => match marker._private {},
         }
     }
@@ -250,6 +300,10 @@ pub enum ImageOutputFormat {
     /// An Image in TGA Format
     Tga,
 
+    #[cfg(feature = "hdr")]
+    /// An Image in Radiance HDR Format
+    Hdr,
+
     #[cfg(feature = "openexr")]
     /// An Image in OpenEXR Format
     OpenExr,
@@ -262,6 +316,10 @@ pub enum ImageOutputFormat {
     /// An image in AVIF Format
     Avif,
 
+    #[cfg(feature = "webp")]
+    /// An Image in WebP Format with specified compression
+    WebP(WebPQuality),
+
     /// A value for signalling an error: An unsupported format was requested
     // Note: When TryFrom is stabilized, this value should not be needed, and
     // a TryInto<ImageOutputFormat> should be used instead of an Into<ImageOutputFormat>.
@@ -290,6 +348,8 @@ impl From<ImageFormat> for ImageOutputFormat {
             ImageFormat::Farbfeld => ImageOutputFormat::Farbfeld,
             #[cfg(feature = "tga")]
             ImageFormat::Tga => ImageOutputFormat::Tga,
+            #[cfg(feature = "hdr")]
+            ImageFormat::Hdr => ImageOutputFormat::Hdr,
             #[cfg(feature = "openexr")]
             ImageFormat::OpenExr => ImageOutputFormat::OpenExr,
             #[cfg(feature = "tiff")]
@@ -297,12 +357,86 @@ impl From<ImageFormat> for ImageOutputFormat {
 
             #[cfg(feature = "avif-encoder")]
             ImageFormat::Avif => ImageOutputFormat::Avif,
+            #[cfg(feature = "webp")]
+            ImageFormat::WebP => ImageOutputFormat::WebP(WebPQuality::Lossless),
 
             f => ImageOutputFormat::Unsupported(format!("{:?}", f)),
         }
     }
 }
 
+/// Format independent settings of an encoder.
+///
+/// Each setting is only applied by formats with a matching parameter and is ignored by all
+/// others, a setting that is not given keeps the default of the format. See
+/// [`DynamicImage::write_with_options`](enum.DynamicImage.html#method.write_with_options).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct EncoderOptions {
+    quality: Option<u8>,
+    compression: Option<CompressionLevel>,
+    speed: Option<u8>,
+}
+
+/// How much effort a lossless compression should spend on making the file small.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CompressionLevel {
+    /// Compress quickly, producing larger files
+    Fast,
+    /// The default trade-off of the format
+    Default,
+    /// Produce the smallest files, taking longer to compress
+    Best,
+
+    #[doc(hidden)]
+    __NonExhaustive(crate::utils::NonExhaustiveMarker),
+}
+
+impl EncoderOptions {
+    /// Create options that keep the defaults of every format.
+    pub fn new() -> Self {
+        EncoderOptions::default()
+    }
+
+    /// Set the quality of lossy formats, between 1 (worst) and 100 (best).
+    ///
+    /// Used by JPEG and AVIF. WebP switches to lossy compression with this quality.
+    pub fn with_quality(mut self, quality: u8) -> Self {
+        self.quality = Some(quality.max(1).min(100));
+        self
+    }
+
+    /// Set the level of lossless compression.
+    ///
+    /// Used by PNG. TIFF compresses its strips with LZW, or with Deflate for `Best`.
+    pub fn with_compression(mut self, compression: CompressionLevel) -> Self {
+        self.compression = Some(compression);
+        self
+    }
+
+    /// Set the speed of the encoder, between 0 (slowest, best result) and 10 (fastest).
+    ///
+    /// Used by AVIF and by the color quantization of GIF.
+    pub fn with_speed(mut self, speed: u8) -> Self {
+        self.speed = Some(speed.min(10));
+        self
+    }
+
+    /// The quality of lossy formats, if set.
+    pub fn quality(&self) -> Option<u8> {
+        self.quality
+    }
+
+    /// The level of lossless compression, if set.
+    pub fn compression(&self) -> Option<CompressionLevel> {
+        self.compression
+    }
+
+    /// The speed of the encoder, if set.
+    pub fn speed(&self) -> Option<u8> {
+        self.speed
+    }
+}
+
 // This struct manages buffering associated with implementing `Read` and `Seek` on decoders that can
 // must decode ranges of bytes at a time.
 #[allow(dead_code)]
@@ -519,6 +653,58 @@ where
     Ok(buf)
 }
 
+/// Check that a section requested from `read_rect` lies inside the image and fits into the buffer.
+pub(crate) fn check_rect<'a, D: ImageDecoder<'a>>(
+    decoder: &D,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    buf: &[u8],
+) -> ImageResult<()> {
+    let (image_width, image_height) = decoder.dimensions();
+    if u64::from(x) + u64::from(width) > u64::from(image_width)
+        || u64::from(y) + u64::from(height) > u64::from(image_height)
+    {
+        return Err(ImageError::Parameter(ParameterError::from_kind(
+            ParameterErrorKind::DimensionMismatch,
+        )));
+    }
+
+    let total_bytes =
+        u64::from(width) * u64::from(height) * u64::from(decoder.color_type().bytes_per_pixel());
+    if buf.len() < usize::try_from(total_bytes).unwrap_or(usize::max_value()) {
+        panic!("output buffer too short\n expected `{}`, provided `{}`", total_bytes, buf.len());
+    }
+    Ok(())
+}
+
+/// Rows of an image that have been decoded at once, for decoders of formats that can not be
+/// decoded row by row.
+#[allow(dead_code)] // Unused if no such decoder is enabled.
+pub(crate) struct DecodedRows {
+    data: Vec<u8>,
+    position: usize,
+}
+
+#[allow(dead_code)]
+impl DecodedRows {
+    pub(crate) fn new(data: Vec<u8>) -> Self {
+        DecodedRows { data, position: 0 }
+    }
+
+    /// Copy the next row into `buf`. Returns `false` after the last row.
+    pub(crate) fn read_into(&mut self, buf: &mut [u8]) -> bool {
+        let row = match self.data.get(self.position..self.position + buf.len()) {
+            Some(row) if !buf.is_empty() => row,
+            _ => return false,
+        };
+        buf.copy_from_slice(row);
+        self.position += buf.len();
+        true
+    }
+}
+
 /// Represents the progress of an image operation.
 ///
 /// Note that this is not necessarily accurate and no change to the values passed to the progress
@@ -570,6 +756,61 @@ pub trait ImageDecoder<'a>: Sized {
         self.color_type().into()
     }
 
+    /// Returns the ICC color profile embedded in the image, if any.
+    ///
+    /// The profile is returned as the raw bytes of the ICC data, which can be handed to a color
+    /// management library. Decoders of formats without profile support always return `None`.
+    fn icc_profile(&mut self) -> Option<Vec<u8>> {
+        None
+    }
+
+    /// Returns the raw EXIF metadata embedded in the image, if any.
+    ///
+    /// The metadata is returned as its TIFF structure, without the `Exif\0\0` signature that
+    /// precedes it in JPEG files.
+    fn exif_metadata(&mut self) -> Option<Vec<u8>> {
+        None
+    }
+
+    /// Returns the XMP packet embedded in the image, if any.
+    fn xmp_metadata(&mut self) -> Option<Vec<u8>> {
+        None
+    }
+
+    /// Returns the physical resolution of the image, if it is stored in the file.
+    fn resolution(&mut self) -> Option<Resolution> {
+        None
+    }
+
+    /// Returns all metadata embedded in the image, to be passed on when it is encoded again.
+    fn metadata(&mut self) -> ImageMetadata {
+        ImageMetadata {
+            exif: self.exif_metadata(),
+            xmp: self.xmp_metadata(),
+            icc_profile: self.icc_profile(),
+        }
+    }
+
+    /// Returns the orientation in which the image is stored, if it is known.
+    ///
+    /// By default this reads the `Orientation` tag of the EXIF metadata. Use
+    /// `DynamicImage::apply_orientation` to display the decoded image upright.
+    fn orientation(&mut self) -> Option<Orientation> {
+        self.exif_metadata()
+            .and_then(|exif| Orientation::from_exif_chunk(&exif))
+    }
+
+    /// Apply resource limits to the decoding of this image.
+    ///
+    /// Returns an `ImageError::Limits` if the image is known to exceed them. The default
+    /// implementation checks the dimensions and the size of the decoded image. Decoders that
+    /// allocate significant memory besides the output buffer should also account for it.
+    fn set_limits(&mut self, limits: &Limits) -> ImageResult<()> {
+        let (width, height) = self.dimensions();
+        limits.check_dimensions(width, height)?;
+        limits.check_alloc(self.total_bytes())
+    }
+
     /// Returns a reader that can be used to obtain the bytes of the image. For the best
     /// performance, always try to read at least `scanline_bytes` from the reader at a time. Reading
     /// fewer bytes will cause the reader to perform internal buffering.
@@ -594,6 +835,80 @@ pub trait ImageDecoder<'a>: Sized {
         self.total_bytes()
     }
 
+    /// Decode the next row of the image into `buf`.
+    ///
+    /// Rows are returned from top to bottom, in the same layout as by `read_image`. The buffer
+    /// must be exactly one row, that is `width * color_type().bytes_per_pixel()` bytes, long.
+    /// Returns `Ok(false)` without modifying the buffer once all rows have been read.
+    ///
+    /// This allows processing images that do not fit into memory one row at a time. Decoders of
+    /// formats that can not be decoded incrementally, such as interlaced PNG or JPEG, decode
+    /// the complete image on the first call and then return its rows. The default implementation
+    /// returns an `ImageError::Unsupported`. Mixing this with other methods that read the image
+    /// data results in missing rows.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `buf.len()` is not the size of a row.
+    fn read_scanline(&mut self, buf: &mut [u8]) -> ImageResult<bool> {
+        let _ = buf;
+        Err(ImageError::Unsupported(UnsupportedError::from_format_and_kind(
+            ImageFormatHint::Unknown,
+            UnsupportedErrorKind::GenericFeature("row-wise decoding".to_owned()),
+        )))
+    }
+
+    /// Returns the number of bytes in a row of the image, the size of the buffer passed to
+    /// `read_scanline`.
+    fn row_bytes(&self) -> u64 {
+        u64::from(self.dimensions().0) * u64::from(self.color_type().bytes_per_pixel())
+    }
+
+    /// Decode a rectangular section of the image into `buf`.
+    ///
+    /// The output buffer is filled with the rows of the section, in the same layout as by
+    /// `read_image`. This allows extracting a small part of a huge image, such as a single tile,
+    /// without holding the complete image in memory. Decoders that can locate parts of the image,
+    /// for example tiled TIFF, only decode what is needed. The default implementation reads rows
+    /// with `read_scanline` up to the bottom of the section, so it can only be called once and
+    /// fails for decoders that do not support reading rows.
+    ///
+    /// An error is returned if the section is not contained in the image.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the output buffer isn't at least
+    /// `color_type().bytes_per_pixel() * width * height` bytes long.
+    fn read_rect(
+        &mut self,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+        buf: &mut [u8],
+    ) -> ImageResult<()> {
+        check_rect(self, x, y, width, height, buf)?;
+        if width == 0 || height == 0 {
+            return Ok(());
+        }
+
+        let bytes_per_pixel = usize::from(self.color_type().bytes_per_pixel());
+        let (start, len) = (x as usize * bytes_per_pixel, width as usize * bytes_per_pixel);
+        let mut row = vec![0; usize::try_from(self.row_bytes()).unwrap()];
+        let mut out_rows = buf.chunks_mut(len);
+        for current in 0..y + height {
+            if !self.read_scanline(&mut row)? {
+                return Err(ImageError::Parameter(ParameterError::from_kind(
+                    ParameterErrorKind::NoMoreData,
+                )));
+            }
+            if current >= y {
+                out_rows.next().unwrap().copy_from_slice(&row[start..][..len]);
+            }
+        }
+        Ok(())
+    }
+
     /// Returns all the bytes in the image.
     ///
     /// This function takes a slice of bytes and writes the pixel data of the image into it.
@@ -654,8 +969,12 @@ pub trait ImageDecoder<'a>: Sized {
 }
 
 /// Specialized image decoding not be supported by all formats
+///
+/// Rectangular sections are decoded with `ImageDecoder::read_rect`, this trait adds progress
+/// reporting for decoders that can seek in the image data and read sections repeatedly.
 pub trait ImageDecoderExt<'a>: ImageDecoder<'a> + Sized {
     /// Decode a rectangular section of the image; see [`read_rect_with_progress()`](#fn.read_rect_with_progress).
+    #[deprecated(note = "use `ImageDecoder::read_rect` instead")]
     fn read_rect(
         &mut self,
         x: u32,
@@ -664,7 +983,7 @@ pub trait ImageDecoderExt<'a>: ImageDecoder<'a> + Sized {
         height: u32,
         buf: &mut [u8],
     ) -> ImageResult<()> {
-        self.read_rect_with_progress(x, y, width, height, buf, |_|{})
+        ImageDecoder::read_rect(self, x, y, width, height, buf)
     }
 
     /// Decode a rectangular section of the image, periodically reporting progress.
@@ -975,6 +1294,30 @@ pub trait GenericImage: GenericImageView {
 ///   - [`GenericImage::sub_image`] to create a mutable view,
 ///   - [`GenericImageView::view`] to create an immutable view,
 ///   - [`SubImage::new`] to instantiate the struct directly.
+///
+/// Views borrow the image instead of copying it, and pixels written through a mutable view are
+/// written to the underlying image. Coordinates are relative to the top left corner of the view,
+/// and accessing a pixel outside of the view panics even if it is inside the underlying image.
+/// Use [`SubImage::to_image`] for an owned copy of the pixels.
+///
+/// # Examples
+///
+/// ```
+/// use image::{GenericImage, GenericImageView, GrayImage, Luma};
+///
+/// let mut image = GrayImage::new(64, 64);
+/// // Process the image in tiles, without copying them.
+/// for ty in 0..4 {
+///     for tx in 0..4 {
+///         let mut tile = image.sub_image(tx * 16, ty * 16, 16, 16);
+///         tile.put_pixel(0, 0, Luma([255]));
+///     }
+/// }
+/// assert_eq!(image.get_pixel(48, 16), &Luma([255]));
+///
+/// let copy = image.view(16, 16, 16, 16).to_image();
+/// assert_eq!(copy.dimensions(), (16, 16));
+/// ```
 pub struct SubImage<I> {
     image: I,
     xoffset: u32,
@@ -1010,6 +1353,32 @@ impl<I> SubImage<I> {
         self.ystride = height;
     }
 
+    /// Panic if `(x, y)` is outside of the view.
+    fn check_pixel(&self, x: u32, y: u32) {
+        if x >= self.xstride || y >= self.ystride {
+            panic!(
+                "Image index {:?} out of bounds {:?}",
+                (x, y),
+                (self.xstride, self.ystride)
+            );
+        }
+    }
+
+    /// Panic if a rectangle is not inside of the view, and return its position in the
+    /// underlying image.
+    fn check_rect(&self, x: u32, y: u32, width: u32, height: u32) -> (u32, u32) {
+        let inside = x.checked_add(width).map_or(false, |x1| x1 <= self.xstride)
+            && y.checked_add(height).map_or(false, |y1| y1 <= self.ystride);
+        if !inside {
+            panic!(
+                "Subimage {:?} out of bounds {:?}",
+                (x, y, width, height),
+                (self.xstride, self.ystride)
+            );
+        }
+        (x + self.xoffset, y + self.yoffset)
+    }
+
     /// Convert this subimage to an ImageBuffer
     pub fn to_image(&self) -> ImageBuffer<DerefPixel<I>, Vec<DerefSubpixel<I>>>
     where
@@ -1047,13 +1416,21 @@ where
         (self.xoffset, self.yoffset, self.xstride, self.ystride)
     }
 
+    fn in_bounds(&self, x: u32, y: u32) -> bool {
+        x < self.xstride && y < self.ystride
+    }
+
     fn get_pixel(&self, x: u32, y: u32) -> Self::Pixel {
+        self.check_pixel(x, y);
         self.image.get_pixel(x + self.xoffset, y + self.yoffset)
     }
 
+    unsafe fn unsafe_get_pixel(&self, x: u32, y: u32) -> Self::Pixel {
+        self.image.unsafe_get_pixel(x + self.xoffset, y + self.yoffset)
+    }
+
     fn view(&self, x: u32, y: u32, width: u32, height: u32) -> SubImage<&Self::InnerImageView> {
-        let x = self.xoffset + x;
-        let y = self.yoffset + y;
+        let (x, y) = self.check_rect(x, y, width, height);
         SubImage::new(self.inner(), x, y, width, height)
     }
 
@@ -1071,16 +1448,24 @@ where
     type InnerImage = I::Target;
 
     fn get_pixel_mut(&mut self, x: u32, y: u32) -> &mut Self::Pixel {
+        self.check_pixel(x, y);
         self.image.get_pixel_mut(x + self.xoffset, y + self.yoffset)
     }
 
     fn put_pixel(&mut self, x: u32, y: u32, pixel: Self::Pixel) {
+        self.check_pixel(x, y);
         self.image
             .put_pixel(x + self.xoffset, y + self.yoffset, pixel)
     }
 
+    unsafe fn unsafe_put_pixel(&mut self, x: u32, y: u32, pixel: Self::Pixel) {
+        self.image
+            .unsafe_put_pixel(x + self.xoffset, y + self.yoffset, pixel)
+    }
+
     /// DEPRECATED: This method will be removed. Blend the pixel directly instead.
     fn blend_pixel(&mut self, x: u32, y: u32, pixel: Self::Pixel) {
+        self.check_pixel(x, y);
         self.image
             .blend_pixel(x + self.xoffset, y + self.yoffset, pixel)
     }
@@ -1092,8 +1477,7 @@ where
         width: u32,
         height: u32,
     ) -> SubImage<&mut Self::InnerImage> {
-        let x = self.xoffset + x;
-        let y = self.yoffset + y;
+        let (x, y) = self.check_rect(x, y, width, height);
         SubImage::new(self.inner_mut(), x, y, width, height)
     }
 
@@ -1179,6 +1563,41 @@ mod tests {
         assert_eq!(*source.get_pixel(1, 1), view2.get_pixel(0, 0));
     }
 
+    #[test]
+    fn test_subimage_writes_through() {
+        let mut source = ImageBuffer::from_pixel(4, 4, Rgba([0u8, 0, 0, 255]));
+        {
+            let mut sub = source.sub_image(1, 2, 3, 2);
+            assert!(sub.in_bounds(2, 1));
+            assert!(!sub.in_bounds(3, 0));
+            assert!(!sub.in_bounds(0, 2));
+
+            let patch = ImageBuffer::from_pixel(2, 2, Rgba([9, 9, 9, 9]));
+            sub.copy_from(&patch, 1, 0).unwrap();
+            *sub.get_pixel_mut(0, 0) = Rgba([1, 2, 3, 4]);
+            assert!(sub.copy_from(&patch, 2, 0).is_err());
+        }
+
+        assert_eq!(*source.get_pixel(1, 2), Rgba([1, 2, 3, 4]));
+        assert_eq!(*source.get_pixel(2, 2), Rgba([9, 9, 9, 9]));
+        assert_eq!(*source.get_pixel(3, 3), Rgba([9, 9, 9, 9]));
+        assert_eq!(*source.get_pixel(1, 3), Rgba([0, 0, 0, 255]));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_subimage_pixel_outside_of_view() {
+        let source: GrayImage = ImageBuffer::new(4, 4);
+        source.view(0, 0, 2, 2).get_pixel(2, 0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_nested_view_outside_of_view() {
+        let mut source: GrayImage = ImageBuffer::new(4, 4);
+        source.sub_image(1, 1, 2, 2).sub_image(1, 1, 2, 1);
+    }
+
     #[test]
     fn test_load_rect() {
         struct MockDecoder {scanline_number: u64, scanline_bytes: u64}
@@ -1282,6 +1701,16 @@ mod tests {
     }
 
 
+    #[test]
+    fn test_image_format_from_mime_type() {
+        assert_eq!(ImageFormat::from_mime_type("image/jpeg"), Some(ImageFormat::Jpeg));
+        assert_eq!(ImageFormat::from_mime_type("Image/PNG"), Some(ImageFormat::Png));
+        assert_eq!(ImageFormat::from_mime_type(" image/webp ; q=1"), Some(ImageFormat::WebP));
+        assert_eq!(ImageFormat::from_mime_type("image/x-portable-graymap"), Some(ImageFormat::Pnm));
+        assert_eq!(ImageFormat::from_mime_type("image/svg+xml"), None);
+        assert_eq!(ImageFormat::from_mime_type(""), None);
+    }
+
     #[test]
     fn test_image_format_from_path() {
         fn from_path(s: &str) -> ImageResult<ImageFormat> {
@@ -1306,6 +1735,7 @@ mod tests {
         assert_eq!(from_path("./a.Ppm").unwrap(), ImageFormat::Pnm);
         assert_eq!(from_path("./a.pgm").unwrap(), ImageFormat::Pnm);
         assert_eq!(from_path("./a.AViF").unwrap(), ImageFormat::Avif);
+        assert_eq!(from_path("./a.jxl").unwrap(), ImageFormat::JpegXl);
         assert!(from_path("./a.txt").is_err());
         assert!(from_path("./a").is_err());
     }
@@ -1428,4 +1858,66 @@ mod tests {
         let v: ImageResult<Vec<u8>> = super::decoder_to_vec(D);
         assert!(v.is_err());
     }
+
+    #[test]
+    fn read_scanline_unsupported() {
+        struct D;
+        impl<'a> ImageDecoder<'a> for D {
+            type Reader = io::Cursor<Vec<u8>>;
+            fn color_type(&self) -> ColorType { ColorType::Rgb8 }
+            fn dimensions(&self) -> (u32, u32) { (2, 2) }
+            fn into_reader(self) -> ImageResult<Self::Reader> { unreachable!() }
+        }
+
+        assert_eq!(D.row_bytes(), 6);
+        let mut row = [0u8; 6];
+        match D.read_scanline(&mut row) {
+            Err(crate::ImageError::Unsupported(_)) => {}
+            other => panic!("expected an unsupported error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn decoded_rows() {
+        let mut rows = super::DecodedRows::new((0..6).collect());
+        let mut row = [0u8; 3];
+        assert!(rows.read_into(&mut row));
+        assert_eq!(row, [0, 1, 2]);
+        assert!(rows.read_into(&mut row));
+        assert_eq!(row, [3, 4, 5]);
+        assert!(!rows.read_into(&mut row));
+        assert_eq!(row, [3, 4, 5]);
+
+        // Rows of zero-width images and incomplete rows are never returned.
+        assert!(!super::DecodedRows::new(Vec::new()).read_into(&mut []));
+        assert!(!super::DecodedRows::new(vec![0; 2]).read_into(&mut row));
+    }
+
+    #[test]
+    fn read_rect_from_rows() {
+        struct D(u8);
+        impl<'a> ImageDecoder<'a> for D {
+            type Reader = io::Cursor<Vec<u8>>;
+            fn color_type(&self) -> ColorType { ColorType::La8 }
+            fn dimensions(&self) -> (u32, u32) { (3, 4) }
+            fn into_reader(self) -> ImageResult<Self::Reader> { unreachable!() }
+            fn read_scanline(&mut self, buf: &mut [u8]) -> ImageResult<bool> {
+                if self.0 == 4 {
+                    return Ok(false);
+                }
+                for (i, b) in buf.iter_mut().enumerate() {
+                    *b = 10 * self.0 + i as u8;
+                }
+                self.0 += 1;
+                Ok(true)
+            }
+        }
+
+        let mut output = [0u8; 9];
+        D(0).read_rect(1, 1, 2, 2, &mut output).unwrap();
+        assert_eq!(output, [12, 13, 14, 15, 22, 23, 24, 25, 0]);
+
+        assert!(D(0).read_rect(2, 1, 2, 2, &mut output).is_err());
+        assert!(D(2).read_rect(0, 1, 1, 3, &mut output).is_err());
+    }
 }
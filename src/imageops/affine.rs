@@ -1,9 +1,13 @@
 //! Functions for performing affine transformations.
 
+use num_traits::NumCast;
+
 use crate::ImageBuffer;
 use crate::image::{GenericImage, GenericImageView};
 use crate::error::{ImageError, ParameterError, ParameterErrorKind};
-use crate::traits::Pixel;
+use crate::imageops::sample::{filter_of_type, Filter, FilterType, FloatNearest};
+use crate::traits::{Pixel, Primitive};
+use crate::utils::clamp;
 
 /// Rotate an image 90 degrees clockwise.
 pub fn rotate90<I: GenericImageView>(
@@ -116,6 +120,113 @@ pub fn rotate270_in<I, Container>(
     Ok(())
 }
 
+/// Rotate an image clockwise by an arbitrary angle, given in radians.
+///
+/// The result is just large enough to hold the whole rotated image, the areas it does not cover
+/// are filled with `background`. Pixels are interpolated with `filter`: `Nearest` takes the
+/// closest pixel, `Triangle` interpolates bilinearly and `CatmullRom` bicubically. Outside of the
+/// image the interpolation sees `background`, so that the edges blend into it smoothly.
+pub fn rotate<I, P, S>(
+    image: &I,
+    radians: f32,
+    filter: FilterType,
+    background: P,
+) -> ImageBuffer<P, Vec<S>>
+where
+    I: GenericImageView<Pixel = P>,
+    P: Pixel<Subpixel = S> + 'static,
+    S: Primitive + 'static,
+{
+    let (width, height) = image.dimensions();
+    let (w, h) = (width as f32, height as f32);
+    let (sin, cos) = radians.sin_cos();
+
+    // The bounding box of the rotated image. Rounding errors are tolerated so that multiples of a
+    // right angle keep the exact size.
+    let new_width = (w * cos.abs() + h * sin.abs() - 1e-3).ceil().max(0.0) as u32;
+    let new_height = (w * sin.abs() + h * cos.abs() - 1e-3).ceil().max(0.0) as u32;
+
+    let filter = filter_of_type(filter);
+    let max: f32 = NumCast::from(S::max_value()).unwrap();
+    let (cx, cy) = ((w - 1.0) / 2.0, (h - 1.0) / 2.0);
+    let (ncx, ncy) = ((new_width as f32 - 1.0) / 2.0, (new_height as f32 - 1.0) / 2.0);
+
+    ImageBuffer::from_fn(new_width, new_height, |x, y| {
+        // Rotate back around the centres to find the position in the source image.
+        let (px, py) = (x as f32 - ncx, y as f32 - ncy);
+        let sx = cx + cos * px + sin * py;
+        let sy = cy - sin * px + cos * py;
+        interpolate(image, sx, sy, &filter, background, max)
+    })
+}
+
+// Sample an image at a fractional position, with the centres of pixels at whole numbers.
+// Positions outside of the image take the ```background``` color.
+fn interpolate<I, P, S>(image: &I, x: f32, y: f32, filter: &Filter, background: P, max: f32) -> P
+where
+    I: GenericImageView<Pixel = P>,
+    P: Pixel<Subpixel = S> + 'static,
+    S: Primitive + 'static,
+{
+    let (width, height) = image.dimensions();
+    if x < -0.5 || y < -0.5 || x > width as f32 - 0.5 || y > height as f32 - 0.5 {
+        return background;
+    }
+
+    let get = |x: i64, y: i64| {
+        if x < 0 || y < 0 || x >= width as i64 || y >= height as i64 {
+            background
+        } else {
+            image.get_pixel(x as u32, y as u32)
+        }
+    };
+    let nearest = || get(x.round() as i64, y.round() as i64);
+    if filter.support <= 0.0 {
+        return nearest();
+    }
+
+    let weights = |u: f32| -> Vec<(i64, f32)> {
+        let first = (u - filter.support).ceil() as i64;
+        let last = (u + filter.support).floor() as i64;
+        (first..=last).map(|i| (i, (filter.kernel)(i as f32 - u))).collect()
+    };
+
+    let (columns, rows) = (weights(x), weights(y));
+
+    let mut t = (0.0, 0.0, 0.0, 0.0);
+    let mut sum = 0.0;
+    for &(j, wy) in &rows {
+        for &(i, wx) in &columns {
+            let w = wx * wy;
+            let (k1, k2, k3, k4) = get(i, j).channels4();
+            let vec: (f32, f32, f32, f32) = (
+                NumCast::from(k1).unwrap(),
+                NumCast::from(k2).unwrap(),
+                NumCast::from(k3).unwrap(),
+                NumCast::from(k4).unwrap(),
+            );
+
+            t.0 += vec.0 * w;
+            t.1 += vec.1 * w;
+            t.2 += vec.2 * w;
+            t.3 += vec.3 * w;
+            sum += w;
+        }
+    }
+
+    if sum.abs() < std::f32::EPSILON {
+        return nearest();
+    }
+
+    let (t1, t2, t3, t4) = (t.0 / sum, t.1 / sum, t.2 / sum, t.3 / sum);
+    Pixel::from_channels(
+        NumCast::from(FloatNearest(clamp(t1, 0.0, max))).unwrap(),
+        NumCast::from(FloatNearest(clamp(t2, 0.0, max))).unwrap(),
+        NumCast::from(FloatNearest(clamp(t3, 0.0, max))).unwrap(),
+        NumCast::from(FloatNearest(clamp(t4, 0.0, max))).unwrap(),
+    )
+}
+
 /// Flip an image horizontally
 pub fn flip_horizontal<I: GenericImageView>(
     image: &I,
@@ -254,10 +365,11 @@ pub fn flip_vertical_in_place<I: GenericImage>(image: &mut I) {
 #[cfg(test)]
 mod test {
     use super::{
-        flip_horizontal, flip_horizontal_in_place, flip_vertical, flip_vertical_in_place,
+        flip_horizontal, flip_horizontal_in_place, flip_vertical, flip_vertical_in_place, rotate,
         rotate180, rotate180_in_place, rotate270, rotate90,
     };
-    use crate::{GrayImage, ImageBuffer};
+    use crate::imageops::FilterType;
+    use crate::{GrayImage, ImageBuffer, Luma};
     use crate::image::GenericImage;
     use crate::traits::Pixel;
 
@@ -325,6 +437,34 @@ mod test {
         assert_pixels_eq!(&rotate270(&image), &expected);
     }
 
+    #[test]
+    fn test_rotate_right_angles() {
+        use std::f32::consts::PI;
+
+        let image: GrayImage = ImageBuffer::from_fn(5, 3, |x, y| Luma([(x * 40 + y * 7) as u8]));
+        let background = Luma([255]);
+
+        for &filter in &[FilterType::Nearest, FilterType::Triangle, FilterType::CatmullRom] {
+            assert_pixels_eq!(&rotate(&image, 0.0, filter, background), &image);
+            assert_pixels_eq!(&rotate(&image, PI / 2.0, filter, background), &rotate90(&image));
+            assert_pixels_eq!(&rotate(&image, PI, filter, background), &rotate180(&image));
+            assert_pixels_eq!(&rotate(&image, -PI / 2.0, filter, background), &rotate270(&image));
+        }
+    }
+
+    #[test]
+    fn test_rotate_fills_background() {
+        let image: GrayImage = ImageBuffer::from_pixel(10, 10, Luma([100]));
+        let rotated = rotate(&image, std::f32::consts::PI / 4.0, FilterType::Triangle, Luma([0]));
+
+        // The diagonal of the square becomes the width.
+        assert_eq!(rotated.dimensions(), (15, 15));
+        assert_eq!(rotated.get_pixel(0, 0), &Luma([0]));
+        assert_eq!(rotated.get_pixel(14, 14), &Luma([0]));
+        assert_eq!(rotated.get_pixel(7, 7), &Luma([100]));
+        assert_eq!(rotated.get_pixel(7, 1), &Luma([100]));
+    }
+
     #[test]
     fn test_rotate180_in_place() {
         let mut image: GrayImage =
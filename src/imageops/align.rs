@@ -0,0 +1,431 @@
+//! Registration of images with phase correlation.
+
+use std::f32::consts::PI;
+
+use crate::color::Luma;
+use crate::image::GenericImageView;
+use crate::imageops::fft::{self, Complex};
+use crate::imageops::plane::{self, Plane};
+use crate::traits::{Pixel, Primitive};
+use crate::ImageBuffer;
+
+/// Standard deviation of the low pass applied during phase correlation, in cycles per pixel.
+const PASSBAND: f64 = 0.07;
+
+/// A translation between two images, as estimated by [`estimate_translation`].
+///
+/// [`estimate_translation`]: fn.estimate_translation.html
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Translation {
+    /// The horizontal displacement in pixels.
+    pub dx: f32,
+    /// The vertical displacement in pixels.
+    pub dy: f32,
+    /// The height of the correlation peak, between `0.0` and `1.0`. Values close to zero
+    /// indicate that the images could not be matched reliably.
+    pub response: f32,
+}
+
+/// A rotation, uniform scaling and translation between two images, as estimated by
+/// [`estimate_similarity`].
+///
+/// [`estimate_similarity`]: fn.estimate_similarity.html
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Similarity {
+    /// The rotation about the image center in radians. As the y axis points down, positive
+    /// angles rotate clockwise on screen.
+    pub rotation: f32,
+    /// The scale factor about the image center.
+    pub scale: f32,
+    /// The horizontal displacement in pixels, applied after rotating and scaling.
+    pub dx: f32,
+    /// The vertical displacement in pixels, applied after rotating and scaling.
+    pub dy: f32,
+    /// The height of the correlation peak of the translation, see [`Translation::response`].
+    ///
+    /// [`Translation::response`]: struct.Translation.html#structfield.response
+    pub response: f32,
+}
+
+/// Estimate the translation of `moved` relative to `reference` with subpixel accuracy.
+///
+/// The result is the shift that has to be applied to `reference` to match `moved`, so
+/// `translate(moved, -dx, -dy)` aligns `moved` with `reference`. Both images are compared by
+/// their luminance. Phase correlation is robust against changes in brightness and contrast, but
+/// the images have to overlap substantially and shifts are only detected up to half the image
+/// size.
+///
+/// # Panics
+///
+/// Panics if the images do not have the same dimensions.
+pub fn estimate_translation<I, J, P, S>(reference: &I, moved: &J) -> Translation
+where
+    I: GenericImageView<Pixel = P>,
+    J: GenericImageView<Pixel = P>,
+    P: Pixel<Subpixel = S>,
+    S: Primitive,
+{
+    assert_eq!(
+        reference.dimensions(),
+        moved.dimensions(),
+        "the images to align must have the same dimensions"
+    );
+
+    let reference = plane::luma(&plane::split(reference));
+    let moved = plane::luma(&plane::split(moved));
+    translation(&reference, &moved)
+}
+
+/// Estimate the rotation, scaling and translation of `moved` relative to `reference`.
+///
+/// `moved` is modeled as `reference` rotated and scaled about the image center and then shifted.
+/// Rotation and scale are recovered by phase correlation of the magnitude spectra in log-polar
+/// coordinates, after Reddy and Chatterji, "An FFT-based technique for translation, rotation,
+/// and scale-invariant image registration", 1996. The translation is then estimated as in
+/// [`estimate_translation`]. Rotations of any angle and scale factors of up to about 2 can be
+/// detected.
+///
+/// # Panics
+///
+/// Panics if the images do not have the same dimensions.
+///
+/// [`estimate_translation`]: fn.estimate_translation.html
+pub fn estimate_similarity<I, J, P, S>(reference: &I, moved: &J) -> Similarity
+where
+    I: GenericImageView<Pixel = P>,
+    J: GenericImageView<Pixel = P>,
+    P: Pixel<Subpixel = S>,
+    S: Primitive,
+{
+    assert_eq!(
+        reference.dimensions(),
+        moved.dimensions(),
+        "the images to align must have the same dimensions"
+    );
+
+    let reference = plane::luma(&plane::split(reference));
+    let moved = plane::luma(&plane::split(moved));
+    let (width, height) = reference.dimensions();
+    let size = width.max(height).next_power_of_two() as usize;
+
+    let reference_polar = log_polar(&magnitude_spectrum(&reference, size));
+    let moved_polar = log_polar(&magnitude_spectrum(&moved, size));
+    let shift = correlate(&reference_polar, &moved_polar);
+
+    // The magnitude spectrum is symmetric, so the angle axis only covers half a turn.
+    let rotation = shift.dy * PI / size as f32;
+    let scale = (-shift.dx * (size as f32 / 2.0).ln() / size as f32).exp();
+
+    let mut best: Option<Similarity> = None;
+    for &rotation in &[rotation, rotation + PI] {
+        let rotation = if rotation > PI {
+            rotation - 2.0 * PI
+        } else {
+            rotation
+        };
+        let corrected = warp(&moved, -rotation, 1.0 / scale);
+        let t = translation(&reference, &corrected);
+
+        // The translation was measured in the frame of the corrected image.
+        let (sin, cos) = rotation.sin_cos();
+        let candidate = Similarity {
+            rotation,
+            scale,
+            dx: scale * (cos * t.dx - sin * t.dy),
+            dy: scale * (sin * t.dx + cos * t.dy),
+            response: t.response,
+        };
+        if best.map_or(true, |best| candidate.response > best.response) {
+            best = Some(candidate);
+        }
+    }
+
+    best.unwrap()
+}
+
+/// Shift an image by a possibly fractional offset, using bilinear interpolation.
+///
+/// Pixel `(x, y)` of the result is taken from position `(x - dx, y - dy)` of `image`. Areas that
+/// are not covered by the shifted image are filled with zeros.
+pub fn translate<I, P, S>(image: &I, dx: f32, dy: f32) -> ImageBuffer<P, Vec<S>>
+where
+    I: GenericImageView<Pixel = P>,
+    P: Pixel<Subpixel = S> + 'static,
+    S: Primitive + 'static,
+{
+    let planes: Vec<Plane> = plane::split(image)
+        .iter()
+        .map(|channel| {
+            ImageBuffer::from_fn(channel.width(), channel.height(), |x, y| {
+                Luma([sample_or_zero(channel, x as f32 - dx, y as f32 - dy)])
+            })
+        })
+        .collect();
+    plane::merge(&planes)
+}
+
+/// Phase correlation of two planes of equal size.
+fn translation(reference: &Plane, moved: &Plane) -> Translation {
+    let (width, height) = reference.dimensions();
+    let (w, h) = (
+        width.next_power_of_two() as usize,
+        height.next_power_of_two() as usize,
+    );
+    let reference = windowed(reference, w, h);
+    let moved = windowed(moved, w, h);
+    correlate(&(reference, w, h), &(moved, w, h))
+}
+
+/// Phase correlation of two equally sized arrays given as `(samples, width, height)`.
+fn correlate(
+    reference: &(Vec<f64>, usize, usize),
+    moved: &(Vec<f64>, usize, usize),
+) -> Translation {
+    let (w, h) = (reference.1, reference.2);
+    let mut a: Vec<Complex> = reference.0.iter().map(|&v| Complex::new(v, 0.0)).collect();
+    let mut b: Vec<Complex> = moved.0.iter().map(|&v| Complex::new(v, 0.0)).collect();
+    fft::fft2d(&mut a, w, h, false);
+    fft::fft2d(&mut b, w, h, false);
+
+    // The normalized cross power spectrum, whose inverse is a peak at the displacement. It is
+    // weighted with a gaussian low pass, which suppresses the noise dominated high frequencies
+    // and gives the peak a gaussian shape that can be located with subpixel accuracy.
+    let frequency = |i: usize, n: usize| {
+        if i > n / 2 {
+            i as f64 / n as f64 - 1.0
+        } else {
+            i as f64 / n as f64
+        }
+    };
+    let mut total_weight = 0.0;
+    for (index, (b, &a)) in b.iter_mut().zip(&a).enumerate() {
+        let (fx, fy) = (frequency(index % w, w), frequency(index / w, h));
+        let weight = (-(fx * fx + fy * fy) / (2.0 * PASSBAND * PASSBAND)).exp();
+        total_weight += weight;
+
+        let cross = *b * a.conj();
+        let norm = cross.norm();
+        *b = if norm > 1e-12 {
+            cross.scale(weight / norm)
+        } else {
+            Complex::default()
+        };
+    }
+    fft::fft2d(&mut b, w, h, true);
+    // Scale the surface such that a perfect match has a peak of one.
+    let normalization = (w * h) as f64 / total_weight;
+    b.iter_mut().for_each(|c| *c = c.scale(normalization));
+
+    let surface: Vec<f64> = b.iter().map(|c| c.re).collect();
+    let (peak, &response) =
+        surface
+            .iter()
+            .enumerate()
+            .fold((0, &std::f64::MIN), |best, current| {
+                if current.1 > best.1 {
+                    current
+                } else {
+                    best
+                }
+            });
+    let (px, py) = (peak % w, peak / w);
+
+    // Refine the peak with a gaussian through its neighbours, which is a parabola through their
+    // logarithms, wrapping around the edges.
+    let at = |x: usize, y: usize| surface[(y % h) * w + x % w];
+    let offset = |prev: f64, next: f64| {
+        let (prev, center, next) = if prev > 0.0 && next > 0.0 {
+            (prev.ln(), response.ln(), next.ln())
+        } else {
+            (prev, response, next)
+        };
+        let denominator = prev - 2.0 * center + next;
+        if denominator.abs() > 1e-12 {
+            (0.5 * (prev - next) / denominator).max(-0.5).min(0.5)
+        } else {
+            0.0
+        }
+    };
+    let fx = offset(at(px + w - 1, py), at(px + 1, py));
+    let fy = offset(at(px, py + h - 1), at(px, py + 1));
+
+    let wrap = |p: usize, n: usize| {
+        if p > n / 2 {
+            p as f64 - n as f64
+        } else {
+            p as f64
+        }
+    };
+    Translation {
+        dx: (wrap(px, w) + fx) as f32,
+        dy: (wrap(py, h) + fy) as f32,
+        response: response.max(0.0).min(1.0) as f32,
+    }
+}
+
+/// Multiply a plane with a Hann window, to suppress the discontinuities at its borders, and
+/// zero pad it to the given size.
+fn windowed(plane: &Plane, width: usize, height: usize) -> Vec<f64> {
+    let (pw, ph) = plane.dimensions();
+    let mean = plane.iter().map(|&v| f64::from(v)).sum::<f64>() / (pw as f64 * ph as f64);
+    let hann = |i: u32, n: u32| {
+        0.5 - 0.5 * (2.0 * std::f64::consts::PI * f64::from(i) / f64::from(n)).cos()
+    };
+
+    let mut out = vec![0.0; width * height];
+    for (x, y, value) in plane.enumerate_pixels() {
+        let weight = hann(x, pw) * hann(y, ph);
+        out[y as usize * width + x as usize] = (f64::from(value[0]) - mean) * weight;
+    }
+    out
+}
+
+/// The high pass filtered magnitude spectrum of a plane with the zero frequency at the center.
+fn magnitude_spectrum(plane: &Plane, size: usize) -> Plane {
+    let mut data: Vec<Complex> = windowed(plane, size, size)
+        .into_iter()
+        .map(|v| Complex::new(v, 0.0))
+        .collect();
+    fft::fft2d(&mut data, size, size, false);
+
+    let half = size / 2;
+    ImageBuffer::from_fn(size as u32, size as u32, |x, y| {
+        let (fx, fy) = ((x as usize + half) % size, (y as usize + half) % size);
+        // Emphasizes the high frequencies, which carry most of the rotation information.
+        let cx = (std::f64::consts::PI * (x as f64 - half as f64) / size as f64).cos();
+        let cy = (std::f64::consts::PI * (y as f64 - half as f64) / size as f64).cos();
+        let highpass = (1.0 - cx * cy) * (2.0 - cx * cy);
+        Luma([(data[fy * size + fx].norm() * highpass) as f32])
+    })
+}
+
+/// Resample a centered spectrum to log-polar coordinates.
+///
+/// The x axis is the logarithm of the radius, from one sample to half the size, and the y axis
+/// covers the angles from `0` to `PI`.
+fn log_polar(spectrum: &Plane) -> (Vec<f64>, usize, usize) {
+    let size = spectrum.width() as usize;
+    let center = (size / 2) as f32;
+    let log_base = center.ln() / size as f32;
+
+    let mut out = vec![0.0; size * size];
+    for a in 0..size {
+        let (sin, cos) = (a as f32 * PI / size as f32).sin_cos();
+        for r in 0..size {
+            let radius = (r as f32 * log_base).exp();
+            let value =
+                plane::sample_bilinear(spectrum, center + radius * cos, center + radius * sin);
+            out[a * size + r] = f64::from(value);
+        }
+    }
+    (out, size, size)
+}
+
+/// Rotate and scale a plane about its center, filling uncovered areas with zeros.
+fn warp(plane: &Plane, rotation: f32, scale: f32) -> Plane {
+    let (width, height) = plane.dimensions();
+    let (cx, cy) = (width as f32 / 2.0, height as f32 / 2.0);
+    let (sin, cos) = rotation.sin_cos();
+
+    ImageBuffer::from_fn(width, height, |x, y| {
+        let (px, py) = ((x as f32 - cx) / scale, (y as f32 - cy) / scale);
+        let sx = cx + cos * px + sin * py;
+        let sy = cy - sin * px + cos * py;
+        Luma([sample_or_zero(plane, sx, sy)])
+    })
+}
+
+/// Sample a plane with bilinear interpolation, treating the area outside of it as zero.
+fn sample_or_zero(plane: &Plane, x: f32, y: f32) -> f32 {
+    let (width, height) = plane.dimensions();
+    let (x0, y0) = (x.floor(), y.floor());
+    let (fx, fy) = (x - x0, y - y0);
+    let (x0, y0) = (x0 as i64, y0 as i64);
+
+    let get = |x: i64, y: i64| {
+        if x < 0 || y < 0 || x >= i64::from(width) || y >= i64::from(height) {
+            0.0
+        } else {
+            plane.get_pixel(x as u32, y as u32)[0]
+        }
+    };
+    let top = get(x0, y0) * (1.0 - fx) + get(x0 + 1, y0) * fx;
+    let bottom = get(x0, y0 + 1) * (1.0 - fx) + get(x0 + 1, y0 + 1) * fx;
+    top * (1.0 - fy) + bottom * fy
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{estimate_similarity, estimate_translation, translate, warp};
+    use crate::imageops::plane::{self, Plane};
+    use crate::{GrayImage, ImageBuffer, Luma};
+
+    /// A smooth, aperiodic test pattern.
+    fn scene(x: f32, y: f32) -> f32 {
+        let blob =
+            |cx: f32, cy: f32, r: f32| (-((x - cx).powi(2) + (y - cy).powi(2)) / (r * r)).exp();
+        40.0 + 150.0 * blob(30.0, 40.0, 9.0)
+            + 90.0 * blob(80.0, 30.0, 14.0)
+            + 120.0 * blob(55.0, 85.0, 6.0)
+            + 20.0 * (x / 7.0).sin() * (y / 11.0).cos()
+    }
+
+    fn render(dx: f32, dy: f32) -> GrayImage {
+        ImageBuffer::from_fn(128, 128, |x, y| {
+            Luma([scene(x as f32 - dx, y as f32 - dy).round() as u8])
+        })
+    }
+
+    #[test]
+    fn test_estimate_translation() {
+        let reference = render(0.0, 0.0);
+
+        let t = estimate_translation(&reference, &render(7.0, -4.0));
+        assert!(
+            (t.dx - 7.0).abs() < 0.2 && (t.dy + 4.0).abs() < 0.2,
+            "{:?}",
+            t
+        );
+        assert!(t.response > 0.3);
+
+        let t = estimate_translation(&reference, &render(-3.5, 2.25));
+        assert!(
+            (t.dx + 3.5).abs() < 0.3 && (t.dy - 2.25).abs() < 0.3,
+            "{:?}",
+            t
+        );
+    }
+
+    #[test]
+    fn test_translate() {
+        let img = GrayImage::from_fn(6, 4, |x, y| Luma([(x * 10 + y) as u8 + 1]));
+        let shifted = translate(&img, 2.0, 1.0);
+        assert_eq!(shifted.get_pixel(0, 0)[0], 0);
+        assert_eq!(shifted.get_pixel(3, 2), img.get_pixel(1, 1));
+
+        let half = translate(&img, 0.5, 0.0);
+        assert_eq!(half.get_pixel(2, 0)[0], 16);
+    }
+
+    #[test]
+    fn test_estimate_similarity() {
+        let reference: Plane = plane::split(&render(0.0, 0.0)).remove(0);
+        let rotation = 12.0f32.to_radians();
+        let moved: GrayImage = plane::merge(&[warp(&reference, rotation, 1.15)]);
+        let moved = translate(&moved, 3.0, -2.0);
+        let reference: GrayImage = plane::merge(&[reference]);
+
+        let s = estimate_similarity(&reference, &moved);
+        assert!(
+            (s.rotation - rotation).abs() < 1.0f32.to_radians(),
+            "{:?}",
+            s
+        );
+        assert!((s.scale - 1.15).abs() < 0.03, "{:?}", s);
+        assert!(
+            (s.dx - 3.0).abs() < 1.0 && (s.dy + 2.0).abs() < 1.0,
+            "{:?}",
+            s
+        );
+    }
+}
@@ -0,0 +1,356 @@
+//! Packing of images into sprite sheets and slicing them apart again.
+
+use std::cmp::Reverse;
+
+use crate::error::{ImageError, ImageResult, ParameterError, ParameterErrorKind};
+use crate::image::{GenericImage, GenericImageView};
+use crate::math::Rect;
+use crate::traits::{Pixel, Primitive};
+use crate::ImageBuffer;
+
+/// The algorithm used to arrange sprites on a sheet.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PackingMethod {
+    /// Place the sprites in rows, sorted by height. Fast and predictable, works best for sprites
+    /// of similar height.
+    Shelf,
+    /// Place every sprite into the free rectangle that fits it most tightly, as described by
+    /// Jukka Jylänki, "A Thousand Ways to Pack the Bin", 2010. Produces denser sheets for sprites
+    /// of varying size.
+    MaxRects,
+}
+
+/// A sprite sheet created by [`pack_sprites`].
+///
+/// [`pack_sprites`]: fn.pack_sprites.html
+#[derive(Clone, Debug)]
+pub struct SpriteSheet<P: Pixel> {
+    /// The image containing all sprites.
+    pub image: ImageBuffer<P, Vec<P::Subpixel>>,
+    /// The position of every sprite on the sheet, in the order in which they were given.
+    pub placements: Vec<Rect>,
+}
+
+/// Pack a set of images into a single sprite sheet no wider than `max_width`.
+///
+/// The sheet is made as small as the packing allows, with `padding` pixels kept free between
+/// sprites to avoid bleeding when the sheet is sampled with filtering. Uncovered areas of the
+/// sheet are filled with zeros, which is transparent for pixels with an alpha channel. The
+/// placements can be used with [`slice_sprites`] to recover the images.
+///
+/// An error is returned if a sprite is wider than `max_width`.
+///
+/// [`slice_sprites`]: fn.slice_sprites.html
+pub fn pack_sprites<I, P, S>(
+    sprites: &[I],
+    max_width: u32,
+    padding: u32,
+    method: PackingMethod,
+) -> ImageResult<SpriteSheet<P>>
+where
+    I: GenericImageView<Pixel = P>,
+    P: Pixel<Subpixel = S> + 'static,
+    S: Primitive + 'static,
+{
+    if let Some(sprite) = sprites.iter().find(|sprite| sprite.width() > max_width) {
+        return Err(ImageError::Parameter(ParameterError::from_kind(
+            ParameterErrorKind::Generic(format!(
+                "sprite of width {} does not fit into a sheet of width {}",
+                sprite.width(),
+                max_width
+            )),
+        )));
+    }
+
+    // Every sprite occupies its own size plus the padding to its right and bottom.
+    let sizes: Vec<(u32, u32)> = sprites
+        .iter()
+        .map(|sprite| (sprite.width() + padding, sprite.height() + padding))
+        .collect();
+    let positions = match method {
+        PackingMethod::Shelf => pack_shelf(&sizes, max_width + padding),
+        PackingMethod::MaxRects => pack_max_rects(&sizes, max_width + padding),
+    };
+
+    let placements: Vec<Rect> = sprites
+        .iter()
+        .zip(positions)
+        .map(|(sprite, (x, y))| Rect {
+            x,
+            y,
+            width: sprite.width(),
+            height: sprite.height(),
+        })
+        .collect();
+    let width = placements.iter().map(|r| r.x + r.width).max().unwrap_or(0);
+    let height = placements.iter().map(|r| r.y + r.height).max().unwrap_or(0);
+
+    let mut image = ImageBuffer::new(width, height);
+    for (sprite, rect) in sprites.iter().zip(&placements) {
+        image.copy_from(sprite, rect.x, rect.y)?;
+    }
+
+    Ok(SpriteSheet { image, placements })
+}
+
+/// Cut a sprite sheet into tiles of equal size, in row-major order.
+///
+/// Tiles are `spacing` pixels apart from each other, tiles that would extend past the edge of
+/// the sheet are skipped.
+pub fn slice_grid<I, P, S>(
+    sheet: &I,
+    tile_width: u32,
+    tile_height: u32,
+    spacing: u32,
+) -> Vec<ImageBuffer<P, Vec<S>>>
+where
+    I: GenericImageView<Pixel = P>,
+    P: Pixel<Subpixel = S> + 'static,
+    S: Primitive + 'static,
+{
+    let (width, height) = sheet.dimensions();
+    let mut tiles = Vec::new();
+    if tile_width == 0 || tile_height == 0 {
+        return tiles;
+    }
+
+    let mut y = 0;
+    while y + tile_height <= height {
+        let mut x = 0;
+        while x + tile_width <= width {
+            tiles.push(copy_rect(sheet, x, y, tile_width, tile_height));
+            x += tile_width + spacing;
+        }
+        y += tile_height + spacing;
+    }
+    tiles
+}
+
+/// Cut the given rectangles out of a sprite sheet, for example the placements of a
+/// [`SpriteSheet`].
+///
+/// An error is returned if a rectangle is not fully contained in the sheet.
+///
+/// [`SpriteSheet`]: struct.SpriteSheet.html
+pub fn slice_sprites<I, P, S>(sheet: &I, rects: &[Rect]) -> ImageResult<Vec<ImageBuffer<P, Vec<S>>>>
+where
+    I: GenericImageView<Pixel = P>,
+    P: Pixel<Subpixel = S> + 'static,
+    S: Primitive + 'static,
+{
+    let (width, height) = sheet.dimensions();
+    rects
+        .iter()
+        .map(|rect| {
+            let fits = u64::from(rect.x) + u64::from(rect.width) <= u64::from(width)
+                && u64::from(rect.y) + u64::from(rect.height) <= u64::from(height);
+            if !fits {
+                return Err(ImageError::Parameter(ParameterError::from_kind(
+                    ParameterErrorKind::DimensionMismatch,
+                )));
+            }
+            Ok(copy_rect(sheet, rect.x, rect.y, rect.width, rect.height))
+        })
+        .collect()
+}
+
+fn copy_rect<I, P, S>(sheet: &I, x: u32, y: u32, width: u32, height: u32) -> ImageBuffer<P, Vec<S>>
+where
+    I: GenericImageView<Pixel = P>,
+    P: Pixel<Subpixel = S> + 'static,
+    S: Primitive + 'static,
+{
+    ImageBuffer::from_fn(width, height, |dx, dy| sheet.get_pixel(x + dx, y + dy))
+}
+
+/// Place rectangles in rows, tallest first.
+fn pack_shelf(sizes: &[(u32, u32)], bin_width: u32) -> Vec<(u32, u32)> {
+    let mut order: Vec<usize> = (0..sizes.len()).collect();
+    order.sort_by_key(|&i| Reverse((sizes[i].1, sizes[i].0)));
+
+    let mut positions = vec![(0, 0); sizes.len()];
+    let (mut x, mut y, mut shelf_height) = (0, 0, 0);
+    for index in order {
+        let (width, height) = sizes[index];
+        if x + width > bin_width && x > 0 {
+            x = 0;
+            y += shelf_height;
+            shelf_height = 0;
+        }
+        positions[index] = (x, y);
+        x += width;
+        shelf_height = shelf_height.max(height);
+    }
+    positions
+}
+
+/// Place rectangles with the maximal rectangles algorithm and the best short side fit rule.
+fn pack_max_rects(sizes: &[(u32, u32)], bin_width: u32) -> Vec<(u32, u32)> {
+    let mut order: Vec<usize> = (0..sizes.len()).collect();
+    order.sort_by_key(|&i| Reverse(u64::from(sizes[i].0) * u64::from(sizes[i].1)));
+
+    // The bin is unbounded in height, so a single free rectangle of the total height suffices.
+    let bin_height: u32 = sizes.iter().map(|&(_, h)| h).sum();
+    let mut free = vec![Rect {
+        x: 0,
+        y: 0,
+        width: bin_width,
+        height: bin_height,
+    }];
+
+    let mut positions = vec![(0, 0); sizes.len()];
+    for index in order {
+        let (width, height) = sizes[index];
+        if width == 0 || height == 0 {
+            continue;
+        }
+
+        // Prefer low positions to keep the sheet short, then the tightest fit.
+        let best = free
+            .iter()
+            .filter(|r| r.width >= width && r.height >= height)
+            .min_by_key(|r| {
+                let short = (r.width - width).min(r.height - height);
+                let long = (r.width - width).max(r.height - height);
+                (r.y + height, short, long)
+            })
+            .cloned()
+            .expect("the free area always contains the full bin width");
+
+        let placed = Rect {
+            x: best.x,
+            y: best.y,
+            width,
+            height,
+        };
+        positions[index] = (placed.x, placed.y);
+
+        let mut next = Vec::with_capacity(free.len() + 4);
+        for r in free {
+            if intersects(&r, &placed) {
+                next.extend(split(&r, &placed));
+            } else {
+                next.push(r);
+            }
+        }
+        // Remove free rectangles contained in others.
+        let mut pruned: Vec<Rect> = Vec::with_capacity(next.len());
+        for (i, r) in next.iter().enumerate() {
+            let redundant = next
+                .iter()
+                .enumerate()
+                .any(|(j, other)| i != j && contains(other, r) && (other != r || j < i));
+            if !redundant {
+                pruned.push(*r);
+            }
+        }
+        free = pruned;
+    }
+    positions
+}
+
+fn intersects(a: &Rect, b: &Rect) -> bool {
+    a.x < b.x + b.width && b.x < a.x + a.width && a.y < b.y + b.height && b.y < a.y + a.height
+}
+
+fn contains(outer: &Rect, inner: &Rect) -> bool {
+    inner.x >= outer.x
+        && inner.y >= outer.y
+        && inner.x + inner.width <= outer.x + outer.width
+        && inner.y + inner.height <= outer.y + outer.height
+}
+
+/// The maximal free rectangles left of `free` after `used` is taken out of it.
+fn split(free: &Rect, used: &Rect) -> Vec<Rect> {
+    let mut parts = Vec::with_capacity(4);
+    if used.x > free.x {
+        parts.push(Rect {
+            width: used.x - free.x,
+            ..*free
+        });
+    }
+    if used.x + used.width < free.x + free.width {
+        parts.push(Rect {
+            x: used.x + used.width,
+            width: free.x + free.width - used.x - used.width,
+            ..*free
+        });
+    }
+    if used.y > free.y {
+        parts.push(Rect {
+            height: used.y - free.y,
+            ..*free
+        });
+    }
+    if used.y + used.height < free.y + free.height {
+        parts.push(Rect {
+            y: used.y + used.height,
+            height: free.y + free.height - used.y - used.height,
+            ..*free
+        });
+    }
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{intersects, pack_sprites, slice_grid, slice_sprites, PackingMethod};
+    use crate::math::Rect;
+    use crate::{GrayImage, Luma};
+
+    fn sprites() -> Vec<GrayImage> {
+        let sizes = [(10, 4), (3, 12), (7, 7), (16, 2), (5, 5), (2, 9), (8, 3)];
+        sizes
+            .iter()
+            .enumerate()
+            .map(|(i, &(w, h))| GrayImage::from_pixel(w, h, Luma([i as u8 + 1])))
+            .collect()
+    }
+
+    #[test]
+    fn test_pack_and_slice_roundtrip() {
+        let sprites = sprites();
+        for &method in &[PackingMethod::Shelf, PackingMethod::MaxRects] {
+            let sheet = pack_sprites(&sprites, 20, 1, method).unwrap();
+            assert!(sheet.image.width() <= 20);
+
+            for (i, a) in sheet.placements.iter().enumerate() {
+                for b in &sheet.placements[i + 1..] {
+                    // With padding, no two sprites even touch.
+                    let grown = Rect {
+                        width: a.width + 1,
+                        height: a.height + 1,
+                        ..*a
+                    };
+                    assert!(!intersects(&grown, b), "{:?} {:?} {:?}", method, a, b);
+                }
+            }
+
+            let sliced = slice_sprites(&sheet.image, &sheet.placements).unwrap();
+            assert_eq!(sliced, sprites);
+        }
+    }
+
+    #[test]
+    fn test_pack_errors() {
+        let sprites = sprites();
+        assert!(pack_sprites(&sprites, 12, 0, PackingMethod::Shelf).is_err());
+        let sheet = pack_sprites(&sprites[..1], 12, 0, PackingMethod::MaxRects).unwrap();
+        let outside = Rect {
+            x: 5,
+            y: 0,
+            width: 10,
+            height: 1,
+        };
+        assert!(slice_sprites(&sheet.image, &[outside]).is_err());
+    }
+
+    #[test]
+    fn test_slice_grid() {
+        let sheet = GrayImage::from_fn(11, 5, |x, y| Luma([(x / 4 + 10 * (y / 3)) as u8]));
+        let tiles = slice_grid(&sheet, 3, 2, 1);
+        assert_eq!(tiles.len(), 3 * 2);
+        assert_eq!(tiles[1].get_pixel(0, 0)[0], 1);
+        assert_eq!(tiles[5].get_pixel(2, 1)[0], 12);
+    }
+}
@@ -0,0 +1,171 @@
+//! Box blurs, and the fast approximation of a Gaussian blur built from them.
+
+use crate::image::GenericImageView;
+use crate::imageops::plane::{self, Plane};
+use crate::traits::{Pixel, Primitive};
+use crate::utils::clamp;
+use crate::ImageBuffer;
+
+/// Replace every pixel by the mean of the square of `2 * radius + 1` pixels around it.
+///
+/// The running time does not depend on the radius. Pixels outside of the image are replaced by
+/// the nearest edge pixel.
+pub fn box_blur<I, P, S>(image: &I, radius: u32) -> ImageBuffer<P, Vec<S>>
+where
+    I: GenericImageView<Pixel = P>,
+    P: Pixel<Subpixel = S> + 'static,
+    S: Primitive + 'static,
+{
+    blur_planes(image, &[radius])
+}
+
+/// Approximate a Gaussian blur with standard deviation `sigma` by three successive box blurs.
+///
+/// This takes time proportional to the number of pixels, independent of `sigma`, and is much
+/// faster than [`blur`] for all but the smallest `sigma`. The box sizes are chosen so that the
+/// combined filter has the requested standard deviation, following Peter Kovesi, "Fast
+/// Almost-Gaussian Filtering", 2010. The filter is piecewise quadratic instead of smooth: the
+/// result differs from a true Gaussian blur by about one percent of the value range at most,
+/// but the weights drop to zero at about three standard deviations and small sigmas can only be
+/// approximated by the discrete box sizes. Use [`blur`] where exact results matter.
+///
+/// [`blur`]: fn.blur.html
+pub fn fast_blur<I, P, S>(image: &I, sigma: f32) -> ImageBuffer<P, Vec<S>>
+where
+    I: GenericImageView<Pixel = P>,
+    P: Pixel<Subpixel = S> + 'static,
+    S: Primitive + 'static,
+{
+    let sigma = if sigma <= 0.0 { 1.0 } else { sigma };
+    blur_planes(image, &gaussian_box_radii(sigma, 3))
+}
+
+/// Blur all channels of an image with box blurs of the given radii, one after the other.
+fn blur_planes<I, P, S>(image: &I, radii: &[u32]) -> ImageBuffer<P, Vec<S>>
+where
+    I: GenericImageView<Pixel = P>,
+    P: Pixel<Subpixel = S> + 'static,
+    S: Primitive + 'static,
+{
+    let planes: Vec<Plane> = plane::split(image)
+        .into_iter()
+        .map(|mut channel| {
+            for &radius in radii {
+                channel = box_blur_plane(&channel, radius);
+            }
+            channel
+        })
+        .collect();
+    plane::merge(&planes)
+}
+
+/// The radii of `passes` box blurs that together have the standard deviation `sigma`.
+fn gaussian_box_radii(sigma: f32, passes: usize) -> Vec<u32> {
+    let n = passes as f32;
+    let ideal = (12.0 * sigma * sigma / n + 1.0).sqrt();
+    let mut lower = ideal.floor() as i32;
+    if lower % 2 == 0 {
+        lower -= 1;
+    }
+    let lower = lower.max(1) as f32;
+    let upper = lower + 2.0;
+
+    // The number of passes using the smaller size, so that the variances add up best.
+    let smaller = ((12.0 * sigma * sigma - n * lower * lower - 4.0 * n * lower - 3.0 * n)
+        / (-4.0 * lower - 4.0))
+        .round();
+
+    (0..passes)
+        .map(|i| {
+            let size = if (i as f32) < smaller { lower } else { upper };
+            (size as u32 - 1) / 2
+        })
+        .collect()
+}
+
+/// Box blur a plane horizontally and then vertically.
+fn box_blur_plane(plane: &Plane, radius: u32) -> Plane {
+    let (width, height) = plane.dimensions();
+    let (w, h) = (width as usize, height as usize);
+    let mut samples = plane.as_raw().clone();
+
+    let mut line = Vec::new();
+    let mut out = Vec::new();
+    for row in samples.chunks_mut(w.max(1)) {
+        line.clear();
+        line.extend_from_slice(row);
+        blur_line(&line, radius, &mut out);
+        row.copy_from_slice(&out);
+    }
+    for x in 0..w {
+        line.clear();
+        line.extend((0..h).map(|y| samples[y * w + x]));
+        blur_line(&line, radius, &mut out);
+        for (y, &value) in out.iter().enumerate() {
+            samples[y * w + x] = value;
+        }
+    }
+
+    ImageBuffer::from_raw(width, height, samples).unwrap()
+}
+
+/// Average a window of `2 * radius + 1` samples around every sample, with a running sum.
+fn blur_line(line: &[f32], radius: u32, out: &mut Vec<f32>) {
+    out.clear();
+    if line.is_empty() {
+        return;
+    }
+
+    let last = line.len() as i64 - 1;
+    let at = |i: i64| line[clamp(i, 0, last) as usize];
+    let radius = i64::from(radius);
+    let scale = 1.0 / (2 * radius + 1) as f32;
+
+    let mut sum: f32 = (-radius..=radius).map(at).sum();
+    for x in 0..line.len() as i64 {
+        out.push(sum * scale);
+        sum += at(x + radius + 1) - at(x - radius);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{box_blur, fast_blur, gaussian_box_radii};
+    use crate::imageops::blur;
+    use crate::{GrayImage, ImageBuffer, Luma};
+
+    #[test]
+    fn test_box_blur() {
+        let image: GrayImage = ImageBuffer::from_raw(5, 1, vec![0, 0, 90, 0, 0]).unwrap();
+        assert_eq!(box_blur(&image, 0), image);
+        assert_eq!(box_blur(&image, 1).into_raw(), vec![0, 30, 30, 30, 0]);
+        // The edge pixels are repeated.
+        assert_eq!(box_blur(&image, 4).into_raw(), vec![10, 10, 10, 10, 10]);
+
+        let flat = GrayImage::from_pixel(7, 9, Luma([77]));
+        assert_eq!(box_blur(&flat, 3), flat);
+    }
+
+    #[test]
+    fn test_gaussian_box_radii() {
+        assert_eq!(gaussian_box_radii(1.0, 3), vec![0, 0, 1]);
+        assert_eq!(gaussian_box_radii(4.0, 3), vec![3, 3, 4]);
+        assert_eq!(gaussian_box_radii(5.0, 3), vec![4, 4, 5]);
+    }
+
+    #[test]
+    fn test_fast_blur_approximates_gaussian() {
+        let image = GrayImage::from_fn(40, 30, |x, y| {
+            Luma([if (x / 10 + y / 10) % 2 == 0 { 40 } else { 220 }])
+        });
+
+        let exact = blur(&image, 4.0);
+        let fast = fast_blur(&image, 4.0);
+        let error: i64 = exact
+            .pixels()
+            .zip(fast.pixels())
+            .map(|(a, b)| (i64::from(a[0]) - i64::from(b[0])).abs())
+            .sum();
+        assert!(error < 40 * 30 * 4, "mean error {}", error as f64 / 1200.0);
+    }
+}
@@ -0,0 +1,105 @@
+//! Extraction of a foreground from a background of uniform color.
+
+use crate::color::{Rgb, Rgba};
+use crate::{RgbImage, RgbaImage};
+
+/// Cut the foreground out of an image shot in front of a colored screen, such as a green
+/// screen, and return it with an alpha matte.
+///
+/// Pixels are compared with `key_color` by their chroma, the Cb and Cr components of YCbCr, so
+/// that shadows and creases on the screen are keyed out along with the rest of it. Chroma
+/// distances are scaled so that fully saturated colors are about `0.5` from gray. Pixels closer
+/// to the key than `tolerance` become transparent, pixels farther than `tolerance + softness`
+/// stay opaque, and the alpha of those in between rises smoothly, which gives soft edges around
+/// hair and motion blur.
+///
+/// Light reflected by the screen tints the edges of the foreground. This spill is removed by
+/// limiting the channel that is strongest in the key color to the mean of the other two, in
+/// every pixel.
+///
+/// # Examples
+///
+/// ```
+/// use image::imageops::chroma_key;
+/// use image::{Rgb, RgbImage, Rgba};
+///
+/// let image = RgbImage::from_fn(2, 1, |x, _| {
+///     if x == 0 { Rgb([20, 200, 30]) } else { Rgb([200, 50, 40]) }
+/// });
+/// let keyed = chroma_key(&image, Rgb([0, 255, 0]), 0.2, 0.1);
+/// assert_eq!(keyed.get_pixel(0, 0)[3], 0);
+/// assert_eq!(keyed.get_pixel(1, 0), &Rgba([200, 50, 40, 255]));
+/// ```
+pub fn chroma_key(image: &RgbImage, key_color: Rgb<u8>, tolerance: f32, softness: f32) -> RgbaImage {
+    let key = chroma(key_color);
+    let strongest = (0..3).max_by_key(|&c| key_color[c]).unwrap_or(1);
+
+    let (width, height) = image.dimensions();
+    RgbaImage::from_fn(width, height, |x, y| {
+        let pixel = *image.get_pixel(x, y);
+        let (cb, cr) = chroma(pixel);
+        let distance = (cb - key.0).hypot(cr - key.1);
+        let alpha = if distance <= tolerance {
+            0.0
+        } else if distance >= tolerance + softness {
+            1.0
+        } else {
+            let t = (distance - tolerance) / softness;
+            t * t * (3.0 - 2.0 * t)
+        };
+
+        let mut rgb = pixel.0;
+        let others: u16 = (0..3).filter(|&c| c != strongest).map(|c| u16::from(rgb[c])).sum();
+        let limit = ((others + 1) / 2) as u8;
+        if rgb[strongest] > limit {
+            rgb[strongest] = limit;
+        }
+        Rgba([rgb[0], rgb[1], rgb[2], (alpha * 255.0).round() as u8])
+    })
+}
+
+/// The Cb and Cr components of an sRGB color, as defined by ITU-R BT.601, in `[-0.5, 0.5]`.
+fn chroma(color: Rgb<u8>) -> (f32, f32) {
+    let [r, g, b] = color.0;
+    let (r, g, b) = (f32::from(r) / 255.0, f32::from(g) / 255.0, f32::from(b) / 255.0);
+    let cb = -0.168_736 * r - 0.331_264 * g + 0.5 * b;
+    let cr = 0.5 * r - 0.418_688 * g - 0.081_312 * b;
+    (cb, cr)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::chroma_key;
+    use crate::{Rgb, RgbImage, Rgba};
+
+    #[test]
+    fn test_matte() {
+        // A gradient from the key color to a skin tone.
+        let (key, skin) = ([40.0, 180.0, 60.0], [220.0, 170.0, 140.0]);
+        let image = RgbImage::from_fn(33, 1, |x, _| {
+            let t = x as f32 / 32.0;
+            let mix = |c: usize| (key[c] * (1.0 - t) + skin[c] * t).round() as u8;
+            Rgb([mix(0), mix(1), mix(2)])
+        });
+        let keyed = chroma_key(&image, Rgb([40, 180, 60]), 0.1, 0.15);
+
+        let alpha: Vec<u8> = keyed.pixels().map(|p| p[3]).collect();
+        assert_eq!(alpha[0], 0);
+        assert_eq!(alpha[32], 255);
+        assert!(alpha.windows(2).all(|w| w[0] <= w[1]), "{:?}", alpha);
+        assert!(alpha.iter().any(|&a| a > 0 && a < 255), "{:?}", alpha);
+        assert_eq!(keyed.get_pixel(32, 0), &Rgba([220, 170, 140, 255]));
+    }
+
+    #[test]
+    fn test_despill() {
+        let image = RgbImage::from_pixel(1, 1, Rgb([120, 160, 100]));
+        let keyed = chroma_key(&image, Rgb([0, 255, 0]), 0.0, 0.0);
+        assert_eq!(keyed.get_pixel(0, 0), &Rgba([120, 110, 100, 255]));
+
+        // Blue screens limit the blue channel.
+        let image = RgbImage::from_pixel(1, 1, Rgb([100, 100, 200]));
+        let keyed = chroma_key(&image, Rgb([10, 20, 230]), 0.0, 0.0);
+        assert_eq!(keyed.get_pixel(0, 0), &Rgba([100, 100, 100, 255]));
+    }
+}
@@ -1,10 +1,13 @@
 //! Functions for altering and converting the color of pixelbufs
 
-use num_traits::{Bounded, Num, NumCast};
+use num_traits::{Num, NumCast};
 use std::f64::consts::PI;
 
 use crate::color::{Luma, Rgba};
+use crate::color_space::{hsl_to_rgb, rgb_to_hsl};
 use crate::image::{GenericImage, GenericImageView};
+use crate::imageops::sample::FloatNearest;
+use crate::imageops::{dither_with, plane, srgb, DitherMethod};
 #[allow(deprecated)]
 use crate::math::nq;
 use crate::traits::{Pixel, Primitive};
@@ -32,6 +35,79 @@ where
     out
 }
 
+/// The weights of red, green and blue in the luma computed by [`grayscale_with`].
+///
+/// [`grayscale_with`]: fn.grayscale_with.html
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LumaStandard {
+    /// ITU-R BT.601, for standard definition video: `0.299`, `0.587` and `0.114`.
+    Rec601,
+    /// ITU-R BT.709, for high definition video and sRGB: `0.2126`, `0.7152` and `0.0722`.
+    Rec709,
+    /// ITU-R BT.2100, for ultra high definition and HDR video: `0.2627`, `0.6780` and `0.0593`.
+    Rec2100,
+    /// The mean of the three channels.
+    Average,
+    /// Weights of red, green and blue, which should add up to one.
+    Custom([f32; 3]),
+}
+
+impl LumaStandard {
+    /// The weights of red, green and blue.
+    pub fn coefficients(self) -> [f32; 3] {
+        match self {
+            LumaStandard::Rec601 => [0.299, 0.587, 0.114],
+            LumaStandard::Rec709 => [0.2126, 0.7152, 0.0722],
+            LumaStandard::Rec2100 => [0.2627, 0.6780, 0.0593],
+            LumaStandard::Average => [1.0 / 3.0; 3],
+            LumaStandard::Custom(weights) => weights,
+        }
+    }
+}
+
+/// Convert the supplied image to grayscale with the weights of `standard`.
+///
+/// The weights are applied to the gamma encoded samples, as video standards define luma, or to
+/// the samples in linear light if `linear` is set, which gives the relative luminance of colors
+/// and keeps the brightness of saturated colors closer to how they are perceived. The result is
+/// encoded in sRGB again in either case. Samples are rounded to the nearest level.
+///
+/// # Examples
+///
+/// ```
+/// use image::imageops::{grayscale_with, LumaStandard};
+/// use image::{Luma, Rgb, RgbImage};
+///
+/// let green = RgbImage::from_pixel(1, 1, Rgb([0, 255, 0]));
+/// assert_eq!(grayscale_with(&green, LumaStandard::Rec601, false)[(0, 0)], Luma([150]));
+/// assert_eq!(grayscale_with(&green, LumaStandard::Rec709, false)[(0, 0)], Luma([182]));
+/// assert_eq!(grayscale_with(&green, LumaStandard::Rec709, true)[(0, 0)], Luma([220]));
+/// ```
+pub fn grayscale_with<I>(
+    image: &I,
+    standard: LumaStandard,
+    linear: bool,
+) -> ImageBuffer<Luma<Subpixel<I>>, Vec<Subpixel<I>>>
+where
+    I: GenericImageView,
+    Subpixel<I>: 'static,
+{
+    let max = plane::nominal_max::<Subpixel<I>>();
+    let weights = standard.coefficients();
+    let (width, height) = image.dimensions();
+    ImageBuffer::from_fn(width, height, |x, y| {
+        let rgb = image.get_pixel(x, y).to_rgb();
+        let mut luma = 0.0;
+        for (&sample, &weight) in rgb.channels().iter().zip(&weights) {
+            let value: f32 = NumCast::from(sample).unwrap();
+            let value = value / max;
+            luma += weight * if linear { srgb::to_linear(value) } else { value };
+        }
+        let luma = if linear { srgb::from_linear(clamp(luma, 0.0, 1.0)) } else { luma };
+        Luma([NumCast::from(FloatNearest(clamp(luma, 0.0, 1.0) * max)).unwrap()])
+    })
+}
+
 /// Invert each pixel within the supplied image.
 /// This function operates in place.
 pub fn invert<I: GenericImage>(image: &mut I) {
@@ -61,7 +137,7 @@ where
     let (width, height) = image.dimensions();
     let mut out = ImageBuffer::new(width, height);
 
-    let max = S::max_value();
+    let max = S::default_max_value();
     let max: f32 = NumCast::from(max).unwrap();
 
     let percent = ((100.0 + contrast) / 100.0).powi(2);
@@ -95,7 +171,7 @@ where
 {
     let (width, height) = image.dimensions();
 
-    let max = <<I::Pixel as Pixel>::Subpixel as Bounded>::max_value();
+    let max = <I::Pixel as Pixel>::Subpixel::default_max_value();
     let max: f32 = NumCast::from(max).unwrap();
 
     let percent = ((100.0 + contrast) / 100.0).powi(2);
@@ -130,15 +206,15 @@ where
     let (width, height) = image.dimensions();
     let mut out = ImageBuffer::new(width, height);
 
-    let max = S::max_value();
-    let max: i32 = NumCast::from(max).unwrap();
+    let max = S::default_max_value();
+    let max: f32 = NumCast::from(max).unwrap();
 
     for y in 0..height {
         for x in 0..width {
             let e = image.get_pixel(x, y).map_with_alpha(
                 |b| {
-                    let c: i32 = NumCast::from(b).unwrap();
-                    let d = clamp(c + value, 0, max);
+                    let c: f32 = NumCast::from(b).unwrap();
+                    let d = clamp(c + value as f32, 0.0, max);
 
                     NumCast::from(d).unwrap()
                 },
@@ -163,15 +239,15 @@ where
 {
     let (width, height) = image.dimensions();
 
-    let max = <<I::Pixel as Pixel>::Subpixel as Bounded>::max_value();
-    let max: i32 = NumCast::from(max).unwrap();
+    let max = <I::Pixel as Pixel>::Subpixel::default_max_value();
+    let max: f32 = NumCast::from(max).unwrap();
 
     for y in 0..height {
         for x in 0..width {
             let e = image.get_pixel(x, y).map_with_alpha(
                 |b| {
-                    let c: i32 = NumCast::from(b).unwrap();
-                    let d = clamp(c + value, 0, max);
+                    let c: f32 = NumCast::from(b).unwrap();
+                    let d = clamp(c + value as f32, 0.0, max);
 
                     NumCast::from(d).unwrap()
                 },
@@ -233,7 +309,7 @@ where
         let new_r = matrix[0] * r + matrix[1] * g + matrix[2] * b;
         let new_g = matrix[3] * r + matrix[4] * g + matrix[5] * b;
         let new_b = matrix[6] * r + matrix[7] * g + matrix[8] * b;
-        let max = 255f64;
+        let max: f64 = NumCast::from(S::default_max_value()).unwrap();
         let outpixel = Pixel::from_channels(
             NumCast::from(clamp(new_r, 0.0, max)).unwrap(),
             NumCast::from(clamp(new_g, 0.0, max)).unwrap(),
@@ -293,7 +369,7 @@ where
             let new_r = matrix[0] * r + matrix[1] * g + matrix[2] * b;
             let new_g = matrix[3] * r + matrix[4] * g + matrix[5] * b;
             let new_b = matrix[6] * r + matrix[7] * g + matrix[8] * b;
-            let max = 255f64;
+            let max: f64 = NumCast::from(<I::Pixel as Pixel>::Subpixel::default_max_value()).unwrap();
             let outpixel = Pixel::from_channels(
                 NumCast::from(clamp(new_r, 0.0, max)).unwrap(),
                 NumCast::from(clamp(new_g, 0.0, max)).unwrap(),
@@ -306,6 +382,266 @@ where
     }
 }
 
+/// Adjust the hue, saturation and lightness of the supplied image in place.
+///
+/// Every pixel is converted to HSL with `f32` precision, as by
+/// [`color_space::rgb_to_hsl`](../color_space/fn.rgb_to_hsl.html). Then `hue` degrees are
+/// added to its hue, which turns every color around the color wheel unlike the approximation of
+/// [`huerotate`], and its saturation and lightness are multiplied by `saturation` and
+/// `lightness` and clamped to `[0, 1]`. The values `(0.0, 1.0, 1.0)` leave the image unchanged.
+///
+/// Grayscale images only have their lightness adjusted. The alpha channel is left untouched.
+///
+/// # Examples
+///
+/// ```
+/// use image::imageops::adjust_hsl;
+/// use image::{Rgb, RgbImage};
+///
+/// let mut image = RgbImage::from_pixel(1, 1, Rgb([255, 0, 0]));
+/// adjust_hsl(&mut image, 120.0, 1.0, 0.5);
+/// assert_eq!(image.get_pixel(0, 0), &Rgb([0, 128, 0]));
+/// ```
+///
+/// [`huerotate`]: fn.huerotate.html
+pub fn adjust_hsl<I>(image: &mut I, hue: f32, saturation: f32, lightness: f32)
+where
+    I: GenericImage,
+{
+    map_colors(image, |rgb| {
+        let [h, s, l] = rgb_to_hsl(rgb);
+        hsl_to_rgb([
+            h + hue,
+            clamp(s * saturation, 0.0, 1.0),
+            clamp(l * lightness, 0.0, 1.0),
+        ])
+    });
+}
+
+/// Change the saturation of the supplied image in place.
+///
+/// The saturation of every pixel in HSL is multiplied by `factor`: `0.0` makes the image gray,
+/// values below `1.0` mute the colors and values above make them more vivid. This is
+/// [`adjust_hsl`] without changes to the hue and lightness.
+///
+/// [`adjust_hsl`]: fn.adjust_hsl.html
+pub fn saturate<I>(image: &mut I, factor: f32)
+where
+    I: GenericImage,
+{
+    adjust_hsl(image, 0.0, factor, 1.0);
+}
+
+/// Apply a gamma curve to the supplied image in place.
+///
+/// Every color sample, scaled to `[0, 1]`, is raised to the power of `gamma`. Values below
+/// `1.0` brighten the midtones and values above darken them, while black and white stay as they
+/// are. The alpha channel is left untouched.
+///
+/// # Examples
+///
+/// ```
+/// use image::imageops::adjust_gamma;
+/// use image::{GrayImage, Luma};
+///
+/// let mut image = GrayImage::from_pixel(1, 1, Luma([128]));
+/// adjust_gamma(&mut image, 2.0);
+/// assert_eq!(image.get_pixel(0, 0), &Luma([64]));
+/// ```
+pub fn adjust_gamma<I>(image: &mut I, gamma: f32)
+where
+    I: GenericImage,
+{
+    map_samples(image, |value| value.powf(gamma));
+}
+
+/// Change the exposure of the supplied image in place by `ev` stops.
+///
+/// Like opening the aperture of a camera, every stop doubles the light: the samples are decoded
+/// from sRGB to linear light, multiplied by `2^ev` and encoded again. Positive values brighten
+/// the image and negative values darken it, and highlights that become brighter than white are
+/// clipped. The alpha channel is left untouched.
+pub fn adjust_exposure<I>(image: &mut I, ev: f32)
+where
+    I: GenericImage,
+{
+    let gain = ev.exp2();
+    map_samples(image, |value| srgb::from_linear(clamp(srgb::to_linear(value) * gain, 0.0, 1.0)));
+}
+
+/// Apply a tone curve through the given control points to the supplied image in place.
+///
+/// The points map input to output values, both in `[0, 1]`, like the curves tool of an image
+/// editor. They are joined by a monotone cubic spline, which passes smoothly through every
+/// point without overshooting between them, and the curve is flat beyond the first and the
+/// last point. For integer samples of up to 16 bits, the curve is evaluated once per possible
+/// sample value and looked up from a table. The curve applies to every color channel alike, the
+/// alpha channel is left untouched.
+///
+/// # Panics
+///
+/// Panics if there are fewer than two points, or if their input values are not strictly
+/// increasing.
+///
+/// # Examples
+///
+/// ```
+/// use image::imageops::apply_curve;
+/// use image::{GrayImage, Luma};
+///
+/// // An S-curve, which raises the contrast of the midtones.
+/// let curve = [(0.0, 0.0), (0.25, 0.15), (0.75, 0.85), (1.0, 1.0)];
+/// let mut image = GrayImage::from_fn(3, 1, |x, _| Luma([[64, 128, 191][x as usize]]));
+/// apply_curve(&mut image, &curve);
+/// assert_eq!(image.get_pixel(0, 0), &Luma([39]));
+/// assert_eq!(image.get_pixel(2, 0), &Luma([216]));
+/// ```
+pub fn apply_curve<I>(image: &mut I, points: &[(f32, f32)])
+where
+    I: GenericImage,
+{
+    let spline = MonotoneSpline::new(points);
+    map_samples(image, |value| spline.evaluate(value));
+}
+
+/// A monotone cubic interpolation of control points, after Fritsch and Carlson, "Monotone
+/// Piecewise Cubic Interpolation", 1980.
+struct MonotoneSpline {
+    points: Vec<(f32, f32)>,
+    tangents: Vec<f32>,
+}
+
+impl MonotoneSpline {
+    fn new(points: &[(f32, f32)]) -> Self {
+        assert!(points.len() >= 2, "a curve needs at least two points");
+        assert!(
+            points.windows(2).all(|w| w[0].0 < w[1].0),
+            "the input values of the curve points must be strictly increasing"
+        );
+
+        let secants: Vec<f32> = points
+            .windows(2)
+            .map(|w| (w[1].1 - w[0].1) / (w[1].0 - w[0].0))
+            .collect();
+        let n = points.len();
+        let mut tangents = vec![0.0; n];
+        tangents[0] = secants[0];
+        tangents[n - 1] = secants[n - 2];
+        for k in 1..n - 1 {
+            if secants[k - 1] * secants[k] > 0.0 {
+                tangents[k] = (secants[k - 1] + secants[k]) / 2.0;
+            }
+        }
+
+        // Limit the tangents so that no segment overshoots.
+        for (k, &secant) in secants.iter().enumerate() {
+            if secant == 0.0 {
+                tangents[k] = 0.0;
+                tangents[k + 1] = 0.0;
+                continue;
+            }
+            let (a, b) = (tangents[k] / secant, tangents[k + 1] / secant);
+            let length = a.hypot(b);
+            if length > 3.0 {
+                tangents[k] = 3.0 / length * a * secant;
+                tangents[k + 1] = 3.0 / length * b * secant;
+            }
+        }
+
+        MonotoneSpline { points: points.to_vec(), tangents }
+    }
+
+    fn evaluate(&self, x: f32) -> f32 {
+        let (first, last) = (self.points[0], self.points[self.points.len() - 1]);
+        if x <= first.0 {
+            return first.1;
+        }
+        if x >= last.0 {
+            return last.1;
+        }
+
+        let k = self.points.iter().rposition(|p| p.0 <= x).unwrap();
+        let ((x0, y0), (x1, y1)) = (self.points[k], self.points[k + 1]);
+        let h = x1 - x0;
+        let t = (x - x0) / h;
+        let (t2, t3) = (t * t, t * t * t);
+        (2.0 * t3 - 3.0 * t2 + 1.0) * y0
+            + (t3 - 2.0 * t2 + t) * h * self.tangents[k]
+            + (-2.0 * t3 + 3.0 * t2) * y1
+            + (t3 - t2) * h * self.tangents[k + 1]
+    }
+}
+
+/// Replace every color sample of an image by a function of its value in `[0, 1]`.
+///
+/// Integer samples of up to 16 bits are looked up from a table of the function.
+fn map_samples<I, F>(image: &mut I, f: F)
+where
+    I: GenericImage,
+    F: Fn(f32) -> f32,
+{
+    let max = plane::nominal_max::<<I::Pixel as Pixel>::Subpixel>();
+    let tone = |value: f32| clamp(f(clamp(value / max, 0.0, 1.0)), 0.0, 1.0) * max;
+    let table: Option<Vec<f32>> = if max > 1.0 && max <= 65535.0 {
+        Some((0..=max as u32).map(|v| tone(v as f32)).collect())
+    } else {
+        None
+    };
+
+    let (width, height) = image.dimensions();
+    for y in 0..height {
+        for x in 0..width {
+            let pixel = image.get_pixel(x, y).map_with_alpha(
+                |sample| {
+                    let value = match table {
+                        Some(ref table) => table[NumCast::from(sample).unwrap_or(0usize)],
+                        None => tone(NumCast::from(sample).unwrap()),
+                    };
+                    NumCast::from(FloatNearest(value)).unwrap()
+                },
+                |alpha| alpha,
+            );
+            image.put_pixel(x, y, pixel);
+        }
+    }
+}
+
+/// Replace the color of every pixel of an image by a function of its sRGB components in
+/// `[0, 1]`. Grayscale pixels are passed as three equal components, and the first component of
+/// the result is kept.
+fn map_colors<I, F>(image: &mut I, f: F)
+where
+    I: GenericImage,
+    F: Fn([f32; 3]) -> [f32; 3],
+{
+    let (width, height) = image.dimensions();
+    let max = plane::nominal_max::<<I::Pixel as Pixel>::Subpixel>();
+    let channels = srgb::rgb_channels::<I::Pixel>();
+
+    for y in 0..height {
+        for x in 0..width {
+            let mut pixel = image.get_pixel(x, y);
+            {
+                let samples = pixel.channels_mut();
+                let sample = |c: usize| {
+                    let value: f32 = NumCast::from(samples[c]).unwrap();
+                    value / max
+                };
+                let result = match channels {
+                    Some([r, g, b]) => f([sample(r), sample(g), sample(b)]),
+                    None => f([sample(0); 3]),
+                };
+                let count = if channels.is_some() { 3 } else { 1 };
+                let indices = channels.unwrap_or([0, 0, 0]);
+                for (&c, &value) in indices.iter().zip(&result).take(count) {
+                    samples[c] = NumCast::from(FloatNearest(clamp(value, 0.0, 1.0) * max)).unwrap();
+                }
+            }
+            image.put_pixel(x, y, pixel);
+        }
+    }
+}
+
 /// A color map
 pub trait ColorMap {
     /// The color type on which the map operates on
@@ -443,70 +779,18 @@ impl ColorMap for color_quant::NeuQuant {
     }
 }
 
-/// Floyd-Steinberg error diffusion
-fn diffuse_err<P: Pixel<Subpixel = u8>>(pixel: &mut P, error: [i16; 3], factor: i16) {
-    for (e, c) in error.iter().zip(pixel.channels_mut().iter_mut()) {
-        *c = match <i16 as From<_>>::from(*c) + e * factor / 16 {
-            val if val < 0 => 0,
-            val if val > 0xFF => 0xFF,
-            val => val as u8,
-        }
-    }
-}
-
-macro_rules! do_dithering(
-    ($map:expr, $image:expr, $err:expr, $x:expr, $y:expr) => (
-        {
-            let old_pixel = $image[($x, $y)];
-            let new_pixel = $image.get_pixel_mut($x, $y);
-            $map.map_color(new_pixel);
-            for ((e, &old), &new) in $err.iter_mut()
-                                        .zip(old_pixel.channels().iter())
-                                        .zip(new_pixel.channels().iter())
-            {
-                *e = <i16 as From<_>>::from(old) - <i16 as From<_>>::from(new)
-            }
-        }
-    )
-);
-
 /// Reduces the colors of the image using the supplied `color_map` while applying
 /// Floyd-Steinberg dithering to improve the visual conception
+///
+/// See [`dither_with`] for other dithering methods.
+///
+/// [`dither_with`]: ../fn.dither_with.html
 pub fn dither<Pix, Map>(image: &mut ImageBuffer<Pix, Vec<u8>>, color_map: &Map)
 where
     Map: ColorMap<Color = Pix> + ?Sized,
     Pix: Pixel<Subpixel = u8> + 'static,
 {
-    let (width, height) = image.dimensions();
-    let mut err: [i16; 3] = [0; 3];
-    for y in 0..height - 1 {
-        let x = 0;
-        do_dithering!(color_map, image, err, x, y);
-        diffuse_err(image.get_pixel_mut(x + 1, y), err, 7);
-        diffuse_err(image.get_pixel_mut(x, y + 1), err, 5);
-        diffuse_err(image.get_pixel_mut(x + 1, y + 1), err, 1);
-        for x in 1..width - 1 {
-            do_dithering!(color_map, image, err, x, y);
-            diffuse_err(image.get_pixel_mut(x + 1, y), err, 7);
-            diffuse_err(image.get_pixel_mut(x - 1, y + 1), err, 3);
-            diffuse_err(image.get_pixel_mut(x, y + 1), err, 5);
-            diffuse_err(image.get_pixel_mut(x + 1, y + 1), err, 1);
-        }
-        let x = width - 1;
-        do_dithering!(color_map, image, err, x, y);
-        diffuse_err(image.get_pixel_mut(x - 1, y + 1), err, 3);
-        diffuse_err(image.get_pixel_mut(x, y + 1), err, 5);
-    }
-    let y = height - 1;
-    let x = 0;
-    do_dithering!(color_map, image, err, x, y);
-    diffuse_err(image.get_pixel_mut(x + 1, y), err, 7);
-    for x in 1..width - 1 {
-        do_dithering!(color_map, image, err, x, y);
-        diffuse_err(image.get_pixel_mut(x + 1, y), err, 7);
-    }
-    let x = width - 1;
-    do_dithering!(color_map, image, err, x, y);
+    dither_with(image, color_map, DitherMethod::FloydSteinberg)
 }
 
 /// Reduces the colors using the supplied `color_map` and returns an image of the indices
@@ -529,7 +813,7 @@ where
 mod test {
 
     use super::*;
-    use crate::ImageBuffer;
+    use crate::{ImageBuffer, Rgb, RgbImage};
 
     #[test]
     fn test_dither() {
@@ -539,4 +823,133 @@ mod test {
         assert_eq!(&*image, &[0, 0xFF, 0xFF, 0]);
         assert_eq!(index_colors(&image, &cmap).into_raw(), vec![0, 1, 1, 0])
     }
+
+    #[test]
+    fn test_grayscale_with() {
+        let image = RgbImage::from_fn(3, 1, |x, _| {
+            [Rgb([255, 0, 0]), Rgb([0, 0, 255]), Rgb([90, 90, 90])][x as usize]
+        });
+        let rec601 = grayscale_with(&image, LumaStandard::Rec601, false);
+        assert_eq!(rec601.into_raw(), [76, 29, 90]);
+        let rec2100 = grayscale_with(&image, LumaStandard::Rec2100, false);
+        assert_eq!(rec2100.into_raw(), [67, 15, 90]);
+        let average = grayscale_with(&image, LumaStandard::Average, true);
+        assert_eq!(average.into_raw(), [156, 156, 90]);
+
+        // Channel order and alpha don't matter, and neither does the bit depth.
+        let bgra = ImageBuffer::from_pixel(1, 1, crate::Bgra([0u16, 0, 65535, 1]));
+        let custom = grayscale_with(&bgra, LumaStandard::Custom([0.5, 0.25, 0.25]), false);
+        assert_eq!(custom[(0, 0)], Luma([32768]));
+    }
+
+    #[test]
+    fn test_adjust_hsl() {
+        use crate::{LumaA, Rgba, RgbaImage};
+
+        let mut image = RgbaImage::from_pixel(1, 1, Rgba([255, 0, 0, 100]));
+        adjust_hsl(&mut image, -120.0, 1.0, 1.0);
+        assert_eq!(image.get_pixel(0, 0), &Rgba([0, 0, 255, 100]));
+        adjust_hsl(&mut image, 0.0, 1.0, 1.0);
+        assert_eq!(image.get_pixel(0, 0), &Rgba([0, 0, 255, 100]));
+
+        // HSL keeps the lightness when desaturating, pure colors become mid gray.
+        saturate(&mut image, 0.0);
+        assert_eq!(image.get_pixel(0, 0), &Rgba([128, 128, 128, 100]));
+
+        let mut image = ImageBuffer::from_pixel(1, 1, Rgba([0.2f32, 0.4, 0.6, 1.0]));
+        saturate(&mut image, 0.5);
+        let p = image.get_pixel(0, 0);
+        for (&a, &b) in p.0.iter().zip(&[0.3, 0.4, 0.5, 1.0]) {
+            assert!((a - b).abs() < 1e-5, "{:?}", p);
+        }
+
+        let mut gray = ImageBuffer::from_pixel(1, 1, LumaA([200u8, 7]));
+        adjust_hsl(&mut gray, 90.0, 2.0, 0.5);
+        assert_eq!(gray.get_pixel(0, 0), &LumaA([100, 7]));
+    }
+
+    #[test]
+    fn test_adjust_hsl_edge_cases() {
+        use crate::{Bgr, Rgb, RgbImage};
+
+        // Hues wrap around in both directions.
+        for &hue in &[360.0, -720.0, 3600.0] {
+            let mut image = RgbImage::from_pixel(1, 1, Rgb([200, 50, 10]));
+            adjust_hsl(&mut image, hue, 1.0, 1.0);
+            assert_eq!(image.get_pixel(0, 0), &Rgb([200, 50, 10]), "{}", hue);
+        }
+
+        // Factors are clamped, negative ones act like zero.
+        let mut image = RgbImage::from_pixel(1, 1, Rgb([200, 50, 10]));
+        adjust_hsl(&mut image, 0.0, -1.0, 1.0);
+        assert_eq!(image.get_pixel(0, 0), &Rgb([105, 105, 105]));
+        let mut image = RgbImage::from_pixel(1, 1, Rgb([200, 50, 10]));
+        adjust_hsl(&mut image, 0.0, 1.0, -3.0);
+        assert_eq!(image.get_pixel(0, 0), &Rgb([0, 0, 0]));
+        let mut image = RgbImage::from_pixel(1, 1, Rgb([200, 50, 10]));
+        adjust_hsl(&mut image, 0.0, 1.0, 100.0);
+        assert_eq!(image.get_pixel(0, 0), &Rgb([255, 255, 255]));
+
+        // Channels in BGR order are rotated like RGB ones.
+        let mut bgr = ImageBuffer::from_pixel(1, 1, Bgr([0u8, 0, 255]));
+        adjust_hsl(&mut bgr, 120.0, 1.0, 1.0);
+        assert_eq!(bgr.get_pixel(0, 0), &Bgr([0, 255, 0]));
+
+        // 16-bit samples and empty images.
+        let mut deep = ImageBuffer::from_pixel(1, 1, Rgb([65535u16, 0, 0]));
+        adjust_hsl(&mut deep, 240.0, 1.0, 1.0);
+        assert_eq!(deep.get_pixel(0, 0), &Rgb([0, 0, 65535]));
+        let mut empty = RgbImage::new(0, 0);
+        saturate(&mut empty, 2.0);
+        assert_eq!(empty.dimensions(), (0, 0));
+    }
+
+    #[test]
+    fn test_gamma_and_exposure() {
+        use crate::{Rgba, RgbaImage};
+
+        let original = RgbaImage::from_fn(16, 16, |x, y| {
+            Rgba([(x * 16) as u8, (y * 16) as u8, 255, (x * y) as u8])
+        });
+        let mut image = original.clone();
+        adjust_gamma(&mut image, 1.0);
+        adjust_exposure(&mut image, 0.0);
+        assert_eq!(image, original);
+
+        adjust_gamma(&mut image, 0.5);
+        assert_eq!(image.get_pixel(4, 1), &Rgba([128, 64, 255, 4]));
+
+        // One stop doubles the light, 0.5 in sRGB is about 0.214 in linear light.
+        let mut image = ImageBuffer::from_pixel(1, 1, Luma([0.5f32]));
+        adjust_exposure(&mut image, 1.0);
+        assert!((image.get_pixel(0, 0)[0] - 0.6858).abs() < 1e-3, "{:?}", image.get_pixel(0, 0));
+        adjust_exposure(&mut image, -1.0);
+        assert!((image.get_pixel(0, 0)[0] - 0.5).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_apply_curve() {
+        let mut image: ImageBuffer<Luma<u16>, Vec<u16>> =
+            ImageBuffer::from_fn(256, 1, |x, _| Luma([(x * 257) as u16]));
+        apply_curve(&mut image, &[(0.0, 0.0), (1.0, 1.0)]);
+        assert!(image.pixels().enumerate().all(|(x, p)| p[0] == x as u16 * 257));
+
+        // The curve goes through its points, stays monotone and is flat outside of them.
+        let points = [(0.1, 0.2), (0.3, 0.25), (0.5, 0.9), (0.7, 0.95)];
+        apply_curve(&mut image, &points);
+        let values: Vec<f32> = image.pixels().map(|p| p[0] as f32 / 65535.0).collect();
+        for &(x, y) in &points {
+            let index = (x * 255.0f32).round() as usize;
+            assert!((values[index] - y).abs() < 0.02, "{} at {}", values[index], x);
+        }
+        assert!(values.windows(2).all(|w| w[0] <= w[1]));
+        assert!((values[0] - 0.2).abs() < 1e-4 && (values[255] - 0.95).abs() < 1e-4);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_apply_curve_unordered() {
+        let mut image = ImageBuffer::from_pixel(1, 1, Luma([0u8]));
+        apply_curve(&mut image, &[(0.5, 0.0), (0.2, 1.0)]);
+    }
 }
@@ -0,0 +1,266 @@
+//! Porter-Duff compositing and separable blend modes.
+
+use num_traits::NumCast;
+
+use crate::image::{GenericImage, GenericImageView};
+use crate::imageops::overlay_bounds;
+use crate::imageops::plane;
+use crate::imageops::sample::FloatNearest;
+use crate::traits::{Pixel, Primitive};
+use crate::utils::clamp;
+
+/// How [`composite`] combines the pixels of the source with those of the destination.
+///
+/// The first group are the operators of Thomas Porter and Tom Duff, "Compositing Digital Images",
+/// 1984, which decide by coverage which image is visible. The second group mixes the colors where
+/// both images are visible and is composited like `SourceOver`, following the W3C Compositing
+/// and Blending specification.
+///
+/// [`composite`]: fn.composite.html
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum BlendMode {
+    /// Neither image is visible.
+    Clear,
+    /// Only the source is visible.
+    Source,
+    /// Only the destination is visible.
+    Destination,
+    /// The source is placed over the destination, the same as [`overlay`].
+    ///
+    /// [`overlay`]: fn.overlay.html
+    SourceOver,
+    /// The destination is placed over the source.
+    DestinationOver,
+    /// The source where the destination is visible.
+    SourceIn,
+    /// The destination where the source is visible.
+    DestinationIn,
+    /// The source where the destination is not visible.
+    SourceOut,
+    /// The destination where the source is not visible.
+    DestinationOut,
+    /// The source over the destination, only where the destination is visible.
+    SourceAtop,
+    /// The destination over the source, only where the source is visible.
+    DestinationAtop,
+    /// The source and the destination where the other one is not visible.
+    Xor,
+    /// The product of the colors, which is always darker.
+    Multiply,
+    /// The inverse of the product of the inverted colors, which is always lighter.
+    Screen,
+    /// Multiply dark and screen light colors of the destination, increasing contrast.
+    Overlay,
+    /// The darker of the two colors.
+    Darken,
+    /// The lighter of the two colors.
+    Lighten,
+    /// The absolute difference of the colors.
+    Difference,
+}
+
+impl BlendMode {
+    /// The fractions of the source and the destination in the result, given their alphas.
+    fn fractions(self, source: f32, destination: f32) -> (f32, f32) {
+        use self::BlendMode::*;
+        match self {
+            Clear => (0.0, 0.0),
+            Source => (1.0, 0.0),
+            Destination => (0.0, 1.0),
+            SourceOver | Multiply | Screen | Overlay | Darken | Lighten | Difference => {
+                (1.0, 1.0 - source)
+            }
+            DestinationOver => (1.0 - destination, 1.0),
+            SourceIn => (destination, 0.0),
+            DestinationIn => (0.0, source),
+            SourceOut => (1.0 - destination, 0.0),
+            DestinationOut => (0.0, 1.0 - source),
+            SourceAtop => (destination, 1.0 - source),
+            DestinationAtop => (1.0 - destination, source),
+            Xor => (1.0 - destination, 1.0 - source),
+        }
+    }
+
+    /// Mix a destination and a source color, both normalized and not premultiplied, or `None` for
+    /// the Porter-Duff operators.
+    fn mix(self, destination: f32, source: f32) -> Option<f32> {
+        use self::BlendMode::*;
+        let multiply = |a: f32, b: f32| a * b;
+        let screen = |a: f32, b: f32| a + b - a * b;
+        match self {
+            Multiply => Some(multiply(destination, source)),
+            Screen => Some(screen(destination, source)),
+            Overlay if destination <= 0.5 => Some(multiply(source, 2.0 * destination)),
+            Overlay => Some(screen(source, 2.0 * destination - 1.0)),
+            Darken => Some(destination.min(source)),
+            Lighten => Some(destination.max(source)),
+            Difference => Some((destination - source).abs()),
+            _ => None,
+        }
+    }
+}
+
+/// Composite the image `top` onto `bottom` at the given coordinate, with the given mode.
+///
+/// Colors are premultiplied by their alpha for compositing and divided by the resulting alpha
+/// afterwards, so transparent pixels never bleed their color into the result. Images without an
+/// alpha channel are treated as opaque; where the result of an operator such as `SourceOut` is
+/// transparent, they are set to black. Only the area covered by `top` is changed, so operators
+/// like `SourceIn` leave `bottom` untouched outside of it.
+///
+/// # Examples
+///
+/// ```
+/// use image::imageops::{composite, BlendMode};
+/// use image::{Rgba, RgbaImage};
+///
+/// let mut bottom = RgbaImage::from_pixel(4, 4, Rgba([200, 100, 50, 255]));
+/// let top = RgbaImage::from_pixel(2, 2, Rgba([128, 128, 128, 255]));
+/// composite(&mut bottom, &top, 1, 1, BlendMode::Multiply);
+/// assert_eq!(bottom.get_pixel(1, 1), &Rgba([100, 50, 25, 255]));
+/// assert_eq!(bottom.get_pixel(0, 0), &Rgba([200, 100, 50, 255]));
+/// ```
+pub fn composite<I, J, P, S>(bottom: &mut I, top: &J, x: u32, y: u32, mode: BlendMode)
+where
+    I: GenericImage<Pixel = P>,
+    J: GenericImageView<Pixel = P>,
+    P: Pixel<Subpixel = S> + 'static,
+    S: Primitive + 'static,
+{
+    let max = plane::nominal_max::<S>();
+    let alpha = plane::alpha_channel::<P>();
+    let (range_width, range_height) = overlay_bounds(bottom.dimensions(), top.dimensions(), x, y);
+
+    let normalized = |pixel: &P| -> [f32; 4] {
+        let mut values = [0.0; 4];
+        for (value, &sample) in values.iter_mut().zip(pixel.channels()) {
+            let sample: f32 = NumCast::from(sample).unwrap();
+            *value = sample / max;
+        }
+        values
+    };
+
+    for top_y in 0..range_height {
+        for top_x in 0..range_width {
+            let mut pixel = bottom.get_pixel(x + top_x, y + top_y);
+            let destination = normalized(&pixel);
+            let source = normalized(&top.get_pixel(top_x, top_y));
+            let (source_alpha, destination_alpha) = match alpha {
+                Some(a) => (source[a], destination[a]),
+                None => (1.0, 1.0),
+            };
+
+            let (fs, fd) = mode.fractions(source_alpha, destination_alpha);
+            let result_alpha = fs * source_alpha + fd * destination_alpha;
+
+            for (channel, sample) in pixel.channels_mut().iter_mut().enumerate() {
+                let value = if Some(channel) == alpha {
+                    result_alpha
+                } else if result_alpha <= 0.0 {
+                    0.0
+                } else {
+                    let (d, s) = (destination[channel], source[channel]);
+                    let s = match mode.mix(d, s) {
+                        Some(mixed) => (1.0 - destination_alpha) * s + destination_alpha * mixed,
+                        None => s,
+                    };
+                    (fs * s * source_alpha + fd * d * destination_alpha) / result_alpha
+                };
+                *sample = NumCast::from(FloatNearest(clamp(value, 0.0, 1.0) * max)).unwrap();
+            }
+
+            bottom.put_pixel(x + top_x, y + top_y, pixel);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{composite, BlendMode};
+    use crate::{GrayImage, Luma, Rgb, RgbImage, Rgba, RgbaImage};
+
+    fn one(bottom: Rgba<u8>, top: Rgba<u8>, mode: BlendMode) -> Rgba<u8> {
+        let mut image = RgbaImage::from_pixel(1, 1, bottom);
+        composite(&mut image, &RgbaImage::from_pixel(1, 1, top), 0, 0, mode);
+        *image.get_pixel(0, 0)
+    }
+
+    #[test]
+    fn test_porter_duff() {
+        use super::BlendMode::*;
+        let red = Rgba([255, 0, 0, 255]);
+        let blue = Rgba([0, 0, 255, 255]);
+        let clear = Rgba([0, 0, 0, 0]);
+
+        // Opaque red over opaque blue, and over nothing.
+        let cases = [
+            (Clear, clear, clear),
+            (Source, red, red),
+            (Destination, blue, clear),
+            (SourceOver, red, red),
+            (DestinationOver, blue, red),
+            (SourceIn, red, clear),
+            (DestinationIn, blue, clear),
+            (SourceOut, clear, red),
+            (DestinationOut, clear, clear),
+            (SourceAtop, red, clear),
+            (DestinationAtop, blue, red),
+            (Xor, clear, red),
+        ];
+        for &(mode, over_blue, over_nothing) in &cases {
+            assert_eq!(one(blue, red, mode), over_blue, "{:?}", mode);
+            assert_eq!(one(clear, red, mode), over_nothing, "{:?}", mode);
+        }
+    }
+
+    #[test]
+    fn test_source_over_is_premultiplied() {
+        // Half transparent red over half transparent blue: the result alpha is 0.75 and red
+        // covers two thirds of it.
+        let result = one(Rgba([0, 0, 255, 128]), Rgba([255, 0, 0, 128]), BlendMode::SourceOver);
+        assert_eq!(result, Rgba([170, 0, 85, 192]));
+
+        // The color of fully transparent pixels has no influence.
+        let result = one(Rgba([0, 255, 0, 0]), Rgba([255, 0, 0, 128]), BlendMode::SourceOver);
+        assert_eq!(result, Rgba([255, 0, 0, 128]));
+    }
+
+    #[test]
+    fn test_blend_modes() {
+        use super::BlendMode::*;
+        let bottom = Rgba([200, 100, 51, 255]);
+        let top = Rgba([102, 204, 153, 255]);
+
+        let cases = [
+            (Multiply, [80, 80, 31]),
+            (Screen, [222, 224, 173]),
+            (Overlay, [189, 160, 61]),
+            (Darken, [102, 100, 51]),
+            (Lighten, [200, 204, 153]),
+            (Difference, [98, 104, 102]),
+        ];
+        for &(mode, [r, g, b]) in &cases {
+            assert_eq!(one(bottom, top, mode), Rgba([r, g, b, 255]), "{:?}", mode);
+        }
+
+        // Over a transparent destination the source is unchanged, and a transparent source
+        // changes nothing.
+        for &(mode, _) in &cases {
+            assert_eq!(one(Rgba([9, 9, 9, 0]), top, mode), top, "{:?}", mode);
+            assert_eq!(one(bottom, Rgba([9, 9, 9, 0]), mode), bottom, "{:?}", mode);
+        }
+    }
+
+    #[test]
+    fn test_without_alpha() {
+        let mut bottom = RgbImage::from_pixel(3, 3, Rgb([10, 20, 30]));
+        let top = RgbImage::from_pixel(2, 2, Rgb([255, 255, 255]));
+        composite(&mut bottom, &top, 2, 2, BlendMode::SourceOut);
+        assert_eq!(bottom.get_pixel(2, 2), &Rgb([0, 0, 0]));
+        assert_eq!(bottom.get_pixel(1, 2), &Rgb([10, 20, 30]));
+
+        let mut bottom = GrayImage::from_pixel(2, 2, Luma([100]));
+        composite(&mut bottom, &GrayImage::from_pixel(2, 2, Luma([50])), 0, 0, BlendMode::Lighten);
+        assert!(bottom.pixels().all(|&p| p == Luma([100])));
+    }
+}
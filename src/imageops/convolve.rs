@@ -0,0 +1,194 @@
+//! Convolution with kernels of arbitrary size.
+
+use crate::color::Luma;
+use crate::error::{ImageError, ImageResult, ParameterError, ParameterErrorKind};
+use crate::image::GenericImageView;
+use crate::imageops::plane::{self, Plane};
+use crate::traits::{Pixel, Primitive};
+use crate::ImageBuffer;
+
+/// The layout of the weights of a convolution kernel.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum KernelShape {
+    /// A square kernel with an odd number of weights per side, stored row by row.
+    Square,
+    /// A separable kernel, stored as the weights of the horizontal pass followed by the weights
+    /// of the vertical pass. Both passes have the same, odd number of weights.
+    ///
+    /// Separable kernels such as the Gaussian take time proportional to their size instead of its
+    /// square.
+    Separable,
+}
+
+/// How a convolution samples pixels outside of the image.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum BorderMode {
+    /// Repeat the nearest edge pixel.
+    Clamp,
+    /// Mirror the image at its edges, repeating the edge pixel.
+    Reflect,
+    /// Continue with the opposite edge of the image.
+    Wrap,
+    /// Treat all pixels outside of the image as zero.
+    Zero,
+}
+
+impl BorderMode {
+    /// The index of the sample used for position `i` on an axis of length `len`.
+    pub(crate) fn index(self, i: i64, len: u32) -> Option<usize> {
+        let len = i64::from(len);
+        let index = match self {
+            _ if i >= 0 && i < len => i,
+            BorderMode::Clamp => if i < 0 { 0 } else { len - 1 },
+            BorderMode::Reflect => {
+                let period = 2 * len;
+                let i = (i % period + period) % period;
+                if i < len { i } else { period - 1 - i }
+            }
+            BorderMode::Wrap => (i % len + len) % len,
+            BorderMode::Zero => return None,
+        };
+        Some(index as usize)
+    }
+}
+
+/// Convolve an image with a kernel of arbitrary size.
+///
+/// The kernel is centered on each pixel and applied as given, without flipping it and without
+/// normalizing its weights, so that kernels summing to zero such as edge detectors keep working.
+/// All channels are filtered, including alpha. The results are rounded and clamped to the range
+/// of the subpixel type. Pixels outside of the image are sampled according to `border`.
+///
+/// An error is returned if the number of weights does not match the `shape`.
+pub fn convolve<I, P, S>(
+    image: &I,
+    kernel: &[f32],
+    shape: KernelShape,
+    border: BorderMode,
+) -> ImageResult<ImageBuffer<P, Vec<S>>>
+where
+    I: GenericImageView<Pixel = P>,
+    P: Pixel<Subpixel = S> + 'static,
+    S: Primitive + 'static,
+{
+    let size = match shape {
+        KernelShape::Square => (1..).take_while(|n| n * n <= kernel.len()).last().unwrap_or(0),
+        KernelShape::Separable => kernel.len() / 2,
+    };
+    let expected = match shape {
+        KernelShape::Square => size * size,
+        KernelShape::Separable => 2 * size,
+    };
+    if size % 2 == 0 || kernel.len() != expected {
+        return Err(ImageError::Parameter(ParameterError::from_kind(
+            ParameterErrorKind::Generic(format!(
+                "a kernel of {} weights does not have the {:?} shape with an odd size",
+                kernel.len(),
+                shape
+            )),
+        )));
+    }
+
+    let planes: Vec<Plane> = plane::split(image)
+        .iter()
+        .map(|channel| match shape {
+            KernelShape::Square => convolve_plane(channel, kernel, size, size, border),
+            KernelShape::Separable => {
+                let (horizontal, vertical) = kernel.split_at(size);
+                let tmp = convolve_plane(channel, horizontal, size, 1, border);
+                convolve_plane(&tmp, vertical, 1, size, border)
+            }
+        })
+        .collect();
+    Ok(plane::merge(&planes))
+}
+
+/// Convolve a plane with a kernel of `kernel_width` × `kernel_height` weights stored row by row.
+fn convolve_plane(
+    plane: &Plane,
+    kernel: &[f32],
+    kernel_width: usize,
+    kernel_height: usize,
+    border: BorderMode,
+) -> Plane {
+    let (width, height) = plane.dimensions();
+    let (rx, ry) = ((kernel_width / 2) as i64, (kernel_height / 2) as i64);
+    let samples = plane.as_raw();
+
+    ImageBuffer::from_fn(width, height, |x, y| {
+        let mut sum = 0.0;
+        for (j, row) in kernel.chunks(kernel_width).enumerate() {
+            let sy = match border.index(i64::from(y) + j as i64 - ry, height) {
+                Some(sy) => sy,
+                None => continue,
+            };
+            for (i, &weight) in row.iter().enumerate() {
+                if let Some(sx) = border.index(i64::from(x) + i as i64 - rx, width) {
+                    sum += weight * samples[sy * width as usize + sx];
+                }
+            }
+        }
+        Luma([sum])
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{convolve, BorderMode, KernelShape};
+    use crate::{GrayImage, ImageBuffer, Luma};
+
+    #[test]
+    fn test_border_modes() {
+        let image: GrayImage = ImageBuffer::from_raw(3, 1, vec![10, 20, 30]).unwrap();
+        // Takes the pixel two to the left.
+        let mut kernel = vec![0.0; 25];
+        kernel[10] = 1.0;
+
+        let convolved = |border| {
+            convolve(&image, &kernel, KernelShape::Square, border)
+                .unwrap()
+                .into_raw()
+        };
+        assert_eq!(convolved(BorderMode::Clamp), vec![10, 10, 10]);
+        assert_eq!(convolved(BorderMode::Reflect), vec![20, 10, 10]);
+        assert_eq!(convolved(BorderMode::Wrap), vec![20, 30, 10]);
+        assert_eq!(convolved(BorderMode::Zero), vec![0, 0, 10]);
+    }
+
+    #[test]
+    fn test_separable_matches_square() {
+        let image = GrayImage::from_fn(9, 7, |x, y| Luma([(x * 25 + y * y * 5) as u8]));
+        let row = [1.0, 4.0, 6.0, 4.0, 1.0];
+        let row: Vec<f32> = row.iter().map(|w| w / 16.0).collect();
+
+        let square: Vec<f32> = row
+            .iter()
+            .flat_map(|a| row.iter().map(move |b| a * b))
+            .collect();
+        let separable: Vec<f32> = row.iter().chain(&row).cloned().collect();
+
+        let a = convolve(&image, &square, KernelShape::Square, BorderMode::Reflect).unwrap();
+        let b = convolve(&image, &separable, KernelShape::Separable, BorderMode::Reflect).unwrap();
+        for (p, q) in a.pixels().zip(b.pixels()) {
+            assert!((i16::from(p[0]) - i16::from(q[0])).abs() <= 1, "{:?} != {:?}", p, q);
+        }
+    }
+
+    #[test]
+    fn test_unnormalized_kernel() {
+        let image = GrayImage::from_pixel(4, 4, Luma([20]));
+        let kernel = [1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0];
+        let convolved = convolve(&image, &kernel, KernelShape::Square, BorderMode::Clamp).unwrap();
+        assert!(convolved.pixels().all(|p| p[0] == 180));
+    }
+
+    #[test]
+    fn test_invalid_kernels() {
+        let image = GrayImage::new(4, 4);
+        assert!(convolve(&image, &[1.0; 4], KernelShape::Square, BorderMode::Clamp).is_err());
+        assert!(convolve(&image, &[1.0; 8], KernelShape::Square, BorderMode::Clamp).is_err());
+        assert!(convolve(&image, &[1.0; 3], KernelShape::Separable, BorderMode::Clamp).is_err());
+        assert!(convolve(&image, &[], KernelShape::Separable, BorderMode::Clamp).is_err());
+        assert!(convolve(&image, &[1.0; 6], KernelShape::Separable, BorderMode::Clamp).is_ok());
+    }
+}
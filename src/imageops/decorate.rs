@@ -0,0 +1,169 @@
+//! Decorations around and on top of images, for galleries and thumbnails.
+
+use num_traits::NumCast;
+
+use crate::color::{Luma, Rgba};
+use crate::image::{GenericImage, GenericImageView};
+use crate::imageops::plane::{self, Plane};
+use crate::imageops::sample::FloatNearest;
+use crate::imageops::{overlay, replace};
+use crate::traits::{Pixel, Primitive};
+use crate::utils::clamp;
+use crate::{ImageBuffer, RgbaImage};
+
+/// Darken the corners of the supplied image in place, like the light falloff of a lens.
+///
+/// The color samples are multiplied by `1 - strength * r²`, where `r` is the distance from the
+/// center relative to the distance of the corners. A `strength` of `0.0` leaves the image
+/// unchanged and `1.0` makes the corners black. The alpha channel is left untouched.
+pub fn vignette<I>(image: &mut I, strength: f32)
+where
+    I: GenericImage,
+{
+    let (width, height) = image.dimensions();
+    let (cx, cy) = ((width as f32 - 1.0) / 2.0, (height as f32 - 1.0) / 2.0);
+    let corner = cx * cx + cy * cy;
+    if corner <= 0.0 {
+        return;
+    }
+
+    for y in 0..height {
+        for x in 0..width {
+            let (dx, dy) = (x as f32 - cx, y as f32 - cy);
+            let factor = clamp(1.0 - strength * (dx * dx + dy * dy) / corner, 0.0, 1.0);
+            let pixel = image.get_pixel(x, y).map_with_alpha(
+                |sample| {
+                    let value: f32 = NumCast::from(sample).unwrap();
+                    NumCast::from(FloatNearest(value * factor)).unwrap()
+                },
+                |alpha| alpha,
+            );
+            image.put_pixel(x, y, pixel);
+        }
+    }
+}
+
+/// Surround an image with a border of `width` pixels of `color` on every side.
+///
+/// # Examples
+///
+/// ```
+/// use image::imageops::add_border;
+/// use image::{Rgb, RgbImage};
+///
+/// let framed = add_border(&RgbImage::new(40, 30), 5, Rgb([255, 255, 255]));
+/// assert_eq!(framed.dimensions(), (50, 40));
+/// ```
+pub fn add_border<I, P, S>(image: &I, width: u32, color: P) -> ImageBuffer<P, Vec<S>>
+where
+    I: GenericImageView<Pixel = P>,
+    P: Pixel<Subpixel = S> + 'static,
+    S: Primitive + 'static,
+{
+    let (image_width, image_height) = image.dimensions();
+    let mut out = ImageBuffer::from_pixel(
+        image_width + 2 * width,
+        image_height + 2 * width,
+        color,
+    );
+    replace(&mut out, image, width, width);
+    out
+}
+
+/// Place an image over its own shadow, on a transparent canvas enlarged to fit the shadow.
+///
+/// The shadow has the shape of the opaque parts of the image, is moved by `offset` and blurred
+/// with a gaussian of standard deviation `blur`. Its opacity is the alpha of `color`. The image
+/// is at `(margin + max(0, -dx), margin + max(0, -dy))` on the canvas, where the
+/// margin is three standard deviations.
+///
+/// # Examples
+///
+/// ```
+/// use image::imageops::drop_shadow;
+/// use image::{Rgba, RgbaImage};
+///
+/// let card = RgbaImage::from_pixel(100, 60, Rgba([255, 255, 255, 255]));
+/// let shadowed = drop_shadow(&card, (4, 6), 2.0, Rgba([0, 0, 0, 128]));
+/// assert_eq!(shadowed.dimensions(), (100 + 4 + 12, 60 + 6 + 12));
+/// assert_eq!(shadowed.get_pixel(6, 6), &Rgba([255, 255, 255, 255]));
+/// ```
+pub fn drop_shadow<I>(image: &I, offset: (i32, i32), blur: f32, color: Rgba<u8>) -> RgbaImage
+where
+    I: GenericImageView<Pixel = Rgba<u8>>,
+{
+    let (width, height) = image.dimensions();
+    let margin = if blur > 0.0 { (3.0 * blur).ceil() as u32 } else { 0 };
+    let (dx, dy) = offset;
+    let left = margin + (-dx).max(0) as u32;
+    let top = margin + (-dy).max(0) as u32;
+    let canvas_width = width + 2 * margin + dx.abs() as u32;
+    let canvas_height = height + 2 * margin + dy.abs() as u32;
+
+    let (shadow_x, shadow_y) = (margin + dx.max(0) as u32, margin + dy.max(0) as u32);
+    let mut shadow = Plane::new(canvas_width, canvas_height);
+    for (x, y, pixel) in image.pixels() {
+        shadow.put_pixel(shadow_x + x, shadow_y + y, Luma([pixel[3] as f32 / 255.0]));
+    }
+    if blur > 0.0 {
+        shadow = plane::gaussian_blur(&shadow, blur);
+    }
+
+    let opacity = color[3] as f32;
+    let mut out = RgbaImage::from_fn(canvas_width, canvas_height, |x, y| {
+        let alpha = clamp(shadow.get_pixel(x, y)[0] * opacity, 0.0, 255.0);
+        Rgba([color[0], color[1], color[2], alpha.round() as u8])
+    });
+    overlay(&mut out, image, left, top);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{add_border, drop_shadow, vignette};
+    use crate::{GrayImage, Luma, LumaA, ImageBuffer, Rgba, RgbaImage};
+
+    #[test]
+    fn test_vignette() {
+        let mut image = ImageBuffer::from_pixel(21, 11, LumaA([200u8, 50]));
+        vignette(&mut image, 0.5);
+        assert_eq!(image.get_pixel(10, 5), &LumaA([200, 50]));
+        assert_eq!(image.get_pixel(0, 0), &LumaA([100, 50]));
+        assert_eq!(image.get_pixel(20, 10), &LumaA([100, 50]));
+        assert!(image.get_pixel(0, 5)[0] > 100 && image.get_pixel(0, 5)[0] < 200);
+
+        let mut single = GrayImage::from_pixel(1, 1, Luma([9]));
+        vignette(&mut single, 1.0);
+        assert_eq!(single.get_pixel(0, 0), &Luma([9]));
+    }
+
+    #[test]
+    fn test_add_border() {
+        let image = GrayImage::from_pixel(3, 2, Luma([7]));
+        let framed = add_border(&image, 2, Luma([1]));
+        assert_eq!(framed.dimensions(), (7, 6));
+        let inner = framed.enumerate_pixels().filter(|p| p.2[0] == 7).count();
+        assert_eq!(inner, 6);
+        assert_eq!(framed.get_pixel(2, 2), &Luma([7]));
+        assert_eq!(framed.get_pixel(5, 4), &Luma([1]));
+    }
+
+    #[test]
+    fn test_drop_shadow() {
+        let image = RgbaImage::from_pixel(4, 4, Rgba([255, 0, 0, 255]));
+
+        // Without blur the shadow is a sharp copy, up and to the left.
+        let shadowed = drop_shadow(&image, (-2, -1), 0.0, Rgba([0, 0, 0, 100]));
+        assert_eq!(shadowed.dimensions(), (6, 5));
+        assert_eq!(shadowed.get_pixel(0, 0), &Rgba([0, 0, 0, 100]));
+        assert_eq!(shadowed.get_pixel(2, 1), &Rgba([255, 0, 0, 255]));
+        assert_eq!(shadowed.get_pixel(5, 0)[3], 0);
+
+        let shadowed = drop_shadow(&image, (3, 3), 1.0, Rgba([0, 0, 0, 255]));
+        assert_eq!(shadowed.dimensions(), (13, 13));
+        assert_eq!(shadowed.get_pixel(0, 0)[3], 0);
+        assert_eq!(shadowed.get_pixel(3, 3), &Rgba([255, 0, 0, 255]));
+        let fringe = shadowed.get_pixel(9, 9)[3];
+        assert!(fringe > 0 && fringe < 255, "{}", fringe);
+    }
+}
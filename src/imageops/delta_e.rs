@@ -0,0 +1,220 @@
+//! Perceptual color differences between images.
+
+use std::f64::consts::PI;
+
+use crate::color::Luma;
+use crate::color_space::rgb_to_lab;
+use crate::image::GenericImageView;
+use crate::imageops::{plane, srgb};
+use crate::imageops::stats::{channel_stats, ChannelStats};
+use crate::traits::{Pixel, Primitive};
+use crate::ImageBuffer;
+
+/// The formula used to compute the difference between two colors.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DeltaEFormula {
+    /// The euclidean distance in CIE L\*a\*b\*. Fast, but overestimates differences of saturated
+    /// colors.
+    Cie76,
+    /// The CIEDE2000 formula, which corrects the perceptual non-uniformities of CIE76.
+    Ciede2000,
+}
+
+/// The per-pixel color difference between two images, as computed by [`delta_e`].
+///
+/// [`delta_e`]: fn.delta_e.html
+#[derive(Clone, Debug)]
+pub struct DeltaE {
+    /// The color difference of every pixel. A difference of about 1.0 is just noticeable.
+    pub map: ImageBuffer<Luma<f32>, Vec<f32>>,
+    /// Statistics of the differences, such as their mean, maximum and percentiles.
+    pub stats: ChannelStats,
+}
+
+/// Compute the perceptual color difference between two images of the same size.
+///
+/// Both images are interpreted as sRGB and compared in CIE L\*a\*b\* space, grayscale images by
+/// their lightness only. The alpha channel is ignored. This is useful for regression tests of
+/// rendering code, where a tolerance on the maximum or a high percentile of the difference is
+/// more meaningful than one on the raw sample values.
+///
+/// # Panics
+///
+/// Panics if the images do not have the same dimensions.
+pub fn delta_e<I, J, P, S>(first: &I, second: &J, formula: DeltaEFormula) -> DeltaE
+where
+    I: GenericImageView<Pixel = P>,
+    J: GenericImageView<Pixel = P>,
+    P: Pixel<Subpixel = S>,
+    S: Primitive,
+{
+    assert_eq!(
+        first.dimensions(),
+        second.dimensions(),
+        "the images to compare must have the same dimensions"
+    );
+
+    let (width, height) = first.dimensions();
+    let first = lab_pixels(first);
+    let second = lab_pixels(second);
+    let map = ImageBuffer::from_fn(width, height, |x, y| {
+        let index = y as usize * width as usize + x as usize;
+        let (a, b) = (first[index], second[index]);
+        let difference = match formula {
+            DeltaEFormula::Cie76 => cie76(a, b),
+            DeltaEFormula::Ciede2000 => ciede2000(a, b),
+        };
+        Luma([difference as f32])
+    });
+
+    let stats = channel_stats(&map).remove(0);
+    DeltaE { map, stats }
+}
+
+fn lab_pixels<I, P, S>(image: &I) -> Vec<[f64; 3]>
+where
+    I: GenericImageView<Pixel = P>,
+    P: Pixel<Subpixel = S>,
+    S: Primitive,
+{
+    let planes = plane::split(image);
+    let max = plane::nominal_max::<S>();
+    let [r, g, b] = srgb::rgb_channels::<P>().unwrap_or([0, 0, 0]);
+    (0..planes[0].len())
+        .map(|i| {
+            let lab = rgb_to_lab([
+                planes[r].as_raw()[i] / max,
+                planes[g].as_raw()[i] / max,
+                planes[b].as_raw()[i] / max,
+            ]);
+            [f64::from(lab[0]), f64::from(lab[1]), f64::from(lab[2])]
+        })
+        .collect()
+}
+
+fn cie76(a: [f64; 3], b: [f64; 3]) -> f64 {
+    ((a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2) + (a[2] - b[2]).powi(2)).sqrt()
+}
+
+/// CIEDE2000 with unit weighting factors, following Sharma, Wu and Dalal, "The CIEDE2000
+/// Color-Difference Formula: Implementation Notes, Supplementary Test Data, and Mathematical
+/// Observations", 2005.
+fn ciede2000(first: [f64; 3], second: [f64; 3]) -> f64 {
+    let [l1, a1, b1] = first;
+    let [l2, a2, b2] = second;
+
+    let c_mean = (a1.hypot(b1) + a2.hypot(b2)) / 2.0;
+    let c7 = c_mean.powi(7);
+    let g = 0.5 * (1.0 - (c7 / (c7 + 25f64.powi(7))).sqrt());
+    let (a1, a2) = ((1.0 + g) * a1, (1.0 + g) * a2);
+    let (c1, c2) = (a1.hypot(b1), a2.hypot(b2));
+
+    let hue = |b: f64, a: f64| {
+        if a == 0.0 && b == 0.0 {
+            0.0
+        } else {
+            let h = b.atan2(a).to_degrees();
+            if h < 0.0 {
+                h + 360.0
+            } else {
+                h
+            }
+        }
+    };
+    let (h1, h2) = (hue(b1, a1), hue(b2, a2));
+
+    let delta_l = l2 - l1;
+    let delta_c = c2 - c1;
+    let delta_h = if c1 * c2 == 0.0 {
+        0.0
+    } else if (h2 - h1).abs() <= 180.0 {
+        h2 - h1
+    } else if h2 - h1 > 180.0 {
+        h2 - h1 - 360.0
+    } else {
+        h2 - h1 + 360.0
+    };
+    let delta_big_h = 2.0 * (c1 * c2).sqrt() * (delta_h.to_radians() / 2.0).sin();
+
+    let l_mean = (l1 + l2) / 2.0;
+    let c_mean = (c1 + c2) / 2.0;
+    let h_mean = if c1 * c2 == 0.0 {
+        h1 + h2
+    } else if (h1 - h2).abs() <= 180.0 {
+        (h1 + h2) / 2.0
+    } else if h1 + h2 < 360.0 {
+        (h1 + h2 + 360.0) / 2.0
+    } else {
+        (h1 + h2 - 360.0) / 2.0
+    };
+
+    let t = 1.0 - 0.17 * (h_mean - 30.0).to_radians().cos()
+        + 0.24 * (2.0 * h_mean).to_radians().cos()
+        + 0.32 * (3.0 * h_mean + 6.0).to_radians().cos()
+        - 0.20 * (4.0 * h_mean - 63.0).to_radians().cos();
+    let delta_theta = 30.0 * (-((h_mean - 275.0) / 25.0).powi(2)).exp();
+    let c7 = c_mean.powi(7);
+    let r_c = 2.0 * (c7 / (c7 + 25f64.powi(7))).sqrt();
+    let l50 = (l_mean - 50.0).powi(2);
+    let s_l = 1.0 + 0.015 * l50 / (20.0 + l50).sqrt();
+    let s_c = 1.0 + 0.045 * c_mean;
+    let s_h = 1.0 + 0.015 * c_mean * t;
+    let r_t = -(2.0 * delta_theta * PI / 180.0).sin() * r_c;
+
+    let (l, c, h) = (delta_l / s_l, delta_c / s_c, delta_big_h / s_h);
+    (l * l + c * c + h * h + r_t * c * h).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ciede2000, delta_e, DeltaEFormula};
+    use crate::{GrayImage, ImageBuffer, Luma, Rgb};
+
+    #[test]
+    fn test_ciede2000_reference_data() {
+        // Pairs from the test data of Sharma et al.
+        let pairs = [
+            ([50.0, 2.6772, -79.7751], [50.0, 0.0, -82.7485], 2.0425),
+            ([50.0, 3.1571, -77.2803], [50.0, 0.0, -82.7485], 2.8615),
+            ([50.0, -1.3802, -84.2814], [50.0, 0.0, -82.7485], 1.0000),
+            ([50.0, 2.5, 0.0], [50.0, 0.0, -2.5], 4.3065),
+            (
+                [60.2574, -34.0099, 36.2677],
+                [60.4626, -34.1751, 39.4387],
+                1.2644,
+            ),
+            (
+                [2.0776, 0.0795, -1.1350],
+                [0.9033, -0.0636, -0.5514],
+                0.9082,
+            ),
+        ];
+        for &(a, b, expected) in &pairs {
+            assert!((ciede2000(a, b) - expected).abs() < 1e-4, "{:?} {:?}", a, b);
+            assert!((ciede2000(b, a) - expected).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn test_delta_e_map() {
+        let a = ImageBuffer::from_fn(4, 2, |x, _| Rgb([x as u8 * 60, 100, 50]));
+        let mut b = a.clone();
+        b.put_pixel(3, 1, Rgb([255, 0, 0]));
+
+        for &formula in &[DeltaEFormula::Cie76, DeltaEFormula::Ciede2000] {
+            let result = delta_e(&a, &b, formula);
+            assert_eq!(result.map.dimensions(), (4, 2));
+            assert_eq!(result.stats.count, 8);
+            assert_eq!(result.map.get_pixel(0, 0)[0], 0.0);
+            assert!(result.map.get_pixel(3, 1)[0] > 10.0);
+            assert_eq!(f64::from(result.map.get_pixel(3, 1)[0]), result.stats.max);
+            assert_eq!(result.stats.median(), Some(0.0));
+        }
+
+        // Black and white differ by 100 in lightness.
+        let black = GrayImage::new(1, 1);
+        let white = GrayImage::from_pixel(1, 1, Luma([255]));
+        let result = delta_e(&black, &white, DeltaEFormula::Cie76);
+        assert!((result.stats.max - 100.0).abs() < 0.01);
+    }
+}
@@ -0,0 +1,494 @@
+//! Noise reduction filters.
+
+use std::cmp::Ordering;
+
+use crate::image::GenericImageView;
+use crate::imageops::plane::{self, Plane};
+use crate::traits::{Pixel, Primitive};
+use crate::utils::clamp;
+use crate::ImageBuffer;
+
+/// Reduce noise with the non-local means algorithm.
+///
+/// Every pixel is replaced by a weighted average of the pixels in a search window around it,
+/// where pixels whose surrounding patch looks similar to the patch around the current pixel
+/// receive a higher weight. This preserves edges and fine textures much better than local
+/// filters, at the cost of a considerably longer running time.
+///
+/// `sigma` is the standard deviation of the noise in sample units, e.g. `10.0` for moderate noise
+/// in an 8-bit image or `2560.0` for the same noise level in a 16-bit image. Patch size, search
+/// window and filtering strength are chosen based on it, following Buades, Coll and Morel, "Non-Local
+/// Means Denoising", Image Processing On Line, 2011. The alpha channel is left untouched.
+pub fn denoise_nl_means<I, P, S>(image: &I, sigma: f32) -> ImageBuffer<P, Vec<S>>
+where
+    I: GenericImageView<Pixel = P>,
+    P: Pixel<Subpixel = S> + 'static,
+    S: Primitive + 'static,
+{
+    let mut planes = plane::split(image);
+    if sigma <= 0.0 || planes[0].is_empty() {
+        return plane::merge(&planes);
+    }
+
+    let alpha = plane::alpha_channel::<P>();
+    let color: Vec<usize> = (0..planes.len()).filter(|&c| Some(c) != alpha).collect();
+
+    // The parameters were tuned for 8-bit images.
+    let sigma8 = sigma * 255.0 / plane::nominal_max::<S>();
+    let (patch_radius, search_radius, h_factor) = if sigma8 <= 15.0 {
+        (1, 10, 0.4)
+    } else if sigma8 <= 30.0 {
+        (2, 10, 0.4)
+    } else if sigma8 <= 45.0 {
+        (3, 17, 0.35)
+    } else if sigma8 <= 75.0 {
+        (4, 17, 0.35)
+    } else {
+        (5, 17, 0.3)
+    };
+
+    let (width, height) = planes[0].dimensions();
+    let (w, h) = (width as usize, height as usize);
+    let variance = f64::from(sigma) * f64::from(sigma);
+    let filter = f64::from(h_factor * sigma) * f64::from(h_factor * sigma);
+
+    let mut weight_sum = vec![0.0f64; w * h];
+    let mut accumulated = vec![vec![0.0f64; w * h]; color.len()];
+    let mut integral = vec![0.0f64; (w + 1) * (h + 1)];
+
+    for dy in -search_radius..=search_radius {
+        for dx in -search_radius..=search_radius {
+            // Summed-area table of the squared difference between the image and its shifted copy,
+            // from which every patch distance for this shift is read in constant time.
+            for y in 0..h {
+                let mut row = 0.0;
+                for x in 0..w {
+                    let mut diff = 0.0;
+                    for &c in &color {
+                        let a = planes[c].as_raw()[y * w + x];
+                        let b = plane::get_clamped(&planes[c], x as i64 + dx, y as i64 + dy);
+                        diff += f64::from(a - b) * f64::from(a - b);
+                    }
+                    row += diff / color.len() as f64;
+                    integral[(y + 1) * (w + 1) + x + 1] = integral[y * (w + 1) + x + 1] + row;
+                }
+            }
+
+            for y in 0..h {
+                let top = y.saturating_sub(patch_radius);
+                let bottom = (y + patch_radius + 1).min(h);
+                for x in 0..w {
+                    let left = x.saturating_sub(patch_radius);
+                    let right = (x + patch_radius + 1).min(w);
+                    let area = ((bottom - top) * (right - left)) as f64;
+                    let sum = integral[bottom * (w + 1) + right] - integral[top * (w + 1) + right]
+                        - integral[bottom * (w + 1) + left]
+                        + integral[top * (w + 1) + left];
+
+                    let distance = sum / area;
+                    let weight = (-(distance - 2.0 * variance).max(0.0) / filter).exp();
+                    weight_sum[y * w + x] += weight;
+                    for (acc, &c) in accumulated.iter_mut().zip(&color) {
+                        let neighbour = plane::get_clamped(&planes[c], x as i64 + dx, y as i64 + dy);
+                        acc[y * w + x] += weight * f64::from(neighbour);
+                    }
+                }
+            }
+        }
+    }
+
+    for (acc, &c) in accumulated.iter().zip(&color) {
+        for ((value, sum), weight) in planes[c].iter_mut().zip(acc).zip(&weight_sum) {
+            *value = (sum / weight) as f32;
+        }
+    }
+
+    plane::merge(&planes)
+}
+
+/// Reduce noise by thresholding the coefficients of a stationary wavelet transform.
+///
+/// The image is decomposed with the à trous algorithm using a B3 spline into four detail scales,
+/// and detail coefficients below three times the expected noise level of their scale, which are
+/// most likely noise, are discarded. This is much faster than [`denoise_nl_means`] and works well
+/// on smooth content, but softens sharp high contrast edges.
+///
+/// `sigma` is the standard deviation of the noise in sample units, as for [`denoise_nl_means`].
+/// The alpha channel is left untouched.
+///
+/// [`denoise_nl_means`]: fn.denoise_nl_means.html
+pub fn denoise_wavelet<I, P, S>(image: &I, sigma: f32) -> ImageBuffer<P, Vec<S>>
+where
+    I: GenericImageView<Pixel = P>,
+    P: Pixel<Subpixel = S> + 'static,
+    S: Primitive + 'static,
+{
+    // Standard deviation of white noise with unit variance on each scale of the transform.
+    const NOISE_PER_SCALE: [f32; 4] = [0.889, 0.200, 0.086, 0.041];
+    const B3_SPLINE: [f32; 5] = [1.0 / 16.0, 4.0 / 16.0, 6.0 / 16.0, 4.0 / 16.0, 1.0 / 16.0];
+
+    let mut planes = plane::split(image);
+    if sigma <= 0.0 || planes[0].is_empty() {
+        return plane::merge(&planes);
+    }
+
+    let alpha = plane::alpha_channel::<P>();
+    for (c, channel) in planes.iter_mut().enumerate() {
+        if Some(c) == alpha {
+            continue;
+        }
+
+        let mut result = Plane::new(channel.width(), channel.height());
+        let mut current = channel.clone();
+        for (scale, &noise) in NOISE_PER_SCALE.iter().enumerate() {
+            // Insert 2^scale - 1 holes between the taps of the kernel.
+            let step = 1 << scale;
+            let mut kernel = vec![0.0; 4 * step + 1];
+            for (i, &tap) in B3_SPLINE.iter().enumerate() {
+                kernel[i * step] = tap;
+            }
+
+            let smooth = plane::convolve_separable(&current, &kernel);
+            let threshold = 3.0 * sigma * noise;
+            for ((out, &fine), &coarse) in result.iter_mut().zip(current.iter()).zip(smooth.iter()) {
+                let detail = fine - coarse;
+                if detail.abs() > threshold {
+                    *out += detail;
+                }
+            }
+            current = smooth;
+        }
+
+        for (out, &coarse) in result.iter_mut().zip(current.iter()) {
+            *out += coarse;
+        }
+        *channel = result;
+    }
+
+    plane::merge(&planes)
+}
+
+/// Replace every sample by the median of the `2 * radius + 1` square window around it.
+///
+/// The median removes impulse noise such as dead pixels or salt and pepper noise completely,
+/// while keeping straight edges sharp. Each channel, including alpha, is filtered independently
+/// and pixels outside of the image are replaced by the nearest edge pixel.
+///
+/// The window is moved over the image with a sliding histogram as described by Huang, Yang and
+/// Tang, "A fast two-dimensional median filtering algorithm", 1979. This takes time proportional
+/// to the radius per pixel, instead of its square.
+pub fn median_filter<I, P, S>(image: &I, radius: u32) -> ImageBuffer<P, Vec<S>>
+where
+    I: GenericImageView<Pixel = P>,
+    P: Pixel<Subpixel = S> + 'static,
+    S: Primitive + 'static,
+{
+    let planes: Vec<Plane> = plane::split(image)
+        .iter()
+        .map(|channel| median_plane(channel, radius))
+        .collect();
+    plane::merge(&planes)
+}
+
+/// Counts of the samples in a window, tracking the bin of their median.
+struct SlidingHistogram {
+    counts: Vec<u64>,
+    /// The current guess of the median bin.
+    median: usize,
+    /// The number of samples in bins below `median`.
+    below: u64,
+    /// The index of the median in the sorted samples of the window.
+    rank: u64,
+}
+
+impl SlidingHistogram {
+    fn add(&mut self, bin: usize) {
+        self.counts[bin] += 1;
+        if bin < self.median {
+            self.below += 1;
+        }
+    }
+
+    fn remove(&mut self, bin: usize) {
+        self.counts[bin] -= 1;
+        if bin < self.median {
+            self.below -= 1;
+        }
+    }
+
+    /// Move to the first bin whose samples reach beyond the rank of the median.
+    fn median(&mut self) -> usize {
+        while self.below > self.rank {
+            self.median -= 1;
+            self.below -= self.counts[self.median];
+        }
+        while self.below + self.counts[self.median] <= self.rank {
+            self.below += self.counts[self.median];
+            self.median += 1;
+        }
+        self.median
+    }
+}
+
+fn median_plane(channel: &Plane, radius: u32) -> Plane {
+    let (width, height) = channel.dimensions();
+    if channel.is_empty() {
+        return channel.clone();
+    }
+
+    // The histogram has one bin for every distinct sample value, in ascending order.
+    let compare = |a: &f32, b: &f32| a.partial_cmp(b).unwrap_or(Ordering::Equal);
+    let mut values = channel.as_raw().clone();
+    values.sort_by(compare);
+    values.dedup();
+    let bins: Vec<usize> = channel
+        .iter()
+        .map(|v| {
+            values
+                .binary_search_by(|probe| compare(probe, v))
+                .unwrap_or_else(|i| i.min(values.len() - 1))
+        })
+        .collect();
+
+    let (w, h) = (i64::from(width), i64::from(height));
+    let r = i64::from(radius);
+    let at = |x: i64, y: i64| bins[(clamp(y, 0, h - 1) * w + clamp(x, 0, w - 1)) as usize];
+
+    let side = 2 * r as u64 + 1;
+    let mut window = SlidingHistogram {
+        counts: vec![0; values.len()],
+        median: 0,
+        below: 0,
+        rank: side * side / 2,
+    };
+    for dy in -r..=r {
+        for dx in -r..=r {
+            window.add(at(dx, dy));
+        }
+    }
+
+    // Traverse the rows alternately left to right and right to left, so that the window only
+    // ever moves by a single pixel.
+    let mut out = vec![0.0; channel.len()];
+    let (mut x, mut y, mut step) = (0, 0, 1);
+    loop {
+        out[(y * w + x) as usize] = values[window.median()];
+
+        let next = x + step;
+        if next >= 0 && next < w {
+            let (leaving, entering) = if step > 0 { (x - r, next + r) } else { (x + r, next - r) };
+            for dy in -r..=r {
+                window.remove(at(leaving, y + dy));
+                window.add(at(entering, y + dy));
+            }
+            x = next;
+        } else if y + 1 < h {
+            for dx in -r..=r {
+                window.remove(at(x + dx, y - r));
+                window.add(at(x + dx, y + r + 1));
+            }
+            y += 1;
+            step = -step;
+        } else {
+            break;
+        }
+    }
+
+    Plane::from_raw(width, height, out).unwrap()
+}
+
+/// Smooth an image while preserving its edges with a bilateral filter.
+///
+/// Every pixel is replaced by an average of its neighbourhood, weighted both by the spatial
+/// distance with a Gaussian of standard deviation `sigma_space` in pixels, and by the difference
+/// in color with a Gaussian of standard deviation `sigma_color`. Neighbours across an edge differ
+/// strongly in color and hardly contribute, so edges stay sharp while flat regions are smoothed.
+///
+/// `sigma_color` is given in sample units, e.g. `30.0` for an 8-bit image. The color difference
+/// is the euclidean distance over all color channels. The alpha channel is left untouched.
+pub fn bilateral_filter<I, P, S>(image: &I, sigma_space: f32, sigma_color: f32) -> ImageBuffer<P, Vec<S>>
+where
+    I: GenericImageView<Pixel = P>,
+    P: Pixel<Subpixel = S> + 'static,
+    S: Primitive + 'static,
+{
+    let mut planes = plane::split(image);
+    if sigma_space <= 0.0 || sigma_color <= 0.0 || planes[0].is_empty() {
+        return plane::merge(&planes);
+    }
+
+    let alpha = plane::alpha_channel::<P>();
+    let color: Vec<usize> = (0..planes.len()).filter(|&c| Some(c) != alpha).collect();
+
+    let (width, height) = planes[0].dimensions();
+    let (w, h) = (width as usize, height as usize);
+    let radius = (2.0 * sigma_space).ceil() as i64;
+    let spatial: Vec<f32> = (-radius..=radius)
+        .flat_map(|dy| {
+            (-radius..=radius).map(move |dx| {
+                (-((dx * dx + dy * dy) as f32) / (2.0 * sigma_space * sigma_space)).exp()
+            })
+        })
+        .collect();
+    let color_scale = -1.0 / (2.0 * sigma_color * sigma_color);
+
+    let mut filtered = vec![vec![0.0; w * h]; color.len()];
+    let mut sums = vec![0.0; color.len()];
+    for y in 0..h {
+        for x in 0..w {
+            let mut weight_sum = 0.0;
+            sums.iter_mut().for_each(|sum| *sum = 0.0);
+
+            for dy in -radius..=radius {
+                let ny = y as i64 + dy;
+                if ny < 0 || ny >= h as i64 {
+                    continue;
+                }
+                for dx in -radius..=radius {
+                    let nx = x as i64 + dx;
+                    if nx < 0 || nx >= w as i64 {
+                        continue;
+                    }
+
+                    let neighbour = ny as usize * w + nx as usize;
+                    let distance: f32 = color
+                        .iter()
+                        .map(|&c| {
+                            let d = planes[c].as_raw()[neighbour] - planes[c].as_raw()[y * w + x];
+                            d * d
+                        })
+                        .sum();
+                    let index = ((dy + radius) * (2 * radius + 1) + dx + radius) as usize;
+                    let weight = spatial[index] * (distance * color_scale).exp();
+
+                    weight_sum += weight;
+                    for (sum, &c) in sums.iter_mut().zip(&color) {
+                        *sum += weight * planes[c].as_raw()[neighbour];
+                    }
+                }
+            }
+
+            for (out, sum) in filtered.iter_mut().zip(&sums) {
+                out[y * w + x] = sum / weight_sum;
+            }
+        }
+    }
+
+    for (values, &c) in filtered.into_iter().zip(&color) {
+        planes[c] = Plane::from_raw(width, height, values).unwrap();
+    }
+    plane::merge(&planes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{bilateral_filter, denoise_nl_means, denoise_wavelet, median_filter};
+    use crate::{GrayImage, ImageBuffer, Luma, Rgba};
+
+    fn clean(x: u32, y: u32) -> f32 {
+        125.0 + 60.0 * (x as f32 / 6.0).sin() * (y as f32 / 7.0).cos()
+    }
+
+    /// A deterministic, roughly gaussian noise source.
+    fn noisy_image(sigma: f32) -> GrayImage {
+        let mut state = 0x2545_f491u32;
+        let mut uniform = move || {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            f32::from(state as u16) / 65536.0
+        };
+
+        ImageBuffer::from_fn(64, 64, |x, y| {
+            // Irwin-Hall approximation of a standard normal distribution.
+            let normal: f32 = (0..12).map(|_| uniform()).sum::<f32>() - 6.0;
+            let value = clean(x, y) + sigma * normal;
+            Luma([value.max(0.0).min(255.0).round() as u8])
+        })
+    }
+
+    fn rmse(image: &GrayImage) -> f32 {
+        let sum: f32 = image
+            .enumerate_pixels()
+            .map(|(x, y, p)| (f32::from(p[0]) - clean(x, y)).powi(2))
+            .sum();
+        (sum / (image.width() * image.height()) as f32).sqrt()
+    }
+
+    #[test]
+    fn test_nl_means_reduces_noise() {
+        let noisy = noisy_image(20.0);
+        let denoised = denoise_nl_means(&noisy, 20.0);
+        assert!(rmse(&denoised) < rmse(&noisy) / 2.0);
+    }
+
+    #[test]
+    fn test_wavelet_reduces_noise() {
+        let noisy = noisy_image(20.0);
+        let denoised = denoise_wavelet(&noisy, 20.0);
+        assert!(rmse(&denoised) < rmse(&noisy) / 2.0);
+    }
+
+    /// Left half dark, right half bright.
+    fn step_edge() -> GrayImage {
+        ImageBuffer::from_fn(16, 12, |x, _| Luma([if x < 8 { 50 } else { 200 }]))
+    }
+
+    #[test]
+    fn test_median_removes_impulses() {
+        let mut img = GrayImage::from_pixel(10, 10, Luma([100]));
+        img.put_pixel(3, 3, Luma([255]));
+        img.put_pixel(7, 2, Luma([0]));
+        img.put_pixel(0, 9, Luma([255]));
+        assert_eq!(median_filter(&img, 1), GrayImage::from_pixel(10, 10, Luma([100])));
+
+        let edge = step_edge();
+        assert_eq!(median_filter(&edge, 2), edge);
+    }
+
+    #[test]
+    fn test_median_matches_sorting() {
+        let noisy = noisy_image(30.0);
+        let radius = 2i64;
+        let filtered = median_filter(&noisy, radius as u32);
+
+        let (w, h) = (i64::from(noisy.width()), i64::from(noisy.height()));
+        for (x, y, pixel) in filtered.enumerate_pixels() {
+            let mut window = Vec::new();
+            for dy in -radius..=radius {
+                for dx in -radius..=radius {
+                    let nx = (i64::from(x) + dx).max(0).min(w - 1) as u32;
+                    let ny = (i64::from(y) + dy).max(0).min(h - 1) as u32;
+                    window.push(noisy.get_pixel(nx, ny)[0]);
+                }
+            }
+            window.sort();
+            assert_eq!(pixel[0], window[window.len() / 2], "pixel ({}, {})", x, y);
+        }
+    }
+
+    #[test]
+    fn test_bilateral_preserves_edges() {
+        let edge = step_edge();
+        assert_eq!(bilateral_filter(&edge, 3.0, 10.0), edge);
+
+        let noisy = noisy_image(20.0);
+        let denoised = bilateral_filter(&noisy, 2.0, 40.0);
+        assert!(rmse(&denoised) < rmse(&noisy) / 1.5);
+    }
+
+    #[test]
+    fn test_denoise_keeps_alpha() {
+        let img = ImageBuffer::from_fn(8, 8, |x, y| Rgba([x as u16 * 1000, 0, y as u16, x as u16]));
+        let denoised = denoise_nl_means(&img, 500.0);
+        for (a, b) in denoised.pixels().zip(img.pixels()) {
+            assert_eq!(a[3], b[3]);
+        }
+        assert_eq!(denoise_wavelet(&img, 0.0), img);
+
+        let filtered = bilateral_filter(&img, 1.0, 2000.0);
+        for (a, b) in filtered.pixels().zip(img.pixels()) {
+            assert_eq!(a[3], b[3]);
+        }
+    }
+}
@@ -0,0 +1,213 @@
+//! Distance transforms of binary images.
+
+use std::f32;
+
+use crate::color::Luma;
+use crate::{GrayImage, ImageBuffer};
+
+/// How [`distance_transform`] measures the distance between two pixels.
+///
+/// [`distance_transform`]: fn.distance_transform.html
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DistanceMetric {
+    /// The straight line distance, `sqrt(dx² + dy²)`.
+    Euclidean,
+    /// The larger of the distances along both axes, `max(|dx|, |dy|)`, the number of king moves
+    /// on a chessboard.
+    Chebyshev,
+}
+
+/// Compute the distance of every pixel to the nearest foreground pixel of a binary image.
+///
+/// Pixels with a nonzero value are foreground and have a distance of zero, as in the output of
+/// [`threshold`]. If there is no foreground at all, every distance is infinite. The exact
+/// euclidean distances are computed in linear time with the algorithm of Pedro Felzenszwalb and
+/// Daniel Huttenlocher, "Distance Transforms of Sampled Functions", 2012.
+///
+/// The distance transforms of a mask and of its [`invert`]ed copy together give a signed
+/// distance field, as used for rendering scalable glyphs.
+///
+/// # Examples
+///
+/// ```
+/// use image::imageops::{distance_transform, DistanceMetric};
+/// use image::{GrayImage, Luma};
+///
+/// let mut image = GrayImage::new(5, 5);
+/// image.put_pixel(0, 0, Luma([255]));
+/// let distances = distance_transform(&image, DistanceMetric::Euclidean);
+/// assert_eq!(distances.get_pixel(3, 4), &Luma([5.0]));
+/// let distances = distance_transform(&image, DistanceMetric::Chebyshev);
+/// assert_eq!(distances.get_pixel(3, 4), &Luma([4.0]));
+/// ```
+///
+/// [`threshold`]: fn.threshold.html
+/// [`invert`]: fn.invert.html
+pub fn distance_transform(
+    image: &GrayImage,
+    metric: DistanceMetric,
+) -> ImageBuffer<Luma<f32>, Vec<f32>> {
+    let (width, height) = image.dimensions();
+    let (w, h) = (width as usize, height as usize);
+    let mut distances: Vec<f32> = image
+        .as_raw()
+        .iter()
+        .map(|&v| if v > 0 { 0.0 } else { std::f32::INFINITY })
+        .collect();
+
+    match metric {
+        DistanceMetric::Euclidean => {
+            // Squared distances along the columns, then along the rows.
+            let mut column = vec![0.0; h];
+            let mut scratch = Scratch::new(w.max(h));
+            for x in 0..w {
+                for y in 0..h {
+                    column[y] = distances[y * w + x];
+                }
+                scratch.transform(&mut column);
+                for y in 0..h {
+                    distances[y * w + x] = column[y];
+                }
+            }
+            for row in distances.chunks_mut(w.max(1)) {
+                scratch.transform(row);
+            }
+            distances.iter_mut().for_each(|d| *d = d.sqrt());
+        }
+        DistanceMetric::Chebyshev => {
+            // A forward and a backward pass, each looking at the four neighbours already seen.
+            let relax = |distances: &mut [f32], x: usize, y: usize, neighbours: &[(isize, isize)]| {
+                let mut best = distances[y * w + x];
+                for &(dx, dy) in neighbours {
+                    let (nx, ny) = (x as isize + dx, y as isize + dy);
+                    if nx >= 0 && ny >= 0 && (nx as usize) < w && (ny as usize) < h {
+                        best = best.min(distances[ny as usize * w + nx as usize] + 1.0);
+                    }
+                }
+                distances[y * w + x] = best;
+            };
+            for y in 0..h {
+                for x in 0..w {
+                    relax(&mut distances, x, y, &[(-1, 0), (-1, -1), (0, -1), (1, -1)]);
+                }
+            }
+            for y in (0..h).rev() {
+                for x in (0..w).rev() {
+                    relax(&mut distances, x, y, &[(1, 0), (1, 1), (0, 1), (-1, 1)]);
+                }
+            }
+        }
+    }
+
+    ImageBuffer::from_raw(width, height, distances).unwrap()
+}
+
+/// The buffers of the one dimensional squared euclidean distance transform.
+struct Scratch {
+    /// The positions of the parabolas forming the lower envelope.
+    vertices: Vec<usize>,
+    /// The boundaries between the parabolas of the envelope.
+    boundaries: Vec<f32>,
+    values: Vec<f32>,
+}
+
+impl Scratch {
+    fn new(len: usize) -> Self {
+        Scratch {
+            vertices: vec![0; len],
+            boundaries: vec![0.0; len + 1],
+            values: vec![0.0; len],
+        }
+    }
+
+    /// Replace every value `f(q)` by `min over p of (q - p)² + f(p)`.
+    fn transform(&mut self, f: &mut [f32]) {
+        let n = f.len();
+        let first = match f.iter().position(|v| v.is_finite()) {
+            Some(first) => first,
+            None => return,
+        };
+
+        let (v, z) = (&mut self.vertices, &mut self.boundaries);
+        let mut k = 0;
+        v[0] = first;
+        z[0] = std::f32::NEG_INFINITY;
+        z[1] = std::f32::INFINITY;
+        for q in first + 1..n {
+            if !f[q].is_finite() {
+                continue;
+            }
+            let intersection = |p: usize| {
+                let (qf, pf) = (q as f32, p as f32);
+                ((f[q] + qf * qf) - (f[p] + pf * pf)) / (2.0 * (qf - pf))
+            };
+            let mut s = intersection(v[k]);
+            while s <= z[k] {
+                k -= 1;
+                s = intersection(v[k]);
+            }
+            k += 1;
+            v[k] = q;
+            z[k] = s;
+            z[k + 1] = std::f32::INFINITY;
+        }
+
+        k = 0;
+        for q in 0..n {
+            while z[k + 1] < q as f32 {
+                k += 1;
+            }
+            let d = q as f32 - v[k] as f32;
+            self.values[q] = d * d + f[v[k]];
+        }
+        f.copy_from_slice(&self.values[..n]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{distance_transform, DistanceMetric};
+    use crate::{GrayImage, Luma};
+
+    fn brute_force(image: &GrayImage, metric: DistanceMetric) -> Vec<f32> {
+        let foreground: Vec<_> = image.enumerate_pixels().filter(|p| p.2[0] > 0).collect();
+        image
+            .enumerate_pixels()
+            .map(|(x, y, _)| {
+                foreground
+                    .iter()
+                    .map(|&(fx, fy, _)| {
+                        let dx = (x as f32 - fx as f32).abs();
+                        let dy = (y as f32 - fy as f32).abs();
+                        match metric {
+                            DistanceMetric::Euclidean => (dx * dx + dy * dy).sqrt(),
+                            DistanceMetric::Chebyshev => dx.max(dy),
+                        }
+                    })
+                    .fold(std::f32::INFINITY, f32::min)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_matches_brute_force() {
+        let image = GrayImage::from_fn(23, 17, |x, y| {
+            Luma([if (x * 7 + y * 11) % 37 == 0 || (x == 20 && y > 3) { 255 } else { 0 }])
+        });
+        for &metric in &[DistanceMetric::Euclidean, DistanceMetric::Chebyshev] {
+            let distances = distance_transform(&image, metric);
+            for (i, (&a, &b)) in distances.as_raw().iter().zip(&brute_force(&image, metric)).enumerate() {
+                assert!((a - b).abs() < 1e-4, "{:?} at {}: {} != {}", metric, i, a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn test_no_foreground() {
+        for &metric in &[DistanceMetric::Euclidean, DistanceMetric::Chebyshev] {
+            let distances = distance_transform(&GrayImage::new(4, 3), metric);
+            assert!(distances.pixels().all(|p| p[0].is_infinite()));
+            assert_eq!(distance_transform(&GrayImage::new(0, 5), metric).dimensions(), (0, 5));
+        }
+    }
+}
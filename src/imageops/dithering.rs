@@ -0,0 +1,400 @@
+//! Dithering against arbitrary color maps.
+
+use crate::imageops::colorops::ColorMap;
+use crate::imageops::plane;
+use crate::traits::Pixel;
+use crate::utils::clamp;
+use crate::ImageBuffer;
+
+/// How [`dither_with`] hides the banding of a reduced palette.
+///
+/// [`dither_with`]: fn.dither_with.html
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DitherMethod {
+    /// Error diffusion of Robert Floyd and Louis Steinberg, 1976.
+    ///
+    /// The whole error of every pixel is spread to its right and lower neighbours, which keeps
+    /// the average color of every region but produces a grainy, irregular pattern.
+    FloydSteinberg,
+    /// Error diffusion of Bill Atkinson, as used on the Macintosh.
+    ///
+    /// Only three quarters of the error are spread over a wider neighbourhood, which gives more
+    /// contrast and less grain than Floyd-Steinberg, at the cost of losing detail in very dark
+    /// and very bright regions.
+    Atkinson,
+    /// Ordered dithering with a Bayer matrix of `2^n` × `2^n` thresholds, for `n` from 1 to 4.
+    ///
+    /// Every pixel is offset by the threshold at its position before it is mapped, which gives a
+    /// regular crosshatch pattern. Unlike error diffusion, every pixel is treated on its own, so
+    /// the pattern stays stable between the frames of an animation.
+    Bayer(u8),
+}
+
+/// The neighbours receiving the error of a pixel, as `(dx, dy, weight)`, and the sum the weights
+/// are divided by.
+type Diffusion = (&'static [(isize, usize, f32)], f32);
+
+const FLOYD_STEINBERG: Diffusion = (&[(1, 0, 7.0), (-1, 1, 3.0), (0, 1, 5.0), (1, 1, 1.0)], 16.0);
+
+const ATKINSON: Diffusion = (
+    &[(1, 0, 1.0), (2, 0, 1.0), (-1, 1, 1.0), (0, 1, 1.0), (1, 1, 1.0), (0, 2, 1.0)],
+    8.0,
+);
+
+/// Reduces the colors of the image to those of `color_map`, dithering with the given method.
+///
+/// Any color map works, from the two shades of [`BiLevel`] to a [`Palette`] chosen by a
+/// quantizer or the 216 colors of [`Palette::web_safe`]. The alpha channel, if any, is mapped
+/// along with the other channels but never dithered.
+///
+/// # Panics
+///
+/// Panics if the order of a Bayer matrix is not in the range from 1 to 4.
+///
+/// # Examples
+///
+/// ```
+/// use image::imageops::{dither_with, DitherMethod};
+/// use image::quant::Palette;
+/// use image::{Rgba, RgbaImage};
+///
+/// let mut image = RgbaImage::from_fn(64, 64, |x, y| Rgba([(x * 4) as u8, (y * 4) as u8, 90, 255]));
+/// let palette = Palette::web_safe();
+/// dither_with(&mut image, &palette, DitherMethod::Atkinson);
+/// assert!(image.pixels().all(|pixel| palette.colors().contains(pixel)));
+/// ```
+///
+/// [`BiLevel`]: colorops/struct.BiLevel.html
+/// [`Palette`]: ../quant/struct.Palette.html
+/// [`Palette::web_safe`]: ../quant/struct.Palette.html#method.web_safe
+pub fn dither_with<Pix, Map>(
+    image: &mut ImageBuffer<Pix, Vec<u8>>,
+    color_map: &Map,
+    method: DitherMethod,
+) where
+    Map: ColorMap<Color = Pix> + ?Sized,
+    Pix: Pixel<Subpixel = u8> + 'static,
+{
+    match method {
+        DitherMethod::FloydSteinberg => diffuse(image, color_map, FLOYD_STEINBERG),
+        DitherMethod::Atkinson => diffuse(image, color_map, ATKINSON),
+        DitherMethod::Bayer(order) => {
+            assert!(order >= 1 && order <= 4, "the Bayer order needs to be in the range [1, 4]");
+            ordered(image, color_map, order)
+        }
+    }
+}
+
+/// The number of channels that are dithered, all but alpha.
+fn color_channels<Pix: Pixel>() -> usize {
+    let channels = usize::from(Pix::CHANNEL_COUNT);
+    if Pix::COLOR_TYPE.has_alpha() {
+        channels - 1
+    } else {
+        channels
+    }
+}
+
+fn diffuse<Pix, Map>(image: &mut ImageBuffer<Pix, Vec<u8>>, color_map: &Map, diffusion: Diffusion)
+where
+    Map: ColorMap<Color = Pix> + ?Sized,
+    Pix: Pixel<Subpixel = u8> + 'static,
+{
+    let (neighbours, divisor) = diffusion;
+    let colors = color_channels::<Pix>();
+    let (width, height) = image.dimensions();
+
+    // The errors of the current and the next two rows, with two columns of padding on either
+    // side so that no neighbour needs a bounds check.
+    let stride = (width as usize + 4) * colors;
+    let mut errors = vec![0.0f32; 3 * stride];
+
+    for y in 0..height {
+        for x in 0..width {
+            let offset = (x as usize + 2) * colors;
+            let pixel = image.get_pixel_mut(x, y);
+
+            let mut wanted = [0.0f32; 4];
+            for (c, (value, error)) in pixel
+                .channels_mut()
+                .iter_mut()
+                .zip(&errors[offset..offset + colors])
+                .enumerate()
+            {
+                wanted[c] = clamp(f32::from(*value) + error, 0.0, 255.0);
+                *value = wanted[c].round() as u8;
+            }
+
+            color_map.map_color(pixel);
+
+            let mapped = wanted.iter().zip(pixel.channels()).take(colors);
+            for (c, (&wanted, &value)) in mapped.enumerate() {
+                let error = (wanted - f32::from(value)) / divisor;
+                for &(dx, dy, weight) in neighbours {
+                    let index = dy * stride + (offset as isize + dx * colors as isize) as usize + c;
+                    errors[index] += error * weight;
+                }
+            }
+        }
+
+        errors.rotate_left(stride);
+        for error in &mut errors[2 * stride..] {
+            *error = 0.0;
+        }
+    }
+}
+
+fn ordered<Pix, Map>(image: &mut ImageBuffer<Pix, Vec<u8>>, color_map: &Map, order: u8)
+where
+    Map: ColorMap<Color = Pix> + ?Sized,
+    Pix: Pixel<Subpixel = u8> + 'static,
+{
+    let colors = color_channels::<Pix>();
+    let spread = bayer_spread(color_map, colors);
+    let size = 1u32 << order;
+    let cells = (size * size) as f32;
+
+    for (x, y, pixel) in image.enumerate_pixels_mut() {
+        let threshold = (bayer_index(x % size, y % size, order) as f32 + 0.5) / cells - 0.5;
+        for value in &mut pixel.channels_mut()[..colors] {
+            let offset = f32::from(*value) + threshold * spread;
+            *value = clamp(offset.round(), 0.0, 255.0) as u8;
+        }
+        color_map.map_color(pixel);
+    }
+}
+
+/// Quantizes samples in `[0, 1]` to the integer range of the subpixels of `Q`, dithering the
+/// color channels with `dither` to hide the banding of the reduced bit depth, or rounding them
+/// to the nearest level.
+///
+/// Both pixel types need to have the same channels.
+pub(crate) fn reduce_depth<P, Q>(
+    image: &ImageBuffer<P, Vec<f32>>,
+    dither: Option<DitherMethod>,
+) -> ImageBuffer<Q, Vec<Q::Subpixel>>
+where
+    P: Pixel<Subpixel = f32> + 'static,
+    Q: Pixel + 'static,
+{
+    let max = plane::nominal_max::<Q::Subpixel>();
+    let channels = usize::from(P::CHANNEL_COUNT);
+    let colors = color_channels::<P>();
+    let (width, height) = image.dimensions();
+    let mut out: ImageBuffer<Q, Vec<Q::Subpixel>> = ImageBuffer::new(width, height);
+    let level = |value: f32| -> Q::Subpixel {
+        num_traits::NumCast::from(clamp(value, 0.0, max).round()).unwrap()
+    };
+
+    let method = match dither {
+        Some(method) => method,
+        None => {
+            for (sample, &value) in out.iter_mut().zip(image.iter()) {
+                *sample = level(value * max);
+            }
+            return out;
+        }
+    };
+    let diffusion = match method {
+        DitherMethod::FloydSteinberg => FLOYD_STEINBERG,
+        DitherMethod::Atkinson => ATKINSON,
+        DitherMethod::Bayer(order) => {
+            assert!(order >= 1 && order <= 4, "the Bayer order needs to be in the range [1, 4]");
+            let size = 1u32 << order;
+            let cells = (size * size) as f32;
+            for (x, y, pixel) in out.enumerate_pixels_mut() {
+                let threshold = (bayer_index(x % size, y % size, order) as f32 + 0.5) / cells - 0.5;
+                let source = image.get_pixel(x, y).channels();
+                for (c, value) in pixel.channels_mut().iter_mut().enumerate() {
+                    let offset = if c < colors { threshold } else { 0.0 };
+                    *value = level(source[c] * max + offset);
+                }
+            }
+            return out;
+        }
+    };
+
+    // The errors of the current and the next two rows, in levels of the output, padded as in
+    // `diffuse`.
+    let (neighbours, divisor) = diffusion;
+    let stride = (width as usize + 4) * colors;
+    let mut errors = vec![0.0f32; 3 * stride];
+    for y in 0..height {
+        for x in 0..width {
+            let offset = (x as usize + 2) * colors;
+            let source = image.get_pixel(x, y).channels();
+            let pixel = out.get_pixel_mut(x, y).channels_mut();
+            for c in 0..channels {
+                if c >= colors {
+                    pixel[c] = level(source[c] * max);
+                    continue;
+                }
+                let wanted = clamp(source[c] * max + errors[offset + c], 0.0, max);
+                pixel[c] = level(wanted);
+                let error = (wanted - wanted.round()) / divisor;
+                for &(dx, dy, weight) in neighbours {
+                    let index = dy * stride + (offset as isize + dx * colors as isize) as usize + c;
+                    errors[index] += error * weight;
+                }
+            }
+        }
+
+        errors.rotate_left(stride);
+        for error in &mut errors[2 * stride..] {
+            *error = 0.0;
+        }
+    }
+    out
+}
+
+/// The position of a cell in the order of a Bayer matrix of `2^order` × `2^order` cells.
+///
+/// The matrix of order `n + 1` repeats the one of order `n` in its four quadrants, scaled by
+/// four and offset by the matrix of order 1, `[[0, 2], [3, 1]]`. So every bit of the coordinates
+/// contributes one digit in base four, the least significant bits the most significant digit.
+fn bayer_index(x: u32, y: u32, order: u8) -> u32 {
+    (0..u32::from(order)).fold(0, |index, bit| {
+        let (xb, yb) = ((x >> bit) & 1, (y >> bit) & 1);
+        index | (((xb ^ yb) << 1 | yb) << (2 * (u32::from(order) - 1 - bit)))
+    })
+}
+
+/// How far apart the colors of the map are along a channel, which is how far pixels need to be
+/// offset to reach the neighbouring colors.
+///
+/// This assumes the colors form an even grid, which is exact for palettes like the web safe one
+/// and a fair guess for quantized ones. Maps without `lookup` are assumed to have 256 colors.
+fn bayer_spread<Map: ColorMap + ?Sized>(color_map: &Map, colors: usize) -> f32 {
+    let count = if color_map.has_lookup() {
+        (0..=256).take_while(|&index| color_map.lookup(index).is_some()).count()
+    } else {
+        256
+    };
+
+    let levels = (count.max(2) as f32).powf(1.0 / colors as f32);
+    clamp(255.0 / (levels - 1.0), 0.0, 255.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{bayer_index, dither_with, reduce_depth, DitherMethod};
+    use crate::imageops::colorops::BiLevel;
+    use crate::quant::Palette;
+    use crate::{GrayImage, ImageBuffer, Luma, LumaA, Rgba, RgbaImage};
+
+    const METHODS: [DitherMethod; 4] = [
+        DitherMethod::FloydSteinberg,
+        DitherMethod::Atkinson,
+        DitherMethod::Bayer(2),
+        DitherMethod::Bayer(4),
+    ];
+
+    #[test]
+    fn test_bayer_index() {
+        let matrix: Vec<u32> = (0..16).map(|i| bayer_index(i % 4, i / 4, 2)).collect();
+        assert_eq!(matrix, [0, 8, 2, 10, 12, 4, 14, 6, 3, 11, 1, 9, 15, 7, 13, 5]);
+    }
+
+    #[test]
+    fn test_keeps_mean_gray() {
+        for &level in &[64u8, 128, 200] {
+            for &method in &METHODS {
+                let mut image = GrayImage::from_pixel(32, 32, Luma([level]));
+                dither_with(&mut image, &BiLevel, method);
+
+                assert!(image.pixels().all(|p| p[0] == 0 || p[0] == 255));
+                let white = image.pixels().filter(|p| p[0] == 255).count() as f32;
+                let mean = 255.0 * white / 1024.0;
+                // Atkinson drops a quarter of the error and drifts towards black and white.
+                let tolerance = if method == DitherMethod::Atkinson { 32.0 } else { 8.0 };
+                assert!(
+                    (mean - f32::from(level)).abs() < tolerance,
+                    "{:?} gives {} for {}", method, mean, level
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_error_diffusion_palette() {
+        let palette = Palette::new(vec![
+            Rgba([0, 0, 0, 255]),
+            Rgba([255, 0, 0, 255]),
+            Rgba([0, 0, 255, 255]),
+            Rgba([255, 255, 255, 255]),
+        ]);
+        // Purple lies between red and blue and needs both.
+        for &method in &[DitherMethod::FloydSteinberg, DitherMethod::Atkinson] {
+            let mut image = RgbaImage::from_pixel(16, 16, Rgba([128, 0, 128, 255]));
+            dither_with(&mut image, &palette, method);
+
+            assert!(image.pixels().all(|pixel| palette.colors().contains(pixel)));
+            assert!(image.pixels().any(|&pixel| pixel == Rgba([255, 0, 0, 255])), "{:?}", method);
+            assert!(image.pixels().any(|&pixel| pixel == Rgba([0, 0, 255, 255])), "{:?}", method);
+        }
+    }
+
+    #[test]
+    fn test_bayer_web_safe() {
+        // Halfway between the two darkest levels of the web safe palette.
+        let palette = Palette::web_safe();
+        for &order in &[1, 2, 4] {
+            let mut image = RgbaImage::from_pixel(16, 16, Rgba([25, 25, 25, 255]));
+            dither_with(&mut image, &palette, DitherMethod::Bayer(order));
+
+            let dark = image.pixels().filter(|&&pixel| pixel == Rgba([0, 0, 0, 255])).count();
+            let light = image.pixels().filter(|&&pixel| pixel == Rgba([51, 51, 51, 255])).count();
+            assert_eq!(dark + light, 256);
+            assert!((dark as i32 - 128).abs() <= 4, "{} dark pixels with order {}", dark, order);
+        }
+    }
+
+    #[test]
+    fn test_alpha_is_not_dithered() {
+        let palette = Palette::new(vec![
+            Rgba([0, 0, 0, 255]),
+            Rgba([255, 255, 255, 255]),
+            Rgba([0, 0, 0, 0]),
+            Rgba([255, 255, 255, 0]),
+        ]);
+        for &method in &METHODS {
+            let mut image = RgbaImage::from_fn(8, 8, |x, _| {
+                Rgba([100, 100, 100, if x < 4 { 0 } else { 255 }])
+            });
+            dither_with(&mut image, &palette, method);
+            for (x, _, pixel) in image.enumerate_pixels() {
+                assert_eq!(pixel[3], if x < 4 { 0 } else { 255 }, "{:?}", method);
+            }
+        }
+    }
+
+    #[test]
+    fn test_small_images() {
+        for &(width, height) in &[(0, 0), (1, 5), (5, 1), (1, 1)] {
+            for &method in &METHODS {
+                let mut image = GrayImage::from_pixel(width, height, Luma([100]));
+                dither_with(&mut image, &BiLevel, method);
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_bayer_order() {
+        dither_with(&mut GrayImage::new(4, 4), &BiLevel, DitherMethod::Bayer(5));
+    }
+
+    #[test]
+    fn test_reduce_depth() {
+        // A level between two of the output, with alpha exactly between two levels.
+        let image = ImageBuffer::from_pixel(16, 16, LumaA([100.25f32 / 255.0, 0.5]));
+        for &method in &METHODS {
+            let reduced: ImageBuffer<LumaA<u8>, Vec<u8>> = reduce_depth(&image, Some(method));
+            assert!(reduced.pixels().all(|p| (p[0] == 100 || p[0] == 101) && p[1] == 128));
+            let high = reduced.pixels().filter(|p| p[0] == 101).count();
+            // Atkinson drops a quarter of the error, as above.
+            let least = if method == DitherMethod::Atkinson { 32 } else { 48 };
+            assert!(high >= least && high <= 80, "{:?} gives {} of 256", method, high);
+        }
+    }
+}
@@ -0,0 +1,347 @@
+//! Image gradients and edge detection.
+
+use num_traits::NumCast;
+
+use crate::imageops::blur;
+use crate::imageops::sample::FloatNearest;
+use crate::traits::Primitive;
+use crate::utils::clamp;
+use crate::{GrayImage, ImageBuffer, Luma};
+
+type Derivative<T> = ImageBuffer<Luma<T>, Vec<T>>;
+
+/// The 3 × 3 kernels used to approximate the derivatives of an image.
+///
+/// All operators take the difference of the neighbours on either side of a pixel along one axis
+/// and smooth it along the other axis. They only differ in the smoothing weights.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum GradientOperator {
+    /// Smoothing weights of 1, 1, 1.
+    Prewitt,
+    /// Smoothing weights of 1, 2, 1.
+    Sobel,
+    /// Smoothing weights of 3, 10, 3, which are closer to rotation invariance than Sobel.
+    Scharr,
+}
+
+impl GradientOperator {
+    fn weights(self) -> [i32; 3] {
+        match self {
+            GradientOperator::Prewitt => [1, 1, 1],
+            GradientOperator::Sobel => [1, 2, 1],
+            GradientOperator::Scharr => [3, 10, 3],
+        }
+    }
+}
+
+/// Compute the horizontal and vertical derivatives of an image.
+///
+/// Returns the derivatives along the x axis and the y axis. They are positive where the image
+/// gets brighter to the right and to the bottom respectively, and are not normalized: a step
+/// from black to white gives a Sobel derivative of 4 × 255. Results outside of the range of `T`
+/// are clamped, so use a signed type such as `i16`, which holds the derivatives of all operators
+/// exactly, or `f32`. Pixels outside of the image are replaced by the nearest edge pixel.
+pub fn gradients<T>(
+    image: &GrayImage,
+    operator: GradientOperator,
+) -> (Derivative<T>, Derivative<T>)
+where
+    T: Primitive + 'static,
+{
+    let (width, height) = image.dimensions();
+    let mut dx = ImageBuffer::new(width, height);
+    let mut dy = ImageBuffer::new(width, height);
+    for y in 0..height {
+        for x in 0..width {
+            let (gx, gy) = gradient_at(image, operator, x, y);
+            dx.put_pixel(x, y, Luma([saturate(gx as f32)]));
+            dy.put_pixel(x, y, Luma([saturate(gy as f32)]));
+        }
+    }
+    (dx, dy)
+}
+
+/// Compute the magnitude of the gradient of an image, the strength of its edges.
+///
+/// The magnitude is the euclidean norm of the derivatives computed by [`gradients`], clamped to
+/// the range of `T`. The Sobel magnitude of an 8 bit image fits into `u16`.
+///
+/// [`gradients`]: fn.gradients.html
+pub fn gradient_magnitude<T>(
+    image: &GrayImage,
+    operator: GradientOperator,
+) -> ImageBuffer<Luma<T>, Vec<T>>
+where
+    T: Primitive + 'static,
+{
+    let (width, height) = image.dimensions();
+    ImageBuffer::from_fn(width, height, |x, y| {
+        let (gx, gy) = gradient_at(image, operator, x, y);
+        Luma([saturate((gx as f32).hypot(gy as f32))])
+    })
+}
+
+/// Compute the Laplacian of an image, the sum of its second derivatives.
+///
+/// The Laplacian is zero in flat regions and on linear ramps, and changes sign across edges. It
+/// is computed with the kernel of the four direct neighbours minus four times the center.
+/// Results outside of the range of `T` are clamped, so use a signed type such as `i16` or `f32`.
+/// Pixels outside of the image are replaced by the nearest edge pixel.
+pub fn laplacian<T>(image: &GrayImage) -> ImageBuffer<Luma<T>, Vec<T>>
+where
+    T: Primitive + 'static,
+{
+    let (width, height) = image.dimensions();
+    ImageBuffer::from_fn(width, height, |x, y| {
+        let at = |dx: i64, dy: i64| sample_clamped(image, x as i64 + dx, y as i64 + dy);
+        let sum = at(-1, 0) + at(1, 0) + at(0, -1) + at(0, 1) - 4 * at(0, 0);
+        Luma([saturate(sum as f32)])
+    })
+}
+
+/// Detect edges with the Canny edge detector.
+///
+/// The image is smoothed with a Gaussian blur of standard deviation 1.4 before computing the
+/// Sobel gradient. Edges are thinned to a width of one pixel by keeping only the pixels where
+/// the gradient magnitude is maximal across the edge. Pixels with a magnitude of at least
+/// `high_threshold` are edges, and so are pixels with a magnitude of at least `low_threshold`
+/// that are connected to them, so that edges are traced through weaker sections without picking
+/// up isolated noise.
+///
+/// The thresholds refer to the Sobel magnitude, see [`gradient_magnitude`]: a step from black to
+/// white has a magnitude of about 500 after blurring, and thresholds of 50 and 100 are a good
+/// start. Returns a binary image with edge pixels set to 255.
+///
+/// [`gradient_magnitude`]: fn.gradient_magnitude.html
+pub fn canny(image: &GrayImage, low_threshold: f32, high_threshold: f32) -> GrayImage {
+    const SIGMA: f32 = 1.4;
+
+    let (width, height) = image.dimensions();
+    let mut edges = GrayImage::new(width, height);
+    if width == 0 || height == 0 {
+        return edges;
+    }
+
+    let blurred = blur(image, SIGMA);
+    let (w, h) = (width as usize, height as usize);
+    let derivatives: Vec<(f32, f32)> = (0..height)
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .map(|(x, y)| {
+            let (gx, gy) = gradient_at(&blurred, GradientOperator::Sobel, x, y);
+            (gx as f32, gy as f32)
+        })
+        .collect();
+    let magnitude: Vec<f32> = derivatives.iter().map(|&(gx, gy)| gx.hypot(gy)).collect();
+    let magnitude_at = |x: i64, y: i64| {
+        if x < 0 || y < 0 || x >= w as i64 || y >= h as i64 {
+            0.0
+        } else {
+            magnitude[y as usize * w + x as usize]
+        }
+    };
+
+    // Non-maximum suppression along the gradient direction, rounded to multiples of 45 degrees.
+    // Of two pixels with the same magnitude only one is kept, so that edges stay thin. Pixels
+    // that are not kept are no edge candidates at all, whatever the thresholds.
+    let mut thin = vec![None; w * h];
+    for (i, &(gx, gy)) in derivatives.iter().enumerate() {
+        let m = magnitude[i];
+        if m == 0.0 {
+            continue;
+        }
+
+        let mut angle = gy.atan2(gx).to_degrees();
+        if angle < 0.0 {
+            angle += 180.0;
+        }
+        let (dx, dy) = if angle < 22.5 || angle >= 157.5 {
+            (1, 0)
+        } else if angle < 67.5 {
+            (1, 1)
+        } else if angle < 112.5 {
+            (0, 1)
+        } else {
+            (-1, 1)
+        };
+
+        let (x, y) = ((i % w) as i64, (i / w) as i64);
+        if m >= magnitude_at(x + dx, y + dy) && m > magnitude_at(x - dx, y - dy) {
+            thin[i] = Some(m);
+        }
+    }
+
+    // Hysteresis, following weak edges from strong ones.
+    let mut stack: Vec<usize> = Vec::new();
+    for (i, &m) in thin.iter().enumerate() {
+        let strong = m.map_or(false, |m| m >= high_threshold && m >= low_threshold);
+        if !strong || edges.as_raw()[i] != 0 {
+            continue;
+        }
+
+        stack.push(i);
+        while let Some(j) = stack.pop() {
+            let (x, y) = (j % w, j / w);
+            if edges.get_pixel(x as u32, y as u32)[0] != 0 {
+                continue;
+            }
+            edges.put_pixel(x as u32, y as u32, Luma([255]));
+
+            for ny in y.saturating_sub(1)..(y + 2).min(h) {
+                for nx in x.saturating_sub(1)..(x + 2).min(w) {
+                    let k = ny * w + nx;
+                    let weak = thin[k].map_or(false, |m| m >= low_threshold);
+                    if weak && edges.as_raw()[k] == 0 {
+                        stack.push(k);
+                    }
+                }
+            }
+        }
+    }
+
+    edges
+}
+
+/// The derivatives along the x and y axis at a pixel.
+fn gradient_at(image: &GrayImage, operator: GradientOperator, x: u32, y: u32) -> (i32, i32) {
+    let at = |dx: i64, dy: i64| sample_clamped(image, x as i64 + dx, y as i64 + dy);
+    let (mut gx, mut gy) = (0, 0);
+    for (i, &weight) in operator.weights().iter().enumerate() {
+        let offset = i as i64 - 1;
+        gx += weight * (at(1, offset) - at(-1, offset));
+        gy += weight * (at(offset, 1) - at(offset, -1));
+    }
+    (gx, gy)
+}
+
+fn sample_clamped(image: &GrayImage, x: i64, y: i64) -> i32 {
+    let (width, height) = image.dimensions();
+    let x = clamp(x, 0, width as i64 - 1) as u32;
+    let y = clamp(y, 0, height as i64 - 1) as u32;
+    image.get_pixel(x, y)[0] as i32
+}
+
+/// Convert a value to a sample type, rounding it for integers and clamping it to the range.
+fn saturate<T: Primitive>(value: f32) -> T {
+    let min: f32 = NumCast::from(T::min_value()).unwrap();
+    let max: f32 = NumCast::from(T::max_value()).unwrap();
+    NumCast::from(FloatNearest(clamp(value, min, max))).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cmp;
+
+    use super::{canny, gradient_magnitude, gradients, laplacian, GradientOperator};
+    use crate::{GrayImage, ImageBuffer, Luma};
+
+    /// A vertical step from 0 to 100 between the columns 4 and 5.
+    fn step() -> GrayImage {
+        GrayImage::from_fn(10, 6, |x, _| Luma([if x < 5 { 0 } else { 100 }]))
+    }
+
+    #[test]
+    fn test_gradients() {
+        let (dx, dy) = gradients::<i16>(&step(), GradientOperator::Sobel);
+        for (x, _, pixel) in dx.enumerate_pixels() {
+            assert_eq!(pixel[0], if x == 4 || x == 5 { 400 } else { 0 });
+        }
+        assert!(dy.pixels().all(|p| p[0] == 0));
+
+        let (dx, _) = gradients::<f32>(&step(), GradientOperator::Scharr);
+        assert_eq!(dx.get_pixel(4, 3)[0], 1600.0);
+        let (dx, _) = gradients::<i16>(&step(), GradientOperator::Prewitt);
+        assert_eq!(dx.get_pixel(5, 0)[0], 300);
+
+        // The transposed step only has a vertical derivative, negative when getting darker.
+        let image = GrayImage::from_fn(6, 10, |_, y| Luma([if y < 5 { 100 } else { 0 }]));
+        let (dx, dy) = gradients::<i16>(&image, GradientOperator::Sobel);
+        assert!(dx.pixels().all(|p| p[0] == 0));
+        assert_eq!(dy.get_pixel(2, 5)[0], -400);
+        // Unsigned types clamp negative derivatives.
+        let (_, dy) = gradients::<u8>(&image, GradientOperator::Sobel);
+        assert!(dy.pixels().all(|p| p[0] == 0));
+    }
+
+    #[test]
+    fn test_gradient_magnitude() {
+        let magnitude = gradient_magnitude::<f32>(&step(), GradientOperator::Sobel);
+        assert_eq!(magnitude.get_pixel(5, 2)[0], 400.0);
+
+        // A diagonal ramp has equal derivatives.
+        let ramp = GrayImage::from_fn(8, 8, |x, y| Luma([(x + y) as u8 * 10]));
+        let magnitude = gradient_magnitude::<u16>(&ramp, GradientOperator::Sobel);
+        assert_eq!(magnitude.get_pixel(3, 4)[0], 113);
+        let magnitude = gradient_magnitude::<u8>(&step(), GradientOperator::Scharr);
+        assert_eq!(magnitude.get_pixel(5, 2)[0], 255);
+    }
+
+    #[test]
+    fn test_laplacian() {
+        let mut image = GrayImage::new(5, 5);
+        image.put_pixel(2, 2, Luma([10]));
+        let result = laplacian::<i16>(&image);
+        assert_eq!(result.get_pixel(2, 2)[0], -40);
+        assert_eq!(result.get_pixel(1, 2)[0], 10);
+        assert_eq!(result.get_pixel(1, 1)[0], 0);
+
+        let ramp: GrayImage = ImageBuffer::from_fn(6, 3, |x, _| Luma([x as u8 * 20]));
+        assert_eq!(laplacian::<f32>(&ramp).get_pixel(3, 1)[0], 0.0);
+    }
+
+    #[test]
+    fn test_canny() {
+        // A bright square covering the pixels from 10 to 29.
+        let square = GrayImage::from_fn(40, 40, |x, y| {
+            let inside = x >= 10 && x < 30 && y >= 10 && y < 30;
+            Luma([if inside { 255 } else { 0 }])
+        });
+        let edges = canny(&square, 50.0, 100.0);
+        let near_border = |v: u32| (v >= 8 && v <= 11) || (v >= 28 && v <= 31);
+        let near_square = |v: u32| v >= 8 && v <= 31;
+        for (x, y, pixel) in edges.enumerate_pixels() {
+            if pixel[0] != 0 {
+                assert_eq!(pixel[0], 255);
+                let on_edge = (near_border(x) && near_square(y)) || (near_border(y) && near_square(x));
+                assert!(on_edge, "edge at ({}, {})", x, y);
+            }
+        }
+        // The sides are closed and one pixel wide.
+        for y in 12..28 {
+            let left = (8..12).filter(|&x| edges.get_pixel(x, y)[0] != 0).count();
+            assert_eq!(left, 1, "row {}", y);
+        }
+
+        let flat = GrayImage::from_pixel(20, 20, Luma([90]));
+        assert!(canny(&flat, 0.0, 0.0).pixels().all(|p| p[0] == 0));
+        assert_eq!(canny(&GrayImage::new(0, 4), 50.0, 100.0).dimensions(), (0, 4));
+    }
+
+    #[test]
+    fn test_canny_hysteresis() {
+        // A vertical step around 128 with a contrast changing from top to bottom.
+        let step = |contrast: &dyn Fn(u32) -> u32| {
+            GrayImage::from_fn(20, 40, |x, y| {
+                let half = contrast(y) / 2;
+                Luma([(if x < 10 { 128 - half } else { 128 + half }) as u8])
+            })
+        };
+        let has_edge = |edges: &GrayImage, y: u32| (8..12).any(|x| edges.get_pixel(x, y)[0] != 0);
+
+        // A weak step is not detected on its own.
+        let weak = canny(&step(&|_| 40), 40.0, 200.0);
+        assert!(weak.pixels().all(|p| p[0] == 0));
+        let weak = canny(&step(&|_| 40), 40.0, 60.0);
+        assert!((0..40).all(|y| has_edge(&weak, y)));
+
+        // But it is where it continues a strong one.
+        let fading = |y: u32| {
+            if y < 8 {
+                255
+            } else {
+                cmp::max(40, 255u32.saturating_sub((y - 8) * 27))
+            }
+        };
+        let edges = canny(&step(&fading), 40.0, 200.0);
+        assert!((0..40).all(|y| has_edge(&edges, y)));
+    }
+}
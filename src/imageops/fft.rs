@@ -0,0 +1,153 @@
+//! Fast Fourier transforms of power of two sizes, used by the frequency domain filters.
+
+use std::f64::consts::PI;
+use std::ops::{Add, Mul, Sub};
+
+/// A complex number.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) struct Complex {
+    pub(crate) re: f64,
+    pub(crate) im: f64,
+}
+
+impl Complex {
+    pub(crate) fn new(re: f64, im: f64) -> Self {
+        Complex { re, im }
+    }
+
+    /// The complex number of magnitude one with the given argument.
+    pub(crate) fn from_angle(angle: f64) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        Complex::new(cos, sin)
+    }
+
+    pub(crate) fn conj(self) -> Self {
+        Complex::new(self.re, -self.im)
+    }
+
+    pub(crate) fn norm(self) -> f64 {
+        self.re.hypot(self.im)
+    }
+
+    pub(crate) fn scale(self, factor: f64) -> Self {
+        Complex::new(self.re * factor, self.im * factor)
+    }
+}
+
+impl Add for Complex {
+    type Output = Complex;
+
+    fn add(self, other: Complex) -> Complex {
+        Complex::new(self.re + other.re, self.im + other.im)
+    }
+}
+
+impl Sub for Complex {
+    type Output = Complex;
+
+    fn sub(self, other: Complex) -> Complex {
+        Complex::new(self.re - other.re, self.im - other.im)
+    }
+}
+
+impl Mul for Complex {
+    type Output = Complex;
+
+    fn mul(self, other: Complex) -> Complex {
+        Complex::new(
+            self.re * other.re - self.im * other.im,
+            self.re * other.im + self.im * other.re,
+        )
+    }
+}
+
+/// Transform `data` in place with the iterative radix-2 Cooley-Tukey algorithm.
+///
+/// The length of `data` must be a power of two. The inverse transform includes the normalization
+/// by the length, so that a forward and an inverse transform restore the input.
+pub(crate) fn fft(data: &mut [Complex], inverse: bool) {
+    let n = data.len();
+    debug_assert!(n.is_power_of_two());
+
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            data.swap(i, j);
+        }
+    }
+
+    let sign = if inverse { 1.0 } else { -1.0 };
+    let mut len = 2;
+    while len <= n {
+        let half = len / 2;
+        let twiddles: Vec<Complex> = (0..half)
+            .map(|k| Complex::from_angle(sign * 2.0 * PI * k as f64 / len as f64))
+            .collect();
+        for start in (0..n).step_by(len) {
+            for (k, &w) in twiddles.iter().enumerate() {
+                let u = data[start + k];
+                let v = data[start + k + half] * w;
+                data[start + k] = u + v;
+                data[start + k + half] = u - v;
+            }
+        }
+        len <<= 1;
+    }
+
+    if inverse {
+        let factor = 1.0 / n as f64;
+        data.iter_mut().for_each(|c| *c = c.scale(factor));
+    }
+}
+
+/// Transform a row major two dimensional array in place, see `fft`.
+pub(crate) fn fft2d(data: &mut [Complex], width: usize, height: usize, inverse: bool) {
+    debug_assert_eq!(data.len(), width * height);
+    for row in data.chunks_mut(width) {
+        fft(row, inverse);
+    }
+
+    let mut column = vec![Complex::default(); height];
+    for x in 0..width {
+        for (y, c) in column.iter_mut().enumerate() {
+            *c = data[y * width + x];
+        }
+        fft(&mut column, inverse);
+        for (y, &c) in column.iter().enumerate() {
+            data[y * width + x] = c;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fft, fft2d, Complex};
+
+    #[test]
+    fn test_fft_of_impulse_and_roundtrip() {
+        let mut data = vec![Complex::default(); 8];
+        data[0] = Complex::new(1.0, 0.0);
+        fft(&mut data, false);
+        assert!(data
+            .iter()
+            .all(|c| (c.re - 1.0).abs() < 1e-12 && c.im.abs() < 1e-12));
+
+        let original: Vec<Complex> = (0..32)
+            .map(|i| Complex::new((i * 7 % 5) as f64, (i % 3) as f64))
+            .collect();
+        let mut data = original.clone();
+        fft2d(&mut data, 8, 4, false);
+        // The DC term is the sum of all values.
+        assert!((data[0].re - original.iter().map(|c| c.re).sum::<f64>()).abs() < 1e-9);
+        fft2d(&mut data, 8, 4, true);
+        for (a, b) in data.iter().zip(&original) {
+            assert!((*a - *b).norm() < 1e-9);
+        }
+    }
+}
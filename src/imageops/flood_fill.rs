@@ -0,0 +1,198 @@
+//! Filling of connected regions of similar color.
+
+use num_traits::NumCast;
+
+use crate::image::GenericImage;
+use crate::imageops::plane;
+use crate::traits::{Pixel, Primitive};
+
+/// Which neighbours of a pixel are connected to it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Connectivity {
+    /// The pixels above, below, left and right.
+    Four,
+    /// The four direct neighbours and the four diagonal ones.
+    Eight,
+}
+
+/// Fill the region around the pixel at `(x, y)` with `color`, like the paint bucket of an image
+/// editor.
+///
+/// The region contains all pixels connected to the starting pixel through pixels of similar
+/// color. Colors are similar if no channel differs from the starting pixel by more than
+/// `tolerance`, relative to the range of the samples: `0.0` fills only the exact color, `1.0`
+/// fills everything that is connected. The region is filled a row span at a time.
+///
+/// # Panics
+///
+/// Panics if `(x, y)` is outside of the image.
+///
+/// # Examples
+///
+/// ```
+/// use image::imageops::{flood_fill, Connectivity};
+/// use image::{Luma, GrayImage};
+///
+/// // A diagonal line, which separates the corners only for four-connectivity.
+/// let line = GrayImage::from_fn(4, 4, |x, y| Luma([if x == y { 255 } else { 0 }]));
+///
+/// let mut image = line.clone();
+/// flood_fill(&mut image, 3, 0, Luma([100]), 0.0, Connectivity::Four);
+/// assert_eq!(image.get_pixel(0, 3), &Luma([0]));
+///
+/// let mut image = line.clone();
+/// flood_fill(&mut image, 3, 0, Luma([100]), 0.0, Connectivity::Eight);
+/// assert_eq!(image.get_pixel(0, 3), &Luma([100]));
+/// ```
+pub fn flood_fill<I, P, S>(
+    image: &mut I,
+    x: u32,
+    y: u32,
+    color: P,
+    tolerance: f32,
+    connectivity: Connectivity,
+) where
+    I: GenericImage<Pixel = P>,
+    P: Pixel<Subpixel = S> + 'static,
+    S: Primitive + 'static,
+{
+    let (width, height) = image.dimensions();
+    assert!(x < width && y < height, "the starting pixel is outside of the image");
+
+    let seed = image.get_pixel(x, y);
+    let tolerance = tolerance * plane::nominal_max::<S>();
+    let matches = |pixel: P| {
+        pixel.channels().iter().zip(seed.channels()).all(|(&a, &b)| {
+            let a: f32 = NumCast::from(a).unwrap();
+            let b: f32 = NumCast::from(b).unwrap();
+            (a - b).abs() <= tolerance
+        })
+    };
+
+    let width_usize = width as usize;
+    let mut visited = vec![false; width_usize * height as usize];
+    let mut stack = vec![(x, y)];
+
+    while let Some((x, y)) = stack.pop() {
+        let row = y as usize * width_usize;
+        if visited[row + x as usize] || !matches(image.get_pixel(x, y)) {
+            continue;
+        }
+
+        // Extend the span to both sides and fill it.
+        let mut left = x;
+        while left > 0 && !visited[row + left as usize - 1] && matches(image.get_pixel(left - 1, y)) {
+            left -= 1;
+        }
+        let mut right = x;
+        while right + 1 < width
+            && !visited[row + right as usize + 1]
+            && matches(image.get_pixel(right + 1, y))
+        {
+            right += 1;
+        }
+        for span_x in left..=right {
+            visited[row + span_x as usize] = true;
+            image.put_pixel(span_x, y, color);
+        }
+
+        // Queue one pixel of every run of matching pixels next to the span in the rows above and
+        // below.
+        let (first, last) = match connectivity {
+            Connectivity::Four => (left, right),
+            Connectivity::Eight => (left.saturating_sub(1), (right + 1).min(width - 1)),
+        };
+        let neighbours = [y.checked_sub(1), Some(y + 1).filter(|&below| below < height)];
+        for &next_y in neighbours.iter().flatten() {
+            let next_row = next_y as usize * width_usize;
+            let mut in_run = false;
+            for next_x in first..=last {
+                let open = !visited[next_row + next_x as usize]
+                    && matches(image.get_pixel(next_x, next_y));
+                if open && !in_run {
+                    stack.push((next_x, next_y));
+                }
+                in_run = open;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{flood_fill, Connectivity};
+    use crate::{GrayImage, Luma, Rgba, RgbaImage};
+
+    fn from_rows(rows: &[&str]) -> GrayImage {
+        GrayImage::from_fn(rows[0].len() as u32, rows.len() as u32, |x, y| {
+            Luma([if rows[y as usize].as_bytes()[x as usize] == b'#' { 255 } else { 0 }])
+        })
+    }
+
+    fn to_rows(image: &GrayImage) -> Vec<String> {
+        image
+            .rows()
+            .map(|row| {
+                row.map(|p| match p[0] {
+                    255 => '#',
+                    0 => '.',
+                    _ => 'o',
+                })
+                .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_fills_enclosed_region() {
+        let rows = [
+            "#######",
+            "#..#..#",
+            "#..#.##",
+            "#.###.#",
+            "#....#.",
+            "######.",
+        ];
+
+        let mut image = from_rows(&rows);
+        flood_fill(&mut image, 1, 1, Luma([128]), 0.0, Connectivity::Four);
+        assert_eq!(
+            to_rows(&image),
+            ["#######", "#oo#..#", "#oo#.##", "#o###.#", "#oooo#.", "######."]
+        );
+
+        // With diagonals, the region leaks through the corners.
+        let mut image = from_rows(&rows);
+        flood_fill(&mut image, 1, 1, Luma([128]), 0.0, Connectivity::Eight);
+        assert_eq!(
+            to_rows(&image),
+            ["#######", "#oo#oo#", "#oo#o##", "#o###o#", "#oooo#o", "######o"]
+        );
+    }
+
+    #[test]
+    fn test_same_color() {
+        // Filling with the color of the region itself must terminate.
+        let mut image = GrayImage::from_pixel(50, 40, Luma([7]));
+        flood_fill(&mut image, 25, 20, Luma([7]), 0.5, Connectivity::Eight);
+        assert!(image.pixels().all(|&p| p == Luma([7])));
+    }
+
+    #[test]
+    fn test_tolerance() {
+        // A horizontal gradient, filled from the left edge.
+        let gradient = RgbaImage::from_fn(11, 3, |x, _| Rgba([(x * 25) as u8, 0, 0, 255]));
+        for &(tolerance, filled) in &[(0.0, 1), (0.1, 2), (0.5, 6), (1.0, 11)] {
+            let mut image = gradient.clone();
+            flood_fill(&mut image, 0, 1, Rgba([0, 0, 255, 255]), tolerance, Connectivity::Four);
+            let count = image.pixels().filter(|p| p[2] == 255).count();
+            assert_eq!(count, 3 * filled, "tolerance {}", tolerance);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_outside() {
+        flood_fill(&mut GrayImage::new(3, 3), 3, 0, Luma([1]), 0.0, Connectivity::Four);
+    }
+}
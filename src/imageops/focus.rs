@@ -0,0 +1,126 @@
+//! Merging of focus bracketed image stacks.
+
+use crate::image::GenericImageView;
+use crate::imageops::plane::{self, Plane};
+use crate::traits::{Pixel, Primitive};
+use crate::ImageBuffer;
+
+/// Merge a stack of aligned images, each focused at a different depth, into a single image that
+/// is in focus everywhere.
+///
+/// For every pixel, the image with the highest local sharpness is selected. Sharpness is measured
+/// as the energy of the Laplacian of the luminance, smoothed over a small neighbourhood. To hide
+/// the seams between regions taken from different images, the selection is blended with a
+/// laplacian pyramid: fine details are switched sharply while coarse structures are mixed over
+/// larger areas.
+///
+/// The images have to be aligned to each other already.
+///
+/// # Panics
+///
+/// Panics if `images` is empty or if the images do not all have the same dimensions.
+pub fn focus_stack<I, P, S>(images: &[I]) -> ImageBuffer<P, Vec<S>>
+where
+    I: GenericImageView<Pixel = P>,
+    P: Pixel<Subpixel = S> + 'static,
+    S: Primitive + 'static,
+{
+    assert!(!images.is_empty(), "focus stacking requires at least one image");
+    let (width, height) = images[0].dimensions();
+    assert!(
+        images.iter().all(|image| image.dimensions() == (width, height)),
+        "all images of a focus stack must have the same dimensions"
+    );
+
+    let planes: Vec<Vec<Plane>> = images.iter().map(plane::split).collect();
+    let sharpness: Vec<Plane> = planes
+        .iter()
+        .map(|planes| {
+            let mut energy = plane::laplacian(&plane::luma(planes));
+            energy.iter_mut().for_each(|v| *v *= *v);
+            plane::gaussian_blur(&energy, 2.0)
+        })
+        .collect();
+
+    // Hard selection masks: every pixel belongs to exactly one image of the stack.
+    let mut masks = vec![Plane::new(width, height); images.len()];
+    for index in 0..(width as usize * height as usize) {
+        let mut best = 0;
+        for (k, energy) in sharpness.iter().enumerate() {
+            if energy.as_raw()[index] > sharpness[best].as_raw()[index] {
+                best = k;
+            }
+        }
+        let mask: &mut [f32] = &mut masks[best];
+        mask[index] = 1.0;
+    }
+
+    let levels = plane::pyramid_levels(width, height);
+    let weights: Vec<Vec<Plane>> = masks
+        .iter()
+        .map(|mask| plane::gaussian_pyramid(mask, levels))
+        .collect();
+
+    let channels = planes[0].len();
+    let merged: Vec<Plane> = (0..channels)
+        .map(|c| {
+            let mut blended: Vec<Plane> = weights[0]
+                .iter()
+                .map(|level| Plane::new(level.width(), level.height()))
+                .collect();
+
+            for (image, weights) in planes.iter().zip(&weights) {
+                let pyramid = plane::laplacian_pyramid(&image[c], levels);
+                for ((out, detail), weight) in blended.iter_mut().zip(&pyramid).zip(weights) {
+                    for ((o, d), w) in out.iter_mut().zip(detail.iter()).zip(weight.iter()) {
+                        *o += d * w;
+                    }
+                }
+            }
+
+            plane::collapse(&blended)
+        })
+        .collect();
+
+    plane::merge(&merged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::focus_stack;
+    use crate::{GrayImage, Luma};
+
+    fn checkerboard(x: u32, y: u32) -> u8 {
+        if (x / 2 + y / 2) % 2 == 0 {
+            40
+        } else {
+            220
+        }
+    }
+
+    #[test]
+    fn test_focus_stack_selects_sharp_regions() {
+        // Each image is sharp in one half and flat gray in the other.
+        let left = GrayImage::from_fn(64, 32, |x, y| {
+            Luma([if x < 32 { checkerboard(x, y) } else { 130 }])
+        });
+        let right = GrayImage::from_fn(64, 32, |x, y| {
+            Luma([if x >= 32 { checkerboard(x, y) } else { 130 }])
+        });
+
+        let merged = focus_stack(&[left, right]);
+        let expected = GrayImage::from_fn(64, 32, |x, y| Luma([checkerboard(x, y)]));
+
+        let mut total_error = 0u64;
+        for (a, b) in merged.pixels().zip(expected.pixels()) {
+            total_error += (i64::from(a[0]) - i64::from(b[0])).abs() as u64;
+        }
+        assert!(total_error / (64 * 32) < 10, "mean error {}", total_error / (64 * 32));
+    }
+
+    #[test]
+    fn test_focus_stack_single_image() {
+        let img = GrayImage::from_fn(20, 20, |x, y| Luma([(x * y) as u8]));
+        assert_eq!(focus_stack(std::slice::from_ref(&img)), img);
+    }
+}
@@ -0,0 +1,354 @@
+//! Histograms, and the exposure corrections based on them.
+
+use num_traits::NumCast;
+
+use crate::image::GenericImageView;
+use crate::imageops::plane;
+use crate::imageops::sample::FloatNearest;
+use crate::traits::{Pixel, Primitive};
+use crate::utils::clamp;
+use crate::ImageBuffer;
+
+/// Count the samples of every channel of an image in 256 bins.
+///
+/// Returns one histogram per channel, including alpha. Samples of types wider than `u8` are
+/// scaled to the bins, so that a `u16` sample of 65535 or a float sample of `1.0` lands in the
+/// last bin. Float samples outside of `0.0..=1.0` are counted in the first or last bin.
+pub fn histogram<I, P, S>(image: &I) -> Vec<[u64; 256]>
+where
+    I: GenericImageView<Pixel = P>,
+    P: Pixel<Subpixel = S> + 'static,
+    S: Primitive + 'static,
+{
+    let max = plane::nominal_max::<S>();
+    let mut bins = vec![[0u64; 256]; <usize as From<u8>>::from(P::CHANNEL_COUNT)];
+    for (_, _, pixel) in image.pixels() {
+        for (counts, &sample) in bins.iter_mut().zip(pixel.channels()) {
+            counts[bin(sample, max)] += 1;
+        }
+    }
+    bins
+}
+
+/// Spread the samples of every color channel evenly over the value range.
+///
+/// Each sample is replaced by the share of samples of its channel that are at most as bright,
+/// which maximizes the contrast of images using only part of the range. The channels are
+/// equalized separately, which may shift the colors of color images. Alpha is not changed.
+/// Samples of types wider than `u8` are equalized at the resolution of the [`histogram`].
+///
+/// [`histogram`]: fn.histogram.html
+pub fn equalize_histogram<P, S>(image: &mut ImageBuffer<P, Vec<S>>)
+where
+    P: Pixel<Subpixel = S> + 'static,
+    S: Primitive + 'static,
+{
+    let max = plane::nominal_max::<S>();
+    let alpha = plane::alpha_channel::<P>();
+
+    for (channel, counts) in histogram(&*image).iter().enumerate() {
+        if Some(channel) == alpha {
+            continue;
+        }
+
+        let cdf = cumulative(counts);
+        let total = cdf[255];
+        // The number of samples in the darkest bin, which is mapped to black.
+        let first = cdf.iter().cloned().find(|&c| c > 0).unwrap_or(0);
+        if total == first {
+            continue;
+        }
+
+        let levels: Vec<S> = cdf
+            .iter()
+            .map(|&c| {
+                let share = c.saturating_sub(first) as f64 / (total - first) as f64;
+                NumCast::from(FloatNearest(share as f32 * max)).unwrap()
+            })
+            .collect();
+        for pixel in image.pixels_mut() {
+            let sample = &mut pixel.channels_mut()[channel];
+            *sample = levels[bin(*sample, max)];
+        }
+    }
+}
+
+/// Stretch the samples of every color channel linearly to cover the value range.
+///
+/// `lower_pct` and `upper_pct` are percentiles between 0 and 100: the darkest `lower_pct`
+/// percent of the samples of a channel become black and the brightest `100 - upper_pct`
+/// percent become white, so that a few outliers do not limit the correction. Values such as
+/// `1.0` and `99.0` work well for photographs, while `0.0` and `100.0` stretch from the darkest
+/// to the brightest sample. The channels are stretched separately, which also removes color
+/// casts. Alpha is not changed.
+pub fn stretch_contrast<P, S>(image: &mut ImageBuffer<P, Vec<S>>, lower_pct: f32, upper_pct: f32)
+where
+    P: Pixel<Subpixel = S> + 'static,
+    S: Primitive + 'static,
+{
+    let max = plane::nominal_max::<S>();
+    let alpha = plane::alpha_channel::<P>();
+    let lower_pct = clamp(lower_pct as f64, 0.0, 100.0);
+    let upper_pct = clamp(upper_pct as f64, 0.0, 100.0);
+
+    for (channel, counts) in histogram(&*image).iter().enumerate() {
+        if Some(channel) == alpha {
+            continue;
+        }
+
+        let cdf = cumulative(counts);
+        let total = cdf[255] as f64;
+        let low = cdf.iter().position(|&c| c as f64 > total * lower_pct / 100.0);
+        let high = cdf.iter().position(|&c| c as f64 >= total * upper_pct / 100.0);
+        let (low, high) = match (low, high) {
+            (Some(low), Some(high)) if low < high => (low as f32, high as f32),
+            _ => continue,
+        };
+
+        let (low, high) = (low / 255.0 * max, high / 255.0 * max);
+        for pixel in image.pixels_mut() {
+            let sample = &mut pixel.channels_mut()[channel];
+            let value: f32 = NumCast::from(*sample).unwrap();
+            let stretched = clamp((value - low) / (high - low) * max, 0.0, max);
+            *sample = NumCast::from(FloatNearest(stretched)).unwrap();
+        }
+    }
+}
+
+/// Equalize the histogram of every color channel locally, limiting the gain in contrast.
+///
+/// This is contrast limited adaptive histogram equalization (CLAHE). The image is divided into
+/// `grid.0` × `grid.1` tiles, each tile gets its own equalization and every sample is mapped by
+/// interpolating between the mappings of the four nearest tiles, so that details in both dark and
+/// bright regions become visible. Before equalizing, the histogram bins of a tile are cut off at
+/// `clip_limit` times the mean count per bin and the excess is spread over all bins. This limits
+/// the slope of the mapping and keeps noise in flat regions from being amplified: values between
+/// 2 and 4 are common, while `std::f32::INFINITY` gives unlimited adaptive equalization. Grids
+/// of 8 × 8 tiles work well for most images, the grid is reduced to at most one tile per pixel.
+///
+/// As with [`equalize_histogram`], the channels are processed separately and alpha is not
+/// changed.
+///
+/// [`equalize_histogram`]: fn.equalize_histogram.html
+pub fn clahe<P, S>(image: &mut ImageBuffer<P, Vec<S>>, grid: (u32, u32), clip_limit: f32)
+where
+    P: Pixel<Subpixel = S> + 'static,
+    S: Primitive + 'static,
+{
+    let (width, height) = image.dimensions();
+    if width == 0 || height == 0 {
+        return;
+    }
+    let (tiles_x, tiles_y) = (clamp(grid.0, 1, width), clamp(grid.1, 1, height));
+    let max = plane::nominal_max::<S>();
+    let alpha = plane::alpha_channel::<P>();
+
+    let tile_width = width as f32 / tiles_x as f32;
+    let tile_height = height as f32 / tiles_y as f32;
+    let columns: Vec<_> = (0..width)
+        .map(|x| neighbour_tiles(x, tile_width, tiles_x))
+        .collect();
+
+    let channels = <usize as From<u8>>::from(P::CHANNEL_COUNT);
+    for channel in 0..channels {
+        if Some(channel) == alpha {
+            continue;
+        }
+
+        let bins: Vec<usize> = image
+            .pixels()
+            .map(|pixel| bin(pixel.channels()[channel], max))
+            .collect();
+
+        // The mapping of every tile, row by row.
+        let mut tiles = Vec::with_capacity(tiles_x as usize * tiles_y as usize);
+        for ty in 0..tiles_y {
+            let (y0, y1) = tile_span(ty, tiles_y, height);
+            for tx in 0..tiles_x {
+                let (x0, x1) = tile_span(tx, tiles_x, width);
+                let mut counts = [0u64; 256];
+                for y in y0..y1 {
+                    let row = &bins[y as usize * width as usize..][..width as usize];
+                    for &b in &row[x0 as usize..x1 as usize] {
+                        counts[b] += 1;
+                    }
+                }
+                tiles.push(clipped_levels(&mut counts, clip_limit, max));
+            }
+        }
+
+        for (x, y, pixel) in image.enumerate_pixels_mut() {
+            let b = bins[y as usize * width as usize + x as usize];
+            let at = |tx: usize, ty: usize| tiles[ty * tiles_x as usize + tx][b];
+            let (x0, x1, wx) = columns[x as usize];
+            let (y0, y1, wy) = neighbour_tiles(y, tile_height, tiles_y);
+
+            let top = at(x0, y0) + (at(x1, y0) - at(x0, y0)) * wx;
+            let bottom = at(x0, y1) + (at(x1, y1) - at(x0, y1)) * wx;
+            let value = clamp(top + (bottom - top) * wy, 0.0, max);
+            pixel.channels_mut()[channel] = NumCast::from(FloatNearest(value)).unwrap();
+        }
+    }
+}
+
+/// The bin of a sample in a histogram of 256 bins.
+fn bin<S: Primitive>(sample: S, max: f32) -> usize {
+    let value: f32 = NumCast::from(sample).unwrap();
+    clamp((value / max * 255.0).round(), 0.0, 255.0) as usize
+}
+
+/// The number of samples in every bin or any bin before it.
+fn cumulative(counts: &[u64; 256]) -> [u64; 256] {
+    let mut cdf = [0; 256];
+    let mut sum = 0;
+    for (total, &count) in cdf.iter_mut().zip(counts.iter()) {
+        sum += count;
+        *total = sum;
+    }
+    cdf
+}
+
+/// The first and one past the last position of a tile on an axis of length `len`.
+fn tile_span(index: u32, tiles: u32, len: u32) -> (u32, u32) {
+    let at = |i: u32| (i as u64 * len as u64 / tiles as u64) as u32;
+    (at(index), at(index + 1))
+}
+
+/// The two tiles with their centres nearest to a position, and the weight of the second one.
+fn neighbour_tiles(position: u32, tile_size: f32, tiles: u32) -> (usize, usize, f32) {
+    let last = (tiles - 1) as f32;
+    let t = (position as f32 + 0.5) / tile_size - 0.5;
+    let first = clamp(t.floor(), 0.0, last);
+    let second = (first + 1.0).min(last);
+    (first as usize, second as usize, clamp(t - first, 0.0, 1.0))
+}
+
+/// Clip the histogram of a tile and compute the equalizing mapping of every bin.
+fn clipped_levels(counts: &mut [u64; 256], clip_limit: f32, max: f32) -> [f32; 256] {
+    let total: u64 = counts.iter().sum();
+    let limit = (clip_limit as f64 * total as f64 / 256.0).max(1.0);
+    if limit < total as f64 {
+        let limit = limit as u64;
+        let mut excess = 0;
+        for count in counts.iter_mut() {
+            if *count > limit {
+                excess += *count - limit;
+                *count = limit;
+            }
+        }
+
+        for count in counts.iter_mut() {
+            *count += excess / 256;
+        }
+        let remainder = (excess % 256) as usize;
+        for i in 0..remainder {
+            counts[i * 256 / remainder] += 1;
+        }
+    }
+
+    let mut levels = [0.0; 256];
+    for (level, &c) in levels.iter_mut().zip(cumulative(counts).iter()) {
+        *level = c as f32 / total as f32 * max;
+    }
+    levels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{clahe, equalize_histogram, histogram, stretch_contrast};
+    use crate::{GrayImage, ImageBuffer, Luma, LumaA, Rgb, RgbImage};
+
+    #[test]
+    fn test_histogram() {
+        let image = RgbImage::from_fn(4, 2, |x, y| Rgb([x as u8, 7, 200 + y as u8]));
+        let bins = histogram(&image);
+        assert_eq!(bins.len(), 3);
+        assert_eq!(&bins[0][..5], &[2, 2, 2, 2, 0][..]);
+        assert_eq!(bins[1][7], 8);
+        assert_eq!((bins[2][200], bins[2][201]), (4, 4));
+        assert!(bins.iter().all(|b| b.iter().sum::<u64>() == 8));
+
+        let wide: ImageBuffer<Luma<u16>, Vec<u16>> =
+            ImageBuffer::from_raw(3, 1, vec![0, 2570, 65535]).unwrap();
+        let bins = histogram(&wide);
+        assert_eq!((bins[0][0], bins[0][10], bins[0][255]), (1, 1, 1));
+    }
+
+    #[test]
+    fn test_equalize_histogram() {
+        let mut image = GrayImage::from_fn(8, 8, |x, _| Luma([100 + (x / 2) as u8]));
+        equalize_histogram(&mut image);
+        for (x, _, pixel) in image.enumerate_pixels() {
+            assert_eq!(pixel[0], [0, 85, 170, 255][x as usize / 2]);
+        }
+
+        // Images of a single level and alpha channels are not changed.
+        let mut flat: ImageBuffer<LumaA<u8>, Vec<u8>> =
+            ImageBuffer::from_fn(3, 3, |x, _| LumaA([40, 10 * x as u8]));
+        let expected = flat.clone();
+        equalize_histogram(&mut flat);
+        assert_eq!(flat, expected);
+    }
+
+    #[test]
+    fn test_stretch_contrast() {
+        let mut image = GrayImage::from_fn(101, 1, |x, _| Luma([50 + x as u8]));
+        stretch_contrast(&mut image, 0.0, 100.0);
+        assert_eq!(image.get_pixel(0, 0)[0], 0);
+        assert_eq!(image.get_pixel(50, 0)[0], 128);
+        assert_eq!(image.get_pixel(100, 0)[0], 255);
+
+        // A few outliers are ignored.
+        let mut image = GrayImage::from_fn(100, 1, |x, _| {
+            Luma([match x {
+                0 => 0,
+                99 => 255,
+                _ => 100 + (x % 2) as u8 * 20,
+            }])
+        });
+        stretch_contrast(&mut image, 2.0, 98.0);
+        assert_eq!(image.get_pixel(1, 0)[0], 255);
+        assert_eq!(image.get_pixel(2, 0)[0], 0);
+        assert_eq!(image.get_pixel(99, 0)[0], 255);
+    }
+
+    #[test]
+    fn test_clahe() {
+        // Two regions of little contrast.
+        let image = GrayImage::from_fn(64, 32, |x, y| {
+            Luma([if x < 32 {
+                100 + (x * y * 7 % 11) as u8
+            } else {
+                150 + ((x + y * 3) % 11) as u8
+            }])
+        });
+        // The contrast of the left region.
+        let range = |image: &GrayImage| {
+            let left = image.enumerate_pixels().filter(|&(x, _, _)| x < 32);
+            let (min, max) = left.fold((255, 0), |(lo, hi), (_, _, p)| (lo.min(p[0]), hi.max(p[0])));
+            max - min
+        };
+
+        // Stronger clip limits give more contrast.
+        let mut ranges = Vec::new();
+        for &clip_limit in &[2.0, 4.0, std::f32::INFINITY] {
+            let mut equalized = image.clone();
+            clahe(&mut equalized, (4, 2), clip_limit);
+            ranges.push(range(&equalized));
+        }
+        assert!(range(&image) < ranges[0], "{:?}", ranges);
+        assert!(ranges[0] < ranges[1] && ranges[1] < ranges[2], "{:?}", ranges);
+
+        // Flat regions are barely changed.
+        let mut flat = GrayImage::from_pixel(64, 64, Luma([128]));
+        clahe(&mut flat, (2, 2), 2.0);
+        assert!(flat.pixels().all(|p| p[0] >= 126 && p[0] <= 130));
+
+        // Grids finer than the image, alpha and empty images.
+        let mut small: ImageBuffer<LumaA<u8>, Vec<u8>> =
+            ImageBuffer::from_fn(3, 2, |x, y| LumaA([(x * 50 + y) as u8, 77]));
+        clahe(&mut small, (8, 8), 3.0);
+        assert!(small.pixels().all(|p| p[1] == 77));
+        clahe(&mut GrayImage::new(0, 5), (8, 8), 3.0);
+    }
+}
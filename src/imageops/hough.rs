@@ -0,0 +1,284 @@
+//! Detection of lines and circles with the Hough transform.
+
+use std::cmp::Reverse;
+use std::f32::consts::PI;
+
+use crate::GrayImage;
+
+/// Number of angle bins of the line accumulator, covering half a turn in steps of half a degree.
+const ANGLE_STEPS: usize = 360;
+
+/// A straight line in normal form, the set of points with `x * cos(theta) + y * sin(theta) = rho`.
+///
+/// `theta` is given in radians in the range `0.0..PI`, so a horizontal line has a `theta` of
+/// `PI / 2` and the skew angle of a document can be read from the dominant text lines as
+/// `theta - PI / 2`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Line {
+    /// The signed distance of the line from the origin, in pixels.
+    pub rho: f32,
+    /// The angle between the x axis and the normal of the line, in radians.
+    pub theta: f32,
+    /// The number of edge pixels lying on the line.
+    pub votes: u32,
+}
+
+/// A circle found in an image.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Circle {
+    /// The horizontal position of the center.
+    pub x: u32,
+    /// The vertical position of the center.
+    pub y: u32,
+    /// The radius in pixels.
+    pub radius: u32,
+    /// The number of edge pixels lying on the circle.
+    pub votes: u32,
+}
+
+/// Find straight lines in a binary edge image.
+///
+/// Every non-zero pixel of `edges` is treated as an edge point, such as the output of an edge
+/// detector or a thresholding operation. Lines are returned if at least `min_votes` edge pixels
+/// lie on them and they are the strongest line within `suppression_radius` bins of the
+/// accumulator, where one bin is half a degree in angle and one pixel in distance. The result is
+/// sorted with the strongest line first.
+pub fn detect_lines(edges: &GrayImage, min_votes: u32, suppression_radius: u32) -> Vec<Line> {
+    let (width, height) = edges.dimensions();
+    let max_rho = (f64::from(width).hypot(f64::from(height))).ceil() as i64;
+    let rho_bins = (2 * max_rho + 1) as usize;
+
+    let trig: Vec<(f32, f32)> = (0..ANGLE_STEPS)
+        .map(|i| angle(i).sin_cos())
+        .map(|(sin, cos)| (cos, sin))
+        .collect();
+
+    let mut accumulator = vec![0u32; ANGLE_STEPS * rho_bins];
+    for (x, y, pixel) in edges.enumerate_pixels() {
+        if pixel[0] == 0 {
+            continue;
+        }
+        for (a, &(cos, sin)) in trig.iter().enumerate() {
+            let rho = (x as f32 * cos + y as f32 * sin).round() as i64;
+            accumulator[a * rho_bins + (rho + max_rho) as usize] += 1;
+        }
+    }
+
+    let mut lines = Vec::new();
+    for (index, &votes) in accumulator.iter().enumerate() {
+        let (a, r) = (index / rho_bins, index % rho_bins);
+        if votes >= min_votes.max(1)
+            && is_line_maximum(&accumulator, rho_bins, (a, r), suppression_radius as usize)
+        {
+            lines.push(Line {
+                rho: (r as i64 - max_rho) as f32,
+                theta: angle(a),
+                votes,
+            });
+        }
+    }
+
+    lines.sort_by_key(|line| Reverse(line.votes));
+    lines
+}
+
+/// Find circles with a radius between `min_radius` and `max_radius` in a binary edge image.
+///
+/// Every non-zero pixel of `edges` is treated as an edge point. A circle is returned if at least
+/// the fraction `min_coverage`, between `0.0` and `1.0`, of its circumference is covered by edge
+/// pixels and no stronger circle has a center within `suppression_radius` pixels and a similar
+/// radius. The result is sorted with the best covered circle first.
+pub fn detect_circles(
+    edges: &GrayImage,
+    min_radius: u32,
+    max_radius: u32,
+    min_coverage: f32,
+    suppression_radius: u32,
+) -> Vec<Circle> {
+    let (width, height) = edges.dimensions();
+    let (w, h) = (width as usize, height as usize);
+    let points: Vec<(i64, i64)> = edges
+        .enumerate_pixels()
+        .filter(|&(_, _, p)| p[0] != 0)
+        .map(|(x, y, _)| (i64::from(x), i64::from(y)))
+        .collect();
+
+    let min_radius = min_radius.max(1);
+    let mut candidates = Vec::new();
+    for radius in min_radius..=max_radius {
+        let offsets = circle_offsets(radius);
+        let mut accumulator = vec![0u32; w * h];
+        for &(px, py) in &points {
+            for &(dx, dy) in &offsets {
+                let (cx, cy) = (px - dx, py - dy);
+                if cx >= 0 && cy >= 0 && (cx as usize) < w && (cy as usize) < h {
+                    accumulator[cy as usize * w + cx as usize] += 1;
+                }
+            }
+        }
+
+        let min_votes = ((min_coverage * offsets.len() as f32).ceil() as u32).max(1);
+        for (index, &votes) in accumulator.iter().enumerate() {
+            if votes >= min_votes
+                && is_local_maximum(&accumulator, (h, w), (index / w, index % w), 1)
+            {
+                let coverage = votes as f32 / offsets.len() as f32;
+                let circle = Circle {
+                    x: (index % w) as u32,
+                    y: (index / w) as u32,
+                    radius,
+                    votes,
+                };
+                candidates.push((coverage, circle));
+            }
+        }
+    }
+
+    candidates.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+    let mut circles: Vec<Circle> = Vec::new();
+    for (_, candidate) in candidates {
+        let suppressed = circles.iter().any(|c| {
+            let dx = i64::from(c.x) - i64::from(candidate.x);
+            let dy = i64::from(c.y) - i64::from(candidate.y);
+            let dr = i64::from(c.radius) - i64::from(candidate.radius);
+            let reach = i64::from(suppression_radius);
+            dx * dx + dy * dy <= reach * reach && dr.abs() <= reach.max(1)
+        });
+        if !suppressed {
+            circles.push(candidate);
+        }
+    }
+
+    circles
+}
+
+fn angle(step: usize) -> f32 {
+    step as f32 * PI / ANGLE_STEPS as f32
+}
+
+/// The distinct pixel offsets of a rasterized circle around the origin.
+fn circle_offsets(radius: u32) -> Vec<(i64, i64)> {
+    let r = radius as f32;
+    let steps = (8.0 * r).ceil() as usize;
+    let mut offsets: Vec<(i64, i64)> = (0..steps)
+        .map(|i| {
+            let (sin, cos) = (2.0 * PI * i as f32 / steps as f32).sin_cos();
+            ((r * cos).round() as i64, (r * sin).round() as i64)
+        })
+        .collect();
+    offsets.sort();
+    offsets.dedup();
+    offsets
+}
+
+/// Whether a bin of the line accumulator is the largest one within `radius` bins.
+///
+/// The angle wraps around at half a turn, where the sign of the distance flips.
+fn is_line_maximum(data: &[u32], rho_bins: usize, (a, r): (usize, usize), radius: usize) -> bool {
+    let value = data[a * rho_bins + r];
+    let radius = radius as i64;
+    for da in -radius..=radius {
+        let mut na = a as i64 + da;
+        let mirror = na < 0 || na >= ANGLE_STEPS as i64;
+        if na < 0 {
+            na += ANGLE_STEPS as i64;
+        } else if na >= ANGLE_STEPS as i64 {
+            na -= ANGLE_STEPS as i64;
+        }
+
+        for dr in -radius..=radius {
+            let mut nr = r as i64 + dr;
+            if nr < 0 || nr >= rho_bins as i64 {
+                continue;
+            }
+            if mirror {
+                nr = rho_bins as i64 - 1 - nr;
+            }
+
+            let index = na as usize * rho_bins + nr as usize;
+            let other = data[index];
+            if other > value || (other == value && index < a * rho_bins + r) {
+                return false;
+            }
+        }
+    }
+    true
+}
+
+/// Whether the value at `(row, col)` is not exceeded by any value within `radius` rows and
+/// columns of it. Ties are resolved in favour of the first entry in memory order.
+fn is_local_maximum(
+    data: &[u32],
+    (rows, cols): (usize, usize),
+    (row, col): (usize, usize),
+    radius: usize,
+) -> bool {
+    let value = data[row * cols + col];
+    for r in row.saturating_sub(radius)..(row + radius + 1).min(rows) {
+        for c in col.saturating_sub(radius)..(col + radius + 1).min(cols) {
+            let other = data[r * cols + c];
+            if other > value || (other == value && (r, c) < (row, col)) {
+                return false;
+            }
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{circle_offsets, detect_circles, detect_lines};
+    use crate::{GrayImage, Luma};
+    use std::f32::consts::PI;
+
+    #[test]
+    fn test_detect_horizontal_and_vertical_lines() {
+        let mut img = GrayImage::new(50, 40);
+        for x in 0..50 {
+            img.put_pixel(x, 12, Luma([255]));
+        }
+        for y in 0..40 {
+            img.put_pixel(30, y, Luma([255]));
+        }
+
+        let lines = detect_lines(&img, 30, 5);
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].votes, 50);
+        assert!((lines[0].theta - PI / 2.0).abs() <= 1.0f32.to_radians());
+        assert_eq!(lines[0].rho, 12.0);
+        assert_eq!(lines[1].votes, 40);
+        assert!(lines[1].theta.abs() <= 1.0f32.to_radians());
+        assert_eq!(lines[1].rho, 30.0);
+    }
+
+    #[test]
+    fn test_detect_skewed_line() {
+        let mut img = GrayImage::new(100, 60);
+        let skew = 5.0f32.to_radians();
+        for x in 0..100 {
+            let y = 20.0 + x as f32 * skew.tan();
+            img.put_pixel(x, y.round() as u32, Luma([255]));
+        }
+
+        let lines = detect_lines(&img, 50, 10);
+        assert_eq!(lines.len(), 1);
+        assert!((lines[0].theta - PI / 2.0 - skew).abs() < 0.5f32.to_radians());
+    }
+
+    #[test]
+    fn test_detect_circle() {
+        let mut img = GrayImage::new(60, 60);
+        for (dx, dy) in circle_offsets(15) {
+            img.put_pixel((25 + dx) as u32, (32 + dy) as u32, Luma([255]));
+        }
+        img.put_pixel(3, 3, Luma([255]));
+
+        let circles = detect_circles(&img, 5, 25, 0.8, 5);
+        assert_eq!(circles.len(), 1);
+        assert_eq!(
+            (circles[0].x, circles[0].y, circles[0].radius),
+            (25, 32, 15)
+        );
+        assert!(detect_circles(&GrayImage::new(10, 10), 1, 5, 0.5, 2).is_empty());
+    }
+}
@@ -0,0 +1,189 @@
+//! Summed-area tables for sums over rectangles in constant time.
+
+use num_traits::NumCast;
+
+use crate::image::GenericImageView;
+use crate::math::Rect;
+use crate::traits::{Pixel, Primitive};
+
+/// The sums of the samples of an image over all rectangles starting at its top left corner,
+/// computed by [`integral_image`] or [`integral_squared_image`].
+///
+/// With these, the sum over any rectangle of the image takes four lookups, independent of its
+/// size. This makes box filters and local means and variances as fast for large windows as for
+/// small ones.
+///
+/// [`integral_image`]: fn.integral_image.html
+/// [`integral_squared_image`]: fn.integral_squared_image.html
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IntegralImage<T> {
+    width: u32,
+    height: u32,
+    channels: usize,
+    /// `(width + 1) × (height + 1)` entries of `channels` sums each, with zeros in the first
+    /// row and column.
+    sums: Vec<T>,
+}
+
+/// Compute the summed-area table of an image.
+///
+/// The sums have the type `T`, usually `u32` or `u64`. Samples are converted to it as they are,
+/// so for floating point images a floating point type should be used. The sums of an image with
+/// `n` pixels of 8 bits fit into a `u32` if `n` is below 2<sup>24</sup>, or a 4096x4096 image.
+///
+/// # Panics
+///
+/// Panics if a sum overflows `T`, in debug builds.
+///
+/// # Examples
+///
+/// ```
+/// use image::imageops::integral_image;
+/// use image::math::Rect;
+/// use image::{GrayImage, Luma};
+///
+/// let image = GrayImage::from_fn(100, 100, |x, y| Luma([(x + y) as u8]));
+/// let integral = integral_image::<_, _, u64>(&image);
+/// let sum = integral.sum_region(Rect { x: 10, y: 20, width: 2, height: 2 });
+/// assert_eq!(sum[0], 30 + 31 + 31 + 32);
+/// ```
+pub fn integral_image<I, P, T>(image: &I) -> IntegralImage<T>
+where
+    I: GenericImageView<Pixel = P>,
+    P: Pixel,
+    T: Primitive,
+{
+    IntegralImage::new(image, |sample| sample)
+}
+
+/// Compute the summed-area table of the squares of the samples of an image.
+///
+/// Together with the table of [`integral_image`], this gives the variance of any rectangle in
+/// constant time. The sums of squares of 8 bit samples need a `u64` already for images with more
+/// than 2<sup>16</sup> pixels.
+///
+/// [`integral_image`]: fn.integral_image.html
+pub fn integral_squared_image<I, P, T>(image: &I) -> IntegralImage<T>
+where
+    I: GenericImageView<Pixel = P>,
+    P: Pixel,
+    T: Primitive,
+{
+    IntegralImage::new(image, |sample| sample * sample)
+}
+
+impl<T: Primitive> IntegralImage<T> {
+    fn new<I, P, F>(image: &I, map: F) -> Self
+    where
+        I: GenericImageView<Pixel = P>,
+        P: Pixel,
+        F: Fn(T) -> T,
+    {
+        let (width, height) = image.dimensions();
+        let channels = <usize as From<u8>>::from(P::CHANNEL_COUNT);
+        let stride = (width as usize + 1) * channels;
+        let mut sums = vec![T::zero(); stride * (height as usize + 1)];
+
+        let mut row_sums = vec![T::zero(); channels];
+        for y in 0..height {
+            row_sums.iter_mut().for_each(|s| *s = T::zero());
+            for x in 0..width {
+                let pixel = image.get_pixel(x, y);
+                let index = (y as usize + 1) * stride + (x as usize + 1) * channels;
+                for (c, &sample) in pixel.channels().iter().enumerate() {
+                    row_sums[c] = row_sums[c] + map(NumCast::from(sample).unwrap());
+                    sums[index + c] = sums[index - stride + c] + row_sums[c];
+                }
+            }
+        }
+
+        IntegralImage { width, height, channels, sums }
+    }
+
+    /// The width and height of the image the table was computed from.
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// The sums of every channel over a rectangle of the image.
+    ///
+    /// Channels that the pixels do not have are zero, as with [`Pixel::channels4`]. An empty
+    /// rectangle has a sum of zero.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the rectangle is not inside the image.
+    ///
+    /// [`Pixel::channels4`]: ../trait.Pixel.html#tymethod.channels4
+    pub fn sum_region(&self, rect: Rect) -> [T; 4] {
+        let x1 = rect.x.checked_add(rect.width).filter(|&x1| x1 <= self.width);
+        let y1 = rect.y.checked_add(rect.height).filter(|&y1| y1 <= self.height);
+        let (x1, y1) = match (x1, y1) {
+            (Some(x1), Some(y1)) => (x1, y1),
+            _ => panic!("the rectangle {:?} is outside of the image", rect),
+        };
+
+        let stride = (self.width as usize + 1) * self.channels;
+        let at = |x: u32, y: u32, c: usize| {
+            self.sums[y as usize * stride + x as usize * self.channels + c]
+        };
+        let mut sums = [T::zero(); 4];
+        for (c, sum) in sums.iter_mut().enumerate().take(self.channels) {
+            // Grouped so that unsigned sums never go below zero.
+            *sum = (at(x1, y1, c) - at(rect.x, y1, c)) - (at(x1, rect.y, c) - at(rect.x, rect.y, c));
+        }
+        sums
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{integral_image, integral_squared_image};
+    use crate::math::Rect;
+    use crate::{GrayImage, ImageBuffer, Luma, Rgb, RgbImage};
+
+    #[test]
+    fn test_sums_match_direct_sums() {
+        let image = RgbImage::from_fn(13, 9, |x, y| {
+            Rgb([(x * 17 + y * 3) as u8, (x * y) as u8, 255 - (x * 7) as u8])
+        });
+        let integral = integral_image::<_, _, u32>(&image);
+        let squared = integral_squared_image::<_, _, u64>(&image);
+        assert_eq!(integral.dimensions(), (13, 9));
+
+        let rects = [(0, 0, 13, 9), (3, 2, 5, 4), (12, 8, 1, 1), (4, 4, 0, 3), (0, 5, 13, 4)];
+        for &(x, y, width, height) in &rects {
+            let rect = Rect { x, y, width, height };
+            let mut expected = [0u32; 4];
+            let mut expected_squared = [0u64; 4];
+            for py in y..y + height {
+                for px in x..x + width {
+                    for c in 0..3 {
+                        let v = image.get_pixel(px, py)[c];
+                        expected[c] += u32::from(v);
+                        expected_squared[c] += u64::from(v) * u64::from(v);
+                    }
+                }
+            }
+            assert_eq!(integral.sum_region(rect), expected, "{:?}", rect);
+            assert_eq!(squared.sum_region(rect), expected_squared, "{:?}", rect);
+        }
+    }
+
+    #[test]
+    fn test_float_and_empty() {
+        let image = ImageBuffer::from_pixel(4, 4, Luma([0.25f32]));
+        let integral = integral_image::<_, _, f64>(&image);
+        assert_eq!(integral.sum_region(Rect { x: 1, y: 1, width: 2, height: 2 })[0], 1.0);
+
+        let integral = integral_image::<_, _, u32>(&GrayImage::new(0, 0));
+        assert_eq!(integral.sum_region(Rect { x: 0, y: 0, width: 0, height: 0 }), [0; 4]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_outside() {
+        let integral = integral_image::<_, _, u32>(&GrayImage::new(4, 4));
+        integral.sum_region(Rect { x: 2, y: 0, width: 3, height: 1 });
+    }
+}
@@ -5,6 +5,7 @@ use num_traits::{NumCast};
 
 use crate::image::{GenericImage, GenericImageView, SubImage};
 use crate::traits::{Lerp, Primitive, Pixel};
+use crate::ImageBuffer;
 
 pub use self::sample::FilterType;
 
@@ -13,21 +14,131 @@ pub use self::sample::FilterType::{CatmullRom, Gaussian, Lanczos3, Nearest, Tria
 /// Affine transformations
 pub use self::affine::{
     flip_horizontal, flip_horizontal_in_place, flip_vertical, flip_vertical_in_place, rotate180,
-    rotate180_in_place, rotate270, rotate90, rotate180_in, rotate90_in, rotate270_in, flip_horizontal_in, flip_vertical_in
+    rotate180_in_place, rotate270, rotate90, rotate180_in, rotate90_in, rotate270_in, flip_horizontal_in, flip_vertical_in,
+    rotate,
 };
 
 /// Image sampling
-pub use self::sample::{blur, filter3x3, resize, thumbnail, unsharpen};
+pub use self::sample::{
+    blur, blur_premultiplied, filter3x3, resize, resize_linear_srgb, resize_premultiplied,
+    thumbnail, thumbnail_filtered, unsharpen,
+};
+pub use self::box_blur::{box_blur, fast_blur};
+
+/// Convolution
+pub use self::convolve::{convolve, BorderMode, KernelShape};
+
+/// Parallel processing
+#[cfg(feature = "rayon")]
+pub use self::parallel::{
+    par_blur, par_resize, par_rotate180, par_rotate270, par_rotate90, par_unsharpen,
+};
+
+/// Content-aware resizing
+pub use self::seam::seam_carve;
 
 /// Color operations
-pub use self::colorops::{brighten, contrast, dither, grayscale, huerotate, index_colors, invert,
-                         BiLevel, ColorMap};
+pub use self::colorops::{adjust_exposure, adjust_gamma, adjust_hsl, apply_curve, brighten,
+                         contrast, dither, grayscale, grayscale_with, huerotate, index_colors,
+                         invert, BiLevel, ColorMap, LumaStandard};
+pub use self::dithering::{dither_with, DitherMethod};
+pub use self::flood_fill::{flood_fill, Connectivity};
+pub use self::white_balance::{white_balance, WhiteBalanceMethod};
+pub use self::tonemap::{tonemap, TonemapOperator};
+
+/// Summed-area tables
+pub use self::integral::{integral_image, integral_squared_image, IntegralImage};
+
+/// Compositing
+pub use self::composite::{composite, BlendMode};
+pub use self::chroma_key::chroma_key;
+pub use self::transfer::color_transfer;
+pub use self::histogram::{clahe, equalize_histogram, histogram, stretch_contrast};
+
+/// Image statistics
+pub use self::stats::{channel_stats, channel_stats_masked, ChannelStats};
+pub use self::delta_e::{delta_e, DeltaE, DeltaEFormula};
+
+/// Noise reduction
+pub use self::denoise::{bilateral_filter, denoise_nl_means, denoise_wavelet, median_filter};
+
+/// Deblurring
+pub use self::deconvolve::{deconvolve_richardson_lucy, gaussian_psf};
+
+/// Edge detection
+pub use self::edges::{canny, gradient_magnitude, gradients, laplacian, GradientOperator};
+
+/// Thresholding
+pub use self::threshold::{otsu_level, threshold, ThresholdMethod};
+
+/// Morphology
+pub use self::morphology::{closing, dilate, erode, opening, StructuringElement};
+pub use self::distance::{distance_transform, DistanceMetric};
+
+/// Segmentation
+pub use self::slic::{slic, Superpixels};
+
+/// Shape detection
+pub use self::hough::{detect_circles, detect_lines, Circle, Line};
+
+/// Image registration
+pub use self::align::{estimate_similarity, estimate_translation, translate, Similarity, Translation};
+
+/// Image fusion
+pub use self::focus::focus_stack;
+pub use self::poisson::seamless_clone;
+#[cfg(feature = "stitching")]
+pub use self::stitch::stitch_panorama;
+
+/// Decorations
+pub use self::decorate::{add_border, drop_shadow, vignette};
+
+/// Sprite sheets
+pub use self::atlas::{pack_sprites, slice_grid, slice_sprites, PackingMethod, SpriteSheet};
 
 mod affine;
+mod align;
+mod atlas;
+mod box_blur;
+mod chroma_key;
 // Public only because of Rust bug:
 // https://github.com/rust-lang/rust/issues/18241
 pub mod colorops;
+mod composite;
+mod convolve;
+mod deconvolve;
+mod decorate;
+mod delta_e;
+mod denoise;
+mod distance;
+mod dithering;
+mod edges;
+mod fft;
+mod flood_fill;
+mod focus;
+mod histogram;
+mod hough;
+mod integral;
+mod morphology;
+pub mod noise;
+#[cfg(feature = "rayon")]
+mod parallel;
+pub(crate) mod plane;
+mod poisson;
 mod sample;
+mod seam;
+mod slic;
+pub(crate) mod srgb;
+mod stats;
+#[cfg(feature = "stitching")]
+mod stitch;
+mod threshold;
+mod tonemap;
+mod transfer;
+mod white_balance;
+
+pub(crate) use self::dithering::reduce_depth;
+pub(crate) use self::sample::FloatNearest;
 
 /// Return a mutable view into an image
 /// The coordinates set the position of the top left corner of the crop.
@@ -55,6 +166,72 @@ pub fn crop_imm<I: GenericImageView>(
     SubImage::new(image, x, y, width, height)
 }
 
+/// How [`crop_padded`] fills the parts of the crop that are outside of the image.
+///
+/// [`crop_padded`]: fn.crop_padded.html
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PadMode<P> {
+    /// Fill with a color.
+    Constant(P),
+    /// Repeat the nearest edge pixel.
+    Edge,
+    /// Mirror the image at its edges, repeating the edge pixel.
+    Reflect,
+}
+
+/// Copy a rectangle out of an image, which may extend beyond the edges of the image.
+///
+/// The top left corner of the rectangle is at `(x, y)`, and may be left of or above the image.
+/// The result always has the requested size, and the parts outside of the image are filled
+/// according to `pad`. The pixels outside of an empty image are zero unless `pad` gives a
+/// color.
+///
+/// # Examples
+///
+/// ```
+/// use image::imageops::{crop_padded, PadMode};
+/// use image::{GrayImage, Luma};
+///
+/// let image = GrayImage::from_fn(3, 1, |x, _| Luma([x as u8 + 1]));
+/// let crop = crop_padded(&image, -2, 0, 6, 1, PadMode::Constant(Luma([9])));
+/// assert_eq!(crop.into_raw(), [9, 9, 1, 2, 3, 9]);
+/// let crop = crop_padded(&image, -2, 0, 6, 1, PadMode::Edge);
+/// assert_eq!(crop.into_raw(), [1, 1, 1, 2, 3, 3]);
+/// let crop = crop_padded(&image, -2, 0, 6, 1, PadMode::Reflect);
+/// assert_eq!(crop.into_raw(), [2, 1, 1, 2, 3, 3]);
+/// ```
+pub fn crop_padded<I, P, S>(
+    image: &I,
+    x: i64,
+    y: i64,
+    width: u32,
+    height: u32,
+    pad: PadMode<P>,
+) -> ImageBuffer<P, Vec<S>>
+where
+    I: GenericImageView<Pixel = P>,
+    P: Pixel<Subpixel = S> + 'static,
+    S: Primitive + 'static,
+{
+    let mode = match pad {
+        PadMode::Constant(_) => BorderMode::Zero,
+        PadMode::Edge => BorderMode::Clamp,
+        PadMode::Reflect => BorderMode::Reflect,
+    };
+    let mut out = match pad {
+        PadMode::Constant(color) => ImageBuffer::from_pixel(width, height, color),
+        _ => ImageBuffer::new(width, height),
+    };
+    for (out_x, out_y, pixel) in out.enumerate_pixels_mut() {
+        // Positions past the range of `i64` are far outside of the image either way.
+        let (source_x, source_y) = (x.saturating_add(out_x.into()), y.saturating_add(out_y.into()));
+        if let Some(source) = repeated_pixel(image, source_x, source_y, mode) {
+            *pixel = source;
+        }
+    }
+    out
+}
+
 fn crop_dimms<I: GenericImageView>(
     image: &I,
     x: u32,
@@ -73,6 +250,62 @@ fn crop_dimms<I: GenericImageView>(
     (x, y, width, height)
 }
 
+/// Where an image is placed inside a larger area, for example which part of an image is kept by
+/// [`DynamicImage::resize_to_fill`].
+///
+/// The names are compass directions, with north at the top.
+///
+/// [`DynamicImage::resize_to_fill`]: ../enum.DynamicImage.html#method.resize_to_fill
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Gravity {
+    /// The center of both axes.
+    Center,
+    /// The top edge, centered horizontally.
+    North,
+    /// The top right corner.
+    NorthEast,
+    /// The right edge, centered vertically.
+    East,
+    /// The bottom right corner.
+    SouthEast,
+    /// The bottom edge, centered horizontally.
+    South,
+    /// The bottom left corner.
+    SouthWest,
+    /// The left edge, centered vertically.
+    West,
+    /// The top left corner.
+    NorthWest,
+}
+
+impl Default for Gravity {
+    fn default() -> Gravity {
+        Gravity::Center
+    }
+}
+
+impl Gravity {
+    /// The position of the top left corner of an area of size `inner` placed inside `outer`.
+    ///
+    /// An axis on which `inner` is larger than `outer` gets the position 0.
+    pub(crate) fn offset(self, outer: (u32, u32), inner: (u32, u32)) -> (u32, u32) {
+        use self::Gravity::*;
+        let free_x = outer.0.saturating_sub(inner.0);
+        let free_y = outer.1.saturating_sub(inner.1);
+        let x = match self {
+            NorthWest | West | SouthWest => 0,
+            North | Center | South => free_x / 2,
+            NorthEast | East | SouthEast => free_x,
+        };
+        let y = match self {
+            NorthWest | North | NorthEast => 0,
+            West | Center | East => free_y / 2,
+            SouthWest | South | SouthEast => free_y,
+        };
+        (x, y)
+    }
+}
+
 /// Calculate the region that can be copied from top to bottom.
 ///
 /// Given image size of bottom and top image, and a point at which we want to place the top image
@@ -192,6 +425,99 @@ where
     }
 }
 
+/// Fill an image with copies of another one, without seams or cut off copies at the edges.
+///
+/// Every pixel of `bottom` is taken from `top`, repeated endlessly in both directions. With
+/// `mirror`, every other copy is flipped so that neighbouring copies meet at matching edges,
+/// which hides the seams of textures that do not tile by themselves. The pixel of the repeated
+/// pattern at `offset` is placed at the top left corner of `bottom`, which shifts the pattern.
+///
+/// Unlike [`tile`], `top` replaces the pixels of `bottom` instead of being blended onto them.
+/// An empty `top` leaves `bottom` unchanged.
+///
+/// # Examples
+///
+/// ```
+/// use image::{GrayImage, Luma};
+///
+/// let texture = GrayImage::from_fn(3, 1, |x, _| Luma([x as u8]));
+/// let mut row = GrayImage::new(8, 1);
+/// image::imageops::tile_seamless(&mut row, &texture, true, (1, 0));
+/// assert_eq!(row.into_raw(), [1, 2, 2, 1, 0, 0, 1, 2]);
+/// ```
+///
+/// [`tile`]: fn.tile.html
+pub fn tile_seamless<I, J>(bottom: &mut I, top: &J, mirror: bool, offset: (u32, u32))
+where
+    I: GenericImage,
+    J: GenericImageView<Pixel = I::Pixel>,
+{
+    let mode = if mirror { BorderMode::Reflect } else { BorderMode::Wrap };
+    let (width, height) = bottom.dimensions();
+    for y in 0..height {
+        for x in 0..width {
+            let (source_x, source_y) = (x as i64 + offset.0 as i64, y as i64 + offset.1 as i64);
+            if let Some(pixel) = repeated_pixel(top, source_x, source_y, mode) {
+                bottom.put_pixel(x, y, pixel);
+            }
+        }
+    }
+}
+
+/// Create an image of any size from an image extended beyond its edges.
+///
+/// The image is placed at the top left corner of the result, and the rest is filled according
+/// to `mode`: `Wrap` repeats the image, `Reflect` repeats it mirrored, `Clamp` stretches its
+/// last row and column, and `Zero` leaves the rest black and transparent. An empty image gives
+/// an all zero result.
+///
+/// # Examples
+///
+/// ```
+/// use image::imageops::{repeat, BorderMode};
+/// use image::{GrayImage, Luma};
+///
+/// let image = GrayImage::from_fn(2, 1, |x, _| Luma([x as u8 + 1]));
+/// assert_eq!(repeat(&image, 5, 1, BorderMode::Wrap).into_raw(), [1, 2, 1, 2, 1]);
+/// assert_eq!(repeat(&image, 5, 1, BorderMode::Reflect).into_raw(), [1, 2, 2, 1, 1]);
+/// assert_eq!(repeat(&image, 5, 1, BorderMode::Clamp).into_raw(), [1, 2, 2, 2, 2]);
+/// ```
+pub fn repeat<I, P, S>(
+    image: &I,
+    width: u32,
+    height: u32,
+    mode: BorderMode,
+) -> ImageBuffer<P, Vec<S>>
+where
+    I: GenericImageView<Pixel = P>,
+    P: Pixel<Subpixel = S> + 'static,
+    S: Primitive + 'static,
+{
+    let mut out = ImageBuffer::new(width, height);
+    for (x, y, pixel) in out.enumerate_pixels_mut() {
+        if let Some(source) = repeated_pixel(image, x as i64, y as i64, mode) {
+            *pixel = source;
+        }
+    }
+    out
+}
+
+/// The pixel of an endlessly extended image at a position, or `None` outside of the image for
+/// `BorderMode::Zero` and for empty images.
+fn repeated_pixel<I>(image: &I, x: i64, y: i64, mode: BorderMode) -> Option<I::Pixel>
+where
+    I: GenericImageView,
+{
+    let (width, height) = image.dimensions();
+    if width == 0 || height == 0 {
+        return None;
+    }
+    match (mode.index(x, width), mode.index(y, height)) {
+        (Some(x), Some(y)) => Some(image.get_pixel(x as u32, y as u32)),
+        _ => None,
+    }
+}
+
 /// Fill the image with a linear vertical gradient
 /// 
 /// This function assumes a linear color space.
@@ -364,6 +690,121 @@ mod tests {
         assert_eq!(img.get_pixel(0, img.height() - 1), &end);
     }
 
+    #[test]
+    fn test_repeat() {
+        use super::{repeat, tile_seamless, BorderMode};
+        use crate::{GrayImage, Luma, Rgba};
+
+        let image = GrayImage::from_fn(2, 2, |x, y| Luma([(10 * y + x) as u8]));
+        let wrapped = repeat(&image, 3, 3, BorderMode::Wrap);
+        assert_eq!(wrapped.into_raw(), [0, 1, 0, 10, 11, 10, 0, 1, 0]);
+        let zero = repeat(&image, 3, 3, BorderMode::Zero);
+        assert_eq!(zero.into_raw(), [0, 1, 0, 10, 11, 0, 0, 0, 0]);
+        let empty = repeat(&GrayImage::new(0, 4), 2, 2, BorderMode::Reflect);
+        assert_eq!(empty.into_raw(), [0; 4]);
+
+        let texture = RgbaImage::from_fn(3, 2, |x, y| Rgba([x as u8, y as u8, 0, 255]));
+        let mut bottom = RgbaImage::new(7, 5);
+        tile_seamless(&mut bottom, &texture, false, (0, 0));
+        assert!(bottom
+            .enumerate_pixels()
+            .all(|(x, y, p)| p[0] == (x % 3) as u8 && p[1] == (y % 2) as u8));
+
+        // Mirrored copies meet at matching edges.
+        tile_seamless(&mut bottom, &texture, true, (2, 0));
+        assert_eq!(bottom.get_pixel(0, 0), bottom.get_pixel(1, 0));
+        assert_eq!(bottom.get_pixel(3, 0), bottom.get_pixel(4, 0));
+        assert_eq!(bottom.get_pixel(0, 1), bottom.get_pixel(0, 2));
+    }
+
+    #[test]
+    fn test_repeat_edge_cases() {
+        use super::{repeat, tile_seamless, BorderMode};
+        use crate::{GrayImage, Luma};
+
+        let image = GrayImage::from_fn(3, 1, |x, _| Luma([x as u8 + 1]));
+        for &mode in &[BorderMode::Wrap, BorderMode::Reflect, BorderMode::Clamp, BorderMode::Zero] {
+            assert_eq!(repeat(&image, 0, 0, mode).dimensions(), (0, 0));
+            assert_eq!(repeat(&image, 0, 5, mode).dimensions(), (0, 5));
+            // Cropping is a special case of repeating.
+            assert_eq!(repeat(&image, 2, 1, mode).into_raw(), [1, 2]);
+        }
+
+        // A single pixel is repeated in every mode that extends the image.
+        let dot = GrayImage::from_pixel(1, 1, Luma([7]));
+        for &mode in &[BorderMode::Wrap, BorderMode::Reflect, BorderMode::Clamp] {
+            assert_eq!(repeat(&dot, 3, 2, mode).into_raw(), [7; 6]);
+        }
+
+        // An empty texture leaves the image as it is.
+        let mut bottom = GrayImage::from_pixel(2, 2, Luma([5]));
+        tile_seamless(&mut bottom, &GrayImage::new(0, 3), true, (1, 1));
+        assert_eq!(bottom.into_raw(), [5; 4]);
+
+        // Offsets far beyond the texture, whole periods do not shift the pattern.
+        let mut shifted = GrayImage::new(4, 1);
+        tile_seamless(&mut shifted, &image, false, (u32::max_value(), 0));
+        assert_eq!(shifted.into_raw(), [1, 2, 3, 1]);
+        let mut mirrored = GrayImage::new(4, 1);
+        tile_seamless(&mut mirrored, &image, true, (6 * 1000, 0));
+        assert_eq!(mirrored.into_raw(), [1, 2, 3, 3]);
+    }
+
+    #[test]
+    fn test_crop_padded() {
+        use super::{crop_padded, PadMode};
+        use crate::{GrayImage, Luma};
+
+        let image = GrayImage::from_fn(4, 3, |x, y| Luma([(10 * y + x) as u8]));
+        let inside = crop_padded(&image, 1, 1, 2, 2, PadMode::Constant(Luma([99])));
+        assert_eq!(inside.into_raw(), [11, 12, 21, 22]);
+
+        let corner = crop_padded(&image, 3, 2, 2, 2, PadMode::Constant(Luma([99])));
+        assert_eq!(corner.into_raw(), [23, 99, 99, 99]);
+        let corner = crop_padded(&image, 3, 2, 2, 2, PadMode::Edge);
+        assert_eq!(corner.into_raw(), [23, 23, 23, 23]);
+        let corner = crop_padded(&image, -1, -1, 2, 2, PadMode::Reflect);
+        assert_eq!(corner.into_raw(), [0, 0, 0, 0]);
+
+        // Entirely outside of the image.
+        let far = crop_padded(&image, -100, 50, 2, 1, PadMode::Edge);
+        assert_eq!(far.into_raw(), [20, 20]);
+        let empty = crop_padded(&GrayImage::new(0, 0), 0, 0, 2, 1, PadMode::Reflect);
+        assert_eq!(empty.into_raw(), [0, 0]);
+    }
+
+    #[test]
+    fn test_crop_padded_edge_cases() {
+        use super::{crop_padded, PadMode};
+        use crate::{GrayImage, Luma, Rgba};
+
+        let image = GrayImage::from_fn(4, 3, |x, y| Luma([(10 * y + x) as u8]));
+        for &pad in &[PadMode::Constant(Luma([99])), PadMode::Edge, PadMode::Reflect] {
+            // Empty crops, anywhere.
+            assert_eq!(crop_padded(&image, 1, 1, 0, 0, pad).dimensions(), (0, 0));
+            assert_eq!(crop_padded(&image, -9, 9, 0, 2, pad).dimensions(), (0, 2));
+            // The whole image is copied as it is.
+            assert_eq!(crop_padded(&image, 0, 0, 4, 3, pad), image);
+        }
+
+        // Positions at the ends of the range do not overflow.
+        let far = crop_padded(&image, i64::max_value(), i64::min_value(), 2, 2, PadMode::Edge);
+        assert_eq!(far.into_raw(), [3, 3, 3, 3]);
+        let far = crop_padded(&image, i64::max_value() - 1, 0, 2, 1, PadMode::Constant(Luma([1])));
+        assert_eq!(far.into_raw(), [1, 1]);
+
+        // Reflections repeat with a period of twice the size.
+        let near = crop_padded(&image, -3, 0, 12, 1, PadMode::Reflect);
+        let far = crop_padded(&image, 8 * 1000 - 3, 0, 12, 1, PadMode::Reflect);
+        assert_eq!(near.into_raw(), [2, 1, 0, 0, 1, 2, 3, 3, 2, 1, 0, 0]);
+        assert_eq!(far, crop_padded(&image, -3, 0, 12, 1, PadMode::Reflect));
+
+        // The padding color includes alpha.
+        let rgba = RgbaImage::from_pixel(1, 1, Rgba([1, 2, 3, 255]));
+        let padded = crop_padded(&rgba, -1, 0, 2, 1, PadMode::Constant(Rgba([0, 0, 0, 0])));
+        assert_eq!(padded.into_raw(), [0, 0, 0, 0, 1, 2, 3, 255]);
+    }
+
     #[test]
     /// Test blur doens't panick when passed 0.0
     fn test_blur_zero() {
@@ -0,0 +1,211 @@
+//! Morphological operations: erosion, dilation, opening and closing.
+
+use crate::image::GenericImageView;
+use crate::traits::{Pixel, Primitive};
+use crate::ImageBuffer;
+
+/// The neighbourhood of a pixel considered by morphological operations.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum StructuringElement {
+    /// A rectangle of the given size, with the pixel at `(width / 2, height / 2)` as its origin.
+    /// An empty rectangle leaves the image unchanged.
+    Rect {
+        /// The width in pixels.
+        width: u32,
+        /// The height in pixels.
+        height: u32,
+    },
+    /// All pixels within the given euclidean distance.
+    Disk(u32),
+    /// The pixels in the same row or column up to the given distance, a plus sign.
+    Cross(u32),
+}
+
+impl StructuringElement {
+    /// The positions of the pixels of the element relative to its origin.
+    fn offsets(self) -> Vec<(i64, i64)> {
+        match self {
+            StructuringElement::Rect { width, height } => {
+                let (cx, cy) = (i64::from(width / 2), i64::from(height / 2));
+                (0..i64::from(height))
+                    .flat_map(|y| (0..i64::from(width)).map(move |x| (x - cx, y - cy)))
+                    .collect()
+            }
+            StructuringElement::Disk(radius) => {
+                let r = i64::from(radius);
+                (-r..=r)
+                    .flat_map(|y| (-r..=r).map(move |x| (x, y)))
+                    .filter(|&(x, y)| x * x + y * y <= r * r)
+                    .collect()
+            }
+            StructuringElement::Cross(radius) => {
+                let r = i64::from(radius);
+                (-r..=r)
+                    .map(|x| (x, 0))
+                    .chain((-r..=r).filter(|&y| y != 0).map(|y| (0, y)))
+                    .collect()
+            }
+        }
+    }
+}
+
+/// Erode an image: replace every sample by the minimum of its neighbourhood.
+///
+/// Bright regions shrink and bright details smaller than the element disappear. On binary masks
+/// with values of zero and the maximum, this is binary erosion. Each channel is processed
+/// separately and pixels outside of the image are ignored.
+pub fn erode<I, P, S>(image: &I, element: StructuringElement) -> ImageBuffer<P, Vec<S>>
+where
+    I: GenericImageView<Pixel = P>,
+    P: Pixel<Subpixel = S> + 'static,
+    S: Primitive + 'static,
+{
+    let offsets = element.offsets();
+    morph(image, &offsets, min)
+}
+
+/// Dilate an image: replace every sample by the maximum of its neighbourhood.
+///
+/// Bright regions grow and dark details smaller than the element disappear. On binary masks with
+/// values of zero and the maximum, this is binary dilation. Each channel is processed separately
+/// and pixels outside of the image are ignored.
+pub fn dilate<I, P, S>(image: &I, element: StructuringElement) -> ImageBuffer<P, Vec<S>>
+where
+    I: GenericImageView<Pixel = P>,
+    P: Pixel<Subpixel = S> + 'static,
+    S: Primitive + 'static,
+{
+    // Dilation uses the reflected element, so that it is the dual of erosion.
+    let offsets: Vec<(i64, i64)> = element.offsets().iter().map(|&(x, y)| (-x, -y)).collect();
+    morph(image, &offsets, max)
+}
+
+/// Erode and then dilate an image.
+///
+/// This removes bright details smaller than the element, such as specks of noise in a mask,
+/// while keeping the shape of larger regions.
+pub fn opening<I, P, S>(image: &I, element: StructuringElement) -> ImageBuffer<P, Vec<S>>
+where
+    I: GenericImageView<Pixel = P>,
+    P: Pixel<Subpixel = S> + 'static,
+    S: Primitive + 'static,
+{
+    dilate(&erode(image, element), element)
+}
+
+/// Dilate and then erode an image.
+///
+/// This fills dark details smaller than the element, such as holes and gaps in a mask, while
+/// keeping the shape of larger regions.
+pub fn closing<I, P, S>(image: &I, element: StructuringElement) -> ImageBuffer<P, Vec<S>>
+where
+    I: GenericImageView<Pixel = P>,
+    P: Pixel<Subpixel = S> + 'static,
+    S: Primitive + 'static,
+{
+    erode(&dilate(image, element), element)
+}
+
+fn min<S: PartialOrd>(a: S, b: S) -> S {
+    if b < a {
+        b
+    } else {
+        a
+    }
+}
+
+fn max<S: PartialOrd>(a: S, b: S) -> S {
+    if b > a {
+        b
+    } else {
+        a
+    }
+}
+
+/// Combine every pixel with its neighbours at the given offsets, channel by channel.
+fn morph<I, P, S>(image: &I, offsets: &[(i64, i64)], pick: fn(S, S) -> S) -> ImageBuffer<P, Vec<S>>
+where
+    I: GenericImageView<Pixel = P>,
+    P: Pixel<Subpixel = S> + 'static,
+    S: Primitive + 'static,
+{
+    let (width, height) = image.dimensions();
+    ImageBuffer::from_fn(width, height, |x, y| {
+        let mut pixel = image.get_pixel(x, y);
+        for &(dx, dy) in offsets {
+            let (nx, ny) = (i64::from(x) + dx, i64::from(y) + dy);
+            if nx < 0 || ny < 0 || nx >= i64::from(width) || ny >= i64::from(height) {
+                continue;
+            }
+            pixel = pixel.map2(&image.get_pixel(nx as u32, ny as u32), pick);
+        }
+        pixel
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{closing, dilate, erode, opening, StructuringElement};
+    use crate::{GrayImage, Luma, Rgb, RgbImage};
+
+    /// Whether both coordinates are in `start..end`.
+    fn within(x: u32, y: u32, start: u32, end: u32) -> bool {
+        x >= start && x < end && y >= start && y < end
+    }
+
+    /// A filled square of 6 × 6 pixels on a black background.
+    fn square() -> GrayImage {
+        GrayImage::from_fn(16, 16, |x, y| Luma([if within(x, y, 5, 11) { 255 } else { 0 }]))
+    }
+
+    #[test]
+    fn test_element_sizes() {
+        assert_eq!(StructuringElement::Rect { width: 3, height: 2 }.offsets().len(), 6);
+        assert_eq!(StructuringElement::Rect { width: 0, height: 2 }.offsets().len(), 0);
+        assert_eq!(StructuringElement::Disk(0).offsets(), vec![(0, 0)]);
+        assert_eq!(StructuringElement::Disk(1).offsets().len(), 5);
+        assert_eq!(StructuringElement::Disk(2).offsets().len(), 13);
+        assert_eq!(StructuringElement::Cross(2).offsets().len(), 9);
+    }
+
+    #[test]
+    fn test_erode_dilate() {
+        let element = StructuringElement::Rect { width: 3, height: 3 };
+        let eroded = erode(&square(), element);
+        let dilated = dilate(&square(), element);
+        for (x, y, pixel) in eroded.enumerate_pixels() {
+            let inside = within(x, y, 6, 10);
+            assert_eq!(pixel[0] == 255, inside, "eroded ({}, {})", x, y);
+        }
+        for (x, y, pixel) in dilated.enumerate_pixels() {
+            let inside = within(x, y, 4, 12);
+            assert_eq!(pixel[0] == 255, inside, "dilated ({}, {})", x, y);
+        }
+    }
+
+    #[test]
+    fn test_opening_removes_specks() {
+        let mut noisy = square();
+        noisy.put_pixel(1, 1, Luma([255]));
+        noisy.put_pixel(14, 2, Luma([255]));
+        let element = StructuringElement::Rect { width: 3, height: 3 };
+        assert_eq!(opening(&noisy, element), square());
+    }
+
+    #[test]
+    fn test_closing_fills_holes() {
+        let mut holey = square();
+        holey.put_pixel(7, 7, Luma([0]));
+        holey.put_pixel(8, 9, Luma([0]));
+        let element = StructuringElement::Rect { width: 2, height: 2 };
+        assert_eq!(closing(&holey, element), square());
+        assert_eq!(closing(&holey, StructuringElement::Disk(1)), square());
+    }
+
+    #[test]
+    fn test_channels_are_separate() {
+        let image = RgbImage::from_fn(3, 1, |x, _| Rgb([x as u8 * 10, 50 - x as u8 * 10, 7]));
+        let eroded = erode(&image, StructuringElement::Cross(1));
+        assert_eq!(eroded.get_pixel(1, 0), &Rgb([0, 30, 7]));
+    }
+}
@@ -0,0 +1,279 @@
+//! Generation of random noise, and addition of noise to images.
+//!
+//! All functions take a seed and produce the same noise for the same seed on every platform,
+//! which makes them usable for reproducible tests.
+//!
+//! # Examples
+//!
+//! ```
+//! use image::imageops::noise;
+//!
+//! // Clouds, with features of about 64 pixels and finer detail on top.
+//! let clouds = noise::perlin(256, 256, 64.0, 4, 7);
+//!
+//! // A photo degraded by sensor noise.
+//! let mut photo = image::RgbImage::new(64, 64);
+//! noise::add_gaussian(&mut photo, 0.05, 1);
+//! ```
+
+use std::f32::consts::PI;
+
+use num_traits::NumCast;
+
+use crate::color::Luma;
+use crate::image::GenericImage;
+use crate::imageops::plane;
+use crate::imageops::sample::FloatNearest;
+use crate::traits::Pixel;
+use crate::utils::clamp;
+use crate::GrayImage;
+
+/// Generate an image of normally distributed noise.
+///
+/// `mean` and `stddev` are relative to the range of the samples, so a mean of `0.5` is mid
+/// gray. Samples are clamped to the range.
+pub fn gaussian(width: u32, height: u32, mean: f32, stddev: f32, seed: u64) -> GrayImage {
+    let mut rng = Rng::new(seed);
+    GrayImage::from_fn(width, height, |_, _| {
+        let value = clamp(mean + stddev * rng.next_gaussian(), 0.0, 1.0);
+        Luma([(value * 255.0).round() as u8])
+    })
+}
+
+/// Generate an image of fractal Perlin noise, a smooth random pattern for clouds, terrain and
+/// textures.
+///
+/// The first octave has features of about `scale` pixels. Every further octave adds detail at
+/// half the size and half the strength of the previous one, at least one octave is used. The
+/// values are centered on mid gray.
+pub fn perlin(width: u32, height: u32, scale: f32, octaves: u32, seed: u64) -> GrayImage {
+    let noise = Perlin::new(seed);
+    let octaves = octaves.max(1);
+    let total: f32 = (0..octaves).map(|o| 0.5f32.powi(o as i32)).sum();
+    GrayImage::from_fn(width, height, |x, y| {
+        let mut value = 0.0;
+        let (mut frequency, mut amplitude) = (1.0 / scale, 1.0);
+        for _ in 0..octaves {
+            value += amplitude * noise.sample(x as f32 * frequency, y as f32 * frequency);
+            frequency *= 2.0;
+            amplitude *= 0.5;
+        }
+        let value = clamp((value / total + 1.0) / 2.0, 0.0, 1.0);
+        Luma([(value * 255.0).round() as u8])
+    })
+}
+
+/// Add normally distributed noise to every color sample of an image in place.
+///
+/// `stddev` is relative to the range of the samples, and the results are clamped to it. The
+/// channels get independent noise, like the sensor of a camera at a high ISO setting. The alpha
+/// channel is left untouched.
+pub fn add_gaussian<I>(image: &mut I, stddev: f32, seed: u64)
+where
+    I: GenericImage,
+{
+    let max = plane::nominal_max::<<I::Pixel as Pixel>::Subpixel>();
+    let mut rng = Rng::new(seed);
+    let (width, height) = image.dimensions();
+    for y in 0..height {
+        for x in 0..width {
+            let pixel = image.get_pixel(x, y).map_with_alpha(
+                |sample| {
+                    let value: f32 = NumCast::from(sample).unwrap();
+                    let value = value + stddev * max * rng.next_gaussian();
+                    NumCast::from(FloatNearest(clamp(value, 0.0, max))).unwrap()
+                },
+                |alpha| alpha,
+            );
+            image.put_pixel(x, y, pixel);
+        }
+    }
+}
+
+/// Set a random share of the pixels of an image to black or white in place, like the dead and
+/// stuck pixels of a faulty sensor.
+///
+/// Every pixel becomes black or white with a probability of `density / 2` each. The alpha
+/// channel is left untouched.
+pub fn add_salt_and_pepper<I>(image: &mut I, density: f32, seed: u64)
+where
+    I: GenericImage,
+{
+    let max = plane::nominal_max::<<I::Pixel as Pixel>::Subpixel>();
+    let mut rng = Rng::new(seed);
+    let (width, height) = image.dimensions();
+    for y in 0..height {
+        for x in 0..width {
+            let chance = rng.next_f32();
+            if chance >= density {
+                continue;
+            }
+            let value = if chance < density / 2.0 { 0.0 } else { max };
+            let pixel = image
+                .get_pixel(x, y)
+                .map_with_alpha(|_| NumCast::from(value).unwrap(), |alpha| alpha);
+            image.put_pixel(x, y, pixel);
+        }
+    }
+}
+
+/// A small and fast pseudorandom number generator, SplitMix64 by Sebastiano Vigna.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Rng(seed)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A uniformly distributed number in `[0, 1)`.
+    fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    /// A normally distributed number with a mean of 0 and a standard deviation of 1, by the
+    /// Box-Muller transform.
+    fn next_gaussian(&mut self) -> f32 {
+        let u = 1.0 - self.next_f32();
+        let v = self.next_f32();
+        (-2.0 * u.ln()).sqrt() * (2.0 * PI * v).cos()
+    }
+}
+
+/// Gradient noise after Ken Perlin, "Improving Noise", 2002.
+struct Perlin {
+    permutation: [u8; 256],
+}
+
+impl Perlin {
+    fn new(seed: u64) -> Self {
+        let mut permutation = [0u8; 256];
+        for (i, p) in permutation.iter_mut().enumerate() {
+            *p = i as u8;
+        }
+        let mut rng = Rng::new(seed);
+        for i in (1..256).rev() {
+            let j = (rng.next_u64() % (i as u64 + 1)) as usize;
+            permutation.swap(i, j);
+        }
+        Perlin { permutation }
+    }
+
+    /// The noise at a point, in `[-1, 1]`.
+    fn sample(&self, x: f32, y: f32) -> f32 {
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+        let (xi, yi) = (x0 as i64 as usize & 255, y0 as i64 as usize & 255);
+
+        let p = |i: usize| self.permutation[i & 255] as usize;
+        let corner = |dx: usize, dy: usize| {
+            let hash = p(p(xi + dx) + yi + dy);
+            gradient(hash, fx - dx as f32, fy - dy as f32)
+        };
+        let (u, v) = (fade(fx), fade(fy));
+        let top = lerp(corner(0, 0), corner(1, 0), u);
+        let bottom = lerp(corner(0, 1), corner(1, 1), u);
+        lerp(top, bottom, v)
+    }
+}
+
+fn fade(t: f32) -> f32 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+/// The dot product of one of eight gradients, chosen by `hash`, with the offset to a corner.
+fn gradient(hash: usize, x: f32, y: f32) -> f32 {
+    match hash & 7 {
+        0 => x + y,
+        1 => x - y,
+        2 => -x + y,
+        3 => -x - y,
+        4 => x,
+        5 => -x,
+        6 => y,
+        _ => -y,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Rgba, RgbaImage};
+
+    fn mean_stddev(image: &GrayImage) -> (f64, f64) {
+        let n = image.as_raw().len() as f64;
+        let mean = image.as_raw().iter().map(|&v| v as f64).sum::<f64>() / n;
+        let variance = image
+            .as_raw()
+            .iter()
+            .map(|&v| (v as f64 - mean).powi(2))
+            .sum::<f64>()
+            / n;
+        (mean, variance.sqrt())
+    }
+
+    #[test]
+    fn test_gaussian() {
+        let noise = gaussian(200, 200, 0.5, 0.1, 3);
+        let (mean, stddev) = mean_stddev(&noise);
+        assert!((mean - 127.5).abs() < 1.0, "{}", mean);
+        assert!((stddev - 25.5).abs() < 1.0, "{}", stddev);
+
+        assert_eq!(noise, gaussian(200, 200, 0.5, 0.1, 3));
+        assert_ne!(noise, gaussian(200, 200, 0.5, 0.1, 4));
+    }
+
+    #[test]
+    fn test_perlin() {
+        let noise = perlin(128, 128, 32.0, 1, 9);
+        assert_eq!(noise, perlin(128, 128, 32.0, 1, 9));
+        assert_ne!(noise, perlin(128, 128, 32.0, 1, 10));
+
+        // A single octave is smooth and zero on the lattice.
+        assert_eq!(noise.get_pixel(64, 32)[0], 128);
+        for y in 0..128 {
+            for x in 1..128 {
+                let step = noise.get_pixel(x, y)[0] as i32 - noise.get_pixel(x - 1, y)[0] as i32;
+                assert!(step.abs() <= 12, "{} at ({}, {})", step, x, y);
+            }
+        }
+        let (_, stddev) = mean_stddev(&noise);
+        assert!(stddev > 10.0, "{}", stddev);
+
+        let detailed = perlin(128, 128, 32.0, 5, 9);
+        assert_ne!(noise, detailed);
+    }
+
+    #[test]
+    fn test_add_noise() {
+        let original = RgbaImage::from_pixel(100, 100, Rgba([100, 150, 200, 77]));
+
+        let mut image = original.clone();
+        add_gaussian(&mut image, 0.0, 1);
+        assert_eq!(image, original);
+        add_gaussian(&mut image, 0.05, 1);
+        assert_ne!(image, original);
+        assert!(image.pixels().all(|p| p[3] == 77));
+        let mean = image.pixels().map(|p| p[1] as f64).sum::<f64>() / 10_000.0;
+        assert!((mean - 150.0).abs() < 1.0, "{}", mean);
+
+        let mut image = original.clone();
+        add_salt_and_pepper(&mut image, 0.2, 5);
+        let salt = image.pixels().filter(|&&p| p == Rgba([255, 255, 255, 77])).count();
+        let pepper = image.pixels().filter(|&&p| p == Rgba([0, 0, 0, 77])).count();
+        let unchanged = image.pixels().filter(|&&p| p == original[(0, 0)]).count();
+        assert_eq!(salt + pepper + unchanged, 10_000);
+        assert!(salt > 850 && salt < 1150 && pepper > 850 && pepper < 1150, "{} {}", salt, pepper);
+    }
+}
@@ -0,0 +1,170 @@
+//! Parallel versions of image processing functions, distributing the work over all cores.
+//!
+//! Each function computes the same result as its sequential counterpart, using the global
+//! `rayon` thread pool.
+
+use num_traits::NumCast;
+
+use crate::image::GenericImageView;
+use crate::imageops::sample::{
+    blur_filter, filter_of_type, sample_pixel, sample_weights, unsharpen_pixel, FilterType,
+    SampleWeights,
+};
+use crate::traits::{Pixel, Primitive};
+use crate::ImageBuffer;
+
+use rayon::prelude::*;
+
+// Create an image computing every pixel from its coordinates in parallel.
+fn par_from_fn<P, F>(width: u32, height: u32, f: F) -> ImageBuffer<P, Vec<P::Subpixel>>
+where
+    P: Pixel + Send + 'static,
+    P::Subpixel: Send + 'static,
+    F: Fn(u32, u32) -> P + Sync,
+{
+    let mut out = ImageBuffer::new(width, height);
+    out.par_enumerate_pixels_mut()
+        .for_each(|(x, y, pixel)| *pixel = f(x, y));
+    out
+}
+
+// Sample the columns and then the rows of the image with the precomputed weights.
+fn par_sample<I, P, S>(
+    image: &I,
+    vertical: &[SampleWeights],
+    horizontal: &[SampleWeights],
+) -> ImageBuffer<P, Vec<S>>
+where
+    I: GenericImageView<Pixel = P> + Sync,
+    P: Pixel<Subpixel = S> + Send + Sync + 'static,
+    S: Primitive + Send + Sync + 'static,
+{
+    let max: f32 = NumCast::from(S::max_value()).unwrap();
+    let (width, _) = image.dimensions();
+
+    let tmp = par_from_fn(width, vertical.len() as u32, |x, y| {
+        sample_pixel(image, &vertical[y as usize], max, |i| (x, i))
+    });
+    par_from_fn(horizontal.len() as u32, tmp.height(), |x, y| {
+        sample_pixel(&tmp, &horizontal[x as usize], max, |i| (i, y))
+    })
+}
+
+/// Resize the supplied image to the specified dimensions in parallel.
+///
+/// See [`resize`](fn.resize.html).
+pub fn par_resize<I, P, S>(
+    image: &I,
+    nwidth: u32,
+    nheight: u32,
+    filter: FilterType,
+) -> ImageBuffer<P, Vec<S>>
+where
+    I: GenericImageView<Pixel = P> + Sync,
+    P: Pixel<Subpixel = S> + Send + Sync + 'static,
+    S: Primitive + Send + Sync + 'static,
+{
+    let mut method = filter_of_type(filter);
+    let (width, height) = image.dimensions();
+
+    let vertical = sample_weights(&mut method, height, nheight);
+    let horizontal = sample_weights(&mut method, width, nwidth);
+    par_sample(image, &vertical, &horizontal)
+}
+
+/// Performs a Gaussian blur on the supplied image in parallel.
+///
+/// See [`blur`](fn.blur.html).
+pub fn par_blur<I, P, S>(image: &I, sigma: f32) -> ImageBuffer<P, Vec<S>>
+where
+    I: GenericImageView<Pixel = P> + Sync,
+    P: Pixel<Subpixel = S> + Send + Sync + 'static,
+    S: Primitive + Send + Sync + 'static,
+{
+    let mut method = blur_filter(sigma);
+    let (width, height) = image.dimensions();
+
+    let vertical = sample_weights(&mut method, height, height);
+    let horizontal = sample_weights(&mut method, width, width);
+    par_sample(image, &vertical, &horizontal)
+}
+
+/// Performs an unsharpen mask on the supplied image in parallel.
+///
+/// See [`unsharpen`](fn.unsharpen.html).
+pub fn par_unsharpen<I, P, S>(image: &I, sigma: f32, threshold: i32) -> ImageBuffer<P, Vec<S>>
+where
+    I: GenericImageView<Pixel = P> + Sync,
+    P: Pixel<Subpixel = S> + Send + Sync + 'static,
+    S: Primitive + Send + Sync + 'static,
+{
+    let mut tmp = par_blur(image, sigma);
+    let max: f32 = NumCast::from(S::default_max_value()).unwrap();
+
+    tmp.par_enumerate_pixels_mut().for_each(|(x, y, b)| {
+        *b = unsharpen_pixel(image.get_pixel(x, y), b, threshold, max);
+    });
+    tmp
+}
+
+/// Rotate an image 90 degrees clockwise in parallel.
+pub fn par_rotate90<I, P, S>(image: &I) -> ImageBuffer<P, Vec<S>>
+where
+    I: GenericImageView<Pixel = P> + Sync,
+    P: Pixel<Subpixel = S> + Send + Sync + 'static,
+    S: Primitive + Send + Sync + 'static,
+{
+    let (width, height) = image.dimensions();
+    par_from_fn(height, width, |x, y| image.get_pixel(y, height - x - 1))
+}
+
+/// Rotate an image 180 degrees clockwise in parallel.
+pub fn par_rotate180<I, P, S>(image: &I) -> ImageBuffer<P, Vec<S>>
+where
+    I: GenericImageView<Pixel = P> + Sync,
+    P: Pixel<Subpixel = S> + Send + Sync + 'static,
+    S: Primitive + Send + Sync + 'static,
+{
+    let (width, height) = image.dimensions();
+    par_from_fn(width, height, |x, y| {
+        image.get_pixel(width - x - 1, height - y - 1)
+    })
+}
+
+/// Rotate an image 270 degrees clockwise in parallel.
+pub fn par_rotate270<I, P, S>(image: &I) -> ImageBuffer<P, Vec<S>>
+where
+    I: GenericImageView<Pixel = P> + Sync,
+    P: Pixel<Subpixel = S> + Send + Sync + 'static,
+    S: Primitive + Send + Sync + 'static,
+{
+    let (width, height) = image.dimensions();
+    par_from_fn(height, width, |x, y| image.get_pixel(width - y - 1, x))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{par_blur, par_resize, par_rotate180, par_rotate270, par_rotate90, par_unsharpen};
+    use crate::imageops::{blur, resize, rotate180, rotate270, rotate90, unsharpen, FilterType};
+    use crate::{ImageBuffer, RgbImage};
+
+    fn test_image() -> RgbImage {
+        ImageBuffer::from_fn(23, 17, |x, y| {
+            crate::Rgb([(x * 11) as u8, (y * 13) as u8, (x * y) as u8])
+        })
+    }
+
+    #[test]
+    fn matches_sequential() {
+        let image = test_image();
+
+        for &filter in &[FilterType::Nearest, FilterType::Triangle, FilterType::Lanczos3] {
+            assert_eq!(par_resize(&image, 40, 9, filter), resize(&image, 40, 9, filter));
+        }
+        assert_eq!(par_blur(&image, 1.5), blur(&image, 1.5));
+        assert_eq!(par_unsharpen(&image, 1.5, 3), unsharpen(&image, 1.5, 3));
+        assert_eq!(par_rotate90(&image), rotate90(&image));
+        assert_eq!(par_rotate180(&image), rotate180(&image));
+        assert_eq!(par_rotate270(&image), rotate270(&image));
+    }
+}
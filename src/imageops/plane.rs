@@ -0,0 +1,287 @@
+//! Single channel floating point images, the working format of the multi-step filters.
+
+use num_traits::{NumCast, One, Zero};
+
+use crate::color::Luma;
+use crate::image::GenericImageView;
+use crate::imageops::sample::FloatNearest;
+use crate::traits::{Pixel, Primitive};
+use crate::utils::clamp;
+use crate::ImageBuffer;
+
+/// One channel of an image, with samples kept in their original scale.
+pub(crate) type Plane = ImageBuffer<Luma<f32>, Vec<f32>>;
+
+/// Split an image into one plane per channel.
+pub(crate) fn split<I, P, S>(image: &I) -> Vec<Plane>
+where
+    I: GenericImageView<Pixel = P>,
+    P: Pixel<Subpixel = S>,
+    S: Primitive,
+{
+    let (width, height) = image.dimensions();
+    let channels = <usize as From<u8>>::from(P::CHANNEL_COUNT);
+    let mut planes = vec![Plane::new(width, height); channels];
+
+    for (x, y, pixel) in image.pixels() {
+        for (plane, &sample) in planes.iter_mut().zip(pixel.channels()) {
+            plane.put_pixel(x, y, Luma([NumCast::from(sample).unwrap()]));
+        }
+    }
+
+    planes
+}
+
+/// Merge planes back into an image, rounding and clamping the samples to the subpixel range.
+///
+/// All planes must have the same dimensions and there must be one plane per channel.
+pub(crate) fn merge<P, S>(planes: &[Plane]) -> ImageBuffer<P, Vec<S>>
+where
+    P: Pixel<Subpixel = S> + 'static,
+    S: Primitive + 'static,
+{
+    debug_assert_eq!(planes.len(), <usize as From<u8>>::from(P::CHANNEL_COUNT));
+    let (width, height) = planes[0].dimensions();
+    let min: f32 = NumCast::from(S::min_value()).unwrap();
+    let max: f32 = NumCast::from(S::max_value()).unwrap();
+    let mut out = ImageBuffer::<P, Vec<S>>::new(width, height);
+
+    for (index, pixel) in out.pixels_mut().enumerate() {
+        for (sample, plane) in pixel.channels_mut().iter_mut().zip(planes) {
+            *sample = NumCast::from(FloatNearest(clamp(plane.as_raw()[index], min, max))).unwrap();
+        }
+    }
+
+    out
+}
+
+/// The index of the alpha channel of a pixel type, if it has one.
+pub(crate) fn alpha_channel<P: Pixel>() -> Option<usize> {
+    let zero = <P::Subpixel as Zero>::zero();
+    let one = <P::Subpixel as One>::one();
+    let probe = P::from_channels(zero, zero, zero, zero).map_with_alpha(|_| zero, |_| one);
+    probe.channels().iter().position(|&c| c == one)
+}
+
+/// The nominal white level of a subpixel type: its maximum for integers and `1.0` for floats.
+pub(crate) fn nominal_max<S: Primitive>() -> f32 {
+    let max: f64 = NumCast::from(S::max_value()).unwrap();
+    if max > std::u32::MAX as f64 {
+        1.0
+    } else {
+        max as f32
+    }
+}
+
+/// Compute the luminance of an image given as planes.
+///
+/// Planes of images with three or more channels are interpreted as RGB(A), planes of images with
+/// fewer channels as L(A). The alpha channel is ignored.
+pub(crate) fn luma(planes: &[Plane]) -> Plane {
+    if planes.len() < 3 {
+        return planes[0].clone();
+    }
+
+    let (width, height) = planes[0].dimensions();
+    let mut out = Plane::new(width, height);
+    let (r, g, b) = (planes[0].as_raw(), planes[1].as_raw(), planes[2].as_raw());
+    for (index, value) in out.iter_mut().enumerate() {
+        *value = 0.2126 * r[index] + 0.7152 * g[index] + 0.0722 * b[index];
+    }
+    out
+}
+
+/// Sample a plane, clamping the coordinates to its edges.
+#[inline]
+pub(crate) fn get_clamped(plane: &Plane, x: i64, y: i64) -> f32 {
+    let (width, height) = plane.dimensions();
+    let x = clamp(x, 0, width as i64 - 1) as usize;
+    let y = clamp(y, 0, height as i64 - 1) as usize;
+    plane.as_raw()[y * width as usize + x]
+}
+
+/// Convolve a plane with a one dimensional kernel along both axes.
+///
+/// The kernel must have odd length and is centered on the current sample. Samples outside of the
+/// plane are replaced by the nearest edge sample.
+pub(crate) fn convolve_separable(plane: &Plane, kernel: &[f32]) -> Plane {
+    let (width, height) = plane.dimensions();
+    let radius = (kernel.len() / 2) as i64;
+
+    let mut tmp = Plane::new(width, height);
+    for y in 0..height {
+        for x in 0..width {
+            let mut sum = 0.0;
+            for (k, &weight) in kernel.iter().enumerate() {
+                sum += weight * get_clamped(plane, x as i64 + k as i64 - radius, y as i64);
+            }
+            tmp.put_pixel(x, y, Luma([sum]));
+        }
+    }
+
+    let mut out = Plane::new(width, height);
+    for y in 0..height {
+        for x in 0..width {
+            let mut sum = 0.0;
+            for (k, &weight) in kernel.iter().enumerate() {
+                sum += weight * get_clamped(&tmp, x as i64, y as i64 + k as i64 - radius);
+            }
+            out.put_pixel(x, y, Luma([sum]));
+        }
+    }
+
+    out
+}
+
+/// A normalized gaussian kernel covering three standard deviations on either side.
+pub(crate) fn gaussian_kernel(sigma: f32) -> Vec<f32> {
+    let sigma = if sigma <= 0.0 { 1.0 } else { sigma };
+    let radius = (3.0 * sigma).ceil() as i32;
+    let mut kernel: Vec<f32> = (-radius..=radius)
+        .map(|i| (-(i * i) as f32 / (2.0 * sigma * sigma)).exp())
+        .collect();
+    let sum: f32 = kernel.iter().sum();
+    kernel.iter_mut().for_each(|k| *k /= sum);
+    kernel
+}
+
+/// Blur a plane with a gaussian of the given standard deviation.
+pub(crate) fn gaussian_blur(plane: &Plane, sigma: f32) -> Plane {
+    convolve_separable(plane, &gaussian_kernel(sigma))
+}
+
+/// The discrete Laplacian of a plane, using the 4-neighbourhood.
+pub(crate) fn laplacian(plane: &Plane) -> Plane {
+    let (width, height) = plane.dimensions();
+    ImageBuffer::from_fn(width, height, |x, y| {
+        let (x, y) = (x as i64, y as i64);
+        let value = get_clamped(plane, x - 1, y)
+            + get_clamped(plane, x + 1, y)
+            + get_clamped(plane, x, y - 1)
+            + get_clamped(plane, x, y + 1)
+            - 4.0 * get_clamped(plane, x, y);
+        Luma([value])
+    })
+}
+
+/// Halve the resolution of a plane after low pass filtering it.
+pub(crate) fn reduce(plane: &Plane) -> Plane {
+    const BINOMIAL: [f32; 5] = [1.0 / 16.0, 4.0 / 16.0, 6.0 / 16.0, 4.0 / 16.0, 1.0 / 16.0];
+    let smooth = convolve_separable(plane, &BINOMIAL);
+    let (width, height) = plane.dimensions();
+    ImageBuffer::from_fn((width + 1) / 2, (height + 1) / 2, |x, y| {
+        *smooth.get_pixel(2 * x, 2 * y)
+    })
+}
+
+/// Sample a plane at a fractional position with bilinear interpolation, clamping to its edges.
+pub(crate) fn sample_bilinear(plane: &Plane, x: f32, y: f32) -> f32 {
+    let (x0, y0) = (x.floor(), y.floor());
+    let (fx, fy) = (x - x0, y - y0);
+    let (x0, y0) = (x0 as i64, y0 as i64);
+
+    let top = get_clamped(plane, x0, y0) * (1.0 - fx) + get_clamped(plane, x0 + 1, y0) * fx;
+    let bottom =
+        get_clamped(plane, x0, y0 + 1) * (1.0 - fx) + get_clamped(plane, x0 + 1, y0 + 1) * fx;
+    top * (1.0 - fy) + bottom * fy
+}
+
+/// Bilinearly interpolate a plane to the given dimensions.
+pub(crate) fn expand(plane: &Plane, width: u32, height: u32) -> Plane {
+    let (src_width, src_height) = plane.dimensions();
+    let scale_x = src_width as f32 / width as f32;
+    let scale_y = src_height as f32 / height as f32;
+
+    ImageBuffer::from_fn(width, height, |x, y| {
+        let sx = ((x as f32 + 0.5) * scale_x - 0.5).max(0.0);
+        let sy = ((y as f32 + 0.5) * scale_y - 0.5).max(0.0);
+        Luma([sample_bilinear(plane, sx, sy)])
+    })
+}
+
+/// The number of pyramid levels such that the coarsest level is still at least 8 samples wide
+/// and high, but at most 8 levels.
+pub(crate) fn pyramid_levels(width: u32, height: u32) -> usize {
+    let mut size = width.min(height);
+    let mut levels = 1;
+    while size >= 16 && levels < 8 {
+        size = (size + 1) / 2;
+        levels += 1;
+    }
+    levels
+}
+
+/// Build a gaussian pyramid with `levels` levels, the first level being the plane itself.
+pub(crate) fn gaussian_pyramid(plane: &Plane, levels: usize) -> Vec<Plane> {
+    let mut pyramid = vec![plane.clone()];
+    for _ in 1..levels {
+        let next = reduce(pyramid.last().unwrap());
+        pyramid.push(next);
+    }
+    pyramid
+}
+
+/// Build a laplacian pyramid with `levels` levels.
+///
+/// Every level except the last stores the detail lost by reducing the previous level, the last
+/// level is the coarsest gaussian level. `collapse` reconstructs the plane exactly.
+pub(crate) fn laplacian_pyramid(plane: &Plane, levels: usize) -> Vec<Plane> {
+    let mut pyramid = gaussian_pyramid(plane, levels);
+    for level in 0..levels - 1 {
+        let (width, height) = pyramid[level].dimensions();
+        let coarse = expand(&pyramid[level + 1], width, height);
+        for (fine, coarse) in pyramid[level].iter_mut().zip(coarse.iter()) {
+            *fine -= *coarse;
+        }
+    }
+    pyramid
+}
+
+/// Reconstruct a plane from its laplacian pyramid.
+pub(crate) fn collapse(pyramid: &[Plane]) -> Plane {
+    let mut plane = pyramid.last().unwrap().clone();
+    for level in pyramid.iter().rev().skip(1) {
+        let (width, height) = level.dimensions();
+        plane = expand(&plane, width, height);
+        for (value, detail) in plane.iter_mut().zip(level.iter()) {
+            *value += *detail;
+        }
+    }
+    plane
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pyramid_roundtrip() {
+        let plane = Plane::from_fn(37, 23, |x, y| Luma([((x * 7 + y * 13) % 31) as f32]));
+        let levels = pyramid_levels(37, 23);
+        assert_eq!(levels, 2);
+        let restored = collapse(&laplacian_pyramid(&plane, levels));
+        for (a, b) in plane.iter().zip(restored.iter()) {
+            assert!((a - b).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn test_split_merge_roundtrip() {
+        let img = ImageBuffer::from_fn(5, 4, |x, y| crate::Rgb([x as u8 * 50, y as u8, 255]));
+        let planes = split(&img);
+        assert_eq!(planes.len(), 3);
+        let merged: ImageBuffer<crate::Rgb<u8>, _> = merge(&planes);
+        assert_eq!(merged, img);
+    }
+
+    #[test]
+    fn test_merge_clamps_to_subpixel_range() {
+        let plane = Plane::from_fn(3, 1, |x, _| Luma([[-40_000.0, -12.4, 300.0][x as usize]]));
+        let signed: ImageBuffer<Luma<i16>, _> = merge(std::slice::from_ref(&plane));
+        assert_eq!(signed.into_raw(), vec![std::i16::MIN, -12, 300]);
+        let unsigned: ImageBuffer<Luma<u8>, _> = merge(std::slice::from_ref(&plane));
+        assert_eq!(unsigned.into_raw(), vec![0, 0, 255]);
+        let float: ImageBuffer<Luma<f32>, _> = merge(&[plane]);
+        assert_eq!(float.into_raw(), vec![-40_000.0, -12.4, 300.0]);
+    }
+}
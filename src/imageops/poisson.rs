@@ -0,0 +1,239 @@
+//! Gradient domain image editing.
+
+use crate::image::{GenericImage, GenericImageView};
+use crate::imageops::overlay_bounds;
+use crate::imageops::plane::{self, Plane};
+use crate::traits::{Pixel, Primitive};
+use crate::{GrayImage, ImageBuffer};
+
+/// Blend the region of `source` selected by `mask` into `target` at the given coordinates,
+/// without visible seams.
+///
+/// Instead of copying the pixels, the gradients of the source are copied and the colors are
+/// reconstructed by solving the Poisson equation with the colors of the target around the region
+/// as boundary condition, as described by Pérez, Gangnet and Blake, "Poisson Image Editing",
+/// 2003. The inserted region thus adopts the surrounding brightness and tint of the target while
+/// keeping the details of the source. The mask should include a small margin around the object.
+///
+/// Pixels of `source` are selected where `mask` is non-zero, parts falling outside of `target`
+/// are ignored. The alpha channel of the target is left untouched.
+///
+/// # Panics
+///
+/// Panics if the dimensions of `mask` and `source` differ.
+pub fn seamless_clone<I, J, P, S>(target: &mut I, source: &J, mask: &GrayImage, x: u32, y: u32)
+where
+    I: GenericImage<Pixel = P>,
+    J: GenericImageView<Pixel = P>,
+    P: Pixel<Subpixel = S> + 'static,
+    S: Primitive + 'static,
+{
+    assert_eq!(
+        source.dimensions(),
+        mask.dimensions(),
+        "mask dimensions must match the source dimensions"
+    );
+
+    let (width, height) = overlay_bounds(target.dimensions(), source.dimensions(), x, y);
+    let (tw, th) = target.dimensions();
+
+    // Number the unknowns, the masked pixels within the target, in row major order.
+    let mut index = vec![None; tw as usize * th as usize];
+    let mut unknowns = Vec::new();
+    for sy in 0..height {
+        for sx in 0..width {
+            if mask.get_pixel(sx, sy)[0] != 0 {
+                index[((y + sy) * tw + x + sx) as usize] = Some(unknowns.len());
+                unknowns.push((sx, sy));
+            }
+        }
+    }
+    if unknowns.is_empty() {
+        return;
+    }
+
+    let mut target_planes = plane::split(target);
+    let source_planes = plane::split(source);
+    let alpha = plane::alpha_channel::<P>();
+
+    for (c, (target_plane, source_plane)) in
+        target_planes.iter_mut().zip(&source_planes).enumerate()
+    {
+        if Some(c) == alpha {
+            continue;
+        }
+
+        let system = PoissonSystem {
+            target: target_plane,
+            source: source_plane,
+            index: &index,
+            unknowns: &unknowns,
+            offset: (x, y),
+        };
+        let solution = system.solve();
+
+        let buffer: &mut [f32] = &mut *target_plane;
+        for (&(sx, sy), value) in unknowns.iter().zip(solution) {
+            buffer[((y + sy) * tw + x + sx) as usize] = value as f32;
+        }
+    }
+
+    let result: ImageBuffer<P, Vec<S>> = plane::merge(&target_planes);
+    for &(sx, sy) in &unknowns {
+        target.put_pixel(x + sx, y + sy, *result.get_pixel(x + sx, y + sy));
+    }
+}
+
+/// The discrete Poisson equation for one channel, restricted to the masked pixels.
+struct PoissonSystem<'a> {
+    target: &'a Plane,
+    source: &'a Plane,
+    index: &'a [Option<usize>],
+    unknowns: &'a [(u32, u32)],
+    offset: (u32, u32),
+}
+
+impl<'a> PoissonSystem<'a> {
+    /// The neighbours of an unknown that lie within the target, as source coordinates.
+    fn neighbours(&self, sx: u32, sy: u32) -> impl Iterator<Item = (i64, i64)> {
+        let (tw, th) = self.target.dimensions();
+        let (tx, ty) = (i64::from(self.offset.0 + sx), i64::from(self.offset.1 + sy));
+        let (sx, sy) = (i64::from(sx), i64::from(sy));
+        [(-1, 0), (1, 0), (0, -1), (0, 1)]
+            .iter()
+            .filter(move |&&(dx, dy)| {
+                tx + dx >= 0 && ty + dy >= 0 && tx + dx < i64::from(tw) && ty + dy < i64::from(th)
+            })
+            .map(move |&(dx, dy)| (sx + dx, sy + dy))
+    }
+
+    fn target_index(&self, sx: i64, sy: i64) -> usize {
+        let tw = i64::from(self.target.width());
+        ((i64::from(self.offset.1) + sy) * tw + i64::from(self.offset.0) + sx) as usize
+    }
+
+    /// Apply the negative Laplacian to the unknowns.
+    fn apply(&self, values: &[f64], out: &mut [f64]) {
+        for (o, &(sx, sy)) in out.iter_mut().zip(self.unknowns) {
+            let own = values[self.index[self.target_index(i64::from(sx), i64::from(sy))].unwrap()];
+            *o = 0.0;
+            for (nx, ny) in self.neighbours(sx, sy) {
+                *o += own;
+                if let Some(n) = self.index[self.target_index(nx, ny)] {
+                    *o -= values[n];
+                }
+            }
+        }
+    }
+
+    /// The right hand side: the divergence of the source gradients plus the boundary values.
+    fn rhs(&self) -> Vec<f64> {
+        self.unknowns
+            .iter()
+            .map(|&(sx, sy)| {
+                let own = f64::from(self.source.get_pixel(sx, sy)[0]);
+                let mut sum = 0.0;
+                for (nx, ny) in self.neighbours(sx, sy) {
+                    sum += own - f64::from(plane::get_clamped(self.source, nx, ny));
+                    let target = self.target_index(nx, ny);
+                    if self.index[target].is_none() {
+                        sum += f64::from(self.target.as_raw()[target]);
+                    }
+                }
+                sum
+            })
+            .collect()
+    }
+
+    /// Solve the system with the conjugate gradient method, starting from the source values.
+    fn solve(&self) -> Vec<f64> {
+        let n = self.unknowns.len();
+        let b = self.rhs();
+        let mut x: Vec<f64> = self
+            .unknowns
+            .iter()
+            .map(|&(sx, sy)| f64::from(self.source.get_pixel(sx, sy)[0]))
+            .collect();
+
+        let mut r = vec![0.0; n];
+        self.apply(&x, &mut r);
+        for (r, &b) in r.iter_mut().zip(&b) {
+            *r = b - *r;
+        }
+        let mut p = r.clone();
+        let mut ap = vec![0.0; n];
+        let mut rr: f64 = r.iter().map(|v| v * v).sum();
+        let tolerance = 1e-10 * b.iter().map(|v| v * v).sum::<f64>().max(1.0);
+
+        for _ in 0..(4 * n).max(100) {
+            if rr <= tolerance {
+                break;
+            }
+            self.apply(&p, &mut ap);
+            let pap: f64 = p.iter().zip(&ap).map(|(p, ap)| p * ap).sum();
+            if pap <= 0.0 {
+                break;
+            }
+            let alpha = rr / pap;
+            for i in 0..n {
+                x[i] += alpha * p[i];
+                r[i] -= alpha * ap[i];
+            }
+            let rr_next: f64 = r.iter().map(|v| v * v).sum();
+            let beta = rr_next / rr;
+            for i in 0..n {
+                p[i] = r[i] + beta * p[i];
+            }
+            rr = rr_next;
+        }
+
+        x
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::seamless_clone;
+    use crate::{GrayImage, ImageBuffer, Luma, Rgba};
+
+    #[test]
+    fn test_seamless_clone_matches_surroundings() {
+        // A bright source with a dark spot, inserted into a mid gray target.
+        let source = GrayImage::from_fn(20, 20, |x, y| {
+            let d = (x as i32 - 10).pow(2) + (y as i32 - 10).pow(2);
+            Luma([if d < 16 { 180 } else { 230 }])
+        });
+        let mask = GrayImage::from_fn(20, 20, |x, y| {
+            Luma([if x >= 2 && y >= 2 && x < 18 && y < 18 {
+                255
+            } else {
+                0
+            }])
+        });
+        let mut target = GrayImage::from_pixel(40, 40, Luma([100]));
+        seamless_clone(&mut target, &source, &mask, 10, 10);
+
+        // The flat part of the source blends into the target.
+        assert!((i32::from(target.get_pixel(12, 12)[0]) - 100).abs() <= 3);
+        // The detail is kept: the spot is still 50 levels darker than its surroundings.
+        let spot = i32::from(target.get_pixel(20, 20)[0]);
+        let ring = i32::from(target.get_pixel(20, 15)[0]);
+        assert!((ring - spot - 50).abs() <= 5, "spot {} ring {}", spot, ring);
+        // Pixels outside the mask are untouched.
+        assert_eq!(target.get_pixel(11, 11)[0], 100);
+        assert_eq!(target.get_pixel(0, 0)[0], 100);
+    }
+
+    #[test]
+    fn test_seamless_clone_clips_and_keeps_alpha() {
+        let source = ImageBuffer::from_pixel(8, 8, Rgba([50u8, 60, 70, 10]));
+        let mask = GrayImage::from_pixel(8, 8, Luma([1]));
+        let mut target = ImageBuffer::from_pixel(10, 10, Rgba([200u8, 100, 0, 255]));
+        seamless_clone(&mut target, &source, &mask, 6, 6);
+
+        // A flat source takes on the color of the target.
+        for pixel in target.pixels() {
+            assert_eq!(*pixel, Rgba([200, 100, 0, 255]));
+        }
+    }
+}
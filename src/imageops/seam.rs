@@ -0,0 +1,273 @@
+//! Content-aware resizing by seam carving.
+
+use std::cmp;
+
+use num_traits::NumCast;
+
+use crate::image::GenericImageView;
+use crate::imageops::sample::FloatNearest;
+use crate::traits::{Pixel, Primitive};
+use crate::ImageBuffer;
+
+/// Pixels in row-major order, the working format of seam carving.
+#[derive(Clone)]
+struct Grid<P> {
+    width: usize,
+    height: usize,
+    pixels: Vec<P>,
+}
+
+/// Resize an image to the given dimensions while preserving its salient content.
+///
+/// Instead of scaling the whole image, seams of least importance are removed or duplicated. A
+/// seam is a connected path of pixels from one edge of the image to the opposite one, with one
+/// pixel in every row (or column). The importance of a pixel is the gradient magnitude of the
+/// luminance around it, so flat regions such as sky are resized while objects keep their shape.
+///
+/// The width is changed first, then the height. When enlarging, the seams that would be removed
+/// first are duplicated, at most half of the current size at once so that the same seam is not
+/// repeated over and over.
+///
+/// # Panics
+///
+/// Panics if the image has no pixels in one dimension and should be enlarged in the other.
+pub fn seam_carve<I, P, S>(image: &I, new_width: u32, new_height: u32) -> ImageBuffer<P, Vec<S>>
+where
+    I: GenericImageView<Pixel = P>,
+    P: Pixel<Subpixel = S> + 'static,
+    S: Primitive + 'static,
+{
+    let (width, height) = image.dimensions();
+    let mut pixels = Vec::with_capacity(width as usize * height as usize);
+    for y in 0..height {
+        for x in 0..width {
+            pixels.push(image.get_pixel(x, y));
+        }
+    }
+
+    let grid = Grid {
+        width: width as usize,
+        height: height as usize,
+        pixels,
+    };
+    // Rows are carved as the columns of the transposed image.
+    let grid = carve_width(grid, new_width as usize);
+    let grid = carve_width(grid.transposed(), new_height as usize).transposed();
+
+    let mut out = ImageBuffer::new(new_width, new_height);
+    for (pixel, carved) in out.pixels_mut().zip(grid.pixels) {
+        *pixel = carved;
+    }
+    out
+}
+
+impl<P: Copy> Grid<P> {
+    fn transposed(&self) -> Grid<P> {
+        let pixels = (0..self.width)
+            .flat_map(|x| (0..self.height).map(move |y| self.pixels[y * self.width + x]))
+            .collect();
+
+        Grid {
+            width: self.height,
+            height: self.width,
+            pixels,
+        }
+    }
+}
+
+/// Remove or insert vertical seams until the grid has the requested width.
+fn carve_width<P, S>(mut grid: Grid<P>, new_width: usize) -> Grid<P>
+where
+    P: Pixel<Subpixel = S>,
+    S: Primitive,
+{
+    if grid.height == 0 {
+        grid.width = new_width;
+        return grid;
+    }
+    assert!(
+        grid.width > 0 || new_width == 0,
+        "an image without pixels can not be enlarged"
+    );
+
+    while grid.width > new_width {
+        let seam = find_seam(&grid);
+        grid.pixels = remove_seam(&grid.pixels, grid.width, &seam);
+        grid.width -= 1;
+    }
+
+    while grid.width < new_width {
+        let count = cmp::min(new_width - grid.width, cmp::max(grid.width / 2, 1));
+        grid = insert_seams(&grid, count);
+    }
+
+    grid
+}
+
+/// Duplicate the `count` seams that would be removed first, blending each with its right
+/// neighbour.
+fn insert_seams<P, S>(grid: &Grid<P>, count: usize) -> Grid<P>
+where
+    P: Pixel<Subpixel = S>,
+    S: Primitive,
+{
+    // Track the original column of every remaining pixel while removing the seams.
+    let mut carved = grid.clone();
+    let mut columns: Vec<usize> = (0..grid.height).flat_map(|_| 0..grid.width).collect();
+    let mut duplicate = vec![false; grid.pixels.len()];
+
+    for _ in 0..count {
+        let seam = find_seam(&carved);
+        for (y, &x) in seam.iter().enumerate() {
+            duplicate[y * grid.width + columns[y * carved.width + x]] = true;
+        }
+        carved.pixels = remove_seam(&carved.pixels, carved.width, &seam);
+        columns = remove_seam(&columns, carved.width, &seam);
+        carved.width -= 1;
+    }
+
+    let width = grid.width + count;
+    let mut pixels = Vec::with_capacity(width * grid.height);
+    for (row, flags) in grid.pixels.chunks(grid.width).zip(duplicate.chunks(grid.width)) {
+        for (x, (&pixel, &flag)) in row.iter().zip(flags).enumerate() {
+            pixels.push(pixel);
+            if flag {
+                let next = row.get(x + 1).cloned().unwrap_or(pixel);
+                pixels.push(average(pixel, next));
+            }
+        }
+    }
+
+    Grid {
+        width,
+        height: grid.height,
+        pixels,
+    }
+}
+
+/// Find the vertical seam with the least total energy, as its column in every row.
+fn find_seam<P, S>(grid: &Grid<P>) -> Vec<usize>
+where
+    P: Pixel<Subpixel = S>,
+    S: Primitive,
+{
+    let (width, height) = (grid.width, grid.height);
+    let luma: Vec<f32> = grid
+        .pixels
+        .iter()
+        .map(|p| NumCast::from(p.to_luma()[0]).unwrap())
+        .collect();
+    let at = |x: usize, y: usize| luma[y * width + x];
+
+    // The cheapest seam from the top row to each pixel.
+    let mut cost: Vec<f32> = Vec::with_capacity(width * height);
+    for y in 0..height {
+        for x in 0..width {
+            let dx = at(cmp::min(x + 1, width - 1), y) - at(x.saturating_sub(1), y);
+            let dy = at(x, cmp::min(y + 1, height - 1)) - at(x, y.saturating_sub(1));
+            let above = if y == 0 {
+                0.0
+            } else {
+                let row = &cost[(y - 1) * width..y * width];
+                row[neighbours(x, width)]
+                    .iter()
+                    .cloned()
+                    .fold(std::f32::INFINITY, f32::min)
+            };
+            cost.push(dx.abs() + dy.abs() + above);
+        }
+    }
+
+    let mut seam = vec![0; height];
+    seam[height - 1] = argmin(&cost[(height - 1) * width..]);
+    for y in (0..height - 1).rev() {
+        let range = neighbours(seam[y + 1], width);
+        let start = range.start;
+        seam[y] = start + argmin(&cost[y * width..(y + 1) * width][range]);
+    }
+
+    seam
+}
+
+/// The columns adjacent to `x`, including itself.
+fn neighbours(x: usize, width: usize) -> std::ops::Range<usize> {
+    x.saturating_sub(1)..cmp::min(x + 2, width)
+}
+
+/// The index of the first smallest value.
+fn argmin(values: &[f32]) -> usize {
+    let mut best = 0;
+    for (i, &v) in values.iter().enumerate() {
+        if v < values[best] {
+            best = i;
+        }
+    }
+    best
+}
+
+/// Remove one element per row of a row-major grid.
+fn remove_seam<T: Copy>(values: &[T], width: usize, seam: &[usize]) -> Vec<T> {
+    let mut out = Vec::with_capacity(values.len() - seam.len());
+    for (row, &x) in values.chunks(width).zip(seam) {
+        out.extend_from_slice(&row[..x]);
+        out.extend_from_slice(&row[x + 1..]);
+    }
+    out
+}
+
+fn average<P, S>(a: P, b: P) -> P
+where
+    P: Pixel<Subpixel = S>,
+    S: Primitive,
+{
+    a.map2(&b, |c, d| {
+        let c: f32 = NumCast::from(c).unwrap();
+        let d: f32 = NumCast::from(d).unwrap();
+        NumCast::from(FloatNearest((c + d) / 2.0)).unwrap()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::seam_carve;
+    use crate::{GrayImage, Luma, RgbImage};
+
+    // A detailed pattern on the left, flat gray on the right.
+    fn test_image() -> GrayImage {
+        GrayImage::from_fn(20, 10, |x, y| {
+            Luma([if x < 5 { ((x + y) % 2 * 200) as u8 } else { 100 }])
+        })
+    }
+
+    #[test]
+    fn test_seam_carve_shrinks_flat_regions() {
+        let image = test_image();
+        let carved = seam_carve(&image, 12, 10);
+        assert_eq!(carved.dimensions(), (12, 10));
+
+        // The pattern keeps its shape, only flat pixels were removed.
+        for (x, y, pixel) in carved.enumerate_pixels() {
+            let expected = if x < 5 { image.get_pixel(x, y) } else { &Luma([100]) };
+            assert_eq!(pixel, expected, "pixel ({}, {})", x, y);
+        }
+
+        assert_eq!(seam_carve(&image, 12, 6).dimensions(), (12, 6));
+    }
+
+    #[test]
+    fn test_seam_carve_enlarges() {
+        let image = test_image();
+        let carved = seam_carve(&image, 45, 13);
+        assert_eq!(carved.dimensions(), (45, 13));
+        for x in 0..5 {
+            assert_eq!(carved.get_pixel(x, 0), image.get_pixel(x, 0), "column {}", x);
+        }
+    }
+
+    #[test]
+    fn test_seam_carve_same_size() {
+        let image = RgbImage::from_fn(7, 5, |x, y| crate::Rgb([x as u8 * 30, y as u8 * 40, 7]));
+        assert_eq!(seam_carve(&image, 7, 5), image);
+        assert_eq!(seam_carve(&image, 0, 0).dimensions(), (0, 0));
+    }
+}
@@ -0,0 +1,318 @@
+//! Superpixel segmentation with simple linear iterative clustering.
+
+use num_traits::NumCast;
+
+use crate::color::Luma;
+use crate::color_space::rgb_to_lab;
+use crate::image::GenericImageView;
+use crate::imageops::{plane, srgb};
+use crate::imageops::sample::FloatNearest;
+use crate::traits::{Pixel, Primitive};
+use crate::utils::clamp;
+use crate::ImageBuffer;
+
+/// The result of a superpixel segmentation.
+#[derive(Clone, Debug)]
+pub struct Superpixels<P: Pixel> {
+    /// The index of the segment of every pixel.
+    pub labels: ImageBuffer<Luma<u32>, Vec<u32>>,
+    /// The average color of every segment, indexed by label.
+    pub colors: Vec<P>,
+}
+
+impl<P: Pixel + 'static> Superpixels<P> {
+    /// The number of segments.
+    pub fn len(&self) -> usize {
+        self.colors.len()
+    }
+
+    /// Returns `true` if there are no segments, which is only the case for empty images.
+    pub fn is_empty(&self) -> bool {
+        self.colors.is_empty()
+    }
+
+    /// Render an image where every pixel has the average color of its segment.
+    pub fn to_image(&self) -> ImageBuffer<P, Vec<P::Subpixel>> {
+        let (width, height) = self.labels.dimensions();
+        ImageBuffer::from_fn(width, height, |x, y| {
+            self.colors[self.labels.get_pixel(x, y)[0] as usize]
+        })
+    }
+}
+
+/// Partition an image into about `segments` compact regions of similar color.
+///
+/// This implements SLIC as described by Achanta et al., "SLIC Superpixels Compared to
+/// State-of-the-art Superpixel Methods", 2012. Pixels are clustered by their CIE L\*a\*b\* color
+/// and position, where `compactness` weighs the spatial distance against the color distance:
+/// values around `10.0` give a good balance, larger values produce more regular, grid-like
+/// segments. Every segment is connected and the labels are numbered consecutively from zero.
+/// Grayscale images are clustered by lightness, the alpha channel is ignored for clustering.
+pub fn slic<I, P, S>(image: &I, segments: u32, compactness: f32) -> Superpixels<P>
+where
+    I: GenericImageView<Pixel = P>,
+    P: Pixel<Subpixel = S> + 'static,
+    S: Primitive + 'static,
+{
+    const ITERATIONS: usize = 10;
+
+    let (width, height) = image.dimensions();
+    let (w, h) = (width as usize, height as usize);
+    let mut labels = vec![0u32; w * h];
+    if w == 0 || h == 0 {
+        return Superpixels {
+            labels: ImageBuffer::new(width, height),
+            colors: Vec::new(),
+        };
+    }
+
+    let features = lab_features(image);
+    let step = ((w * h) as f32 / segments.max(1) as f32).sqrt().max(1.0);
+
+    // Seed the clusters on a regular grid, moved to the lowest gradient in their neighbourhood
+    // to avoid starting on an edge.
+    let mut centers: Vec<[f32; 5]> = Vec::new();
+    let mut y = step / 2.0;
+    while y < h as f32 {
+        let mut x = step / 2.0;
+        while x < w as f32 {
+            let (cx, cy) = lowest_gradient(&features, w, h, x as usize, y as usize);
+            let f = features[cy * w + cx];
+            centers.push([f[0], f[1], f[2], cx as f32, cy as f32]);
+            x += step;
+        }
+        y += step;
+    }
+
+    let weight = (compactness / step) * (compactness / step);
+    let mut distances = vec![std::f32::MAX; w * h];
+    for _ in 0..ITERATIONS {
+        distances.iter_mut().for_each(|d| *d = std::f32::MAX);
+        for (k, center) in centers.iter().enumerate() {
+            let x0 = (center[3] - step).max(0.0) as usize;
+            let y0 = (center[4] - step).max(0.0) as usize;
+            let x1 = ((center[3] + step) as usize + 1).min(w);
+            let y1 = ((center[4] + step) as usize + 1).min(h);
+            for y in y0..y1 {
+                for x in x0..x1 {
+                    let f = features[y * w + x];
+                    let color = (f[0] - center[0]).powi(2)
+                        + (f[1] - center[1]).powi(2)
+                        + (f[2] - center[2]).powi(2);
+                    let space = (x as f32 - center[3]).powi(2) + (y as f32 - center[4]).powi(2);
+                    let distance = color + space * weight;
+                    if distance < distances[y * w + x] {
+                        distances[y * w + x] = distance;
+                        labels[y * w + x] = k as u32;
+                    }
+                }
+            }
+        }
+
+        let mut sums = vec![[0.0f64; 6]; centers.len()];
+        for (index, &label) in labels.iter().enumerate() {
+            let f = features[index];
+            let sum = &mut sums[label as usize];
+            sum[0] += f[0] as f64;
+            sum[1] += f[1] as f64;
+            sum[2] += f[2] as f64;
+            sum[3] += (index % w) as f64;
+            sum[4] += (index / w) as f64;
+            sum[5] += 1.0;
+        }
+        for (center, sum) in centers.iter_mut().zip(&sums) {
+            if sum[5] > 0.0 {
+                for (c, s) in center.iter_mut().zip(sum.iter()) {
+                    *c = (s / sum[5]) as f32;
+                }
+            }
+        }
+    }
+
+    let count = enforce_connectivity(&mut labels, w, h, (step * step / 4.0) as usize);
+    let colors = average_colors(image, &labels, count);
+    Superpixels {
+        labels: ImageBuffer::from_raw(width, height, labels).unwrap(),
+        colors,
+    }
+}
+
+/// The L\*a\*b\* color of every pixel.
+fn lab_features<I, P, S>(image: &I) -> Vec<[f32; 3]>
+where
+    I: GenericImageView<Pixel = P>,
+    P: Pixel<Subpixel = S>,
+    S: Primitive,
+{
+    let planes = plane::split(image);
+    let max = plane::nominal_max::<S>();
+    let [r, g, b] = srgb::rgb_channels::<P>().unwrap_or([0, 0, 0]);
+    (0..planes[0].len())
+        .map(|i| {
+            rgb_to_lab([
+                planes[r].as_raw()[i] / max,
+                planes[g].as_raw()[i] / max,
+                planes[b].as_raw()[i] / max,
+            ])
+        })
+        .collect()
+}
+
+/// The position with the smallest color gradient in the 3x3 neighbourhood of `(x, y)`.
+fn lowest_gradient(
+    features: &[[f32; 3]],
+    w: usize,
+    h: usize,
+    x: usize,
+    y: usize,
+) -> (usize, usize) {
+    let gradient = |x: usize, y: usize| {
+        let at = |x: usize, y: usize| features[y.min(h - 1) * w + x.min(w - 1)];
+        let (left, right) = (at(x.saturating_sub(1), y), at(x + 1, y));
+        let (up, down) = (at(x, y.saturating_sub(1)), at(x, y + 1));
+        (0..3)
+            .map(|c| (right[c] - left[c]).powi(2) + (down[c] - up[c]).powi(2))
+            .sum::<f32>()
+    };
+
+    let mut best = (x, y);
+    let mut best_gradient = gradient(x, y);
+    for ny in y.saturating_sub(1)..(y + 2).min(h) {
+        for nx in x.saturating_sub(1)..(x + 2).min(w) {
+            let g = gradient(nx, ny);
+            if g < best_gradient {
+                best = (nx, ny);
+                best_gradient = g;
+            }
+        }
+    }
+    best
+}
+
+/// Relabel the connected components of the label map, merging components smaller than
+/// `min_size` into a neighbouring one. Returns the number of labels.
+fn enforce_connectivity(labels: &mut [u32], w: usize, h: usize, min_size: usize) -> usize {
+    const UNSET: u32 = std::u32::MAX;
+    let mut new_labels = vec![UNSET; w * h];
+    let mut count = 0u32;
+    let mut component = Vec::new();
+
+    for start in 0..w * h {
+        if new_labels[start] != UNSET {
+            continue;
+        }
+
+        // A label of an already relabeled neighbour, which small components are merged into.
+        let (sx, sy) = (start % w, start / w);
+        let adjacent = if sx > 0 {
+            new_labels[start - 1]
+        } else if sy > 0 {
+            new_labels[start - w]
+        } else {
+            UNSET
+        };
+
+        component.clear();
+        component.push(start);
+        new_labels[start] = count;
+        let mut next = 0;
+        while next < component.len() {
+            let index = component[next];
+            next += 1;
+            let (x, y) = (index % w, index / w);
+            let neighbours = [
+                (x > 0, index.wrapping_sub(1)),
+                (x + 1 < w, index + 1),
+                (y > 0, index.wrapping_sub(w)),
+                (y + 1 < h, index + w),
+            ];
+            for &(valid, n) in &neighbours {
+                if valid && new_labels[n] == UNSET && labels[n] == labels[start] {
+                    new_labels[n] = count;
+                    component.push(n);
+                }
+            }
+        }
+
+        if component.len() < min_size && adjacent != UNSET {
+            for &index in &component {
+                new_labels[index] = adjacent;
+            }
+        } else {
+            count += 1;
+        }
+    }
+
+    labels.copy_from_slice(&new_labels);
+    count as usize
+}
+
+/// The average pixel of every segment.
+fn average_colors<I, P, S>(image: &I, labels: &[u32], count: usize) -> Vec<P>
+where
+    I: GenericImageView<Pixel = P>,
+    P: Pixel<Subpixel = S> + 'static,
+    S: Primitive + 'static,
+{
+    let channels = <usize as From<u8>>::from(P::CHANNEL_COUNT);
+    let mut sums = vec![vec![0.0f64; channels + 1]; count];
+    let width = image.width() as usize;
+    for (x, y, pixel) in image.pixels() {
+        let sum = &mut sums[labels[y as usize * width + x as usize] as usize];
+        for (s, &c) in sum.iter_mut().zip(pixel.channels()) {
+            *s += <f64 as NumCast>::from(c).unwrap();
+        }
+        sum[channels] += 1.0;
+    }
+
+    let max: f64 = NumCast::from(S::max_value()).unwrap();
+    sums.iter()
+        .map(|sum| {
+            let mut pixel = image.get_pixel(0, 0);
+            for (c, &s) in pixel.channels_mut().iter_mut().zip(sum.iter()) {
+                let mean = clamp(s / sum[channels], 0.0, max);
+                *c = NumCast::from(FloatNearest(mean as f32)).unwrap();
+            }
+            pixel
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::slic;
+    use crate::{ImageBuffer, Rgb};
+
+    #[test]
+    fn test_slic_follows_color_edges() {
+        // Four quadrants of distinct colors.
+        let img = ImageBuffer::from_fn(40, 40, |x, y| match (x < 20, y < 20) {
+            (true, true) => Rgb([200u8, 30, 30]),
+            (false, true) => Rgb([30, 200, 30]),
+            (true, false) => Rgb([30, 30, 200]),
+            (false, false) => Rgb([220, 220, 220]),
+        });
+
+        let superpixels = slic(&img, 16, 10.0);
+        assert!(superpixels.len() >= 8 && superpixels.len() <= 24);
+
+        // No segment crosses a quadrant boundary, so every average color is one of the inputs.
+        for (x, y, pixel) in img.enumerate_pixels() {
+            let label = superpixels.labels.get_pixel(x, y)[0] as usize;
+            assert_eq!(superpixels.colors[label], *pixel);
+        }
+        assert_eq!(superpixels.to_image(), img);
+    }
+
+    #[test]
+    fn test_slic_labels_are_consecutive() {
+        let img = ImageBuffer::from_fn(30, 17, |x, y| Rgb([(x * 8) as u8, (y * 15) as u8, 0]));
+        let superpixels = slic(&img, 10, 20.0);
+        let max = superpixels.labels.pixels().map(|p| p[0]).max().unwrap();
+        assert_eq!(max as usize + 1, superpixels.len());
+        for label in 0..superpixels.len() as u32 {
+            assert!(superpixels.labels.pixels().any(|p| p[0] == label));
+        }
+        assert!(slic(&ImageBuffer::<Rgb<u8>, _>::new(0, 0), 10, 10.0).is_empty());
+    }
+}
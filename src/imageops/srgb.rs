@@ -0,0 +1,33 @@
+//! The sRGB transfer function and the order of color channels, shared by the color operations.
+
+use crate::traits::Pixel;
+
+/// The indices of the red, green and blue channels of a pixel type, or `None` for grayscale
+/// pixels.
+pub(crate) fn rgb_channels<P: Pixel>() -> Option<[usize; 3]> {
+    if P::COLOR_MODEL.starts_with("RGB") {
+        Some([0, 1, 2])
+    } else if P::COLOR_MODEL.starts_with("BGR") {
+        Some([2, 1, 0])
+    } else {
+        None
+    }
+}
+
+/// Decode an sRGB sample in `0.0..=1.0` to linear light.
+pub(crate) fn to_linear(c: f32) -> f32 {
+    if c <= 0.040_45 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Encode a sample in linear light in `0.0..=1.0` with the sRGB transfer function.
+pub(crate) fn from_linear(c: f32) -> f32 {
+    if c <= 0.003_130_8 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
@@ -0,0 +1,254 @@
+//! Per-channel statistics of images.
+
+use num_traits::{Bounded, NumCast};
+
+use crate::image::GenericImageView;
+use crate::traits::{Pixel, Primitive};
+use crate::utils::clamp;
+use crate::GrayImage;
+
+/// Statistics of the samples of a single image channel.
+///
+/// If no samples were taken into account, all values are zero.
+#[derive(Clone, Debug)]
+pub struct ChannelStats {
+    /// The smallest sample value.
+    pub min: f64,
+    /// The largest sample value.
+    pub max: f64,
+    /// The arithmetic mean of the sample values.
+    pub mean: f64,
+    /// The population standard deviation of the sample values.
+    pub stddev: f64,
+    /// The number of samples that were taken into account.
+    pub count: u64,
+    samples: Samples,
+}
+
+#[derive(Clone, Debug)]
+enum Samples {
+    /// Exact counts of every possible value, used for `u8` and `u16` subpixels.
+    Histogram(Vec<u64>),
+    /// All sample values in ascending order, used for every other subpixel type.
+    Sorted(Vec<f64>),
+}
+
+impl ChannelStats {
+    fn new(histogram_bins: Option<usize>) -> Self {
+        let samples = match histogram_bins {
+            Some(bins) => Samples::Histogram(vec![0; bins]),
+            None => Samples::Sorted(Vec::new()),
+        };
+
+        ChannelStats {
+            min: 0.0,
+            max: 0.0,
+            mean: 0.0,
+            stddev: 0.0,
+            count: 0,
+            samples,
+        }
+    }
+
+    fn add(&mut self, value: f64, m2: &mut f64) {
+        if self.count == 0 {
+            self.min = value;
+            self.max = value;
+        } else {
+            self.min = self.min.min(value);
+            self.max = self.max.max(value);
+        }
+
+        // Welford's online algorithm, numerically stable for large images.
+        self.count += 1;
+        let delta = value - self.mean;
+        self.mean += delta / self.count as f64;
+        *m2 += delta * (value - self.mean);
+
+        match self.samples {
+            Samples::Histogram(ref mut bins) => bins[value as usize] += 1,
+            Samples::Sorted(ref mut values) => values.push(value),
+        }
+    }
+
+    fn finish(&mut self, m2: f64) {
+        if self.count > 0 {
+            self.stddev = (m2 / self.count as f64).sqrt();
+        }
+
+        if let Samples::Sorted(ref mut values) = self.samples {
+            values.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+        }
+    }
+
+    /// Returns the `p`-th percentile of the sample values, using the nearest-rank method.
+    ///
+    /// `p` is clamped to the range `0.0..=100.0`, so that `percentile(0.0)` is the minimum,
+    /// `percentile(50.0)` the median and `percentile(100.0)` the maximum sample value.
+    /// Returns `None` if no samples were taken into account.
+    pub fn percentile(&self, p: f64) -> Option<f64> {
+        if self.count == 0 {
+            return None;
+        }
+
+        let p = if p.is_nan() { 0.0 } else { clamp(p, 0.0, 100.0) };
+        let rank = ((p / 100.0 * self.count as f64).ceil() as u64).max(1);
+
+        match self.samples {
+            Samples::Histogram(ref bins) => {
+                let mut seen = 0;
+                for (value, &n) in bins.iter().enumerate() {
+                    seen += n;
+                    if seen >= rank {
+                        return Some(value as f64);
+                    }
+                }
+                Some(self.max)
+            }
+            Samples::Sorted(ref values) => Some(values[rank as usize - 1]),
+        }
+    }
+
+    /// Returns the median of the sample values, or `None` if no samples were taken into account.
+    pub fn median(&self) -> Option<f64> {
+        self.percentile(50.0)
+    }
+}
+
+/// Compute statistics for every channel of an image in a single pass.
+///
+/// The returned vector contains one entry per channel, in the channel order of the pixel type.
+/// Percentiles of `u8` and `u16` images are computed from an exact histogram, other subpixel
+/// types retain all sample values which requires memory proportional to the image size.
+///
+/// # Examples
+/// ```
+/// use image::{GrayImage, Luma};
+/// use image::imageops::channel_stats;
+///
+/// let img = GrayImage::from_fn(4, 1, |x, _| Luma([x as u8 * 10]));
+/// let stats = channel_stats(&img);
+/// assert_eq!(stats[0].min, 0.0);
+/// assert_eq!(stats[0].max, 30.0);
+/// assert_eq!(stats[0].mean, 15.0);
+/// assert_eq!(stats[0].median(), Some(10.0));
+/// ```
+pub fn channel_stats<I, P, S>(image: &I) -> Vec<ChannelStats>
+where
+    I: GenericImageView<Pixel = P>,
+    P: Pixel<Subpixel = S>,
+    S: Primitive,
+{
+    collect_stats(image, None)
+}
+
+/// Compute statistics for every channel of an image, only considering pixels whose value in
+/// `mask` is non-zero.
+///
+/// # Panics
+///
+/// Panics if the dimensions of `mask` and `image` differ.
+pub fn channel_stats_masked<I, P, S>(image: &I, mask: &GrayImage) -> Vec<ChannelStats>
+where
+    I: GenericImageView<Pixel = P>,
+    P: Pixel<Subpixel = S>,
+    S: Primitive,
+{
+    assert_eq!(
+        image.dimensions(),
+        mask.dimensions(),
+        "mask dimensions must match the image dimensions"
+    );
+    collect_stats(image, Some(mask))
+}
+
+fn collect_stats<I, P, S>(image: &I, mask: Option<&GrayImage>) -> Vec<ChannelStats>
+where
+    I: GenericImageView<Pixel = P>,
+    P: Pixel<Subpixel = S>,
+    S: Primitive,
+{
+    let min: f64 = NumCast::from(S::min_value()).unwrap();
+    let max: f64 = NumCast::from(<S as Bounded>::max_value()).unwrap();
+    // Only `u8` and `u16` have a value range small enough for an exact histogram.
+    let histogram_bins = if min == 0.0 && max <= <f64 as From<u16>>::from(std::u16::MAX) {
+        Some(max as usize + 1)
+    } else {
+        None
+    };
+
+    let channels = <usize as From<u8>>::from(P::CHANNEL_COUNT);
+    let mut stats = vec![ChannelStats::new(histogram_bins); channels];
+    let mut m2 = vec![0.0; channels];
+
+    for (x, y, pixel) in image.pixels() {
+        if let Some(mask) = mask {
+            if mask.get_pixel(x, y)[0] == 0 {
+                continue;
+            }
+        }
+
+        for (c, &sample) in pixel.channels().iter().enumerate() {
+            let value: f64 = NumCast::from(sample).unwrap();
+            stats[c].add(value, &mut m2[c]);
+        }
+    }
+
+    for (stat, m2) in stats.iter_mut().zip(m2) {
+        stat.finish(m2);
+    }
+
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{channel_stats, channel_stats_masked};
+    use crate::{GrayImage, ImageBuffer, Luma, Rgb};
+
+    #[test]
+    fn test_channel_stats_rgb() {
+        let img = ImageBuffer::from_fn(10, 10, |x, y| Rgb([x as u8, y as u8 * 2, 7]));
+        let stats = channel_stats(&img);
+        assert_eq!(stats.len(), 3);
+
+        assert_eq!(stats[0].count, 100);
+        assert_eq!(stats[0].min, 0.0);
+        assert_eq!(stats[0].max, 9.0);
+        assert!((stats[0].mean - 4.5).abs() < 1e-9);
+        assert!((stats[0].stddev - 8.25f64.sqrt()).abs() < 1e-9);
+
+        assert_eq!(stats[1].max, 18.0);
+        assert_eq!(stats[2].stddev, 0.0);
+        assert_eq!(stats[2].median(), Some(7.0));
+    }
+
+    #[test]
+    fn test_channel_stats_percentiles() {
+        let img = ImageBuffer::from_fn(100, 1, |x, _| Luma([x as u16 * 100]));
+        let stats = channel_stats(&img);
+        assert_eq!(stats[0].percentile(0.0), Some(0.0));
+        assert_eq!(stats[0].percentile(10.0), Some(900.0));
+        assert_eq!(stats[0].percentile(100.0), Some(9900.0));
+
+        let img = ImageBuffer::from_fn(100, 1, |x, _| Luma([x as f32 / 100.0]));
+        let stats = channel_stats(&img);
+        assert_eq!(stats[0].percentile(10.0), Some(f64::from(9.0f32 / 100.0)));
+        assert_eq!(stats[0].percentile(100.0), Some(f64::from(99.0f32 / 100.0)));
+    }
+
+    #[test]
+    fn test_channel_stats_masked() {
+        let img = GrayImage::from_fn(4, 4, |x, _| Luma([x as u8]));
+        let mask = GrayImage::from_fn(4, 4, |x, _| Luma([if x >= 2 { 255 } else { 0 }]));
+        let stats = channel_stats_masked(&img, &mask);
+        assert_eq!(stats[0].count, 8);
+        assert_eq!(stats[0].min, 2.0);
+        assert_eq!(stats[0].mean, 2.5);
+
+        let empty = GrayImage::new(4, 4);
+        let stats = channel_stats_masked(&img, &empty);
+        assert_eq!(stats[0].count, 0);
+        assert_eq!(stats[0].percentile(50.0), None);
+    }
+}
@@ -0,0 +1,338 @@
+//! Stitching of horizontal panoramas.
+
+use crate::color::Luma;
+use crate::error::{ImageError, ImageResult, ParameterError, ParameterErrorKind};
+use crate::image::GenericImageView;
+use crate::imageops::plane::{self, Plane};
+use crate::traits::{Pixel, Primitive};
+use crate::ImageBuffer;
+
+/// Stitch a sequence of overlapping photos, ordered from left to right, into a single panorama.
+///
+/// This is a deliberately constrained implementation: consecutive images are assumed to be
+/// related by a translation that is mostly horizontal, as produced by rotating a camera about its
+/// vertical axis. Every image must overlap its predecessor by at least a tenth of its width.
+///
+/// The stitching proceeds in three steps:
+///
+/// 1. If a `focal_length` (in pixels) is given, every image is first projected onto a cylinder,
+///    which turns the rotation of the camera into a horizontal translation.
+/// 2. The offset between consecutive images is estimated by maximizing the normalized
+///    cross-correlation of their luminance, coarse-to-fine on an image pyramid.
+/// 3. The images are placed onto a common canvas and merged with multi-band blending, which hides
+///    seams as well as differences in exposure.
+///
+/// Parts of the canvas not covered by any image are left at zero, including the alpha channel
+/// if the pixel type has one.
+///
+/// Returns an error if `images` is empty or the images do not all have the same dimensions.
+pub fn stitch_panorama<I, P, S>(
+    images: &[I],
+    focal_length: Option<f32>,
+) -> ImageResult<ImageBuffer<P, Vec<S>>>
+where
+    I: GenericImageView<Pixel = P>,
+    P: Pixel<Subpixel = S> + 'static,
+    S: Primitive + 'static,
+{
+    if images.is_empty() {
+        return Err(ImageError::Parameter(ParameterError::from_kind(
+            ParameterErrorKind::Generic("no images to stitch".into()),
+        )));
+    }
+
+    let (width, height) = images[0].dimensions();
+    if images.iter().any(|image| image.dimensions() != (width, height)) {
+        return Err(ImageError::Parameter(ParameterError::from_kind(
+            ParameterErrorKind::DimensionMismatch,
+        )));
+    }
+
+    let warped: Vec<(Vec<Plane>, Plane)> = images
+        .iter()
+        .map(|image| {
+            let planes = plane::split(image);
+            match focal_length {
+                Some(f) if f > 0.0 => cylindrical_warp(&planes, f),
+                _ => (planes, Plane::from_pixel(width, height, Luma([1.0]))),
+            }
+        })
+        .collect();
+
+    // Position of every image on an unbounded canvas.
+    let mut positions = vec![(0i64, 0i64)];
+    for pair in warped.windows(2) {
+        let (dx, dy) = estimate_offset(
+            (&plane::luma(&pair[0].0), &pair[0].1),
+            (&plane::luma(&pair[1].0), &pair[1].1),
+        );
+        let &(x, y) = positions.last().unwrap();
+        positions.push((x + dx, y + dy));
+    }
+
+    let min_x = positions.iter().map(|p| p.0).min().unwrap();
+    let min_y = positions.iter().map(|p| p.1).min().unwrap();
+    let max_x = positions.iter().map(|p| p.0).max().unwrap() + i64::from(width);
+    let max_y = positions.iter().map(|p| p.1).max().unwrap() + i64::from(height);
+    let canvas_width = (max_x - min_x) as u32;
+    let canvas_height = (max_y - min_y) as u32;
+    for position in positions.iter_mut() {
+        position.0 -= min_x;
+        position.1 -= min_y;
+    }
+
+    // Every canvas pixel is owned by the covering image whose center is closest, which places the
+    // seams in the middle of the overlaps.
+    let mut masks = vec![Plane::new(canvas_width, canvas_height); images.len()];
+    let mut covered = vec![false; canvas_width as usize * canvas_height as usize];
+    for y in 0..canvas_height {
+        for x in 0..canvas_width {
+            let mut owner = None;
+            let mut nearest = 0;
+            let mut nearest_distance = i64::max_value();
+            for (k, &(px, py)) in positions.iter().enumerate() {
+                let distance = (i64::from(x) - px - i64::from(width / 2)).abs();
+                let (lx, ly) = (i64::from(x) - px, i64::from(y) - py);
+                let inside = lx >= 0 && ly >= 0 && lx < i64::from(width) && ly < i64::from(height);
+                if inside && warped[k].1.get_pixel(lx as u32, ly as u32)[0] > 0.5 {
+                    let better = owner.map_or(true, |(_, d)| distance < d);
+                    if better {
+                        owner = Some((k, distance));
+                    }
+                }
+                if distance < nearest_distance {
+                    nearest = k;
+                    nearest_distance = distance;
+                }
+            }
+
+            let index = y as usize * canvas_width as usize + x as usize;
+            covered[index] = owner.is_some();
+            let owner = owner.map_or(nearest, |(k, _)| k);
+            masks[owner].put_pixel(x, y, Luma([1.0]));
+        }
+    }
+
+    let levels = plane::pyramid_levels(canvas_width, canvas_height);
+    let weights: Vec<Vec<Plane>> = masks
+        .iter()
+        .map(|mask| plane::gaussian_pyramid(mask, levels))
+        .collect();
+
+    let channels = warped[0].0.len();
+    let merged: Vec<Plane> = (0..channels)
+        .map(|c| {
+            let mut blended: Vec<Plane> = weights[0]
+                .iter()
+                .map(|level| Plane::new(level.width(), level.height()))
+                .collect();
+
+            for ((planes, _), (&(px, py), weights)) in
+                warped.iter().zip(positions.iter().zip(&weights))
+            {
+                // Extend the image over the whole canvas by repeating its edges, so that the
+                // coarse levels of the pyramid are not darkened by empty surroundings.
+                let placed = Plane::from_fn(canvas_width, canvas_height, |x, y| {
+                    let sample =
+                        plane::get_clamped(&planes[c], i64::from(x) - px, i64::from(y) - py);
+                    Luma([sample])
+                });
+
+                let pyramid = plane::laplacian_pyramid(&placed, levels);
+                for ((out, detail), weight) in blended.iter_mut().zip(&pyramid).zip(weights) {
+                    for ((o, d), w) in out.iter_mut().zip(detail.iter()).zip(weight.iter()) {
+                        *o += d * w;
+                    }
+                }
+            }
+
+            let mut merged = plane::collapse(&blended);
+            for (value, &covered) in merged.iter_mut().zip(&covered) {
+                if !covered {
+                    *value = 0.0;
+                }
+            }
+            merged
+        })
+        .collect();
+
+    Ok(plane::merge(&merged))
+}
+
+/// Project planes onto a cylinder with the given focal length in pixels.
+///
+/// Returns the warped planes and a mask that is one where the result has a source pixel. Outside
+/// of the mask the planes are filled by repeating the nearest edge.
+fn cylindrical_warp(planes: &[Plane], focal_length: f32) -> (Vec<Plane>, Plane) {
+    let (width, height) = planes[0].dimensions();
+    let (cx, cy) = ((width as f32 - 1.0) / 2.0, (height as f32 - 1.0) / 2.0);
+
+    let source = |x: u32, y: u32| {
+        let theta = (x as f32 - cx) / focal_length;
+        let sx = focal_length * theta.tan() + cx;
+        let sy = (y as f32 - cy) / theta.cos() + cy;
+        (sx, sy)
+    };
+
+    let warped = planes
+        .iter()
+        .map(|plane| {
+            Plane::from_fn(width, height, |x, y| {
+                let (sx, sy) = source(x, y);
+                Luma([plane::sample_bilinear(plane, sx, sy)])
+            })
+        })
+        .collect();
+
+    let mask = Plane::from_fn(width, height, |x, y| {
+        let (sx, sy) = source(x, y);
+        let inside =
+            sx >= 0.0 && sy >= 0.0 && sx <= width as f32 - 1.0 && sy <= height as f32 - 1.0;
+        Luma([if inside { 1.0 } else { 0.0 }])
+    });
+
+    (warped, mask)
+}
+
+/// Estimate the offset of `right` relative to `left`, each given as luminance and mask.
+fn estimate_offset(left: (&Plane, &Plane), right: (&Plane, &Plane)) -> (i64, i64) {
+    // Search exhaustively on a level that is at most 64 pixels wide, then refine on the finer
+    // levels in a small neighbourhood of the previous estimate.
+    let mut levels = 1;
+    let mut size = left.0.width();
+    while size > 64 && levels < 8 {
+        size = (size + 1) / 2;
+        levels += 1;
+    }
+
+    let left_pyramid = (
+        plane::gaussian_pyramid(left.0, levels),
+        plane::gaussian_pyramid(left.1, levels),
+    );
+    let right_pyramid = (
+        plane::gaussian_pyramid(right.0, levels),
+        plane::gaussian_pyramid(right.1, levels),
+    );
+
+    let coarsest = levels - 1;
+    let (width, height) = left_pyramid.0[coarsest].dimensions();
+    let (width, height) = (i64::from(width), i64::from(height));
+    let mut candidates = Vec::new();
+    for dy in -(height / 4)..=(height / 4) {
+        for dx in (width / 10).max(1)..width {
+            candidates.push((dx, dy));
+        }
+    }
+
+    let mut best = (0, 0);
+    for level in (0..levels).rev() {
+        if level != coarsest {
+            let (bx, by) = (best.0 * 2, best.1 * 2);
+            candidates.clear();
+            for dy in -2..=2 {
+                for dx in -2..=2 {
+                    candidates.push((bx + dx, by + dy));
+                }
+            }
+        }
+
+        let a = (&left_pyramid.0[level], &left_pyramid.1[level]);
+        let b = (&right_pyramid.0[level], &right_pyramid.1[level]);
+        let mut best_score = std::f64::NEG_INFINITY;
+        for &(dx, dy) in &candidates {
+            if let Some(score) = correlation(a, b, dx, dy) {
+                if score > best_score {
+                    best_score = score;
+                    best = (dx, dy);
+                }
+            }
+        }
+    }
+
+    best
+}
+
+/// The normalized cross-correlation of `a` and `b` shifted by `(dx, dy)`, in their overlap.
+///
+/// Returns `None` if the overlap is too small to be meaningful.
+fn correlation(a: (&Plane, &Plane), b: (&Plane, &Plane), dx: i64, dy: i64) -> Option<f64> {
+    let (width, height) = a.0.dimensions();
+    let (width, height) = (i64::from(width), i64::from(height));
+    if dx <= 0 || dx >= width || dy.abs() >= height {
+        return None;
+    }
+
+    let (mut n, mut sum_a, mut sum_b, mut sum_aa, mut sum_bb, mut sum_ab) =
+        (0.0f64, 0.0, 0.0, 0.0, 0.0, 0.0);
+    for y in dy.max(0)..height.min(height + dy) {
+        for x in dx..width {
+            let (ax, ay) = (x as u32, y as u32);
+            let (bx, by) = ((x - dx) as u32, (y - dy) as u32);
+            if a.1.get_pixel(ax, ay)[0] < 0.5 || b.1.get_pixel(bx, by)[0] < 0.5 {
+                continue;
+            }
+
+            let va = f64::from(a.0.get_pixel(ax, ay)[0]);
+            let vb = f64::from(b.0.get_pixel(bx, by)[0]);
+            n += 1.0;
+            sum_a += va;
+            sum_b += vb;
+            sum_aa += va * va;
+            sum_bb += vb * vb;
+            sum_ab += va * vb;
+        }
+    }
+
+    if n < 16.0 {
+        return None;
+    }
+
+    let covariance = sum_ab - sum_a * sum_b / n;
+    let variance = (sum_aa - sum_a * sum_a / n) * (sum_bb - sum_b * sum_b / n);
+    if variance <= 0.0 {
+        return Some(0.0);
+    }
+    Some(covariance / variance.sqrt())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::stitch_panorama;
+    use crate::{GrayImage, ImageBuffer, Luma};
+
+    fn scene(x: u32, y: u32) -> u8 {
+        let (x, y) = (x as f32, y as f32);
+        (128.0 + 60.0 * (x / 7.0).sin() * (y / 5.0).cos() + 40.0 * (x / 23.0 + y / 17.0).sin())
+            as u8
+    }
+
+    #[test]
+    fn test_stitch_translated_views() {
+        let views: Vec<GrayImage> = (0..3)
+            .map(|k| ImageBuffer::from_fn(80, 60, |x, y| Luma([scene(x + 50 * k, y + 2 * k)])))
+            .collect();
+
+        let panorama = stitch_panorama(&views, None).unwrap();
+        assert_eq!(panorama.dimensions(), (180, 64));
+
+        // The middle band is covered by every view and must match the scene closely.
+        for x in 10..170 {
+            let expected = scene(x, 30);
+            let actual = panorama.get_pixel(x, 30)[0];
+            assert!(
+                (i32::from(expected) - i32::from(actual)).abs() <= 2,
+                "mismatch at {}: {} != {}",
+                x,
+                actual,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_stitch_rejects_mismatched_dimensions() {
+        let views = vec![GrayImage::new(10, 10), GrayImage::new(10, 11)];
+        assert!(stitch_panorama(&views, None).is_err());
+        assert!(stitch_panorama::<GrayImage, _, _>(&[], None).is_err());
+    }
+}
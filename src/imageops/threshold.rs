@@ -0,0 +1,174 @@
+//! Binarization of grayscale images.
+
+use std::cmp;
+
+use crate::imageops::integral_image;
+use crate::math::Rect;
+use crate::{GrayImage, Luma};
+
+/// How [`threshold`] decides which pixels belong to the foreground.
+///
+/// [`threshold`]: fn.threshold.html
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ThresholdMethod {
+    /// Pixels brighter than the given level are foreground.
+    Fixed(u8),
+    /// The level is chosen with Otsu's method, see [`otsu_level`].
+    ///
+    /// [`otsu_level`]: fn.otsu_level.html
+    Otsu,
+    /// Every pixel is compared to the mean of the `block` × `block` pixels around it, minus `c`.
+    ///
+    /// This copes with uneven lighting, such as shadows on scanned or photographed documents.
+    /// Pixels outside of the image are left out of the mean. A positive `c` keeps flat regions
+    /// in the foreground, while a negative one moves them to the background.
+    AdaptiveMean {
+        /// The side length of the neighbourhood, should be odd.
+        block: u32,
+        /// The offset subtracted from the mean.
+        c: i32,
+    },
+}
+
+/// Turn a grayscale image into a binary one.
+///
+/// Foreground pixels are set to 255 and background pixels to 0, so dark text on a light page
+/// becomes background. Use [`invert`] on either the input or the result to swap them.
+///
+/// [`invert`]: fn.invert.html
+pub fn threshold(image: &GrayImage, method: ThresholdMethod) -> GrayImage {
+    let (width, height) = image.dimensions();
+    let binary = |foreground: bool| Luma([if foreground { 255 } else { 0 }]);
+
+    match method {
+        ThresholdMethod::Fixed(level) => {
+            GrayImage::from_fn(width, height, |x, y| binary(image.get_pixel(x, y)[0] > level))
+        }
+        ThresholdMethod::Otsu => {
+            let level = otsu_level(image);
+            GrayImage::from_fn(width, height, |x, y| binary(image.get_pixel(x, y)[0] > level))
+        }
+        ThresholdMethod::AdaptiveMean { block, c } => {
+            let integral = integral_image::<_, _, i64>(image);
+            let radius = block / 2;
+            GrayImage::from_fn(width, height, |x, y| {
+                let (x0, y0) = (x.saturating_sub(radius), y.saturating_sub(radius));
+                let x1 = cmp::min(x.saturating_add(radius).saturating_add(1), width);
+                let y1 = cmp::min(y.saturating_add(radius).saturating_add(1), height);
+                let count = i64::from(x1 - x0) * i64::from(y1 - y0);
+                let rect = Rect { x: x0, y: y0, width: x1 - x0, height: y1 - y0 };
+                let sum = integral.sum_region(rect)[0];
+
+                // Compares the pixel to `sum / count - c` without rounding.
+                let value = i64::from(image.get_pixel(x, y)[0]);
+                binary(value * count > sum - i64::from(c) * count)
+            })
+        }
+    }
+}
+
+/// The level that best separates the pixels of an image into a dark and a bright class.
+///
+/// Otsu's method picks the level maximizing the variance between the two classes, which works
+/// well for images with a bimodal histogram such as scanned text. Pixels brighter than the level
+/// form the bright class.
+pub fn otsu_level(image: &GrayImage) -> u8 {
+    let mut histogram = [0u64; 256];
+    for pixel in image.pixels() {
+        histogram[pixel[0] as usize] += 1;
+    }
+
+    let total: u64 = histogram.iter().sum();
+    let total_sum: f64 = histogram
+        .iter()
+        .enumerate()
+        .map(|(value, &count)| value as f64 * count as f64)
+        .sum();
+
+    let mut level = 0;
+    let mut best = 0.0;
+    let mut dark = 0;
+    let mut dark_sum = 0.0;
+    for (value, &count) in histogram.iter().enumerate() {
+        dark += count;
+        dark_sum += value as f64 * count as f64;
+        if dark == 0 {
+            continue;
+        }
+        let bright = total - dark;
+        if bright == 0 {
+            break;
+        }
+
+        let dark_mean = dark_sum / dark as f64;
+        let bright_mean = (total_sum - dark_sum) / bright as f64;
+        let variance = dark as f64 * bright as f64 * (dark_mean - bright_mean).powi(2);
+        if variance > best {
+            best = variance;
+            level = value as u8;
+        }
+    }
+
+    level
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{otsu_level, threshold, ThresholdMethod};
+    use crate::{GrayImage, ImageBuffer, Luma};
+
+    #[test]
+    fn test_fixed() {
+        let image: GrayImage = ImageBuffer::from_raw(4, 1, vec![0, 100, 101, 255]).unwrap();
+        let binary = threshold(&image, ThresholdMethod::Fixed(100));
+        assert_eq!(binary.into_raw(), vec![0, 0, 255, 255]);
+    }
+
+    #[test]
+    fn test_otsu() {
+        let image: GrayImage = ImageBuffer::from_raw(2, 1, vec![10, 20]).unwrap();
+        assert_eq!(otsu_level(&image), 10);
+
+        // Two noisy classes around 50 and 200.
+        let image = GrayImage::from_fn(20, 20, |x, y| {
+            let noise = ((x * 7 + y * 13) % 21) as u8;
+            Luma([if x < 12 { 40 + noise } else { 190 + noise }])
+        });
+        let level = otsu_level(&image);
+        assert!(level >= 60 && level < 190, "level {}", level);
+
+        let binary = threshold(&image, ThresholdMethod::Otsu);
+        for (x, _, pixel) in binary.enumerate_pixels() {
+            assert_eq!(pixel[0], if x < 12 { 0 } else { 255 });
+        }
+    }
+
+    #[test]
+    fn test_adaptive_mean() {
+        // Dark marks on a background getting brighter from left to right.
+        let is_mark = |x: u32, y: u32| (x / 2 == 5 || x / 2 == 10) && y / 2 == 3;
+        let image = GrayImage::from_fn(32, 16, |x, y| {
+            let background = 60 + x as u8 * 4;
+            Luma([if is_mark(x, y) { background - 50 } else { background }])
+        });
+
+        let method = ThresholdMethod::AdaptiveMean { block: 7, c: 10 };
+        let binary = threshold(&image, method);
+        for (x, y, pixel) in binary.enumerate_pixels() {
+            let expected = if is_mark(x, y) { 0 } else { 255 };
+            assert_eq!(pixel[0], expected, "pixel ({}, {})", x, y);
+        }
+
+        // A global level can not separate them.
+        let global = threshold(&image, ThresholdMethod::Otsu);
+        assert_ne!(global, binary);
+    }
+
+    #[test]
+    fn test_empty() {
+        let image = GrayImage::new(0, 3);
+        let method = ThresholdMethod::AdaptiveMean { block: 5, c: 0 };
+        assert_eq!(threshold(&image, method).dimensions(), (0, 3));
+        assert_eq!(otsu_level(&image), 0);
+    }
+}
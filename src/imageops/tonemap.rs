@@ -0,0 +1,130 @@
+//! Mapping of high dynamic range images to displayable ones.
+
+use crate::buffer_::Rgb32FImage;
+use crate::color::Rgb;
+use crate::imageops::srgb;
+use crate::utils::clamp;
+use crate::RgbImage;
+
+/// The curve with which [`tonemap`] compresses the range of an image.
+///
+/// [`tonemap`]: fn.tonemap.html
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TonemapOperator {
+    /// The global operator of Reinhard et al., "Photographic Tone Reproduction for Digital
+    /// Images", 2002, `L / (1 + L)` on the luminance.
+    ///
+    /// It keeps the hue and saturation of every pixel, but flattens the contrast of bright
+    /// regions, and only reaches white for infinite luminance.
+    Reinhard,
+    /// The approximation of the filmic curve of the Academy Color Encoding System by Krzysztof
+    /// Narkowicz, applied to every channel.
+    ///
+    /// It has a toe in the shadows and a shoulder in the highlights, which gives a punchy look
+    /// and lets very bright colors fade to white as on film.
+    Aces,
+    /// The filmic curve of John Hable for Uncharted 2, applied to every channel, with white at
+    /// a linear value of 11.2.
+    ///
+    /// It is softer than `Aces` and keeps more detail in the highlights.
+    Hable,
+}
+
+/// Convert an image in linear light of unbounded range, as read from an OpenEXR or Radiance
+/// HDR file, to an 8-bit sRGB image.
+///
+/// The colors are compressed into `[0, 1]` with the curve of `operator` and then encoded with
+/// the sRGB transfer function. A linear value of `1.0` is taken as the brightness that a
+/// display shows as mid-to-light gray, so scale the image beforehand to change the exposure.
+/// Negative and NaN samples are treated as zero, infinite ones as very bright.
+///
+/// # Examples
+///
+/// ```
+/// use image::imageops::{tonemap, TonemapOperator};
+/// use image::{ImageBuffer, Rgb};
+///
+/// let hdr = ImageBuffer::from_fn(3, 1, |x, _| Rgb([[0.0, 1.0, 1000.0][x as usize]; 3]));
+/// let sdr = tonemap(&hdr, TonemapOperator::Reinhard);
+/// assert_eq!(sdr.get_pixel(0, 0), &Rgb([0, 0, 0]));
+/// assert_eq!(sdr.get_pixel(1, 0), &Rgb([188, 188, 188]));
+/// assert_eq!(sdr.get_pixel(2, 0), &Rgb([255, 255, 255]));
+/// ```
+pub fn tonemap(image: &Rgb32FImage, operator: TonemapOperator) -> RgbImage {
+    let (width, height) = image.dimensions();
+    RgbImage::from_fn(width, height, |x, y| {
+        let mut rgb = image.get_pixel(x, y).0;
+        for v in rgb.iter_mut() {
+            // Bounded by the largest half float, which keeps the curves away from infinities.
+            *v = if v.is_nan() { 0.0 } else { clamp(*v, 0.0, 65504.0) };
+        }
+
+        let mapped = match operator {
+            TonemapOperator::Reinhard => {
+                let luminance = 0.2126 * rgb[0] + 0.7152 * rgb[1] + 0.0722 * rgb[2];
+                let scale = if luminance > 0.0 { 1.0 / (1.0 + luminance) } else { 1.0 };
+                [rgb[0] * scale, rgb[1] * scale, rgb[2] * scale]
+            }
+            TonemapOperator::Aces => [aces(rgb[0]), aces(rgb[1]), aces(rgb[2])],
+            TonemapOperator::Hable => {
+                let white = hable(HABLE_WHITE);
+                [
+                    hable(2.0 * rgb[0]) / white,
+                    hable(2.0 * rgb[1]) / white,
+                    hable(2.0 * rgb[2]) / white,
+                ]
+            }
+        };
+
+        let encode = |v: f32| (srgb::from_linear(clamp(v, 0.0, 1.0)) * 255.0).round() as u8;
+        Rgb([encode(mapped[0]), encode(mapped[1]), encode(mapped[2])])
+    })
+}
+
+/// The linear value that the Hable curve maps to white.
+const HABLE_WHITE: f32 = 11.2;
+
+fn aces(x: f32) -> f32 {
+    let (a, b, c, d, e) = (2.51, 0.03, 2.43, 0.59, 0.14);
+    (x * (a * x + b)) / (x * (c * x + d) + e)
+}
+
+fn hable(x: f32) -> f32 {
+    let (a, b, c, d, e, f) = (0.15, 0.50, 0.10, 0.20, 0.02, 0.30);
+    ((x * (a * x + c * b) + d * e) / (x * (a * x + b) + d * f)) - e / f
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{tonemap, TonemapOperator};
+    use crate::{ImageBuffer, Rgb};
+
+    #[test]
+    fn test_operators_are_monotone() {
+        let hdr = ImageBuffer::from_fn(64, 1, |x, _| {
+            let v = if x == 0 { 0.0 } else { (x as f32 / 4.0 - 8.0).exp2() };
+            Rgb([v, v / 2.0, v / 4.0])
+        });
+        for &operator in &[TonemapOperator::Reinhard, TonemapOperator::Aces, TonemapOperator::Hable] {
+            let sdr = tonemap(&hdr, operator);
+            assert_eq!(sdr.get_pixel(0, 0), &Rgb([0, 0, 0]), "{:?}", operator);
+            for c in 0..3 {
+                let channel: Vec<u8> = sdr.pixels().map(|p| p[c]).collect();
+                assert!(channel.windows(2).all(|w| w[0] <= w[1]), "{:?}: {:?}", operator, channel);
+            }
+            // Brighter colors keep their order of channels.
+            assert!(sdr.pixels().all(|p| p[0] >= p[1] && p[1] >= p[2]), "{:?}", operator);
+            assert!(sdr.get_pixel(63, 0)[0] >= 250, "{:?}", operator);
+        }
+    }
+
+    #[test]
+    fn test_invalid_samples() {
+        let hdr = ImageBuffer::from_pixel(1, 1, Rgb([std::f32::NAN, -3.0, std::f32::INFINITY]));
+        for &operator in &[TonemapOperator::Reinhard, TonemapOperator::Aces, TonemapOperator::Hable] {
+            let sdr = tonemap(&hdr, operator);
+            assert_eq!(&sdr.get_pixel(0, 0).0[..2], &[0, 0], "{:?}", operator);
+            assert!(sdr.get_pixel(0, 0)[2] >= 250, "{:?}", operator);
+        }
+    }
+}
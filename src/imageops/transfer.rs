@@ -0,0 +1,133 @@
+//! Transfer of the color statistics of one image to another.
+
+use crate::color_space::{lab_to_rgb, rgb_to_lab};
+use crate::image::GenericImageView;
+use crate::imageops::plane::{self, Plane};
+use crate::imageops::srgb;
+use crate::traits::{Pixel, Primitive};
+use crate::ImageBuffer;
+
+/// Give `image` the overall color mood of `reference`.
+///
+/// Following Reinhard et al., "Color Transfer between Images", 2001, every channel of the image
+/// is shifted and scaled in CIE L\*a\*b\* space so that its mean and standard deviation match
+/// those of the reference. Harmonizing a batch of photos is done by transferring the colors of
+/// the same reference to each of them. Grayscale images only have their lightness matched, the
+/// alpha channel is left untouched.
+pub fn color_transfer<I, J, P, S>(image: &I, reference: &J) -> ImageBuffer<P, Vec<S>>
+where
+    I: GenericImageView<Pixel = P>,
+    J: GenericImageView<Pixel = P>,
+    P: Pixel<Subpixel = S> + 'static,
+    S: Primitive + 'static,
+{
+    let mut planes = plane::split(image);
+    if planes[0].is_empty() || reference.width() == 0 || reference.height() == 0 {
+        return plane::merge(&planes);
+    }
+
+    let max = plane::nominal_max::<S>();
+    let channels = srgb::rgb_channels::<P>();
+    let source = to_lab(&planes, channels, max);
+    let target = to_lab(&plane::split(reference), channels, max);
+
+    let source_stats: Vec<(f64, f64)> = source.iter().map(|c| mean_stddev(c)).collect();
+    let target_stats: Vec<(f64, f64)> = target.iter().map(|c| mean_stddev(c)).collect();
+
+    let pixels = source[0].len();
+    for index in 0..pixels {
+        let mut value = [0.0f32; 3];
+        for (c, v) in value.iter_mut().enumerate() {
+            let (mean, stddev) = source_stats[c];
+            let (target_mean, target_stddev) = target_stats[c];
+            let scale = if stddev > 1e-6 {
+                target_stddev / stddev
+            } else {
+                1.0
+            };
+            *v = ((f64::from(source[c][index]) - mean) * scale + target_mean) as f32;
+        }
+
+        let rgb = lab_to_rgb(value);
+        match channels {
+            Some(rgb_indices) => {
+                for (&c, &v) in rgb_indices.iter().zip(&rgb) {
+                    let buffer: &mut [f32] = &mut planes[c];
+                    buffer[index] = v * max;
+                }
+            }
+            None => {
+                let buffer: &mut [f32] = &mut planes[0];
+                buffer[index] = rgb[1] * max;
+            }
+        }
+    }
+
+    plane::merge(&planes)
+}
+
+/// Convert the color channels of an image to three planes of L\*, a\* and b\* values.
+fn to_lab(planes: &[Plane], channels: Option<[usize; 3]>, max: f32) -> [Vec<f32>; 3] {
+    let count = planes[0].len();
+    let mut out = [vec![0.0; count], vec![0.0; count], vec![0.0; count]];
+    let [r, g, b] = channels.unwrap_or([0, 0, 0]);
+    for index in 0..count {
+        let rgb = [
+            planes[r].as_raw()[index] / max,
+            planes[g].as_raw()[index] / max,
+            planes[b].as_raw()[index] / max,
+        ];
+        let lab = rgb_to_lab(rgb);
+        for (plane, &value) in out.iter_mut().zip(&lab) {
+            plane[index] = value;
+        }
+    }
+    out
+}
+
+fn mean_stddev(values: &[f32]) -> (f64, f64) {
+    let n = values.len() as f64;
+    let mean = values.iter().map(|&v| f64::from(v)).sum::<f64>() / n;
+    let variance = values
+        .iter()
+        .map(|&v| (f64::from(v) - mean) * (f64::from(v) - mean))
+        .sum::<f64>()
+        / n;
+    (mean, variance.sqrt())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::color_transfer;
+    use crate::imageops::channel_stats;
+    use crate::{GrayImage, ImageBuffer, Luma, Rgb, Rgba};
+
+    #[test]
+    fn test_color_transfer_matches_tint() {
+        let image = ImageBuffer::from_fn(16, 16, |x, y| {
+            Rgb([(x * 8) as u8 + 40, (y * 8) as u8 + 40, 90])
+        });
+        let reference = ImageBuffer::from_fn(16, 16, |x, _| Rgb([200, 120 + x as u8, 60]));
+
+        let result = color_transfer(&image, &reference);
+        let result_stats = channel_stats(&result);
+        let reference_stats = channel_stats(&reference);
+        for (a, b) in result_stats.iter().zip(&reference_stats) {
+            assert!((a.mean - b.mean).abs() < 12.0, "{} vs {}", a.mean, b.mean);
+        }
+        // The structure of the image is kept: the red channel still increases to the right.
+        assert!(result.get_pixel(15, 8)[0] > result.get_pixel(0, 8)[0]);
+    }
+
+    #[test]
+    fn test_color_transfer_gray_and_alpha() {
+        let image = GrayImage::from_fn(8, 8, |x, _| Luma([x as u8 * 10]));
+        let reference = GrayImage::from_fn(8, 8, |x, _| Luma([100 + x as u8 * 20]));
+        let result = color_transfer(&image, &reference);
+        let mean = channel_stats(&result)[0].mean;
+        assert!((mean - 170.0).abs() < 3.0, "{}", mean);
+
+        let image = ImageBuffer::from_fn(4, 4, |x, y| Rgba([x as u8 * 50, 20, 200, y as u8]));
+        assert_eq!(color_transfer(&image, &image.clone()), image);
+    }
+}
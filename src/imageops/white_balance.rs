@@ -0,0 +1,143 @@
+//! Correction of color casts with per-channel gains.
+
+use num_traits::NumCast;
+
+use crate::image::GenericImage;
+use crate::imageops::sample::FloatNearest;
+use crate::imageops::{plane, srgb};
+use crate::traits::Pixel;
+use crate::utils::clamp;
+
+/// How [`white_balance`] chooses the gains of the red, green and blue channels.
+///
+/// [`white_balance`]: fn.white_balance.html
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WhiteBalanceMethod {
+    /// Assume that the scene is gray on average, and scale every channel so that its mean
+    /// becomes the mean of all three channels.
+    ///
+    /// This removes the tint of the light source from most natural photos, but also the color
+    /// of scenes that are dominated by one color, such as a forest or a sunset.
+    GrayWorld,
+    /// Multiply the channels by the given gains, as read off a gray card or chosen by eye.
+    Manual {
+        /// The gain of the red channel.
+        r: f32,
+        /// The gain of the green channel.
+        g: f32,
+        /// The gain of the blue channel.
+        b: f32,
+    },
+}
+
+/// Remove a color cast from the supplied image in place and return the gains of its red, green
+/// and blue channels.
+///
+/// The samples are multiplied by the gains as they are stored and clamped to their nominal
+/// range. Grayscale images have no cast to remove and are left unchanged, with gains of `1.0`.
+/// The alpha channel is left untouched.
+///
+/// # Examples
+///
+/// ```
+/// use image::imageops::{white_balance, WhiteBalanceMethod};
+/// use image::{Rgb, RgbImage};
+///
+/// // A gray scene under warm light.
+/// let mut image = RgbImage::from_fn(2, 1, |x, _| {
+///     if x == 0 { Rgb([150, 100, 50]) } else { Rgb([90, 60, 30]) }
+/// });
+/// let gains = white_balance(&mut image, WhiteBalanceMethod::GrayWorld);
+/// assert_eq!(gains, [2.0 / 3.0, 1.0, 2.0]);
+/// assert_eq!(image.get_pixel(0, 0), &Rgb([100, 100, 100]));
+/// ```
+pub fn white_balance<I>(image: &mut I, method: WhiteBalanceMethod) -> [f32; 3]
+where
+    I: GenericImage,
+{
+    let channels = match srgb::rgb_channels::<I::Pixel>() {
+        Some(channels) => channels,
+        None => return [1.0; 3],
+    };
+    let gains = match method {
+        WhiteBalanceMethod::GrayWorld => gray_world_gains(image, channels),
+        WhiteBalanceMethod::Manual { r, g, b } => [r, g, b],
+    };
+
+    let max = plane::nominal_max::<<I::Pixel as Pixel>::Subpixel>();
+    let (width, height) = image.dimensions();
+    for y in 0..height {
+        for x in 0..width {
+            let mut pixel = image.get_pixel(x, y);
+            {
+                let samples = pixel.channels_mut();
+                for (&c, &gain) in channels.iter().zip(&gains) {
+                    let value: f32 = NumCast::from(samples[c]).unwrap();
+                    samples[c] = NumCast::from(FloatNearest(clamp(value * gain, 0.0, max))).unwrap();
+                }
+            }
+            image.put_pixel(x, y, pixel);
+        }
+    }
+    gains
+}
+
+/// The gains that make the means of the red, green and blue channels equal to their average.
+fn gray_world_gains<I: GenericImage>(image: &I, channels: [usize; 3]) -> [f32; 3] {
+    let mut sums = [0.0f64; 3];
+    for (_, _, pixel) in image.pixels() {
+        let samples = pixel.channels();
+        for (sum, &c) in sums.iter_mut().zip(&channels) {
+            let value: f64 = NumCast::from(samples[c]).unwrap();
+            *sum += value;
+        }
+    }
+
+    let gray = (sums[0] + sums[1] + sums[2]) / 3.0;
+    let mut gains = [1.0; 3];
+    for (gain, &sum) in gains.iter_mut().zip(&sums) {
+        if sum > 0.0 {
+            *gain = (gray / sum) as f32;
+        }
+    }
+    gains
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{white_balance, WhiteBalanceMethod};
+    use crate::{Bgra, GrayImage, ImageBuffer, Luma, Rgb, RgbImage};
+
+    #[test]
+    fn test_gray_world() {
+        let mut image = RgbImage::from_fn(8, 8, |x, y| {
+            let v = (x * 20 + y * 10) as u8;
+            Rgb([v / 2 + 20, v / 2 + 40, v / 2])
+        });
+        white_balance(&mut image, WhiteBalanceMethod::GrayWorld);
+
+        let mut means = [0.0; 3];
+        for pixel in image.pixels() {
+            for c in 0..3 {
+                means[c] += f64::from(pixel[c]) / 64.0;
+            }
+        }
+        assert!((means[0] - means[1]).abs() < 0.5 && (means[1] - means[2]).abs() < 0.5, "{:?}", means);
+
+        // A black image has no cast.
+        let mut black = RgbImage::new(3, 3);
+        assert_eq!(white_balance(&mut black, WhiteBalanceMethod::GrayWorld), [1.0; 3]);
+    }
+
+    #[test]
+    fn test_manual() {
+        let mut image = ImageBuffer::from_pixel(1, 1, Bgra([100u8, 100, 200, 9]));
+        let method = WhiteBalanceMethod::Manual { r: 2.0, g: 1.5, b: 0.5 };
+        assert_eq!(white_balance(&mut image, method), [2.0, 1.5, 0.5]);
+        assert_eq!(image.get_pixel(0, 0), &Bgra([50, 150, 255, 9]));
+
+        let mut gray = GrayImage::from_pixel(1, 1, Luma([100]));
+        white_balance(&mut gray, method);
+        assert_eq!(gray.get_pixel(0, 0), &Luma([100]));
+    }
+}
@@ -9,9 +9,10 @@ use crate::{ImageOutputFormat, color, error::{UnsupportedError, UnsupportedError
 use crate::image;
 use crate::dynimage::DynamicImage;
 use crate::error::{ImageError, ImageFormatHint, ImageResult};
-use crate::image::ImageFormat;
+use crate::image::{EncoderOptions, ImageFormat};
+use crate::io::Limits;
 #[allow(unused_imports)]  // When no features are supported
-use crate::image::{ImageDecoder, ImageEncoder};
+use crate::image::{CompressionLevel, ImageDecoder, ImageEncoder};
 
 pub(crate) fn open_impl(path: &Path) -> ImageResult<DynamicImage> {
     let fin = match File::open(path) {
@@ -31,44 +32,79 @@ pub(crate) fn open_impl(path: &Path) -> ImageResult<DynamicImage> {
 /// Try [`io::Reader`] for more advanced uses.
 ///
 /// [`io::Reader`]: io/struct.Reader.html
+pub fn load<R: BufRead + Seek>(r: R, format: ImageFormat) -> ImageResult<DynamicImage> {
+    load_impl(r, format, false, &Limits::default())
+}
+
+/// Create a new image from a Reader within `limits`, optionally rotated as described by its
+/// orientation.
 #[allow(unused_variables)]
 // r is unused if no features are supported.
-pub fn load<R: BufRead + Seek>(r: R, format: ImageFormat) -> ImageResult<DynamicImage> {
+pub(crate) fn load_impl<R: BufRead + Seek>(
+    r: R,
+    format: ImageFormat,
+    apply_orientation: bool,
+    limits: &Limits,
+) -> ImageResult<DynamicImage> {
     #[allow(unreachable_patterns)]
     // Default is unreachable if all features are supported.
     match format {
         #[cfg(feature = "avif-decoder")]
-        image::ImageFormat::Avif => DynamicImage::from_decoder(avif::AvifDecoder::new(r)?),
+        ImageFormat::Avif => decode(avif::AvifDecoder::new(r)?, apply_orientation, limits),
         #[cfg(feature = "png")]
-        image::ImageFormat::Png => DynamicImage::from_decoder(png::PngDecoder::new(r)?),
+        ImageFormat::Png => decode(png::PngDecoder::new(r)?, apply_orientation, limits),
         #[cfg(feature = "gif")]
-        image::ImageFormat::Gif => DynamicImage::from_decoder(gif::GifDecoder::new(r)?),
+        ImageFormat::Gif => decode(gif::GifDecoder::new(r)?, apply_orientation, limits),
         #[cfg(feature = "jpeg")]
-        image::ImageFormat::Jpeg => DynamicImage::from_decoder(jpeg::JpegDecoder::new(r)?),
+        ImageFormat::Jpeg => decode(jpeg::JpegDecoder::new(r)?, apply_orientation, limits),
         #[cfg(feature = "webp")]
-        image::ImageFormat::WebP => DynamicImage::from_decoder(webp::WebPDecoder::new(r)?),
+        ImageFormat::WebP => decode(webp::WebPDecoder::new(r)?, apply_orientation, limits),
         #[cfg(feature = "tiff")]
-        image::ImageFormat::Tiff => DynamicImage::from_decoder(tiff::TiffDecoder::new(r)?),
+        ImageFormat::Tiff => decode(tiff::TiffDecoder::new(r)?, apply_orientation, limits),
         #[cfg(feature = "tga")]
-        image::ImageFormat::Tga => DynamicImage::from_decoder(tga::TgaDecoder::new(r)?),
+        ImageFormat::Tga => decode(tga::TgaDecoder::new(r)?, apply_orientation, limits),
         #[cfg(feature = "dds")]
-        image::ImageFormat::Dds => DynamicImage::from_decoder(dds::DdsDecoder::new(r)?),
+        ImageFormat::Dds => decode(dds::DdsDecoder::new(r)?, apply_orientation, limits),
         #[cfg(feature = "bmp")]
-        image::ImageFormat::Bmp => DynamicImage::from_decoder(bmp::BmpDecoder::new(r)?),
+        ImageFormat::Bmp => decode(bmp::BmpDecoder::new(r)?, apply_orientation, limits),
         #[cfg(feature = "ico")]
-        image::ImageFormat::Ico => DynamicImage::from_decoder(ico::IcoDecoder::new(r)?),
+        ImageFormat::Ico => decode(ico::IcoDecoder::new(r)?, apply_orientation, limits),
         #[cfg(feature = "hdr")]
-        image::ImageFormat::Hdr => DynamicImage::from_decoder(hdr::HdrAdapter::new(BufReader::new(r))?),
+        ImageFormat::Hdr => {
+            decode(hdr::HdrAdapter::new(BufReader::new(r))?, apply_orientation, limits)
+        }
         #[cfg(feature = "openexr")]
-        image::ImageFormat::OpenExr => DynamicImage::from_decoder(openexr::OpenExrDecoder::new(r)?),
+        ImageFormat::OpenExr => {
+            decode(openexr::OpenExrDecoder::new(r)?, apply_orientation, limits)
+        }
         #[cfg(feature = "pnm")]
-        image::ImageFormat::Pnm => DynamicImage::from_decoder(pnm::PnmDecoder::new(BufReader::new(r))?),
+        ImageFormat::Pnm => {
+            decode(pnm::PnmDecoder::new(BufReader::new(r))?, apply_orientation, limits)
+        }
         #[cfg(feature = "farbfeld")]
-        image::ImageFormat::Farbfeld => DynamicImage::from_decoder(farbfeld::FarbfeldDecoder::new(r)?),
+        ImageFormat::Farbfeld => {
+            decode(farbfeld::FarbfeldDecoder::new(r)?, apply_orientation, limits)
+        }
+        #[cfg(feature = "jxl")]
+        ImageFormat::JpegXl => decode(jxl::JxlDecoder::new(r)?, apply_orientation, limits),
         _ => Err(ImageError::Unsupported(ImageFormatHint::Exact(format).into())),
     }
 }
 
+#[allow(dead_code)]
+// Unused if no features are supported.
+fn decode<'a>(mut decoder: impl ImageDecoder<'a>, apply_orientation: bool, limits: &Limits)
+    -> ImageResult<DynamicImage>
+{
+    decoder.set_limits(limits)?;
+    let orientation = if apply_orientation { decoder.orientation() } else { None };
+    let mut image = DynamicImage::from_decoder(decoder)?;
+    if let Some(orientation) = orientation {
+        image.apply_orientation(orientation);
+    }
+    Ok(image)
+}
+
 pub(crate) fn image_dimensions_impl(path: &Path) -> ImageResult<(u32, u32)> {
     let format = image::ImageFormat::from_path(path)?;
 
@@ -111,6 +147,8 @@ pub(crate) fn image_dimensions_with_format_impl<R: BufRead + Seek>(fin: R, forma
         image::ImageFormat::Hdr => hdr::HdrAdapter::new(fin)?.dimensions(),
         #[cfg(feature = "openexr")]
         image::ImageFormat::OpenExr => openexr::OpenExrDecoder::new(fin)?.dimensions(),
+        #[cfg(feature = "jxl")]
+        image::ImageFormat::JpegXl => jxl::JxlDecoder::new(fin)?.dimensions(),
         #[cfg(feature = "pnm")]
         image::ImageFormat::Pnm => {
             pnm::PnmDecoder::new(fin)?.dimensions()
@@ -158,8 +196,6 @@ pub(crate) fn save_buffer_with_format_impl(
                 _ => { return Err(ImageError::Unsupported(ImageFormatHint::Exact(format).into())) }, // Unsupported Pnm subtype.
             })
         },
-        // #[cfg(feature = "hdr")]
-        // image::ImageFormat::Hdr => hdr::HdrEncoder::new(fout).encode(&[Rgb<f32>], width, height), // usize
         #[cfg(feature = "tiff")]
         image::ImageFormat::Tiff => {
             return tiff::TiffEncoder::new(fout).write_image(buf, width, height, color);
@@ -167,7 +203,7 @@ pub(crate) fn save_buffer_with_format_impl(
         format => format.into(),
     };
 
-    write_buffer_impl(fout, buf, width, height, color, format)
+    write_buffer_impl(fout, buf, width, height, color, format, EncoderOptions::default())
 }
 
 #[allow(unused_variables)]
@@ -179,19 +215,22 @@ pub(crate) fn write_buffer_impl<W: std::io::Write>(
     height: u32,
     color: color::ColorType,
     format: ImageOutputFormat,
+    options: EncoderOptions,
 ) -> ImageResult<()> {
     match format {
         #[cfg(feature = "png")]
-        ImageOutputFormat::Png => png::PngEncoder::new(fout).write_image(buf, width, height, color),
+        ImageOutputFormat::Png => png_encoder(fout, options).write_image(buf, width, height, color),
         #[cfg(feature = "jpeg")]
-        ImageOutputFormat::Jpeg(quality) => jpeg::JpegEncoder::new_with_quality(fout, quality)
-            .write_image(buf, width, height, color),
+        ImageOutputFormat::Jpeg(quality) => {
+            jpeg::JpegEncoder::new_with_quality(fout, options.quality().unwrap_or(quality))
+                .write_image(buf, width, height, color)
+        }
         #[cfg(feature = "pnm")]
         ImageOutputFormat::Pnm(subtype) => pnm::PnmEncoder::new(fout)
             .with_subtype(subtype)
             .write_image(buf, width, height, color),
         #[cfg(feature = "gif")]
-        ImageOutputFormat::Gif => gif::GifEncoder::new(fout).encode(buf, width, height, color),
+        ImageOutputFormat::Gif => gif_encoder(fout, options).encode(buf, width, height, color),
         #[cfg(feature = "ico")]
         ImageOutputFormat::Ico => ico::IcoEncoder::new(fout).write_image(buf, width, height, color),
         #[cfg(feature = "bmp")]
@@ -200,18 +239,37 @@ pub(crate) fn write_buffer_impl<W: std::io::Write>(
         ImageOutputFormat::Farbfeld => farbfeld::FarbfeldEncoder::new(fout).write_image(buf, width, height, color),
         #[cfg(feature = "tga")]
         ImageOutputFormat::Tga => tga::TgaEncoder::new(fout).write_image(buf, width, height, color),
+        #[cfg(feature = "hdr")]
+        ImageOutputFormat::Hdr => hdr::HdrEncoder::new(fout).write_image(buf, width, height, color),
         #[cfg(feature = "openexr")]
         ImageOutputFormat::OpenExr => openexr::OpenExrEncoder::new(fout).write_image(buf, width, height, color),
         #[cfg(feature = "tiff")]
         ImageOutputFormat::Tiff => {
             let mut cursor = std::io::Cursor::new(Vec::new());
-            tiff::TiffEncoder::new(&mut cursor).write_image(buf, width, height, color)?;
+            let mut encoder = tiff::TiffEncoder::new(&mut cursor);
+            if let Some(compression) = options.compression() {
+                encoder = encoder.with_compression(match compression {
+                    CompressionLevel::Best => tiff::CompressionType::Deflate,
+                    _ => tiff::CompressionType::Lzw,
+                });
+            }
+            encoder.write_image(buf, width, height, color)?;
             fout.write(&cursor.into_inner()[..])
                 .map(|_| ())
                 .map_err(ImageError::IoError)
         }
         #[cfg(feature = "avif-encoder")]
-        ImageOutputFormat::Avif => avif::AvifEncoder::new(fout).write_image(buf, width, height, color),
+        ImageOutputFormat::Avif => avif::AvifEncoder::new_with_speed_quality(
+            fout,
+            options.speed().unwrap_or(1),
+            options.quality().unwrap_or(100),
+        )
+        .write_image(buf, width, height, color),
+        #[cfg(feature = "webp")]
+        ImageOutputFormat::WebP(quality) => {
+            let quality = options.quality().map_or(quality, webp::WebPQuality::Lossy);
+            webp::WebPEncoder::new_with_quality(fout, quality).write_image(buf, width, height, color)
+        }
 
         image::ImageOutputFormat::Unsupported(msg) => {
             Err(ImageError::Unsupported(UnsupportedError::from_format_and_kind(
@@ -224,7 +282,28 @@ pub(crate) fn write_buffer_impl<W: std::io::Write>(
     }
 }
 
-static MAGIC_BYTES: [(&[u8], ImageFormat); 21] = [
+/// Create a PNG encoder with the compression of `options`.
+#[cfg(feature = "png")]
+pub(crate) fn png_encoder<W: std::io::Write>(w: W, options: EncoderOptions) -> png::PngEncoder<W> {
+    match options.compression() {
+        Some(CompressionLevel::Default) => png::PngEncoder::new_with_quality(
+            w, png::CompressionType::Default, png::FilterType::Sub),
+        Some(CompressionLevel::Best) => png::PngEncoder::new_with_quality(
+            w, png::CompressionType::Best, png::FilterType::Adaptive),
+        _ => png::PngEncoder::new(w),
+    }
+}
+
+/// Create a GIF encoder, mapping the speed of `options` onto the quantization speed 1 to 30.
+#[cfg(feature = "gif")]
+pub(crate) fn gif_encoder<W: std::io::Write>(w: W, options: EncoderOptions) -> gif::GifEncoder<W> {
+    match options.speed() {
+        Some(speed) => gif::GifEncoder::new_with_speed(w, 1 + i32::from(speed) * 29 / 10),
+        None => gif::GifEncoder::new(w),
+    }
+}
+
+static MAGIC_BYTES: [(&[u8], ImageFormat); 26] = [
     (b"\x89PNG\r\n\x1a\n", ImageFormat::Png),
     (&[0xff, 0xd8, 0xff], ImageFormat::Jpeg),
     (b"GIF89a", ImageFormat::Gif),
@@ -232,6 +311,8 @@ static MAGIC_BYTES: [(&[u8], ImageFormat); 21] = [
     (b"RIFF", ImageFormat::WebP), // TODO: better magic byte detection, see https://github.com/image-rs/image/issues/660
     (b"MM\x00*", ImageFormat::Tiff),
     (b"II*\x00", ImageFormat::Tiff),
+    (b"MM\x00+", ImageFormat::Tiff), // BigTIFF
+    (b"II+\x00", ImageFormat::Tiff), // BigTIFF
     (b"DDS ", ImageFormat::Dds),
     (b"BM", ImageFormat::Bmp),
     (&[0, 0, 1, 0], ImageFormat::Ico),
@@ -245,7 +326,10 @@ static MAGIC_BYTES: [(&[u8], ImageFormat); 21] = [
     (b"P7", ImageFormat::Pnm),
     (b"farbfeld", ImageFormat::Farbfeld),
     (b"\0\0\0 ftypavif", ImageFormat::Avif),
+    (b"\0\0\0 ftypavis", ImageFormat::Avif),
     (&[0x76, 0x2f, 0x31, 0x01], ImageFormat::OpenExr), // = &exr::meta::magic_number::BYTES
+    (&[0xff, 0x0a], ImageFormat::JpegXl), // A bare codestream
+    (b"\0\0\0\x0cJXL \r\n\x87\n", ImageFormat::JpegXl), // The container
 ];
 
 /// Guess image format from memory block
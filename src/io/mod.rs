@@ -3,3 +3,104 @@ mod reader;
 pub(crate) mod free_functions;
 
 pub use self::reader::Reader;
+
+use crate::error::{ImageError, ImageResult, LimitError, LimitErrorKind};
+
+/// Resource limits for decoding an image.
+///
+/// Decoding untrusted input can allocate an arbitrary amount of memory, as a small file may
+/// declare huge dimensions. Limits are checked before the image data is decoded and exceeding
+/// them results in an `ImageError::Limits`. By default nothing is limited.
+///
+/// ```
+/// use image::io::Limits;
+///
+/// let mut limits = Limits::default();
+/// limits.max_image_width = Some(8192);
+/// limits.max_image_height = Some(8192);
+/// limits.max_alloc = Some(256 * 1024 * 1024);
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Limits {
+    /// The maximum width of an image in pixels.
+    pub max_image_width: Option<u32>,
+    /// The maximum height of an image in pixels.
+    pub max_image_height: Option<u32>,
+    /// The maximum number of bytes the decoded image may occupy.
+    pub max_alloc: Option<u64>,
+    /// Allows adding limits without breaking struct literals.
+    _non_exhaustive: (),
+}
+
+impl Limits {
+    /// Limits that do not restrict anything.
+    pub fn no_limits() -> Self {
+        Limits::default()
+    }
+
+    /// Check that an image of `width` by `height` pixels does not exceed the dimension limits.
+    pub fn check_dimensions(&self, width: u32, height: u32) -> ImageResult<()> {
+        let too_wide = self.max_image_width.map_or(false, |max| width > max);
+        let too_high = self.max_image_height.map_or(false, |max| height > max);
+        if too_wide || too_high {
+            return Err(ImageError::Limits(LimitError::from_kind(LimitErrorKind::DimensionError)));
+        }
+        Ok(())
+    }
+
+    /// Check that an allocation of `amount` bytes does not exceed the allocation limit.
+    pub fn check_alloc(&self, amount: u64) -> ImageResult<()> {
+        if self.max_alloc.map_or(false, |max| amount > max) {
+            return Err(ImageError::Limits(LimitError::from_kind(LimitErrorKind::InsufficientMemory)));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Limits;
+
+    #[test]
+    fn check_limits() {
+        let mut limits = Limits::default();
+        assert!(limits.check_dimensions(u32::max_value(), u32::max_value()).is_ok());
+        assert!(limits.check_alloc(u64::max_value()).is_ok());
+
+        limits.max_image_width = Some(100);
+        limits.max_alloc = Some(1000);
+        assert!(limits.check_dimensions(100, 5000).is_ok());
+        assert!(limits.check_dimensions(101, 1).is_err());
+        assert!(limits.check_alloc(1000).is_ok());
+        assert!(limits.check_alloc(1001).is_err());
+    }
+
+    #[cfg(feature = "pnm")]
+    #[test]
+    fn reader_applies_limits() {
+        use std::io::Cursor;
+        use super::Reader;
+        use crate::error::{ImageError, LimitErrorKind};
+        use crate::ImageFormat;
+
+        // Declares a huge image but contains almost no data.
+        let data = b"P5 1000000 1000000 255\n\0\0\0\0";
+        let reader = |limits| {
+            Reader::with_format(Cursor::new(&data[..]), ImageFormat::Pnm).with_limits(limits)
+        };
+
+        let limits = Limits { max_image_width: Some(4096), ..Limits::default() };
+        match reader(limits).decode() {
+            Err(ImageError::Limits(err)) => assert_eq!(err.kind(), LimitErrorKind::DimensionError),
+            other => panic!("unexpected result {:?}", other.map(|_| ())),
+        }
+
+        let limits = Limits { max_alloc: Some(1 << 30), ..Limits::default() };
+        match reader(limits).decode() {
+            Err(ImageError::Limits(err)) => {
+                assert_eq!(err.kind(), LimitErrorKind::InsufficientMemory)
+            }
+            other => panic!("unexpected result {:?}", other.map(|_| ())),
+        }
+    }
+}
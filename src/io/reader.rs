@@ -8,6 +8,7 @@ use crate::error::{ImageFormatHint, UnsupportedError, UnsupportedErrorKind};
 use crate::{ImageError, ImageResult};
 
 use super::free_functions;
+use super::Limits;
 
 /// A multi-format image reader.
 ///
@@ -63,6 +64,10 @@ pub struct Reader<R: Read> {
     inner: R,
     /// The format, if one has been set or deduced.
     format: Option<ImageFormat>,
+    /// Whether to rotate the decoded image as described by its orientation.
+    apply_orientation: bool,
+    /// The resource limits of decoding.
+    limits: Limits,
 }
 
 impl<R: Read> Reader<R> {
@@ -80,6 +85,8 @@ impl<R: Read> Reader<R> {
         Reader {
             inner: buffered_reader,
             format: None,
+            apply_orientation: false,
+            limits: Limits::default(),
         }
     }
 
@@ -91,6 +98,25 @@ impl<R: Read> Reader<R> {
         Reader {
             inner: buffered_reader,
             format: Some(format),
+            apply_orientation: false,
+            limits: Limits::default(),
+        }
+    }
+
+    /// Construct a reader with the format given by a MIME type, such as the `Content-Type` of an
+    /// HTTP request. The format is left unset if the type is not recognized.
+    ///
+    /// As the declared type is not necessarily correct, follow this call with a call to
+    /// [`with_guessed_format`], which keeps the declared format only if the content could not be
+    /// identified.
+    ///
+    /// [`with_guessed_format`]: #method.with_guessed_format
+    pub fn new_with_mime(buffered_reader: R, mime_type: &str) -> Self {
+        Reader {
+            inner: buffered_reader,
+            format: ImageFormat::from_mime_type(mime_type),
+            apply_orientation: false,
+            limits: Limits::default(),
         }
     }
 
@@ -112,6 +138,57 @@ impl<R: Read> Reader<R> {
         self.format = None;
     }
 
+    /// Rotate and flip the decoded image so that it is displayed upright.
+    ///
+    /// Photos are commonly stored in the orientation of the camera sensor, with an EXIF tag
+    /// describing how they have to be displayed. With this option [`decode`] applies that
+    /// transformation, by default the image is returned as stored. Images without orientation
+    /// information are not changed.
+    ///
+    /// [`decode`]: #method.decode
+    pub fn with_applied_orientation(mut self) -> Self {
+        self.apply_orientation = true;
+        self
+    }
+
+    /// Restrict the resources that [`decode`] may use.
+    ///
+    /// Images exceeding the limits fail with an `ImageError::Limits` before their data is
+    /// decoded. By default nothing is limited, which should be changed when decoding untrusted
+    /// input.
+    ///
+    /// [`decode`]: #method.decode
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Read the remaining input into memory, keeping the format and all options.
+    ///
+    /// Guessing the format and decoding requires seeking, which is not possible on a network
+    /// stream or a pipe. The buffered reader supports both, for example:
+    ///
+    /// ```no_run
+    /// # use image::ImageError;
+    /// # use image::io::Reader;
+    /// # fn main() -> Result<(), ImageError> {
+    /// let image = Reader::new(std::io::stdin())
+    ///     .into_buffered()?
+    ///     .with_guessed_format()?
+    ///     .decode()?;
+    /// # Ok(()) }
+    /// ```
+    pub fn into_buffered(mut self) -> io::Result<Reader<Cursor<Vec<u8>>>> {
+        let mut data = Vec::new();
+        self.inner.read_to_end(&mut data)?;
+        Ok(Reader {
+            inner: Cursor::new(data),
+            format: self.format,
+            apply_orientation: self.apply_orientation,
+            limits: self.limits,
+        })
+    }
+
     /// Unwrap the reader.
     pub fn into_inner(self) -> R {
         self.inner
@@ -136,10 +213,33 @@ impl Reader<BufReader<File>> {
         Ok(Reader {
             inner: BufReader::new(file),
             format: ImageFormat::from_path(path).ok(),
+            apply_orientation: false,
+            limits: Limits::default(),
         })
     }
 }
 
+#[cfg(feature = "async")]
+impl Reader<Cursor<Vec<u8>>> {
+    /// Read an image from an asynchronous reader, guessing its format from the content.
+    ///
+    /// The whole input is read into memory without blocking, after which it is decoded like any
+    /// other in-memory image with [`decode`]. Decoding itself is not asynchronous, so large images
+    /// are best decoded on a thread that may block.
+    ///
+    /// [`decode`]: #method.decode
+    pub async fn from_async_reader<A>(mut reader: A) -> io::Result<Self>
+    where
+        A: futures_util::io::AsyncRead + Unpin,
+    {
+        use futures_util::io::AsyncReadExt;
+
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data).await?;
+        Reader::new(Cursor::new(data)).with_guessed_format()
+    }
+}
+
 impl<R: BufRead + Seek> Reader<R> {
     /// Make a format guess based on the content, replacing it on success.
     ///
@@ -207,7 +307,7 @@ impl<R: BufRead + Seek> Reader<R> {
     /// If no format was determined, returns an `ImageError::Unsupported`.
     pub fn decode(mut self) -> ImageResult<DynamicImage> {
         let format = self.require_format()?;
-        free_functions::load(self.inner, format)
+        free_functions::load_impl(self.inner, format, self.apply_orientation, &self.limits)
     }
 
     fn require_format(&mut self) -> ImageResult<ImageFormat> {
@@ -217,3 +317,93 @@ impl<R: BufRead + Seek> Reader<R> {
                 UnsupportedErrorKind::Format(ImageFormatHint::Unknown))))
     }
 }
+
+#[cfg(all(test, feature = "pnm"))]
+mod tests {
+    use std::io::{self, Read};
+
+    use super::Reader;
+    use crate::ImageFormat;
+
+    /// A reader that can not seek, like a network stream.
+    struct Stream<'a>(&'a [u8]);
+
+    impl Read for Stream<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.0.read(buf)
+        }
+    }
+
+    #[test]
+    fn mime_type_with_guessed_format() {
+        let data: &[u8] = b"P5 2 1 255\n\x01\x02";
+        let reader = Reader::new_with_mime(Stream(data), "image/png; charset=binary");
+        assert_eq!(reader.format(), Some(ImageFormat::Png));
+        let reader = reader.into_buffered().unwrap().with_guessed_format().unwrap();
+        assert_eq!(reader.format(), Some(ImageFormat::Pnm));
+        assert_eq!(reader.decode().unwrap().as_bytes(), &[1, 2]);
+
+        let reader = Reader::new_with_mime(Stream(b"unknown"), "image/x-portable-anymap");
+        let reader = reader.into_buffered().unwrap().with_guessed_format().unwrap();
+        assert_eq!(reader.format(), Some(ImageFormat::Pnm));
+
+        let reader = Reader::new_with_mime(Stream(data), "text/plain");
+        assert_eq!(reader.format(), None);
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn from_async_reader() {
+        use futures_util::FutureExt;
+        use crate::GenericImageView;
+
+        let data: &[u8] = b"P5 2 1 255\n\x01\x02";
+        let reader = Reader::from_async_reader(data)
+            .now_or_never()
+            .expect("reading a slice never waits")
+            .unwrap();
+        assert_eq!(reader.format(), Some(ImageFormat::Pnm));
+        let image = reader.decode().unwrap();
+        assert_eq!(image.dimensions(), (2, 1));
+        assert_eq!(image.as_bytes(), &[1, 2]);
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn from_async_reader_unknown_format() {
+        use futures_util::FutureExt;
+        use crate::ImageError;
+
+        for &data in &[&b""[..], b"not an image"] {
+            let reader = Reader::from_async_reader(data).now_or_never().unwrap().unwrap();
+            assert_eq!(reader.format(), None);
+            match reader.decode() {
+                Err(ImageError::Unsupported(_)) => {}
+                other => panic!("expected an unsupported error, got {:?}", other.map(|_| ())),
+            }
+        }
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn from_async_reader_error() {
+        use std::pin::Pin;
+        use std::task::{Context, Poll};
+        use futures_util::FutureExt;
+
+        struct Failing;
+
+        impl futures_util::io::AsyncRead for Failing {
+            fn poll_read(
+                self: Pin<&mut Self>,
+                _: &mut Context<'_>,
+                _: &mut [u8],
+            ) -> Poll<io::Result<usize>> {
+                Poll::Ready(Err(io::Error::new(io::ErrorKind::BrokenPipe, "failing reader")))
+            }
+        }
+
+        let result = Reader::from_async_reader(Failing).now_or_never().unwrap();
+        assert_eq!(result.err().map(|err| err.kind()), Some(io::ErrorKind::BrokenPipe));
+    }
+}
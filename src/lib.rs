@@ -105,12 +105,14 @@ use std::io::Write;
 
 pub use crate::color::{ColorType, ExtendedColorType};
 
-pub use crate::color::{Luma, LumaA, Rgb, Rgba, Bgr, Bgra};
+pub use crate::color::{Luma, LumaA, Rgb, Rgba, Bgr, Bgra, Cmyk, Bands};
 
 pub use crate::error::{ImageError, ImageResult};
 
 pub use crate::image::{
     AnimationDecoder,
+    CompressionLevel,
+    EncoderOptions,
     GenericImage,
     GenericImageView,
     ImageDecoder,
@@ -136,9 +138,10 @@ pub use crate::buffer_::{
 };
 
 pub use crate::flat::FlatSamples;
+pub use crate::view::{ImageView, ImageViewMut};
 
 // Traits
-pub use crate::traits::{EncodableLayout, Primitive, Pixel};
+pub use crate::traits::{BandArray, EncodableLayout, Primitive, Pixel};
 
 // Opening and loading images
 pub use crate::io::free_functions::{guess_format, load};
@@ -148,6 +151,7 @@ pub use crate::dynimage::{load_from_memory, load_from_memory_with_format, open,
 pub use crate::dynimage::DynamicImage;
 
 pub use crate::animation::{Delay, Frame, Frames};
+pub use crate::animation::{resample_frames, stretch_frames, FrameBlending};
 
 // More detailed error type
 pub mod error;
@@ -171,9 +175,36 @@ pub mod buffer {
 // Math utils
 pub mod math;
 
+// Image metadata
+pub mod metadata;
+
 // Image processing functions
 pub mod imageops;
 
+// Drawing of lines and shapes
+pub mod draw;
+
+// Color space conversions
+pub mod color_space;
+
+// ICC color profiles
+pub mod color_profile;
+
+// Planar YUV images
+pub mod yuv;
+
+// Color quantization
+pub mod quant;
+
+// Image comparison
+pub mod metrics;
+
+// Perceptual hashes
+pub mod hash;
+
+// Color lookup tables
+pub mod lut;
+
 // Io bindings
 pub mod io;
 
@@ -240,6 +271,8 @@ pub mod codecs {
     pub mod ico;
     #[cfg(feature = "jpeg")]
     pub mod jpeg;
+    #[cfg(feature = "jxl")]
+    pub mod jxl;
     #[cfg(feature = "png")]
     pub mod png;
     #[cfg(feature = "pnm")]
@@ -298,7 +331,7 @@ pub mod gif {
 #[cfg(feature = "hdr")]
 #[deprecated = "Use codecs::hdr instead"]
 pub mod hdr {
-    //! Decoding of Radiance HDR Images
+    //! Decoding and Encoding of Radiance HDR Images
     #[allow(deprecated)]
     pub use crate::codecs::hdr::{
         read_raw_file, rgbe8, to_rgbe8, HDRAdapter, HDREncoder, HDRImageDecoderIterator,
@@ -371,8 +404,13 @@ mod buffer_;
 mod color;
 mod dynimage;
 mod image;
+#[cfg(feature = "ndarray")]
+mod ndarray_interop;
+#[cfg(feature = "serde")]
+mod serde_impls;
 mod traits;
 mod utils;
+mod view;
 
 // Can't use the macro-call itself within the `doc` attribute. So force it to eval it as part of
 // the macro invocation.
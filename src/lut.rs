@@ -0,0 +1,367 @@
+//! Color lookup tables, as used for color grading.
+//!
+//! A [`Lut`] maps every RGB color to another one. One dimensional tables map each channel
+//! separately through a curve, three dimensional ones map the whole color through a cube of
+//! samples, with trilinear interpolation between them. Tables are usually exchanged as `.cube`
+//! files, the text format introduced by Adobe and used by DaVinci Resolve and most other grading
+//! tools, which [`Lut::read_cube`] parses.
+//!
+//! # Examples
+//!
+//! ```
+//! use image::lut::Lut;
+//! use image::{Rgb, RgbImage};
+//!
+//! // A 1D table that inverts every channel.
+//! let cube = "LUT_1D_SIZE 2\n1.0 1.0 1.0\n0.0 0.0 0.0\n";
+//! let lut = Lut::read_cube(cube.as_bytes()).unwrap();
+//!
+//! let mut image = RgbImage::from_pixel(2, 2, Rgb([0, 100, 255]));
+//! lut.apply(&mut image);
+//! assert_eq!(image.get_pixel(0, 0), &Rgb([255, 155, 0]));
+//! ```
+//!
+//! [`Lut`]: struct.Lut.html
+//! [`Lut::read_cube`]: struct.Lut.html#method.read_cube
+
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+use num_traits::NumCast;
+
+use crate::color::{Rgb, Rgba};
+use crate::error::{DecodingError, ImageError, ImageFormatHint, ImageResult};
+use crate::imageops::plane::nominal_max;
+use crate::imageops::FloatNearest;
+use crate::traits::Primitive;
+use crate::utils::clamp;
+use crate::ImageBuffer;
+
+/// Whether a [`Lut`] maps channels separately or colors as a whole.
+///
+/// [`Lut`]: struct.Lut.html
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum LutKind {
+    /// A curve for every channel.
+    OneDimensional,
+    /// A cube of colors, indexed by all three channels.
+    ThreeDimensional,
+}
+
+/// A color lookup table.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Lut {
+    kind: LutKind,
+    size: usize,
+    domain_min: [f32; 3],
+    domain_max: [f32; 3],
+    /// The samples, red changing fastest, then green, then blue.
+    table: Vec<[f32; 3]>,
+}
+
+impl Lut {
+    /// Create a lookup table from its samples, for the input domain `[0, 1]`.
+    ///
+    /// A one dimensional table has `size` samples, a three dimensional one `size³`, ordered with
+    /// red changing fastest and blue slowest, as in `.cube` files.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `size` is less than 2 or the number of samples does not match it.
+    pub fn new(kind: LutKind, size: usize, table: Vec<[f32; 3]>) -> Lut {
+        assert!(size >= 2, "a lookup table needs at least two samples per axis");
+        assert_eq!(table.len(), sample_count(kind, size), "wrong number of samples");
+        Lut { kind, size, domain_min: [0.0; 3], domain_max: [1.0; 3], table }
+    }
+
+    /// The three dimensional table that maps every color to itself.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `size` is less than 2.
+    pub fn identity(size: usize) -> Lut {
+        assert!(size >= 2, "a lookup table needs at least two samples per axis");
+        let step = |i: usize| i as f32 / (size - 1) as f32;
+        let table = (0..size * size * size)
+            .map(|i| [step(i % size), step(i / size % size), step(i / (size * size))])
+            .collect();
+        Lut::new(LutKind::ThreeDimensional, size, table)
+    }
+
+    /// Parse a table in the `.cube` format.
+    ///
+    /// Both one and three dimensional tables are supported, with the keywords `TITLE`,
+    /// `LUT_1D_SIZE`, `LUT_3D_SIZE`, `DOMAIN_MIN` and `DOMAIN_MAX`. Lines starting with `#`
+    /// are comments.
+    pub fn read_cube<R: BufRead>(reader: R) -> ImageResult<Lut> {
+        let mut kind = None;
+        let mut domain_min = [0.0; 3];
+        let mut domain_max = [1.0; 3];
+        let mut table = Vec::new();
+
+        for line in reader.lines() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut words = line.split_whitespace();
+            let keyword = words.next().unwrap_or("");
+            let rest: Vec<&str> = words.collect();
+            match keyword {
+                "TITLE" => {}
+                "LUT_1D_SIZE" | "LUT_3D_SIZE" => {
+                    if kind.is_some() {
+                        return Err(cube_error("more than one table size"));
+                    }
+                    let size = match rest.as_slice() {
+                        [size] => size.parse::<usize>().ok(),
+                        _ => None,
+                    };
+                    let size = match size {
+                        Some(size) if size >= 2 && size <= 256 => size,
+                        _ => return Err(cube_error("invalid table size")),
+                    };
+                    kind = Some(if keyword == "LUT_1D_SIZE" {
+                        (LutKind::OneDimensional, size)
+                    } else {
+                        (LutKind::ThreeDimensional, size)
+                    });
+                }
+                "DOMAIN_MIN" => domain_min = parse_triple(&rest)?,
+                "DOMAIN_MAX" => domain_max = parse_triple(&rest)?,
+                _ if keyword.starts_with(|c: char| c.is_ascii_alphabetic()) => {
+                    return Err(cube_error(format!("unknown keyword {}", keyword)));
+                }
+                _ => {
+                    let mut values = vec![keyword];
+                    values.extend(rest);
+                    table.push(parse_triple(&values)?);
+                }
+            }
+        }
+
+        let (kind, size) = kind.ok_or_else(|| cube_error("missing table size"))?;
+        if table.len() != sample_count(kind, size) {
+            return Err(cube_error(format!(
+                "expected {} samples, found {}",
+                sample_count(kind, size),
+                table.len()
+            )));
+        }
+        if (0..3).any(|c| domain_min[c] >= domain_max[c]) {
+            return Err(cube_error("empty domain"));
+        }
+
+        Ok(Lut { kind, size, domain_min, domain_max, table })
+    }
+
+    /// Open and parse a `.cube` file.
+    pub fn open_cube<P: AsRef<Path>>(path: P) -> ImageResult<Lut> {
+        Lut::read_cube(BufReader::new(File::open(path)?))
+    }
+
+    /// Whether the table is one or three dimensional.
+    pub fn kind(&self) -> LutKind {
+        self.kind
+    }
+
+    /// The number of samples along every axis.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Map a color, with channels usually in `[0, 1]`.
+    ///
+    /// Inputs outside of the domain of the table are clamped to it. The result is not clamped,
+    /// since tables may produce colors outside of `[0, 1]`, for example for HDR footage.
+    pub fn map_color(&self, color: [f32; 3]) -> [f32; 3] {
+        let last = (self.size - 1) as f32;
+        let mut position = [0.0; 3];
+        for c in 0..3 {
+            let t = (color[c] - self.domain_min[c]) / (self.domain_max[c] - self.domain_min[c]);
+            // Also maps NaN to zero.
+            position[c] = clamp(t * last, 0.0, last).max(0.0);
+        }
+
+        match self.kind {
+            LutKind::OneDimensional => {
+                let mut out = [0.0; 3];
+                for c in 0..3 {
+                    let (i, fraction) = self.cell(position[c]);
+                    out[c] = lerp(self.table[i][c], self.table[i + 1][c], fraction);
+                }
+                out
+            }
+            LutKind::ThreeDimensional => {
+                let (r, fr) = self.cell(position[0]);
+                let (g, fg) = self.cell(position[1]);
+                let (b, fb) = self.cell(position[2]);
+                let size = self.size;
+                let at = |r: usize, g: usize, b: usize| self.table[(b * size + g) * size + r];
+
+                let mut out = [0.0; 3];
+                for (c, value) in out.iter_mut().enumerate() {
+                    let plane = |b: usize| {
+                        let low = lerp(at(r, g, b)[c], at(r + 1, g, b)[c], fr);
+                        let high = lerp(at(r, g + 1, b)[c], at(r + 1, g + 1, b)[c], fr);
+                        lerp(low, high, fg)
+                    };
+                    *value = lerp(plane(b), plane(b + 1), fb);
+                }
+                out
+            }
+        }
+    }
+
+    /// Map every pixel of an RGB image through the table.
+    ///
+    /// Samples are scaled to `[0, 1]` by the maximum of their type, or taken as they are for
+    /// floating point images. Results are clamped to the range of integer types.
+    pub fn apply<S: Primitive + 'static>(&self, image: &mut ImageBuffer<Rgb<S>, Vec<S>>) {
+        for pixel in image.pixels_mut() {
+            self.map_samples(&mut pixel.0);
+        }
+    }
+
+    /// Map every pixel of an RGBA image through the table, leaving alpha unchanged.
+    ///
+    /// See [`apply`](#method.apply) for how samples are scaled.
+    pub fn apply_rgba<S: Primitive + 'static>(&self, image: &mut ImageBuffer<Rgba<S>, Vec<S>>) {
+        for pixel in image.pixels_mut() {
+            self.map_samples(&mut pixel.0[..3]);
+        }
+    }
+
+    fn map_samples<S: Primitive>(&self, samples: &mut [S]) {
+        let max = nominal_max::<S>();
+        let is_float = max == 1.0;
+        let mut color = [0.0; 3];
+        for (c, &sample) in color.iter_mut().zip(samples.iter()) {
+            let sample: f32 = NumCast::from(sample).unwrap();
+            *c = sample / max;
+        }
+
+        for (sample, &value) in samples.iter_mut().zip(self.map_color(color).iter()) {
+            let value = if is_float { value } else { clamp(value * max, 0.0, max) };
+            *sample = NumCast::from(FloatNearest(value)).unwrap();
+        }
+    }
+
+    /// The index of the sample below a position and the fraction towards the next one.
+    fn cell(&self, position: f32) -> (usize, f32) {
+        let i = (position.floor() as usize).min(self.size - 2);
+        (i, position - i as f32)
+    }
+}
+
+fn sample_count(kind: LutKind, size: usize) -> usize {
+    match kind {
+        LutKind::OneDimensional => size,
+        LutKind::ThreeDimensional => size * size * size,
+    }
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+fn parse_triple(words: &[&str]) -> ImageResult<[f32; 3]> {
+    let mut values = [0.0; 3];
+    if words.len() != 3 {
+        return Err(cube_error("expected three values in a line"));
+    }
+    for (value, word) in values.iter_mut().zip(words) {
+        *value = match word.parse::<f32>() {
+            Ok(v) if v.is_finite() => v,
+            _ => return Err(cube_error(format!("invalid number {}", word))),
+        };
+    }
+    Ok(values)
+}
+
+fn cube_error<E: Into<Box<dyn std::error::Error + Send + Sync>>>(err: E) -> ImageError {
+    ImageError::Decoding(DecodingError::new(ImageFormatHint::Name("cube".to_owned()), err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Lut, LutKind};
+    use crate::{ImageBuffer, Rgb, RgbImage, Rgba, RgbaImage};
+
+    #[test]
+    fn test_identity() {
+        let lut = Lut::identity(5);
+        for &color in &[[0.0, 0.0, 0.0], [0.3, 0.6, 0.9], [1.0, 0.5, 0.0]] {
+            let mapped = lut.map_color(color);
+            for c in 0..3 {
+                assert!((mapped[c] - color[c]).abs() < 1e-6, "{:?} {:?}", color, mapped);
+            }
+        }
+
+        let original = RgbImage::from_fn(16, 16, |x, y| Rgb([(x * 16) as u8, (y * 16) as u8, 77]));
+        let mut image = original.clone();
+        lut.apply(&mut image);
+        assert_eq!(image, original);
+    }
+
+    #[test]
+    fn test_read_3d_cube() {
+        // Swaps red and blue, with a title, comments and a domain.
+        let mut cube = String::from("# comment\nTITLE \"swap\"\nLUT_3D_SIZE 2\n");
+        cube.push_str("DOMAIN_MIN 0 0 0\nDOMAIN_MAX 2 2 2\n");
+        for i in 0..8 {
+            let (r, g, b) = (i & 1, (i >> 1) & 1, (i >> 2) & 1);
+            cube.push_str(&format!("{} {} {}\n", b, g, r));
+        }
+        let lut = Lut::read_cube(cube.as_bytes()).unwrap();
+        assert_eq!(lut.kind(), LutKind::ThreeDimensional);
+        assert_eq!(lut.size(), 2);
+
+        // The domain ends at 2, so 1.0 is half way.
+        assert_eq!(lut.map_color([2.0, 1.0, 0.0]), [0.0, 0.5, 1.0]);
+
+        let mut image = ImageBuffer::from_pixel(1, 1, Rgb([0.5f32, 2.0, 5.0]));
+        lut.apply(&mut image);
+        assert_eq!(image.get_pixel(0, 0), &Rgb([1.0, 1.0, 0.25]));
+
+        let mut image = RgbaImage::from_pixel(1, 1, Rgba([255, 0, 0, 9]));
+        lut.apply_rgba(&mut image);
+        assert_eq!(image.get_pixel(0, 0), &Rgba([0, 0, 128, 9]));
+    }
+
+    #[test]
+    fn test_1d_cube() {
+        // A gamma curve, with output beyond the range that is clamped for integers.
+        let cube = "LUT_1D_SIZE 3\n0 0 0\n0.25 0.25 0.25\n1.5 1 1\n";
+        let lut = Lut::read_cube(cube.as_bytes()).unwrap();
+        assert_eq!(lut.kind(), LutKind::OneDimensional);
+        assert_eq!(lut.map_color([0.25, 0.5, 0.75]), [0.125, 0.25, 0.625]);
+        assert_eq!(lut.map_color([1.0, 1.0, 1.0]), [1.5, 1.0, 1.0]);
+
+        let mut image = RgbImage::from_pixel(1, 1, Rgb([255, 255, 0]));
+        lut.apply(&mut image);
+        assert_eq!(image.get_pixel(0, 0), &Rgb([255, 255, 0]));
+    }
+
+    #[test]
+    fn test_invalid_cubes() {
+        let cubes = [
+            "",
+            "0 0 0\n1 1 1\n",
+            "LUT_1D_SIZE 2\n0 0 0\n",
+            "LUT_1D_SIZE 2\n0 0 0\n1 1 1\n1 1 1\n",
+            "LUT_1D_SIZE 1\n0 0 0\n",
+            "LUT_1D_SIZE 2\n0 0\n1 1 1\n",
+            "LUT_1D_SIZE 2\n0 0 x\n1 1 1\n",
+            "LUT_1D_SIZE 2\nLUT_3D_SIZE 2\n0 0 0\n1 1 1\n",
+            "LUT_1D_SIZE 2\nDOMAIN_MAX 0 1 1\n0 0 0\n1 1 1\n",
+            "LUT_1D_SIZE 2\nLUT_IN_VIDEO_RANGE\n0 0 0\n1 1 1\n",
+        ];
+        for cube in &cubes {
+            assert!(Lut::read_cube(cube.as_bytes()).is_err(), "{:?}", cube);
+        }
+    }
+}
@@ -0,0 +1,401 @@
+//! Types describing image metadata stored alongside the pixel data.
+
+use std::convert::TryFrom;
+
+/// The orientation of an image, as described by the EXIF `Orientation` tag.
+///
+/// Cameras usually store photos in the orientation of their sensor and record in this tag how the
+/// image has to be transformed to appear upright. Each variant names the transformation that has
+/// to be applied to the stored image for display, see `DynamicImage::apply_orientation`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Orientation {
+    /// The image is stored upright, EXIF value 1.
+    NoTransforms,
+    /// Rotate the image by 90 degrees clockwise, EXIF value 6.
+    Rotate90,
+    /// Rotate the image by 180 degrees, EXIF value 3.
+    Rotate180,
+    /// Rotate the image by 270 degrees clockwise, EXIF value 8.
+    Rotate270,
+    /// Flip the image horizontally, EXIF value 2.
+    FlipHorizontal,
+    /// Flip the image vertically, EXIF value 4.
+    FlipVertical,
+    /// Rotate the image by 90 degrees clockwise, then flip it horizontally, EXIF value 5.
+    Rotate90FlipH,
+    /// Rotate the image by 270 degrees clockwise, then flip it horizontally, EXIF value 7.
+    Rotate270FlipH,
+}
+
+impl Orientation {
+    /// Convert the value of an EXIF `Orientation` tag, returns `None` for invalid values.
+    pub fn from_exif(value: u8) -> Option<Self> {
+        match value {
+            1 => Some(Orientation::NoTransforms),
+            2 => Some(Orientation::FlipHorizontal),
+            3 => Some(Orientation::Rotate180),
+            4 => Some(Orientation::FlipVertical),
+            5 => Some(Orientation::Rotate90FlipH),
+            6 => Some(Orientation::Rotate90),
+            7 => Some(Orientation::Rotate270FlipH),
+            8 => Some(Orientation::Rotate270),
+            _ => None,
+        }
+    }
+
+    /// The value of the EXIF `Orientation` tag describing this orientation.
+    pub fn to_exif(self) -> u8 {
+        match self {
+            Orientation::NoTransforms => 1,
+            Orientation::FlipHorizontal => 2,
+            Orientation::Rotate180 => 3,
+            Orientation::FlipVertical => 4,
+            Orientation::Rotate90FlipH => 5,
+            Orientation::Rotate90 => 6,
+            Orientation::Rotate270FlipH => 7,
+            Orientation::Rotate270 => 8,
+        }
+    }
+
+    /// Read the orientation from a block of EXIF metadata.
+    ///
+    /// The data is the TIFF structure of the metadata, optionally preceded by the `Exif\0\0`
+    /// signature of JPEG files. Returns `None` if the data is malformed or contains no valid
+    /// orientation.
+    pub fn from_exif_chunk(chunk: &[u8]) -> Option<Self> {
+        const ORIENTATION: u16 = 0x0112;
+        const SHORT: u16 = 3;
+
+        let chunk = if chunk.starts_with(b"Exif\0\0") {
+            &chunk[6..]
+        } else {
+            chunk
+        };
+        let big_endian = match chunk.get(..4)? {
+            b"II*\0" => false,
+            b"MM\0*" => true,
+            _ => return None,
+        };
+        let u16_at = |offset: usize| -> Option<u16> {
+            let bytes = chunk.get(offset..offset.checked_add(2)?)?;
+            let bytes = [bytes[0], bytes[1]];
+            Some(if big_endian {
+                u16::from_be_bytes(bytes)
+            } else {
+                u16::from_le_bytes(bytes)
+            })
+        };
+        let u32_at = |offset: usize| -> Option<u32> {
+            let bytes = chunk.get(offset..offset.checked_add(4)?)?;
+            let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+            Some(if big_endian {
+                u32::from_be_bytes(bytes)
+            } else {
+                u32::from_le_bytes(bytes)
+            })
+        };
+
+        // The orientation is stored in the first image file directory.
+        let ifd = usize::try_from(u32_at(4)?).ok()?;
+        let entries = u16_at(ifd)?;
+        for entry in 0..usize::from(entries) {
+            let offset = ifd + 2 + entry * 12;
+            if u16_at(offset)? == ORIENTATION && u16_at(offset + 2)? == SHORT {
+                // A single short is stored in the first bytes of the value field.
+                let value = u16_at(offset + 8)?;
+                return Orientation::from_exif(u8::try_from(value).ok()?);
+            }
+        }
+        None
+    }
+}
+
+/// The metadata stored alongside the pixel data of an image.
+///
+/// Each kind of metadata is kept as the raw bytes of the file, so it can be passed on unchanged
+/// when the image is encoded in another format. See `ImageDecoder::metadata`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ImageMetadata {
+    /// The EXIF metadata, as its TIFF structure without the `Exif\0\0` signature of JPEG files.
+    pub exif: Option<Vec<u8>>,
+    /// The XMP packet, an XML document.
+    pub xmp: Option<Vec<u8>>,
+    /// The ICC color profile.
+    pub icc_profile: Option<Vec<u8>>,
+}
+
+impl ImageMetadata {
+    /// Returns whether no metadata is present.
+    pub fn is_empty(&self) -> bool {
+        self.exif.is_none() && self.xmp.is_none() && self.icc_profile.is_none()
+    }
+
+    /// The orientation described by the EXIF metadata, if any.
+    pub fn orientation(&self) -> Option<Orientation> {
+        self.exif.as_ref().and_then(|exif| Orientation::from_exif_chunk(exif))
+    }
+}
+
+/// The physical resolution of an image, as stored in its file.
+///
+/// Print and layout software use it to determine the size at which the image is displayed.
+///
+/// ```
+/// use image::metadata::{Resolution, ResolutionUnit};
+///
+/// let print = Resolution::dpi(300);
+/// assert_eq!(print, Resolution { density: (300, 300), unit: ResolutionUnit::Inches });
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Resolution {
+    /// The horizontal and vertical number of pixels per unit
+    pub density: (u32, u32),
+    /// The unit in which the density is measured
+    pub unit: ResolutionUnit,
+}
+
+/// The unit of a `Resolution`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ResolutionUnit {
+    /// No physical unit, the density only describes the aspect ratio of the pixels
+    AspectRatio,
+    /// Pixels per inch
+    Inches,
+    /// Pixels per centimeter
+    Centimeters,
+    /// Pixels per meter
+    Meters,
+}
+
+impl Resolution {
+    /// The same density in both directions, in pixels per inch.
+    pub fn dpi(dpi: u32) -> Self {
+        Resolution {
+            density: (dpi, dpi),
+            unit: ResolutionUnit::Inches,
+        }
+    }
+
+    /// The horizontal and vertical density in pixels per inch, `None` if the unit is not physical.
+    pub fn to_dpi(&self) -> Option<(f64, f64)> {
+        let units_per_inch = match self.unit {
+            ResolutionUnit::AspectRatio => return None,
+            ResolutionUnit::Inches => 1.0,
+            ResolutionUnit::Centimeters => 2.54,
+            ResolutionUnit::Meters => 0.0254,
+        };
+        Some((
+            f64::from(self.density.0) * units_per_inch,
+            f64::from(self.density.1) * units_per_inch,
+        ))
+    }
+}
+
+/// The signature of APP1 segments containing XMP metadata.
+pub(crate) const JPEG_XMP_SIGNATURE: &[u8] = b"http://ns.adobe.com/xap/1.0/\0";
+
+/// Find the EXIF metadata in the header of a JPEG file.
+///
+/// Returns the TIFF structure of the first APP1 segment with an EXIF signature. The header may be
+/// truncated anywhere, segments after the first scan are not searched.
+#[allow(dead_code)] // Unused without jpeg support.
+pub(crate) fn exif_from_jpeg(header: &[u8]) -> Option<Vec<u8>> {
+    segment_from_jpeg(header, APP1, b"Exif\0\0")
+}
+
+/// Find the XMP packet in the header of a JPEG file.
+#[allow(dead_code)] // Unused without jpeg support.
+pub(crate) fn xmp_from_jpeg(header: &[u8]) -> Option<Vec<u8>> {
+    segment_from_jpeg(header, APP1, JPEG_XMP_SIGNATURE)
+}
+
+/// Find the resolution in the JFIF segment of the header of a JPEG file.
+#[allow(dead_code)] // Unused without jpeg support.
+pub(crate) fn resolution_from_jpeg(header: &[u8]) -> Option<Resolution> {
+    const APP0: u8 = 0xE0;
+
+    // version, unit and the horizontal and vertical density
+    let jfif = segment_from_jpeg(header, APP0, b"JFIF\0")?;
+    let jfif = jfif.get(..7)?;
+    let unit = match jfif[2] {
+        0 => ResolutionUnit::AspectRatio,
+        1 => ResolutionUnit::Inches,
+        2 => ResolutionUnit::Centimeters,
+        _ => return None,
+    };
+    Some(Resolution {
+        density: (
+            u32::from(u16::from_be_bytes([jfif[3], jfif[4]])),
+            u32::from(u16::from_be_bytes([jfif[5], jfif[6]])),
+        ),
+        unit,
+    })
+}
+
+#[allow(dead_code)] // Unused without jpeg support.
+const APP1: u8 = 0xE1;
+
+/// Find the first segment with `marker` starting with `signature` and return the data following
+/// the signature.
+#[allow(dead_code)] // Unused without jpeg support.
+fn segment_from_jpeg(header: &[u8], wanted: u8, signature: &[u8]) -> Option<Vec<u8>> {
+    const SOS: u8 = 0xDA;
+    const EOI: u8 = 0xD9;
+
+    if !header.starts_with(&[0xFF, 0xD8]) {
+        return None;
+    }
+    let mut position = 2;
+    loop {
+        if *header.get(position)? != 0xFF {
+            return None;
+        }
+        // Markers may be preceded by any number of fill bytes.
+        while *header.get(position + 1)? == 0xFF {
+            position += 1;
+        }
+        let marker = header[position + 1];
+        position += 2;
+        match marker {
+            SOS | EOI => return None,
+            // Markers without a segment.
+            0x01 | 0xD0..=0xD7 => continue,
+            _ => {}
+        }
+
+        let length = usize::from(u16::from_be_bytes([
+            *header.get(position)?,
+            *header.get(position + 1)?,
+        ]));
+        let segment = header.get(position + 2..position + length.max(2))?;
+        if marker == wanted && segment.starts_with(signature) {
+            return Some(segment[signature.len()..].to_vec());
+        }
+        position += length;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        exif_from_jpeg, resolution_from_jpeg, xmp_from_jpeg, ImageMetadata, Orientation,
+        Resolution, ResolutionUnit,
+    };
+
+    /// A little endian EXIF chunk with an orientation tag between two other tags.
+    fn exif_chunk(orientation: u16) -> Vec<u8> {
+        let mut chunk = b"II*\0".to_vec();
+        chunk.extend_from_slice(&8u32.to_le_bytes());
+        chunk.extend_from_slice(&3u16.to_le_bytes());
+        for &(tag, value) in &[(0x010F, 0), (0x0112, orientation), (0x011A, 72)] {
+            chunk.extend_from_slice(&u16::to_le_bytes(tag));
+            chunk.extend_from_slice(&3u16.to_le_bytes());
+            chunk.extend_from_slice(&1u32.to_le_bytes());
+            chunk.extend_from_slice(&value.to_le_bytes());
+            chunk.extend_from_slice(&[0, 0]);
+        }
+        chunk.extend_from_slice(&0u32.to_le_bytes());
+        chunk
+    }
+
+    #[test]
+    fn test_exif_values_roundtrip() {
+        for value in 1..=8 {
+            assert_eq!(Orientation::from_exif(value).unwrap().to_exif(), value);
+        }
+        assert_eq!(Orientation::from_exif(0), None);
+        assert_eq!(Orientation::from_exif(9), None);
+    }
+
+    #[test]
+    fn test_orientation_from_exif_chunk() {
+        let chunk = exif_chunk(6);
+        assert_eq!(
+            Orientation::from_exif_chunk(&chunk),
+            Some(Orientation::Rotate90)
+        );
+
+        let mut with_signature = b"Exif\0\0".to_vec();
+        with_signature.extend_from_slice(&exif_chunk(3));
+        assert_eq!(
+            Orientation::from_exif_chunk(&with_signature),
+            Some(Orientation::Rotate180)
+        );
+
+        let big_endian = [
+            b'M', b'M', 0, 42, 0, 0, 0, 8, 0, 1, 0x01, 0x12, 0, 3, 0, 0, 0, 1, 0, 8, 0, 0,
+        ];
+        assert_eq!(
+            Orientation::from_exif_chunk(&big_endian),
+            Some(Orientation::Rotate270)
+        );
+
+        assert_eq!(Orientation::from_exif_chunk(&exif_chunk(0)), None);
+        assert_eq!(Orientation::from_exif_chunk(&chunk[..20]), None);
+        assert_eq!(Orientation::from_exif_chunk(b"not exif"), None);
+    }
+
+    #[test]
+    fn test_exif_from_jpeg_header() {
+        let exif = exif_chunk(8);
+        let mut jpeg = vec![0xFF, 0xD8];
+        // A JFIF segment before the EXIF one.
+        jpeg.extend_from_slice(&[0xFF, 0xE0, 0, 4, 0xAB, 0xCD]);
+        jpeg.extend_from_slice(&[0xFF, 0xE1]);
+        jpeg.extend_from_slice(&(exif.len() as u16 + 8).to_be_bytes());
+        jpeg.extend_from_slice(b"Exif\0\0");
+        jpeg.extend_from_slice(&exif);
+        jpeg.extend_from_slice(&[0xFF, 0xDA, 0, 2]);
+
+        assert_eq!(exif_from_jpeg(&jpeg), Some(exif));
+        assert_eq!(xmp_from_jpeg(&jpeg), None);
+        assert_eq!(exif_from_jpeg(&jpeg[..10]), None);
+        assert_eq!(exif_from_jpeg(&[0xFF, 0xD8, 0xFF, 0xDA, 0, 2]), None);
+    }
+
+    #[test]
+    fn test_xmp_from_jpeg_header() {
+        let xmp = b"<x:xmpmeta xmlns:x='adobe:ns:meta/'/>";
+        let mut jpeg = vec![0xFF, 0xD8, 0xFF, 0xE1];
+        jpeg.extend_from_slice(&(xmp.len() as u16 + 31).to_be_bytes());
+        jpeg.extend_from_slice(b"http://ns.adobe.com/xap/1.0/\0");
+        jpeg.extend_from_slice(xmp);
+        jpeg.extend_from_slice(&[0xFF, 0xDA, 0, 2]);
+
+        assert_eq!(xmp_from_jpeg(&jpeg), Some(xmp.to_vec()));
+        assert_eq!(exif_from_jpeg(&jpeg), None);
+    }
+
+    #[test]
+    fn test_metadata_orientation() {
+        let mut metadata = ImageMetadata::default();
+        assert!(metadata.is_empty());
+        assert_eq!(metadata.orientation(), None);
+
+        metadata.exif = Some(exif_chunk(6));
+        assert!(!metadata.is_empty());
+        assert_eq!(metadata.orientation(), Some(Orientation::Rotate90));
+    }
+
+    #[test]
+    fn test_resolution_from_jpeg_header() {
+        let mut jpeg = vec![0xFF, 0xD8, 0xFF, 0xE0, 0, 16];
+        jpeg.extend_from_slice(b"JFIF\0\x01\x02\x02\x00\x76\x00\x76\0\0");
+        jpeg.extend_from_slice(&[0xFF, 0xDA, 0, 2]);
+        assert_eq!(
+            resolution_from_jpeg(&jpeg),
+            Some(Resolution { density: (118, 118), unit: ResolutionUnit::Centimeters })
+        );
+        assert_eq!(resolution_from_jpeg(&jpeg[..12]), None);
+    }
+
+    #[test]
+    fn test_resolution_to_dpi() {
+        assert_eq!(Resolution::dpi(300).to_dpi(), Some((300.0, 300.0)));
+        let metric = Resolution { density: (100, 3937), unit: ResolutionUnit::Meters };
+        let (x, y) = metric.to_dpi().unwrap();
+        assert!((x - 2.54).abs() < 1e-9 && (y - 100.0).abs() < 0.01);
+        let ratio = Resolution { density: (1, 2), unit: ResolutionUnit::AspectRatio };
+        assert_eq!(ratio.to_dpi(), None);
+    }
+}
@@ -0,0 +1,218 @@
+//! Measures of the difference between two images.
+//!
+//! These compare a distorted image, for example the result of a lossy encoder, to a reference.
+//! All channels are compared, including alpha, with samples scaled by the nominal maximum of
+//! their type, so that results of images with different sample types are comparable.
+//!
+//! * [`mse`] is the mean squared difference of the samples.
+//! * [`psnr`] expresses the same in decibels relative to the maximum sample value, which is the
+//!   usual way to report the quality of codecs. Higher is better.
+//! * [`ssim`] compares local structure instead of samples, and agrees much better with perceived
+//!   quality. It is 1 for identical images and smaller the more they differ.
+//!
+//! # Examples
+//!
+//! ```
+//! use image::metrics::{psnr, ssim};
+//! use image::{GrayImage, Luma};
+//!
+//! let reference = GrayImage::from_fn(32, 32, |x, y| Luma([(x * 8 + y) as u8]));
+//! let distorted = GrayImage::from_fn(32, 32, |x, y| Luma([(x * 8 + y) as u8 ^ 1]));
+//! assert!(psnr(&reference, &distorted) > 45.0);
+//! assert!(ssim(&reference, &distorted) > 0.99);
+//! ```
+//!
+//! [`mse`]: fn.mse.html
+//! [`psnr`]: fn.psnr.html
+//! [`ssim`]: fn.ssim.html
+
+use crate::image::GenericImageView;
+use crate::imageops::plane::{self, Plane};
+use crate::traits::{Pixel, Primitive};
+
+/// The mean squared difference of the samples of two images, with samples scaled to `[0, 1]`.
+///
+/// # Panics
+///
+/// Panics if the images do not have the same dimensions.
+pub fn mse<I, J, P, S>(first: &I, second: &J) -> f64
+where
+    I: GenericImageView<Pixel = P>,
+    J: GenericImageView<Pixel = P>,
+    P: Pixel<Subpixel = S>,
+    S: Primitive,
+{
+    let (first, second) = normalized_planes(first, second);
+    let mut sum = 0.0;
+    let mut count = 0;
+    for (a, b) in first.iter().zip(&second) {
+        for (&x, &y) in a.as_raw().iter().zip(b.as_raw()) {
+            let d = f64::from(x) - f64::from(y);
+            sum += d * d;
+            count += 1;
+        }
+    }
+
+    if count == 0 {
+        0.0
+    } else {
+        sum / f64::from(count)
+    }
+}
+
+/// The peak signal-to-noise ratio of two images in decibels.
+///
+/// This is `10 log10(1 / mse)`, and infinite for identical images. For 8-bit images, values
+/// above 40 dB mean differences that are hard to see.
+///
+/// # Panics
+///
+/// Panics if the images do not have the same dimensions.
+pub fn psnr<I, J, P, S>(first: &I, second: &J) -> f64
+where
+    I: GenericImageView<Pixel = P>,
+    J: GenericImageView<Pixel = P>,
+    P: Pixel<Subpixel = S>,
+    S: Primitive,
+{
+    -10.0 * mse(first, second).log10()
+}
+
+/// The mean structural similarity of two images.
+///
+/// Follows Wang, Bovik, Sheikh and Simoncelli, "Image Quality Assessment: From Error Visibility
+/// to Structural Similarity", 2004: the means, variances and covariance of both images are
+/// compared in an 11x11 Gaussian window with a standard deviation of 1.5 around every pixel,
+/// where samples beyond the edges repeat the edge. The result is the mean over all pixels and
+/// channels, between -1 and 1.
+///
+/// # Panics
+///
+/// Panics if the images do not have the same dimensions.
+pub fn ssim<I, J, P, S>(first: &I, second: &J) -> f64
+where
+    I: GenericImageView<Pixel = P>,
+    J: GenericImageView<Pixel = P>,
+    P: Pixel<Subpixel = S>,
+    S: Primitive,
+{
+    const SIGMA: f32 = 1.5;
+    const C1: f32 = 0.01 * 0.01;
+    const C2: f32 = 0.03 * 0.03;
+
+    let (first, second) = normalized_planes(first, second);
+    let mut sum = 0.0;
+    let mut count = 0;
+    for (x, y) in first.iter().zip(&second) {
+        let product = |a: &Plane, b: &Plane| {
+            let mut out = a.clone();
+            for (o, &b) in out.iter_mut().zip(b.as_raw()) {
+                *o *= b;
+            }
+            plane::gaussian_blur(&out, SIGMA)
+        };
+        let (mean_x, mean_y) = (plane::gaussian_blur(x, SIGMA), plane::gaussian_blur(y, SIGMA));
+        let (xx, yy, xy) = (product(x, x), product(y, y), product(x, y));
+
+        for i in 0..mean_x.as_raw().len() {
+            let (mx, my) = (mean_x.as_raw()[i], mean_y.as_raw()[i]);
+            let var_x = xx.as_raw()[i] - mx * mx;
+            let var_y = yy.as_raw()[i] - my * my;
+            let cov = xy.as_raw()[i] - mx * my;
+            let similarity = ((2.0 * mx * my + C1) * (2.0 * cov + C2))
+                / ((mx * mx + my * my + C1) * (var_x + var_y + C2));
+            sum += f64::from(similarity);
+            count += 1;
+        }
+    }
+
+    if count == 0 {
+        1.0
+    } else {
+        sum / f64::from(count)
+    }
+}
+
+/// Split both images into planes with samples divided by their nominal maximum.
+fn normalized_planes<I, J, P, S>(first: &I, second: &J) -> (Vec<Plane>, Vec<Plane>)
+where
+    I: GenericImageView<Pixel = P>,
+    J: GenericImageView<Pixel = P>,
+    P: Pixel<Subpixel = S>,
+    S: Primitive,
+{
+    assert_eq!(
+        first.dimensions(),
+        second.dimensions(),
+        "the images to compare must have the same dimensions"
+    );
+
+    let max = plane::nominal_max::<S>();
+    let normalize = |mut planes: Vec<Plane>| {
+        for plane in &mut planes {
+            plane.iter_mut().for_each(|s| *s /= max);
+        }
+        planes
+    };
+    (normalize(plane::split(first)), normalize(plane::split(second)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{mse, psnr, ssim};
+    use crate::{GrayImage, ImageBuffer, Luma, Pixel, Rgb, RgbImage};
+
+    fn pattern() -> RgbImage {
+        RgbImage::from_fn(40, 30, |x, y| {
+            Rgb([(x * 6) as u8, (y * 8) as u8, ((x * y) % 256) as u8])
+        })
+    }
+
+    #[test]
+    fn test_identical() {
+        let image = pattern();
+        assert_eq!(mse(&image, &image), 0.0);
+        assert!(psnr(&image, &image).is_infinite());
+        assert!((ssim(&image, &image) - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_mse_and_psnr() {
+        // Every sample differs by a tenth of the range.
+        let first = GrayImage::from_pixel(4, 4, Luma([100]));
+        let second = GrayImage::from_pixel(4, 4, Luma([100 + 25]));
+        let expected = (25.0f64 / 255.0).powi(2);
+        assert!((mse(&first, &second) - expected).abs() < 1e-7);
+        assert!((psnr(&first, &second) - -10.0 * expected.log10()).abs() < 1e-4);
+
+        // The scale of the sample type does not matter.
+        let first = ImageBuffer::from_pixel(4, 4, Luma([0.5f32]));
+        let second = ImageBuffer::from_pixel(4, 4, Luma([0.6f32]));
+        assert!((mse(&first, &second) - 0.01).abs() < 1e-6);
+
+        assert_eq!(mse(&GrayImage::new(0, 0), &GrayImage::new(0, 0)), 0.0);
+    }
+
+    #[test]
+    fn test_ssim_ranks_distortions() {
+        let image = pattern();
+        let noisy = RgbImage::from_fn(40, 30, |x, y| {
+            let noise = if (x * 7 + y * 13) % 5 < 2 { 12 } else { 0 };
+            image.get_pixel(x, y).map(|s| s.saturating_add(noise))
+        });
+        let flat = RgbImage::from_pixel(40, 30, Rgb([128, 128, 128]));
+
+        let slightly = ssim(&image, &noisy);
+        let heavily = ssim(&image, &flat);
+        assert!(slightly < 1.0 && slightly > 0.5, "{}", slightly);
+        assert!(heavily < slightly, "{} {}", heavily, slightly);
+        // Symmetric in its arguments.
+        assert!((ssim(&noisy, &image) - slightly).abs() < 1e-6);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_dimension_mismatch() {
+        mse(&GrayImage::new(2, 3), &GrayImage::new(3, 2));
+    }
+}
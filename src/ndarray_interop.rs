@@ -0,0 +1,187 @@
+//! Conversions between image buffers and `ndarray` arrays.
+//!
+//! Images correspond to three dimensional arrays of the shape `(height, width, channels)`, so
+//! that `array[[y, x, c]]` is the sample of channel `c` of the pixel at `(x, y)`. This is the
+//! row-major layout of the samples in an `ImageBuffer`, which lets owned buffers and arrays be
+//! converted into each other without copying, and buffers be viewed as arrays.
+
+use std::convert::TryFrom;
+use std::mem::size_of;
+use std::ops::{Deref, DerefMut};
+
+use ndarray::{Array3, ArrayView3, ArrayViewMut3};
+
+use crate::buffer_::ImageBuffer;
+use crate::error::{ImageError, ParameterError, ParameterErrorKind};
+use crate::traits::Pixel;
+
+fn dimension_mismatch() -> ImageError {
+    ImageError::Parameter(ParameterError::from_kind(ParameterErrorKind::DimensionMismatch))
+}
+
+/// The width and height of an array of the given shape, if it can hold pixels of type `P`.
+fn image_dimensions<P: Pixel>(shape: &[usize]) -> Result<(u32, u32), ImageError> {
+    if shape[2] != usize::from(P::CHANNEL_COUNT) {
+        return Err(dimension_mismatch());
+    }
+    match (u32::try_from(shape[1]), u32::try_from(shape[0])) {
+        (Ok(width), Ok(height)) => Ok((width, height)),
+        _ => Err(dimension_mismatch()),
+    }
+}
+
+fn array_shape<P: Pixel>(width: u32, height: u32) -> (usize, usize, usize) {
+    (height as usize, width as usize, usize::from(P::CHANNEL_COUNT))
+}
+
+impl<P, Container> ImageBuffer<P, Container>
+where
+    P: Pixel + 'static,
+    Container: Deref<Target = [P::Subpixel]>,
+{
+    /// View the samples as an array of the shape `(height, width, channels)`.
+    ///
+    /// Requires the `ndarray` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use image::{Rgb, RgbImage};
+    ///
+    /// let image = RgbImage::from_fn(4, 3, |x, y| Rgb([x as u8, y as u8, 0]));
+    /// let array = image.as_ndarray();
+    /// assert_eq!(array.shape(), &[3, 4, 3]);
+    /// assert_eq!(array[[2, 1, 0]], 1);
+    /// ```
+    pub fn as_ndarray(&self) -> ArrayView3<'_, P::Subpixel> {
+        let (width, height) = self.dimensions();
+        let len = width as usize * height as usize * usize::from(P::CHANNEL_COUNT);
+        ArrayView3::from_shape(array_shape::<P>(width, height), &self.as_raw()[..len]).unwrap()
+    }
+}
+
+impl<P, Container> ImageBuffer<P, Container>
+where
+    P: Pixel + 'static,
+    Container: Deref<Target = [P::Subpixel]> + DerefMut,
+{
+    /// View the samples as a mutable array of the shape `(height, width, channels)`.
+    ///
+    /// Requires the `ndarray` feature.
+    pub fn as_ndarray_mut(&mut self) -> ArrayViewMut3<'_, P::Subpixel> {
+        let (width, height) = self.dimensions();
+        let len = width as usize * height as usize * usize::from(P::CHANNEL_COUNT);
+        let samples: &mut [P::Subpixel] = self;
+        ArrayViewMut3::from_shape(array_shape::<P>(width, height), &mut samples[..len]).unwrap()
+    }
+}
+
+impl<P> From<ImageBuffer<P, Vec<P::Subpixel>>> for Array3<P::Subpixel>
+where
+    P: Pixel + 'static,
+{
+    fn from(image: ImageBuffer<P, Vec<P::Subpixel>>) -> Self {
+        let (width, height) = image.dimensions();
+        let mut samples = image.into_raw();
+        samples.truncate(width as usize * height as usize * usize::from(P::CHANNEL_COUNT));
+        Array3::from_shape_vec(array_shape::<P>(width, height), samples).unwrap()
+    }
+}
+
+impl<P> TryFrom<Array3<P::Subpixel>> for ImageBuffer<P, Vec<P::Subpixel>>
+where
+    P: Pixel + 'static,
+{
+    type Error = ImageError;
+
+    /// Fails if the last axis of the array does not have one element per channel of `P`, or the
+    /// dimensions do not fit into `u32`. Arrays in standard layout are converted without copying
+    /// their samples.
+    fn try_from(array: Array3<P::Subpixel>) -> Result<Self, ImageError> {
+        let (width, height) = image_dimensions::<P>(array.shape())?;
+        let samples = if array.is_standard_layout() {
+            // The elements are contiguous, but may start after the beginning of the storage of
+            // an array that has been sliced.
+            let (start, len) = (array.as_ptr() as usize, array.len());
+            let mut samples = array.into_raw_vec();
+            let bytes = start - samples[..].as_ptr() as usize;
+            let offset = bytes / size_of::<P::Subpixel>();
+            samples.truncate(offset + len);
+            samples.drain(..offset);
+            samples
+        } else {
+            array.iter().cloned().collect()
+        };
+        Ok(ImageBuffer::from_raw(width, height, samples).unwrap())
+    }
+}
+
+impl<'a, P> TryFrom<ArrayView3<'a, P::Subpixel>> for ImageBuffer<P, &'a [P::Subpixel]>
+where
+    P: Pixel + 'static,
+{
+    type Error = ImageError;
+
+    /// Fails if the array is not in standard layout, the last axis does not have one element
+    /// per channel of `P`, or the dimensions do not fit into `u32`.
+    fn try_from(array: ArrayView3<'a, P::Subpixel>) -> Result<Self, ImageError> {
+        let (width, height) = image_dimensions::<P>(array.shape())?;
+        let samples = array.to_slice().ok_or_else(dimension_mismatch)?;
+        Ok(ImageBuffer::from_raw(width, height, samples).unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use ndarray::{s, Array3, Axis};
+
+    use crate::{GrayImage, ImageBuffer, Luma, Rgb, RgbImage};
+
+    #[test]
+    fn test_owned_roundtrip() {
+        let image = RgbImage::from_fn(5, 3, |x, y| Rgb([x as u8, y as u8, 9]));
+        let array = Array3::from(image.clone());
+        assert_eq!(array.shape(), &[3, 5, 3]);
+        assert_eq!(array[[2, 4, 0]], 4);
+        assert_eq!(array[[2, 4, 1]], 2);
+        assert_eq!(RgbImage::try_from(array).unwrap(), image);
+
+        assert!(GrayImage::try_from(Array3::<u8>::zeros((3, 5, 3))).is_err());
+    }
+
+    #[test]
+    fn test_non_standard_arrays() {
+        let array = Array3::from_shape_fn((4, 6, 1), |(y, x, _)| (10 * y + x) as u8);
+
+        // Transposed, so the samples have to be reordered.
+        let mut transposed = array.clone();
+        transposed.swap_axes(0, 1);
+        let image = GrayImage::try_from(transposed).unwrap();
+        assert_eq!(image.dimensions(), (4, 6));
+        assert_eq!(image.get_pixel(3, 5), &Luma([35]));
+
+        // Sliced rows, which start in the middle of the storage.
+        let mut rows = array.clone();
+        rows.slice_collapse(s![1..3, .., ..]);
+        let image = GrayImage::try_from(rows).unwrap();
+        assert_eq!(image.dimensions(), (6, 2));
+        assert_eq!(image.get_pixel(0, 0), &Luma([10]));
+        assert_eq!(image.get_pixel(5, 1), &Luma([25]));
+    }
+
+    #[test]
+    fn test_views() {
+        let mut image = RgbImage::new(4, 2);
+        image.as_ndarray_mut().index_axis_mut(Axis(2), 1).fill(200);
+        assert!(image.pixels().all(|p| p == &Rgb([0, 200, 0])));
+
+        let array = image.as_ndarray();
+        let view = ImageBuffer::<Rgb<u8>, &[u8]>::try_from(array).unwrap();
+        assert_eq!(view.get_pixel(3, 1), &Rgb([0, 200, 0]));
+
+        let columns = array.slice(s![.., 1..3, ..]);
+        assert!(ImageBuffer::<Rgb<u8>, &[u8]>::try_from(columns).is_err());
+    }
+}
@@ -0,0 +1,125 @@
+use std::cmp::Reverse;
+
+use super::{clamp_colors, color_counts, exact_palette, mean_color, Palette, Quantizer};
+use crate::{Rgba, RgbaImage};
+
+/// Quantization by median cut, after Paul Heckbert, "Color Image Quantization for Frame Buffer
+/// Display", 1982.
+///
+/// The colors of the image start out in a single box spanning their range. The box with the
+/// longest side is split at the median of its pixels along that side, until there are enough
+/// boxes. Every box then contributes the mean of its colors to the palette, so that the palette
+/// colors are distributed in proportion to the number of pixels.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct MedianCut;
+
+impl Quantizer for MedianCut {
+    fn palette(&self, image: &RgbaImage, max_colors: usize) -> Palette {
+        let max_colors = clamp_colors(max_colors);
+        let colors = color_counts(image);
+        if let Some(palette) = exact_palette(&colors, max_colors) {
+            return palette;
+        }
+
+        let mut boxes = vec![ColorBox { colors }];
+        while boxes.len() < max_colors {
+            let longest = boxes
+                .iter()
+                .enumerate()
+                .filter(|&(_, b)| b.colors.len() > 1)
+                .max_by_key(|&(i, b)| (b.longest_side().1, Reverse(i)));
+            let index = match longest {
+                Some((index, _)) => index,
+                None => break,
+            };
+
+            let (low, high) = boxes.swap_remove(index).split();
+            boxes.push(low);
+            boxes.push(high);
+        }
+
+        Palette::new(boxes.iter().map(ColorBox::mean).collect())
+    }
+}
+
+/// Distinct colors with their number of pixels.
+struct ColorBox {
+    colors: Vec<([u8; 4], u64)>,
+}
+
+impl ColorBox {
+    /// The channel with the largest range of values, and that range.
+    fn longest_side(&self) -> (usize, u8) {
+        (0..4)
+            .map(|channel| {
+                let values = self.colors.iter().map(|&(color, _)| color[channel]);
+                let min = values.clone().min().unwrap();
+                let max = values.max().unwrap();
+                (channel, max - min)
+            })
+            .max_by_key(|&(channel, range)| (range, Reverse(channel)))
+            .unwrap()
+    }
+
+    /// Split the box at the median of its pixels along its longest side.
+    fn split(mut self) -> (ColorBox, ColorBox) {
+        let (channel, _) = self.longest_side();
+        self.colors.sort_by_key(|&(color, _)| color[channel]);
+
+        // The position where at least half of the pixels come before it, but both halves keep at
+        // least one color.
+        let total: u64 = self.colors.iter().map(|&(_, count)| count).sum();
+        let mut seen = 0;
+        let mut median = self.colors.len() - 1;
+        for (i, &(_, count)) in self.colors.iter().enumerate() {
+            seen += count;
+            if 2 * seen >= total {
+                median = i + 1;
+                break;
+            }
+        }
+        let median = median.min(self.colors.len() - 1);
+
+        let high = self.colors.split_off(median);
+        (self, ColorBox { colors: high })
+    }
+
+    fn mean(&self) -> Rgba<u8> {
+        let mut sum = [0; 4];
+        let mut count = 0;
+        for &(color, n) in &self.colors {
+            for (s, &c) in sum.iter_mut().zip(color.iter()) {
+                *s += u64::from(c) * n;
+            }
+            count += n;
+        }
+        mean_color(sum, count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MedianCut;
+    use crate::quant::Quantizer;
+    use crate::{Rgba, RgbaImage};
+
+    #[test]
+    fn test_clusters() {
+        // Two clusters of noisy colors with the same number of pixels.
+        let image = RgbaImage::from_fn(16, 16, |x, y| {
+            let noise = ((x * 7 + y * 3) % 5) as u8;
+            if x < 8 {
+                Rgba([20 + noise, 30 + noise, 40 - noise, 255])
+            } else {
+                Rgba([200 + noise, 180 - noise, 60, 255])
+            }
+        });
+
+        let palette = MedianCut.palette(&image, 2);
+        let mut colors = palette.colors().to_vec();
+        colors.sort_by_key(|c| c[0]);
+        assert_eq!(colors.len(), 2);
+        assert!(colors[0][0] >= 20 && colors[0][0] <= 24, "{:?}", colors);
+        assert!(colors[1][0] >= 200 && colors[1][0] <= 204, "{:?}", colors);
+    }
+}
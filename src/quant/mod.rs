@@ -0,0 +1,357 @@
+//! Color quantization, the reduction of the colors of an image to a small palette.
+//!
+//! A [`Quantizer`] chooses a [`Palette`] for an image and maps every pixel to the index of its
+//! closest palette color. The GIF encoder uses a quantizer for every frame, and the palette and
+//! indices can be written directly as an indexed PNG with [`PngEncoder::encode_indexed`].
+//!
+//! Three quantizers are available:
+//!
+//! * [`MedianCut`] is fast and deterministic, and a good default.
+//! * [`Octree`] keeps rare but distinct colors better.
+//! * [`NeuQuant`] is slower but gives the best results for photographs.
+//!
+//! All of them return the colors of the image exactly if there are few enough of them.
+//!
+//! # Examples
+//!
+//! ```
+//! use image::quant::{MedianCut, Quantizer};
+//! use image::{Rgba, RgbaImage};
+//!
+//! let image = RgbaImage::from_fn(64, 64, |x, y| Rgba([(x * 4) as u8, (y * 4) as u8, 128, 255]));
+//! let (palette, indices) = MedianCut.quantize(&image, 16);
+//! assert!(palette.colors().len() <= 16);
+//! assert_eq!(indices.dimensions(), image.dimensions());
+//! ```
+//!
+//! [`Quantizer`]: trait.Quantizer.html
+//! [`Palette`]: struct.Palette.html
+//! [`MedianCut`]: struct.MedianCut.html
+//! [`Octree`]: struct.Octree.html
+//! [`NeuQuant`]: struct.NeuQuant.html
+//! [`PngEncoder::encode_indexed`]: ../codecs/png/struct.PngEncoder.html#method.encode_indexed
+
+use std::collections::HashMap;
+
+use crate::color::Rgba;
+use crate::imageops::ColorMap;
+use crate::utils::clamp;
+use crate::{GrayImage, ImageBuffer, RgbaImage};
+
+mod median_cut;
+mod octree;
+
+pub use self::median_cut::MedianCut;
+pub use self::octree::Octree;
+
+/// An algorithm choosing a palette for an image.
+pub trait Quantizer {
+    /// Choose a palette of at most `max_colors` colors for the pixels of `image`.
+    ///
+    /// `max_colors` is clamped to the range from 1 to 256, so that every index fits into a byte.
+    fn palette(&self, image: &RgbaImage, max_colors: usize) -> Palette;
+
+    /// Choose a palette and map every pixel of `image` to the index of its closest color.
+    fn quantize(&self, image: &RgbaImage, max_colors: usize) -> (Palette, GrayImage) {
+        let palette = self.palette(image, max_colors);
+        let indices = palette.index_image(image);
+        (palette, indices)
+    }
+}
+
+/// A list of up to 256 colors, which maps every color to the closest of them.
+///
+/// Colors are compared by their euclidean distance in RGBA space.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Palette {
+    colors: Vec<Rgba<u8>>,
+}
+
+impl Palette {
+    /// Create a palette of the given colors.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are no colors or more than 256.
+    pub fn new(colors: Vec<Rgba<u8>>) -> Palette {
+        assert!(
+            !colors.is_empty() && colors.len() <= 256,
+            "a palette needs between 1 and 256 colors"
+        );
+        Palette { colors }
+    }
+
+    /// The 216 opaque colors of the web safe palette, with every channel one of 0, 51, 102, 153,
+    /// 204 and 255.
+    pub fn web_safe() -> Palette {
+        let levels = [0, 51, 102, 153, 204, 255];
+        let mut colors = Vec::with_capacity(216);
+        for &r in &levels {
+            for &g in &levels {
+                for &b in &levels {
+                    colors.push(Rgba([r, g, b, 255]));
+                }
+            }
+        }
+        Palette::new(colors)
+    }
+
+    /// The colors of the palette.
+    pub fn colors(&self) -> &[Rgba<u8>] {
+        &self.colors
+    }
+
+    /// Map every pixel of `image` to the index of its closest color.
+    pub fn index_image(&self, image: &RgbaImage) -> GrayImage {
+        let mut cache = HashMap::new();
+        let indices = image
+            .pixels()
+            .map(|pixel| *cache.entry(pixel.0).or_insert_with(|| self.index_of(pixel) as u8))
+            .collect();
+
+        let (width, height) = image.dimensions();
+        ImageBuffer::from_raw(width, height, indices).unwrap()
+    }
+}
+
+impl ColorMap for Palette {
+    type Color = Rgba<u8>;
+
+    fn index_of(&self, color: &Rgba<u8>) -> usize {
+        let distance = |other: &Rgba<u8>| -> u32 {
+            color
+                .0
+                .iter()
+                .zip(other.0.iter())
+                .map(|(&a, &b)| {
+                    let d = i32::from(a) - i32::from(b);
+                    (d * d) as u32
+                })
+                .sum()
+        };
+
+        let mut best = 0;
+        let mut best_distance = std::u32::MAX;
+        for (index, other) in self.colors.iter().enumerate() {
+            let d = distance(other);
+            if d < best_distance {
+                best = index;
+                best_distance = d;
+            }
+        }
+        best
+    }
+
+    fn lookup(&self, index: usize) -> Option<Rgba<u8>> {
+        self.colors.get(index).cloned()
+    }
+
+    fn has_lookup(&self) -> bool {
+        true
+    }
+
+    fn map_color(&self, color: &mut Rgba<u8>) {
+        *color = self.colors[self.index_of(color)];
+    }
+}
+
+/// Quantization with the NeuQuant neural network of Anthony Dekker, "Kohonen neural networks
+/// for optimal colour quantization", 1994.
+///
+/// The network makes a single pass over a sample of the pixels, which leaves colors poorly
+/// placed when there are few pixels for every color of the palette. The trained palette is
+/// therefore refined with two iterations of Lloyd's algorithm over the colors of the image.
+///
+/// This is the quantizer used by the GIF encoder by default.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct NeuQuant {
+    speed: i32,
+}
+
+impl NeuQuant {
+    /// Create a NeuQuant quantizer with the given speed, from 1 to 30.
+    ///
+    /// Only every `speed`-th pixel is used to train the network, so higher values are faster
+    /// and give worse results. A speed of 10 is a good compromise.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the speed is out of range.
+    pub fn new(speed: i32) -> NeuQuant {
+        assert!(speed >= 1 && speed <= 30, "speed needs to be in the range [1, 30]");
+        NeuQuant { speed }
+    }
+}
+
+impl Quantizer for NeuQuant {
+    fn palette(&self, image: &RgbaImage, max_colors: usize) -> Palette {
+        let max_colors = clamp_colors(max_colors);
+        let colors = color_counts(image);
+        if let Some(palette) = exact_palette(&colors, max_colors) {
+            return palette;
+        }
+        // The network needs at least two neurons.
+        if max_colors == 1 {
+            return MedianCut.palette(image, 1);
+        }
+
+        let network = color_quant::NeuQuant::new(self.speed, max_colors, image.as_raw());
+        let trained = network
+            .color_map_rgba()
+            .chunks(4)
+            .map(|c| Rgba([c[0], c[1], c[2], c[3]]))
+            .collect();
+        let mut palette = Palette::new(trained);
+        for _ in 0..2 {
+            palette = refine(&palette, &colors);
+        }
+        palette
+    }
+}
+
+/// Move every color of the palette to the mean of the image colors closest to it, one
+/// iteration of Lloyd's algorithm. Colors that no pixel is closest to are kept.
+fn refine(palette: &Palette, colors: &[([u8; 4], u64)]) -> Palette {
+    let mut sums = vec![([0u64; 4], 0u64); palette.colors().len()];
+    for &(color, count) in colors {
+        let (sum, total) = &mut sums[palette.index_of(&Rgba(color))];
+        for (s, &c) in sum.iter_mut().zip(color.iter()) {
+            *s += u64::from(c) * count;
+        }
+        *total += count;
+    }
+
+    let refined = sums
+        .iter()
+        .zip(palette.colors())
+        .map(|(&(sum, total), &color)| if total == 0 { color } else { mean_color(sum, total) })
+        .collect();
+    Palette::new(refined)
+}
+
+fn clamp_colors(max_colors: usize) -> usize {
+    clamp(max_colors, 1, 256)
+}
+
+/// The distinct colors of an image with their number of pixels, sorted by color.
+fn color_counts(image: &RgbaImage) -> Vec<([u8; 4], u64)> {
+    let mut counts = HashMap::new();
+    for pixel in image.pixels() {
+        *counts.entry(pixel.0).or_insert(0) += 1;
+    }
+
+    let mut counts: Vec<_> = counts.into_iter().collect();
+    counts.sort();
+    counts
+}
+
+/// The palette of all colors, if there are at most `max_colors` of them.
+///
+/// The palette of an image without pixels contains transparent black.
+fn exact_palette(colors: &[([u8; 4], u64)], max_colors: usize) -> Option<Palette> {
+    if colors.is_empty() {
+        Some(Palette::new(vec![Rgba([0, 0, 0, 0])]))
+    } else if colors.len() <= max_colors {
+        Some(Palette::new(colors.iter().map(|&(color, _)| Rgba(color)).collect()))
+    } else {
+        None
+    }
+}
+
+/// The mean of colors weighted by their number of pixels, rounded.
+fn mean_color(sum: [u64; 4], count: u64) -> Rgba<u8> {
+    let mut mean = [0; 4];
+    for (m, &s) in mean.iter_mut().zip(sum.iter()) {
+        *m = ((s + count / 2) / count) as u8;
+    }
+    Rgba(mean)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MedianCut, NeuQuant, Octree, Palette, Quantizer};
+    use crate::imageops::ColorMap;
+    use crate::{Rgba, RgbaImage};
+
+    fn quantizers() -> Vec<Box<dyn Quantizer>> {
+        vec![Box::new(MedianCut), Box::new(Octree), Box::new(NeuQuant::new(10))]
+    }
+
+    /// The mean absolute difference of the samples of two images.
+    fn error(a: &RgbaImage, b: &RgbaImage) -> f64 {
+        let sum: u64 = a
+            .as_raw()
+            .iter()
+            .zip(b.as_raw())
+            .map(|(&x, &y)| (i64::from(x) - i64::from(y)).abs() as u64)
+            .sum();
+        sum as f64 / a.as_raw().len() as f64
+    }
+
+    fn reconstruct(palette: &Palette, indices: &crate::GrayImage) -> RgbaImage {
+        let (width, height) = indices.dimensions();
+        RgbaImage::from_fn(width, height, |x, y| {
+            palette.colors()[indices.get_pixel(x, y)[0] as usize]
+        })
+    }
+
+    #[test]
+    fn test_palette() {
+        let palette = Palette::new(vec![
+            Rgba([0, 0, 0, 255]),
+            Rgba([250, 10, 10, 255]),
+            Rgba([0, 0, 0, 0]),
+        ]);
+        assert_eq!(palette.index_of(&Rgba([200, 50, 40, 255])), 1);
+        assert_eq!(palette.index_of(&Rgba([10, 10, 10, 20])), 2);
+        assert_eq!(palette.lookup(1), Some(Rgba([250, 10, 10, 255])));
+        assert_eq!(palette.lookup(3), None);
+
+        let mut color = Rgba([30, 20, 10, 240]);
+        palette.map_color(&mut color);
+        assert_eq!(color, Rgba([0, 0, 0, 255]));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_empty_palette() {
+        Palette::new(Vec::new());
+    }
+
+    #[test]
+    fn test_few_colors_are_exact() {
+        let colors = [Rgba([255, 0, 0, 255]), Rgba([0, 0, 255, 128]), Rgba([0, 0, 0, 0])];
+        let image = RgbaImage::from_fn(9, 5, |x, y| colors[((x + y) % 3) as usize]);
+
+        for quantizer in quantizers() {
+            let (palette, indices) = quantizer.quantize(&image, 4);
+            assert_eq!(palette.colors().len(), 3);
+            assert_eq!(reconstruct(&palette, &indices), image);
+        }
+    }
+
+    #[test]
+    fn test_gradient() {
+        let image = RgbaImage::from_fn(64, 64, |x, y| {
+            Rgba([(x * 4) as u8, (y * 4) as u8, ((x + y) * 2) as u8, 255])
+        });
+
+        for quantizer in quantizers() {
+            for &(max_colors, max_error) in &[(1, 64.0), (16, 16.0), (256, 5.0), (1000, 5.0)] {
+                let (palette, indices) = quantizer.quantize(&image, max_colors);
+                assert!(palette.colors().len() <= max_colors.min(256));
+                let error = error(&reconstruct(&palette, &indices), &image);
+                assert!(error < max_error, "error {} with {} colors", error, max_colors);
+            }
+        }
+    }
+
+    #[test]
+    fn test_empty_image() {
+        for quantizer in quantizers() {
+            let (palette, indices) = quantizer.quantize(&RgbaImage::new(0, 3), 16);
+            assert_eq!(palette.colors(), &[Rgba([0, 0, 0, 0])][..]);
+            assert_eq!(indices.dimensions(), (0, 3));
+        }
+    }
+}
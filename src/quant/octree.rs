@@ -0,0 +1,176 @@
+use super::{clamp_colors, color_counts, exact_palette, mean_color, Palette, Quantizer};
+use crate::{Rgba, RgbaImage};
+
+/// The number of bits of a channel, and the depth of the leaves of the tree.
+const DEPTH: usize = 8;
+
+/// Quantization with an octree, after Michael Gervautz and Werner Purgathofer, "A Simple Method
+/// for Color Quantization: Octree Quantization", 1988.
+///
+/// The colors of the image are sorted into a tree by the bits of their channels, from the most
+/// significant bit down, so that every node covers a cube of colors. Alpha is treated as a
+/// fourth dimension, which makes every node have up to 16 children instead of 8. Then the least
+/// used nodes of the deepest level are merged with their children until there are few enough
+/// leaves. Since merging only happens within a cube, rare colors that are far from all others
+/// keep an entry of their own.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Octree;
+
+impl Quantizer for Octree {
+    fn palette(&self, image: &RgbaImage, max_colors: usize) -> Palette {
+        let max_colors = clamp_colors(max_colors);
+        let colors = color_counts(image);
+        if let Some(palette) = exact_palette(&colors, max_colors) {
+            return palette;
+        }
+
+        let mut tree = Tree::default();
+        for &(color, count) in &colors {
+            tree.insert(color, count);
+        }
+        tree.reduce(max_colors);
+
+        let mut palette = Vec::with_capacity(max_colors);
+        tree.collect_leaves(0, &mut palette);
+        Palette::new(palette)
+    }
+}
+
+#[derive(Default)]
+struct Node {
+    /// The indices of the children in the tree, zero where there is none.
+    children: [usize; 16],
+    /// The sum of the colors of all pixels below this node.
+    sum: [u64; 4],
+    /// The number of pixels below this node.
+    count: u64,
+}
+
+impl Node {
+    fn is_leaf(&self) -> bool {
+        self.children.iter().all(|&child| child == 0)
+    }
+}
+
+struct Tree {
+    /// All nodes, with the root first.
+    nodes: Vec<Node>,
+    /// The indices of the inner nodes on every level.
+    levels: Vec<Vec<usize>>,
+    leaves: usize,
+}
+
+impl Default for Tree {
+    fn default() -> Tree {
+        let mut levels = vec![Vec::new(); DEPTH];
+        levels[0].push(0);
+        Tree {
+            nodes: vec![Node::default()],
+            levels,
+            leaves: 0,
+        }
+    }
+}
+
+impl Tree {
+    fn insert(&mut self, color: [u8; 4], count: u64) {
+        let mut node = 0;
+        for depth in 0..=DEPTH {
+            let n = &mut self.nodes[node];
+            for (sum, &c) in n.sum.iter_mut().zip(color.iter()) {
+                *sum += u64::from(c) * count;
+            }
+            n.count += count;
+            if depth == DEPTH {
+                break;
+            }
+
+            // One bit of every channel selects the child.
+            let shift = DEPTH - 1 - depth;
+            let child = color
+                .iter()
+                .enumerate()
+                .fold(0, |index, (i, &c)| index | (usize::from((c >> shift) & 1) << i));
+
+            if self.nodes[node].children[child] == 0 {
+                let index = self.nodes.len();
+                self.nodes.push(Node::default());
+                self.nodes[node].children[child] = index;
+                if depth + 1 == DEPTH {
+                    self.leaves += 1;
+                } else {
+                    self.levels[depth + 1].push(index);
+                }
+            }
+            node = self.nodes[node].children[child];
+        }
+    }
+
+    /// Merge nodes with their children, deepest and least used first, until there are at most
+    /// `max_leaves` leaves.
+    fn reduce(&mut self, max_leaves: usize) {
+        for depth in (0..DEPTH).rev() {
+            let mut level = self.levels[depth].clone();
+            level.sort_by_key(|&index| self.nodes[index].count);
+
+            for index in level {
+                if self.leaves <= max_leaves {
+                    return;
+                }
+                // All deeper nodes have already been merged, so the children are leaves.
+                let node = &mut self.nodes[index];
+                let children = node.children.iter().filter(|&&child| child != 0).count();
+                node.children = [0; 16];
+                self.leaves -= children - 1;
+            }
+        }
+    }
+
+    fn collect_leaves(&self, index: usize, palette: &mut Vec<Rgba<u8>>) {
+        let node = &self.nodes[index];
+        if node.is_leaf() {
+            palette.push(mean_color(node.sum, node.count));
+        } else {
+            for &child in node.children.iter().filter(|&&child| child != 0) {
+                self.collect_leaves(child, palette);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Octree;
+    use crate::quant::Quantizer;
+    use crate::{Rgba, RgbaImage};
+
+    #[test]
+    fn test_keeps_rare_colors() {
+        // A gray gradient with a few red pixels.
+        let image = RgbaImage::from_fn(64, 16, |x, y| {
+            if x == 5 && y < 2 {
+                Rgba([255, 0, 0, 255])
+            } else {
+                let v = (x * 4) as u8;
+                Rgba([v, v, v, 255])
+            }
+        });
+
+        let palette = Octree.palette(&image, 8);
+        assert!(palette.colors().len() <= 8);
+        assert!(palette.colors().contains(&Rgba([255, 0, 0, 255])), "{:?}", palette);
+    }
+
+    #[test]
+    fn test_transparency() {
+        // Transparent and opaque pixels of the same color stay apart.
+        let image = RgbaImage::from_fn(32, 8, |x, y| {
+            let v = (x * 8) as u8;
+            Rgba([v, v, v, if y < 4 { 0 } else { 255 }])
+        });
+
+        let palette = Octree.palette(&image, 4);
+        assert!(palette.colors().iter().any(|c| c[3] == 0), "{:?}", palette);
+        assert!(palette.colors().iter().any(|c| c[3] == 255), "{:?}", palette);
+    }
+}
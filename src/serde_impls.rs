@@ -0,0 +1,105 @@
+//! Serialization of image buffers with `serde`.
+//!
+//! An `ImageBuffer` is serialized as a struct of its `width`, `height` and the sequence of its
+//! samples as `data`. A `DynamicImage` is serialized as an enum whose variant, such as
+//! `ImageRgb8`, names the color type, holding the buffer.
+
+use std::ops::Deref;
+
+use serde::de::Error;
+use serde::ser::SerializeStruct;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::buffer_::ImageBuffer;
+use crate::traits::Pixel;
+
+/// The serialized form of an image buffer.
+#[derive(Deserialize)]
+#[serde(rename = "ImageBuffer")]
+struct RawBuffer<S> {
+    width: u32,
+    height: u32,
+    data: Vec<S>,
+}
+
+impl<P, Container> Serialize for ImageBuffer<P, Container>
+where
+    P: Pixel + 'static,
+    P::Subpixel: Serialize,
+    Container: Deref<Target = [P::Subpixel]>,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let (width, height) = self.dimensions();
+        let len = width as usize * height as usize * usize::from(P::CHANNEL_COUNT);
+        let mut state = serializer.serialize_struct("ImageBuffer", 3)?;
+        state.serialize_field("width", &width)?;
+        state.serialize_field("height", &height)?;
+        state.serialize_field("data", &self.as_raw()[..len])?;
+        state.end()
+    }
+}
+
+impl<'de, P> Deserialize<'de> for ImageBuffer<P, Vec<P::Subpixel>>
+where
+    P: Pixel + 'static,
+    P::Subpixel: Deserialize<'de>,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let RawBuffer { width, height, data } = RawBuffer::deserialize(deserializer)?;
+        let expected = (width as usize)
+            .checked_mul(height as usize)
+            .and_then(|pixels| pixels.checked_mul(usize::from(P::CHANNEL_COUNT)));
+        if expected != Some(data.len()) {
+            return Err(D::Error::custom(format_args!(
+                "{} samples do not match an image of {}x{} pixels with {} channels",
+                data.len(),
+                width,
+                height,
+                P::CHANNEL_COUNT,
+            )));
+        }
+        Ok(ImageBuffer::from_raw(width, height, data).unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{DynamicImage, GrayImage, ImageBuffer, Luma, Rgb, Rgba, RgbaImage};
+
+    #[test]
+    fn test_buffer_roundtrip() {
+        let image = ImageBuffer::from_fn(2, 1, |x, _| Rgb([x as u16, 1000, 65535]));
+        let json = serde_json::to_string(&image).unwrap();
+        assert_eq!(json, r#"{"width":2,"height":1,"data":[0,1000,65535,1,1000,65535]}"#);
+        let back: ImageBuffer<Rgb<u16>, Vec<u16>> = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, image);
+
+        // Views serialize like owned buffers.
+        let samples = [7, 8, 9];
+        let view = ImageBuffer::<Luma<u8>, _>::from_raw(1, 2, &samples[..]).unwrap();
+        let json = serde_json::to_string(&view).unwrap();
+        assert_eq!(json, r#"{"width":1,"height":2,"data":[7,8]}"#);
+    }
+
+    #[test]
+    fn test_invalid_buffers() {
+        let short = r#"{"width":2,"height":2,"data":[1,2,3]}"#;
+        assert!(serde_json::from_str::<GrayImage>(short).is_err());
+        let long = r#"{"width":1,"height":1,"data":[1,2,3,4,5]}"#;
+        assert!(serde_json::from_str::<RgbaImage>(long).is_err());
+        let overflow = r#"{"width":4294967295,"height":4294967295,"data":[]}"#;
+        assert!(serde_json::from_str::<RgbaImage>(overflow).is_err());
+    }
+
+    #[test]
+    fn test_dynamic_image() {
+        let image = DynamicImage::ImageRgba8(RgbaImage::from_pixel(1, 1, Rgba([1, 2, 3, 4])));
+        let json = serde_json::to_string(&image).unwrap();
+        assert_eq!(json, r#"{"ImageRgba8":{"width":1,"height":1,"data":[1,2,3,4]}}"#);
+        assert_eq!(serde_json::from_str::<DynamicImage>(&json).unwrap(), image);
+
+        let float = DynamicImage::ImageRgb32F(ImageBuffer::from_pixel(2, 2, Rgb([0.5, 1.0, 0.0])));
+        let json = serde_json::to_string(&float).unwrap();
+        assert_eq!(serde_json::from_str::<DynamicImage>(&json).unwrap(), float);
+    }
+}
@@ -26,12 +26,43 @@ impl EncodableLayout for [u16] {
     }
 }
 
+impl EncodableLayout for [f32] {
+    fn as_bytes(&self) -> &[u8] {
+        bytemuck::cast_slice(self)
+    }
+}
+
 /// Primitive trait from old stdlib
-pub trait Primitive: Copy + NumCast + Num + PartialOrd<Self> + Clone + Bounded {}
+pub trait Primitive: Copy + NumCast + Num + PartialOrd<Self> + Clone + Bounded {
+    /// The maximum value of a sample in the context of color, the value of full intensity. For
+    /// floats this is `1.0`, integer types use their usual maximum value.
+    ///
+    /// Defaults to `Bounded::max_value`.
+    fn default_max_value() -> Self {
+        Self::max_value()
+    }
+
+    /// The minimum value of a sample in the context of color. For floats this is `0.0`, integer
+    /// types use their usual minimum value.
+    ///
+    /// Defaults to `Bounded::min_value`.
+    fn default_min_value() -> Self {
+        Self::min_value()
+    }
+
+    /// The position of this type among the sample types of `ColorType`, `u8`, `u16` and `f32`,
+    /// or 3 if it is none of them.
+    #[doc(hidden)]
+    const COLOR_SAMPLE_INDEX: usize = 3;
+}
 
 impl Primitive for usize {}
-impl Primitive for u8 {}
-impl Primitive for u16 {}
+impl Primitive for u8 {
+    const COLOR_SAMPLE_INDEX: usize = 0;
+}
+impl Primitive for u16 {
+    const COLOR_SAMPLE_INDEX: usize = 1;
+}
 impl Primitive for u32 {}
 impl Primitive for u64 {}
 impl Primitive for isize {}
@@ -39,8 +70,80 @@ impl Primitive for i8 {}
 impl Primitive for i16 {}
 impl Primitive for i32 {}
 impl Primitive for i64 {}
-impl Primitive for f32 {}
-impl Primitive for f64 {}
+
+impl Primitive for f32 {
+    const COLOR_SAMPLE_INDEX: usize = 2;
+
+    fn default_max_value() -> Self {
+        1.0
+    }
+
+    fn default_min_value() -> Self {
+        0.0
+    }
+}
+
+impl Primitive for f64 {
+    fn default_max_value() -> Self {
+        1.0
+    }
+
+    fn default_min_value() -> Self {
+        0.0
+    }
+}
+
+/// Arrays of samples that can be held by a [`Bands`] pixel, one sample per band.
+///
+/// This trait is sealed and implemented for arrays of one to 32 samples of any primitive type,
+/// which stands in for a pixel generic over the channel count.
+///
+/// [`Bands`]: struct.Bands.html
+pub trait BandArray: seals::BandArray + Copy + Clone {
+    /// The type of the samples.
+    type Sample: Primitive;
+
+    /// The number of samples.
+    const LEN: u8;
+
+    /// An array with every sample set to `sample`.
+    fn filled(sample: Self::Sample) -> Self;
+
+    /// Returns the samples as a slice.
+    fn as_slice(&self) -> &[Self::Sample];
+
+    /// Returns the samples as a mutable slice.
+    fn as_mut_slice(&mut self) -> &mut [Self::Sample];
+}
+
+macro_rules! declare_band_arrays {
+    ($($len:expr),*) => {$(
+        impl<T: Primitive> seals::BandArray for [T; $len] {}
+
+        impl<T: Primitive> BandArray for [T; $len] {
+            type Sample = T;
+
+            const LEN: u8 = $len;
+
+            fn filled(sample: T) -> Self {
+                [sample; $len]
+            }
+
+            fn as_slice(&self) -> &[T] {
+                self
+            }
+
+            fn as_mut_slice(&mut self) -> &mut [T] {
+                self
+            }
+        }
+    )*};
+}
+
+declare_band_arrays!(
+    1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16,
+    17, 18, 19, 20, 21, 22, 23, 24, 25, 26, 27, 28, 29, 30, 31, 32
+);
 
 /// An Enlargable::Larger value should be enough to calculate
 /// the sum (average) of a few hundred or thousand Enlargeable values.
@@ -66,6 +169,9 @@ impl Enlargeable for u16 {
 impl Enlargeable for u32 {
     type Larger = u64;
 }
+impl Enlargeable for f32 {
+    type Larger = f64;
+}
 
 /// Linear interpolation without involving floating numbers.
 pub trait Lerp: Bounded + NumCast {
@@ -131,7 +237,16 @@ pub trait Pixel: Copy + Clone {
     }
 
     /// ColorType for this pixel format
+    ///
+    /// Pixel formats without an exact color type, see `EXACT_COLOR_TYPE`, report a color type of
+    /// the same color model with different samples.
     const COLOR_TYPE: ColorType;
+
+    /// The ColorType that exactly describes the samples of this pixel format, or `None` if there
+    /// is no such color type, for example for `Luma<f32>` or any pixel with `u32` samples.
+    ///
+    /// Defaults to `Some(Self::COLOR_TYPE)`.
+    const EXACT_COLOR_TYPE: Option<ColorType> = Some(Self::COLOR_TYPE);
     /// Returns the ColorType for this pixel format
     #[deprecated(note="please use COLOR_TYPE associated constant")]
     fn color_type() -> ColorType {
@@ -260,4 +375,7 @@ mod seals {
 
     impl EncodableLayout for [u8] {}
     impl EncodableLayout for [u16] {}
+    impl EncodableLayout for [f32] {}
+
+    pub trait BandArray {}
 }
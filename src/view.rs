@@ -0,0 +1,322 @@
+//! Images borrowed from sample slices with arbitrary row strides.
+
+use std::marker::PhantomData;
+
+use crate::buffer_::ImageBuffer;
+use crate::image::{GenericImage, GenericImageView};
+use crate::traits::Pixel;
+
+/// An image borrowed from a slice of samples, for processing frames of video decoders, cameras
+/// or foreign code without copying them into an [`ImageBuffer`].
+///
+/// The pixels of a row are packed, but rows may be padded: the `stride` is the number of samples
+/// from the start of one row to the start of the next, as often found in frame buffers whose
+/// rows are aligned. The last row needs no padding.
+///
+/// For layouts with other strides, such as planar images, see [`FlatSamples`].
+///
+/// # Examples
+///
+/// ```
+/// use image::{GenericImageView, ImageView, Rgb};
+///
+/// // Two rows of two pixels, each row padded to eight samples.
+/// let samples = [1, 2, 3, 4, 5, 6, 0, 0, 7, 8, 9, 10, 11, 12];
+/// let view = ImageView::<Rgb<u8>>::with_stride(&samples, 2, 2, 8).unwrap();
+/// assert_eq!(view.get_pixel(0, 1), Rgb([7, 8, 9]));
+/// ```
+///
+/// [`ImageBuffer`]: struct.ImageBuffer.html
+/// [`FlatSamples`]: flat/struct.FlatSamples.html
+#[derive(Clone, Copy)]
+pub struct ImageView<'a, P: Pixel> {
+    samples: &'a [P::Subpixel],
+    width: u32,
+    height: u32,
+    stride: usize,
+    pixel: PhantomData<P>,
+}
+
+/// A mutable image borrowed from a slice of samples, the mutable counterpart of
+/// [`ImageView`].
+///
+/// Pixels written to the view are written to the slice.
+///
+/// # Examples
+///
+/// ```
+/// use image::{GenericImage, ImageViewMut, Luma};
+///
+/// let mut frame = vec![0u8; 4 * 3];
+/// {
+///     let mut view = ImageViewMut::<Luma<u8>>::with_stride(&mut frame, 3, 3, 4).unwrap();
+///     view.put_pixel(2, 1, Luma([255]));
+/// }
+/// assert_eq!(frame[6], 255);
+/// ```
+///
+/// [`ImageView`]: struct.ImageView.html
+pub struct ImageViewMut<'a, P: Pixel> {
+    samples: &'a mut [P::Subpixel],
+    width: u32,
+    height: u32,
+    stride: usize,
+    pixel: PhantomData<P>,
+}
+
+/// The number of samples needed for an image of the given layout, or `None` if the layout is
+/// invalid.
+fn required_len<P: Pixel>(width: u32, height: u32, stride: usize) -> Option<usize> {
+    let row = (width as usize).checked_mul(<usize as From<u8>>::from(P::CHANNEL_COUNT))?;
+    if row > stride {
+        return None;
+    }
+    if height == 0 {
+        return Some(0);
+    }
+    stride.checked_mul(height as usize - 1)?.checked_add(row)
+}
+
+fn pixel_index<P: Pixel>(x: u32, y: u32, stride: usize) -> usize {
+    y as usize * stride + x as usize * <usize as From<u8>>::from(P::CHANNEL_COUNT)
+}
+
+fn check_pixel((x, y): (u32, u32), (width, height): (u32, u32)) {
+    if x >= width || y >= height {
+        panic!("Image index {:?} out of bounds {:?}", (x, y), (width, height));
+    }
+}
+
+impl<'a, P: Pixel> ImageView<'a, P> {
+    /// Borrow an image with packed rows.
+    ///
+    /// Returns `None` if the slice is too small for the dimensions.
+    pub fn new(samples: &'a [P::Subpixel], width: u32, height: u32) -> Option<Self> {
+        let stride = (width as usize).checked_mul(<usize as From<u8>>::from(P::CHANNEL_COUNT))?;
+        Self::with_stride(samples, width, height, stride)
+    }
+
+    /// Borrow an image whose rows start `stride` samples apart.
+    ///
+    /// Returns `None` if the stride is smaller than a row or the slice is too small for the
+    /// dimensions.
+    pub fn with_stride(
+        samples: &'a [P::Subpixel],
+        width: u32,
+        height: u32,
+        stride: usize,
+    ) -> Option<Self> {
+        if samples.len() < required_len::<P>(width, height, stride)? {
+            return None;
+        }
+        Some(ImageView { samples, width, height, stride, pixel: PhantomData })
+    }
+
+    /// The number of samples from the start of one row to the start of the next.
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+
+    /// Copy the pixels into an owned buffer with packed rows.
+    pub fn to_image(&self) -> ImageBuffer<P, Vec<P::Subpixel>>
+    where
+        P: 'static,
+    {
+        to_image(self.samples, self.width, self.height, self.stride)
+    }
+}
+
+impl<'a, P: Pixel> ImageViewMut<'a, P> {
+    /// Borrow an image with packed rows.
+    ///
+    /// Returns `None` if the slice is too small for the dimensions.
+    pub fn new(samples: &'a mut [P::Subpixel], width: u32, height: u32) -> Option<Self> {
+        let stride = (width as usize).checked_mul(<usize as From<u8>>::from(P::CHANNEL_COUNT))?;
+        Self::with_stride(samples, width, height, stride)
+    }
+
+    /// Borrow an image whose rows start `stride` samples apart.
+    ///
+    /// Returns `None` if the stride is smaller than a row or the slice is too small for the
+    /// dimensions.
+    pub fn with_stride(
+        samples: &'a mut [P::Subpixel],
+        width: u32,
+        height: u32,
+        stride: usize,
+    ) -> Option<Self> {
+        if samples.len() < required_len::<P>(width, height, stride)? {
+            return None;
+        }
+        Some(ImageViewMut { samples, width, height, stride, pixel: PhantomData })
+    }
+
+    /// The number of samples from the start of one row to the start of the next.
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+
+    /// Reborrow as an immutable view.
+    pub fn as_view(&self) -> ImageView<'_, P> {
+        ImageView {
+            samples: self.samples,
+            width: self.width,
+            height: self.height,
+            stride: self.stride,
+            pixel: PhantomData,
+        }
+    }
+
+    /// Copy the pixels into an owned buffer with packed rows.
+    pub fn to_image(&self) -> ImageBuffer<P, Vec<P::Subpixel>>
+    where
+        P: 'static,
+    {
+        to_image(self.samples, self.width, self.height, self.stride)
+    }
+}
+
+fn to_image<P: Pixel + 'static>(
+    samples: &[P::Subpixel],
+    width: u32,
+    height: u32,
+    stride: usize,
+) -> ImageBuffer<P, Vec<P::Subpixel>> {
+    let row = width as usize * <usize as From<u8>>::from(P::CHANNEL_COUNT);
+    let mut raw = Vec::with_capacity(row * height as usize);
+    for y in 0..height as usize {
+        raw.extend_from_slice(&samples[y * stride..y * stride + row]);
+    }
+    ImageBuffer::from_raw(width, height, raw).unwrap()
+}
+
+impl<'a, P: Pixel> GenericImageView for ImageView<'a, P> {
+    type Pixel = P;
+
+    // We don't proxy an inner image.
+    type InnerImageView = Self;
+
+    fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    fn bounds(&self) -> (u32, u32, u32, u32) {
+        (0, 0, self.width, self.height)
+    }
+
+    fn get_pixel(&self, x: u32, y: u32) -> P {
+        check_pixel((x, y), self.dimensions());
+        let index = pixel_index::<P>(x, y, self.stride);
+        *P::from_slice(&self.samples[index..index + <usize as From<u8>>::from(P::CHANNEL_COUNT)])
+    }
+
+    fn inner(&self) -> &Self {
+        self
+    }
+}
+
+impl<'a, P: Pixel> GenericImageView for ImageViewMut<'a, P> {
+    type Pixel = P;
+
+    // We don't proxy an inner image.
+    type InnerImageView = Self;
+
+    fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    fn bounds(&self) -> (u32, u32, u32, u32) {
+        (0, 0, self.width, self.height)
+    }
+
+    fn get_pixel(&self, x: u32, y: u32) -> P {
+        self.as_view().get_pixel(x, y)
+    }
+
+    fn inner(&self) -> &Self {
+        self
+    }
+}
+
+impl<'a, P: Pixel> GenericImage for ImageViewMut<'a, P> {
+    type InnerImage = Self;
+
+    fn get_pixel_mut(&mut self, x: u32, y: u32) -> &mut P {
+        check_pixel((x, y), self.dimensions());
+        let index = pixel_index::<P>(x, y, self.stride);
+        let channels = <usize as From<u8>>::from(P::CHANNEL_COUNT);
+        P::from_slice_mut(&mut self.samples[index..index + channels])
+    }
+
+    fn put_pixel(&mut self, x: u32, y: u32, pixel: P) {
+        *self.get_pixel_mut(x, y) = pixel;
+    }
+
+    fn blend_pixel(&mut self, x: u32, y: u32, pixel: P) {
+        self.get_pixel_mut(x, y).blend(&pixel);
+    }
+
+    fn inner_mut(&mut self) -> &mut Self {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ImageView, ImageViewMut};
+    use crate::image::{GenericImage, GenericImageView};
+    use crate::imageops;
+    use crate::{Luma, Rgb, RgbImage};
+
+    #[test]
+    fn test_layout_checks() {
+        let samples = [0u8; 20];
+        assert!(ImageView::<Rgb<u8>>::new(&samples, 2, 3).is_some());
+        assert!(ImageView::<Rgb<u8>>::new(&samples, 3, 3).is_none());
+        // The last row needs no padding.
+        assert!(ImageView::<Rgb<u8>>::with_stride(&samples, 2, 3, 7).is_some());
+        assert!(ImageView::<Rgb<u8>>::with_stride(&samples, 2, 3, 8).is_none());
+        assert!(ImageView::<Rgb<u8>>::with_stride(&samples, 2, 1, 5).is_none());
+        assert!(ImageView::<Rgb<u8>>::with_stride(&[], 5, 0, 15).is_some());
+    }
+
+    #[test]
+    fn test_view_matches_buffer() {
+        let image = RgbImage::from_fn(5, 4, |x, y| Rgb([x as u8, y as u8, 7]));
+        let stride = 5 * 3 + 2;
+        let mut samples = vec![99u8; stride * 4];
+        for (y, row) in samples.chunks_mut(stride).enumerate() {
+            for x in 0..5 {
+                row[x * 3..x * 3 + 3].copy_from_slice(&image.get_pixel(x as u32, y as u32).0);
+            }
+        }
+
+        let view = ImageView::<Rgb<u8>>::with_stride(&samples, 5, 4, stride).unwrap();
+        assert_eq!(view.to_image(), image);
+        assert_eq!(imageops::flip_horizontal(&view), imageops::flip_horizontal(&image));
+        assert_eq!(view.view(1, 1, 2, 2).get_pixel(1, 0), Rgb([2, 1, 7]));
+    }
+
+    #[test]
+    fn test_view_mut_writes_through() {
+        let mut samples = vec![0u16; 3 * 4];
+        {
+            let mut view = ImageViewMut::<Luma<u16>>::with_stride(&mut samples, 3, 3, 4).unwrap();
+            imageops::invert(&mut view);
+            view.put_pixel(1, 2, Luma([5]));
+            view.sub_image(0, 0, 1, 2).put_pixel(0, 1, Luma([6]));
+            assert_eq!(view.as_view().get_pixel(1, 2), Luma([5]));
+        }
+        assert_eq!(
+            samples,
+            [65535, 65535, 65535, 0, 6, 65535, 65535, 0, 65535, 5, 65535, 0]
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_outside() {
+        let samples = [0u8; 16];
+        ImageView::<Luma<u8>>::with_stride(&samples, 3, 4, 4).unwrap().get_pixel(3, 0);
+    }
+}
@@ -0,0 +1,359 @@
+//! Planar YCbCr images with subsampled chroma, as produced and consumed by video codecs and
+//! cameras.
+//!
+//! A [`Yuv420Image`] stores the luma (Y) of every pixel in one plane, and the two chroma
+//! components (U and V, or Cb and Cr) of every 2×2 block of pixels in two planes of half the
+//! width and height, rounded up. It can be read from and written to the two most common memory
+//! layouts: I420, with the three planes one after the other, and NV12, with the U and V samples
+//! interleaved in a second plane.
+//!
+//! Images are converted to and from `RgbImage` with [`yuv420_to_rgb`] and [`rgb_to_yuv420`]. The
+//! conversions use fixed-point integer arithmetic in simple loops over rows, which compilers
+//! turn into vector instructions where the target supports them.
+//!
+//! # Examples
+//!
+//! ```
+//! use image::yuv::{rgb_to_yuv420, yuv420_to_rgb, Yuv420Image, YuvMatrix, YuvRange};
+//! use image::{Rgb, RgbImage};
+//!
+//! // A frame as it comes out of a hardware decoder.
+//! let nv12 = vec![128; 4 * 2 * 3 / 2];
+//! let frame = Yuv420Image::from_nv12(4, 2, &nv12).unwrap();
+//! let rgb = yuv420_to_rgb(&frame, YuvMatrix::Bt709, YuvRange::Limited);
+//! assert_eq!(rgb.get_pixel(0, 0), &Rgb([130, 130, 130]));
+//!
+//! let red = RgbImage::from_pixel(2, 2, Rgb([255, 0, 0]));
+//! let yuv = rgb_to_yuv420(&red, YuvMatrix::Bt601, YuvRange::Limited);
+//! assert_eq!((yuv.y_plane()[0], yuv.u_plane()[0], yuv.v_plane()[0]), (81, 90, 240));
+//! ```
+//!
+//! [`Yuv420Image`]: struct.Yuv420Image.html
+//! [`yuv420_to_rgb`]: fn.yuv420_to_rgb.html
+//! [`rgb_to_yuv420`]: fn.rgb_to_yuv420.html
+
+use crate::color::Rgb;
+use crate::utils::clamp;
+use crate::RgbImage;
+
+/// The weights of red, green and blue in the luma, which differ between video standards.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum YuvMatrix {
+    /// ITU-R BT.601, used by standard definition video and JPEG.
+    Bt601,
+    /// ITU-R BT.709, used by high definition video.
+    Bt709,
+}
+
+/// The range of the samples.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum YuvRange {
+    /// Luma in `[16, 235]` and chroma in `[16, 240]`, as in most video.
+    Limited,
+    /// Luma and chroma use all of `[0, 255]`, as in JPEG.
+    Full,
+}
+
+/// A planar image with chroma subsampled by two in both directions.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Yuv420Image {
+    width: u32,
+    height: u32,
+    y: Vec<u8>,
+    u: Vec<u8>,
+    v: Vec<u8>,
+}
+
+/// The lengths of the luma plane and of each chroma plane.
+fn plane_lens(width: u32, height: u32) -> Option<(usize, usize)> {
+    let luma = (width as usize).checked_mul(height as usize)?;
+    let chroma = (width as usize + 1) / 2 * ((height as usize + 1) / 2);
+    // The whole frame must be addressable in the I420 and NV12 layouts.
+    luma.checked_add(2 * chroma)?;
+    Some((luma, chroma))
+}
+
+impl Yuv420Image {
+    /// Create an image from its three planes.
+    ///
+    /// Returns `None` if a plane does not have exactly the length required by the dimensions.
+    pub fn from_planes(
+        width: u32,
+        height: u32,
+        y: Vec<u8>,
+        u: Vec<u8>,
+        v: Vec<u8>,
+    ) -> Option<Yuv420Image> {
+        let (luma, chroma) = plane_lens(width, height)?;
+        if y.len() != luma || u.len() != chroma || v.len() != chroma {
+            return None;
+        }
+        Some(Yuv420Image { width, height, y, u, v })
+    }
+
+    /// Copy an image from a buffer in the I420 layout: the Y plane, then the U plane, then the V
+    /// plane, each with packed rows.
+    ///
+    /// Returns `None` if the buffer is too small. Trailing data is ignored.
+    pub fn from_i420(width: u32, height: u32, data: &[u8]) -> Option<Yuv420Image> {
+        let (luma, chroma) = plane_lens(width, height)?;
+        if data.len() < luma + 2 * chroma {
+            return None;
+        }
+        let (y, rest) = data.split_at(luma);
+        let (u, rest) = rest.split_at(chroma);
+        Yuv420Image::from_planes(width, height, y.to_vec(), u.to_vec(), rest[..chroma].to_vec())
+    }
+
+    /// Copy an image from a buffer in the NV12 layout: the Y plane, then a plane of interleaved
+    /// U and V samples, each with packed rows.
+    ///
+    /// Returns `None` if the buffer is too small. Trailing data is ignored.
+    pub fn from_nv12(width: u32, height: u32, data: &[u8]) -> Option<Yuv420Image> {
+        let (luma, chroma) = plane_lens(width, height)?;
+        if data.len() < luma + 2 * chroma {
+            return None;
+        }
+        let (y, uv) = data.split_at(luma);
+        let uv = &uv[..2 * chroma];
+        let u = uv.iter().step_by(2).cloned().collect();
+        let v = uv.iter().skip(1).step_by(2).cloned().collect();
+        Yuv420Image::from_planes(width, height, y.to_vec(), u, v)
+    }
+
+    /// Write the image to a buffer in the I420 layout.
+    pub fn to_i420(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(self.y.len() + 2 * self.u.len());
+        data.extend_from_slice(&self.y);
+        data.extend_from_slice(&self.u);
+        data.extend_from_slice(&self.v);
+        data
+    }
+
+    /// Write the image to a buffer in the NV12 layout.
+    pub fn to_nv12(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(self.y.len() + 2 * self.u.len());
+        data.extend_from_slice(&self.y);
+        for (&u, &v) in self.u.iter().zip(&self.v) {
+            data.push(u);
+            data.push(v);
+        }
+        data
+    }
+
+    /// The width of the image in pixels.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// The height of the image in pixels.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// The width and height of the image in pixels.
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// The width and height of the chroma planes.
+    pub fn chroma_dimensions(&self) -> (u32, u32) {
+        ((self.width + 1) / 2, (self.height + 1) / 2)
+    }
+
+    /// The luma plane, with packed rows of `width` samples.
+    pub fn y_plane(&self) -> &[u8] {
+        &self.y
+    }
+
+    /// The blue-difference chroma plane, with packed rows of half the width, rounded up.
+    pub fn u_plane(&self) -> &[u8] {
+        &self.u
+    }
+
+    /// The red-difference chroma plane, with packed rows of half the width, rounded up.
+    pub fn v_plane(&self) -> &[u8] {
+        &self.v
+    }
+}
+
+/// The number of fractional bits of the fixed-point coefficients.
+const SHIFT: u32 = 16;
+const ONE: f32 = (1 << SHIFT) as f32;
+const HALF: i32 = 1 << (SHIFT - 1);
+
+fn fixed(value: f32) -> i32 {
+    (value * ONE).round() as i32
+}
+
+/// The weights of red and blue in the luma.
+fn weights(matrix: YuvMatrix) -> (f32, f32) {
+    match matrix {
+        YuvMatrix::Bt601 => (0.299, 0.114),
+        YuvMatrix::Bt709 => (0.2126, 0.0722),
+    }
+}
+
+/// The offset of black, and the scales of luma and chroma relative to the full range.
+fn range_scales(range: YuvRange) -> (i32, f32, f32) {
+    match range {
+        YuvRange::Limited => (16, 219.0 / 255.0, 224.0 / 255.0),
+        YuvRange::Full => (0, 1.0, 1.0),
+    }
+}
+
+fn clamp_u8(value: i32) -> u8 {
+    clamp(value, 0, 255) as u8
+}
+
+/// Convert a planar image to RGB, with the chroma of every 2×2 block shared by its pixels.
+pub fn yuv420_to_rgb(image: &Yuv420Image, matrix: YuvMatrix, range: YuvRange) -> RgbImage {
+    let (kr, kb) = weights(matrix);
+    let kg = 1.0 - kr - kb;
+    let (black, luma_scale, chroma_scale) = range_scales(range);
+    let y_mul = fixed(1.0 / luma_scale);
+    let r_v = fixed(2.0 * (1.0 - kr) / chroma_scale);
+    let g_u = fixed(2.0 * kb * (1.0 - kb) / kg / chroma_scale);
+    let g_v = fixed(2.0 * kr * (1.0 - kr) / kg / chroma_scale);
+    let b_u = fixed(2.0 * (1.0 - kb) / chroma_scale);
+
+    let (width, height) = image.dimensions();
+    let chroma_width = image.chroma_dimensions().0 as usize;
+    let mut out = RgbImage::new(width, height);
+    if width == 0 {
+        return out;
+    }
+    for (y, row) in out.chunks_mut(3 * width as usize).enumerate() {
+        let luma = &image.y[y * width as usize..][..width as usize];
+        let chroma = (y / 2) * chroma_width;
+        let (us, vs) = (&image.u[chroma..][..chroma_width], &image.v[chroma..][..chroma_width]);
+        for (x, (pixel, &l)) in row.chunks_mut(3).zip(luma).enumerate() {
+            let l = (i32::from(l) - black) * y_mul + HALF;
+            let u = i32::from(us[x / 2]) - 128;
+            let v = i32::from(vs[x / 2]) - 128;
+            pixel[0] = clamp_u8((l + r_v * v) >> SHIFT);
+            pixel[1] = clamp_u8((l - g_u * u - g_v * v) >> SHIFT);
+            pixel[2] = clamp_u8((l + b_u * u) >> SHIFT);
+        }
+    }
+    out
+}
+
+/// Convert an RGB image to a planar one, with the chroma of every 2×2 block averaged.
+pub fn rgb_to_yuv420(image: &RgbImage, matrix: YuvMatrix, range: YuvRange) -> Yuv420Image {
+    let (kr, kb) = weights(matrix);
+    let kg = 1.0 - kr - kb;
+    let (black, luma_scale, chroma_scale) = range_scales(range);
+    let luma_coefficients = [fixed(kr * luma_scale), fixed(kg * luma_scale), fixed(kb * luma_scale)];
+    let u_scale = chroma_scale / (2.0 * (1.0 - kb));
+    let u_coefficients = [fixed(-kr * u_scale), fixed(-kg * u_scale), fixed((1.0 - kb) * u_scale)];
+    let v_scale = chroma_scale / (2.0 * (1.0 - kr));
+    let v_coefficients = [fixed((1.0 - kr) * v_scale), fixed(-kg * v_scale), fixed(-kb * v_scale)];
+    let dot = |c: &[i32; 3], p: [i32; 3]| c[0] * p[0] + c[1] * p[1] + c[2] * p[2];
+
+    let (width, height) = image.dimensions();
+    let y = image
+        .pixels()
+        .map(|&Rgb([r, g, b])| {
+            let p = [i32::from(r), i32::from(g), i32::from(b)];
+            clamp_u8(black + ((dot(&luma_coefficients, p) + HALF) >> SHIFT))
+        })
+        .collect();
+
+    let (chroma_width, chroma_height) = ((width + 1) / 2, (height + 1) / 2);
+    let chroma_len = chroma_width as usize * chroma_height as usize;
+    let (mut u, mut v) = (Vec::with_capacity(chroma_len), Vec::with_capacity(chroma_len));
+    for cy in 0..chroma_height {
+        for cx in 0..chroma_width {
+            let mut sum = [0; 3];
+            let mut count = 0;
+            for y in 2 * cy..(2 * cy + 2).min(height) {
+                for x in 2 * cx..(2 * cx + 2).min(width) {
+                    let pixel = image.get_pixel(x, y);
+                    for c in 0..3 {
+                        sum[c] += i32::from(pixel[c]);
+                    }
+                    count += 1;
+                }
+            }
+            // Chroma is centered on 128, so the sums are positive and truncation rounds.
+            let offset = (128 << SHIFT) * count + count * HALF;
+            u.push(clamp_u8((offset + dot(&u_coefficients, sum)) / (count << SHIFT)));
+            v.push(clamp_u8((offset + dot(&v_coefficients, sum)) / (count << SHIFT)));
+        }
+    }
+
+    Yuv420Image { width, height, y, u, v }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reference_colors() {
+        let colors = [
+            ([255, 255, 255], YuvMatrix::Bt601, [235, 128, 128]),
+            ([0, 0, 0], YuvMatrix::Bt601, [16, 128, 128]),
+            ([255, 0, 0], YuvMatrix::Bt601, [81, 90, 240]),
+            ([0, 0, 255], YuvMatrix::Bt601, [41, 240, 110]),
+            ([255, 0, 0], YuvMatrix::Bt709, [63, 102, 240]),
+        ];
+        for &(rgb, matrix, yuv) in &colors {
+            let image = RgbImage::from_pixel(2, 2, Rgb(rgb));
+            let planar = rgb_to_yuv420(&image, matrix, YuvRange::Limited);
+            assert_eq!([planar.y[0], planar.u[0], planar.v[0]], yuv, "{:?}", rgb);
+            // Limited range has fewer levels, so colors may be off by one on the way back.
+            let back = yuv420_to_rgb(&planar, matrix, YuvRange::Limited);
+            let close = |p: &Rgb<u8>| (0..3).all(|c| (i32::from(p[c]) - i32::from(rgb[c])).abs() <= 1);
+            assert!(back.pixels().all(close), "{:?}: {:?}", rgb, back.get_pixel(0, 0));
+        }
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        // Odd dimensions, and a smooth image so that subsampling loses little.
+        let image = RgbImage::from_fn(7, 5, |x, y| Rgb([30 * x as u8, 40 * y as u8, 100]));
+        for &matrix in &[YuvMatrix::Bt601, YuvMatrix::Bt709] {
+            for &range in &[YuvRange::Limited, YuvRange::Full] {
+                let planar = rgb_to_yuv420(&image, matrix, range);
+                assert_eq!(planar.chroma_dimensions(), (4, 3));
+                let back = yuv420_to_rgb(&planar, matrix, range);
+                for (a, b) in image.pixels().zip(back.pixels()) {
+                    for c in 0..3 {
+                        let error = (i32::from(a[c]) - i32::from(b[c])).abs();
+                        assert!(error <= 40, "{:?} {:?}: {:?} {:?}", matrix, range, a, b);
+                    }
+                }
+            }
+        }
+
+        // Without chroma detail the roundtrip is close to exact.
+        let image = RgbImage::from_fn(6, 4, |x, y| {
+            let v = 40 * x as u8 + 10 * y as u8;
+            Rgb([v, v, v])
+        });
+        let planar = rgb_to_yuv420(&image, YuvMatrix::Bt709, YuvRange::Full);
+        assert_eq!(yuv420_to_rgb(&planar, YuvMatrix::Bt709, YuvRange::Full), image);
+    }
+
+    #[test]
+    fn test_layouts() {
+        let y: Vec<u8> = (0..15).collect();
+        let u = vec![20, 21, 22, 23, 24, 25];
+        let v = vec![30, 31, 32, 33, 34, 35];
+        let image = Yuv420Image::from_planes(5, 3, y, u, v).unwrap();
+        assert_eq!(image.chroma_dimensions(), (3, 2));
+
+        let nv12 = image.to_nv12();
+        assert_eq!(&nv12[15..], &[20, 30, 21, 31, 22, 32, 23, 33, 24, 34, 25, 35]);
+        assert_eq!(Yuv420Image::from_nv12(5, 3, &nv12), Some(image.clone()));
+
+        let i420 = image.to_i420();
+        assert_eq!(&i420[15..], &[20, 21, 22, 23, 24, 25, 30, 31, 32, 33, 34, 35]);
+        assert_eq!(Yuv420Image::from_i420(5, 3, &i420), Some(image));
+
+        assert!(Yuv420Image::from_nv12(5, 3, &nv12[..26]).is_none());
+        assert!(Yuv420Image::from_planes(4, 4, vec![0; 16], vec![0; 4], vec![0; 3]).is_none());
+    }
+}